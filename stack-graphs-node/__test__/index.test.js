@@ -0,0 +1,73 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+const assert = require('node:assert')
+const fs = require('node:fs')
+const os = require('node:os')
+const path = require('node:path')
+const { before, test } = require('node:test')
+
+const { Database, FileStatus, Loader, StackGraph } = require('..')
+
+const TSG_PATH = path.join(__dirname, 'fixtures', 'stack-graphs.tsg')
+
+const SOURCE = `def foo():
+    pass
+
+foo()
+`
+
+let dir
+let sourcePath
+
+before(() => {
+  dir = fs.realpathSync(fs.mkdtempSync(path.join(os.tmpdir(), 'stack-graphs-')))
+  const grammar = path.dirname(require.resolve('tree-sitter-python/package.json'))
+  fs.writeFileSync(
+    path.join(dir, 'stack-graphs.toml'),
+    `[[language]]\ngrammar = ${JSON.stringify(grammar)}\ntsg = ${JSON.stringify(TSG_PATH)}\n`,
+  )
+  sourcePath = path.join(dir, 'test.py')
+  fs.writeFileSync(sourcePath, SOURCE)
+})
+
+test('can build stack graph for file', () => {
+  const loader = Loader.find(dir)
+  const graph = new StackGraph()
+  assert.strictEqual(graph.build(loader, 'test.py', SOURCE), true)
+  assert.strictEqual(graph.build(loader, 'test.txt', 'not python'), false)
+  assert.throws(() => graph.build(loader, 'test.py', SOURCE))
+
+  const json = graph.toJson()
+  const copy = StackGraph.fromJson(json)
+  assert.strictEqual(copy.toJson(), json)
+
+  const html = graph.toHtml('test')
+  assert.match(html, /<title>test<\/title>/)
+})
+
+test('can index and query database', () => {
+  const loader = Loader.fromConfig(path.join(dir, 'stack-graphs.toml'))
+  const db = new Database(path.join(dir, 'stack-graphs.sqlite'))
+  db.index(loader, [dir])
+  assert.strictEqual(db.status(sourcePath), FileStatus.Indexed)
+
+  const results = db.definitions(sourcePath, 3, 0)
+  assert.strictEqual(results.length, 1)
+  assert.deepStrictEqual(results[0].reference, {
+    path: sourcePath,
+    startLine: 3,
+    startColumn: 0,
+    endLine: 3,
+    endColumn: 3,
+  })
+  assert.deepStrictEqual(results[0].definitions, [
+    { path: sourcePath, startLine: 0, startColumn: 4, endLine: 0, endColumn: 7 },
+  ])
+
+  assert.match(db.toHtml([sourcePath]), /stack-graph/)
+})
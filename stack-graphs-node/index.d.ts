@@ -0,0 +1,87 @@
+/* tslint:disable */
+/* eslint-disable */
+
+/* auto-generated by NAPI-RS */
+
+/** The indexing status of a file in a database. */
+export const enum FileStatus {
+  Missing = 'Missing',
+  Indexed = 'Indexed',
+  Error = 'Error'
+}
+/** A source span.  Lines and columns are 0-based, and columns are counted in graphemes. */
+export interface SourceSpan {
+  path: string
+  startLine: number
+  startColumn: number
+  endLine: number
+  endColumn: number
+}
+/** The definitions found for a reference. */
+export interface QueryResult {
+  reference: SourceSpan
+  definitions: Array<SourceSpan>
+}
+/**
+ * A SQLite database of indexed source files.  Paths of indexed files are stored in canonical
+ * form, and all methods that accept paths canonicalize them before use.
+ */
+export class Database {
+  /**
+   * Creates a database at the given path.  The database file is created when files are
+   * first indexed.
+   */
+  constructor(path: string)
+  /**
+   * Indexes the given source files and directories, using the languages from the given
+   * loader.  Files that are already indexed with the same content are skipped, unless `force`
+   * is set.
+   */
+  index(loader: Loader, sourcePaths: Array<string>, force?: boolean | undefined | null): void
+  /** Returns the indexing status of the given file. */
+  status(path: string): FileStatus
+  /**
+   * Finds the definitions of the references at the given position.  Lines and columns are
+   * 0-based, and columns are counted in graphemes.  The file must have been indexed.
+   */
+  definitions(path: string, line: number, column: number): Array<QueryResult>
+  /**
+   * Returns a self-contained HTML visualization of the stack graphs of the given source files
+   * and directories, including all complete paths that start at their references.
+   * Visualizations only work for very small graphs.
+   */
+  toHtml(sourcePaths: Array<string>, title?: string | undefined | null): string
+}
+/** A stack graph, which can contain the stack graphs of several source files. */
+export class StackGraph {
+  /** Creates a new, empty stack graph. */
+  constructor()
+  /** Creates a stack graph from its JSON representation, as produced by `toJson`. */
+  static fromJson(json: string): StackGraph
+  /**
+   * Builds the stack graph of a source file, using the language that the loader selects for
+   * the file, and adds it to this graph.  Returns `false` if the loader has no language for
+   * the file.  Every file can only be added once.
+   */
+  build(loader: Loader, path: string, source: string): boolean
+  /** Returns the JSON representation of this stack graph. */
+  toJson(): string
+  /**
+   * Returns a self-contained HTML visualization of this stack graph, including all complete
+   * paths that start at its references.  Visualizations only work for very small graphs.
+   */
+  toHtml(title?: string | undefined | null): string
+}
+/** Loads the stack graph languages that are used to build stack graphs for source files. */
+export class Loader {
+  /**
+   * Creates a loader for the languages declared in the given loader configuration file.  If a
+   * cache directory is given, it is used to cache the builtins of the languages.
+   */
+  static fromConfig(configPath: string, cacheDir?: string | undefined | null): Loader
+  /**
+   * Creates a loader for the loader configuration file with the default name,
+   * `stack-graphs.toml`, in the given directory or any of its ancestors.
+   */
+  static find(dir: string): Loader
+}
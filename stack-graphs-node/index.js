@@ -0,0 +1,30 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+// Loads the native addon for the current platform, as built by `napi build --platform`.
+
+const { existsSync } = require('fs')
+const { join } = require('path')
+
+function platformSuffix() {
+  const { platform, arch } = process
+  if (platform === 'linux') {
+    const { glibcVersionRuntime } = process.report.getReport().header
+    return `linux-${arch}-${glibcVersionRuntime ? 'gnu' : 'musl'}`
+  }
+  if (platform === 'win32') {
+    return `win32-${arch}-msvc`
+  }
+  return `${platform}-${arch}`
+}
+
+const binding = join(__dirname, `stack-graphs.${platformSuffix()}.node`)
+if (!existsSync(binding)) {
+  throw new Error(`No stack-graphs binding found at ${binding}. Did you run \`npm run build\`?`)
+}
+
+module.exports = require(binding)
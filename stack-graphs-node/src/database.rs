@@ -0,0 +1,193 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! JavaScript class for indexing and querying stack graph databases.
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use napi::Result;
+use napi_derive::napi;
+use stack_graphs::serde::NoFilter;
+use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::storage::SQLiteReader;
+use stack_graphs::storage::SQLiteWriter;
+use tree_sitter_stack_graphs::cli::index::Indexer;
+use tree_sitter_stack_graphs::cli::query::Querier;
+use tree_sitter_stack_graphs::cli::util::reporter::ConsoleReporter;
+use tree_sitter_stack_graphs::cli::util::PositionEncoding;
+use tree_sitter_stack_graphs::cli::util::SourcePosition;
+use tree_sitter_stack_graphs::NoCancellation;
+
+use crate::js_error;
+use crate::loader::Loader;
+
+/// The indexing status of a file in a database.
+#[napi(string_enum)]
+pub enum FileStatus {
+    Missing,
+    Indexed,
+    Error,
+}
+
+impl From<stack_graphs::storage::FileStatus> for FileStatus {
+    fn from(status: stack_graphs::storage::FileStatus) -> Self {
+        match status {
+            stack_graphs::storage::FileStatus::Missing => Self::Missing,
+            stack_graphs::storage::FileStatus::Indexed => Self::Indexed,
+            stack_graphs::storage::FileStatus::Error(_) => Self::Error,
+        }
+    }
+}
+
+/// A source span.  Lines and columns are 0-based, and columns are counted in graphemes.
+#[napi(object)]
+pub struct SourceSpan {
+    pub path: String,
+    pub start_line: u32,
+    pub start_column: u32,
+    pub end_line: u32,
+    pub end_column: u32,
+}
+
+impl From<tree_sitter_stack_graphs::cli::util::SourceSpan> for SourceSpan {
+    fn from(span: tree_sitter_stack_graphs::cli::util::SourceSpan) -> Self {
+        Self {
+            path: span.path.to_string_lossy().into_owned(),
+            start_line: span.span.start.line as u32,
+            start_column: span.span.start.column.grapheme_offset as u32,
+            end_line: span.span.end.line as u32,
+            end_column: span.span.end.column.grapheme_offset as u32,
+        }
+    }
+}
+
+/// The definitions found for a reference.
+#[napi(object)]
+pub struct QueryResult {
+    pub reference: SourceSpan,
+    pub definitions: Vec<SourceSpan>,
+}
+
+/// A SQLite database of indexed source files.  Paths of indexed files are stored in canonical
+/// form, and all methods that accept paths canonicalize them before use.
+#[napi]
+pub struct Database {
+    path: PathBuf,
+}
+
+#[napi]
+impl Database {
+    /// Creates a database at the given path.  The database file is created when files are
+    /// first indexed.
+    #[napi(constructor)]
+    pub fn new(path: String) -> Self {
+        Self {
+            path: PathBuf::from(path),
+        }
+    }
+
+    /// Indexes the given source files and directories, using the languages from the given
+    /// loader.  Files that are already indexed with the same content are skipped, unless `force`
+    /// is set.
+    #[napi]
+    pub fn index(
+        &self,
+        loader: &mut Loader,
+        source_paths: Vec<String>,
+        force: Option<bool>,
+    ) -> Result<()> {
+        let source_paths = source_paths
+            .iter()
+            .map(|p| Path::new(p).canonicalize())
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(js_error)?;
+        let mut db = SQLiteWriter::open(&self.path).map_err(js_error)?;
+        let reporter = ConsoleReporter::none();
+        let mut indexer = Indexer::new(&mut db, &mut loader.inner, &reporter);
+        indexer.force = force.unwrap_or(false);
+        indexer
+            .index_all(source_paths, None::<&Path>, &NoCancellation)
+            .map_err(js_error)
+    }
+
+    /// Returns the indexing status of the given file.
+    #[napi]
+    pub fn status(&self, path: String) -> Result<FileStatus> {
+        let path = Path::new(&path).canonicalize().map_err(js_error)?;
+        let mut db = SQLiteReader::open(&self.path).map_err(js_error)?;
+        let status = db
+            .status_for_file(&path.to_string_lossy(), None::<&String>)
+            .map_err(js_error)?;
+        Ok(status.into())
+    }
+
+    /// Finds the definitions of the references at the given position.  Lines and columns are
+    /// 0-based, and columns are counted in graphemes.  The file must have been indexed.
+    #[napi]
+    pub fn definitions(&self, path: String, line: u32, column: u32) -> Result<Vec<QueryResult>> {
+        let mut position = SourcePosition {
+            path: PathBuf::from(path),
+            line: line as usize,
+            column: column as usize,
+            end_column: None,
+            encoding: PositionEncoding::Grapheme,
+        };
+        position.canonicalize().map_err(js_error)?;
+        let mut db = SQLiteReader::open(&self.path).map_err(js_error)?;
+        let reporter = ConsoleReporter::none();
+        let mut querier = Querier::new(&mut db, &reporter);
+        let results = querier
+            .definitions(position, &NoCancellation)
+            .map_err(js_error)?;
+        Ok(results
+            .into_iter()
+            .map(|r| QueryResult {
+                reference: r.source.into(),
+                definitions: r.targets.into_iter().map(|t| t.span.into()).collect(),
+            })
+            .collect())
+    }
+
+    /// Returns a self-contained HTML visualization of the stack graphs of the given source files
+    /// and directories, including all complete paths that start at their references.
+    /// Visualizations only work for very small graphs.
+    #[napi]
+    pub fn to_html(&self, source_paths: Vec<String>, title: Option<String>) -> Result<String> {
+        let cancellation_flag = &stack_graphs::NoCancellation;
+        let mut db = SQLiteReader::open(&self.path).map_err(js_error)?;
+        for source_path in &source_paths {
+            let source_path = Path::new(source_path).canonicalize().map_err(js_error)?;
+            db.load_graphs_for_file_or_directory(&source_path, cancellation_flag)
+                .map_err(js_error)?;
+        }
+        let (graph, _, _) = db.get();
+        let references = graph
+            .iter_nodes()
+            .filter(|n| graph[*n].is_reference())
+            .collect::<Vec<_>>();
+        let mut complete_paths_db = stack_graphs::stitching::Database::new();
+        ForwardPartialPathStitcher::find_all_complete_partial_paths(
+            &mut db,
+            references,
+            cancellation_flag,
+            |g, ps, p| {
+                complete_paths_db.add_partial_path(g, ps, p.clone());
+            },
+        )
+        .map_err(js_error)?;
+        let (graph, partials, _) = db.get();
+        graph
+            .to_html_string(
+                title.as_deref().unwrap_or("stack-graph"),
+                partials,
+                &mut complete_paths_db,
+                &NoFilter,
+            )
+            .map_err(js_error)
+    }
+}
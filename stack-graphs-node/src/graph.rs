@@ -0,0 +1,123 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! JavaScript class for building stack graphs.
+
+use std::path::Path;
+
+use napi::Error;
+use napi::Result;
+use napi_derive::napi;
+use stack_graphs::partial::PartialPaths;
+use stack_graphs::serde::NoFilter;
+use stack_graphs::stitching::Database;
+use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::stitching::GraphEdgeCandidates;
+use tree_sitter_stack_graphs::NoCancellation;
+use tree_sitter_stack_graphs::Variables;
+
+use crate::js_error;
+use crate::loader::Loader;
+
+/// A stack graph, which can contain the stack graphs of several source files.
+#[napi]
+pub struct StackGraph {
+    inner: stack_graphs::graph::StackGraph,
+}
+
+#[napi]
+impl StackGraph {
+    /// Creates a new, empty stack graph.
+    #[napi(constructor)]
+    pub fn new() -> Self {
+        Self {
+            inner: stack_graphs::graph::StackGraph::new(),
+        }
+    }
+
+    /// Creates a stack graph from its JSON representation, as produced by `toJson`.
+    #[napi(factory)]
+    pub fn from_json(json: String) -> Result<Self> {
+        let value: stack_graphs::serde::StackGraph =
+            serde_json::from_str(&json).map_err(js_error)?;
+        let mut inner = stack_graphs::graph::StackGraph::new();
+        value.load_into(&mut inner).map_err(js_error)?;
+        Ok(Self { inner })
+    }
+
+    /// Builds the stack graph of a source file, using the language that the loader selects for
+    /// the file, and adds it to this graph.  Returns `false` if the loader has no language for
+    /// the file.  Every file can only be added once.
+    #[napi]
+    pub fn build(&mut self, loader: &mut Loader, path: String, source: String) -> Result<bool> {
+        let lcs = loader
+            .inner
+            .load_for_file(
+                Path::new(&path),
+                &mut Some(source.as_str()),
+                &NoCancellation,
+            )
+            .map_err(js_error)?;
+        let lc = match lcs.primary {
+            Some(lc) => lc,
+            None => return Ok(false),
+        };
+        let file = self
+            .inner
+            .add_file(&path)
+            .map_err(|_| Error::from_reason(format!("file {} already exists", path)))?;
+        let globals = Variables::new();
+        lc.sgl
+            .build_stack_graph_into(&mut self.inner, file, &source, &globals, &NoCancellation)
+            .map_err(js_error)?;
+        Ok(true)
+    }
+
+    /// Returns the JSON representation of this stack graph.
+    #[napi]
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(&self.inner.to_serializable()).map_err(js_error)
+    }
+
+    /// Returns a self-contained HTML visualization of this stack graph, including all complete
+    /// paths that start at its references.  Visualizations only work for very small graphs.
+    #[napi]
+    pub fn to_html(&self, title: Option<String>) -> Result<String> {
+        let graph = &self.inner;
+        let mut partials = PartialPaths::new();
+        let mut db = Database::new();
+        let references = graph
+            .iter_nodes()
+            .filter(|n| graph[*n].is_reference())
+            .collect::<Vec<_>>();
+        let mut paths = Vec::new();
+        ForwardPartialPathStitcher::find_all_complete_partial_paths(
+            &mut GraphEdgeCandidates::new(graph, &mut partials, None),
+            references,
+            &stack_graphs::NoCancellation,
+            |_, _, p| paths.push(p.clone()),
+        )
+        .map_err(js_error)?;
+        for path in paths {
+            db.add_partial_path(graph, &mut partials, path);
+        }
+        graph
+            .to_html_string(
+                title.as_deref().unwrap_or("stack-graph"),
+                &mut partials,
+                &mut db,
+                &NoFilter,
+            )
+            .map_err(js_error)
+    }
+}
+
+impl Default for StackGraph {
+    fn default() -> Self {
+        Self::new()
+    }
+}
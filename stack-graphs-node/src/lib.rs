@@ -0,0 +1,36 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Node.js bindings for the [`stack-graphs`][stack_graphs] and
+//! [`tree-sitter-stack-graphs`][tree_sitter_stack_graphs] crates.
+//!
+//! The bindings are exposed as a native Node.js addon, built with [napi-rs](https://napi.rs/).
+//! They cover the same workflow as the `tree-sitter-stack-graphs` CLI:
+//!
+//!  - `Loader` loads stack graph languages from a `stack-graphs.toml` loader configuration,
+//!  - `StackGraph` builds stack graphs for individual source files, and renders them as JSON or
+//!    as an HTML visualization, and
+//!  - `Database` indexes source files into a SQLite database, and queries the definitions of
+//!    references in indexed files.
+//!
+//! To build the addon, use the napi-rs CLI:
+//!
+//! ``` text
+//! $ npm install
+//! $ npm run build
+//! ```
+
+use napi::Error;
+
+pub mod database;
+pub mod graph;
+pub mod loader;
+
+/// Converts any displayable Rust error into a JavaScript error.
+fn js_error<E: std::fmt::Display>(err: E) -> Error {
+    Error::from_reason(err.to_string())
+}
@@ -0,0 +1,52 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! JavaScript class for loading stack graph languages.
+
+use std::path::Path;
+
+use napi::Error;
+use napi::Result;
+use napi_derive::napi;
+use tree_sitter_stack_graphs::loader::LoaderConfig;
+use tree_sitter_stack_graphs::NoCancellation;
+
+use crate::js_error;
+
+/// Loads the stack graph languages that are used to build stack graphs for source files.
+#[napi]
+pub struct Loader {
+    pub(crate) inner: tree_sitter_stack_graphs::loader::Loader,
+}
+
+#[napi]
+impl Loader {
+    /// Creates a loader for the languages declared in the given loader configuration file.  If a
+    /// cache directory is given, it is used to cache the builtins of the languages.
+    #[napi(factory)]
+    pub fn from_config(config_path: String, cache_dir: Option<String>) -> Result<Self> {
+        let config = LoaderConfig::from_path(Path::new(&config_path)).map_err(js_error)?;
+        let inner = tree_sitter_stack_graphs::loader::Loader::from_config(
+            &config,
+            None,
+            cache_dir.as_ref().map(Path::new),
+            &NoCancellation,
+        )
+        .map_err(js_error)?;
+        Ok(Self { inner })
+    }
+
+    /// Creates a loader for the loader configuration file with the default name,
+    /// `stack-graphs.toml`, in the given directory or any of its ancestors.
+    #[napi(factory)]
+    pub fn find(dir: String) -> Result<Self> {
+        let config_path = LoaderConfig::find(Path::new(&dir)).ok_or_else(|| {
+            Error::from_reason(format!("no loader configuration found for {}", dir))
+        })?;
+        Self::from_config(config_path.to_string_lossy().into_owned(), None)
+    }
+}
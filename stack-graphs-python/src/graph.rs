@@ -0,0 +1,261 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Python classes for stack graphs and the handles that refer into them.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use stack_graphs::arena::Handle;
+use stack_graphs::graph::File;
+use stack_graphs::graph::Node;
+use stack_graphs::graph::NodeID;
+use stack_graphs::graph::StackGraph;
+use stack_graphs::graph::Symbol;
+
+macro_rules! handle_class {
+    ($(#[$attr:meta])* $name:ident, $python_name:literal, $target:ty) => {
+        $(#[$attr])*
+        #[pyclass(name = $python_name, frozen)]
+        #[derive(Clone, Copy)]
+        pub struct $name {
+            pub handle: Handle<$target>,
+        }
+
+        impl From<Handle<$target>> for $name {
+            fn from(handle: Handle<$target>) -> Self {
+                Self { handle }
+            }
+        }
+
+        #[pymethods]
+        impl $name {
+            fn __eq__(&self, other: &Self) -> bool {
+                self.handle == other.handle
+            }
+
+            fn __hash__(&self) -> u64 {
+                self.handle.as_index() as u64
+            }
+
+            fn __repr__(&self) -> String {
+                format!("{}({})", $python_name, self.handle.as_index())
+            }
+        }
+    };
+}
+
+handle_class!(
+    /// A handle to a file in a stack graph.
+    PyFile,
+    "File",
+    File
+);
+
+handle_class!(
+    /// A handle to a node in a stack graph.
+    PyNode,
+    "Node",
+    Node
+);
+
+handle_class!(
+    /// A handle to a symbol in a stack graph.
+    PySymbol,
+    "Symbol",
+    Symbol
+);
+
+/// Contains all of the nodes and edges that make up a stack graph.
+#[pyclass(name = "StackGraph")]
+pub struct PyStackGraph {
+    pub inner: StackGraph,
+}
+
+#[pymethods]
+impl PyStackGraph {
+    /// Creates a new, initially empty stack graph.
+    #[new]
+    fn new() -> Self {
+        Self {
+            inner: StackGraph::new(),
+        }
+    }
+
+    /// Creates a stack graph from its JSON serialization.
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<Self> {
+        let mut graph = Self::new();
+        graph.load_json(json)?;
+        Ok(graph)
+    }
+
+    /// Adds the content of a JSON serialized stack graph to this graph.
+    fn load_json(&mut self, json: &str) -> PyResult<()> {
+        let value: stack_graphs::serde::StackGraph =
+            serde_json::from_str(json).map_err(|err| PyValueError::new_err(err.to_string()))?;
+        value
+            .load_into(&mut self.inner)
+            .map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    /// Returns the JSON serialization of this stack graph.
+    fn to_json(&self) -> PyResult<String> {
+        serde_json::to_string(&stack_graphs::serde::StackGraph::from_graph(&self.inner))
+            .map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    /// Returns the file with the given name, creating it if it doesn't exist yet.
+    fn get_or_create_file(&mut self, name: &str) -> PyFile {
+        self.inner.get_or_create_file(name).into()
+    }
+
+    /// Returns the file with the given name, or None if it doesn't exist.
+    fn get_file(&self, name: &str) -> Option<PyFile> {
+        self.inner.get_file(name).map(PyFile::from)
+    }
+
+    /// Returns all of the files in the stack graph.
+    fn files(&self) -> Vec<PyFile> {
+        self.inner.iter_files().map(PyFile::from).collect()
+    }
+
+    /// Returns the name of a file.
+    fn file_name(&self, file: PyFile) -> String {
+        self.inner[file.handle].name().to_string()
+    }
+
+    /// Adds a symbol to the stack graph, returning its handle.  Symbols are deduplicated.
+    fn add_symbol(&mut self, symbol: &str) -> PySymbol {
+        self.inner.add_symbol(symbol).into()
+    }
+
+    /// Returns the content of a symbol.
+    fn symbol_name(&self, symbol: PySymbol) -> String {
+        self.inner[symbol.handle].to_string()
+    }
+
+    /// Returns the singleton root node.
+    #[staticmethod]
+    fn root_node() -> PyNode {
+        StackGraph::root_node().into()
+    }
+
+    /// Returns the singleton "jump to" node.
+    #[staticmethod]
+    fn jump_to_node() -> PyNode {
+        StackGraph::jump_to_node().into()
+    }
+
+    /// Adds a _drop scopes_ node.  Returns None if a node with the same ID already exists.
+    fn add_drop_scopes_node(&mut self, file: PyFile, local_id: u32) -> Option<PyNode> {
+        let id = NodeID::new_in_file(file.handle, local_id);
+        self.inner.add_drop_scopes_node(id).map(PyNode::from)
+    }
+
+    /// Adds a _pop scoped symbol_ node.  Returns None if a node with the same ID already exists.
+    #[pyo3(signature = (file, local_id, symbol, is_definition = false))]
+    fn add_pop_scoped_symbol_node(
+        &mut self,
+        file: PyFile,
+        local_id: u32,
+        symbol: PySymbol,
+        is_definition: bool,
+    ) -> Option<PyNode> {
+        let id = NodeID::new_in_file(file.handle, local_id);
+        self.inner
+            .add_pop_scoped_symbol_node(id, symbol.handle, is_definition)
+            .map(PyNode::from)
+    }
+
+    /// Adds a _pop symbol_ node.  Returns None if a node with the same ID already exists.
+    #[pyo3(signature = (file, local_id, symbol, is_definition = false))]
+    fn add_pop_symbol_node(
+        &mut self,
+        file: PyFile,
+        local_id: u32,
+        symbol: PySymbol,
+        is_definition: bool,
+    ) -> Option<PyNode> {
+        let id = NodeID::new_in_file(file.handle, local_id);
+        self.inner
+            .add_pop_symbol_node(id, symbol.handle, is_definition)
+            .map(PyNode::from)
+    }
+
+    /// Adds a _push scoped symbol_ node, whose attached scope is the exported scope node `scope`.
+    /// Returns None if a node with the same ID already exists.
+    #[pyo3(signature = (file, local_id, symbol, scope, is_reference = false))]
+    fn add_push_scoped_symbol_node(
+        &mut self,
+        file: PyFile,
+        local_id: u32,
+        symbol: PySymbol,
+        scope: PyNode,
+        is_reference: bool,
+    ) -> Option<PyNode> {
+        let id = NodeID::new_in_file(file.handle, local_id);
+        let scope = self.inner[scope.handle].id();
+        self.inner
+            .add_push_scoped_symbol_node(id, symbol.handle, scope, is_reference)
+            .map(PyNode::from)
+    }
+
+    /// Adds a _push symbol_ node.  Returns None if a node with the same ID already exists.
+    #[pyo3(signature = (file, local_id, symbol, is_reference = false))]
+    fn add_push_symbol_node(
+        &mut self,
+        file: PyFile,
+        local_id: u32,
+        symbol: PySymbol,
+        is_reference: bool,
+    ) -> Option<PyNode> {
+        let id = NodeID::new_in_file(file.handle, local_id);
+        self.inner
+            .add_push_symbol_node(id, symbol.handle, is_reference)
+            .map(PyNode::from)
+    }
+
+    /// Adds a _scope_ node.  Returns None if a node with the same ID already exists.
+    #[pyo3(signature = (file, local_id, is_exported = false))]
+    fn add_scope_node(&mut self, file: PyFile, local_id: u32, is_exported: bool) -> Option<PyNode> {
+        let id = NodeID::new_in_file(file.handle, local_id);
+        self.inner.add_scope_node(id, is_exported).map(PyNode::from)
+    }
+
+    /// Adds an edge between two nodes.
+    #[pyo3(signature = (source, sink, precedence = 0))]
+    fn add_edge(&mut self, source: PyNode, sink: PyNode, precedence: i32) {
+        self.inner.add_edge(source.handle, sink.handle, precedence);
+    }
+
+    /// Returns all of the nodes that belong to a file.
+    fn nodes_for_file(&self, file: PyFile) -> Vec<PyNode> {
+        nodes_for_file(&self.inner, file)
+    }
+
+    /// Returns whether a node is a definition.
+    fn node_is_definition(&self, node: PyNode) -> bool {
+        self.inner[node.handle].is_definition()
+    }
+
+    /// Returns whether a node is a reference.
+    fn node_is_reference(&self, node: PyNode) -> bool {
+        self.inner[node.handle].is_reference()
+    }
+
+    /// Returns a human-readable description of a node.
+    fn display_node(&self, node: PyNode) -> String {
+        node.handle.display(&self.inner).to_string()
+    }
+}
+
+pub(crate) fn nodes_for_file(graph: &StackGraph, file: PyFile) -> Vec<PyNode> {
+    graph
+        .nodes_for_file(file.handle)
+        .map(PyNode::from)
+        .collect()
+}
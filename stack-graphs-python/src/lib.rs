@@ -0,0 +1,55 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Python bindings for the [`stack-graphs`][stack_graphs] crate.
+//!
+//! The bindings mirror the surface of the C API.  They are exposed as a Python module named
+//! `stack_graphs`, which contains the following classes:
+//!
+//!  - `StackGraph`, together with the `File`, `Symbol`, and `Node` handles that refer into it,
+//!  - `PartialPaths`, the arena that holds the contents of partial paths, and `PartialPath`,
+//!  - `Database`, a collection of partial paths that can be used for path stitching,
+//!  - `ForwardPartialPathStitcher`, which implements the phased path-stitching algorithm, and
+//!  - `SQLiteReader`, which loads graphs and partial paths from a database written by the
+//!    `tree-sitter-stack-graphs` CLI or by `stack_graphs::storage::SQLiteWriter`.
+//!
+//! Handles and partial paths are only meaningful together with the graph and arena that they
+//! were created in.  Just like in Rust, it is up to you to not mix them up.
+//!
+//! To build the Python extension module, use [maturin](https://www.maturin.rs/):
+//!
+//! ``` text
+//! $ maturin develop --release
+//! ```
+
+use pyo3::prelude::*;
+
+pub mod graph;
+pub mod partial;
+pub mod stitching;
+pub mod storage;
+
+/// The `stack_graphs` Python module.
+#[pymodule]
+#[pyo3(name = "stack_graphs")]
+pub fn python_module(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<graph::PyFile>()?;
+    module.add_class::<graph::PyNode>()?;
+    module.add_class::<graph::PyStackGraph>()?;
+    module.add_class::<graph::PySymbol>()?;
+    module.add_class::<partial::PyPartialPath>()?;
+    module.add_class::<partial::PyPartialPaths>()?;
+    module.add_class::<stitching::PyDatabase>()?;
+    module.add_class::<stitching::PyForwardPartialPathStitcher>()?;
+    module.add_class::<storage::PyFileStatus>()?;
+    module.add_class::<storage::PySQLiteReader>()?;
+    module.add(
+        "StorageError",
+        module.py().get_type_bound::<storage::StorageError>(),
+    )?;
+    Ok(())
+}
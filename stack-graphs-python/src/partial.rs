@@ -0,0 +1,74 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Python classes for partial paths.
+
+use pyo3::prelude::*;
+use stack_graphs::partial::PartialPath;
+use stack_graphs::partial::PartialPaths;
+
+use crate::graph::PyNode;
+use crate::graph::PyStackGraph;
+
+/// Manages the state of a collection of partial paths.
+#[pyclass(name = "PartialPaths")]
+pub struct PyPartialPaths {
+    pub inner: PartialPaths,
+}
+
+#[pymethods]
+impl PyPartialPaths {
+    /// Creates a new, initially empty partial path arena.
+    #[new]
+    fn new() -> Self {
+        Self {
+            inner: PartialPaths::new(),
+        }
+    }
+}
+
+/// A portion of a name-binding path.  The content of a partial path is stored in the
+/// `PartialPaths` arena that it was created in.
+#[pyclass(name = "PartialPath")]
+#[derive(Clone)]
+pub struct PyPartialPath {
+    pub inner: PartialPath,
+}
+
+impl From<PartialPath> for PyPartialPath {
+    fn from(inner: PartialPath) -> Self {
+        Self { inner }
+    }
+}
+
+#[pymethods]
+impl PyPartialPath {
+    /// The node that the partial path starts at.
+    #[getter]
+    fn start_node(&self) -> PyNode {
+        self.inner.start_node.into()
+    }
+
+    /// The node that the partial path ends at.
+    #[getter]
+    fn end_node(&self) -> PyNode {
+        self.inner.end_node.into()
+    }
+
+    /// Returns whether the partial path is complete, i.e., it resolves a reference to a
+    /// definition.
+    fn is_complete(&self, graph: &PyStackGraph) -> bool {
+        self.inner.is_complete(&graph.inner)
+    }
+
+    /// Returns a human-readable description of the partial path.
+    fn display(&self, graph: &PyStackGraph, partials: &mut PyPartialPaths) -> String {
+        self.inner
+            .display(&graph.inner, &mut partials.inner)
+            .to_string()
+    }
+}
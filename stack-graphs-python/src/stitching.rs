@@ -0,0 +1,191 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Python classes for the path-stitching algorithm.
+
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+use stack_graphs::arena::Handle;
+use stack_graphs::partial::PartialPath;
+use stack_graphs::stitching::Database;
+use stack_graphs::stitching::DatabaseCandidates;
+use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::stitching::GraphEdgeCandidates;
+use stack_graphs::CancellationError;
+use stack_graphs::NoCancellation;
+
+use crate::graph::PyFile;
+use crate::graph::PyNode;
+use crate::graph::PyStackGraph;
+use crate::partial::PyPartialPath;
+use crate::partial::PyPartialPaths;
+
+/// Contains a "database" of partial paths, which can be extended with the partial paths that are
+/// needed during path stitching.
+#[pyclass(name = "Database")]
+pub struct PyDatabase {
+    pub inner: Database,
+}
+
+#[pymethods]
+impl PyDatabase {
+    /// Creates a new, initially empty partial path database.
+    #[new]
+    fn new() -> Self {
+        Self {
+            inner: Database::new(),
+        }
+    }
+
+    /// Adds a partial path to the database.
+    fn add_partial_path(
+        &mut self,
+        graph: &PyStackGraph,
+        partials: &mut PyPartialPaths,
+        path: PyPartialPath,
+    ) {
+        self.inner
+            .add_partial_path(&graph.inner, &mut partials.inner, path.inner);
+    }
+}
+
+/// Implements a phased forward partial path stitching algorithm.
+///
+/// Each call to `process_next_phase` extends the partial paths found in the previous phase with
+/// compatible partial paths from a database.  Before each phase, you can use
+/// `previous_phase_partial_paths` to add any partial paths that might be needed to the database.
+#[pyclass(name = "ForwardPartialPathStitcher")]
+pub struct PyForwardPartialPathStitcher {
+    pub inner: ForwardPartialPathStitcher<Handle<PartialPath>>,
+}
+
+#[pymethods]
+impl PyForwardPartialPathStitcher {
+    /// Creates a new stitcher that is "seeded" with a set of starting nodes.  The stitcher will
+    /// be set up to find complete paths only.
+    #[staticmethod]
+    fn from_nodes(
+        graph: &PyStackGraph,
+        partials: &mut PyPartialPaths,
+        starting_nodes: Vec<PyNode>,
+    ) -> Self {
+        let initial_paths = starting_nodes
+            .into_iter()
+            .map(|node| {
+                let mut path =
+                    PartialPath::from_node(&graph.inner, &mut partials.inner, node.handle);
+                path.eliminate_precondition_stack_variables(&mut partials.inner);
+                path
+            })
+            .collect::<Vec<_>>();
+        Self {
+            inner: ForwardPartialPathStitcher::from_partial_paths(
+                &graph.inner,
+                &mut partials.inner,
+                initial_paths,
+            ),
+        }
+    }
+
+    /// Creates a new stitcher that is "seeded" with a set of initial partial paths.
+    #[staticmethod]
+    fn from_partial_paths(
+        graph: &PyStackGraph,
+        partials: &mut PyPartialPaths,
+        initial_partial_paths: Vec<PyPartialPath>,
+    ) -> Self {
+        Self {
+            inner: ForwardPartialPathStitcher::from_partial_paths(
+                &graph.inner,
+                &mut partials.inner,
+                initial_partial_paths.into_iter().map(|path| path.inner),
+            ),
+        }
+    }
+
+    /// Sets whether similar path detection should be enabled during path stitching.
+    fn set_similar_path_detection(&mut self, detect_similar_paths: bool) {
+        self.inner.set_similar_path_detection(detect_similar_paths);
+    }
+
+    /// Sets the maximum amount of work that can be performed during each phase of the algorithm.
+    fn set_max_work_per_phase(&mut self, max_work_per_phase: usize) {
+        self.inner.set_max_work_per_phase(max_work_per_phase);
+    }
+
+    /// Returns the (possibly incomplete) partial paths that were encountered during the most
+    /// recent phase of the algorithm.
+    fn previous_phase_partial_paths(&self) -> Vec<PyPartialPath> {
+        self.inner
+            .previous_phase_partial_paths()
+            .cloned()
+            .map(PyPartialPath::from)
+            .collect()
+    }
+
+    /// Returns whether the stitching algorithm is complete.
+    fn is_complete(&self) -> bool {
+        self.inner.is_complete()
+    }
+
+    /// Runs the next phase of the algorithm, extending the partial paths of the previous phase
+    /// with the partial paths in `db`.
+    fn process_next_phase(
+        &mut self,
+        graph: &PyStackGraph,
+        partials: &mut PyPartialPaths,
+        db: &mut PyDatabase,
+    ) {
+        self.inner.process_next_phase(
+            &mut DatabaseCandidates::new(&graph.inner, &mut partials.inner, &mut db.inner),
+            |_, _, _| true,
+        );
+    }
+
+    /// Finds a minimal set of partial paths in a file, which is sufficient to resolve any
+    /// reference in that file in combination with the partial paths of other files.
+    #[staticmethod]
+    fn find_minimal_partial_path_set_in_file(
+        graph: &PyStackGraph,
+        partials: &mut PyPartialPaths,
+        file: PyFile,
+    ) -> PyResult<Vec<PyPartialPath>> {
+        let mut paths = Vec::new();
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph.inner,
+            &mut partials.inner,
+            file.handle,
+            &NoCancellation,
+            |_, _, path| paths.push(path.clone().into()),
+        )
+        .map_err(cancelled)?;
+        Ok(paths)
+    }
+
+    /// Finds all complete partial paths that are reachable from a set of starting nodes, using
+    /// the edges of the stack graph.
+    #[staticmethod]
+    fn find_all_complete_partial_paths(
+        graph: &PyStackGraph,
+        partials: &mut PyPartialPaths,
+        starting_nodes: Vec<PyNode>,
+    ) -> PyResult<Vec<PyPartialPath>> {
+        let mut paths = Vec::new();
+        ForwardPartialPathStitcher::find_all_complete_partial_paths(
+            &mut GraphEdgeCandidates::new(&graph.inner, &mut partials.inner, None),
+            starting_nodes.into_iter().map(|node| node.handle),
+            &NoCancellation,
+            |_, _, path| paths.push(path.clone().into()),
+        )
+        .map_err(cancelled)?;
+        Ok(paths)
+    }
+}
+
+fn cancelled(err: CancellationError) -> PyErr {
+    PyRuntimeError::new_err(format!("cancelled at {}", err.0))
+}
@@ -0,0 +1,157 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Python classes for reading stack graph databases.
+
+use std::path::PathBuf;
+
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::storage::FileStatus;
+use stack_graphs::storage::SQLiteReader;
+use stack_graphs::NoCancellation;
+
+use crate::graph::nodes_for_file;
+use crate::graph::PyFile;
+use crate::graph::PyNode;
+use crate::partial::PyPartialPath;
+
+create_exception!(
+    stack_graphs,
+    StorageError,
+    PyException,
+    "Raised when reading from a stack graph database fails."
+);
+
+fn storage_error(err: stack_graphs::storage::StorageError) -> PyErr {
+    StorageError::new_err(err.to_string())
+}
+
+/// The status of a file in a stack graph database.
+#[pyclass(name = "FileStatus")]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PyFileStatus {
+    Missing,
+    Indexed,
+    Error,
+}
+
+impl From<FileStatus> for PyFileStatus {
+    fn from(status: FileStatus) -> Self {
+        match status {
+            FileStatus::Missing => Self::Missing,
+            FileStatus::Indexed => Self::Indexed,
+            FileStatus::Error(_) => Self::Error,
+        }
+    }
+}
+
+/// Reader to load stack graphs and partial paths from a SQLite database.
+///
+/// The reader owns the stack graph and partial paths it loads.  Nodes and partial paths returned
+/// by the reader can only be used with the same reader.
+#[pyclass(name = "SQLiteReader", unsendable)]
+pub struct PySQLiteReader {
+    pub inner: SQLiteReader,
+}
+
+#[pymethods]
+impl PySQLiteReader {
+    /// Opens an existing database.
+    #[new]
+    fn open(path: PathBuf) -> PyResult<Self> {
+        let inner = SQLiteReader::open(path).map_err(storage_error)?;
+        Ok(Self { inner })
+    }
+
+    /// Clears all data that has been loaded into this reader.  All existing handles and partial
+    /// paths from this reader are invalid afterwards.
+    fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    /// Clears all partial paths that have been loaded into this reader.  Handles remain valid,
+    /// but existing partial paths from this reader are invalid afterwards.
+    fn clear_paths(&mut self) {
+        self.inner.clear_paths();
+    }
+
+    /// Returns the status of a file in the database.  If a tag is given, it must match or the
+    /// file is reported missing.
+    #[pyo3(signature = (file, tag = None))]
+    fn status_for_file(&mut self, file: &str, tag: Option<&str>) -> PyResult<PyFileStatus> {
+        self.inner
+            .status_for_file(file, tag)
+            .map(PyFileStatus::from)
+            .map_err(storage_error)
+    }
+
+    /// Ensures the graph for the given file is loaded, and returns its handle.
+    fn load_graph_for_file(&mut self, file: &str) -> PyResult<PyFile> {
+        self.inner
+            .load_graph_for_file(file)
+            .map(PyFile::from)
+            .map_err(storage_error)
+    }
+
+    /// Ensures the graphs for all files in the given file or directory are loaded.
+    fn load_graphs_for_file_or_directory(&mut self, file_or_directory: PathBuf) -> PyResult<()> {
+        self.inner
+            .load_graphs_for_file_or_directory(&file_or_directory, &NoCancellation)
+            .map_err(storage_error)
+    }
+
+    /// Ensures all possible extensions of the given partial path are loaded.
+    fn load_partial_path_extensions(&mut self, path: PyPartialPath) -> PyResult<()> {
+        self.inner
+            .load_partial_path_extensions(&path.inner, &NoCancellation)
+            .map_err(storage_error)
+    }
+
+    /// Returns all of the loaded nodes that belong to a file.
+    fn nodes_for_file(&mut self, file: PyFile) -> Vec<PyNode> {
+        let (graph, _, _) = self.inner.get();
+        nodes_for_file(graph, file)
+    }
+
+    /// Returns whether a loaded node is a reference.
+    fn node_is_reference(&mut self, node: PyNode) -> bool {
+        let (graph, _, _) = self.inner.get();
+        graph[node.handle].is_reference()
+    }
+
+    /// Returns a human-readable description of a loaded node.
+    fn display_node(&mut self, node: PyNode) -> String {
+        let (graph, _, _) = self.inner.get();
+        node.handle.display(graph).to_string()
+    }
+
+    /// Returns a human-readable description of a partial path loaded by this reader.
+    fn display_path(&mut self, path: PyPartialPath) -> String {
+        let (graph, partials, _) = self.inner.get();
+        path.inner.display(graph, partials).to_string()
+    }
+
+    /// Finds all complete partial paths that are reachable from a set of starting nodes, loading
+    /// the necessary partial paths from the database as needed.
+    fn find_all_complete_partial_paths(
+        &mut self,
+        starting_nodes: Vec<PyNode>,
+    ) -> PyResult<Vec<PyPartialPath>> {
+        let mut paths = Vec::new();
+        ForwardPartialPathStitcher::find_all_complete_partial_paths(
+            &mut self.inner,
+            starting_nodes.into_iter().map(|node| node.handle),
+            &NoCancellation,
+            |_, _, path| paths.push(path.clone().into()),
+        )
+        .map_err(storage_error)?;
+        Ok(paths)
+    }
+}
@@ -0,0 +1,56 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use crate::run_python;
+
+#[test]
+fn can_create_graph() {
+    run_python(
+        r#"
+import stack_graphs as sg
+
+graph = sg.StackGraph()
+main = graph.get_or_create_file("main.py")
+assert graph.get_or_create_file("main.py") == main
+assert graph.get_file("missing.py") is None
+assert graph.file_name(main) == "main.py"
+
+x = graph.add_symbol("x")
+assert graph.add_symbol("x") == x
+assert graph.symbol_name(x) == "x"
+
+ref = graph.add_push_symbol_node(main, 1, x, is_reference=True)
+assert graph.add_push_symbol_node(main, 1, x) is None
+graph.add_edge(ref, sg.StackGraph.root_node())
+assert graph.nodes_for_file(main) == [ref]
+assert graph.node_is_reference(ref)
+assert not graph.node_is_definition(ref)
+assert graph.display_node(ref) == "[main.py(1) reference x]"
+"#,
+        &[],
+    );
+}
+
+#[test]
+fn can_round_trip_graph_through_json() {
+    run_python(
+        r#"
+import stack_graphs as sg
+
+graph = sg.StackGraph()
+main = graph.get_or_create_file("main.py")
+scope = graph.add_scope_node(main, 1, is_exported=True)
+graph.add_edge(sg.StackGraph.root_node(), scope)
+
+copy = sg.StackGraph.from_json(graph.to_json())
+main = copy.get_file("main.py")
+assert main is not None
+assert [copy.display_node(n) for n in copy.nodes_for_file(main)] == ["[main.py(1) exported scope]"]
+"#,
+        &[],
+    );
+}
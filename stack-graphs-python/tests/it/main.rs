@@ -0,0 +1,39 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+// The tests embed a Python interpreter, which requires linking against libpython.  That is
+// disabled when building the extension module.
+#![cfg(not(feature = "extension-module"))]
+
+use std::sync::Once;
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use stack_graphs_python::python_module;
+
+mod graph;
+mod stitching;
+mod storage;
+
+/// Runs a Python script that can import the `stack_graphs` module.  The given variables are
+/// available as globals in the script.
+pub(crate) fn run_python(code: &str, locals: &[(&str, &str)]) {
+    static REGISTER_MODULE: Once = Once::new();
+    REGISTER_MODULE.call_once(|| {
+        pyo3::append_to_inittab!(python_module);
+    });
+    Python::with_gil(|py| {
+        let dict = PyDict::new_bound(py);
+        for (name, value) in locals {
+            dict.set_item(name, value).unwrap();
+        }
+        if let Err(err) = py.run_bound(code, Some(&dict), None) {
+            err.print(py);
+            panic!("Python script failed: {}", err);
+        }
+    });
+}
@@ -0,0 +1,60 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use crate::run_python;
+
+static CREATE_GRAPH: &'static str = r#"
+import stack_graphs as sg
+
+graph = sg.StackGraph()
+main = graph.get_or_create_file("main.py")
+lib = graph.get_or_create_file("lib.py")
+x = graph.add_symbol("x")
+ref = graph.add_push_symbol_node(main, 1, x, is_reference=True)
+graph.add_edge(ref, sg.StackGraph.root_node())
+definition = graph.add_pop_symbol_node(lib, 1, x, is_definition=True)
+graph.add_edge(sg.StackGraph.root_node(), definition)
+partials = sg.PartialPaths()
+
+EXPECTED = ["<> () [main.py(1) reference x] -> [lib.py(1) definition x] <> ()"]
+"#;
+
+#[test]
+fn can_find_all_complete_partial_paths() {
+    run_python(
+        &(CREATE_GRAPH.to_string()
+            + r#"
+paths = sg.ForwardPartialPathStitcher.find_all_complete_partial_paths(graph, partials, [ref])
+assert [p.display(graph, partials) for p in paths] == EXPECTED
+"#),
+        &[],
+    );
+}
+
+#[test]
+fn can_stitch_partial_paths_in_phases() {
+    run_python(
+        &(CREATE_GRAPH.to_string()
+            + r#"
+db = sg.Database()
+for file in graph.files():
+    for path in sg.ForwardPartialPathStitcher.find_minimal_partial_path_set_in_file(graph, partials, file):
+        db.add_partial_path(graph, partials, path)
+
+stitcher = sg.ForwardPartialPathStitcher.from_nodes(graph, partials, [ref])
+stitcher.set_max_work_per_phase(1)
+results = []
+while not stitcher.is_complete():
+    for path in stitcher.previous_phase_partial_paths():
+        if path.is_complete(graph):
+            results.append(path.display(graph, partials))
+    stitcher.process_next_phase(graph, partials, db)
+assert results == EXPECTED
+"#),
+        &[],
+    );
+}
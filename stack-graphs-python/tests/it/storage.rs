@@ -0,0 +1,93 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use stack_graphs::graph::NodeID;
+use stack_graphs::graph::StackGraph;
+use stack_graphs::partial::PartialPaths;
+use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::storage::SQLiteWriter;
+use stack_graphs::NoCancellation;
+
+use crate::run_python;
+
+fn write_database(path: &std::path::Path) {
+    let mut graph = StackGraph::new();
+    let main = graph.get_or_create_file("main.py");
+    let lib = graph.get_or_create_file("lib.py");
+    let x = graph.add_symbol("x");
+    let root = StackGraph::root_node();
+    let reference = graph
+        .add_push_symbol_node(NodeID::new_in_file(main, 1), x, true)
+        .unwrap();
+    graph.add_edge(reference, root, 0);
+    let definition = graph
+        .add_pop_symbol_node(NodeID::new_in_file(lib, 1), x, true)
+        .unwrap();
+    graph.add_edge(root, definition, 0);
+
+    let mut partials = PartialPaths::new();
+    let mut writer = SQLiteWriter::open(path).unwrap();
+    for file in [main, lib] {
+        let mut paths = Vec::new();
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            &NoCancellation,
+            |_, _, path| paths.push(path.clone()),
+        )
+        .unwrap();
+        writer
+            .store_result_for_file(&graph, file, "", &mut partials, &paths)
+            .unwrap();
+    }
+}
+
+#[test]
+fn can_find_complete_partial_paths_in_database() {
+    let db_path =
+        std::env::temp_dir().join(format!("stack-graphs-python-{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&db_path);
+    write_database(&db_path);
+
+    run_python(
+        r#"
+import stack_graphs as sg
+
+reader = sg.SQLiteReader(db_path)
+assert reader.status_for_file("main.py") == sg.FileStatus.Indexed
+assert reader.status_for_file("main.py", "other") == sg.FileStatus.Missing
+assert reader.status_for_file("missing.py") == sg.FileStatus.Missing
+
+main = reader.load_graph_for_file("main.py")
+references = [n for n in reader.nodes_for_file(main) if reader.node_is_reference(n)]
+paths = reader.find_all_complete_partial_paths(references)
+assert [reader.display_path(p) for p in paths] == [
+    "<> () [main.py(1) reference x] -> [lib.py(1) definition x] <> ()",
+]
+"#,
+        &[("db_path", db_path.to_str().unwrap())],
+    );
+
+    let _ = std::fs::remove_file(&db_path);
+}
+
+#[test]
+fn cannot_open_missing_database() {
+    run_python(
+        r#"
+import stack_graphs as sg
+
+try:
+    sg.SQLiteReader("/does/not/exist.sqlite")
+    assert False, "expected a StorageError"
+except sg.StorageError:
+    pass
+"#,
+        &[],
+    );
+}
@@ -0,0 +1,116 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! WebAssembly bindings for the [`stack-graphs`][stack_graphs] crate.
+//!
+//! The bindings load a stack graph from its JSON serialization, which is the format produced by
+//! `StackGraph::to_serializable` and embedded in HTML visualizations, and resolve references by
+//! path stitching over the graph itself.  Because everything happens in memory, no database is
+//! needed, which makes it possible to answer queries client-side, for example in a browser.
+//!
+//! Nodes are identified by the same `{ file, local_id }` objects that are used in the JSON
+//! serialization.  Complete paths are returned in their JSON serialization as well.
+//!
+//! To build the bindings, use [wasm-pack](https://rustwasm.github.io/wasm-pack/):
+//!
+//! ``` text
+//! $ wasm-pack build --target web
+//! ```
+//!
+//! The [`query`][] module contains the Rust implementation of the queries, which does not depend
+//! on a JavaScript runtime.
+
+use stack_graphs::partial::PartialPaths;
+use wasm_bindgen::prelude::*;
+
+pub mod query;
+
+/// A stack graph that was loaded from its JSON serialization.
+#[wasm_bindgen]
+pub struct StackGraph {
+    graph: stack_graphs::graph::StackGraph,
+    partials: PartialPaths,
+}
+
+#[wasm_bindgen]
+impl StackGraph {
+    /// Loads a stack graph from its JSON serialization.
+    #[wasm_bindgen(js_name = fromJson)]
+    pub fn from_json(json: &str) -> Result<StackGraph, JsError> {
+        let graph = query::load_json(json)?;
+        Ok(Self {
+            graph,
+            partials: PartialPaths::new(),
+        })
+    }
+
+    /// Returns the names of all files in this graph.
+    pub fn files(&self) -> Vec<String> {
+        self.graph
+            .iter_files()
+            .map(|file| self.graph[file].to_string())
+            .collect()
+    }
+
+    /// Returns the IDs of all reference nodes in the given file whose source span contains the
+    /// given position.  Lines and columns are 0-based, and columns are counted in graphemes.
+    #[wasm_bindgen(js_name = referencesAt)]
+    pub fn references_at(&self, file: &str, line: u32, column: u32) -> Result<JsValue, JsError> {
+        let references = query::references_at(&self.graph, file, line as usize, column as usize)
+            .into_iter()
+            .map(|node| stack_graphs::serde::NodeID::from_node(&self.graph, node))
+            .collect::<Vec<_>>();
+        Ok(serde_wasm_bindgen::to_value(&references)?)
+    }
+
+    /// Returns the IDs of the definition nodes that the given reference node resolves to.
+    /// Definitions that are shadowed by other definitions are not included.
+    pub fn definitions(&mut self, reference: JsValue) -> Result<JsValue, JsError> {
+        let reference = self.node(reference)?;
+        let definitions = query::definitions(
+            &self.graph,
+            &mut self.partials,
+            reference,
+            &stack_graphs::NoCancellation,
+        )?
+        .into_iter()
+        .map(|node| stack_graphs::serde::NodeID::from_node(&self.graph, node))
+        .collect::<Vec<_>>();
+        Ok(serde_wasm_bindgen::to_value(&definitions)?)
+    }
+
+    /// Returns the JSON serialization of all complete paths that start at the given reference
+    /// node.
+    #[wasm_bindgen(js_name = completePaths)]
+    pub fn complete_paths(&mut self, reference: JsValue) -> Result<JsValue, JsError> {
+        let reference = self.node(reference)?;
+        let paths = query::complete_paths(
+            &self.graph,
+            &mut self.partials,
+            reference,
+            &stack_graphs::NoCancellation,
+        )?
+        .into_iter()
+        .map(|path| {
+            stack_graphs::serde::PartialPath::from_partial_path(
+                &self.graph,
+                &mut self.partials,
+                &path,
+            )
+        })
+        .collect::<Vec<_>>();
+        Ok(serde_wasm_bindgen::to_value(&paths)?)
+    }
+
+    fn node(
+        &mut self,
+        id: JsValue,
+    ) -> Result<stack_graphs::arena::Handle<stack_graphs::graph::Node>, JsError> {
+        let id: stack_graphs::serde::NodeID = serde_wasm_bindgen::from_value(id)?;
+        Ok(id.to_node(&mut self.graph)?)
+    }
+}
@@ -0,0 +1,105 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Queries over in-memory stack graphs, which back the JavaScript API.
+
+use stack_graphs::arena::Handle;
+use stack_graphs::graph::Node;
+use stack_graphs::graph::StackGraph;
+use stack_graphs::partial::PartialPath;
+use stack_graphs::partial::PartialPaths;
+use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::stitching::GraphEdgeCandidates;
+use stack_graphs::CancellationError;
+use stack_graphs::CancellationFlag;
+use thiserror::Error;
+
+/// An error that can occur while loading a stack graph from JSON.
+#[derive(Debug, Error)]
+pub enum LoadError {
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Serde(#[from] stack_graphs::serde::Error),
+}
+
+/// Loads a stack graph from its JSON serialization.
+pub fn load_json(json: &str) -> Result<StackGraph, LoadError> {
+    let value: stack_graphs::serde::StackGraph = serde_json::from_str(json)?;
+    let mut graph = StackGraph::new();
+    value.load_into(&mut graph)?;
+    Ok(graph)
+}
+
+/// Returns all reference nodes in the given file whose source span contains the given position.
+/// Lines and columns are 0-based, and columns are counted in graphemes.
+pub fn references_at(
+    graph: &StackGraph,
+    file: &str,
+    line: usize,
+    column: usize,
+) -> Vec<Handle<Node>> {
+    let file = match graph.get_file(file) {
+        Some(file) => file,
+        None => return Vec::new(),
+    };
+    graph
+        .nodes_for_file(file)
+        .filter(|node| graph[*node].is_reference())
+        .filter(|node| match graph.source_info(*node) {
+            Some(source_info) => {
+                let span = &source_info.span;
+                let after_start = span.start.line < line
+                    || (span.start.line == line && span.start.column.grapheme_offset <= column);
+                let before_end = span.end.line > line
+                    || (span.end.line == line && span.end.column.grapheme_offset >= column);
+                after_start && before_end
+            }
+            None => false,
+        })
+        .collect()
+}
+
+/// Returns all complete paths that start at the given reference node.  Paths are found by
+/// stitching the edges of the graph, so all files the paths pass through must be part of the
+/// graph.
+pub fn complete_paths(
+    graph: &StackGraph,
+    partials: &mut PartialPaths,
+    reference: Handle<Node>,
+    cancellation_flag: &dyn CancellationFlag,
+) -> Result<Vec<PartialPath>, CancellationError> {
+    let mut paths = Vec::new();
+    ForwardPartialPathStitcher::find_all_complete_partial_paths(
+        &mut GraphEdgeCandidates::new(graph, partials, None),
+        std::iter::once(reference),
+        cancellation_flag,
+        |_, _, path| paths.push(path.clone()),
+    )?;
+    Ok(paths)
+}
+
+/// Returns the definition nodes that the given reference node resolves to.  Definitions whose
+/// paths are shadowed by the path to another definition are not included.
+pub fn definitions(
+    graph: &StackGraph,
+    partials: &mut PartialPaths,
+    reference: Handle<Node>,
+    cancellation_flag: &dyn CancellationFlag,
+) -> Result<Vec<Handle<Node>>, CancellationError> {
+    let paths = complete_paths(graph, partials, reference, cancellation_flag)?;
+    let mut definitions = Vec::new();
+    for path in &paths {
+        cancellation_flag.check("shadowing")?;
+        if paths.iter().all(|other| !other.shadows(partials, path))
+            && !definitions.contains(&path.end_node)
+        {
+            definitions.push(path.end_node);
+        }
+    }
+    Ok(definitions)
+}
@@ -0,0 +1,104 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use pretty_assertions::assert_eq;
+use stack_graphs::arena::Handle;
+use stack_graphs::graph::File;
+use stack_graphs::graph::Node;
+use stack_graphs::graph::StackGraph;
+use stack_graphs::partial::PartialPaths;
+use stack_graphs::NoCancellation;
+use stack_graphs_wasm::query;
+
+fn set_span(graph: &mut StackGraph, node: Handle<Node>, line: usize, start: usize, end: usize) {
+    let span = &mut graph.source_info_mut(node).span;
+    span.start.line = line;
+    span.start.column.grapheme_offset = start;
+    span.end.line = line;
+    span.end.column.grapheme_offset = end;
+}
+
+/// Creates a graph for the following file, where the reference to `x` can resolve to both
+/// definitions, but the first one has a higher precedence:
+///
+/// ``` text
+/// x = 1
+/// x = 2
+/// print(x)
+/// ```
+fn create_graph() -> (StackGraph, Handle<File>) {
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("test.py");
+    let x = graph.add_symbol("x");
+    let root = StackGraph::root_node();
+
+    let id = graph.new_node_id(file);
+    let def1 = graph.add_pop_symbol_node(id, x, true).unwrap();
+    set_span(&mut graph, def1, 0, 0, 1);
+    graph.add_edge(root, def1, 1);
+
+    let id = graph.new_node_id(file);
+    let def2 = graph.add_pop_symbol_node(id, x, true).unwrap();
+    set_span(&mut graph, def2, 1, 0, 1);
+    graph.add_edge(root, def2, 0);
+
+    let id = graph.new_node_id(file);
+    let reference = graph.add_push_symbol_node(id, x, true).unwrap();
+    set_span(&mut graph, reference, 2, 6, 7);
+    graph.add_edge(reference, root, 0);
+
+    (graph, file)
+}
+
+fn to_json(graph: &StackGraph) -> String {
+    serde_json::to_string(&graph.to_serializable()).unwrap()
+}
+
+#[test]
+fn can_load_graph_from_json() {
+    let (graph, _) = create_graph();
+    let json = to_json(&graph);
+    let loaded = query::load_json(&json).expect("Expected graph to load");
+    assert_eq!(json, to_json(&loaded));
+}
+
+#[test]
+fn cannot_load_graph_from_invalid_json() {
+    assert!(query::load_json("{}").is_err());
+    assert!(query::load_json("not json").is_err());
+}
+
+#[test]
+fn can_find_references_at_position() {
+    let (graph, _) = create_graph();
+    let graph = query::load_json(&to_json(&graph)).unwrap();
+    assert_eq!(1, query::references_at(&graph, "test.py", 2, 6).len());
+    assert_eq!(1, query::references_at(&graph, "test.py", 2, 7).len());
+    assert_eq!(0, query::references_at(&graph, "test.py", 2, 2).len());
+    assert_eq!(0, query::references_at(&graph, "test.py", 0, 0).len());
+    assert_eq!(0, query::references_at(&graph, "other.py", 2, 6).len());
+}
+
+#[test]
+fn can_find_definitions_of_reference() {
+    let (graph, file) = create_graph();
+    let mut partials = PartialPaths::new();
+    let reference = query::references_at(&graph, "test.py", 2, 6)[0];
+
+    let paths = query::complete_paths(&graph, &mut partials, reference, &NoCancellation).unwrap();
+    assert_eq!(2, paths.len());
+
+    let definitions =
+        query::definitions(&graph, &mut partials, reference, &NoCancellation).unwrap();
+    assert_eq!(1, definitions.len());
+    let definition = &graph[definitions[0]];
+    assert_eq!(Some(file), definition.id().file());
+    assert_eq!(
+        0,
+        graph.source_info(definitions[0]).unwrap().span.start.line
+    );
+}
@@ -9,12 +9,6 @@
 #include <stdint.h>
 #include <stdlib.h>
 
-// The null value for all of our handles.
-#define SG_NULL_HANDLE 0
-
-// The handle of an empty list.
-#define SG_LIST_EMPTY_HANDLE 4294967295
-
 // The local_id of the singleton root node.
 #define SG_ROOT_NODE_ID 1
 
@@ -27,6 +21,18 @@ enum sg_deque_direction {
     SG_DEQUE_BACKWARDS,
 };
 
+#if defined(SG_STORAGE)
+// The status of a file in a stack graph database.
+enum sg_file_status {
+    // The file is not in the database, or was stored with a different tag.
+    SG_FILE_STATUS_MISSING,
+    // The file was indexed successfully.
+    SG_FILE_STATUS_INDEXED,
+    // Indexing the file resulted in an error.
+    SG_FILE_STATUS_ERROR,
+};
+#endif
+
 // The different kinds of node that can appear in a stack graph.
 enum sg_node_kind {
     // Removes everything from the current scope stack.
@@ -59,6 +65,26 @@ enum sg_result {
     SG_RESULT_CANCELLED,
 };
 
+#if defined(SG_STORAGE)
+// Describes the result of a storage operation
+enum sg_storage_result {
+    SG_STORAGE_RESULT_SUCCESS,
+    SG_STORAGE_RESULT_CANCELLED,
+    // The database was created by an incompatible version of this library.
+    SG_STORAGE_RESULT_INCORRECT_VERSION,
+    // The database file does not exist.
+    SG_STORAGE_RESULT_MISSING_DATABASE,
+    // A string argument was not valid UTF-8.
+    SG_STORAGE_RESULT_INVALID_ARGUMENT,
+    // The underlying SQLite database reported an error.
+    SG_STORAGE_RESULT_DATABASE_ERROR,
+    // Stack graph or partial path data could not be serialized or deserialized.
+    SG_STORAGE_RESULT_SERIALIZATION_ERROR,
+    // The database was locked by another connection for longer than the busy timeout.
+    SG_STORAGE_RESULT_CONTENDED,
+};
+#endif
+
 // Manages the state of a collection of partial paths to be used in the path-stitching algorithm.
 struct sg_partial_path_arena;
 
@@ -79,6 +105,20 @@ struct sg_partial_path_list;
 // Contains all of the nodes and edges that make up a stack graph.
 struct sg_stack_graph;
 
+#if defined(SG_STORAGE)
+// Reader to load stack graphs and partial paths from a SQLite database.
+//
+// The reader owns the stack graph, partial path arena, and partial path database that it loads
+// data into.  Use `sg_storage_reader_stack_graph`, `sg_storage_reader_partial_path_arena`, and
+// `sg_storage_reader_partial_path_database` to access them.
+struct sg_storage_reader;
+#endif
+
+#if defined(SG_STORAGE)
+// Writer to store stack graphs and partial paths in a SQLite database.
+struct sg_storage_writer;
+#endif
+
 // A name that we are trying to resolve using stack graphs.
 //
 // This typically represents a portion of an identifier as it appears in the source language.  It
@@ -98,12 +138,26 @@ struct sg_symbols {
     size_t count;
 };
 
+#if !defined(SG_LARGE_HANDLES)
+// The integer type of all of our handles.  This is `uint64_t` if the library was built with the
+// `large-handles` feature, in which case you must define `SG_LARGE_HANDLES` before including this
+// header, and `uint32_t` otherwise.
+typedef uint32_t sg_handle_index;
+#endif
+
+#if defined(SG_LARGE_HANDLES)
+// The integer type of all of our handles.  This is `uint64_t` if the library was built with the
+// `large-handles` feature, in which case you must define `SG_LARGE_HANDLES` before including this
+// header, and `uint32_t` otherwise.
+typedef uint64_t sg_handle_index;
+#endif
+
 // A handle to a symbol in a stack graph.  A zero handle represents a missing symbol.
 //
 // We deduplicate symbols in a stack graph — that is, we ensure that there are never multiple
 // `struct sg_symbol` instances with the same content.  That means that you can compare symbol
 // handles using simple equality, without having to dereference them.
-typedef uint32_t sg_symbol_handle;
+typedef sg_handle_index sg_symbol_handle;
 
 // Arbitrary string content associated with some part of a stack graph.
 struct sg_string {
@@ -124,7 +178,7 @@ struct sg_strings {
 // We deduplicate strings in a stack graph — that is, we ensure that there are never multiple
 // `struct sg_string` instances with the same content.  That means that you can compare string
 // handles using simple equality, without having to dereference them.
-typedef uint32_t sg_string_handle;
+typedef sg_handle_index sg_string_handle;
 
 // A source file that we have extracted stack graph data from.
 //
@@ -151,7 +205,7 @@ struct sg_files {
 // We deduplicate files in a stack graph — that is, we ensure that there are never multiple
 // `struct sg_file` instances with the same filename.  That means that you can compare file
 // handles using simple equality, without having to dereference them.
-typedef uint32_t sg_file_handle;
+typedef sg_handle_index sg_file_handle;
 
 // Uniquely identifies a node in a stack graph.
 //
@@ -190,7 +244,7 @@ struct sg_nodes {
 };
 
 // A handle to a node in a stack graph.  A zero handle represents a missing node.
-typedef uint32_t sg_node_handle;
+typedef sg_handle_index sg_node_handle;
 
 // Connects two nodes in a stack graph.
 //
@@ -285,8 +339,8 @@ struct sg_node_source_info {
 };
 
 // A handle to an element of a partial scope stack.  A zero handle represents a missing partial
-// scope stack.  A UINT32_MAX handle represents an empty partial scope stack.
-typedef uint32_t sg_partial_scope_stack_cell_handle;
+// scope stack.  An SG_LIST_EMPTY_HANDLE handle represents an empty partial scope stack.
+typedef sg_handle_index sg_partial_scope_stack_cell_handle;
 
 // Represents an unknown list of exported scopes.
 typedef uint32_t sg_scope_stack_variable;
@@ -298,6 +352,11 @@ struct sg_partial_scope_stack {
     // list is empty, or 0 if the list is null.
     sg_partial_scope_stack_cell_handle cells;
     enum sg_deque_direction direction;
+#if defined(SG_LARGE_HANDLES)
+    // Always zero.  Only present with 64-bit handles, to match the layout of the Rust type.
+    uint32_t padding
+#endif
+    ;
     uint32_t length;
     // The scope stack variable representing the unknown content of a partial scope stack, or 0 if
     // the variable is missing.  (If so, this partial scope stack can only match a scope stack
@@ -313,8 +372,8 @@ struct sg_partial_scoped_symbol {
 };
 
 // A handle to an element of a partial symbol stack.  A zero handle represents a missing partial
-// symbol stack.  A UINT32_MAX handle represents an empty partial symbol stack.
-typedef uint32_t sg_partial_symbol_stack_cell_handle;
+// symbol stack.  An SG_LIST_EMPTY_HANDLE handle represents an empty partial symbol stack.
+typedef sg_handle_index sg_partial_symbol_stack_cell_handle;
 
 // An element of a partial symbol stack.
 struct sg_partial_symbol_stack_cell {
@@ -349,6 +408,11 @@ struct sg_partial_symbol_stack {
     // list is empty, or 0 if the list is null.
     sg_partial_symbol_stack_cell_handle cells;
     enum sg_deque_direction direction;
+#if defined(SG_LARGE_HANDLES)
+    // Always zero.  Only present with 64-bit handles, to match the layout of the Rust type.
+    uint32_t padding
+#endif
+    ;
     uint32_t length;
     // The symbol stack variable representing the unknown content of a partial symbol stack, or 0
     // if the variable is missing.  (If so, this partial symbol stack can only match a symbol
@@ -381,8 +445,8 @@ struct sg_partial_path_edge {
 };
 
 // A handle to an element of a partial path edge list.  A zero handle represents a missing partial
-// path edge list.  A UINT32_MAX handle represents an empty partial path edge list.
-typedef uint32_t sg_partial_path_edge_list_cell_handle;
+// path edge list.  An SG_LIST_EMPTY_HANDLE handle represents an empty partial path edge list.
+typedef sg_handle_index sg_partial_path_edge_list_cell_handle;
 
 // An element of a partial path edge list.
 struct sg_partial_path_edge_list_cell {
@@ -408,6 +472,11 @@ struct sg_partial_path_edge_list {
     // empty, or 0 if the list is null.
     sg_partial_path_edge_list_cell_handle cells;
     enum sg_deque_direction direction;
+#if defined(SG_LARGE_HANDLES)
+    // Always zero.  Only present with 64-bit handles, to match the layout of the Rust type.
+    uint32_t padding
+#endif
+    ;
     uint32_t length;
 };
 
@@ -447,7 +516,7 @@ struct sg_partial_paths {
 
 // A handle to a partial path in a partial path database.  A zero handle represents a missing
 // partial path.
-typedef uint32_t sg_partial_path_handle;
+typedef sg_handle_index sg_partial_path_handle;
 
 // Encodes a set of node handles.
 //
@@ -495,6 +564,19 @@ struct sg_forward_partial_path_stitcher {
     bool is_complete;
 };
 
+// The null value for all of our handles.
+#define SG_NULL_HANDLE 0
+
+#if !defined(SG_LARGE_HANDLES)
+// The handle of an empty list.
+#define SG_LIST_EMPTY_HANDLE 4294967295
+#endif
+
+#if defined(SG_LARGE_HANDLES)
+// The handle of an empty list.
+#define SG_LIST_EMPTY_HANDLE 18446744073709551615ull
+#endif
+
 // The handle of the singleton root node.
 #define SG_ROOT_NODE_HANDLE 1
 
@@ -761,13 +843,13 @@ struct sg_partial_paths sg_partial_path_database_partial_paths(const struct sg_p
 // We copy the partial path content into the partial path database.  The array you pass in does
 // not need to outlive the call to this function.
 //
-// You should take care not to add a partial path to the database multiple times.  This won't
-// cause an _error_, in that nothing will break, but it will probably cause you to get duplicate
-// paths from the path-stitching algorithm.
+// If the database already contains a partial path with the same content, including a path that
+// appears earlier in `paths`, it is not added again.  The database can therefore grow by fewer
+// than `count` partial paths.
 //
 // You must also provide an `out` array, which must also have room for `count` elements.  We will
-// fill this array in with the `sg_partial_path_edge_list` instances for each partial path edge
-// list that is created.
+// fill this array in with the handle of each partial path in the database.  For a duplicate, this
+// is the handle of the existing partial path, so the same handle can appear more than once.
 void sg_partial_path_database_add_partial_paths(const struct sg_stack_graph *graph,
                                                 struct sg_partial_path_arena *partials,
                                                 struct sg_partial_path_database *db,
@@ -845,6 +927,156 @@ void sg_forward_partial_path_stitcher_process_next_phase(const struct sg_stack_g
 // Frees a forward path stitcher.
 void sg_forward_partial_path_stitcher_free(struct sg_forward_partial_path_stitcher *stitcher);
 
+#if defined(SG_STORAGE)
+// Opens a file database for writing, creating it if it doesn't exist yet.  On success, the
+// writer is placed in the `writer_out` output parameter.  You must free the writer when you are
+// done with it by calling `sg_storage_writer_free`.
+//
+// The path must be a valid UTF-8 string of `path_len` bytes.
+enum sg_storage_result sg_storage_writer_open(const char *path,
+                                              size_t path_len,
+                                              struct sg_storage_writer **writer_out);
+#endif
+
+#if defined(SG_STORAGE)
+// Frees a storage writer, and closes its database.
+void sg_storage_writer_free(struct sg_storage_writer *writer);
+#endif
+
+#if defined(SG_STORAGE)
+// Stores the stack graph of a file, together with the partial paths that were found in it.  Any
+// data that was previously stored for the file is replaced.  `paths` is the array of partial
+// paths that you want to store; `count` is the number of them.  The partial paths must have been
+// created in `partials`.
+//
+// The tag must be a valid UTF-8 string of `tag_len` bytes.
+enum sg_storage_result sg_storage_writer_store_result_for_file(struct sg_storage_writer *writer,
+                                                               const struct sg_stack_graph *graph,
+                                                               struct sg_partial_path_arena *partials,
+                                                               sg_file_handle file,
+                                                               const char *tag,
+                                                               size_t tag_len,
+                                                               size_t count,
+                                                               const struct sg_partial_path *paths);
+#endif
+
+#if defined(SG_STORAGE)
+// Stores an error for a file that could not be indexed.  Any data that was previously stored for
+// the file is replaced.
+//
+// The file, tag, and error must be valid UTF-8 strings of the given lengths.
+enum sg_storage_result sg_storage_writer_store_error_for_file(struct sg_storage_writer *writer,
+                                                              const char *file,
+                                                              size_t file_len,
+                                                              const char *tag,
+                                                              size_t tag_len,
+                                                              const char *error,
+                                                              size_t error_len);
+#endif
+
+#if defined(SG_STORAGE)
+// Determines the status of a file in the database, placing the result into the `status_out`
+// output parameter.  If `tag` is not null, the stored tag must match, or the file is reported
+// missing.
+//
+// The file and tag must be valid UTF-8 strings of the given lengths.
+enum sg_storage_result sg_storage_writer_status_for_file(struct sg_storage_writer *writer,
+                                                         const char *file,
+                                                         size_t file_len,
+                                                         const char *tag,
+                                                         size_t tag_len,
+                                                         enum sg_file_status *status_out);
+#endif
+
+#if defined(SG_STORAGE)
+// Opens an existing file database for reading.  On success, the reader is placed in the
+// `reader_out` output parameter.  You must free the reader when you are done with it by calling
+// `sg_storage_reader_free`.
+//
+// The path must be a valid UTF-8 string of `path_len` bytes.
+enum sg_storage_result sg_storage_reader_open(const char *path,
+                                              size_t path_len,
+                                              struct sg_storage_reader **reader_out);
+#endif
+
+#if defined(SG_STORAGE)
+// Frees a storage reader, and all of the data that was loaded into it.
+void sg_storage_reader_free(struct sg_storage_reader *reader);
+#endif
+
+#if defined(SG_STORAGE)
+// Returns the stack graph that the reader loads file graphs into.  The resulting pointer is
+// owned by the reader, and is only valid until the reader is freed.
+const struct sg_stack_graph *sg_storage_reader_stack_graph(struct sg_storage_reader *reader);
+#endif
+
+#if defined(SG_STORAGE)
+// Returns the partial path arena that the reader loads partial paths into.  The resulting pointer
+// is owned by the reader, and is only valid until the reader is freed.
+struct sg_partial_path_arena *sg_storage_reader_partial_path_arena(struct sg_storage_reader *reader);
+#endif
+
+#if defined(SG_STORAGE)
+// Returns the partial path database that the reader loads partial paths into.  The resulting
+// pointer is owned by the reader, and is only valid until the reader is freed.
+struct sg_partial_path_database *sg_storage_reader_partial_path_database(struct sg_storage_reader *reader);
+#endif
+
+#if defined(SG_STORAGE)
+// Clears all partial paths that were loaded into the reader.  File handles and node handles
+// remain valid, but all partial path data is invalidated.
+void sg_storage_reader_clear_paths(struct sg_storage_reader *reader);
+#endif
+
+#if defined(SG_STORAGE)
+// Determines the status of a file in the database, placing the result into the `status_out`
+// output parameter.  If `tag` is not null, the stored tag must match, or the file is reported
+// missing.
+//
+// The file and tag must be valid UTF-8 strings of the given lengths.
+enum sg_storage_result sg_storage_reader_status_for_file(struct sg_storage_reader *reader,
+                                                         const char *file,
+                                                         size_t file_len,
+                                                         const char *tag,
+                                                         size_t tag_len,
+                                                         enum sg_file_status *status_out);
+#endif
+
+#if defined(SG_STORAGE)
+// Ensures the stack graph of a file is loaded into the reader's stack graph, placing the file's
+// handle into the `handle_out` output parameter.
+//
+// The file must be a valid UTF-8 string of `file_len` bytes.
+enum sg_storage_result sg_storage_reader_load_graph_for_file(struct sg_storage_reader *reader,
+                                                             const char *file,
+                                                             size_t file_len,
+                                                             sg_file_handle *handle_out);
+#endif
+
+#if defined(SG_STORAGE)
+// Ensures that all possible extensions of the given partial paths are loaded into the reader's
+// partial path database, together with the stack graphs of the files they belong to.  `paths` is
+// the array of partial paths; `count` is the number of them.  The partial paths must have been
+// created in the reader's partial path arena.
+//
+// You will typically call this function with the `previous_phase_partial_paths` of a
+// `sg_forward_partial_path_stitcher`, before calling
+// `sg_forward_partial_path_stitcher_process_next_phase` with the reader's partial path database.
+enum sg_storage_result sg_storage_reader_load_partial_path_extensions(struct sg_storage_reader *reader,
+                                                                      size_t count,
+                                                                      const struct sg_partial_path *paths,
+                                                                      const size_t *cancellation_flag);
+#endif
+
+#if defined(SG_STORAGE)
+// Ensures that all partial paths starting at the root node, whose symbol stack precondition
+// matches the given symbol stack, are loaded into the reader's partial path database.  The
+// symbol stack must have been created in the reader's partial path arena.
+enum sg_storage_result sg_storage_reader_load_partial_paths_for_symbol_stack(struct sg_storage_reader *reader,
+                                                                             struct sg_partial_symbol_stack symbol_stack,
+                                                                             const size_t *cancellation_flag);
+#endif
+
 #ifdef __cplusplus
 } // extern "C"
 #endif // __cplusplus
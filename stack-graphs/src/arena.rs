@@ -24,18 +24,22 @@
 //! [`StackGraph`][] will live as long as the stack graph itself does.  The entire region of memory
 //! for each arena will be freed in a single operation when the stack graph is dropped.
 //!
+//! Handles are 32 bits wide by default, which limits each arena to a little over four billion
+//! instances.  If you need larger graphs, enable the `large-handles` feature to use 64-bit handles
+//! instead.  This doubles the size of every handle, and therefore of most stack graph data types.
+//!
 //! [arena allocation]: https://en.wikipedia.org/wiki/Region-based_memory_management
 //! [`Arena`]: struct.Arena.html
 //! [`Handle`]: struct.Handle.html
 //! [`StackGraph`]: ../graph/struct.StackGraph.html
 
 use std::cell::Cell;
+use std::convert::TryFrom;
 use std::fmt::Debug;
 use std::hash::Hash;
 use std::hash::Hasher;
 use std::marker::PhantomData;
 use std::mem::MaybeUninit;
-use std::num::NonZeroU32;
 use std::ops::Index;
 use std::ops::IndexMut;
 
@@ -48,6 +52,20 @@ use crate::utils::equals_option;
 //-------------------------------------------------------------------------------------------------
 // Arenas and handles
 
+/// The integer type of the index stored in a [`Handle`][].  This is `u32` by default, and `u64`
+/// if the `large-handles` feature is enabled.
+#[cfg(not(feature = "large-handles"))]
+pub type HandleIndex = u32;
+/// The integer type of the index stored in a [`Handle`][].  This is `u32` by default, and `u64`
+/// if the `large-handles` feature is enabled.
+#[cfg(feature = "large-handles")]
+pub type HandleIndex = u64;
+
+#[cfg(not(feature = "large-handles"))]
+pub(crate) type NonZeroHandleIndex = std::num::NonZeroU32;
+#[cfg(feature = "large-handles")]
+pub(crate) type NonZeroHandleIndex = std::num::NonZeroU64;
+
 /// A handle to an instance of type `T` that was allocated from an [`Arena`][].
 ///
 /// #### Safety
@@ -58,23 +76,41 @@ use crate::utils::equals_option;
 /// arena.
 #[repr(transparent)]
 pub struct Handle<T> {
-    index: NonZeroU32,
+    index: NonZeroHandleIndex,
     _phantom: PhantomData<T>,
 }
 
 impl<T> Handle<T> {
-    pub(crate) fn new(index: NonZeroU32) -> Handle<T> {
+    pub(crate) fn new(index: NonZeroHandleIndex) -> Handle<T> {
         Handle {
             index,
             _phantom: PhantomData,
         }
     }
 
+    #[inline(always)]
+    pub fn as_index(self) -> HandleIndex {
+        self.index.get()
+    }
+
+    /// Returns the index of this handle as a `u32`.
+    #[cfg(not(feature = "large-handles"))]
     #[inline(always)]
     pub fn as_u32(self) -> u32 {
         self.index.get()
     }
 
+    /// Returns the index of this handle as a `u32`.
+    ///
+    /// ### Panics
+    ///
+    /// Panics if the index does not fit in 32 bits.
+    #[cfg(feature = "large-handles")]
+    #[inline(always)]
+    pub fn as_u32(self) -> u32 {
+        u32::try_from(self.index.get()).expect("Handle index does not fit in 32 bits")
+    }
+
     #[inline(always)]
     pub fn as_usize(self) -> usize {
         self.index.get() as usize
@@ -82,7 +118,7 @@ impl<T> Handle<T> {
 }
 
 impl<T> Niche for Handle<T> {
-    type Output = u32;
+    type Output = HandleIndex;
 
     #[inline]
     fn none() -> Self::Output {
@@ -101,7 +137,7 @@ impl<T> Niche for Handle<T> {
 
     #[inline]
     fn from_some(value: Self::Output) -> Self {
-        Self::new(unsafe { NonZeroU32::new_unchecked(value) })
+        Self::new(unsafe { NonZeroHandleIndex::new_unchecked(value) })
     }
 }
 
@@ -183,20 +219,39 @@ impl<T> Arena<T> {
 
     /// Clear the arena, keeping underlying allocated capacity.  After this, all previous handles into
     /// the arena are invalid.
-    #[cfg_attr(not(feature = "storage"), allow(dead_code))]
     #[inline(always)]
-    pub(crate) fn clear(&mut self) {
-        self.items.clear();
+    pub fn clear(&mut self) {
+        unsafe {
+            let items = std::mem::transmute::<&mut [MaybeUninit<T>], &mut [T]>(&mut self.items[1..])
+                as *mut [T];
+            items.drop_in_place();
+        }
+        // The first item is never initialized, so that handles are non-zero.
+        self.items.truncate(1);
+    }
+
+    /// Shrinks the capacity of the arena as much as possible, releasing memory that was allocated
+    /// for instances that have since been cleared.
+    pub fn shrink_to_fit(&mut self) {
+        self.items.shrink_to_fit();
     }
 
     /// Adds a new instance to this arena, returning a stable handle to it.
     ///
     /// Note that we do not deduplicate instances of `T` in any way.  If you add two instances that
     /// have the same content, you will get distinct handles for each one.
+    ///
+    /// ### Panics
+    ///
+    /// Panics if the arena already holds as many instances as a [`HandleIndex`][] can address.  If
+    /// you run into this limit, enable the `large-handles` feature.
     pub fn add(&mut self, item: T) -> Handle<T> {
-        let index = self.items.len() as u32;
+        let index = HandleIndex::try_from(self.items.len())
+            .ok()
+            .filter(|index| *index < HandleIndex::MAX)
+            .expect("Arena is full; enable the large-handles feature for larger graphs");
         self.items.push(MaybeUninit::new(item));
-        Handle::new(unsafe { NonZeroU32::new_unchecked(index) })
+        Handle::new(unsafe { NonZeroHandleIndex::new_unchecked(index) })
     }
 
     /// Dereferences a handle to an instance owned by this arena, returning a reference to it.
@@ -213,9 +268,9 @@ impl<T> Arena<T> {
     /// Returns an iterator of all of the handles in this arena.  (Note that this iterator does not
     /// retain a reference to the arena!)
     pub fn iter_handles(&self) -> impl Iterator<Item = Handle<T>> {
-        (1..self.items.len())
-            .into_iter()
-            .map(|index| Handle::new(unsafe { NonZeroU32::new_unchecked(index as u32) }))
+        (1..self.items.len()).into_iter().map(|index| {
+            Handle::new(unsafe { NonZeroHandleIndex::new_unchecked(index as HandleIndex) })
+        })
     }
 
     /// Returns a pointer to this arena's storage.
@@ -228,6 +283,12 @@ impl<T> Arena<T> {
     pub fn len(&self) -> usize {
         self.items.len()
     }
+
+    /// Returns the number of bytes allocated for this arena's storage.  This does not include any
+    /// heap memory owned by the instances themselves.
+    pub fn memory_usage(&self) -> usize {
+        self.items.capacity() * std::mem::size_of::<T>()
+    }
 }
 
 //-------------------------------------------------------------------------------------------------
@@ -293,7 +354,13 @@ impl<H, T> SupplementalArena<H, T> {
     #[cfg_attr(not(feature = "storage"), allow(dead_code))]
     #[inline(always)]
     pub(crate) fn clear(&mut self) {
-        self.items.clear();
+        unsafe {
+            let items = std::mem::transmute::<&mut [MaybeUninit<T>], &mut [T]>(&mut self.items[1..])
+                as *mut [T];
+            items.drop_in_place();
+        }
+        // The first item is never initialized, so that handles are non-zero.
+        self.items.truncate(1);
     }
 
     /// Creates a new, empty supplemental arena, preallocating enough space to store supplemental
@@ -332,13 +399,19 @@ impl<H, T> SupplementalArena<H, T> {
         self.items.len()
     }
 
+    /// Returns the number of bytes allocated for this arena's storage.  This does not include any
+    /// heap memory owned by the instances themselves.
+    pub fn memory_usage(&self) -> usize {
+        self.items.capacity() * std::mem::size_of::<T>()
+    }
+
     /// Iterate over the items in this arena.
-    pub(crate) fn iter(&self) -> impl Iterator<Item = (Handle<T>, &T)> {
-        self.items
-            .iter()
-            .enumerate()
-            .skip(1)
-            .map(|(i, x)| (Handle::from_some(i as u32), unsafe { &*(x.as_ptr()) }))
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (Handle<H>, &T)> {
+        self.items.iter().enumerate().skip(1).map(|(i, x)| {
+            (Handle::from_some(i as HandleIndex), unsafe {
+                &*(x.as_ptr())
+            })
+        })
     }
 }
 
@@ -427,9 +500,14 @@ impl<T> HandleSet<T> {
 
     /// Returns an iterator of all of the handles in this set.
     pub fn iter(&self) -> impl Iterator<Item = Handle<T>> + '_ {
-        self.elements
-            .iter_ones()
-            .map(|index| Handle::new(unsafe { NonZeroU32::new_unchecked(index as u32) }))
+        self.elements.iter_ones().map(|index| {
+            Handle::new(unsafe { NonZeroHandleIndex::new_unchecked(index as HandleIndex) })
+        })
+    }
+
+    /// Returns the number of bytes allocated for this set's storage.
+    pub fn memory_usage(&self) -> usize {
+        self.elements.capacity() / 8
     }
 
     /// Returns a pointer to this set's storage.
@@ -479,7 +557,7 @@ pub struct ListCell<T> {
     tail: Handle<ListCell<T>>,
 }
 
-const EMPTY_LIST_HANDLE: NonZeroU32 = unsafe { NonZeroU32::new_unchecked(u32::MAX) };
+const EMPTY_LIST_HANDLE: NonZeroHandleIndex = NonZeroHandleIndex::new(HandleIndex::MAX).unwrap();
 
 // An arena that's used to manage `List<T>` instances.
 //
@@ -890,6 +968,10 @@ pub struct Deque<T> {
     #[niche]
     list: ReversibleList<T>,
     direction: DequeDirection,
+    // With 64-bit handles, the direction is followed by four bytes of padding.  We make them
+    // explicit so that they are always initialized when the C API copies a deque.
+    #[cfg(feature = "large-handles")]
+    padding: u32,
 }
 
 #[repr(C)]
@@ -933,6 +1015,8 @@ impl<T> Deque<T> {
             // the back, we'll "reverse" the current list before proceeding, but reversing the
             // empty list is a no-op.
             direction: DequeDirection::Forwards,
+            #[cfg(feature = "large-handles")]
+            padding: 0,
         }
     }
 
@@ -1134,6 +1218,8 @@ impl<T> Clone for Deque<T> {
         Deque {
             list: self.list,
             direction: self.direction,
+            #[cfg(feature = "large-handles")]
+            padding: 0,
         }
     }
 }
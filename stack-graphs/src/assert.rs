@@ -29,6 +29,9 @@ pub enum Assertion {
     Defined {
         source: AssertionSource,
         targets: Vec<AssertionTarget>,
+        /// Files that paths to the targets must pass through.  Paths that reach a target without
+        /// passing through all of these files are unexpected.
+        via: Vec<Handle<File>>,
     },
     Defines {
         source: AssertionSource,
@@ -122,6 +125,7 @@ pub enum AssertionError {
         references: Vec<Handle<Node>>,
         missing_targets: Vec<AssertionTarget>,
         unexpected_paths: Vec<PartialPath>,
+        via: Vec<Handle<File>>,
     },
     IncorrectDefinitions {
         source: AssertionSource,
@@ -152,21 +156,23 @@ impl Assertion {
         cancellation_flag: &dyn CancellationFlag,
     ) -> Result<(), AssertionError> {
         match self {
-            Self::Defined { source, targets } => {
-                self.run_defined(graph, partials, db, source, targets, cancellation_flag)
-            }
+            Self::Defined {
+                source,
+                targets,
+                via,
+            } => Self::run_defined(graph, partials, db, source, targets, via, cancellation_flag),
             Self::Defines { source, symbols } => self.run_defines(graph, source, symbols),
             Self::Refers { source, symbols } => self.run_refers(graph, source, symbols),
         }
     }
 
     fn run_defined(
-        &self,
         graph: &StackGraph,
         partials: &mut PartialPaths,
         db: &mut Database,
         source: &AssertionSource,
         expected_targets: &Vec<AssertionTarget>,
+        via: &[Handle<File>],
         cancellation_flag: &dyn CancellationFlag,
     ) -> Result<(), AssertionError> {
         let references = source.iter_references(graph).collect::<Vec<_>>();
@@ -197,24 +203,31 @@ impl Assertion {
             }
         }
 
+        let is_via = actual_paths
+            .iter()
+            .map(|p| via.iter().all(|file| p.visits_file(graph, partials, *file)))
+            .collect::<Vec<_>>();
         let missing_targets = expected_targets
             .iter()
             .filter(|t| {
                 !actual_paths
                     .iter()
-                    .any(|p| t.matches_node(p.end_node, graph))
+                    .zip(&is_via)
+                    .any(|(p, is_via)| *is_via && t.matches_node(p.end_node, graph))
             })
             .cloned()
             .unique()
             .collect::<Vec<_>>();
         let unexpected_paths = actual_paths
             .iter()
-            .filter(|p| {
-                !expected_targets
-                    .iter()
-                    .any(|t| t.matches_node(p.end_node, graph))
+            .zip(&is_via)
+            .filter(|(p, is_via)| {
+                !(**is_via
+                    && expected_targets
+                        .iter()
+                        .any(|t| t.matches_node(p.end_node, graph)))
             })
-            .cloned()
+            .map(|(p, _)| p.clone())
             .collect::<Vec<_>>();
         if !missing_targets.is_empty() || !unexpected_paths.is_empty() {
             return Err(AssertionError::IncorrectlyDefined {
@@ -222,6 +235,7 @@ impl Assertion {
                 references,
                 missing_targets,
                 unexpected_paths,
+                via: via.to_vec(),
             });
         }
 
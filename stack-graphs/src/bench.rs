@@ -0,0 +1,240 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Generates synthetic workloads for measuring the performance of path stitching.
+//!
+//! A [`GraphShape`][] describes a stack graph of files that each define a module with a tree of
+//! nested definitions, and that re-export the definitions of the next file, like a wildcard
+//! import.  The files can form a chain of imports or an import cycle.  Every file contains
+//! references to the nested definitions, which resolve to the definitions in the file itself and
+//! in all files that it imports directly or indirectly.
+//!
+//! The generated [`Workload`][] measures how long it takes to compute the partial paths of its
+//! files, and to stitch those partial paths into complete paths for its references.  Because the
+//! graphs only depend on their shape, the same workload can be used to compare the performance of
+//! different versions of this crate.
+
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::arena::Handle;
+use crate::graph::Node;
+use crate::graph::StackGraph;
+use crate::partial::PartialPaths;
+use crate::stitching::Database;
+use crate::stitching::DatabaseCandidates;
+use crate::stitching::ForwardPartialPathStitcher;
+use crate::stitching::StitchingStats;
+use crate::CancellationError;
+use crate::CancellationFlag;
+
+/// The shape of a synthetic stack graph.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct GraphShape {
+    /// The number of files.
+    pub files: usize,
+    /// The number of definitions that are nested in a module or definition.
+    pub fan_out: usize,
+    /// The nesting depth of definitions in a module.  Each file has `fan_out ^ depth` innermost
+    /// definitions.
+    pub depth: usize,
+    /// The number of references in each file.  Each reference refers to a different innermost
+    /// definition, so there are at most `fan_out ^ depth` references.
+    pub references: usize,
+    /// Whether the last file imports the first one, so that imports form a cycle.
+    pub import_cycles: bool,
+}
+
+impl Default for GraphShape {
+    fn default() -> Self {
+        Self {
+            files: 10,
+            fan_out: 4,
+            depth: 2,
+            references: 8,
+            import_cycles: false,
+        }
+    }
+}
+
+impl GraphShape {
+    /// Generates a stack graph with this shape.
+    pub fn generate(&self) -> Workload {
+        let mut graph = StackGraph::new();
+        let root = StackGraph::root_node();
+        let leaves = self.leaves();
+        let mut references = Vec::new();
+        for index in 0..self.files {
+            let file = graph.add_file(&format!("file{}.sg", index)).unwrap();
+
+            // the module of the file, which contains its definitions and those of the next file
+            let module_symbol = graph.add_symbol(&format!("m{}", index));
+            let id = graph.new_node_id(file);
+            let module = graph.add_pop_symbol_node(id, module_symbol, true).unwrap();
+            let id = graph.new_node_id(file);
+            let module_scope = graph.add_scope_node(id, true).unwrap();
+            graph.add_edge(root, module, 0);
+            graph.add_edge(module, module_scope, 0);
+            let mut parents = vec![module_scope];
+            for _ in 0..self.depth {
+                let mut children = Vec::new();
+                for parent in parents {
+                    for member in 0..self.fan_out {
+                        let symbol = graph.add_symbol(&format!("n{}", member));
+                        let id = graph.new_node_id(file);
+                        let definition = graph.add_pop_symbol_node(id, symbol, true).unwrap();
+                        graph.add_edge(parent, definition, 0);
+                        children.push(definition);
+                    }
+                }
+                parents = children;
+            }
+            let next = index + 1;
+            if next < self.files || self.import_cycles {
+                let symbol = graph.add_symbol(&format!("m{}", next % self.files));
+                let id = graph.new_node_id(file);
+                let import = graph.add_push_symbol_node(id, symbol, false).unwrap();
+                graph.add_edge(module_scope, import, 0);
+                graph.add_edge(import, root, 0);
+            }
+
+            // references to the innermost definitions, resolved in the module scope
+            for leaf in leaves.iter().take(self.references) {
+                let mut reference = None;
+                let mut previous = None;
+                for member in leaf.iter().rev() {
+                    let symbol = graph.add_symbol(&format!("n{}", member));
+                    let id = graph.new_node_id(file);
+                    let node = graph
+                        .add_push_symbol_node(id, symbol, reference.is_none())
+                        .unwrap();
+                    match previous {
+                        Some(previous) => graph.add_edge(previous, node, 0),
+                        None => reference = Some(node),
+                    }
+                    previous = Some(node);
+                }
+                if let (Some(reference), Some(previous)) = (reference, previous) {
+                    graph.add_edge(previous, module_scope, 0);
+                    references.push(reference);
+                }
+            }
+        }
+        Workload { graph, references }
+    }
+
+    /// Returns the members that lead to each innermost definition, outermost first.
+    fn leaves(&self) -> Vec<Vec<usize>> {
+        let mut leaves = vec![Vec::new()];
+        for _ in 0..self.depth {
+            leaves = leaves
+                .into_iter()
+                .flat_map(|leaf| {
+                    (0..self.fan_out).map(move |member| {
+                        let mut leaf = leaf.clone();
+                        leaf.push(member);
+                        leaf
+                    })
+                })
+                .collect();
+        }
+        leaves
+    }
+}
+
+/// A synthetic stack graph, together with the references that can be resolved in it.
+pub struct Workload {
+    pub graph: StackGraph,
+    pub references: Vec<Handle<Node>>,
+}
+
+impl Workload {
+    /// Computes the minimal set of partial paths of every file, and returns a database that
+    /// contains them.
+    pub fn partial_paths(
+        &self,
+        partials: &mut PartialPaths,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<(Database, Measurement), CancellationError> {
+        let start = Instant::now();
+        let mut db = Database::new();
+        let mut paths = 0;
+        for file in self.graph.iter_files() {
+            ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+                &self.graph,
+                partials,
+                file,
+                cancellation_flag,
+                |graph, partials, path| {
+                    db.add_partial_path(graph, partials, path.clone());
+                    paths += 1;
+                },
+            )?;
+        }
+        let measurement = Measurement {
+            paths,
+            elapsed: start.elapsed(),
+            stats: StitchingStats::default(),
+        };
+        Ok((db, measurement))
+    }
+
+    /// Stitches the partial paths in the database into complete paths for all references.
+    pub fn stitch(
+        &self,
+        partials: &mut PartialPaths,
+        db: &mut Database,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Measurement, CancellationError> {
+        let start = Instant::now();
+        let mut stitcher = ForwardPartialPathStitcher::from_starting_nodes(
+            &self.graph,
+            partials,
+            self.references.iter().copied(),
+        );
+        let mut candidates = DatabaseCandidates::new(&self.graph, partials, db);
+        let mut paths = 0;
+        while !stitcher.is_complete() {
+            paths += stitcher
+                .next_batch(&mut candidates, cancellation_flag)?
+                .len();
+        }
+        Ok(Measurement {
+            paths,
+            elapsed: start.elapsed(),
+            stats: stitcher.stats(),
+        })
+    }
+}
+
+/// The number of paths that a measured operation found, and the time it took.
+#[derive(Clone, Copy, Debug)]
+pub struct Measurement {
+    pub paths: usize,
+    pub elapsed: Duration,
+    /// The number of paths that were pruned during path stitching.
+    pub stats: StitchingStats,
+}
+
+impl Measurement {
+    /// Returns the number of paths that were found per second.
+    pub fn paths_per_second(&self) -> f64 {
+        self.paths as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+impl std::fmt::Display for Measurement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} paths in {:.3} ms ({:.0} paths/s)",
+            self.paths,
+            self.elapsed.as_secs_f64() * 1000.0,
+            self.paths_per_second()
+        )
+    }
+}
@@ -0,0 +1,256 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Builds stack graphs by hand, without having to allocate node IDs and connect edges yourself.
+//!
+//! A [`GraphBuilder`][] adds nodes to the files of a stack graph along a _cursor_.  Every node
+//! that is added is connected to the current node with an edge, and becomes the new current node.
+//! This makes it easy to describe paths through a file, one node after the other:
+//!
+//! ```
+//! use stack_graphs::builder::GraphBuilder;
+//! use stack_graphs::graph::StackGraph;
+//!
+//! let mut graph = StackGraph::new();
+//! let mut builder = GraphBuilder::new(&mut graph);
+//! // a.py: def foo
+//! builder.file("a.py").from_root().pop("a").pop(".").defines("foo");
+//! // b.py: from a import foo; foo
+//! builder
+//!     .file("b.py")
+//!     .references("foo")
+//!     .scope("b.scope")
+//!     .push(".")
+//!     .push("a")
+//!     .root();
+//! ```
+//!
+//! Nodes can be given names, which are shared by all files of a builder.  Scopes are always named,
+//! and other nodes can be named with [`FileBuilder::label`][].  The cursor can be moved to a named
+//! node with [`FileBuilder::from`][], and connected to one with [`FileBuilder::to`][], which makes
+//! it possible to build graphs that are not simple paths.
+
+use std::collections::HashMap;
+
+use crate::arena::Handle;
+use crate::graph::File;
+use crate::graph::Node;
+use crate::graph::StackGraph;
+
+/// Adds files and nodes to a stack graph.  See the [module documentation][crate::builder] for an
+/// example.
+pub struct GraphBuilder<'a> {
+    graph: &'a mut StackGraph,
+    names: HashMap<String, Handle<Node>>,
+}
+
+impl<'a> GraphBuilder<'a> {
+    /// Creates a builder that adds to the given stack graph.
+    pub fn new(graph: &'a mut StackGraph) -> Self {
+        Self {
+            graph,
+            names: HashMap::new(),
+        }
+    }
+
+    /// Returns a builder for the file with the given name, which is created if it does not exist
+    /// yet.  The cursor of the returned builder is not at any node.
+    pub fn file(&mut self, name: &str) -> FileBuilder<'_> {
+        let file = self.graph.get_or_create_file(name);
+        FileBuilder {
+            graph: self.graph,
+            names: &mut self.names,
+            file,
+            current: None,
+            precedence: 0,
+        }
+    }
+
+    /// Returns the node with the given name, if it exists.
+    pub fn node(&self, name: &str) -> Option<Handle<Node>> {
+        self.names.get(name).copied()
+    }
+
+    /// Returns the stack graph this builder adds to.
+    pub fn graph(&self) -> &StackGraph {
+        self.graph
+    }
+}
+
+/// Adds nodes to a single file of a stack graph, connecting each new node to the current one.
+pub struct FileBuilder<'a> {
+    graph: &'a mut StackGraph,
+    names: &'a mut HashMap<String, Handle<Node>>,
+    file: Handle<File>,
+    current: Option<Handle<Node>>,
+    precedence: i32,
+}
+
+impl FileBuilder<'_> {
+    /// Returns the file this builder adds nodes to.
+    pub fn file(&self) -> Handle<File> {
+        self.file
+    }
+
+    /// Returns the current node, if there is one.
+    pub fn current(&self) -> Option<Handle<Node>> {
+        self.current
+    }
+
+    /// Moves the cursor to the node with the given name, without adding an edge.
+    ///
+    /// Panics if there is no node with the given name.
+    pub fn from(&mut self, name: &str) -> &mut Self {
+        self.current = Some(self.named_node(name));
+        self
+    }
+
+    /// Moves the cursor to the root node, without adding an edge.
+    pub fn from_root(&mut self) -> &mut Self {
+        self.current = Some(StackGraph::root_node());
+        self
+    }
+
+    /// Adds an edge from the current node to the node with the given name.
+    ///
+    /// Panics if there is no node with the given name.
+    pub fn to(&mut self, name: &str) -> &mut Self {
+        let node = self.named_node(name);
+        self.connect(node)
+    }
+
+    /// Adds an edge from the current node to the root node.
+    pub fn root(&mut self) -> &mut Self {
+        self.connect(StackGraph::root_node())
+    }
+
+    /// Adds an edge from the current node to the jump-to-scope node.
+    pub fn jump_to(&mut self) -> &mut Self {
+        self.connect(StackGraph::jump_to_node())
+    }
+
+    /// Gives the current node a name, so that it can be used with [`from`][Self::from] and
+    /// [`to`][Self::to].
+    ///
+    /// Panics if there is no current node.
+    pub fn label(&mut self, name: &str) -> &mut Self {
+        let node = self.current.expect("No current node to label");
+        self.names.insert(name.to_string(), node);
+        self
+    }
+
+    /// Sets the precedence of the next edge that is added.
+    pub fn precedence(&mut self, precedence: i32) -> &mut Self {
+        self.precedence = precedence;
+        self
+    }
+
+    /// Adds an edge from the current node to the internal scope with the given name.  The scope
+    /// is created if no node with that name exists yet.
+    pub fn scope(&mut self, name: &str) -> &mut Self {
+        let node = self.named_scope(name, false);
+        self.connect(node)
+    }
+
+    /// Adds an edge from the current node to the exported scope with the given name.  The scope
+    /// is created if no node with that name exists yet.
+    pub fn exported_scope(&mut self, name: &str) -> &mut Self {
+        let node = self.named_scope(name, true);
+        self.connect(node)
+    }
+
+    /// Adds a definition of the given symbol.
+    pub fn defines(&mut self, symbol: &str) -> &mut Self {
+        let symbol = self.graph.add_symbol(symbol);
+        let id = self.graph.new_node_id(self.file);
+        let node = self.graph.add_pop_symbol_node(id, symbol, true).unwrap();
+        self.connect(node)
+    }
+
+    /// Adds a reference to the given symbol.
+    pub fn references(&mut self, symbol: &str) -> &mut Self {
+        let symbol = self.graph.add_symbol(symbol);
+        let id = self.graph.new_node_id(self.file);
+        let node = self.graph.add_push_symbol_node(id, symbol, true).unwrap();
+        self.connect(node)
+    }
+
+    /// Adds a node that pops the given symbol.
+    pub fn pop(&mut self, symbol: &str) -> &mut Self {
+        let symbol = self.graph.add_symbol(symbol);
+        let id = self.graph.new_node_id(self.file);
+        let node = self.graph.add_pop_symbol_node(id, symbol, false).unwrap();
+        self.connect(node)
+    }
+
+    /// Adds a node that pushes the given symbol.
+    pub fn push(&mut self, symbol: &str) -> &mut Self {
+        let symbol = self.graph.add_symbol(symbol);
+        let id = self.graph.new_node_id(self.file);
+        let node = self.graph.add_push_symbol_node(id, symbol, false).unwrap();
+        self.connect(node)
+    }
+
+    /// Adds a node that pops the given scoped symbol.
+    pub fn pop_scoped(&mut self, symbol: &str) -> &mut Self {
+        let symbol = self.graph.add_symbol(symbol);
+        let id = self.graph.new_node_id(self.file);
+        let node = self
+            .graph
+            .add_pop_scoped_symbol_node(id, symbol, false)
+            .unwrap();
+        self.connect(node)
+    }
+
+    /// Adds a node that pushes the given symbol with the exported scope with the given name
+    /// attached.  The scope is created if no node with that name exists yet, but it is not
+    /// connected to the current node.
+    pub fn push_scoped(&mut self, symbol: &str, scope: &str) -> &mut Self {
+        let scope = self.named_scope(scope, true);
+        let scope = self.graph[scope].id();
+        let symbol = self.graph.add_symbol(symbol);
+        let id = self.graph.new_node_id(self.file);
+        let node = self
+            .graph
+            .add_push_scoped_symbol_node(id, symbol, scope, false)
+            .unwrap();
+        self.connect(node)
+    }
+
+    /// Adds a node that drops all scopes from the scope stack.
+    pub fn drop_scopes(&mut self) -> &mut Self {
+        let id = self.graph.new_node_id(self.file);
+        let node = self.graph.add_drop_scopes_node(id).unwrap();
+        self.connect(node)
+    }
+
+    fn connect(&mut self, node: Handle<Node>) -> &mut Self {
+        if let Some(current) = self.current {
+            self.graph.add_edge(current, node, self.precedence);
+        }
+        self.precedence = 0;
+        self.current = Some(node);
+        self
+    }
+
+    fn named_node(&self, name: &str) -> Handle<Node> {
+        match self.names.get(name) {
+            Some(node) => *node,
+            None => panic!("Unknown node {}", name),
+        }
+    }
+
+    fn named_scope(&mut self, name: &str, is_exported: bool) -> Handle<Node> {
+        if let Some(node) = self.names.get(name) {
+            return *node;
+        }
+        let id = self.graph.new_node_id(self.file);
+        let node = self.graph.add_scope_node(id, is_exported).unwrap();
+        self.names.insert(name.to_string(), node);
+        node
+    }
+}
@@ -10,10 +10,9 @@
 #![allow(non_camel_case_types)]
 
 use std::convert::TryInto;
+use std::os::raw::c_char;
 use std::sync::atomic::AtomicUsize;
 
-use libc::c_char;
-
 use crate::arena::Handle;
 use crate::graph::File;
 use crate::graph::InternedString;
@@ -32,10 +31,22 @@ use crate::stitching::Database;
 use crate::stitching::DatabaseCandidates;
 use crate::stitching::ForwardPartialPathStitcher;
 use crate::stitching::GraphEdgeCandidates;
+#[cfg(feature = "storage")]
+use crate::storage::FileStatus;
+#[cfg(feature = "storage")]
+use crate::storage::SQLiteReader;
+#[cfg(feature = "storage")]
+use crate::storage::SQLiteWriter;
+#[cfg(feature = "storage")]
+use crate::storage::StorageError;
 use crate::CancellationError;
 use crate::CancellationFlag;
 
 /// Contains all of the nodes and edges that make up a stack graph.
+// The layout must match StackGraph, so that we can hand out pointers to graphs that are owned by
+// other types, like sg_storage_reader.  The repr is hidden from cbindgen, which would otherwise
+// turn this into a typedef of an opaque StackGraph struct.
+#[cfg_attr(all(), repr(transparent))]
 pub struct sg_stack_graph {
     pub inner: StackGraph,
 }
@@ -55,6 +66,8 @@ pub extern "C" fn sg_stack_graph_free(graph: *mut sg_stack_graph) {
 }
 
 /// Manages the state of a collection of partial paths to be used in the path-stitching algorithm.
+// The layout must match the wrapped type.  See sg_stack_graph for details.
+#[cfg_attr(all(), repr(transparent))]
 pub struct sg_partial_path_arena {
     pub inner: PartialPaths,
 }
@@ -82,6 +95,8 @@ pub extern "C" fn sg_partial_path_arena_free(partials: *mut sg_partial_path_aren
 /// We've written the path-stitching algorithm so that you have a chance to only load in the
 /// partial paths that are actually needed, placing them into a sg_partial_path_database instance
 /// as they're needed.
+// The layout must match the wrapped type.  See sg_stack_graph for details.
+#[cfg_attr(all(), repr(transparent))]
 pub struct sg_partial_path_database {
     pub inner: Database,
 }
@@ -100,11 +115,26 @@ pub extern "C" fn sg_partial_path_database_free(db: *mut sg_partial_path_databas
     drop(unsafe { Box::from_raw(db) })
 }
 
+/// The integer type of all of our handles.  This is `uint64_t` if the library was built with the
+/// `large-handles` feature, in which case you must define `SG_LARGE_HANDLES` before including this
+/// header, and `uint32_t` otherwise.
+#[cfg(not(feature = "large-handles"))]
+pub type sg_handle_index = u32;
+/// The integer type of all of our handles.  This is `uint64_t` if the library was built with the
+/// `large-handles` feature, in which case you must define `SG_LARGE_HANDLES` before including this
+/// header, and `uint32_t` otherwise.
+#[cfg(feature = "large-handles")]
+pub type sg_handle_index = u64;
+
 /// The null value for all of our handles.
-pub const SG_NULL_HANDLE: u32 = 0;
+pub const SG_NULL_HANDLE: sg_handle_index = 0;
 
 /// The handle of an empty list.
-pub const SG_LIST_EMPTY_HANDLE: u32 = 0xffffffff;
+#[cfg(not(feature = "large-handles"))]
+pub const SG_LIST_EMPTY_HANDLE: sg_handle_index = 0xffffffff;
+/// The handle of an empty list.
+#[cfg(feature = "large-handles")]
+pub const SG_LIST_EMPTY_HANDLE: sg_handle_index = 0xffffffffffffffff;
 
 /// Describes in which direction the content of a deque is stored in memory.
 #[repr(C)]
@@ -162,7 +192,7 @@ pub struct sg_symbol {
 /// We deduplicate symbols in a stack graph — that is, we ensure that there are never multiple
 /// `struct sg_symbol` instances with the same content.  That means that you can compare symbol
 /// handles using simple equality, without having to dereference them.
-pub type sg_symbol_handle = u32;
+pub type sg_symbol_handle = sg_handle_index;
 
 /// An array of all of the symbols in a stack graph.  Symbol handles are indices into this array.
 /// There will never be a valid symbol at index 0; a handle with the value 0 represents a missing
@@ -237,7 +267,7 @@ pub struct sg_string {
 /// We deduplicate strings in a stack graph — that is, we ensure that there are never multiple
 /// `struct sg_string` instances with the same content.  That means that you can compare string
 /// handles using simple equality, without having to dereference them.
-pub type sg_string_handle = u32;
+pub type sg_string_handle = sg_handle_index;
 
 /// An array of all of the interned strings in a stack graph.  String handles are indices into this
 /// array. There will never be a valid string at index 0; a handle with the value 0 represents a
@@ -318,7 +348,7 @@ pub struct sg_file {
 /// We deduplicate files in a stack graph — that is, we ensure that there are never multiple
 /// `struct sg_file` instances with the same filename.  That means that you can compare file
 /// handles using simple equality, without having to dereference them.
-pub type sg_file_handle = u32;
+pub type sg_file_handle = sg_handle_index;
 
 impl Into<Handle<File>> for sg_file_handle {
     fn into(self) -> Handle<File> {
@@ -471,7 +501,7 @@ pub enum sg_node_kind {
 }
 
 /// A handle to a node in a stack graph.  A zero handle represents a missing node.
-pub type sg_node_handle = u32;
+pub type sg_node_handle = sg_handle_index;
 
 impl Into<Handle<Node>> for sg_node_handle {
     fn into(self) -> Handle<Node> {
@@ -540,7 +570,7 @@ pub extern "C" fn sg_stack_graph_get_or_create_nodes(
 }
 
 fn validate_node_id(graph: &StackGraph, node_id: sg_node_id) -> Option<()> {
-    if node_id.file == 0 || node_id.file >= (graph.files.len() as u32) {
+    if node_id.file == 0 || node_id.file >= (graph.files.len() as sg_file_handle) {
         return None;
     }
     Some(())
@@ -610,8 +640,9 @@ pub extern "C" fn sg_stack_graph_add_edges(
     let graph = unsafe { &mut (*graph).inner };
     let edges = unsafe { std::slice::from_raw_parts(edges, count) };
     for i in 0..count {
-        let source = unsafe { std::mem::transmute(edges[i].source) };
-        let sink = unsafe { std::mem::transmute(edges[i].sink) };
+        let source =
+            unsafe { std::mem::transmute::<sg_node_handle, Handle<Node>>(edges[i].source) };
+        let sink = unsafe { std::mem::transmute::<sg_node_handle, Handle<Node>>(edges[i].sink) };
         graph.add_edge(source, sink, edges[i].precedence);
     }
 }
@@ -734,7 +765,7 @@ pub extern "C" fn sg_stack_graph_add_source_infos(
     let graph = unsafe { &mut (*graph).inner };
     let infos = unsafe { std::slice::from_raw_parts(infos, count) };
     for i in 0..count {
-        let node = unsafe { std::mem::transmute(infos[i].node) };
+        let node = unsafe { std::mem::transmute::<sg_node_handle, Handle<Node>>(infos[i].node) };
         let info = graph.source_info_mut(node);
         *info = unsafe { std::mem::transmute(infos[i].source_info) };
     }
@@ -775,6 +806,9 @@ pub struct sg_partial_symbol_stack {
     /// list is empty, or 0 if the list is null.
     pub cells: sg_partial_symbol_stack_cell_handle,
     pub direction: sg_deque_direction,
+    /// Always zero.  Only present with 64-bit handles, to match the layout of the Rust type.
+    #[cfg(feature = "large-handles")]
+    pub padding: u32,
     pub length: u32,
     /// The symbol stack variable representing the unknown content of a partial symbol stack, or 0
     /// if the variable is missing.  (If so, this partial symbol stack can only match a symbol
@@ -789,9 +823,15 @@ impl From<PartialSymbolStack> for sg_partial_symbol_stack {
     }
 }
 
+impl From<sg_partial_symbol_stack> for PartialSymbolStack {
+    fn from(stack: sg_partial_symbol_stack) -> PartialSymbolStack {
+        unsafe { std::mem::transmute(stack) }
+    }
+}
+
 /// A handle to an element of a partial symbol stack.  A zero handle represents a missing partial
-/// symbol stack.  A UINT32_MAX handle represents an empty partial symbol stack.
-pub type sg_partial_symbol_stack_cell_handle = u32;
+/// symbol stack.  An SG_LIST_EMPTY_HANDLE handle represents an empty partial symbol stack.
+pub type sg_partial_symbol_stack_cell_handle = sg_handle_index;
 
 /// An element of a partial symbol stack.
 #[repr(C)]
@@ -885,6 +925,9 @@ pub struct sg_partial_scope_stack {
     /// list is empty, or 0 if the list is null.
     pub cells: sg_partial_scope_stack_cell_handle,
     pub direction: sg_deque_direction,
+    /// Always zero.  Only present with 64-bit handles, to match the layout of the Rust type.
+    #[cfg(feature = "large-handles")]
+    pub padding: u32,
     pub length: u32,
     /// The scope stack variable representing the unknown content of a partial scope stack, or 0 if
     /// the variable is missing.  (If so, this partial scope stack can only match a scope stack
@@ -900,8 +943,8 @@ impl From<PartialScopeStack> for sg_partial_scope_stack {
 }
 
 /// A handle to an element of a partial scope stack.  A zero handle represents a missing partial
-/// scope stack.  A UINT32_MAX handle represents an empty partial scope stack.
-pub type sg_partial_scope_stack_cell_handle = u32;
+/// scope stack.  An SG_LIST_EMPTY_HANDLE handle represents an empty partial scope stack.
+pub type sg_partial_scope_stack_cell_handle = sg_handle_index;
 
 /// An element of a partial scope stack.
 #[repr(C)]
@@ -1006,6 +1049,9 @@ pub struct sg_partial_path_edge_list {
     /// empty, or 0 if the list is null.
     pub cells: sg_partial_path_edge_list_cell_handle,
     pub direction: sg_deque_direction,
+    /// Always zero.  Only present with 64-bit handles, to match the layout of the Rust type.
+    #[cfg(feature = "large-handles")]
+    pub padding: u32,
     pub length: u32,
 }
 
@@ -1016,8 +1062,8 @@ impl From<PartialPathEdgeList> for sg_partial_path_edge_list {
 }
 
 /// A handle to an element of a partial path edge list.  A zero handle represents a missing partial
-/// path edge list.  A UINT32_MAX handle represents an empty partial path edge list.
-pub type sg_partial_path_edge_list_cell_handle = u32;
+/// path edge list.  An SG_LIST_EMPTY_HANDLE handle represents an empty partial path edge list.
+pub type sg_partial_path_edge_list_cell_handle = sg_handle_index;
 
 /// An element of a partial path edge list.
 #[repr(C)]
@@ -1233,7 +1279,7 @@ pub extern "C" fn sg_partial_path_arena_find_all_complete_paths(
 
 /// A handle to a partial path in a partial path database.  A zero handle represents a missing
 /// partial path.
-pub type sg_partial_path_handle = u32;
+pub type sg_partial_path_handle = sg_handle_index;
 
 /// An array of all of the partial paths in a partial path database.  Partial path handles are
 /// indices into this array.  There will never be a valid partial path at index 0; a handle with
@@ -1264,13 +1310,13 @@ pub extern "C" fn sg_partial_path_database_partial_paths(
 /// We copy the partial path content into the partial path database.  The array you pass in does
 /// not need to outlive the call to this function.
 ///
-/// You should take care not to add a partial path to the database multiple times.  This won't
-/// cause an _error_, in that nothing will break, but it will probably cause you to get duplicate
-/// paths from the path-stitching algorithm.
+/// If the database already contains a partial path with the same content, including a path that
+/// appears earlier in `paths`, it is not added again.  The database can therefore grow by fewer
+/// than `count` partial paths.
 ///
 /// You must also provide an `out` array, which must also have room for `count` elements.  We will
-/// fill this array in with the `sg_partial_path_edge_list` instances for each partial path edge
-/// list that is created.
+/// fill this array in with the handle of each partial path in the database.  For a duplicate, this
+/// is the handle of the existing partial path, so the same handle can appear more than once.
 #[no_mangle]
 pub extern "C" fn sg_partial_path_database_add_partial_paths(
     graph: *const sg_stack_graph,
@@ -1342,7 +1388,7 @@ pub extern "C" fn sg_partial_path_database_mark_local_nodes(
     let db = unsafe { &mut (*db).inner };
     let nodes = unsafe { std::slice::from_raw_parts(nodes, count) };
     for node in nodes {
-        db.mark_local_node(node.clone().into());
+        db.mark_local_node((*node).into());
     }
 }
 
@@ -1549,6 +1595,418 @@ pub extern "C" fn sg_forward_partial_path_stitcher_free(
     drop(unsafe { Box::from_raw(stitcher as *mut InternalForwardPartialPathStitcher) });
 }
 
+//-------------------------------------------------------------------------------------------------
+// Storage
+
+/// Writer to store stack graphs and partial paths in a SQLite database.
+#[cfg(feature = "storage")]
+pub struct sg_storage_writer {
+    pub inner: SQLiteWriter,
+}
+
+/// Reader to load stack graphs and partial paths from a SQLite database.
+///
+/// The reader owns the stack graph, partial path arena, and partial path database that it loads
+/// data into.  Use `sg_storage_reader_stack_graph`, `sg_storage_reader_partial_path_arena`, and
+/// `sg_storage_reader_partial_path_database` to access them.
+#[cfg(feature = "storage")]
+pub struct sg_storage_reader {
+    pub inner: SQLiteReader,
+}
+
+/// Describes the result of a storage operation
+#[cfg(feature = "storage")]
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum sg_storage_result {
+    SG_STORAGE_RESULT_SUCCESS,
+    SG_STORAGE_RESULT_CANCELLED,
+    /// The database was created by an incompatible version of this library.
+    SG_STORAGE_RESULT_INCORRECT_VERSION,
+    /// The database file does not exist.
+    SG_STORAGE_RESULT_MISSING_DATABASE,
+    /// A string argument was not valid UTF-8.
+    SG_STORAGE_RESULT_INVALID_ARGUMENT,
+    /// The underlying SQLite database reported an error.
+    SG_STORAGE_RESULT_DATABASE_ERROR,
+    /// Stack graph or partial path data could not be serialized or deserialized.
+    SG_STORAGE_RESULT_SERIALIZATION_ERROR,
+    /// The database was locked by another connection for longer than the busy timeout.
+    SG_STORAGE_RESULT_CONTENDED,
+}
+
+#[cfg(feature = "storage")]
+impl<T> From<Result<T, StorageError>> for sg_storage_result {
+    fn from(result: Result<T, StorageError>) -> Self {
+        match result {
+            Ok(_) => Self::SG_STORAGE_RESULT_SUCCESS,
+            Err(StorageError::Cancelled(_)) => Self::SG_STORAGE_RESULT_CANCELLED,
+            Err(StorageError::Contended) => Self::SG_STORAGE_RESULT_CONTENDED,
+            Err(StorageError::IncompatibleVersion { .. }) => {
+                Self::SG_STORAGE_RESULT_INCORRECT_VERSION
+            }
+            Err(StorageError::MissingDatabase(_)) => Self::SG_STORAGE_RESULT_MISSING_DATABASE,
+            Err(StorageError::Rusqlite(_)) => Self::SG_STORAGE_RESULT_DATABASE_ERROR,
+            Err(StorageError::Serde(_))
+            | Err(StorageError::SerializeFail(_))
+            | Err(StorageError::DeserializeFail(_))
+            | Err(StorageError::Compression(_)) => Self::SG_STORAGE_RESULT_SERIALIZATION_ERROR,
+        }
+    }
+}
+
+/// The status of a file in a stack graph database.
+#[cfg(feature = "storage")]
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum sg_file_status {
+    /// The file is not in the database, or was stored with a different tag.
+    SG_FILE_STATUS_MISSING,
+    /// The file was indexed successfully.
+    SG_FILE_STATUS_INDEXED,
+    /// Indexing the file resulted in an error.
+    SG_FILE_STATUS_ERROR,
+}
+
+#[cfg(feature = "storage")]
+impl From<FileStatus> for sg_file_status {
+    fn from(status: FileStatus) -> Self {
+        match status {
+            FileStatus::Missing => Self::SG_FILE_STATUS_MISSING,
+            FileStatus::Indexed => Self::SG_FILE_STATUS_INDEXED,
+            FileStatus::Error(_) => Self::SG_FILE_STATUS_ERROR,
+        }
+    }
+}
+
+/// Returns the UTF-8 string of the given length at the given pointer, or None if the content
+/// isn't valid UTF-8.
+#[cfg(feature = "storage")]
+fn str_from_raw_parts<'a>(data: *const c_char, len: usize) -> Option<&'a str> {
+    let bytes = unsafe { std::slice::from_raw_parts(data as *const u8, len) };
+    std::str::from_utf8(bytes).ok()
+}
+
+/// Opens a file database for writing, creating it if it doesn't exist yet.  On success, the
+/// writer is placed in the `writer_out` output parameter.  You must free the writer when you are
+/// done with it by calling `sg_storage_writer_free`.
+///
+/// The path must be a valid UTF-8 string of `path_len` bytes.
+#[cfg(feature = "storage")]
+#[no_mangle]
+pub extern "C" fn sg_storage_writer_open(
+    path: *const c_char,
+    path_len: usize,
+    writer_out: *mut *mut sg_storage_writer,
+) -> sg_storage_result {
+    let path = match str_from_raw_parts(path, path_len) {
+        Some(path) => path,
+        None => return sg_storage_result::SG_STORAGE_RESULT_INVALID_ARGUMENT,
+    };
+    match SQLiteWriter::open(path) {
+        Ok(inner) => {
+            unsafe { *writer_out = Box::into_raw(Box::new(sg_storage_writer { inner })) };
+            sg_storage_result::SG_STORAGE_RESULT_SUCCESS
+        }
+        Err(err) => Err::<(), _>(err).into(),
+    }
+}
+
+/// Frees a storage writer, and closes its database.
+#[cfg(feature = "storage")]
+#[no_mangle]
+pub extern "C" fn sg_storage_writer_free(writer: *mut sg_storage_writer) {
+    drop(unsafe { Box::from_raw(writer) })
+}
+
+/// Stores the stack graph of a file, together with the partial paths that were found in it.  Any
+/// data that was previously stored for the file is replaced.  `paths` is the array of partial
+/// paths that you want to store; `count` is the number of them.  The partial paths must have been
+/// created in `partials`.
+///
+/// The tag must be a valid UTF-8 string of `tag_len` bytes.
+#[cfg(feature = "storage")]
+#[no_mangle]
+pub extern "C" fn sg_storage_writer_store_result_for_file(
+    writer: *mut sg_storage_writer,
+    graph: *const sg_stack_graph,
+    partials: *mut sg_partial_path_arena,
+    file: sg_file_handle,
+    tag: *const c_char,
+    tag_len: usize,
+    count: usize,
+    paths: *const sg_partial_path,
+) -> sg_storage_result {
+    let writer = unsafe { &mut (*writer).inner };
+    let graph = unsafe { &(*graph).inner };
+    let partials = unsafe { &mut (*partials).inner };
+    let tag = match str_from_raw_parts(tag, tag_len) {
+        Some(tag) => tag,
+        None => return sg_storage_result::SG_STORAGE_RESULT_INVALID_ARGUMENT,
+    };
+    let paths = unsafe { std::slice::from_raw_parts(paths as *const PartialPath, count) };
+    writer
+        .store_result_for_file(graph, file.into(), tag, partials, paths)
+        .into()
+}
+
+/// Stores an error for a file that could not be indexed.  Any data that was previously stored for
+/// the file is replaced.
+///
+/// The file, tag, and error must be valid UTF-8 strings of the given lengths.
+#[cfg(feature = "storage")]
+#[no_mangle]
+pub extern "C" fn sg_storage_writer_store_error_for_file(
+    writer: *mut sg_storage_writer,
+    file: *const c_char,
+    file_len: usize,
+    tag: *const c_char,
+    tag_len: usize,
+    error: *const c_char,
+    error_len: usize,
+) -> sg_storage_result {
+    let writer = unsafe { &mut (*writer).inner };
+    let (file, tag, error) = match (
+        str_from_raw_parts(file, file_len),
+        str_from_raw_parts(tag, tag_len),
+        str_from_raw_parts(error, error_len),
+    ) {
+        (Some(file), Some(tag), Some(error)) => (file, tag, error),
+        _ => return sg_storage_result::SG_STORAGE_RESULT_INVALID_ARGUMENT,
+    };
+    writer
+        .store_error_for_file(std::path::Path::new(file), tag, error)
+        .into()
+}
+
+/// Determines the status of a file in the database, placing the result into the `status_out`
+/// output parameter.  If `tag` is not null, the stored tag must match, or the file is reported
+/// missing.
+///
+/// The file and tag must be valid UTF-8 strings of the given lengths.
+#[cfg(feature = "storage")]
+#[no_mangle]
+pub extern "C" fn sg_storage_writer_status_for_file(
+    writer: *mut sg_storage_writer,
+    file: *const c_char,
+    file_len: usize,
+    tag: *const c_char,
+    tag_len: usize,
+    status_out: *mut sg_file_status,
+) -> sg_storage_result {
+    let writer = unsafe { &mut (*writer).inner };
+    let (file, tag) = match storage_file_and_tag(file, file_len, tag, tag_len) {
+        Some(file_and_tag) => file_and_tag,
+        None => return sg_storage_result::SG_STORAGE_RESULT_INVALID_ARGUMENT,
+    };
+    match writer.status_for_file(file, tag) {
+        Ok(status) => {
+            unsafe { *status_out = status.into() };
+            sg_storage_result::SG_STORAGE_RESULT_SUCCESS
+        }
+        Err(err) => Err::<(), _>(err).into(),
+    }
+}
+
+/// Opens an existing file database for reading.  On success, the reader is placed in the
+/// `reader_out` output parameter.  You must free the reader when you are done with it by calling
+/// `sg_storage_reader_free`.
+///
+/// The path must be a valid UTF-8 string of `path_len` bytes.
+#[cfg(feature = "storage")]
+#[no_mangle]
+pub extern "C" fn sg_storage_reader_open(
+    path: *const c_char,
+    path_len: usize,
+    reader_out: *mut *mut sg_storage_reader,
+) -> sg_storage_result {
+    let path = match str_from_raw_parts(path, path_len) {
+        Some(path) => path,
+        None => return sg_storage_result::SG_STORAGE_RESULT_INVALID_ARGUMENT,
+    };
+    match SQLiteReader::open(path) {
+        Ok(inner) => {
+            unsafe { *reader_out = Box::into_raw(Box::new(sg_storage_reader { inner })) };
+            sg_storage_result::SG_STORAGE_RESULT_SUCCESS
+        }
+        Err(err) => Err::<(), _>(err).into(),
+    }
+}
+
+/// Frees a storage reader, and all of the data that was loaded into it.
+#[cfg(feature = "storage")]
+#[no_mangle]
+pub extern "C" fn sg_storage_reader_free(reader: *mut sg_storage_reader) {
+    drop(unsafe { Box::from_raw(reader) })
+}
+
+/// Returns the stack graph that the reader loads file graphs into.  The resulting pointer is
+/// owned by the reader, and is only valid until the reader is freed.
+#[cfg(feature = "storage")]
+#[no_mangle]
+pub extern "C" fn sg_storage_reader_stack_graph(
+    reader: *mut sg_storage_reader,
+) -> *const sg_stack_graph {
+    let reader = unsafe { &mut (*reader).inner };
+    let (graph, _, _) = reader.get();
+    graph as *const StackGraph as *const sg_stack_graph
+}
+
+/// Returns the partial path arena that the reader loads partial paths into.  The resulting pointer
+/// is owned by the reader, and is only valid until the reader is freed.
+#[cfg(feature = "storage")]
+#[no_mangle]
+pub extern "C" fn sg_storage_reader_partial_path_arena(
+    reader: *mut sg_storage_reader,
+) -> *mut sg_partial_path_arena {
+    let reader = unsafe { &mut (*reader).inner };
+    let (_, partials, _) = reader.get();
+    partials as *mut PartialPaths as *mut sg_partial_path_arena
+}
+
+/// Returns the partial path database that the reader loads partial paths into.  The resulting
+/// pointer is owned by the reader, and is only valid until the reader is freed.
+#[cfg(feature = "storage")]
+#[no_mangle]
+pub extern "C" fn sg_storage_reader_partial_path_database(
+    reader: *mut sg_storage_reader,
+) -> *mut sg_partial_path_database {
+    let reader = unsafe { &mut (*reader).inner };
+    let (_, _, db) = reader.get();
+    db as *mut Database as *mut sg_partial_path_database
+}
+
+/// Clears all partial paths that were loaded into the reader.  File handles and node handles
+/// remain valid, but all partial path data is invalidated.
+#[cfg(feature = "storage")]
+#[no_mangle]
+pub extern "C" fn sg_storage_reader_clear_paths(reader: *mut sg_storage_reader) {
+    let reader = unsafe { &mut (*reader).inner };
+    reader.clear_paths();
+}
+
+/// Determines the status of a file in the database, placing the result into the `status_out`
+/// output parameter.  If `tag` is not null, the stored tag must match, or the file is reported
+/// missing.
+///
+/// The file and tag must be valid UTF-8 strings of the given lengths.
+#[cfg(feature = "storage")]
+#[no_mangle]
+pub extern "C" fn sg_storage_reader_status_for_file(
+    reader: *mut sg_storage_reader,
+    file: *const c_char,
+    file_len: usize,
+    tag: *const c_char,
+    tag_len: usize,
+    status_out: *mut sg_file_status,
+) -> sg_storage_result {
+    let reader = unsafe { &mut (*reader).inner };
+    let (file, tag) = match storage_file_and_tag(file, file_len, tag, tag_len) {
+        Some(file_and_tag) => file_and_tag,
+        None => return sg_storage_result::SG_STORAGE_RESULT_INVALID_ARGUMENT,
+    };
+    match reader.status_for_file(file, tag) {
+        Ok(status) => {
+            unsafe { *status_out = status.into() };
+            sg_storage_result::SG_STORAGE_RESULT_SUCCESS
+        }
+        Err(err) => Err::<(), _>(err).into(),
+    }
+}
+
+/// Ensures the stack graph of a file is loaded into the reader's stack graph, placing the file's
+/// handle into the `handle_out` output parameter.
+///
+/// The file must be a valid UTF-8 string of `file_len` bytes.
+#[cfg(feature = "storage")]
+#[no_mangle]
+pub extern "C" fn sg_storage_reader_load_graph_for_file(
+    reader: *mut sg_storage_reader,
+    file: *const c_char,
+    file_len: usize,
+    handle_out: *mut sg_file_handle,
+) -> sg_storage_result {
+    let reader = unsafe { &mut (*reader).inner };
+    let file = match str_from_raw_parts(file, file_len) {
+        Some(file) => file,
+        None => return sg_storage_result::SG_STORAGE_RESULT_INVALID_ARGUMENT,
+    };
+    match reader.load_graph_for_file(file) {
+        Ok(handle) => {
+            unsafe { *handle_out = handle.as_index() };
+            sg_storage_result::SG_STORAGE_RESULT_SUCCESS
+        }
+        Err(err) => Err::<(), _>(err).into(),
+    }
+}
+
+/// Ensures that all possible extensions of the given partial paths are loaded into the reader's
+/// partial path database, together with the stack graphs of the files they belong to.  `paths` is
+/// the array of partial paths; `count` is the number of them.  The partial paths must have been
+/// created in the reader's partial path arena.
+///
+/// You will typically call this function with the `previous_phase_partial_paths` of a
+/// `sg_forward_partial_path_stitcher`, before calling
+/// `sg_forward_partial_path_stitcher_process_next_phase` with the reader's partial path database.
+#[cfg(feature = "storage")]
+#[no_mangle]
+pub extern "C" fn sg_storage_reader_load_partial_path_extensions(
+    reader: *mut sg_storage_reader,
+    count: usize,
+    paths: *const sg_partial_path,
+    cancellation_flag: *const usize,
+) -> sg_storage_result {
+    let reader = unsafe { &mut (*reader).inner };
+    let paths = unsafe { std::slice::from_raw_parts(paths as *const PartialPath, count) };
+    let cancellation_flag: Option<&AtomicUsize> =
+        unsafe { std::mem::transmute(cancellation_flag.as_ref()) };
+    let cancellation_flag = AtomicUsizeCancellationFlag(cancellation_flag);
+    for path in paths {
+        if let Err(err) = reader.load_partial_path_extensions(path, &cancellation_flag) {
+            return Err::<(), _>(err).into();
+        }
+    }
+    sg_storage_result::SG_STORAGE_RESULT_SUCCESS
+}
+
+/// Ensures that all partial paths starting at the root node, whose symbol stack precondition
+/// matches the given symbol stack, are loaded into the reader's partial path database.  The
+/// symbol stack must have been created in the reader's partial path arena.
+#[cfg(feature = "storage")]
+#[no_mangle]
+pub extern "C" fn sg_storage_reader_load_partial_paths_for_symbol_stack(
+    reader: *mut sg_storage_reader,
+    symbol_stack: sg_partial_symbol_stack,
+    cancellation_flag: *const usize,
+) -> sg_storage_result {
+    let reader = unsafe { &mut (*reader).inner };
+    let cancellation_flag: Option<&AtomicUsize> =
+        unsafe { std::mem::transmute(cancellation_flag.as_ref()) };
+    reader
+        .load_paths_for_root(
+            symbol_stack.into(),
+            &AtomicUsizeCancellationFlag(cancellation_flag),
+        )
+        .into()
+}
+
+/// Decodes the file and optional tag arguments of the status functions.
+#[cfg(feature = "storage")]
+fn storage_file_and_tag<'a>(
+    file: *const c_char,
+    file_len: usize,
+    tag: *const c_char,
+    tag_len: usize,
+) -> Option<(&'a str, Option<&'a str>)> {
+    let file = str_from_raw_parts(file, file_len)?;
+    let tag = if tag.is_null() {
+        None
+    } else {
+        Some(str_from_raw_parts(tag, tag_len)?)
+    };
+    Some((file, tag))
+}
+
 //-------------------------------------------------------------------------------------------------
 // Cancellation
 
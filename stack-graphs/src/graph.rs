@@ -51,7 +51,6 @@
 
 use std::collections::HashMap;
 use std::fmt::Display;
-use std::num::NonZeroU32;
 use std::ops::Index;
 use std::ops::IndexMut;
 
@@ -62,7 +61,13 @@ use smallvec::SmallVec;
 
 use crate::arena::Arena;
 use crate::arena::Handle;
+use crate::arena::NonZeroHandleIndex;
 use crate::arena::SupplementalArena;
+use crate::serde::Filter;
+use crate::serde::ImplicationFilter;
+use crate::serde::NoFilter;
+use crate::utils::hash_map_memory_usage;
+use crate::utils::vec_memory_usage;
 
 //-------------------------------------------------------------------------------------------------
 // String content
@@ -199,6 +204,22 @@ impl StackGraph {
         handle
     }
 
+    /// Adds a symbol to the stack graph that is matched case-insensitively.  The symbol is stored
+    /// in case-folded (lowercase) form, so symbols that only differ in case share the same handle,
+    /// and match each other during path finding.  Note that a case-insensitive symbol only matches
+    /// a symbol that was added with [`add_symbol`][Self::add_symbol] if the latter is lowercase.
+    pub fn add_case_insensitive_symbol<S: AsRef<str> + ?Sized>(
+        &mut self,
+        symbol: &S,
+    ) -> Handle<Symbol> {
+        self.add_symbol(&symbol.as_ref().to_lowercase())
+    }
+
+    /// Returns the symbol with a particular content, if it exists.
+    pub fn get_symbol<S: AsRef<str> + ?Sized>(&self, symbol: &S) -> Option<Handle<Symbol>> {
+        self.symbol_handles.get(symbol.as_ref()).copied()
+    }
+
     /// Returns an iterator over all of the handles of all of the symbols in this stack graph.
     /// (Note that because we're only returning _handles_, this iterator does not retain a
     /// reference to the `StackGraph`.)
@@ -425,7 +446,7 @@ impl Handle<File> {
 /// Each node (except for the _root node_ and _jump to scope_ node) lives in a file, and has a
 /// _local ID_ that must be unique within its file.
 #[repr(C)]
-#[derive(Clone, Copy, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct NodeID {
     file: ControlledOption<Handle<File>>,
     local_id: u32,
@@ -647,13 +668,13 @@ impl StackGraph {
     /// Returns a handle to the stack graph's singleton _jump to scope_ node.
     #[inline(always)]
     pub fn jump_to_node() -> Handle<Node> {
-        Handle::new(unsafe { NonZeroU32::new_unchecked(2) })
+        Handle::new(unsafe { NonZeroHandleIndex::new_unchecked(2) })
     }
 
     /// Returns a handle to the stack graph's singleton _root node_.
     #[inline(always)]
     pub fn root_node() -> Handle<Node> {
-        Handle::new(unsafe { NonZeroU32::new_unchecked(1) })
+        Handle::new(unsafe { NonZeroHandleIndex::new_unchecked(1) })
     }
 
     /// Returns an unused [`NodeID`][] for the given file.
@@ -1427,6 +1448,178 @@ impl StackGraph {
     }
 }
 
+//-------------------------------------------------------------------------------------------------
+// Metadata
+
+/// Contains metadata about a stack graph node as key-value pairs of strings.  Unlike debug info,
+/// metadata is meant to be shown to users, for example the documentation, type annotation, or
+/// visibility of a definition.  Each key appears at most once.
+#[derive(Default)]
+pub struct Metadata {
+    entries: Vec<MetadataEntry>,
+}
+
+impl Metadata {
+    /// Sets the value for the given key, replacing any previous value.
+    pub fn add(&mut self, key: Handle<InternedString>, value: Handle<InternedString>) {
+        match self.entries.iter_mut().find(|e| e.key == key) {
+            Some(entry) => entry.value = value,
+            None => self.entries.push(MetadataEntry { key, value }),
+        }
+    }
+
+    /// Returns the value for the given key, if any.
+    pub fn get(&self, key: Handle<InternedString>) -> Option<Handle<InternedString>> {
+        self.entries.iter().find(|e| e.key == key).map(|e| e.value)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, MetadataEntry> {
+        self.entries.iter()
+    }
+}
+
+/// A metadata entry consisting of a string key-value pair.
+pub struct MetadataEntry {
+    pub key: Handle<InternedString>,
+    pub value: Handle<InternedString>,
+}
+
+impl StackGraph {
+    /// Returns the metadata of the stack graph node.
+    pub fn node_metadata(&self, node: Handle<Node>) -> Option<&Metadata> {
+        self.node_metadata.get(node)
+    }
+
+    /// Returns a mutable reference to the metadata of the stack graph node.
+    pub fn node_metadata_mut(&mut self, node: Handle<Node>) -> &mut Metadata {
+        &mut self.node_metadata[node]
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Stable node IDs
+
+/// Identifies a node by its content, instead of by the order in which it was added to its file.
+///
+/// Local IDs are assigned in insertion order, so re-indexing a file after an edit can change the
+/// IDs of all of its nodes.  A stable ID is a hash of the file name, the kind, the symbol, and the
+/// source span of a node.  It only changes when one of those changes, so that external systems
+/// can track nodes across re-indexes.  Note that edits that move a node, such as inserting lines
+/// before it, change its span and therefore its stable ID.  Nodes with the same content are told
+/// apart by the order in which they appear in their file.
+///
+/// Stable IDs are displayed as, and parsed from, 16 hexadecimal digits.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct StableNodeID(u64);
+
+impl StableNodeID {
+    /// Returns the value of this ID.
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl From<u64> for StableNodeID {
+    fn from(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+impl Display for StableNodeID {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl std::str::FromStr for StableNodeID {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        u64::from_str_radix(s, 16).map(Self)
+    }
+}
+
+/// Computes stable IDs with 64-bit FNV-1a, which, unlike the hashers of the standard library, is
+/// guaranteed to give the same results across platforms and versions.
+struct StableHasher(u64);
+
+impl StableHasher {
+    fn new() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    /// Writes a length-prefixed string, so that consecutive strings cannot be confused.
+    fn write_str(&mut self, value: &str) {
+        self.write_u64(value.len() as u64);
+        self.write_bytes(value.as_bytes());
+    }
+}
+
+impl StackGraph {
+    /// Returns the stable IDs of the nodes of a file, in the order of their local IDs.
+    pub fn stable_node_ids(&self, file: Handle<File>) -> Vec<(Handle<Node>, StableNodeID)> {
+        let mut occurrences = HashMap::new();
+        self.nodes_for_file(file)
+            .map(|node| {
+                let mut hasher = StableHasher::new();
+                hasher.write_str(self[file].name());
+                hasher.write_str(self.stable_node_kind(node));
+                hasher.write_str(self[node].symbol().map_or("", |symbol| &self[symbol]));
+                let span = self
+                    .source_info(node)
+                    .map(|source_info| source_info.span.clone())
+                    .unwrap_or_default();
+                for position in [span.start, span.end] {
+                    hasher.write_u64(position.line as u64);
+                    hasher.write_u64(position.column.utf8_offset as u64);
+                }
+                // tell apart nodes with the same content by their order
+                let occurrence = occurrences.entry(hasher.0).or_insert(0u64);
+                hasher.write_u64(*occurrence);
+                *occurrence += 1;
+                (node, StableNodeID(hasher.0))
+            })
+            .collect()
+    }
+
+    /// Returns the node of a file with the given stable ID, if it exists.
+    pub fn node_for_stable_id(&self, file: Handle<File>, id: StableNodeID) -> Option<Handle<Node>> {
+        self.stable_node_ids(file)
+            .into_iter()
+            .find(|(_, stable_id)| *stable_id == id)
+            .map(|(node, _)| node)
+    }
+
+    /// Returns the kind of a node, as it is named in serialized graphs.
+    fn stable_node_kind(&self, node: Handle<Node>) -> &'static str {
+        match &self[node] {
+            Node::DropScopes(_) => "drop_scopes",
+            Node::JumpTo(_) => "jump_to_scope",
+            Node::PopScopedSymbol(_) => "pop_scoped_symbol",
+            Node::PopSymbol(_) => "pop_symbol",
+            Node::PushScopedSymbol(_) => "push_scoped_symbol",
+            Node::PushSymbol(_) => "push_symbol",
+            Node::Root(_) => "root",
+            Node::Scope(_) => "scope",
+        }
+    }
+}
+
 //-------------------------------------------------------------------------------------------------
 // Stack graphs
 
@@ -1445,6 +1638,7 @@ pub struct StackGraph {
     outgoing_edges: SupplementalArena<Node, SmallVec<[OutgoingEdge; 8]>>,
     pub(crate) node_debug_info: SupplementalArena<Node, DebugInfo>,
     pub(crate) edge_debug_info: SupplementalArena<Node, SmallVec<[(Handle<Node>, DebugInfo); 8]>>,
+    pub(crate) node_metadata: SupplementalArena<Node, Metadata>,
 }
 
 impl StackGraph {
@@ -1456,9 +1650,50 @@ impl StackGraph {
     /// Copies the given stack graph into this stack graph. Panics if any of the files
     /// in the other stack graph are already defined in the current one.
     pub fn add_from_graph(&mut self, other: &StackGraph) -> Result<(), Handle<File>> {
+        self.add_from_graph_filtered(other, &NoFilter)
+    }
+
+    /// Copies the files of the given stack graph that are included by a filter into this stack
+    /// graph, for example to only add the builtins that a test actually imports.  Only the nodes
+    /// and edges that the filter includes are copied, and edges to nodes that are not copied are
+    /// left out.  Returns an error if any of the included files are already defined in the
+    /// current one.
+    pub fn add_from_graph_filtered(
+        &mut self,
+        other: &StackGraph,
+        filter: &dyn Filter,
+    ) -> Result<(), Handle<File>> {
+        self.add_from_graph_impl(other, filter, &|name| name.to_string())
+    }
+
+    /// Copies the given stack graph into this stack graph, giving each of its files the name
+    /// returned by `rename` for the file's original name.  This makes it possible to copy graphs
+    /// whose file names are not unique, such as the builtins of different languages.  Returns an
+    /// error if any of the renamed files are already defined in the current one.
+    pub fn add_from_graph_renamed<F>(
+        &mut self,
+        other: &StackGraph,
+        rename: F,
+    ) -> Result<(), Handle<File>>
+    where
+        F: Fn(&str) -> String,
+    {
+        self.add_from_graph_impl(other, &NoFilter, &rename)
+    }
+
+    fn add_from_graph_impl(
+        &mut self,
+        other: &StackGraph,
+        filter: &dyn Filter,
+        rename: &dyn Fn(&str) -> String,
+    ) -> Result<(), Handle<File>> {
+        let filter = ImplicationFilter(filter);
         let mut files = HashMap::new();
-        for other_file in other.iter_files() {
-            let file = self.add_file(other[other_file].name())?;
+        for other_file in other
+            .iter_files()
+            .filter(|other_file| filter.include_file(other, other_file))
+        {
+            let file = self.add_file(&rename(other[other_file].name()))?;
             files.insert(other_file, file);
         }
         let node_id = |other_node_id: NodeID| {
@@ -1477,90 +1712,13 @@ impl StackGraph {
         nodes.insert(Self::root_node(), Self::root_node());
         nodes.insert(Self::jump_to_node(), Self::jump_to_node());
         for other_file in files.keys().cloned() {
-            let file = files[&other_file];
             for other_node in other.nodes_for_file(other_file) {
-                let value: Node = match other[other_node] {
-                    Node::DropScopes(DropScopesNode { id, .. }) => DropScopesNode {
-                        id: NodeID::new_in_file(file, id.local_id),
-                        _symbol: ControlledOption::default(),
-                        _scope: NodeID::default(),
-                        _is_endpoint: bool::default(),
-                    }
-                    .into(),
-                    Node::JumpTo(JumpToNode { .. }) => JumpToNode {
-                        id: NodeID::jump_to(),
-                        _symbol: ControlledOption::default(),
-                        _scope: NodeID::default(),
-                        _is_endpoint: bool::default(),
-                    }
-                    .into(),
-                    Node::PopScopedSymbol(PopScopedSymbolNode {
-                        id,
-                        symbol,
-                        is_definition,
-                        ..
-                    }) => PopScopedSymbolNode {
-                        id: NodeID::new_in_file(file, id.local_id),
-                        symbol: self.add_symbol(&other[symbol]),
-                        _scope: NodeID::default(),
-                        is_definition: is_definition,
-                    }
-                    .into(),
-                    Node::PopSymbol(PopSymbolNode {
-                        id,
-                        symbol,
-                        is_definition,
-                        ..
-                    }) => PopSymbolNode {
-                        id: NodeID::new_in_file(file, id.local_id),
-                        symbol: self.add_symbol(&other[symbol]),
-                        _scope: NodeID::default(),
-                        is_definition: is_definition,
-                    }
-                    .into(),
-                    Node::PushScopedSymbol(PushScopedSymbolNode {
-                        id,
-                        symbol,
-                        scope,
-                        is_reference,
-                        ..
-                    }) => PushScopedSymbolNode {
-                        id: NodeID::new_in_file(file, id.local_id),
-                        symbol: self.add_symbol(&other[symbol]),
-                        scope: node_id(scope),
-                        is_reference: is_reference,
-                        _phantom: (),
-                    }
-                    .into(),
-                    Node::PushSymbol(PushSymbolNode {
-                        id,
-                        symbol,
-                        is_reference,
-                        ..
-                    }) => PushSymbolNode {
-                        id: NodeID::new_in_file(file, id.local_id),
-                        symbol: self.add_symbol(&other[symbol]),
-                        _scope: NodeID::default(),
-                        is_reference: is_reference,
-                    }
-                    .into(),
-                    Node::Root(RootNode { .. }) => RootNode {
-                        id: NodeID::root(),
-                        _symbol: ControlledOption::default(),
-                        _scope: NodeID::default(),
-                        _is_endpoint: bool::default(),
-                    }
-                    .into(),
-                    Node::Scope(ScopeNode {
-                        id, is_exported, ..
-                    }) => ScopeNode {
-                        id: NodeID::new_in_file(file, id.local_id),
-                        _symbol: ControlledOption::default(),
-                        _scope: NodeID::default(),
-                        is_exported: is_exported,
-                    }
-                    .into(),
-                };
+                if !filter.include_node(other, &other_node) {
+                    continue;
+                }
+                let value = Self::translate_node(&other[other_node], node_id, |symbol| {
+                    self.add_symbol(&other[symbol])
+                });
                 let node = self.add_node(value.id(), value).unwrap();
                 nodes.insert(other_node, node);
                 if let Some(source_info) = other.source_info(other_node) {
@@ -1592,19 +1750,330 @@ impl StackGraph {
                             .collect::<Vec<_>>(),
                     };
                 }
+                if let Some(metadata) = other.node_metadata(other_node) {
+                    *self.node_metadata_mut(node) = Metadata {
+                        entries: metadata
+                            .entries
+                            .iter()
+                            .map(|e| MetadataEntry {
+                                key: self.add_string(&other[e.key]),
+                                value: self.add_string(&other[e.value]),
+                            })
+                            .collect::<Vec<_>>(),
+                    };
+                }
+            }
+        }
+        for other_node in nodes.keys().cloned() {
+            for other_edge in other.outgoing_edges(other_node) {
+                let sink = match nodes.get(&other_edge.sink) {
+                    Some(sink) => *sink,
+                    None => continue,
+                };
+                if !filter.include_edge(other, &other_edge.source, &other_edge.sink) {
+                    continue;
+                }
+                self.add_edge(nodes[&other_edge.source], sink, other_edge.precedence);
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves the contents of another stack graph, whose files must not be defined in this stack
+    /// graph, into this one.  This allows the graphs of files to be built in parallel, each in its
+    /// own stack graph on a worker thread, and combined afterwards.  Unlike
+    /// [`add_from_graph`][Self::add_from_graph], which interns a symbol for every node that
+    /// refers to one, every symbol and string of the other graph is interned once, and source
+    /// info, debug info, and metadata are moved instead of copied, so that the time taken is
+    /// linear in the size of the other graph.  If any file of the other graph is already defined
+    /// in this one, this graph is not changed, and the handle of that file is returned as an
+    /// error.
+    pub fn merge_disjoint(&mut self, mut other: StackGraph) -> Result<(), Handle<File>> {
+        if let Some(file) = other
+            .iter_files()
+            .find_map(|other_file| self.get_file(other[other_file].name()))
+        {
+            return Err(file);
+        }
+
+        // The handles of the other graph are translated using tables that are indexed by handle,
+        // since arena handles are consecutive and start at one.
+        let files = other
+            .iter_files()
+            .map(|other_file| {
+                self.add_file(other[other_file].name())
+                    .expect("files are disjoint")
+            })
+            .collect::<Vec<_>>();
+        let symbols = other
+            .iter_symbols()
+            .map(|other_symbol| self.add_symbol(&other[other_symbol]))
+            .collect::<Vec<_>>();
+        let strings = other
+            .strings
+            .iter_handles()
+            .map(|other_string| self.add_string(&other[other_string]))
+            .collect::<Vec<_>>();
+        let string = |other_string: Handle<InternedString>| strings[other_string.as_usize() - 1];
+        let node_id = |other_node_id: NodeID| match other_node_id.file() {
+            Some(other_file) => {
+                NodeID::new_in_file(files[other_file.as_usize() - 1], other_node_id.local_id)
+            }
+            None => other_node_id,
+        };
+
+        let mut nodes = Vec::with_capacity(other.nodes.len());
+        for other_node in other.iter_nodes() {
+            let node = if other[other_node].id().file().is_none() {
+                // the root and jump to scope nodes have the same handles in all graphs
+                other_node
+            } else {
+                let value = Self::translate_node(&other[other_node], node_id, |other_symbol| {
+                    symbols[other_symbol.as_usize() - 1]
+                });
+                self.add_node(value.id(), value)
+                    .expect("files are disjoint")
+            };
+            nodes.push(node);
+        }
+        let node = |other_node: Handle<Node>| nodes[other_node.as_usize() - 1];
+
+        for other_node in other.iter_nodes() {
+            if let Some(source_info) = other.source_info.get_mut(other_node) {
+                let mut source_info = std::mem::take(source_info);
+                source_info.syntax_type = source_info.syntax_type.into_option().map(string).into();
+                source_info.containing_line =
+                    source_info.containing_line.into_option().map(string).into();
+                source_info.fully_qualified_name = source_info
+                    .fully_qualified_name
+                    .into_option()
+                    .map(string)
+                    .into();
+                self.source_info[node(other_node)] = source_info;
+            }
+            if let Some(debug_info) = other.node_debug_info.get_mut(other_node) {
+                let mut debug_info = std::mem::take(debug_info);
+                for entry in &mut debug_info.entries {
+                    entry.key = string(entry.key);
+                    entry.value = string(entry.value);
+                }
+                self.node_debug_info[node(other_node)] = debug_info;
             }
-            for other_node in nodes.keys().cloned() {
-                for other_edge in other.outgoing_edges(other_node) {
-                    self.add_edge(
-                        nodes[&other_edge.source],
-                        nodes[&other_edge.sink],
-                        other_edge.precedence,
-                    );
+            if let Some(metadata) = other.node_metadata.get_mut(other_node) {
+                let mut metadata = std::mem::take(metadata);
+                for entry in &mut metadata.entries {
+                    entry.key = string(entry.key);
+                    entry.value = string(entry.value);
+                }
+                self.node_metadata[node(other_node)] = metadata;
+            }
+            for other_edge in other.outgoing_edges(other_node) {
+                self.add_edge(
+                    node(other_edge.source),
+                    node(other_edge.sink),
+                    other_edge.precedence,
+                );
+            }
+            if let Some(edge_debug_info) = other.edge_debug_info.get_mut(other_node) {
+                for (other_sink, mut debug_info) in std::mem::take(edge_debug_info) {
+                    for entry in &mut debug_info.entries {
+                        entry.key = string(entry.key);
+                        entry.value = string(entry.value);
+                    }
+                    *self.edge_debug_info_mut(node(other_node), node(other_sink)) = debug_info;
                 }
             }
         }
         Ok(())
     }
+
+    /// Returns a copy of a node of another stack graph, using the given functions to translate
+    /// the node IDs and symbols of the other graph into this one.
+    fn translate_node<I, S>(other_node: &Node, mut translate_id: I, mut translate_symbol: S) -> Node
+    where
+        I: FnMut(NodeID) -> NodeID,
+        S: FnMut(Handle<Symbol>) -> Handle<Symbol>,
+    {
+        match *other_node {
+            Node::DropScopes(DropScopesNode { id, .. }) => DropScopesNode {
+                id: translate_id(id),
+                _symbol: ControlledOption::default(),
+                _scope: NodeID::default(),
+                _is_endpoint: bool::default(),
+            }
+            .into(),
+            Node::JumpTo(JumpToNode { .. }) => JumpToNode {
+                id: NodeID::jump_to(),
+                _symbol: ControlledOption::default(),
+                _scope: NodeID::default(),
+                _is_endpoint: bool::default(),
+            }
+            .into(),
+            Node::PopScopedSymbol(PopScopedSymbolNode {
+                id,
+                symbol,
+                is_definition,
+                ..
+            }) => PopScopedSymbolNode {
+                id: translate_id(id),
+                symbol: translate_symbol(symbol),
+                _scope: NodeID::default(),
+                is_definition: is_definition,
+            }
+            .into(),
+            Node::PopSymbol(PopSymbolNode {
+                id,
+                symbol,
+                is_definition,
+                ..
+            }) => PopSymbolNode {
+                id: translate_id(id),
+                symbol: translate_symbol(symbol),
+                _scope: NodeID::default(),
+                is_definition: is_definition,
+            }
+            .into(),
+            Node::PushScopedSymbol(PushScopedSymbolNode {
+                id,
+                symbol,
+                scope,
+                is_reference,
+                ..
+            }) => PushScopedSymbolNode {
+                id: translate_id(id),
+                symbol: translate_symbol(symbol),
+                scope: translate_id(scope),
+                is_reference: is_reference,
+                _phantom: (),
+            }
+            .into(),
+            Node::PushSymbol(PushSymbolNode {
+                id,
+                symbol,
+                is_reference,
+                ..
+            }) => PushSymbolNode {
+                id: translate_id(id),
+                symbol: translate_symbol(symbol),
+                _scope: NodeID::default(),
+                is_reference: is_reference,
+            }
+            .into(),
+            Node::Root(RootNode { .. }) => RootNode {
+                id: NodeID::root(),
+                _symbol: ControlledOption::default(),
+                _scope: NodeID::default(),
+                _is_endpoint: bool::default(),
+            }
+            .into(),
+            Node::Scope(ScopeNode {
+                id, is_exported, ..
+            }) => ScopeNode {
+                id: translate_id(id),
+                _symbol: ControlledOption::default(),
+                _scope: NodeID::default(),
+                is_exported: is_exported,
+            }
+            .into(),
+        }
+    }
+}
+
+impl StackGraph {
+    /// Returns the approximate number of bytes of memory used by the parts of this stack graph.
+    pub fn memory_usage(&self) -> StackGraphMemoryUsage {
+        let interned_strings = self.interned_strings.current_buffer.capacity()
+            + self
+                .interned_strings
+                .full_buffers
+                .iter()
+                .map(Vec::capacity)
+                .sum::<usize>();
+        let spilled_edges = self
+            .outgoing_edges
+            .iter()
+            .map(|(_, edges)| match edges.spilled() {
+                true => edges.capacity() * std::mem::size_of::<OutgoingEdge>(),
+                false => 0,
+            })
+            .sum::<usize>();
+        let node_debug_entries = self
+            .node_debug_info
+            .iter()
+            .map(|(_, debug_info)| vec_memory_usage(&debug_info.entries))
+            .sum::<usize>();
+        let edge_debug_entries = self
+            .edge_debug_info
+            .iter()
+            .map(|(_, edges)| {
+                let spilled = match edges.spilled() {
+                    true => edges.capacity() * std::mem::size_of::<(Handle<Node>, DebugInfo)>(),
+                    false => 0,
+                };
+                spilled
+                    + edges
+                        .iter()
+                        .map(|(_, debug_info)| vec_memory_usage(&debug_info.entries))
+                        .sum::<usize>()
+            })
+            .sum::<usize>();
+        let metadata_entries = self
+            .node_metadata
+            .iter()
+            .map(|(_, metadata)| vec_memory_usage(&metadata.entries))
+            .sum::<usize>();
+        let node_id_handles = self.node_id_handles.files.memory_usage()
+            + self
+                .node_id_handles
+                .files
+                .iter()
+                .map(|(_, handles)| vec_memory_usage(handles))
+                .sum::<usize>();
+        StackGraphMemoryUsage {
+            strings: interned_strings
+                + self.strings.memory_usage()
+                + hash_map_memory_usage(&self.string_handles),
+            symbols: self.symbols.memory_usage() + hash_map_memory_usage(&self.symbol_handles),
+            files: self.files.memory_usage() + hash_map_memory_usage(&self.file_handles),
+            nodes: self.nodes.memory_usage() + node_id_handles,
+            source_info: self.source_info.memory_usage(),
+            edges: self.outgoing_edges.memory_usage() + spilled_edges,
+            debug_info: self.node_debug_info.memory_usage()
+                + node_debug_entries
+                + self.edge_debug_info.memory_usage()
+                + edge_debug_entries,
+            metadata: self.node_metadata.memory_usage() + metadata_entries,
+        }
+    }
+}
+
+/// The approximate number of bytes of memory used by the parts of a stack graph, as returned by
+/// [`StackGraph::memory_usage`][].  The contents of interned strings, which are shared by
+/// symbols, files, and strings, are included in `strings`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct StackGraphMemoryUsage {
+    pub strings: usize,
+    pub symbols: usize,
+    pub files: usize,
+    pub nodes: usize,
+    pub source_info: usize,
+    pub edges: usize,
+    pub debug_info: usize,
+    pub metadata: usize,
+}
+
+impl StackGraphMemoryUsage {
+    /// Returns the total number of bytes used by the stack graph.
+    pub fn total(&self) -> usize {
+        self.strings
+            + self.symbols
+            + self.files
+            + self.nodes
+            + self.source_info
+            + self.edges
+            + self.debug_info
+            + self.metadata
+    }
 }
 
 impl Default for StackGraph {
@@ -1627,6 +2096,7 @@ impl Default for StackGraph {
             outgoing_edges: SupplementalArena::new(),
             node_debug_info: SupplementalArena::new(),
             edge_debug_info: SupplementalArena::new(),
+            node_metadata: SupplementalArena::new(),
         }
     }
 }
@@ -55,24 +55,34 @@
 //! importantly, each “chunk” of the overall graph only depends on “local” information from the
 //! original source file.  (a.k.a., it’s incremental!)
 
+use std::path::Path;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
 use std::time::{Duration, Instant};
 
 use thiserror::Error;
 
 pub mod arena;
 pub mod assert;
+#[cfg(feature = "bench")]
+pub mod bench;
+pub mod builder;
 pub mod c;
 pub mod cycles;
 #[macro_use]
 mod debugging;
 pub mod graph;
+pub mod names;
 pub mod partial;
 pub mod paths;
 pub mod serde;
+pub mod stats;
 pub mod stitching;
 #[cfg(feature = "storage")]
 pub mod storage;
+#[cfg(feature = "testutil")]
+pub mod testutil;
 pub(crate) mod utils;
+pub mod validation;
 #[cfg(feature = "visualization")]
 pub mod visualization;
 
@@ -88,11 +98,15 @@ impl CancellationFlag for NoCancellation {
     }
 }
 
+/// Cancels execution once the given duration has passed.  This is not available on
+/// `wasm32-unknown-unknown`, where the current time cannot be read.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
 pub struct CancelAfterDuration {
     limit: Duration,
     start: Instant,
 }
 
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
 impl CancelAfterDuration {
     pub fn new(limit: Duration) -> Self {
         Self {
@@ -102,6 +116,7 @@ impl CancelAfterDuration {
     }
 }
 
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
 impl CancellationFlag for CancelAfterDuration {
     fn check(&self, at: &'static str) -> Result<(), CancellationError> {
         if self.start.elapsed() > self.limit {
@@ -114,3 +129,22 @@ impl CancellationFlag for CancelAfterDuration {
 #[derive(Clone, Debug, Error)]
 #[error("Cancelled at \"{0}\"")]
 pub struct CancellationError(pub &'static str);
+
+/// Trait to provide the content of source files
+pub trait ContentProvider {
+    fn get(&mut self, path: &Path) -> std::io::Result<Option<&str>>;
+}
+
+/// Provides no content for any file.
+pub struct NoContent;
+impl ContentProvider for NoContent {
+    fn get(&mut self, _path: &Path) -> std::io::Result<Option<&str>> {
+        Ok(None)
+    }
+}
+
+impl ContentProvider for Option<&str> {
+    fn get(&mut self, _path: &Path) -> std::io::Result<Option<&str>> {
+        Ok(*self)
+    }
+}
@@ -0,0 +1,216 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Computes the qualified names of definitions.
+//!
+//! The qualified name of a definition is determined by the symbols that a reference must have on
+//! its symbol stack to reach the definition from the root node.  For example, a definition `bar`
+//! in a module `foo` is reachable from the root with the symbol stack `foo.bar`.  We find these
+//! symbols by stitching together partial paths from a database, working backwards from the
+//! definition until we find a path that starts at the root node.
+//!
+//! How the symbols are turned into a name depends on the language, and is controlled by a
+//! [`Separator`][].
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt::Display;
+
+use crate::arena::Handle;
+use crate::graph::Node;
+use crate::graph::StackGraph;
+use crate::graph::Symbol;
+use crate::partial::PartialPath;
+use crate::partial::PartialPaths;
+use crate::stitching::Database;
+
+/// The qualified name of a definition.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct QualifiedName {
+    /// The symbols that make up the name, outermost first.
+    pub symbols: Vec<Handle<Symbol>>,
+    /// The definitions that a path from the root node to the definition passes through, outermost
+    /// first.  The definition itself is not included.
+    pub enclosing_definitions: Vec<Handle<Node>>,
+}
+
+impl QualifiedName {
+    /// Returns the components of this name, outermost first.  Symbols that the separator
+    /// recognizes as separator symbols are not components.
+    pub fn components<'a, S: Separator + ?Sized>(
+        &self,
+        graph: &'a StackGraph,
+        separator: &S,
+    ) -> Vec<&'a str> {
+        self.symbols
+            .iter()
+            .map(|symbol| &graph[*symbol])
+            .filter(|symbol| !separator.is_separator_symbol(symbol))
+            .collect()
+    }
+
+    /// Returns a displayable representation of this name, with components separated by dots.
+    /// Symbols that consist of only a dot, which some languages use to model member access, are
+    /// not included as components.
+    pub fn display<'a>(&'a self, graph: &'a StackGraph) -> impl Display + 'a {
+        self.display_with_separator(graph, ".")
+    }
+
+    /// Returns a displayable representation of this name, with components separated by the given
+    /// separator.
+    pub fn display_with_separator<'a, S: Separator + ?Sized>(
+        &'a self,
+        graph: &'a StackGraph,
+        separator: &'a S,
+    ) -> impl Display + 'a {
+        DisplayQualifiedName {
+            name: self,
+            graph,
+            separator,
+        }
+    }
+}
+
+struct DisplayQualifiedName<'a, S: ?Sized> {
+    name: &'a QualifiedName,
+    graph: &'a StackGraph,
+    separator: &'a S,
+}
+
+impl<S: Separator + ?Sized> Display for DisplayQualifiedName<'_, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let components = self.name.components(self.graph, self.separator);
+        write!(f, "{}", components.join(self.separator.separator()))
+    }
+}
+
+/// Determines how the components of qualified names are separated.
+///
+/// Languages differ in how they model member access in stack graphs.  Some push and pop a
+/// separate symbol between the components of a name, such as `.` in Python, while others don't use
+/// separator symbols at all.  Implementations of this trait recognize the separator symbols of a
+/// language, so that they are not mistaken for components, and choose the separator that is used
+/// when rendering names.
+///
+/// A string slice is a separator that renders names using itself, and recognizes symbols equal to
+/// itself as separator symbols.  So `"."` works for Python, and `"::"` for languages that use
+/// `::` symbols.
+pub trait Separator {
+    /// Returns whether the symbol separates name components, instead of being one.
+    fn is_separator_symbol(&self, symbol: &str) -> bool;
+
+    /// Returns the string that is put between name components.
+    fn separator(&self) -> &str;
+}
+
+impl Separator for str {
+    fn is_separator_symbol(&self, symbol: &str) -> bool {
+        symbol == self
+    }
+
+    fn separator(&self) -> &str {
+        self
+    }
+}
+
+/// A separator that renders names using a different separator than the separator symbol used
+/// in the stack graph, for example to render names from a graph that uses `.` symbols using `::`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ReplaceSeparator<'a> {
+    /// The separator symbol used in the stack graph.
+    pub symbol: &'a str,
+    /// The string put between name components.
+    pub separator: &'a str,
+}
+
+impl Separator for ReplaceSeparator<'_> {
+    fn is_separator_symbol(&self, symbol: &str) -> bool {
+        symbol == self.symbol
+    }
+
+    fn separator(&self) -> &str {
+        self.separator
+    }
+}
+
+/// Computes the qualified name of a definition, using the partial paths in the database.  Returns
+/// `None` if the database does not contain partial paths that connect the root node to the
+/// definition, for example because the definition is local.  If there are several such paths, we
+/// prefer the ones that are stitched together from the fewest partial paths, and among those, the
+/// one with the shortest name.
+pub fn qualified_name(
+    graph: &StackGraph,
+    partials: &mut PartialPaths,
+    db: &Database,
+    definition: Handle<Node>,
+) -> Option<QualifiedName> {
+    let mut paths_by_end_node = HashMap::<Handle<Node>, Vec<Handle<PartialPath>>>::new();
+    for handle in db.iter_partial_paths() {
+        paths_by_end_node
+            .entry(db[handle].end_node)
+            .or_default()
+            .push(handle);
+    }
+
+    // We extend paths backwards, one partial path at a time.  Each start node is only extended
+    // once, using the path with the shortest symbol stack precondition, which keeps the search
+    // finite in the presence of cycles.
+    let mut paths = paths_by_end_node
+        .get(&definition)
+        .into_iter()
+        .flatten()
+        .map(|handle| db[*handle].clone())
+        .collect::<Vec<_>>();
+    let mut visited = HashSet::new();
+    while !paths.is_empty() {
+        paths.sort_by_key(|path| path.symbol_stack_precondition.len());
+        if let Some(path) = paths.iter().find(|path| graph[path.start_node].is_root()) {
+            return Some(to_qualified_name(graph, partials, path));
+        }
+        let mut extended_paths = Vec::new();
+        for path in paths {
+            if !visited.insert(path.start_node) {
+                continue;
+            }
+            for handle in paths_by_end_node
+                .get(&path.start_node)
+                .into_iter()
+                .flatten()
+            {
+                let mut extended = db[*handle].clone();
+                extended.ensure_no_overlapping_variables(partials, &path);
+                if extended.concatenate(graph, partials, &path).is_ok() {
+                    extended_paths.push(extended);
+                }
+            }
+        }
+        paths = extended_paths;
+    }
+    None
+}
+
+fn to_qualified_name(
+    graph: &StackGraph,
+    partials: &mut PartialPaths,
+    path: &PartialPath,
+) -> QualifiedName {
+    let symbols = path
+        .symbol_stack_precondition
+        .iter(partials)
+        .map(|symbol| symbol.symbol)
+        .collect();
+    let enclosing_definitions = path
+        .edges
+        .iter(partials)
+        .filter_map(|edge| graph.node_for_id(edge.source_node_id))
+        .filter(|node| *node != path.start_node && graph[*node].is_definition())
+        .collect();
+    QualifiedName {
+        symbols,
+        enclosing_definitions,
+    }
+}
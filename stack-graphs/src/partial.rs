@@ -46,6 +46,7 @@ use crate::arena::Deque;
 use crate::arena::DequeArena;
 use crate::arena::Handle;
 use crate::graph::Edge;
+use crate::graph::File;
 use crate::graph::Node;
 use crate::graph::NodeID;
 use crate::graph::StackGraph;
@@ -1763,6 +1764,35 @@ impl PartialPath {
         }
     }
 
+    /// Returns the stack graph edges of this partial path.  Partial paths only record the source
+    /// nodes of their edges, so edges whose source or sink node is not in the graph are left out.
+    pub fn graph_edges(&self, graph: &StackGraph, partials: &mut PartialPaths) -> Vec<Edge> {
+        let mut path_edges = self.edges;
+        let mut sources = Vec::with_capacity(path_edges.len());
+        while let Some(path_edge) = path_edges.pop_front(partials) {
+            sources.push((
+                graph.node_for_id(path_edge.source_node_id),
+                path_edge.precedence,
+            ));
+        }
+        let sinks = sources
+            .iter()
+            .skip(1)
+            .map(|(source, _)| *source)
+            .chain(std::iter::once(Some(self.end_node)));
+        sources
+            .iter()
+            .zip(sinks)
+            .filter_map(|((source, precedence), sink)| {
+                Some(Edge {
+                    source: (*source)?,
+                    sink: sink?,
+                    precedence: *precedence,
+                })
+            })
+            .collect()
+    }
+
     /// Returns whether one path shadows another.  Note that shadowing is not commutative — if path
     /// A shadows path B, the reverse is not true.
     pub fn shadows(&self, partials: &mut PartialPaths, other: &PartialPath) -> bool {
@@ -1836,6 +1866,23 @@ impl PartialPath {
         self.starts_at_reference(graph) && self.ends_at_definition(graph)
     }
 
+    /// Returns whether a partial path passes through a node in the given file, including its
+    /// start and end nodes.  Unlike [`Node::is_in_file`], the singleton _root_ and _jump to
+    /// scope_ nodes do not belong to any file.
+    pub fn visits_file(
+        &self,
+        graph: &StackGraph,
+        partials: &PartialPaths,
+        file: Handle<File>,
+    ) -> bool {
+        graph[self.start_node].file() == Some(file)
+            || graph[self.end_node].file() == Some(file)
+            || self
+                .edges
+                .iter_unordered(partials)
+                .any(|edge| edge.source_node_id.file() == Some(file))
+    }
+
     pub fn starts_at_endpoint(&self, graph: &StackGraph) -> bool {
         graph[self.start_node].is_endpoint()
     }
@@ -2625,10 +2672,76 @@ impl PartialPaths {
         }
     }
 
-    #[cfg_attr(not(feature = "storage"), allow(dead_code))]
-    pub(crate) fn clear(&mut self) {
+    /// Removes all partial symbol stacks, scope stacks, and edge lists from the arenas, keeping
+    /// their allocated capacity so that they can be reused without reallocating.  After this, all
+    /// partial paths that were created using this instance are invalid, and must not be used
+    /// anymore.  That includes any partial paths stored in a [`Database`][], which should be
+    /// cleared as well.
+    ///
+    /// [`Database`]: crate::stitching::Database
+    pub fn clear(&mut self) {
         self.partial_symbol_stacks.clear();
         self.partial_scope_stacks.clear();
         self.partial_path_edges.clear();
     }
+
+    /// Releases memory of the arenas that is not used anymore, for instance after calling
+    /// [`clear`][Self::clear] when the next batch of paths is expected to be much smaller.
+    pub fn shrink_to_fit(&mut self) {
+        self.partial_symbol_stacks.shrink_to_fit();
+        self.partial_scope_stacks.shrink_to_fit();
+        self.partial_path_edges.shrink_to_fit();
+    }
+
+    /// Returns the number of cells allocated in the arenas of this instance.  Cells are never
+    /// released individually, so these counts only grow until the instance is cleared.  They can
+    /// be used to decide when to recycle this instance, for example in a long-running indexer.
+    pub fn cell_counts(&self) -> PartialPathsCellCounts {
+        PartialPathsCellCounts {
+            symbol_stacks: self.partial_symbol_stacks.len() - 1,
+            scope_stacks: self.partial_scope_stacks.len() - 1,
+            edges: self.partial_path_edges.len() - 1,
+        }
+    }
+
+    /// Returns the approximate number of bytes of memory used by the arenas of this instance.
+    pub fn memory_usage(&self) -> PartialPathsMemoryUsage {
+        PartialPathsMemoryUsage {
+            symbol_stacks: self.partial_symbol_stacks.memory_usage(),
+            scope_stacks: self.partial_scope_stacks.memory_usage(),
+            edges: self.partial_path_edges.memory_usage(),
+        }
+    }
+}
+
+/// The approximate number of bytes of memory used by the arenas of a [`PartialPaths`][] instance,
+/// as returned by [`PartialPaths::memory_usage`][].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PartialPathsMemoryUsage {
+    pub symbol_stacks: usize,
+    pub scope_stacks: usize,
+    pub edges: usize,
+}
+
+impl PartialPathsMemoryUsage {
+    /// Returns the total number of bytes used by the arenas.
+    pub fn total(&self) -> usize {
+        self.symbol_stacks + self.scope_stacks + self.edges
+    }
+}
+
+/// The number of cells in the arenas of a [`PartialPaths`][] instance, as returned by
+/// [`PartialPaths::cell_counts`][].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PartialPathsCellCounts {
+    pub symbol_stacks: usize,
+    pub scope_stacks: usize,
+    pub edges: usize,
+}
+
+impl PartialPathsCellCounts {
+    /// Returns the total number of cells in the arenas.
+    pub fn total(&self) -> usize {
+        self.symbol_stacks + self.scope_stacks + self.edges
+    }
 }
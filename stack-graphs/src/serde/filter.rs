@@ -6,6 +6,10 @@
 // ------------------------------------------------------------------------------------------------
 
 use itertools::Itertools;
+use lsp_positions::Span;
+use std::collections::HashSet;
+use std::iter::FromIterator;
+use std::ops::Range;
 
 use crate::arena::Handle;
 use crate::graph::File;
@@ -98,12 +102,25 @@ impl Filter for NoFilter {
     }
 }
 
-/// Filter implementation that includes a single file.
-pub struct FileFilter(pub Handle<File>);
+/// Filter implementation that includes a set of files.
+pub struct FileFilter(pub HashSet<Handle<File>>);
+
+impl FileFilter {
+    /// Returns a filter that includes a single file.
+    pub fn single(file: Handle<File>) -> FileFilter {
+        FileFilter(std::iter::once(file).collect())
+    }
+}
+
+impl FromIterator<Handle<File>> for FileFilter {
+    fn from_iter<I: IntoIterator<Item = Handle<File>>>(files: I) -> FileFilter {
+        FileFilter(files.into_iter().collect())
+    }
+}
 
 impl Filter for FileFilter {
     fn include_file(&self, _graph: &StackGraph, file: &Handle<File>) -> bool {
-        *file == self.0
+        self.0.contains(file)
     }
 
     fn include_node(&self, _graph: &StackGraph, _node: &Handle<Node>) -> bool {
@@ -129,6 +146,212 @@ impl Filter for FileFilter {
     }
 }
 
+/// Filter implementation that includes nodes whose source span overlaps the given span.  Nodes
+/// without a source span, which are typically scope nodes, are always included.  The filter applies
+/// to all files, and is usually combined with a [`FileFilter`][] using [`And`][].
+pub struct SpanRangeFilter(pub Span);
+
+impl Filter for SpanRangeFilter {
+    fn include_file(&self, _graph: &StackGraph, _file: &Handle<File>) -> bool {
+        true
+    }
+
+    fn include_node(&self, graph: &StackGraph, node: &Handle<Node>) -> bool {
+        let span = match graph.source_info(*node) {
+            Some(source_info) if source_info.span != Span::default() => &source_info.span,
+            _ => return true,
+        };
+        self.0.contains(&span.start) || (span.start < self.0.start && self.0.start < span.end)
+    }
+
+    fn include_edge(
+        &self,
+        _graph: &StackGraph,
+        _source: &Handle<Node>,
+        _sink: &Handle<Node>,
+    ) -> bool {
+        true
+    }
+
+    fn include_partial_path(
+        &self,
+        _graph: &StackGraph,
+        _paths: &PartialPaths,
+        _path: &PartialPath,
+    ) -> bool {
+        true
+    }
+}
+
+/// Filter implementation that includes the part of a graph around a range of lines in a file, for
+/// example to visualize the neighborhood of a single reference.
+///
+/// The filter includes the nodes of the file whose source span overlaps the lines, the nodes that
+/// are connected to them by an edge, and the root and jump to scope nodes.  Only the edges that
+/// start or end at a node in the lines are included.
+pub struct RegionFilter {
+    file: Handle<File>,
+    region: HashSet<Handle<Node>>,
+    nodes: HashSet<Handle<Node>>,
+}
+
+impl RegionFilter {
+    /// Creates a filter for a range of zero-based line numbers in a file.
+    pub fn new(graph: &StackGraph, file: Handle<File>, lines: Range<usize>) -> RegionFilter {
+        let region = graph
+            .nodes_for_file(file)
+            .filter(|node| {
+                graph.source_info(*node).is_some_and(|source_info| {
+                    let span = &source_info.span;
+                    *span != Span::default()
+                        && span.start.line < lines.end
+                        && lines.start <= span.end.line
+                })
+            })
+            .collect::<HashSet<_>>();
+        let mut nodes = region.clone();
+        nodes.insert(StackGraph::root_node());
+        nodes.insert(StackGraph::jump_to_node());
+        for source in graph
+            .nodes_for_file(file)
+            .chain(std::iter::once(StackGraph::root_node()))
+        {
+            for edge in graph.outgoing_edges(source) {
+                if region.contains(&edge.source) {
+                    nodes.insert(edge.sink);
+                }
+                if region.contains(&edge.sink) {
+                    nodes.insert(edge.source);
+                }
+            }
+        }
+        RegionFilter {
+            file,
+            region,
+            nodes,
+        }
+    }
+}
+
+impl Filter for RegionFilter {
+    fn include_file(&self, _graph: &StackGraph, file: &Handle<File>) -> bool {
+        *file == self.file
+    }
+
+    fn include_node(&self, _graph: &StackGraph, node: &Handle<Node>) -> bool {
+        self.nodes.contains(node)
+    }
+
+    fn include_edge(
+        &self,
+        _graph: &StackGraph,
+        source: &Handle<Node>,
+        sink: &Handle<Node>,
+    ) -> bool {
+        self.region.contains(source) || self.region.contains(sink)
+    }
+
+    fn include_partial_path(
+        &self,
+        _graph: &StackGraph,
+        _paths: &PartialPaths,
+        _path: &PartialPath,
+    ) -> bool {
+        true
+    }
+}
+
+/// Filter implementation that includes the elements that both filters include.
+pub struct And<A, B>(pub A, pub B);
+
+impl<A: Filter, B: Filter> Filter for And<A, B> {
+    fn include_file(&self, graph: &StackGraph, file: &Handle<File>) -> bool {
+        self.0.include_file(graph, file) && self.1.include_file(graph, file)
+    }
+
+    fn include_node(&self, graph: &StackGraph, node: &Handle<Node>) -> bool {
+        self.0.include_node(graph, node) && self.1.include_node(graph, node)
+    }
+
+    fn include_edge(&self, graph: &StackGraph, source: &Handle<Node>, sink: &Handle<Node>) -> bool {
+        self.0.include_edge(graph, source, sink) && self.1.include_edge(graph, source, sink)
+    }
+
+    fn include_partial_path(
+        &self,
+        graph: &StackGraph,
+        paths: &PartialPaths,
+        path: &PartialPath,
+    ) -> bool {
+        self.0.include_partial_path(graph, paths, path)
+            && self.1.include_partial_path(graph, paths, path)
+    }
+}
+
+/// Filter implementation that includes the elements that either filter includes.
+///
+/// Each element is checked against all implications of both filters, so that, for example, the
+/// nodes of a file that is only included by the first filter are not subject to the node checks
+/// of the second filter.
+pub struct Or<A, B>(pub A, pub B);
+
+impl<A: Filter, B: Filter> Filter for Or<A, B> {
+    fn include_file(&self, graph: &StackGraph, file: &Handle<File>) -> bool {
+        self.0.include_file(graph, file) || self.1.include_file(graph, file)
+    }
+
+    fn include_node(&self, graph: &StackGraph, node: &Handle<Node>) -> bool {
+        ImplicationFilter(&self.0).include_node(graph, node)
+            || ImplicationFilter(&self.1).include_node(graph, node)
+    }
+
+    fn include_edge(&self, graph: &StackGraph, source: &Handle<Node>, sink: &Handle<Node>) -> bool {
+        ImplicationFilter(&self.0).include_edge(graph, source, sink)
+            || ImplicationFilter(&self.1).include_edge(graph, source, sink)
+    }
+
+    fn include_partial_path(
+        &self,
+        graph: &StackGraph,
+        paths: &PartialPaths,
+        path: &PartialPath,
+    ) -> bool {
+        ImplicationFilter(&self.0).include_partial_path(graph, paths, path)
+            || ImplicationFilter(&self.1).include_partial_path(graph, paths, path)
+    }
+}
+
+/// Filter implementation that includes the nodes, edges, and paths that the wrapped filter
+/// excludes.
+///
+/// All files are included, because a file that the wrapped filter includes can still contain
+/// nodes that it excludes.  Note that nodes that do not belong to a file, such as the root node,
+/// are included by file filters, and are therefore excluded by their negation.
+pub struct Not<F>(pub F);
+
+impl<F: Filter> Filter for Not<F> {
+    fn include_file(&self, _graph: &StackGraph, _file: &Handle<File>) -> bool {
+        true
+    }
+
+    fn include_node(&self, graph: &StackGraph, node: &Handle<Node>) -> bool {
+        !ImplicationFilter(&self.0).include_node(graph, node)
+    }
+
+    fn include_edge(&self, graph: &StackGraph, source: &Handle<Node>, sink: &Handle<Node>) -> bool {
+        !ImplicationFilter(&self.0).include_edge(graph, source, sink)
+    }
+
+    fn include_partial_path(
+        &self,
+        graph: &StackGraph,
+        paths: &PartialPaths,
+        path: &PartialPath,
+    ) -> bool {
+        !ImplicationFilter(&self.0).include_partial_path(graph, paths, path)
+    }
+}
+
 /// Filter implementation that enforces all implications of another filter.
 /// For example, that nodes frome excluded files are not included, etc.
 pub(crate) struct ImplicationFilter<'a>(pub &'a dyn Filter);
@@ -5,6 +5,7 @@
 // Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
 // ------------------------------------------------------------------------------------------------
 
+use std::collections::HashMap;
 use thiserror::Error;
 
 use crate::arena::Handle;
@@ -60,6 +61,23 @@ impl StackGraph {
         Ok(())
     }
 
+    /// Renames a file, and updates the IDs of all nodes in that file.
+    #[cfg(feature = "storage")]
+    pub(crate) fn rename_file(&mut self, from: &str, to: &str) {
+        for file in &mut self.files.data {
+            if file == from {
+                *file = to.to_string();
+            }
+        }
+        for node in &mut self.nodes.data {
+            node.rename_file(from, to);
+        }
+        for edge in &mut self.edges.data {
+            edge.source.rename_file(from, to);
+            edge.sink.rename_file(from, to);
+        }
+    }
+
     fn load_files(&self, graph: &mut crate::graph::StackGraph) -> Result<(), Error> {
         for file in self.files.data.iter() {
             graph
@@ -159,6 +177,19 @@ impl StackGraph {
                         },
                     );
                 }
+
+                // load metadata of each node
+                if let Some(metadata) = node.metadata() {
+                    *graph.node_metadata_mut(handle) = metadata.data.iter().fold(
+                        crate::graph::Metadata::default(),
+                        |mut metadata, entry| {
+                            let key = graph.add_string(&entry.key);
+                            let value = graph.add_string(&entry.value);
+                            metadata.add(key, value);
+                            metadata
+                        },
+                    );
+                }
             }
         }
         Ok(())
@@ -202,6 +233,131 @@ impl StackGraph {
     }
 }
 
+impl StackGraph {
+    /// Normalizes the graph, so that graphs that only differ in the order in which nodes and edges
+    /// were added become equal.  The nodes of each file are ordered by their content, which is
+    /// their kind, symbol, source span, and flags, and get new local IDs in that order.  Nodes
+    /// with the same content keep their original order.  Files, nodes, and edges are sorted, as
+    /// are the entries of debug info and metadata.  Nodes without a file, and nodes that edges
+    /// refer to but that are not part of the graph, keep their IDs.
+    pub fn normalize(&mut self) {
+        self.files.data.sort();
+
+        let mut nodes = std::mem::take(&mut self.nodes.data);
+        nodes.sort_by(|a, b| {
+            a.id()
+                .file
+                .cmp(&b.id().file)
+                .then_with(|| a.content_key().cmp(&b.content_key()))
+        });
+        let mut local_ids = HashMap::new();
+        let mut next_local_id = HashMap::new();
+        for node in &nodes {
+            let id = node.id();
+            if let Some(file) = &id.file {
+                let next = next_local_id.entry(file.clone()).or_insert(0u32);
+                local_ids.insert((id.file.clone(), id.local_id), *next);
+                *next += 1;
+            }
+        }
+        let renumber = |id: &mut NodeID| {
+            if let Some(local_id) = local_ids.get(&(id.file.clone(), id.local_id)) {
+                id.local_id = *local_id;
+            }
+        };
+        for node in &mut nodes {
+            node.renumber(renumber);
+            node.sort_entries();
+        }
+        nodes.sort_by(|a, b| a.id().cmp_key().cmp(&b.id().cmp_key()));
+        self.nodes.data = nodes;
+
+        for edge in &mut self.edges.data {
+            renumber(&mut edge.source);
+            renumber(&mut edge.sink);
+            if let Some(debug_info) = &mut edge.debug_info {
+                debug_info.data.sort_by(|a, b| a.key.cmp(&b.key));
+            }
+        }
+        self.edges.data.sort_by(|a, b| {
+            (a.source.cmp_key(), a.sink.cmp_key(), a.precedence).cmp(&(
+                b.source.cmp_key(),
+                b.sink.cmp_key(),
+                b.precedence,
+            ))
+        });
+    }
+
+    /// Removes the debug info of all nodes and edges.
+    pub fn strip_debug_info(&mut self) {
+        for node in &mut self.nodes.data {
+            *node.debug_info_mut() = None;
+        }
+        for edge in &mut self.edges.data {
+            edge.debug_info = None;
+        }
+    }
+
+    /// Returns the files, nodes, and edges that are only in this graph, or only in the other
+    /// graph.  Both graphs should be [normalized][`StackGraph::normalize`] first, so that nodes
+    /// are identified by their content instead of by insertion order.
+    pub fn diff(&self, other: &Self) -> GraphDiff {
+        GraphDiff {
+            removed_files: difference(&self.files.data, &other.files.data),
+            added_files: difference(&other.files.data, &self.files.data),
+            removed_nodes: difference(&self.nodes.data, &other.nodes.data),
+            added_nodes: difference(&other.nodes.data, &self.nodes.data),
+            removed_edges: difference(&self.edges.data, &other.edges.data),
+            added_edges: difference(&other.edges.data, &self.edges.data),
+        }
+    }
+}
+
+/// Returns the elements of `left` that do not appear in `right`, counting duplicates.
+fn difference<T: Clone + PartialEq>(left: &[T], right: &[T]) -> Vec<T> {
+    let mut matched = vec![false; right.len()];
+    left.iter()
+        .filter(|l| {
+            match right
+                .iter()
+                .enumerate()
+                .position(|(i, r)| !matched[i] && *l == r)
+            {
+                Some(i) => {
+                    matched[i] = true;
+                    false
+                }
+                None => true,
+            }
+        })
+        .cloned()
+        .collect()
+}
+
+/// The differences between two graphs, as computed by [`StackGraph::diff`].  A node or edge that
+/// changed appears both as removed and as added.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct GraphDiff {
+    pub removed_files: Vec<String>,
+    pub added_files: Vec<String>,
+    pub removed_nodes: Vec<Node>,
+    pub added_nodes: Vec<Node>,
+    pub removed_edges: Vec<Edge>,
+    pub added_edges: Vec<Edge>,
+}
+
+impl GraphDiff {
+    /// Returns whether the graphs are equal.
+    pub fn is_empty(&self) -> bool {
+        self.removed_files.is_empty()
+            && self.added_files.is_empty()
+            && self.removed_nodes.is_empty()
+            && self.added_nodes.is_empty()
+            && self.removed_edges.is_empty()
+            && self.added_edges.is_empty()
+    }
+}
+
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 #[cfg_attr(
     feature = "serde",
@@ -237,12 +393,14 @@ pub enum Node {
         id: NodeID,
         source_info: Option<SourceInfo>,
         debug_info: Option<DebugInfo>,
+        metadata: Option<Metadata>,
     },
 
     JumpToScope {
         id: NodeID,
         source_info: Option<SourceInfo>,
         debug_info: Option<DebugInfo>,
+        metadata: Option<Metadata>,
     },
 
     PopScopedSymbol {
@@ -251,6 +409,7 @@ pub enum Node {
         is_definition: bool,
         source_info: Option<SourceInfo>,
         debug_info: Option<DebugInfo>,
+        metadata: Option<Metadata>,
     },
 
     PopSymbol {
@@ -259,6 +418,7 @@ pub enum Node {
         is_definition: bool,
         source_info: Option<SourceInfo>,
         debug_info: Option<DebugInfo>,
+        metadata: Option<Metadata>,
     },
 
     PushScopedSymbol {
@@ -268,6 +428,7 @@ pub enum Node {
         is_reference: bool,
         source_info: Option<SourceInfo>,
         debug_info: Option<DebugInfo>,
+        metadata: Option<Metadata>,
     },
 
     PushSymbol {
@@ -276,12 +437,14 @@ pub enum Node {
         is_reference: bool,
         source_info: Option<SourceInfo>,
         debug_info: Option<DebugInfo>,
+        metadata: Option<Metadata>,
     },
 
     Root {
         id: NodeID,
         source_info: Option<SourceInfo>,
         debug_info: Option<DebugInfo>,
+        metadata: Option<Metadata>,
     },
 
     Scope {
@@ -289,10 +452,132 @@ pub enum Node {
         is_exported: bool,
         source_info: Option<SourceInfo>,
         debug_info: Option<DebugInfo>,
+        metadata: Option<Metadata>,
     },
 }
 
 impl Node {
+    pub fn id(&self) -> &NodeID {
+        match self {
+            Self::DropScopes { id, .. }
+            | Self::JumpToScope { id, .. }
+            | Self::PopScopedSymbol { id, .. }
+            | Self::PopSymbol { id, .. }
+            | Self::PushScopedSymbol { id, .. }
+            | Self::PushSymbol { id, .. }
+            | Self::Root { id, .. }
+            | Self::Scope { id, .. } => id,
+        }
+    }
+
+    fn renumber<F: Fn(&mut NodeID)>(&mut self, renumber: F) {
+        match self {
+            Self::PushScopedSymbol { id, scope, .. } => {
+                renumber(id);
+                renumber(scope);
+            }
+            Self::DropScopes { id, .. }
+            | Self::JumpToScope { id, .. }
+            | Self::PopScopedSymbol { id, .. }
+            | Self::PopSymbol { id, .. }
+            | Self::PushSymbol { id, .. }
+            | Self::Root { id, .. }
+            | Self::Scope { id, .. } => renumber(id),
+        }
+    }
+
+    /// Returns a key that orders nodes by their content, ignoring their IDs.
+    fn content_key(&self) -> (u8, &str, [usize; 4], bool) {
+        let (kind, symbol, flag) = match self {
+            Self::DropScopes { .. } => (0, "", false),
+            Self::JumpToScope { .. } => (1, "", false),
+            Self::PopScopedSymbol {
+                symbol,
+                is_definition,
+                ..
+            } => (2, symbol.as_str(), *is_definition),
+            Self::PopSymbol {
+                symbol,
+                is_definition,
+                ..
+            } => (3, symbol.as_str(), *is_definition),
+            Self::PushScopedSymbol {
+                symbol,
+                is_reference,
+                ..
+            } => (4, symbol.as_str(), *is_reference),
+            Self::PushSymbol {
+                symbol,
+                is_reference,
+                ..
+            } => (5, symbol.as_str(), *is_reference),
+            Self::Root { .. } => (6, "", false),
+            Self::Scope { is_exported, .. } => (7, "", *is_exported),
+        };
+        let span = self.source_info().map_or([0; 4], |source_info| {
+            let span = &source_info.span;
+            [
+                span.start.line,
+                span.start.column.utf8_offset,
+                span.end.line,
+                span.end.column.utf8_offset,
+            ]
+        });
+        (kind, symbol, span, flag)
+    }
+
+    fn sort_entries(&mut self) {
+        if let Some(debug_info) = self.debug_info_mut() {
+            debug_info.data.sort_by(|a, b| a.key.cmp(&b.key));
+        }
+        if let Some(metadata) = self.metadata_mut() {
+            metadata.data.sort_by(|a, b| a.key.cmp(&b.key));
+        }
+    }
+
+    fn debug_info_mut(&mut self) -> &mut Option<DebugInfo> {
+        match self {
+            Self::DropScopes { debug_info, .. }
+            | Self::JumpToScope { debug_info, .. }
+            | Self::PopScopedSymbol { debug_info, .. }
+            | Self::PopSymbol { debug_info, .. }
+            | Self::PushScopedSymbol { debug_info, .. }
+            | Self::PushSymbol { debug_info, .. }
+            | Self::Root { debug_info, .. }
+            | Self::Scope { debug_info, .. } => debug_info,
+        }
+    }
+
+    fn metadata_mut(&mut self) -> &mut Option<Metadata> {
+        match self {
+            Self::DropScopes { metadata, .. }
+            | Self::JumpToScope { metadata, .. }
+            | Self::PopScopedSymbol { metadata, .. }
+            | Self::PopSymbol { metadata, .. }
+            | Self::PushScopedSymbol { metadata, .. }
+            | Self::PushSymbol { metadata, .. }
+            | Self::Root { metadata, .. }
+            | Self::Scope { metadata, .. } => metadata,
+        }
+    }
+
+    #[cfg(feature = "storage")]
+    fn rename_file(&mut self, from: &str, to: &str) {
+        match self {
+            Self::PushScopedSymbol { id, scope, .. } => {
+                id.rename_file(from, to);
+                scope.rename_file(from, to);
+            }
+            Self::DropScopes { id, .. }
+            | Self::JumpToScope { id, .. }
+            | Self::PopScopedSymbol { id, .. }
+            | Self::PopSymbol { id, .. }
+            | Self::PushSymbol { id, .. }
+            | Self::Root { id, .. }
+            | Self::Scope { id, .. } => id.rename_file(from, to),
+        }
+    }
+
     fn source_info(&self) -> Option<&SourceInfo> {
         match self {
             Self::DropScopes { source_info, .. } => source_info,
@@ -307,6 +592,20 @@ impl Node {
         .as_ref()
     }
 
+    fn metadata(&self) -> Option<&Metadata> {
+        match self {
+            Self::DropScopes { metadata, .. } => metadata,
+            Self::JumpToScope { metadata, .. } => metadata,
+            Self::PopScopedSymbol { metadata, .. } => metadata,
+            Self::PopSymbol { metadata, .. } => metadata,
+            Self::PushScopedSymbol { metadata, .. } => metadata,
+            Self::PushSymbol { metadata, .. } => metadata,
+            Self::Root { metadata, .. } => metadata,
+            Self::Scope { metadata, .. } => metadata,
+        }
+        .as_ref()
+    }
+
     fn debug_info(&self) -> Option<&DebugInfo> {
         match self {
             Self::DropScopes { debug_info, .. } => debug_info,
@@ -353,6 +652,25 @@ pub struct DebugEntry {
     pub value: String,
 }
 
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize, serde::Serialize),
+    serde(transparent)
+)]
+#[cfg_attr(feature = "bincode", derive(bincode::Encode, bincode::Decode))]
+pub struct Metadata {
+    pub data: Vec<MetadataEntry>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "bincode", derive(bincode::Encode, bincode::Decode))]
+pub struct MetadataEntry {
+    pub key: String,
+    pub value: String,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(
     feature = "serde",
@@ -391,6 +709,23 @@ impl NodeID {
         }
     }
 
+    #[cfg(feature = "storage")]
+    pub(crate) fn is_root(&self) -> bool {
+        self.file.is_none() && self.local_id == crate::graph::ROOT_NODE_ID
+    }
+
+    /// Returns a key that orders IDs by file, with nodes without a file first, and then by local ID.
+    fn cmp_key(&self) -> (Option<&str>, u32) {
+        (self.file.as_deref(), self.local_id)
+    }
+
+    #[cfg(feature = "storage")]
+    pub(crate) fn rename_file(&mut self, from: &str, to: &str) {
+        if self.file.as_deref() == Some(from) {
+            self.file = Some(to.to_string());
+        }
+    }
+
     pub fn from_node(graph: &crate::graph::StackGraph, handle: Handle<crate::graph::Node>) -> Self {
         Self::from_node_id(graph, graph[handle].id())
     }
@@ -440,6 +775,29 @@ pub struct Edge {
     pub debug_info: Option<DebugInfo>,
 }
 
+/// The [stable IDs][crate::graph::StableNodeID] of the nodes of a graph.  They are derived from the
+/// content of the graph, and are therefore not part of serialized graphs, but can be serialized
+/// separately for external systems that need to track nodes across re-indexes.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize, serde::Serialize),
+    serde(transparent)
+)]
+#[cfg_attr(feature = "bincode", derive(bincode::Encode, bincode::Decode))]
+pub struct StableNodeIDs {
+    pub data: Vec<StableNodeIDEntry>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "bincode", derive(bincode::Encode, bincode::Decode))]
+pub struct StableNodeIDEntry {
+    pub id: NodeID,
+    /// The stable ID, formatted as 16 hexadecimal digits.
+    pub stable_id: String,
+}
+
 impl crate::graph::StackGraph {
     pub fn to_serializable(&self) -> StackGraph {
         self.to_serializable_filter(&NoFilter)
@@ -449,6 +807,24 @@ impl crate::graph::StackGraph {
         crate::serde::StackGraph::from_graph_filter(self, f)
     }
 
+    /// Returns the stable IDs of the nodes that are included by the filter.  Stable IDs are not
+    /// part of serialized graphs, so they have to be requested separately.
+    pub fn to_serializable_stable_ids(&self, filter: &dyn Filter) -> StableNodeIDs {
+        let filter = ImplicationFilter(filter);
+        StableNodeIDs {
+            data: self
+                .iter_files()
+                .filter(|file| filter.include_file(self, file))
+                .flat_map(|file| self.stable_node_ids(file))
+                .filter(|(node, _)| filter.include_node(self, node))
+                .map(|(node, stable_id)| StableNodeIDEntry {
+                    id: NodeID::from_node(self, node),
+                    stable_id: stable_id.to_string(),
+                })
+                .collect(),
+        }
+    }
+
     fn filter_files<'a>(&self, filter: &'a dyn Filter) -> Files {
         Files {
             data: self
@@ -492,6 +868,24 @@ impl crate::graph::StackGraph {
         })
     }
 
+    fn filter_node_metadata(
+        &self,
+        _filter: &dyn Filter,
+        handle: Handle<crate::graph::Node>,
+    ) -> Option<Metadata> {
+        self.node_metadata(handle)
+            .filter(|metadata| !metadata.is_empty())
+            .map(|metadata| Metadata {
+                data: metadata
+                    .iter()
+                    .map(|entry| MetadataEntry {
+                        key: self[entry.key].to_owned(),
+                        value: self[entry.value].to_owned(),
+                    })
+                    .collect(),
+            })
+    }
+
     fn filter_nodes<'a>(&self, filter: &'a dyn Filter) -> Nodes {
         Nodes {
             data: self
@@ -502,17 +896,20 @@ impl crate::graph::StackGraph {
                     let id = self.filter_node(filter, node.id());
                     let source_info = self.filter_source_info(filter, handle);
                     let debug_info = self.filter_node_debug_info(filter, handle);
+                    let metadata = self.filter_node_metadata(filter, handle);
 
                     match node {
                         crate::graph::Node::DropScopes(_node) => Node::DropScopes {
                             id,
                             source_info,
                             debug_info,
+                            metadata,
                         },
                         crate::graph::Node::JumpTo(_node) => Node::JumpToScope {
                             id,
                             source_info,
                             debug_info,
+                            metadata,
                         },
                         crate::graph::Node::PopScopedSymbol(node) => Node::PopScopedSymbol {
                             id,
@@ -520,6 +917,7 @@ impl crate::graph::StackGraph {
                             is_definition: node.is_definition,
                             source_info,
                             debug_info,
+                            metadata,
                         },
                         crate::graph::Node::PopSymbol(node) => Node::PopSymbol {
                             id,
@@ -527,6 +925,7 @@ impl crate::graph::StackGraph {
                             is_definition: node.is_definition,
                             source_info,
                             debug_info,
+                            metadata,
                         },
                         crate::graph::Node::PushScopedSymbol(node) => Node::PushScopedSymbol {
                             id,
@@ -535,6 +934,7 @@ impl crate::graph::StackGraph {
                             is_reference: node.is_reference,
                             source_info,
                             debug_info,
+                            metadata,
                         },
                         crate::graph::Node::PushSymbol(node) => Node::PushSymbol {
                             id,
@@ -542,17 +942,20 @@ impl crate::graph::StackGraph {
                             is_reference: node.is_reference,
                             source_info,
                             debug_info,
+                            metadata,
                         },
                         crate::graph::Node::Root(_node) => Node::Root {
                             id,
                             source_info,
                             debug_info,
+                            metadata,
                         },
                         crate::graph::Node::Scope(node) => Node::Scope {
                             id,
                             is_exported: node.is_exported,
                             source_info,
                             debug_info,
+                            metadata,
                         },
                     }
                 })
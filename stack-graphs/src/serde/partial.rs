@@ -56,6 +56,38 @@ impl PartialPath {
         }
     }
 
+    /// Returns the symbol that this path looks up at the root node, if it ends at the root node.
+    #[cfg(feature = "storage")]
+    pub(crate) fn imported_symbol(&self) -> Option<&str> {
+        if !self.end_node.is_root() {
+            return None;
+        }
+        self.symbol_stack_postcondition.first_symbol()
+    }
+
+    /// Returns the symbol that this path defines at the root node, if it starts at the root node.
+    #[cfg(feature = "storage")]
+    pub(crate) fn exported_symbol(&self) -> Option<&str> {
+        if !self.start_node.is_root() {
+            return None;
+        }
+        self.symbol_stack_precondition.first_symbol()
+    }
+
+    /// Renames a file, and updates the IDs of all nodes in that file.
+    #[cfg(feature = "storage")]
+    pub(crate) fn rename_file(&mut self, from: &str, to: &str) {
+        self.start_node.rename_file(from, to);
+        self.end_node.rename_file(from, to);
+        self.symbol_stack_precondition.rename_file(from, to);
+        self.symbol_stack_postcondition.rename_file(from, to);
+        self.scope_stack_precondition.rename_file(from, to);
+        self.scope_stack_postcondition.rename_file(from, to);
+        for edge in &mut self.edges.edges {
+            edge.source.rename_file(from, to);
+        }
+    }
+
     pub fn to_partial_path(
         &self,
         graph: &mut crate::graph::StackGraph,
@@ -94,6 +126,13 @@ pub struct PartialScopeStack {
 }
 
 impl PartialScopeStack {
+    #[cfg(feature = "storage")]
+    fn rename_file(&mut self, from: &str, to: &str) {
+        for scope in &mut self.scopes {
+            scope.rename_file(from, to);
+        }
+    }
+
     pub fn from_partial_scope_stack(
         graph: &crate::graph::StackGraph,
         partials: &mut PartialPaths,
@@ -164,6 +203,20 @@ pub struct PartialSymbolStack {
 }
 
 impl PartialSymbolStack {
+    #[cfg(feature = "storage")]
+    fn first_symbol(&self) -> Option<&str> {
+        self.symbols.first().map(|symbol| symbol.symbol.as_str())
+    }
+
+    #[cfg(feature = "storage")]
+    fn rename_file(&mut self, from: &str, to: &str) {
+        for symbol in &mut self.symbols {
+            if let Some(scopes) = &mut symbol.scopes {
+                scopes.rename_file(from, to);
+            }
+        }
+    }
+
     pub fn from_partial_symbol_stack(
         graph: &crate::graph::StackGraph,
         partials: &mut PartialPaths,
@@ -12,6 +12,7 @@ use super::Error;
 use super::Filter;
 use super::ImplicationFilter;
 use super::NoFilter;
+use super::NodeID;
 use super::PartialPath;
 
 #[derive(PartialEq, Eq, Debug, Clone)]
@@ -66,6 +67,64 @@ impl Database {
     }
 }
 
+/// A self-contained serialization of a partial path database, together with the stack graph its
+/// paths refer to and the nodes that are marked as local in the database. Where a [`Database`][]
+/// can only be loaded into a graph that already contains the referenced nodes, a snapshot can be
+/// loaded into an empty graph, partial path arena, and database. This makes it possible to save a
+/// fully stitched database and reload it later without recomputing any paths.
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct DatabaseSnapshot {
+    pub graph: super::StackGraph,
+    pub paths: Database,
+    pub local_nodes: Vec<NodeID>,
+}
+
+impl DatabaseSnapshot {
+    pub fn from_database(
+        graph: &crate::graph::StackGraph,
+        partials: &mut PartialPaths,
+        value: &crate::stitching::Database,
+    ) -> Self {
+        Self::from_database_filter(graph, partials, value, &NoFilter)
+    }
+
+    pub fn from_database_filter(
+        graph: &crate::graph::StackGraph,
+        partials: &mut PartialPaths,
+        value: &crate::stitching::Database,
+        filter: &dyn Filter,
+    ) -> Self {
+        let local_filter = ImplicationFilter(filter);
+        let local_nodes = value
+            .local_nodes
+            .iter()
+            .filter(|node| local_filter.include_node(graph, node))
+            .map(|node| NodeID::from_node(graph, node))
+            .collect();
+        Self {
+            graph: super::StackGraph::from_graph_filter(graph, filter),
+            paths: Database::from_database_filter(graph, partials, value, filter),
+            local_nodes,
+        }
+    }
+
+    pub fn load_into(
+        &self,
+        graph: &mut crate::graph::StackGraph,
+        partials: &mut PartialPaths,
+        value: &mut crate::stitching::Database,
+    ) -> Result<(), Error> {
+        self.graph.load_into(graph)?;
+        self.paths.load_into(graph, partials, value)?;
+        for node in &self.local_nodes {
+            let node = node.to_node(graph)?;
+            value.mark_local_node(node);
+        }
+        Ok(())
+    }
+}
+
 impl crate::stitching::Database {
     pub fn to_serializable(&self, graph: &StackGraph, partials: &mut PartialPaths) -> Database {
         Database::from_database(graph, partials, self)
@@ -79,4 +138,12 @@ impl crate::stitching::Database {
     ) -> Database {
         Database::from_database_filter(graph, partials, self, filter)
     }
+
+    pub fn to_serializable_snapshot(
+        &self,
+        graph: &StackGraph,
+        partials: &mut PartialPaths,
+    ) -> DatabaseSnapshot {
+        DatabaseSnapshot::from_database(graph, partials, self)
+    }
 }
@@ -0,0 +1,139 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Collects statistics about values, such as the number of nodes or paths per file.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::AddAssign;
+
+/// Records how often each value occurs, so that the distribution of the values can be summarized
+/// by quantiles.
+#[derive(Clone, Debug)]
+pub struct FrequencyDistribution<X>
+where
+    X: Eq + Hash,
+{
+    values: HashMap<X, usize>,
+    count: usize,
+}
+
+impl<X> FrequencyDistribution<X>
+where
+    X: Eq + Hash,
+{
+    pub fn new() -> Self {
+        Self {
+            values: HashMap::new(),
+            count: 0,
+        }
+    }
+
+    /// Records an occurrence of the given value.
+    pub fn record(&mut self, value: X) {
+        *self.values.entry(value).or_default() += 1;
+        self.count += 1;
+    }
+
+    /// Returns the number of recorded occurrences.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Returns the number of distinct recorded values.
+    pub fn unique(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns the number of occurrences of the given value.
+    pub fn frequency(&self, value: &X) -> usize {
+        self.values.get(value).copied().unwrap_or(0)
+    }
+
+    /// Returns the distinct recorded values and the number of their occurrences, in no particular
+    /// order.
+    pub fn iter(&self) -> impl Iterator<Item = (&X, usize)> + '_ {
+        self.values.iter().map(|(value, count)| (value, *count))
+    }
+}
+
+impl<X> FrequencyDistribution<X>
+where
+    X: Eq + Hash + Ord,
+{
+    /// Returns the values at the given quantiles, which must be between 0 and 1.  The quantile 0
+    /// is the smallest value, and the quantile 1 the largest.  Returns an empty vector if no
+    /// values were recorded.
+    pub fn quantiles(&self, quantiles: &[f64]) -> Vec<&X> {
+        if self.count == 0 {
+            return Vec::new();
+        }
+        let mut values = self.values.iter().collect::<Vec<_>>();
+        values.sort_by_key(|(value, _)| *value);
+        quantiles
+            .iter()
+            .map(|quantile| {
+                assert!(
+                    (0.0..=1.0).contains(quantile),
+                    "quantile must be between 0 and 1"
+                );
+                let rank = ((quantile * self.count as f64).ceil() as usize).clamp(1, self.count);
+                let mut seen = 0;
+                for (value, count) in &values {
+                    seen += **count;
+                    if seen >= rank {
+                        return *value;
+                    }
+                }
+                unreachable!("rank is at most the number of recorded values")
+            })
+            .collect()
+    }
+
+    /// Returns the smallest recorded value.
+    pub fn min(&self) -> Option<&X> {
+        self.values.keys().min()
+    }
+
+    /// Returns the largest recorded value.
+    pub fn max(&self) -> Option<&X> {
+        self.values.keys().max()
+    }
+}
+
+impl<X> Default for FrequencyDistribution<X>
+where
+    X: Eq + Hash,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<X> AddAssign for FrequencyDistribution<X>
+where
+    X: Eq + Hash,
+{
+    fn add_assign(&mut self, rhs: Self) {
+        for (value, count) in rhs.values {
+            *self.values.entry(value).or_default() += count;
+        }
+        self.count += rhs.count;
+    }
+}
+
+impl<X> AddAssign<&Self> for FrequencyDistribution<X>
+where
+    X: Eq + Hash + Clone,
+{
+    fn add_assign(&mut self, rhs: &Self) {
+        for (value, count) in &rhs.values {
+            *self.values.entry(value.clone()).or_default() += count;
+        }
+        self.count += rhs.count;
+    }
+}
@@ -35,11 +35,15 @@
 //! [`Database`]: struct.Database.html
 //! [`PathStitcher`]: struct.PathStitcher.html
 
+use fxhash::FxHasher;
+use smallvec::SmallVec;
 use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::collections::VecDeque;
-#[cfg(feature = "copious-debugging")]
 use std::fmt::Display;
+use std::hash::Hash;
+use std::hash::Hasher;
 
 use crate::arena::Arena;
 use crate::arena::Handle;
@@ -61,6 +65,8 @@ use crate::partial::PartialPath;
 use crate::partial::PartialPaths;
 use crate::partial::PartialSymbolStack;
 use crate::paths::PathResolutionError;
+use crate::utils::hash_map_memory_usage;
+use crate::utils::vec_memory_usage;
 use crate::CancellationError;
 use crate::CancellationFlag;
 
@@ -202,6 +208,29 @@ where
     fn get_graph_partials_and_db(&mut self) -> (&StackGraph, &mut PartialPaths, &Db);
 }
 
+/// A candidates instance that can load candidates asynchronously, for example from a storage
+/// backend that is accessed over the network.  Implementations that do not override
+/// [`load_forward_candidates_async`][] load candidates synchronously, using
+/// [`ForwardCandidates::load_forward_candidates`][].
+///
+/// [`load_forward_candidates_async`]: #method.load_forward_candidates_async
+#[cfg(feature = "async")]
+pub trait AsyncForwardCandidates<H, A, Db, Err>: ForwardCandidates<H, A, Db, Err>
+where
+    A: Appendable,
+    Db: ToAppendable<H, A>,
+{
+    /// Load possible forward candidates for the given partial path into this candidates instance,
+    /// like [`ForwardCandidates::load_forward_candidates`][], but asynchronously.
+    fn load_forward_candidates_async(
+        &mut self,
+        path: &PartialPath,
+        cancellation_flag: &(dyn CancellationFlag + Sync),
+    ) -> impl std::future::Future<Output = Result<(), Err>> {
+        std::future::ready(self.load_forward_candidates(path, cancellation_flag))
+    }
+}
+
 //-------------------------------------------------------------------------------------------------
 // FileEdges
 
@@ -244,6 +273,9 @@ impl ForwardCandidates<Edge, Edge, GraphEdges, CancellationError> for GraphEdgeC
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncForwardCandidates<Edge, Edge, GraphEdges, CancellationError> for GraphEdgeCandidates<'_> {}
+
 /// A dummy type to act as the "database" for graph edges. Its [`ToAppendable`] implementation
 /// is the identity on edges.
 pub struct GraphEdges;
@@ -273,6 +305,10 @@ pub struct Database {
     symbol_stack_key_cache: HashMap<SymbolStackCacheKey, SymbolStackKeyHandle>,
     paths_by_start_node: SupplementalArena<Node, Vec<Handle<PartialPath>>>,
     root_paths_by_precondition: SupplementalArena<SymbolStackKeyCell, Vec<Handle<PartialPath>>>,
+    root_path_keys_by_first_symbol: SupplementalArena<Symbol, Vec<SymbolStackKeyHandle>>,
+    paths_by_key: HashMap<PartialPathKey, SmallVec<[Handle<PartialPath>; 1]>>,
+    add_counts: SupplementalArena<PartialPath, usize>,
+    stats: DatabaseStats,
 }
 
 impl Database {
@@ -285,29 +321,90 @@ impl Database {
             symbol_stack_key_cache: HashMap::new(),
             paths_by_start_node: SupplementalArena::new(),
             root_paths_by_precondition: SupplementalArena::new(),
+            root_path_keys_by_first_symbol: SupplementalArena::new(),
+            paths_by_key: HashMap::new(),
+            add_counts: SupplementalArena::new(),
+            stats: DatabaseStats::default(),
+        }
+    }
+
+    /// Returns the approximate number of bytes of memory used by this database.  This does not
+    /// include the contents of the partial paths, which are stored in a [`PartialPaths`][] arena.
+    pub fn memory_usage(&self) -> DatabaseMemoryUsage {
+        let paths_by_start_node = self
+            .paths_by_start_node
+            .iter()
+            .map(|(_, paths)| vec_memory_usage(paths))
+            .sum::<usize>();
+        let root_paths_by_precondition = self
+            .root_paths_by_precondition
+            .iter()
+            .map(|(_, paths)| vec_memory_usage(paths))
+            .sum::<usize>();
+        let root_path_keys_by_first_symbol = self
+            .root_path_keys_by_first_symbol
+            .iter()
+            .map(|(_, keys)| vec_memory_usage(keys))
+            .sum::<usize>();
+        DatabaseMemoryUsage {
+            partial_paths: self.partial_paths.memory_usage(),
+            local_nodes: self.local_nodes.memory_usage(),
+            symbol_stack_keys: self.symbol_stack_keys.memory_usage()
+                + hash_map_memory_usage(&self.symbol_stack_key_cache),
+            indices: self.paths_by_start_node.memory_usage()
+                + paths_by_start_node
+                + self.root_paths_by_precondition.memory_usage()
+                + root_paths_by_precondition
+                + self.root_path_keys_by_first_symbol.memory_usage()
+                + root_path_keys_by_first_symbol
+                + hash_map_memory_usage(&self.paths_by_key)
+                + self.add_counts.memory_usage(),
         }
     }
 
+    /// Returns the number of duplicate partial paths that were not added to this database.
+    pub fn stats(&self) -> DatabaseStats {
+        self.stats
+    }
+
     /// Clear the database.  After this, all previous handles into the database are
     /// invalid.
-    #[cfg_attr(not(feature = "storage"), allow(dead_code))]
-    pub(crate) fn clear(&mut self) {
+    pub fn clear(&mut self) {
         self.partial_paths.clear();
         self.local_nodes.clear();
         self.symbol_stack_keys.clear();
         self.symbol_stack_key_cache.clear();
         self.paths_by_start_node.clear();
         self.root_paths_by_precondition.clear();
+        self.root_path_keys_by_first_symbol.clear();
+        self.paths_by_key.clear();
+        self.add_counts.clear();
     }
 
-    /// Adds a partial path to this database.  We do not deduplicate partial paths in any way; it's
-    /// your responsibility to only add each partial path once.
+    /// Adds a partial path to this database.  If the database already contains a path with the
+    /// same content, the path is not added again, and the handle of the existing path is returned
+    /// instead.  The number of times each path was added is kept as its
+    /// [add count][Self::add_count].
     pub fn add_partial_path(
         &mut self,
         graph: &StackGraph,
         partials: &mut PartialPaths,
         path: PartialPath,
     ) -> Handle<PartialPath> {
+        let key = PartialPathKey::new(partials, &path);
+        let duplicate = self.paths_by_key.get(&key).and_then(|handles| {
+            handles.iter().copied().find(|handle| {
+                let other = self.partial_paths.get(*handle);
+                other.equals(partials, &path) && other.edges.equals(partials, path.edges)
+            })
+        });
+        if let Some(handle) = duplicate {
+            copious_debugging!("    Skip duplicate path {}", path.display(graph, partials));
+            self.add_counts[handle] += 1;
+            self.stats.duplicate_paths += 1;
+            return handle;
+        }
+
         let start_node = path.start_node;
         copious_debugging!(
             "    Add {} path to database {}",
@@ -320,9 +417,15 @@ impl Database {
         );
         let symbol_stack_precondition = path.symbol_stack_precondition;
         let handle = self.partial_paths.add(path);
+        self.paths_by_key.entry(key).or_default().push(handle);
+        self.add_counts[handle] = 1;
 
         // If the partial path starts at the root node, index it by its symbol stack precondition.
+        // Distinct preconditions are indexed by their first symbol as well, so that paths can be
+        // found by a prefix of their precondition without checking all of them.
         if graph[start_node].is_root() {
+            let mut first_symbol = symbol_stack_precondition;
+            let first_symbol = first_symbol.pop_front(partials).map(|symbol| symbol.symbol);
             // The join node is root, so there's no need to use half-open symbol stacks here, as we
             // do for [`PartialPath::concatenate`][].
             let key = SymbolStackKey::from_partial_symbol_stack(
@@ -330,9 +433,13 @@ impl Database {
                 self,
                 symbol_stack_precondition,
             );
-            if !key.is_empty() {
+            if let Some(first_symbol) = first_symbol {
                 let key_handle = key.back_handle();
-                self.root_paths_by_precondition[key_handle].push(handle);
+                let paths = &mut self.root_paths_by_precondition[key_handle];
+                if paths.is_empty() {
+                    self.root_path_keys_by_first_symbol[first_symbol].push(key_handle);
+                }
+                paths.push(handle);
             }
         } else {
             // Otherwise index it by its source node.
@@ -423,6 +530,63 @@ impl Database {
         }
     }
 
+    /// Find all partial paths in this database that start at the root node, and have a symbol
+    /// stack precondition that starts with the given prefix.  Unlike
+    /// [`find_candidate_partial_paths_from_root`][Self::find_candidate_partial_paths_from_root],
+    /// which finds the paths whose precondition is a prefix of a known symbol stack, this finds the
+    /// paths whose precondition extends a partially known symbol stack, for example to complete a
+    /// partially typed name.  If the prefix starts with a symbol, only the preconditions that
+    /// start with that symbol are checked.  Otherwise all of them are, which is slower.
+    #[cfg_attr(not(feature = "copious-debugging"), allow(unused_variables))]
+    pub fn find_candidate_partial_paths_from_root_with_prefix<R>(
+        &self,
+        graph: &StackGraph,
+        partials: &mut PartialPaths,
+        prefix: &SymbolStackPrefix,
+        result: &mut R,
+    ) where
+        R: std::iter::Extend<Handle<PartialPath>>,
+    {
+        copious_debugging!("      Search for symbol stack prefix {}", prefix);
+        let key_handles: Box<dyn Iterator<Item = SymbolStackKeyHandle>> = match prefix
+            .symbols
+            .first()
+        {
+            Some(Some(first_symbol)) => Box::new(
+                graph
+                    .get_symbol(first_symbol)
+                    .and_then(|first_symbol| self.root_path_keys_by_first_symbol.get(first_symbol))
+                    .into_iter()
+                    .flatten()
+                    .copied(),
+            ),
+            _ => Box::new(
+                self.root_paths_by_precondition
+                    .iter()
+                    .map(|(key_handle, _)| key_handle),
+            ),
+        };
+        for key_handle in key_handles {
+            let key = SymbolStackKey {
+                symbols: List::from_handle(key_handle),
+            };
+            if !prefix.matches(key.symbols(self).into_iter().map(|symbol| &graph[symbol])) {
+                continue;
+            }
+            let paths = &self.root_paths_by_precondition[key_handle];
+            #[cfg(feature = "copious-debugging")]
+            {
+                for path in paths {
+                    copious_debugging!(
+                        "        Found path {}",
+                        self[*path].display(graph, partials)
+                    );
+                }
+            }
+            result.extend(paths.iter().copied());
+        }
+    }
+
     /// Find all partial paths in the database that start at the given node.  We don't filter the
     /// results any further than that, since we have to check each partial path for compatibility
     /// as we try to append it to the current incomplete path anyway, and non-root nodes will
@@ -529,6 +693,13 @@ impl Database {
         self.local_nodes.contains(node)
     }
 
+    /// Returns the number of times a partial path was added to this database, counting the
+    /// duplicates that were not added again.  Paths cannot be removed from a database, so the
+    /// count never decreases, until the database is cleared.
+    pub fn add_count(&self, handle: Handle<PartialPath>) -> usize {
+        self.add_counts.get(handle).copied().unwrap_or_default()
+    }
+
     /// Returns an iterator over all of the handles of all of the partial paths in this database.
     /// (Note that because we're only returning _handles_, this iterator does not retain a
     /// reference to the `Database`.)
@@ -551,6 +722,75 @@ impl Database {
     }
 }
 
+/// Identifies the content of a partial path, so that a [`Database`][] can find duplicates of it.
+/// The content is hashed, so paths with equal keys still have to be compared.
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+struct PartialPathKey {
+    start_node: Handle<Node>,
+    end_node: Handle<Node>,
+    content: u64,
+}
+
+impl PartialPathKey {
+    fn new(partials: &mut PartialPaths, path: &PartialPath) -> Self {
+        let mut hasher = FxHasher::default();
+        for symbol_stack in [
+            path.symbol_stack_precondition,
+            path.symbol_stack_postcondition,
+        ] {
+            for symbol in symbol_stack.iter(partials) {
+                symbol.symbol.hash(&mut hasher);
+                symbol.scopes.is_some().hash(&mut hasher);
+            }
+            symbol_stack.variable().hash(&mut hasher);
+        }
+        for scope_stack in [
+            path.scope_stack_precondition,
+            path.scope_stack_postcondition,
+        ] {
+            scope_stack
+                .iter_scopes(partials)
+                .for_each(|scope| scope.hash(&mut hasher));
+            scope_stack.variable().hash(&mut hasher);
+        }
+        for edge in path.edges.iter(partials) {
+            edge.source_node_id.hash(&mut hasher);
+            edge.precedence.hash(&mut hasher);
+        }
+        Self {
+            start_node: path.start_node,
+            end_node: path.end_node,
+            content: hasher.finish(),
+        }
+    }
+}
+
+/// Counts the duplicate partial paths that were not added to a [`Database`][], as returned by
+/// [`Database::stats`][].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct DatabaseStats {
+    /// The number of partial paths that were not added because the database already contained
+    /// a path with the same content.
+    pub duplicate_paths: usize,
+}
+
+/// The approximate number of bytes of memory used by a [`Database`][], as returned by
+/// [`Database::memory_usage`][].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct DatabaseMemoryUsage {
+    pub partial_paths: usize,
+    pub local_nodes: usize,
+    pub symbol_stack_keys: usize,
+    pub indices: usize,
+}
+
+impl DatabaseMemoryUsage {
+    /// Returns the total number of bytes used by the database.
+    pub fn total(&self) -> usize {
+        self.partial_paths + self.local_nodes + self.symbol_stack_keys + self.indices
+    }
+}
+
 impl std::ops::Index<Handle<PartialPath>> for Database {
     type Output = PartialPath;
     #[inline(always)]
@@ -601,6 +841,218 @@ impl ForwardCandidates<Handle<PartialPath>, PartialPath, Database, CancellationE
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncForwardCandidates<Handle<PartialPath>, PartialPath, Database, CancellationError>
+    for DatabaseCandidates<'_>
+{
+}
+
+/// Returns the file that a partial path belongs to, which is the file of its first node that is
+/// not the singleton _root_ or _jump to scope_ node, or `None` if it has no such node.
+fn partial_path_file(
+    graph: &StackGraph,
+    partials: &PartialPaths,
+    path: &PartialPath,
+) -> Option<Handle<File>> {
+    graph[path.start_node]
+        .file()
+        .or_else(|| graph[path.end_node].file())
+        .or_else(|| {
+            path.edges
+                .iter_unordered(partials)
+                .find_map(|edge| edge.source_node_id.file())
+        })
+}
+
+/// Layers databases of partial paths over another candidates implementation.  For example, an
+/// editor can keep the partial paths of files with unsaved changes in an in-memory [`Database`][],
+/// and layer it over a [`SQLiteReader`][crate::storage::SQLiteReader] for the rest of the
+/// workspace.
+///
+/// Candidates are taken from the databases in the order in which they were added, followed by the
+/// candidates of the wrapped implementation.  Each database overlays the ones after it: a database
+/// covers the files that its partial paths belong to, and candidates of those files are ignored in
+/// the later databases and the wrapped implementation.  The graph and partial path arena of the
+/// wrapped implementation are used for stitching, so the partial paths in the layered databases
+/// must have been created using them.
+///
+/// Candidates are copied into a database owned by this instance, so that they can be referred to
+/// by a single kind of handle.  Each candidate is copied at most once.
+pub struct ChainedCandidates<'a, C> {
+    databases: Vec<&'a mut Database>,
+    candidates: C,
+    db: Database,
+    copied: HashMap<(usize, Handle<PartialPath>), Handle<PartialPath>>,
+    // The files covered by each database, computed when candidates are first requested.
+    covered_files: Vec<HashSet<Handle<File>>>,
+}
+
+impl<'a, C> ChainedCandidates<'a, C> {
+    /// Creates a new instance that wraps the given candidates implementation, without any layered
+    /// databases.
+    pub fn new(candidates: C) -> Self {
+        Self {
+            databases: Vec::new(),
+            candidates,
+            db: Database::new(),
+            copied: HashMap::new(),
+            covered_files: Vec::new(),
+        }
+    }
+
+    /// Adds a database, which is consulted after the previously added databases, but before the
+    /// wrapped candidates implementation.  Candidates of the files that the database covers are
+    /// ignored in the databases that are added after it, and in the wrapped implementation.
+    pub fn add_database(&mut self, database: &'a mut Database) {
+        self.databases.push(database);
+    }
+
+    /// Returns the wrapped candidates implementation.
+    pub fn into_inner(self) -> C {
+        self.candidates
+    }
+
+    /// Returns whether the file of the given partial path is covered by any of the first `layers`
+    /// databases.
+    fn is_covered(
+        covered_files: &[HashSet<Handle<File>>],
+        layers: usize,
+        graph: &StackGraph,
+        partials: &PartialPaths,
+        path: &PartialPath,
+    ) -> bool {
+        match partial_path_file(graph, partials, path) {
+            Some(file) => covered_files[..layers]
+                .iter()
+                .any(|files| files.contains(&file)),
+            None => false,
+        }
+    }
+
+    /// Copies a candidate of the given layer into the database of this instance, unless it was
+    /// copied before, and returns its handle in this database.
+    fn copy_candidate(
+        db: &mut Database,
+        copied: &mut HashMap<(usize, Handle<PartialPath>), Handle<PartialPath>>,
+        graph: &StackGraph,
+        partials: &mut PartialPaths,
+        layer: usize,
+        source: &Database,
+        handle: Handle<PartialPath>,
+    ) -> Handle<PartialPath> {
+        match copied.entry((layer, handle)) {
+            std::collections::hash_map::Entry::Occupied(entry) => *entry.get(),
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                *entry.insert(db.add_partial_path(graph, partials, source[handle].clone()))
+            }
+        }
+    }
+}
+
+impl<'a, C, Err> ForwardCandidates<Handle<PartialPath>, PartialPath, Database, Err>
+    for ChainedCandidates<'a, C>
+where
+    C: ForwardCandidates<Handle<PartialPath>, PartialPath, Database, Err>,
+{
+    fn load_forward_candidates(
+        &mut self,
+        path: &PartialPath,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<(), Err> {
+        self.candidates
+            .load_forward_candidates(path, cancellation_flag)
+    }
+
+    fn get_forward_candidates<R>(&mut self, path: &PartialPath, result: &mut R)
+    where
+        R: std::iter::Extend<Handle<PartialPath>>,
+    {
+        let mut wrapped_handles = Vec::new();
+        self.candidates
+            .get_forward_candidates(path, &mut wrapped_handles);
+        let (graph, partials, wrapped_db) = self.candidates.get_graph_partials_and_db();
+
+        for database in &self.databases[self.covered_files.len()..] {
+            let files = database
+                .iter_partial_paths()
+                .filter_map(|handle| partial_path_file(graph, partials, &database[handle]))
+                .collect();
+            self.covered_files.push(files);
+        }
+
+        let mut handles = Vec::new();
+        for (layer, database) in self.databases.iter_mut().enumerate() {
+            handles.clear();
+            database.find_candidate_partial_paths(graph, partials, path, &mut handles);
+            for handle in &handles {
+                if Self::is_covered(
+                    &self.covered_files,
+                    layer,
+                    graph,
+                    partials,
+                    &database[*handle],
+                ) {
+                    continue;
+                }
+                let handle = Self::copy_candidate(
+                    &mut self.db,
+                    &mut self.copied,
+                    graph,
+                    partials,
+                    layer,
+                    database,
+                    *handle,
+                );
+                result.extend(std::iter::once(handle));
+            }
+        }
+
+        let layer = self.databases.len();
+        for handle in wrapped_handles {
+            if Self::is_covered(
+                &self.covered_files,
+                layer,
+                graph,
+                partials,
+                &wrapped_db[handle],
+            ) {
+                continue;
+            }
+            let handle = Self::copy_candidate(
+                &mut self.db,
+                &mut self.copied,
+                graph,
+                partials,
+                layer,
+                wrapped_db,
+                handle,
+            );
+            result.extend(std::iter::once(handle));
+        }
+    }
+
+    fn get_graph_partials_and_db(&mut self) -> (&StackGraph, &mut PartialPaths, &Database) {
+        let (graph, partials, _) = self.candidates.get_graph_partials_and_db();
+        (graph, partials, &self.db)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<'a, C, Err> AsyncForwardCandidates<Handle<PartialPath>, PartialPath, Database, Err>
+    for ChainedCandidates<'a, C>
+where
+    C: AsyncForwardCandidates<Handle<PartialPath>, PartialPath, Database, Err>,
+{
+    fn load_forward_candidates_async(
+        &mut self,
+        path: &PartialPath,
+        cancellation_flag: &(dyn CancellationFlag + Sync),
+    ) -> impl std::future::Future<Output = Result<(), Err>> {
+        self.candidates
+            .load_forward_candidates_async(path, cancellation_flag)
+    }
+}
+
 /// The key type that we use to find partial paths that start from the root node and have a
 /// particular symbol stack as their precondition.
 #[derive(Clone, Copy)]
@@ -629,10 +1081,6 @@ impl SymbolStackKey {
         }
     }
 
-    fn is_empty(&self) -> bool {
-        self.symbols.is_empty()
-    }
-
     /// Pushes a new symbol onto the back of this symbol stack key.
     fn push_back(&mut self, db: &mut Database, symbol: Handle<Symbol>) {
         let cache_key = SymbolStackCacheKey {
@@ -668,6 +1116,16 @@ impl SymbolStackKey {
         result
     }
 
+    /// Returns the symbols of this key, from front to back.
+    fn symbols(mut self, db: &Database) -> Vec<Handle<Symbol>> {
+        let mut symbols = Vec::new();
+        while let Some(symbol) = self.pop_back(db) {
+            symbols.push(symbol);
+        }
+        symbols.reverse();
+        symbols
+    }
+
     /// Returns a handle to the back of the symbol stack key.
     fn back_handle(self) -> SymbolStackKeyHandle {
         // Because the symbols are stored in reverse order, the handle to the "front" of the list
@@ -705,9 +1163,165 @@ impl<'a> Display for DisplaySymbolStackKey<'a> {
     }
 }
 
+/// The start of a symbol stack, which is used to find partial paths from the root node whose
+/// symbol stack precondition starts with certain symbols.  For example, the prefix `<a,*>`, which
+/// has a wildcard as its second element, matches the preconditions `<a,b>` and `<a,.,c>`, but not
+/// `<a>` or `<b,.>`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SymbolStackPrefix {
+    /// The symbols that a symbol stack must start with, from front to back.  `None` is a wildcard
+    /// that matches any symbol.
+    pub symbols: Vec<Option<String>>,
+}
+
+impl SymbolStackPrefix {
+    /// Returns whether a symbol stack, given from front to back, starts with this prefix.
+    pub fn matches<'a, I>(&self, symbols: I) -> bool
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut symbols = symbols.into_iter();
+        self.symbols.iter().all(|expected| match symbols.next() {
+            Some(symbol) => expected.as_ref().is_none_or(|expected| expected == symbol),
+            None => false,
+        })
+    }
+}
+
+impl Display for SymbolStackPrefix {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "<")?;
+        for (idx, symbol) in self.symbols.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", symbol.as_deref().unwrap_or("*"))?;
+        }
+        write!(f, ">")
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Tracing
+
+/// Receives the events that a [`ForwardPartialPathStitcher`][] emits while it stitches partial
+/// paths together.  Tracing is meant for debugging why a reference resolves the way it does, and is
+/// disabled unless a tracer is set using [`ForwardPartialPathStitcher::set_tracer`][].
+pub trait StitchingTracer {
+    /// Called for each event.  The graph and partial path arena that the paths in the event belong
+    /// to are provided, so that the paths can be displayed.
+    fn trace(&mut self, graph: &StackGraph, partials: &mut PartialPaths, event: StitchingEvent);
+}
+
+/// An event that is emitted during path stitching.
+pub enum StitchingEvent<'a> {
+    /// A partial path is queued to be extended during the current phase.
+    PathQueued { path: &'a PartialPath },
+    /// Candidates for extending a partial path were found.
+    CandidatesFound { path: &'a PartialPath, count: usize },
+    /// A partial path was extended with a candidate.  The extended path is queued for the next
+    /// phase.
+    PathExtended {
+        path: &'a PartialPath,
+        extension: &'a dyn Appendable,
+        extended: &'a PartialPath,
+    },
+    /// A partial path, or an extension of it, is not extended any further.
+    PathRejected {
+        path: &'a PartialPath,
+        reason: RejectionReason<'a>,
+    },
+}
+
+/// The reason that a partial path is not extended any further.
+pub enum RejectionReason<'a> {
+    /// The path contains a disallowed cycle.
+    Cyclic,
+    /// The candidate cannot be appended to the path.
+    IncompatibleExtension {
+        extension: &'a dyn Appendable,
+        error: PathResolutionError,
+    },
+    /// The extended path is rejected because a similar path was already found.  The rejected
+    /// event carries the extended path.
+    SimilarPath,
+    /// The `extend_while` closure of [`ForwardPartialPathStitcher::process_next_phase`][]
+    /// returned false for the path.
+    NotExtended,
+}
+
+impl std::fmt::Display for RejectionReason<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Cyclic => write!(f, "cyclic"),
+            Self::IncompatibleExtension { error, .. } => {
+                write!(f, "incompatible extension: {:?}", error)
+            }
+            Self::SimilarPath => write!(f, "similar path"),
+            Self::NotExtended => write!(f, "not extended"),
+        }
+    }
+}
+
+fn trace(
+    tracer: &mut Option<Box<dyn StitchingTracer + Send>>,
+    graph: &StackGraph,
+    partials: &mut PartialPaths,
+    event: StitchingEvent,
+) {
+    if let Some(tracer) = tracer {
+        tracer.trace(graph, partials, event);
+    }
+}
+
 //-------------------------------------------------------------------------------------------------
 // Stitching partial paths together
 
+/// Determines which paths are considered similar during path stitching.  Only one of a set of
+/// similar paths is extended, which prevents an exponential blow up of the number of paths.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SimilarPathDetection {
+    /// Similar paths are not detected.
+    Off,
+    /// Paths are similar if they have the same start and end node, and equal pre- and
+    /// postconditions.  Of two similar paths, the one that shadows the other is kept.
+    Exact,
+    /// Paths are similar if they have the same start and end node, and pre- and postconditions of
+    /// the same lengths.  This prunes more paths than [`Exact`][Self::Exact] detection, at the
+    /// risk of pruning paths that lead to other definitions.
+    Approximate,
+}
+
+/// Configures how the forward partial path stitcher prunes paths.  See
+/// [`ForwardPartialPathStitcher::set_cyclicity_config`][].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CyclicityConfig {
+    /// The strategy used to detect similar paths.  Defaults to [`SimilarPathDetection::Exact`][].
+    pub similar_paths: SimilarPathDetection,
+    /// Paths with more edges than this are not checked for similarity.  Checking long paths is
+    /// expensive, and long paths are less likely to be similar to others.  Defaults to `None`,
+    /// which checks paths of any length.
+    pub max_similar_path_length: Option<usize>,
+}
+
+impl Default for CyclicityConfig {
+    fn default() -> Self {
+        Self {
+            similar_paths: SimilarPathDetection::Exact,
+            max_similar_path_length: None,
+        }
+    }
+}
+
+/// Counts the paths that were pruned by a [`ForwardPartialPathStitcher`][].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct StitchingStats {
+    /// The number of paths that were not extended because they contain a disallowed cycle.
+    pub cyclic_paths: usize,
+    /// The number of extensions that were rejected because a similar path was found before.
+    pub similar_paths: usize,
+}
+
 /// Implements a phased forward partial path stitching algorithm.
 ///
 /// Our overall goal is to start with a set of _seed_ partial paths, and to repeatedly extend each
@@ -745,7 +1359,10 @@ pub struct ForwardPartialPathStitcher<H> {
     next_iteration: (VecDeque<PartialPath>, VecDeque<AppendingCycleDetector<H>>),
     appended_paths: Appendables<H>,
     similar_path_detector: Option<SimilarPathDetector<PartialPath>>,
+    cyclicity: CyclicityConfig,
+    stats: StitchingStats,
     max_work_per_phase: usize,
+    tracer: Option<Box<dyn StitchingTracer + Send>>,
     #[cfg(feature = "copious-debugging")]
     phase_number: usize,
 }
@@ -777,8 +1394,11 @@ impl<H> ForwardPartialPathStitcher<H> {
             next_iteration,
             appended_paths,
             similar_path_detector: Some(SimilarPathDetector::new()),
+            cyclicity: CyclicityConfig::default(),
+            stats: StitchingStats::default(),
             // By default, there's no artificial bound on the amount of work done per phase
             max_work_per_phase: usize::MAX,
+            tracer: None,
             #[cfg(feature = "copious-debugging")]
             phase_number: 1,
         }
@@ -811,11 +1431,33 @@ impl<H: Clone> ForwardPartialPathStitcher<H> {
     /// can lead to exponential blow up during path stitching. Similar path detection is enabled by
     /// default.
     pub fn set_similar_path_detection(&mut self, detect_similar_paths: bool) {
-        if !detect_similar_paths {
+        self.set_cyclicity_config(CyclicityConfig {
+            similar_paths: if detect_similar_paths {
+                SimilarPathDetection::Exact
+            } else {
+                SimilarPathDetection::Off
+            },
+            ..self.cyclicity
+        });
+    }
+
+    /// Sets how paths are pruned during path stitching.  This gives more control over similar path
+    /// detection than [`set_similar_path_detection`][Self::set_similar_path_detection].
+    /// Changing the strategy during path stitching forgets the paths that were seen before.
+    pub fn set_cyclicity_config(&mut self, config: CyclicityConfig) {
+        if config.similar_paths == SimilarPathDetection::Off {
             self.similar_path_detector = None;
-        } else if self.similar_path_detector.is_none() {
+        } else if self.similar_path_detector.is_none()
+            || config.similar_paths != self.cyclicity.similar_paths
+        {
             self.similar_path_detector = Some(SimilarPathDetector::new());
         }
+        self.cyclicity = config;
+    }
+
+    /// Returns the number of paths that were pruned so far.
+    pub fn stats(&self) -> StitchingStats {
+        self.stats
     }
 
     /// Sets the maximum amount of work that can be performed during each phase of the algorithm.
@@ -827,6 +1469,17 @@ impl<H: Clone> ForwardPartialPathStitcher<H> {
         self.max_work_per_phase = max_work_per_phase;
     }
 
+    /// Sets the tracer that receives the events of the algorithm, or disables tracing if `None` is
+    /// given.  Tracing is disabled by default.
+    pub fn set_tracer(&mut self, tracer: Option<Box<dyn StitchingTracer + Send>>) {
+        self.tracer = tracer;
+    }
+
+    /// Removes the tracer from this stitcher and returns it, so that it can be reused.
+    pub fn take_tracer(&mut self) -> Option<Box<dyn StitchingTracer + Send>> {
+        self.tracer.take()
+    }
+
     /// Attempts to extend one partial path as part of the algorithm.  When calling this function,
     /// you are responsible for ensuring that `db` already contains all of the possible appendables
     /// that we might want to extend `partial_path` with.
@@ -865,12 +1518,34 @@ impl<H: Clone> ForwardPartialPathStitcher<H> {
         };
         if cyclic {
             copious_debugging!("      is discontinued: cyclic");
+            self.stats.cyclic_paths += 1;
+            trace(
+                &mut self.tracer,
+                graph,
+                partials,
+                StitchingEvent::PathRejected {
+                    path: partial_path,
+                    reason: RejectionReason::Cyclic,
+                },
+            );
             return 0;
         }
 
         // find candidates to append
         self.candidates.clear();
         candidates.get_forward_candidates(partial_path, &mut self.candidates);
+        if self.tracer.is_some() {
+            let (graph, partials, _) = candidates.get_graph_partials_and_db();
+            trace(
+                &mut self.tracer,
+                graph,
+                partials,
+                StitchingEvent::CandidatesFound {
+                    path: partial_path,
+                    count: self.candidates.len(),
+                },
+            );
+        }
 
         // try to extend path with candidates
         let extension_count = self.candidates.len();
@@ -889,17 +1564,38 @@ impl<H: Clone> ForwardPartialPathStitcher<H> {
             {
                 if let Err(err) = extension_path.append_to(graph, partials, &mut new_partial_path) {
                     copious_debugging!("        is invalid: {:?}", err);
+                    trace(
+                        &mut self.tracer,
+                        graph,
+                        partials,
+                        StitchingEvent::PathRejected {
+                            path: partial_path,
+                            reason: RejectionReason::IncompatibleExtension {
+                                extension: extension_path,
+                                error: err,
+                            },
+                        },
+                    );
                     continue;
                 }
                 copious_debugging!("        is {}", new_partial_path.display(graph, partials));
                 new_cycle_detector.append(&mut self.appended_paths, extension.clone());
-                if let Some(similar_path_detector) = &mut self.similar_path_detector {
+                let check_similar = self
+                    .cyclicity
+                    .max_similar_path_length
+                    .is_none_or(|max_length| new_partial_path.edges.len() <= max_length);
+                let approximate = self.cyclicity.similar_paths == SimilarPathDetection::Approximate;
+                if let Some(similar_path_detector) = self
+                    .similar_path_detector
+                    .as_mut()
+                    .filter(|_| check_similar)
+                {
                     if similar_path_detector.has_similar_path(
                         graph,
                         partials,
                         &new_partial_path,
                         |ps, left, right| {
-                            if !left.equals(ps, right) {
+                            if !approximate && !left.equals(ps, right) {
                                 None
                             } else {
                                 if left.shadows(ps, right) {
@@ -913,9 +1609,29 @@ impl<H: Clone> ForwardPartialPathStitcher<H> {
                         },
                     ) {
                         copious_debugging!("        is rejected: too many similar");
+                        self.stats.similar_paths += 1;
+                        trace(
+                            &mut self.tracer,
+                            graph,
+                            partials,
+                            StitchingEvent::PathRejected {
+                                path: &new_partial_path,
+                                reason: RejectionReason::SimilarPath,
+                            },
+                        );
                         continue;
                     }
                 }
+                trace(
+                    &mut self.tracer,
+                    graph,
+                    partials,
+                    StitchingEvent::PathExtended {
+                        path: partial_path,
+                        extension: extension_path,
+                        extended: &new_partial_path,
+                    },
+                );
             }
             self.next_iteration.0.push_back(new_partial_path);
             self.next_iteration.1.push_back(new_cycle_detector);
@@ -941,6 +1657,7 @@ impl<H: Clone> ForwardPartialPathStitcher<H> {
     /// or not. It is not called on the initial paths.
     ///
     /// [`previous_phase_partial_paths`]: #method.previous_phase_partial_paths
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
     pub fn process_next_phase<A, Db, C, E, Err>(&mut self, candidates: &mut C, extend_while: E)
     where
         A: Appendable,
@@ -949,12 +1666,23 @@ impl<H: Clone> ForwardPartialPathStitcher<H> {
         E: Fn(&StackGraph, &mut PartialPaths, &PartialPath) -> bool,
     {
         copious_debugging!("==> Start phase {}", self.phase_number);
-        self.queue.extend(
-            self.next_iteration
-                .0
-                .drain(..)
-                .zip(self.next_iteration.1.drain(..)),
-        );
+        let (graph, partials, _) = candidates.get_graph_partials_and_db();
+        for (partial_path, cycle_detector) in self
+            .next_iteration
+            .0
+            .drain(..)
+            .zip(self.next_iteration.1.drain(..))
+        {
+            trace(
+                &mut self.tracer,
+                graph,
+                partials,
+                StitchingEvent::PathQueued {
+                    path: &partial_path,
+                },
+            );
+            self.queue.push_back((partial_path, cycle_detector));
+        }
         let mut work_performed = 0;
         while let Some((partial_path, cycle_detector)) = self.queue.pop_front() {
             let (graph, partials, _) = candidates.get_graph_partials_and_db();
@@ -969,6 +1697,15 @@ impl<H: Clone> ForwardPartialPathStitcher<H> {
                     "    Do not extend {}",
                     partial_path.display(graph, partials)
                 );
+                trace(
+                    &mut self.tracer,
+                    graph,
+                    partials,
+                    StitchingEvent::PathRejected {
+                        path: &partial_path,
+                        reason: RejectionReason::NotExtended,
+                    },
+                );
                 continue;
             }
             work_performed += self.extend(candidates, &partial_path, cycle_detector);
@@ -1008,6 +1745,10 @@ impl ForwardPartialPathStitcher<Edge> {
     /// paths will not cover paths going through those edges.
     ///
     /// [`process_next_phase`]: #method.process_next_phase
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(file = graph[file].name()))
+    )]
     pub fn find_minimal_partial_path_set_in_file<F>(
         graph: &StackGraph,
         partials: &mut PartialPaths,
@@ -1048,6 +1789,81 @@ impl ForwardPartialPathStitcher<Edge> {
 }
 
 impl<H: Clone> ForwardPartialPathStitcher<H> {
+    /// Creates a new forward partial path stitcher that finds the complete partial paths that are
+    /// reachable from a set of starting nodes.  Nodes that are not references are ignored.  Use
+    /// [`next_batch`][] to find the paths incrementally.
+    ///
+    /// [`next_batch`]: #method.next_batch
+    pub fn from_starting_nodes<I>(
+        graph: &StackGraph,
+        partials: &mut PartialPaths,
+        starting_nodes: I,
+    ) -> Self
+    where
+        I: IntoIterator<Item = Handle<Node>>,
+    {
+        let initial_paths = starting_nodes
+            .into_iter()
+            .filter(|n| graph[*n].is_reference())
+            .map(|n| {
+                let mut p = PartialPath::from_node(graph, partials, n);
+                p.eliminate_precondition_stack_variables(partials);
+                p
+            })
+            .collect::<Vec<_>>();
+        Self::from_partial_paths(graph, partials, initial_paths)
+    }
+
+    /// Runs the next phase of the search for complete partial paths, and returns the complete
+    /// partial paths that were found during that phase.  Candidates for extending the paths of
+    /// the previous phase are loaded first.  Phases can find no complete paths, so call this
+    /// method until [`is_complete`][] returns true to find all paths.  Callers that only need
+    /// some of the paths can stop at any time, and can do other work between calls.  Combined
+    /// with [`set_max_work_per_phase`][], this bounds the work done by each call.
+    ///
+    /// [`is_complete`]: #method.is_complete
+    /// [`set_max_work_per_phase`]: #method.set_max_work_per_phase
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn next_batch<A, Db, C, Err>(
+        &mut self,
+        candidates: &mut C,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Vec<PartialPath>, Err>
+    where
+        A: Appendable,
+        Db: ToAppendable<H, A>,
+        C: ForwardCandidates<H, A, Db, Err>,
+        Err: std::convert::From<CancellationError>,
+    {
+        self.load_previous_phase_candidates(candidates, cancellation_flag)?;
+        self.process_next_phase(candidates, |_, _, _| true);
+        let (graph, _, _) = candidates.get_graph_partials_and_db();
+        Ok(self
+            .previous_phase_partial_paths()
+            .filter(|path| path.is_complete(graph))
+            .cloned()
+            .collect())
+    }
+
+    /// Loads the candidates for extending the partial paths of the previous phase.
+    fn load_previous_phase_candidates<A, Db, C, Err>(
+        &self,
+        candidates: &mut C,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<(), Err>
+    where
+        A: Appendable,
+        Db: ToAppendable<H, A>,
+        C: ForwardCandidates<H, A, Db, Err>,
+        Err: std::convert::From<CancellationError>,
+    {
+        cancellation_flag.check("finding complete partial paths")?;
+        for path in self.previous_phase_partial_paths() {
+            candidates.load_forward_candidates(path, cancellation_flag)?;
+        }
+        Ok(())
+    }
+
     /// Finds all complete partial paths that are reachable from a set of starting nodes,
     /// building them up by stitching together partial paths from this database, and calling
     /// the `visit` closure on each one.
@@ -1059,6 +1875,7 @@ impl<H: Clone> ForwardPartialPathStitcher<H> {
     /// [`process_next_phase`][] manually.
     ///
     /// [`process_next_phase`]: #method.process_next_phase
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     pub fn find_all_complete_partial_paths<I, F, A, Db, C, Err>(
         candidates: &mut C,
         starting_nodes: I,
@@ -1075,22 +1892,10 @@ impl<H: Clone> ForwardPartialPathStitcher<H> {
     {
         let mut stitcher = {
             let (graph, partials, _) = candidates.get_graph_partials_and_db();
-            let initial_paths = starting_nodes
-                .into_iter()
-                .filter(|n| graph[*n].is_reference())
-                .map(|n| {
-                    let mut p = PartialPath::from_node(graph, partials, n);
-                    p.eliminate_precondition_stack_variables(partials);
-                    p
-                })
-                .collect::<Vec<_>>();
-            ForwardPartialPathStitcher::from_partial_paths(graph, partials, initial_paths)
+            ForwardPartialPathStitcher::from_starting_nodes(graph, partials, starting_nodes)
         };
         while !stitcher.is_complete() {
-            cancellation_flag.check("finding complete partial paths")?;
-            for path in stitcher.previous_phase_partial_paths() {
-                candidates.load_forward_candidates(path, cancellation_flag)?;
-            }
+            stitcher.load_previous_phase_candidates(candidates, cancellation_flag)?;
             stitcher.process_next_phase(candidates, |_, _, _| true);
             let (graph, partials, _) = candidates.get_graph_partials_and_db();
             for path in stitcher.previous_phase_partial_paths() {
@@ -1101,4 +1906,223 @@ impl<H: Clone> ForwardPartialPathStitcher<H> {
         }
         Ok(())
     }
+
+    /// Finds all complete partial paths that are reachable from a set of starting nodes, like
+    /// [`find_all_complete_partial_paths`][], but reuses the paths that were found for earlier
+    /// queries using the same cache.  Paths that are found for a starting node that is not in the
+    /// cache yet are added to it.  This speeds up sessions that query the same references
+    /// repeatedly, such as language servers and batch cross-reference extraction.
+    ///
+    /// Paths are found separately for each starting node, so they may be visited in a different
+    /// order than by [`find_all_complete_partial_paths`][].
+    ///
+    /// [`find_all_complete_partial_paths`]: #method.find_all_complete_partial_paths
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn find_all_complete_partial_paths_with_cache<I, F, A, Db, C, Err>(
+        candidates: &mut C,
+        starting_nodes: I,
+        cache: &mut CompletePathCache,
+        cancellation_flag: &dyn CancellationFlag,
+        mut visit: F,
+    ) -> Result<(), Err>
+    where
+        I: IntoIterator<Item = Handle<Node>>,
+        A: Appendable,
+        Db: ToAppendable<H, A>,
+        C: ForwardCandidates<H, A, Db, Err>,
+        F: FnMut(&StackGraph, &mut PartialPaths, &PartialPath),
+        Err: std::convert::From<CancellationError>,
+    {
+        for node in starting_nodes {
+            let (graph, partials, _) = candidates.get_graph_partials_and_db();
+            if !graph[node].is_reference() {
+                continue;
+            }
+            let mut initial_path = PartialPath::from_node(graph, partials, node);
+            initial_path.eliminate_precondition_stack_variables(partials);
+            let precondition = initial_path.symbol_stack_precondition;
+            if let Some(paths) = cache.get(partials, node, precondition) {
+                for path in paths {
+                    visit(graph, partials, path);
+                }
+                continue;
+            }
+
+            let mut stitcher = Self::from_partial_paths(graph, partials, vec![initial_path]);
+            let mut paths = Vec::new();
+            while !stitcher.is_complete() {
+                paths.extend(stitcher.next_batch(candidates, cancellation_flag)?);
+            }
+            let (graph, partials, _) = candidates.get_graph_partials_and_db();
+            for path in &paths {
+                visit(graph, partials, path);
+            }
+            cache.insert(partials, node, precondition, paths);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl<H: Clone> ForwardPartialPathStitcher<H> {
+    /// Runs the next phase of the search for complete partial paths, like [`next_batch`][], but
+    /// awaits the loading of candidates.  The cancellation flag is checked before the candidates
+    /// of each path are loaded, and control is yielded to the executor at the end of the phase, so
+    /// that long searches do not block other tasks, and can be abandoned between phases.  The
+    /// cancellation flag must be `Sync`, so that the future is `Send` if the candidates are, and
+    /// can be spawned on multi-threaded executors.
+    ///
+    /// [`next_batch`]: #method.next_batch
+    pub async fn next_batch_async<A, Db, C, Err>(
+        &mut self,
+        candidates: &mut C,
+        cancellation_flag: &(dyn CancellationFlag + Sync),
+    ) -> Result<Vec<PartialPath>, Err>
+    where
+        A: Appendable,
+        Db: ToAppendable<H, A>,
+        C: AsyncForwardCandidates<H, A, Db, Err>,
+        Err: std::convert::From<CancellationError>,
+    {
+        cancellation_flag.check("finding complete partial paths")?;
+        for path in self.previous_phase_partial_paths() {
+            cancellation_flag.check("loading forward candidates")?;
+            candidates
+                .load_forward_candidates_async(path, cancellation_flag)
+                .await?;
+        }
+        self.process_next_phase(candidates, |_, _, _| true);
+        let (graph, _, _) = candidates.get_graph_partials_and_db();
+        let complete_paths = self
+            .previous_phase_partial_paths()
+            .filter(|path| path.is_complete(graph))
+            .cloned()
+            .collect();
+        YieldNow(false).await;
+        Ok(complete_paths)
+    }
+
+    /// Finds all complete partial paths that are reachable from a set of starting nodes, like
+    /// [`find_all_complete_partial_paths`][], but awaits the loading of candidates, as described
+    /// for [`next_batch_async`][].
+    ///
+    /// [`find_all_complete_partial_paths`]: #method.find_all_complete_partial_paths
+    /// [`next_batch_async`]: #method.next_batch_async
+    pub async fn find_all_complete_partial_paths_async<I, F, A, Db, C, Err>(
+        candidates: &mut C,
+        starting_nodes: I,
+        cancellation_flag: &(dyn CancellationFlag + Sync),
+        mut visit: F,
+    ) -> Result<(), Err>
+    where
+        I: IntoIterator<Item = Handle<Node>>,
+        A: Appendable,
+        Db: ToAppendable<H, A>,
+        C: AsyncForwardCandidates<H, A, Db, Err>,
+        F: FnMut(&StackGraph, &mut PartialPaths, &PartialPath),
+        Err: std::convert::From<CancellationError>,
+    {
+        let mut stitcher = {
+            let (graph, partials, _) = candidates.get_graph_partials_and_db();
+            ForwardPartialPathStitcher::from_starting_nodes(graph, partials, starting_nodes)
+        };
+        while !stitcher.is_complete() {
+            for path in stitcher
+                .next_batch_async(candidates, cancellation_flag)
+                .await?
+            {
+                let (graph, partials, _) = candidates.get_graph_partials_and_db();
+                visit(graph, partials, &path);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A future that returns control to the executor once, before it completes.
+#[cfg(feature = "async")]
+struct YieldNow(bool);
+
+#[cfg(feature = "async")]
+impl std::future::Future for YieldNow {
+    type Output = ();
+
+    fn poll(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<()> {
+        if self.0 {
+            return std::task::Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        std::task::Poll::Pending
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Caching complete paths
+
+/// Memoizes the complete partial paths that were found from a start node with a particular symbol
+/// stack precondition, so that they do not have to be stitched together again.  See
+/// [`ForwardPartialPathStitcher::find_all_complete_partial_paths_with_cache`][].
+///
+/// The cached paths refer to the stack graph and partial path arena that were used to find them,
+/// so a cache must only be used with those.  The cache does not notice when the partial paths that
+/// are available for stitching change, for example because files were indexed again, so it must be
+/// cleared when that happens.
+#[derive(Default)]
+pub struct CompletePathCache {
+    paths: HashMap<Handle<Node>, Vec<(PartialSymbolStack, Vec<PartialPath>)>>,
+}
+
+impl CompletePathCache {
+    /// Creates a new, empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the cached complete paths for the given start node and symbol stack precondition,
+    /// or `None` if they have not been cached.
+    pub fn get(
+        &self,
+        partials: &mut PartialPaths,
+        start_node: Handle<Node>,
+        symbol_stack_precondition: PartialSymbolStack,
+    ) -> Option<&[PartialPath]> {
+        self.paths
+            .get(&start_node)?
+            .iter()
+            .find(|(precondition, _)| precondition.equals(partials, symbol_stack_precondition))
+            .map(|(_, paths)| paths.as_slice())
+    }
+
+    /// Caches the complete paths for the given start node and symbol stack precondition, replacing
+    /// any paths that were cached for them before.
+    pub fn insert(
+        &mut self,
+        partials: &mut PartialPaths,
+        start_node: Handle<Node>,
+        symbol_stack_precondition: PartialSymbolStack,
+        paths: Vec<PartialPath>,
+    ) {
+        let entries = self.paths.entry(start_node).or_default();
+        match entries
+            .iter_mut()
+            .find(|(precondition, _)| precondition.equals(partials, symbol_stack_precondition))
+        {
+            Some((_, cached)) => *cached = paths,
+            None => entries.push((symbol_stack_precondition, paths)),
+        }
+    }
+
+    /// Removes all cached paths.
+    pub fn clear(&mut self) {
+        self.paths.clear();
+    }
+
+    /// Returns whether no paths are cached.
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
 }
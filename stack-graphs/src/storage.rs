@@ -10,58 +10,356 @@ use bincode::error::EncodeError;
 use rusqlite::functions::FunctionFlags;
 use rusqlite::types::ValueRef;
 use rusqlite::Connection;
+use rusqlite::ErrorCode;
+use rusqlite::OpenFlags;
 use rusqlite::OptionalExtension;
 use rusqlite::Params;
 use rusqlite::Statement;
+use rusqlite::TransactionBehavior;
+use sha1::Digest;
+use sha1::Sha1;
+use std::collections::BTreeSet;
 use std::collections::HashSet;
+use std::ops::Deref;
+use std::ops::DerefMut;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
 use thiserror::Error;
 
 use crate::arena::Handle;
 use crate::graph::File;
 use crate::graph::Node;
+use crate::graph::StableNodeID;
 use crate::graph::StackGraph;
 use crate::partial::PartialPath;
 use crate::partial::PartialPaths;
 use crate::partial::PartialSymbolStack;
 use crate::serde;
 use crate::serde::FileFilter;
+#[cfg(feature = "async")]
+use crate::stitching::AsyncForwardCandidates;
 use crate::stitching::Database;
 use crate::stitching::ForwardCandidates;
+use crate::stitching::SymbolStackPrefix;
 use crate::CancellationError;
 use crate::CancellationFlag;
+use crate::NoCancellation;
 
-const VERSION: usize = 5;
+const VERSION: usize = 12;
+
+/// A migration that upgrades a database from one version to the next.
+struct Migration {
+    /// The version that this migration upgrades from.
+    from: usize,
+    /// Upgrades the database.  It is called inside a transaction.
+    migrate: fn(&Connection) -> Result<()>,
+}
+
+/// The migrations that upgrade older databases to the current version.  Databases are upgraded by
+/// applying migrations in order, starting at the database version.  Databases with versions that
+/// are not reachable through these migrations cannot be upgraded.
+///
+/// Migrations can rewrite stored graphs and paths, but cannot convert serialized values between
+/// incompatible formats.  There are therefore no migrations from versions before such a format
+/// change, such as version 5, which did not have node metadata.  Opening those databases fails
+/// with [`StorageError::IncompatibleVersion`][], instead of discarding their stored results, and
+/// the files must be indexed again in a new database.
+const MIGRATIONS: &[Migration] = &[
+    // Version 7 added the symbols table.
+    Migration {
+        from: 6,
+        migrate: |conn| {
+            conn.execute_batch(SYMBOLS_SCHEMA)?;
+            let mut stmt = conn.prepare("SELECT file, value FROM graphs WHERE error IS NULL")?;
+            let mut rows = stmt.query([])?;
+            while let Some(row) = rows.next()? {
+                let file = row.get::<_, String>(0)?;
+                let value = row.get::<_, Vec<u8>>(1)?;
+                let (file_graph, _): (serde::StackGraph, usize) =
+                    bincode::decode_from_slice(&value, BINCODE_CONFIG)?;
+                let mut graph = StackGraph::new();
+                file_graph.load_into(&mut graph)?;
+                if let Some(file) = graph.get_file(&file) {
+                    SQLiteWriter::store_symbols_for_file_inner(conn, &graph, file)?;
+                }
+            }
+            Ok(())
+        },
+    },
+    // Version 8 stores graphs and paths by content hash.
+    Migration {
+        from: 7,
+        migrate: |conn| {
+            // Keep references to the graphs table in other tables when renaming it.
+            conn.execute_batch(
+                r#"
+                    PRAGMA legacy_alter_table = ON;
+                    ALTER TABLE graphs RENAME TO graphs_v7;
+                    ALTER TABLE file_paths RENAME TO file_paths_v7;
+                    ALTER TABLE root_paths RENAME TO root_paths_v7;
+                    PRAGMA legacy_alter_table = OFF;
+                "#,
+            )?;
+            conn.execute_batch(RESULTS_SCHEMA)?;
+            let mut graph_stmt = conn.prepare("SELECT file, tag, error, value FROM graphs_v7")?;
+            let mut node_stmt =
+                conn.prepare("SELECT local_id, value FROM file_paths_v7 WHERE file = ?")?;
+            let mut root_stmt =
+                conn.prepare("SELECT symbol_stack, value FROM root_paths_v7 WHERE file = ?")?;
+            let mut rows = graph_stmt.query([])?;
+            while let Some(row) = rows.next()? {
+                let file = row.get::<_, String>(0)?;
+                let tag = row.get::<_, String>(1)?;
+                let error = row.get::<_, Option<String>>(2)?;
+                let value = row.get::<_, Vec<u8>>(3)?;
+                let (mut file_graph, _): (serde::StackGraph, usize) =
+                    bincode::decode_from_slice(&value, BINCODE_CONFIG)?;
+                let (hash, is_new) = store_content(conn, &mut file_graph, &file, None)?;
+                conn.execute(
+                    "INSERT INTO graphs (file, tag, error, hash) VALUES (?, ?, ?, ?)",
+                    (&file, &tag, &error, &hash),
+                )?;
+                if !is_new {
+                    continue;
+                }
+                let mut paths = node_stmt.query([&file])?;
+                while let Some(path) = paths.next()? {
+                    let local_id = path.get::<_, u32>(0)?;
+                    let value = path.get::<_, Vec<u8>>(1)?;
+                    conn.execute(
+                        "INSERT INTO file_paths (hash, local_id, value) VALUES (?, ?, ?)",
+                        (&hash, local_id, reencode_path(&value, &file)?),
+                    )?;
+                }
+                let mut paths = root_stmt.query([&file])?;
+                while let Some(path) = paths.next()? {
+                    let symbol_stack = path.get::<_, String>(0)?;
+                    let value = path.get::<_, Vec<u8>>(1)?;
+                    conn.execute(
+                        "INSERT INTO root_paths (hash, symbol_stack, value) VALUES (?, ?, ?)",
+                        (&hash, &symbol_stack, reencode_path(&value, &file)?),
+                    )?;
+                }
+            }
+            conn.execute_batch(
+                r#"
+                    DROP TABLE graphs_v7;
+                    DROP TABLE file_paths_v7;
+                    DROP TABLE root_paths_v7;
+                "#,
+            )?;
+            Ok(())
+        },
+    },
+    // Version 9 added the errors table.
+    Migration {
+        from: 8,
+        migrate: |conn| {
+            conn.execute_batch(ERRORS_SCHEMA)?;
+            conn.execute(
+                "INSERT INTO errors (file, kind, message) SELECT file, ?, error FROM graphs WHERE error IS NOT NULL",
+                [UNKNOWN_ERROR_KIND],
+            )?;
+            Ok(())
+        },
+    },
+    // Version 10 added the sources table.
+    Migration {
+        from: 9,
+        migrate: |conn| {
+            conn.execute_batch(SOURCES_SCHEMA)?;
+            Ok(())
+        },
+    },
+    // Version 11 added node and edge counts of stored graphs, and the time files were indexed.
+    Migration {
+        from: 10,
+        migrate: |conn| {
+            conn.execute_batch(SUMMARY_SCHEMA)?;
+            let mut stmt = conn.prepare("SELECT hash, value FROM contents")?;
+            let mut rows = stmt.query([])?;
+            while let Some(row) = rows.next()? {
+                let hash = row.get::<_, String>(0)?;
+                let value = decompress(row.get::<_, Vec<u8>>(1)?)?;
+                let (graph, _): (serde::StackGraph, usize) =
+                    bincode::decode_from_slice(&value, BINCODE_CONFIG)?;
+                store_content_counts(conn, &hash, &graph)?;
+            }
+            Ok(())
+        },
+    },
+    // Version 12 added imported and exported symbols to the symbols table.
+    Migration {
+        from: 11,
+        migrate: |conn| {
+            let mut file_stmt =
+                conn.prepare("SELECT file, hash FROM graphs WHERE error IS NULL")?;
+            let mut paths_stmt = conn.prepare(
+                r#"
+                    SELECT value FROM file_paths WHERE hash = ?1
+                    UNION ALL
+                    SELECT value FROM root_paths WHERE hash = ?1
+                "#,
+            )?;
+            let mut rows = file_stmt.query([])?;
+            while let Some(row) = rows.next()? {
+                let file = row.get::<_, String>(0)?;
+                let hash = row.get::<_, String>(1)?;
+                let mut paths = Vec::new();
+                let mut values = paths_stmt.query([&hash])?;
+                while let Some(value) = values.next()? {
+                    let value = decompress(value.get::<_, Vec<u8>>(0)?)?;
+                    let (path, _): (serde::PartialPath, usize) =
+                        bincode::decode_from_slice(&value, BINCODE_CONFIG)?;
+                    paths.push(path);
+                }
+                let mut symbols = BTreeSet::new();
+                for path in &paths {
+                    if let Some(symbol) = path.imported_symbol() {
+                        symbols.insert((symbol, IMPORT_KIND));
+                    }
+                    if let Some(symbol) = path.exported_symbol() {
+                        symbols.insert((symbol, EXPORT_KIND));
+                    }
+                }
+                store_symbols(conn, &file, symbols)?;
+            }
+            Ok(())
+        },
+    },
+];
+
+/// Converts a stored path of the given file from the version 7 format, which uses the file name,
+/// to the content format.
+fn reencode_path(value: &[u8], file: &str) -> Result<Vec<u8>> {
+    let (mut path, _): (serde::PartialPath, usize) =
+        bincode::decode_from_slice(value, BINCODE_CONFIG)?;
+    path.rename_file(file, CONTENT_FILE);
+    Ok(bincode::encode_to_vec(&path, BINCODE_CONFIG)?)
+}
 
 const SCHEMA: &str = r#"
         CREATE TABLE metadata (
             version INTEGER NOT NULL
         ) STRICT;
+    "#;
+
+/// Graphs and paths are stored by the hash of their content, so that files with the same content,
+/// such as vendored copies of a dependency, share them.
+const RESULTS_SCHEMA: &str = r#"
         CREATE TABLE graphs (
             file   TEXT PRIMARY KEY,
             tag    TEXT NOT NULL,
             error  TEXT,
+            hash   TEXT NOT NULL
+        ) STRICT;
+        CREATE TABLE contents (
+            hash   TEXT PRIMARY KEY,
             value  BLOB NOT NULL
         ) STRICT;
         CREATE TABLE file_paths (
-            file     TEXT NOT NULL,
+            hash     TEXT NOT NULL,
             local_id INTEGER NOT NULL,
             value    BLOB NOT NULL,
-            FOREIGN KEY(file) REFERENCES graphs(file)
+            FOREIGN KEY(hash) REFERENCES contents(hash)
         ) STRICT;
         CREATE TABLE root_paths (
-            file         TEXT NOT NULL,
+            hash         TEXT NOT NULL,
             symbol_stack TEXT NOT NULL,
             value        BLOB NOT NULL,
+            FOREIGN KEY(hash) REFERENCES contents(hash)
+        ) STRICT;
+    "#;
+
+/// The file name used in stored graphs and paths.  Because these are shared by all files with the
+/// same content, the actual file name is substituted when they are loaded.
+const CONTENT_FILE: &str = "";
+
+const SYMBOLS_SCHEMA: &str = r#"
+        CREATE TABLE symbols (
+            file   TEXT NOT NULL,
+            symbol TEXT NOT NULL,
+            kind   TEXT NOT NULL,
+            FOREIGN KEY(file) REFERENCES graphs(file)
+        ) STRICT;
+    "#;
+
+/// The kind of symbols in the symbols table that are defined in a file.
+const DEFINITION_KIND: &str = "definition";
+/// The kind of symbols in the symbols table that are referenced in a file.
+const REFERENCE_KIND: &str = "reference";
+/// The kind of symbols in the symbols table that partial paths of a file look up at the root
+/// node, and that can therefore be defined by other files.
+const IMPORT_KIND: &str = "import";
+/// The kind of symbols in the symbols table that partial paths of a file define at the root node,
+/// and that can therefore be used by other files.
+const EXPORT_KIND: &str = "export";
+
+/// Structured errors of files for which indexing failed.  Spans are zero-based, and absent if the
+/// error has no location in the file.
+const ERRORS_SCHEMA: &str = r#"
+        CREATE TABLE errors (
+            file         TEXT PRIMARY KEY,
+            kind         TEXT NOT NULL,
+            message      TEXT NOT NULL,
+            start_line   INTEGER,
+            start_column INTEGER,
+            end_line     INTEGER,
+            end_column   INTEGER,
+            FOREIGN KEY(file) REFERENCES graphs(file)
+        ) STRICT;
+    "#;
+
+/// The kind of errors that are stored without a kind.
+const UNKNOWN_ERROR_KIND: &str = "unknown";
+
+/// Source text of indexed files, which is only stored on request.  Values are always compressed.
+const SOURCES_SCHEMA: &str = r#"
+        CREATE TABLE sources (
+            file   TEXT PRIMARY KEY,
+            value  BLOB NOT NULL,
             FOREIGN KEY(file) REFERENCES graphs(file)
         ) STRICT;
     "#;
 
+/// Counts of stored graphs, and the time at which files were indexed, so that the stored data of
+/// files can be summarized without decoding it.  Times are seconds since the Unix epoch, and are
+/// absent for files that were indexed before version 11.
+const SUMMARY_SCHEMA: &str = r#"
+        ALTER TABLE contents ADD COLUMN node_count INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE contents ADD COLUMN edge_count INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE graphs ADD COLUMN indexed_at INTEGER;
+    "#;
+
+/// Query that selects the rows expected by [`FileErrors`][].
+const ERRORS_QUERY: &str = r#"
+        SELECT errors.file, graphs.tag, errors.kind, errors.message,
+               errors.start_line, errors.start_column, errors.end_line, errors.end_column
+        FROM errors JOIN graphs ON errors.file = graphs.file
+    "#;
+
+/// The columns of the tables, which are listed explicitly when rows are copied between databases,
+/// so that copying does not depend on the order of the columns in either database.
+const GRAPHS_COLUMNS: &str = "file, tag, error, hash, indexed_at";
+const CONTENTS_COLUMNS: &str = "hash, value, node_count, edge_count";
+const FILE_PATHS_COLUMNS: &str = "hash, local_id, value";
+const ROOT_PATHS_COLUMNS: &str = "hash, symbol_stack, value";
+const SYMBOLS_COLUMNS: &str = "file, symbol, kind";
+const ERRORS_COLUMNS: &str = "file, kind, message, start_line, start_column, end_line, end_column";
+const SOURCES_COLUMNS: &str = "file, value";
+
 const INDEXES: &str = r#"
         CREATE INDEX IF NOT EXISTS idx_graphs_file ON graphs(file);
-        CREATE INDEX IF NOT EXISTS idx_file_paths_local_id ON file_paths(file, local_id);
+        CREATE INDEX IF NOT EXISTS idx_graphs_hash ON graphs(hash);
+        CREATE INDEX IF NOT EXISTS idx_file_paths_local_id ON file_paths(hash, local_id);
+        CREATE INDEX IF NOT EXISTS idx_root_paths_hash ON root_paths(hash);
         CREATE INDEX IF NOT EXISTS idx_root_paths_symbol_stack ON root_paths(symbol_stack);
+        CREATE INDEX IF NOT EXISTS idx_symbols_symbol ON symbols(symbol, kind);
+        CREATE INDEX IF NOT EXISTS idx_symbols_file ON symbols(file);
     "#;
 
 const PRAGMAS: &str = r#"
@@ -76,8 +374,10 @@ pub static BINCODE_CONFIG: bincode::config::Configuration = bincode::config::sta
 pub enum StorageError {
     #[error("cancelled at {0}")]
     Cancelled(&'static str),
-    #[error("unsupported database version {0}")]
-    IncorrectVersion(usize),
+    #[error("database is locked by another connection")]
+    Contended,
+    #[error("incompatible database version {database}, supported version is {supported}")]
+    IncompatibleVersion { database: usize, supported: usize },
     #[error("database does not exist {0}")]
     MissingDatabase(String),
     #[error(transparent)]
@@ -88,10 +388,30 @@ pub enum StorageError {
     SerializeFail(#[from] EncodeError),
     #[error(transparent)]
     DeserializeFail(#[from] DecodeError),
+    #[error(transparent)]
+    Compression(#[from] std::io::Error),
 }
 
 pub type Result<T> = std::result::Result<T, StorageError>;
 
+impl StorageError {
+    /// Reports SQLite busy errors, which are returned if another connection holds a lock on the
+    /// database for longer than the busy timeout, as contention.
+    fn or_contended(self) -> Self {
+        match &self {
+            Self::Rusqlite(err)
+                if matches!(
+                    err.sqlite_error_code(),
+                    Some(ErrorCode::DatabaseBusy) | Some(ErrorCode::DatabaseLocked)
+                ) =>
+            {
+                Self::Contended
+            }
+            _ => self,
+        }
+    }
+}
+
 impl From<CancellationError> for StorageError {
     fn from(value: CancellationError) -> Self {
         Self::Cancelled(value.0)
@@ -143,56 +463,400 @@ impl<'a, P: Params + Clone> Files<'a, P> {
     }
 }
 
+/// The counts and sizes of the data stored for a file, which show how much the file contributes
+/// to the size of the database and the cost of queries.
+pub struct FileSummary {
+    pub path: PathBuf,
+    pub tag: String,
+    pub status: FileStatus,
+    /// The time at which the file was indexed, if it was indexed with a version that records it.
+    pub indexed_at: Option<SystemTime>,
+    /// The number of nodes in the stored graph that belong to the file.
+    pub node_count: usize,
+    /// The number of edges in the stored graph.
+    pub edge_count: usize,
+    /// The number of stored partial paths that start at a node in the file.
+    pub node_path_count: usize,
+    /// The number of stored partial paths that start at the root node.
+    pub root_path_count: usize,
+    /// The size in bytes of the stored graph, after compression.
+    pub graph_size: usize,
+    /// The total size in bytes of the stored partial paths that start at a node in the file,
+    /// after compression.
+    pub node_paths_size: usize,
+    /// The total size in bytes of the stored partial paths that start at the root node, after
+    /// compression.
+    pub root_paths_size: usize,
+    /// The size in bytes of the compressed source, if it is stored.
+    pub source_size: Option<usize>,
+    /// The number of files, including this one, that have the same content, and therefore share
+    /// the stored graph and partial paths.
+    pub content_file_count: usize,
+}
+
+impl FileSummary {
+    /// The total size in bytes of the data stored for the file.  Data that is shared with other
+    /// files with the same content is included.
+    pub fn total_size(&self) -> usize {
+        self.graph_size
+            + self.node_paths_size
+            + self.root_paths_size
+            + self.source_size.unwrap_or(0)
+    }
+}
+
+/// A structured error of a file for which indexing failed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FileError {
+    /// The kind of error, such as `parse` or `timeout`.  Errors that were stored without a kind
+    /// have the kind `unknown`.
+    pub kind: String,
+    /// The error message.
+    pub message: String,
+    /// The location of the error in the file, if it has one.
+    pub span: Option<FileErrorSpan>,
+}
+
+impl FileError {
+    /// Creates an error of the given kind without a span.
+    pub fn new<K: Into<String>, M: Into<String>>(kind: K, message: M) -> Self {
+        Self {
+            kind: kind.into(),
+            message: message.into(),
+            span: None,
+        }
+    }
+
+    /// Returns this error with the given span.
+    pub fn with_span(mut self, span: FileErrorSpan) -> Self {
+        self.span = Some(span);
+        self
+    }
+}
+
+/// The location of an error in a file.  Lines and columns are zero-based.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FileErrorSpan {
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+/// An error entry in the database.
+pub struct FileErrorEntry {
+    pub path: PathBuf,
+    pub tag: String,
+    pub error: FileError,
+}
+
+/// An iterator over a query returning rows with
+/// (path,tag,kind,message,start_line,start_column,end_line,end_column) tuples.
+pub struct FileErrors<'a, P: Params>(Statement<'a>, P);
+
+impl<'a, P: Params + Clone> FileErrors<'a, P> {
+    pub fn try_iter<'b>(&'b mut self) -> Result<impl Iterator<Item = Result<FileErrorEntry>> + 'b> {
+        let entries = self.0.query_map(self.1.clone(), |r| {
+            let span = match (
+                r.get::<_, Option<usize>>(4)?,
+                r.get::<_, Option<usize>>(5)?,
+                r.get::<_, Option<usize>>(6)?,
+                r.get::<_, Option<usize>>(7)?,
+            ) {
+                (Some(start_line), Some(start_column), Some(end_line), Some(end_column)) => {
+                    Some(FileErrorSpan {
+                        start_line,
+                        start_column,
+                        end_line,
+                        end_column,
+                    })
+                }
+                _ => None,
+            };
+            Ok(FileErrorEntry {
+                path: PathBuf::from(r.get::<_, String>(0)?),
+                tag: r.get::<_, String>(1)?,
+                error: FileError {
+                    kind: r.get::<_, String>(2)?,
+                    message: r.get::<_, String>(3)?,
+                    span,
+                },
+            })
+        })?;
+        let entries = entries.map(|r| -> Result<FileErrorEntry> { Ok(r?) });
+        Ok(entries)
+    }
+}
+
+/// An inconsistency in the database, found by [`SQLiteReader::verify`][].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DatabaseProblem {
+    /// The table that contains the inconsistent data.
+    pub table: &'static str,
+    /// The file or content hash of the inconsistent rows.
+    pub key: String,
+    pub message: String,
+}
+
+impl DatabaseProblem {
+    fn new<K: Into<String>, M: Into<String>>(table: &'static str, key: K, message: M) -> Self {
+        Self {
+            table,
+            key: key.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for DatabaseProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}: {}", self.table, self.key, self.message)
+    }
+}
+
+/// The journal mode of a SQLite database.  See the [SQLite documentation][] for details.
+///
+/// [SQLite documentation]: https://www.sqlite.org/pragma.html#pragma_journal_mode
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum JournalMode {
+    Delete,
+    Truncate,
+    Persist,
+    Memory,
+    Wal,
+    Off,
+}
+
+impl JournalMode {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Delete => "DELETE",
+            Self::Truncate => "TRUNCATE",
+            Self::Persist => "PERSIST",
+            Self::Memory => "MEMORY",
+            Self::Wal => "WAL",
+            Self::Off => "OFF",
+        }
+    }
+}
+
+/// The synchronous level of a SQLite database, which trades durability for write speed.  See the
+/// [SQLite documentation][] for details.
+///
+/// [SQLite documentation]: https://www.sqlite.org/pragma.html#pragma_synchronous
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Synchronous {
+    Off,
+    Normal,
+    Full,
+    Extra,
+}
+
+impl Synchronous {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Off => "OFF",
+            Self::Normal => "NORMAL",
+            Self::Full => "FULL",
+            Self::Extra => "EXTRA",
+        }
+    }
+}
+
+/// Options to tune the write performance of a [`SQLiteWriter`][].
+#[derive(Clone, Debug)]
+pub struct SQLiteWriterOptions {
+    /// The journal mode of the database.  The default is [`JournalMode::Wal`][], which allows
+    /// readers to read the database while it is being written.
+    pub journal_mode: JournalMode,
+    /// The synchronous level of the database.  The default is [`Synchronous::Full`][].
+    pub synchronous: Synchronous,
+    /// How long to wait for a lock held by another connection, such as another indexer writing
+    /// to the same database, before failing with [`StorageError::Contended`][].  If `None`, the
+    /// default timeout of the SQLite bindings, five seconds, is used.
+    pub busy_timeout: Option<Duration>,
+    /// The number of write operations, such as storing the results for a file, that are committed
+    /// together in a single transaction.  The default is 1, which commits every operation
+    /// immediately.  Larger batches improve write throughput, but pending operations are not
+    /// visible to other connections until they are committed, and are lost if the process is
+    /// interrupted.  Pending operations are committed by [`SQLiteWriter::flush`][], and when the
+    /// writer is dropped.
+    pub batch_size: usize,
+    /// The zstd compression level of stored graphs and paths, or `None` to store them
+    /// uncompressed, which is the default.  Compression makes databases considerably smaller at
+    /// the cost of write throughput.  Values are decompressed transparently when they are read,
+    /// and databases can contain both compressed and uncompressed values.
+    pub compression_level: Option<i32>,
+}
+
+impl Default for SQLiteWriterOptions {
+    fn default() -> Self {
+        Self {
+            journal_mode: JournalMode::Wal,
+            synchronous: Synchronous::Full,
+            busy_timeout: None,
+            batch_size: 1,
+            compression_level: None,
+        }
+    }
+}
+
 /// Writer to store stack graphs and partial paths in a SQLite database.
 pub struct SQLiteWriter {
-    conn: Connection,
+    // The connection is only taken by `into_reader`, so that it is not flushed again on drop.
+    conn: Option<Connection>,
+    compression_level: Option<i32>,
+    batch_size: usize,
+    pending: usize,
 }
 
 impl SQLiteWriter {
     /// Open an in-memory database.
     pub fn open_in_memory() -> Result<Self> {
         let mut conn = Connection::open_in_memory()?;
-        Self::init(&mut conn)?;
+        Self::init_or_migrate(&mut conn)?;
         init_indexes(&mut conn)?;
-        Ok(Self { conn })
+        Ok(Self {
+            conn: Some(conn),
+            compression_level: None,
+            batch_size: 1,
+            pending: 0,
+        })
     }
 
-    /// Open a file database.  If the file does not exist, it is automatically created.
-    /// An error is returned if the database version is not supported.
+    /// Open a file database.  If the file does not exist, it is automatically created.  Databases
+    /// with an older version are upgraded.  An error is returned if the database version is not
+    /// supported.
+    ///
+    /// Several writers, in this or other processes, can write to the same database.  Each write
+    /// operation locks the database, and other writers wait for the lock until the busy timeout
+    /// set in [`SQLiteWriterOptions`][] expires, after which they fail with
+    /// [`StorageError::Contended`][].
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let is_new = !path.as_ref().exists();
+        Self::open_with_options(path, SQLiteWriterOptions::default())
+    }
+
+    /// Open a file database with the given options.  If the file does not exist, it is
+    /// automatically created.  Databases with an older version are upgraded.  An error is returned
+    /// if the database version is not supported.
+    pub fn open_with_options<P: AsRef<Path>>(
+        path: P,
+        options: SQLiteWriterOptions,
+    ) -> Result<Self> {
         let mut conn = Connection::open(path)?;
-        set_pragmas_and_functions(&conn)?;
-        if is_new {
-            Self::init(&mut conn)?;
-        } else {
-            check_version(&conn)?;
+        if let Some(busy_timeout) = options.busy_timeout {
+            conn.busy_timeout(busy_timeout)?;
         }
-        init_indexes(&mut conn)?;
-        Ok(Self { conn })
+        set_pragmas_and_functions(&conn).map_err(StorageError::or_contended)?;
+        conn.pragma_update(None, "journal_mode", options.journal_mode.as_str())
+            .map_err(|e| StorageError::from(e).or_contended())?;
+        conn.pragma_update(None, "synchronous", options.synchronous.as_str())?;
+        Self::init_or_migrate(&mut conn).map_err(StorageError::or_contended)?;
+        init_indexes(&mut conn).map_err(StorageError::or_contended)?;
+        Ok(Self {
+            conn: Some(conn),
+            compression_level: options.compression_level,
+            batch_size: options.batch_size.max(1),
+            pending: 0,
+        })
+    }
+
+    /// Runs a write operation.  Each operation is atomic.  Operations are committed in batches
+    /// of the configured size.  Busy errors are reported as [`StorageError::Contended`][].
+    fn write<T, F>(&mut self, f: F) -> Result<T>
+    where
+        F: FnOnce(&Connection) -> Result<T>,
+    {
+        self.write_inner(f).map_err(StorageError::or_contended)
+    }
+
+    fn write_inner<T, F>(&mut self, f: F) -> Result<T>
+    where
+        F: FnOnce(&Connection) -> Result<T>,
+    {
+        // Write transactions are started immediately, so that they wait for locks held by other
+        // writers, instead of failing when they first write.
+        if self.batch_size == 1 {
+            let tx = self
+                .conn_mut()
+                .transaction_with_behavior(TransactionBehavior::Immediate)?;
+            let result = f(&tx)?;
+            tx.commit()?;
+            return Ok(result);
+        }
+        if self.conn().is_autocommit() {
+            self.conn().execute_batch("BEGIN IMMEDIATE")?;
+        }
+        // A savepoint ensures that a failing operation does not leave partial results in the
+        // batch.  It is rolled back when it is dropped without being committed.
+        let sp = self.conn_mut().savepoint()?;
+        let result = f(&sp)?;
+        sp.commit()?;
+        self.pending += 1;
+        if self.pending >= self.batch_size {
+            self.flush()?;
+        }
+        Ok(result)
+    }
+
+    /// Commits all pending write operations.  This is only necessary when writes are batched.
+    /// Pending operations are also committed by [`into_reader`][Self::into_reader] and when the
+    /// writer is dropped, but errors can only be logged there.  Call this method first to handle
+    /// them.
+    pub fn flush(&mut self) -> Result<()> {
+        if !self.conn().is_autocommit() {
+            self.conn()
+                .execute_batch("COMMIT")
+                .map_err(|e| StorageError::from(e).or_contended())?;
+        }
+        self.pending = 0;
+        Ok(())
     }
 
-    /// Create database tables and write metadata.
-    fn init(conn: &mut Connection) -> Result<()> {
-        let tx = conn.transaction()?;
-        tx.execute_batch(SCHEMA)?;
-        tx.execute("INSERT INTO metadata (version) VALUES (?)", [VERSION])?;
+    /// Create database tables and write metadata if the database is new, or upgrade it otherwise.
+    /// This happens in a single immediate transaction, so that concurrent writers opening the
+    /// same database do not both initialize it.
+    fn init_or_migrate(conn: &mut Connection) -> Result<()> {
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let is_new = !tx
+            .prepare("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'metadata'")?
+            .exists([])?;
+        if is_new {
+            tx.execute_batch(SCHEMA)?;
+            tx.execute_batch(RESULTS_SCHEMA)?;
+            tx.execute_batch(SYMBOLS_SCHEMA)?;
+            tx.execute_batch(ERRORS_SCHEMA)?;
+            tx.execute_batch(SOURCES_SCHEMA)?;
+            tx.execute_batch(SUMMARY_SCHEMA)?;
+            tx.execute("INSERT INTO metadata (version) VALUES (?)", [VERSION])?;
+        } else {
+            migrate_inner(&tx)?;
+        }
         tx.commit()?;
         Ok(())
     }
 
     /// Clean all data from the database.
     pub fn clean_all(&mut self) -> Result<usize> {
-        let tx = self.conn.transaction()?;
-        let count = Self::clean_all_inner(&tx)?;
-        tx.commit()?;
-        Ok(count)
+        self.write(Self::clean_all_inner)
     }
 
     /// Clean all data from the database.
     ///
     /// This is an inner method, which does not wrap individual SQL statements in a transaction.
     fn clean_all_inner(conn: &Connection) -> Result<usize> {
+        {
+            let mut stmt = conn.prepare_cached("DELETE FROM symbols")?;
+            stmt.execute([])?;
+        }
+        {
+            let mut stmt = conn.prepare_cached("DELETE FROM errors")?;
+            stmt.execute([])?;
+        }
+        {
+            let mut stmt = conn.prepare_cached("DELETE FROM sources")?;
+            stmt.execute([])?;
+        }
         {
             let mut stmt = conn.prepare_cached("DELETE FROM file_paths")?;
             stmt.execute([])?;
@@ -201,6 +865,10 @@ impl SQLiteWriter {
             let mut stmt = conn.prepare_cached("DELETE FROM root_paths")?;
             stmt.execute([])?;
         }
+        {
+            let mut stmt = conn.prepare_cached("DELETE FROM contents")?;
+            stmt.execute([])?;
+        }
         let count = {
             let mut stmt = conn.prepare_cached("DELETE FROM graphs")?;
             stmt.execute([])?
@@ -211,10 +879,7 @@ impl SQLiteWriter {
     /// Clean file data from the database.  If recursive is true, data for all descendants of
     /// that file is cleaned.
     pub fn clean_file(&mut self, file: &Path) -> Result<usize> {
-        let tx = self.conn.transaction()?;
-        let count = Self::clean_file_inner(&tx, file)?;
-        tx.commit()?;
-        Ok(count)
+        self.write(|conn| Self::clean_file_inner(conn, file))
     }
 
     /// Clean file data from the database.
@@ -223,27 +888,39 @@ impl SQLiteWriter {
     fn clean_file_inner(conn: &Connection, file: &Path) -> Result<usize> {
         let file = file.to_string_lossy();
         {
-            let mut stmt = conn.prepare_cached("DELETE FROM file_paths WHERE file=?")?;
+            let mut stmt = conn.prepare_cached("DELETE FROM symbols WHERE file=?")?;
+            stmt.execute([&file])?;
+        }
+        {
+            let mut stmt = conn.prepare_cached("DELETE FROM errors WHERE file=?")?;
             stmt.execute([&file])?;
         }
         {
-            let mut stmt = conn.prepare_cached("DELETE FROM root_paths WHERE file=?")?;
+            let mut stmt = conn.prepare_cached("DELETE FROM sources WHERE file=?")?;
             stmt.execute([&file])?;
         }
+        let hash = {
+            let mut stmt = conn.prepare_cached("SELECT hash FROM graphs WHERE file=?")?;
+            stmt.query_row([&file], |r| r.get::<_, String>(0))
+                .optional()?
+        };
         let count = {
             let mut stmt = conn.prepare_cached("DELETE FROM graphs WHERE file=?")?;
             stmt.execute([&file])?
         };
+        if let Some(hash) = hash {
+            let mut stmt = conn.prepare_cached("SELECT 1 FROM graphs WHERE hash=? LIMIT 1")?;
+            if !stmt.exists([&hash])? {
+                Self::clean_content_inner(conn, &hash)?;
+            }
+        }
         Ok(count)
     }
 
     /// Clean file or directory data from the database.  Data for all decendants of the given path
     /// is cleaned.
     pub fn clean_file_or_directory(&mut self, file_or_directory: &Path) -> Result<usize> {
-        let tx = self.conn.transaction()?;
-        let count = Self::clean_file_or_directory_inner(&tx, file_or_directory)?;
-        tx.commit()?;
-        Ok(count)
+        self.write(|conn| Self::clean_file_or_directory_inner(conn, file_or_directory))
     }
 
     /// Clean file or directory data from the database.  Data for all decendants of the given path
@@ -254,12 +931,17 @@ impl SQLiteWriter {
         let file_or_directory = file_or_directory.to_string_lossy();
         {
             let mut stmt =
-                conn.prepare_cached("DELETE FROM file_paths WHERE path_descendant_of(file, ?)")?;
+                conn.prepare_cached("DELETE FROM symbols WHERE path_descendant_of(file, ?)")?;
             stmt.execute([&file_or_directory])?;
         }
         {
             let mut stmt =
-                conn.prepare_cached("DELETE FROM root_paths WHERE path_descendant_of(file, ?)")?;
+                conn.prepare_cached("DELETE FROM errors WHERE path_descendant_of(file, ?)")?;
+            stmt.execute([&file_or_directory])?;
+        }
+        {
+            let mut stmt =
+                conn.prepare_cached("DELETE FROM sources WHERE path_descendant_of(file, ?)")?;
             stmt.execute([&file_or_directory])?;
         }
         let count = {
@@ -267,17 +949,125 @@ impl SQLiteWriter {
                 conn.prepare_cached("DELETE FROM graphs WHERE path_descendant_of(file, ?)")?;
             stmt.execute([&file_or_directory])?
         };
+        Self::clean_unused_contents_inner(conn)?;
         Ok(count)
     }
 
-    /// Store an error, indicating that indexing this file failed.
-    pub fn store_error_for_file(&mut self, file: &Path, tag: &str, error: &str) -> Result<()> {
-        let tx = self.conn.transaction()?;
-        Self::store_error_for_file_inner(&tx, file, tag, error)?;
-        tx.commit()?;
+    /// Clean the content with the given hash, and the paths stored for it, from the database.
+    ///
+    /// This is an inner method, which does not wrap individual SQL statements in a transaction.
+    fn clean_content_inner(conn: &Connection, hash: &str) -> Result<()> {
+        {
+            let mut stmt = conn.prepare_cached("DELETE FROM file_paths WHERE hash=?")?;
+            stmt.execute([hash])?;
+        }
+        {
+            let mut stmt = conn.prepare_cached("DELETE FROM root_paths WHERE hash=?")?;
+            stmt.execute([hash])?;
+        }
+        {
+            let mut stmt = conn.prepare_cached("DELETE FROM contents WHERE hash=?")?;
+            stmt.execute([hash])?;
+        }
         Ok(())
     }
 
+    /// Clean all contents that are not used by any file, and the paths stored for them, from the
+    /// database.
+    ///
+    /// This is an inner method, which does not wrap individual SQL statements in a transaction.
+    fn clean_unused_contents_inner(conn: &Connection) -> Result<()> {
+        {
+            let mut stmt = conn.prepare_cached(
+                "DELETE FROM file_paths WHERE hash NOT IN (SELECT hash FROM graphs)",
+            )?;
+            stmt.execute([])?;
+        }
+        {
+            let mut stmt = conn.prepare_cached(
+                "DELETE FROM root_paths WHERE hash NOT IN (SELECT hash FROM graphs)",
+            )?;
+            stmt.execute([])?;
+        }
+        {
+            let mut stmt = conn.prepare_cached(
+                "DELETE FROM contents WHERE hash NOT IN (SELECT hash FROM graphs)",
+            )?;
+            stmt.execute([])?;
+        }
+        Ok(())
+    }
+
+    /// Clean the data of all files for which the given predicate returns true from the database,
+    /// such as files that no longer exist, or whose content changed since they were indexed.
+    /// Returns the number of cleaned files.
+    pub fn prune_files<F>(&mut self, mut is_stale: F) -> Result<usize>
+    where
+        F: FnMut(&FileEntry) -> bool,
+    {
+        self.write(|conn| {
+            let entries = {
+                let mut files = Files(conn.prepare("SELECT file, tag, error FROM graphs")?, ());
+                let entries = files.try_iter()?.collect::<Result<Vec<_>>>()?;
+                entries
+            };
+            let mut count = 0;
+            for entry in entries.iter().filter(|e| is_stale(e)) {
+                count += Self::clean_file_inner(conn, &entry.path)?;
+            }
+            Ok(count)
+        })
+    }
+
+    /// Clean data that does not belong to any file from the database, and rebuild the database
+    /// file so that it does not take up more space than necessary.  Pending write operations are
+    /// committed first.
+    pub fn vacuum(&mut self) -> Result<()> {
+        self.write(|conn| {
+            for table in ["symbols", "errors", "sources"] {
+                conn.execute(
+                    &format!(
+                        "DELETE FROM {} WHERE file NOT IN (SELECT file FROM graphs)",
+                        table
+                    ),
+                    [],
+                )?;
+            }
+            Self::clean_unused_contents_inner(conn)
+        })?;
+        self.flush()?;
+        // The database cannot be vacuumed inside a transaction.  Checkpointing afterwards keeps
+        // the write-ahead log from holding on to the rebuilt pages.
+        self.conn()
+            .execute_batch("VACUUM")
+            .and_then(|_| {
+                self.conn()
+                    .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
+            })
+            .map_err(|e| StorageError::from(e).or_contended())?;
+        Ok(())
+    }
+
+    /// Store an error, indicating that indexing this file failed.  The error is stored with the
+    /// `unknown` kind and without a span.  Use [`Self::store_file_error_for_file`][] to store
+    /// structured errors.
+    pub fn store_error_for_file(&mut self, file: &Path, tag: &str, error: &str) -> Result<()> {
+        self.store_file_error_for_file(file, tag, &FileError::new(UNKNOWN_ERROR_KIND, error))
+    }
+
+    /// Store a structured error, indicating that indexing this file failed.
+    pub fn store_file_error_for_file(
+        &mut self,
+        file: &Path,
+        tag: &str,
+        error: &FileError,
+    ) -> Result<()> {
+        let compression_level = self.compression_level;
+        self.write(|conn| {
+            Self::store_error_for_file_inner(conn, file, tag, error, compression_level)
+        })
+    }
+
     /// Store an error, indicating that indexing this file failed.
     ///
     /// This is an inner method, which does not wrap individual SQL statements in a transaction.
@@ -285,18 +1075,46 @@ impl SQLiteWriter {
         conn: &Connection,
         file: &Path,
         tag: &str,
-        error: &str,
+        error: &FileError,
+        compression_level: Option<i32>,
     ) -> Result<()> {
         copious_debugging!("--> Store error for {}", file.display());
-        let mut stmt = conn
-            .prepare_cached("INSERT INTO graphs (file, tag, error, value) VALUES (?, ?, ?, ?)")?;
-        let graph = crate::serde::StackGraph::default();
-        let serialized = bincode::encode_to_vec(&graph, BINCODE_CONFIG)?;
-        stmt.execute((&file.to_string_lossy(), tag, error, serialized))?;
+        let file = file.to_string_lossy();
+        let mut graph = crate::serde::StackGraph::default();
+        let (hash, _) = store_content(conn, &mut graph, &file, compression_level)?;
+        {
+            let mut stmt = conn.prepare_cached(
+                "INSERT INTO graphs (file, tag, error, hash, indexed_at) VALUES (?, ?, ?, ?, ?)",
+            )?;
+            stmt.execute((&file, tag, &error.message, hash, now()))?;
+        }
+        {
+            let mut stmt = conn.prepare_cached(
+                r#"
+                    INSERT INTO errors (file, kind, message, start_line, start_column, end_line, end_column)
+                    VALUES (?, ?, ?, ?, ?, ?, ?)
+                "#,
+            )?;
+            let span = error.span.as_ref();
+            stmt.execute((
+                &file,
+                &error.kind,
+                &error.message,
+                span.map(|s| s.start_line),
+                span.map(|s| s.start_column),
+                span.map(|s| s.end_line),
+                span.map(|s| s.end_column),
+            ))?;
+        }
         Ok(())
     }
 
-    /// Store the result of a successful file index.
+    /// Store the result of a successful file index.  Files with the same content share the stored
+    /// graph and paths.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(file = graph[file].name()))
+    )]
     pub fn store_result_for_file<'a, IP>(
         &mut self,
         graph: &StackGraph,
@@ -309,15 +1127,31 @@ impl SQLiteWriter {
         IP: IntoIterator<Item = &'a PartialPath>,
     {
         let path = Path::new(graph[file].name());
-        let tx = self.conn.transaction()?;
-        Self::clean_file_inner(&tx, path)?;
-        Self::store_graph_for_file_inner(&tx, graph, file, tag)?;
-        Self::store_partial_paths_for_file_inner(&tx, graph, file, partials, paths)?;
-        tx.commit()?;
-        Ok(())
+        let paths = paths.into_iter().collect::<Vec<_>>();
+        let compression_level = self.compression_level;
+        self.write(|conn| {
+            Self::clean_file_inner(conn, path)?;
+            let (hash, is_new) =
+                Self::store_graph_for_file_inner(conn, graph, file, tag, compression_level)?;
+            Self::store_symbols_for_file_inner(conn, graph, file)?;
+            Self::store_root_symbols_for_file_inner(conn, graph, file, partials, &paths)?;
+            if is_new {
+                Self::store_partial_paths_for_file_inner(
+                    conn,
+                    graph,
+                    file,
+                    &hash,
+                    partials,
+                    paths.iter().copied(),
+                    compression_level,
+                )?;
+            }
+            Ok(())
+        })
     }
 
-    /// Store the file graph.
+    /// Store the file graph.  Returns the hash of the graph content, and whether the content was
+    /// not stored before.
     ///
     /// This is an inner method, which does not wrap individual SQL statements in a transaction.
     fn store_graph_for_file_inner(
@@ -325,17 +1159,79 @@ impl SQLiteWriter {
         graph: &StackGraph,
         file: Handle<File>,
         tag: &str,
-    ) -> Result<()> {
+        compression_level: Option<i32>,
+    ) -> Result<(String, bool)> {
         let file_str = graph[file].name();
         copious_debugging!("--> Store graph for {}", file_str);
+        let mut graph = serde::StackGraph::from_graph_filter(graph, &FileFilter::single(file));
+        let (hash, is_new) = store_content(conn, &mut graph, file_str, compression_level)?;
+        if is_new {
+            store_content_counts(conn, &hash, &graph)?;
+        }
+        let mut stmt = conn.prepare_cached(
+            "INSERT INTO graphs (file, tag, hash, indexed_at) VALUES (?, ?, ?, ?)",
+        )?;
+        stmt.execute((file_str, tag, &hash, now()))?;
+        Ok((hash, is_new))
+    }
+
+    /// Store the symbols that are defined and referenced in the file.
+    ///
+    /// This is an inner method, which does not wrap individual SQL statements in a transaction.
+    fn store_symbols_for_file_inner(
+        conn: &Connection,
+        graph: &StackGraph,
+        file: Handle<File>,
+    ) -> Result<()> {
+        let file_str = graph[file].name();
+        let mut symbols = BTreeSet::new();
+        for node in graph.nodes_for_file(file) {
+            let node = &graph[node];
+            let kind = if node.is_definition() {
+                DEFINITION_KIND
+            } else if node.is_reference() {
+                REFERENCE_KIND
+            } else {
+                continue;
+            };
+            if let Some(symbol) = node.symbol() {
+                symbols.insert((&graph[symbol], kind));
+            }
+        }
         let mut stmt =
-            conn.prepare_cached("INSERT INTO graphs (file, tag, value) VALUES (?, ?, ?)")?;
-        let graph = serde::StackGraph::from_graph_filter(graph, &FileFilter(file));
-        let serialized = bincode::encode_to_vec(&graph, BINCODE_CONFIG)?;
-        stmt.execute((file_str, tag, &serialized))?;
+            conn.prepare_cached("INSERT INTO symbols (file, symbol, kind) VALUES (?, ?, ?)")?;
+        for (symbol, kind) in symbols {
+            stmt.execute((file_str, symbol, kind))?;
+        }
         Ok(())
     }
 
+    /// Store the symbols that the partial paths of the file look up at or define at the root node.
+    ///
+    /// This is an inner method, which does not wrap individual SQL statements in a transaction.
+    fn store_root_symbols_for_file_inner(
+        conn: &Connection,
+        graph: &StackGraph,
+        file: Handle<File>,
+        partials: &mut PartialPaths,
+        paths: &[&PartialPath],
+    ) -> Result<()> {
+        let mut symbols = BTreeSet::new();
+        for path in paths {
+            let (mut symbol_stack, kind) = if graph[path.end_node].is_root() {
+                (path.symbol_stack_postcondition, IMPORT_KIND)
+            } else if graph[path.start_node].is_root() {
+                (path.symbol_stack_precondition, EXPORT_KIND)
+            } else {
+                continue;
+            };
+            if let Some(symbol) = symbol_stack.pop_front(partials) {
+                symbols.insert((&graph[symbol.symbol], kind));
+            }
+        }
+        store_symbols(conn, graph[file].name(), symbols)
+    }
+
     /// Store the file partial paths.
     ///
     /// This is an inner method, which does not wrap individual SQL statements in a transaction.
@@ -343,17 +1239,19 @@ impl SQLiteWriter {
         conn: &Connection,
         graph: &StackGraph,
         file: Handle<File>,
+        hash: &str,
         partials: &mut PartialPaths,
         paths: IP,
+        compression_level: Option<i32>,
     ) -> Result<()>
     where
         IP: IntoIterator<Item = &'a PartialPath>,
     {
         let file_str = graph[file].name();
         let mut node_stmt =
-            conn.prepare_cached("INSERT INTO file_paths (file, local_id, value) VALUES (?, ?, ?)")?;
+            conn.prepare_cached("INSERT INTO file_paths (hash, local_id, value) VALUES (?, ?, ?)")?;
         let mut root_stmt = conn.prepare_cached(
-            "INSERT INTO root_paths (file, symbol_stack, value) VALUES (?, ?, ?)",
+            "INSERT INTO root_paths (hash, symbol_stack, value) VALUES (?, ?, ?)",
         )?;
         #[cfg_attr(not(feature = "copious-debugging"), allow(unused))]
         let mut node_path_count = 0usize;
@@ -372,18 +1270,26 @@ impl SQLiteWriter {
                     path.symbol_stack_precondition.display(graph, partials),
                 );
                 let symbol_stack = path.symbol_stack_precondition.storage_key(graph, partials);
-                let path = serde::PartialPath::from_partial_path(graph, partials, path);
-                let serialized = bincode::encode_to_vec(&path, BINCODE_CONFIG)?;
-                root_stmt.execute((file_str, symbol_stack, serialized))?;
+                let mut path = serde::PartialPath::from_partial_path(graph, partials, path);
+                path.rename_file(file_str, CONTENT_FILE);
+                let serialized = compress(
+                    bincode::encode_to_vec(&path, BINCODE_CONFIG)?,
+                    compression_level,
+                )?;
+                root_stmt.execute((hash, symbol_stack, serialized))?;
                 root_path_count += 1;
             } else if start_node.is_in_file(file) {
                 copious_debugging!(
                     " * Add as node path from node {}",
                     path.start_node.display(graph),
                 );
-                let path = serde::PartialPath::from_partial_path(graph, partials, path);
-                let serialized = bincode::encode_to_vec(&path, BINCODE_CONFIG)?;
-                node_stmt.execute((file_str, path.start_node.local_id, serialized))?;
+                let mut path = serde::PartialPath::from_partial_path(graph, partials, path);
+                path.rename_file(file_str, CONTENT_FILE);
+                let serialized = compress(
+                    bincode::encode_to_vec(&path, BINCODE_CONFIG)?,
+                    compression_level,
+                )?;
+                node_stmt.execute((hash, path.start_node.local_id, serialized))?;
                 node_path_count += 1;
             } else {
                 panic!(
@@ -392,31 +1298,228 @@ impl SQLiteWriter {
                     graph[file].name()
                 );
             }
-            copious_debugging!(
-                " * Added {} node paths and {} root paths",
-                node_path_count,
-                root_path_count,
-            );
+            copious_debugging!(
+                " * Added {} node paths and {} root paths",
+                node_path_count,
+                root_path_count,
+            );
+        }
+        Ok(())
+    }
+
+    /// Merge the files in another database into this one.  Files that are not in this database,
+    /// or that have a different tag, are imported together with their graphs, partial paths,
+    /// symbols, errors, and sources, replacing any existing entries.  Files with the same tag
+    /// are left unchanged.  The other database is only read, and must have the current version,
+    /// or [`StorageError::IncompatibleVersion`][] is returned.  Returns the number of imported
+    /// files.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn merge_from<P: AsRef<Path>>(&mut self, path: P) -> Result<usize> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Err(StorageError::MissingDatabase(
+                path.to_string_lossy().to_string(),
+            ));
+        }
+        {
+            let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+            check_version(&conn).map_err(StorageError::or_contended)?;
+        }
+        self.write_with_attached(path, "merged", Self::merge_inner)
+    }
+
+    /// Export the data of all descendants of a file or directory into a database at the given
+    /// path.  The database is created if it does not exist, and any data it contains is removed
+    /// first.  The exported database can be merged into other
+    /// databases using [`Self::merge_from`][].  Returns the number of exported files.
+    pub fn export_file_or_directory<P: AsRef<Path>>(
+        &mut self,
+        file_or_directory: &Path,
+        path: P,
+    ) -> Result<usize> {
+        let path = path.as_ref();
+        Self::open(path)?.clean_all()?;
+        self.write_with_attached(path, "exported", |conn| {
+            Self::export_inner(conn, file_or_directory)
+        })
+    }
+
+    /// Export the data of all descendants of a file or directory into the attached `exported`
+    /// database.
+    ///
+    /// This is an inner method, which does not wrap individual SQL statements in a transaction.
+    fn export_inner(conn: &Connection, file_or_directory: &Path) -> Result<usize> {
+        let file_or_directory = file_or_directory.to_string_lossy();
+        let count = conn.execute(
+            "INSERT INTO exported.graphs SELECT * FROM main.graphs WHERE path_descendant_of(file, ?)",
+            [&file_or_directory],
+        )?;
+        for table in ["symbols", "errors", "sources"] {
+            conn.execute(
+                &format!(
+                    "INSERT INTO exported.{0} SELECT * FROM main.{0} WHERE path_descendant_of(file, ?)",
+                    table
+                ),
+                [&file_or_directory],
+            )?;
+        }
+        for table in ["contents", "file_paths", "root_paths"] {
+            conn.execute(
+                &format!(
+                    "INSERT INTO exported.{0} SELECT * FROM main.{0} WHERE hash IN (SELECT DISTINCT hash FROM exported.graphs)",
+                    table
+                ),
+                [],
+            )?;
+        }
+        Ok(count)
+    }
+
+    /// Runs a write operation with another database attached under the given name.  Pending
+    /// write operations are committed first, and the operation is committed immediately.
+    fn write_with_attached<T, F>(&mut self, path: &Path, name: &str, f: F) -> Result<T>
+    where
+        F: FnOnce(&Connection) -> Result<T>,
+    {
+        // Databases cannot be attached inside a transaction.
+        self.flush()?;
+        self.conn().execute(
+            &format!("ATTACH DATABASE ? AS {}", name),
+            [path.to_string_lossy()],
+        )?;
+        let result = self.write(f).and_then(|value| {
+            self.flush()?;
+            Ok(value)
+        });
+        if result.is_err() {
+            // Discard a partial operation before detaching.
+            let _ = self.conn().execute_batch("ROLLBACK");
+            self.pending = 0;
+        }
+        self.conn()
+            .execute(&format!("DETACH DATABASE {}", name), [])?;
+        result
+    }
+
+    /// Merge the files in the attached `merged` database into this one.
+    ///
+    /// This is an inner method, which does not wrap individual SQL statements in a transaction.
+    fn merge_inner(conn: &Connection) -> Result<usize> {
+        let files = {
+            let mut stmt = conn.prepare(
+                r#"
+                    SELECT file FROM merged.graphs AS other
+                    WHERE NOT EXISTS (
+                        SELECT 1 FROM main.graphs
+                        WHERE main.graphs.file = other.file AND main.graphs.tag = other.tag
+                    )
+                "#,
+            )?;
+            let files = stmt
+                .query_map([], |r| r.get::<_, String>(0))?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            files
+        };
+        for file in &files {
+            Self::clean_file_inner(conn, Path::new(file))?;
+            let hash = {
+                let mut stmt =
+                    conn.prepare_cached("SELECT hash FROM merged.graphs WHERE file = ?")?;
+                stmt.query_row([file], |r| r.get::<_, String>(0))?
+            };
+            let is_new = {
+                let mut stmt = conn.prepare_cached("SELECT 1 FROM main.contents WHERE hash = ?")?;
+                !stmt.exists([&hash])?
+            };
+            if is_new {
+                for (table, columns) in [
+                    ("contents", CONTENTS_COLUMNS),
+                    ("file_paths", FILE_PATHS_COLUMNS),
+                    ("root_paths", ROOT_PATHS_COLUMNS),
+                ] {
+                    conn.prepare_cached(&format!(
+                        "INSERT INTO main.{0} ({1}) SELECT {1} FROM merged.{0} WHERE hash = ?",
+                        table, columns
+                    ))?
+                    .execute([&hash])?;
+                }
+            }
+            for (table, columns) in [
+                ("graphs", GRAPHS_COLUMNS),
+                ("symbols", SYMBOLS_COLUMNS),
+                ("errors", ERRORS_COLUMNS),
+                ("sources", SOURCES_COLUMNS),
+            ] {
+                conn.prepare_cached(&format!(
+                    "INSERT INTO main.{0} ({1}) SELECT {1} FROM merged.{0} WHERE file = ?",
+                    table, columns
+                ))?
+                .execute([file])?;
+            }
         }
-        Ok(())
+        Ok(files.len())
+    }
+
+    /// Store the source text of a file, so that excerpts can be shown from the database when the
+    /// file itself is not available.  Sources are compressed, using the configured compression
+    /// level or the default zstd level.  Because storing the result for a file removes its
+    /// previous source, the source must be stored after the result.
+    pub fn store_source_for_file(&mut self, file: &Path, source: &str) -> Result<()> {
+        let compression_level = self
+            .compression_level
+            .unwrap_or(zstd::DEFAULT_COMPRESSION_LEVEL);
+        self.write(|conn| {
+            let file = file.to_string_lossy();
+            let value = compress(source.as_bytes().to_vec(), Some(compression_level))?;
+            let mut stmt =
+                conn.prepare_cached("INSERT OR REPLACE INTO sources (file, value) VALUES (?, ?)")?;
+            stmt.execute((&file, value))?;
+            Ok(())
+        })
     }
 
     /// Get the file's status in the database. If a tag is provided, it must match or the file
     /// is reported missing.
     pub fn status_for_file(&mut self, file: &str, tag: Option<&str>) -> Result<FileStatus> {
-        status_for_file(&self.conn, file, tag)
+        status_for_file(self.conn(), file, tag)
     }
 
-    /// Convert this writer into a reader for the same database.
-    pub fn into_reader(self) -> SQLiteReader {
-        SQLiteReader {
-            conn: self.conn,
-            loaded_graphs: HashSet::new(),
-            loaded_node_paths: HashSet::new(),
-            loaded_root_paths: HashSet::new(),
-            graph: StackGraph::new(),
-            partials: PartialPaths::new(),
-            db: Database::new(),
+    /// Returns the files, other than the given one, whose partial paths look up symbols at the
+    /// root node that the given file defines there.  Pending write operations are included.
+    pub fn files_affected_by(&self, file: &str) -> Result<Vec<PathBuf>> {
+        files_affected_by(self.conn(), file)
+    }
+
+    /// Convert this writer into a reader for the same database.  Pending write operations are
+    /// committed first, and failures to commit them are logged.  Call [`flush`][Self::flush]
+    /// before converting a batching writer to handle such failures.
+    pub fn into_reader(mut self) -> SQLiteReader {
+        self.flush_or_log();
+        let conn = self.conn.take().expect("connection is present");
+        SQLiteReader::from_connection(conn)
+    }
+
+    /// Commits all pending write operations where errors cannot be returned.
+    fn flush_or_log(&mut self) {
+        if let Err(err) = self.flush() {
+            log::error!("Cannot commit pending write operations: {}", err);
+        }
+    }
+
+    fn conn(&self) -> &Connection {
+        self.conn.as_ref().expect("connection is present")
+    }
+
+    fn conn_mut(&mut self) -> &mut Connection {
+        self.conn.as_mut().expect("connection is present")
+    }
+}
+
+impl Drop for SQLiteWriter {
+    fn drop(&mut self) {
+        // Errors cannot be returned here.  Call `flush` explicitly to handle them.
+        if self.conn.is_some() {
+            self.flush_or_log();
         }
     }
 }
@@ -433,7 +1536,9 @@ pub struct SQLiteReader {
 }
 
 impl SQLiteReader {
-    /// Open a file database.
+    /// Open a file database.  Readers never upgrade databases, so an error is returned if the
+    /// database does not have the current version, and the database is left unchanged.  Opening
+    /// the database with a [`SQLiteWriter`][] upgrades databases with an older version.
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
         if !path.as_ref().exists() {
             return Err(StorageError::MissingDatabase(
@@ -444,7 +1549,33 @@ impl SQLiteReader {
         set_pragmas_and_functions(&conn)?;
         check_version(&conn)?;
         init_indexes(&mut conn)?;
-        Ok(Self {
+        Ok(Self::from_connection(conn))
+    }
+
+    /// Open a file database in read-only mode.  Because databases use write-ahead logging, any
+    /// number of read-only readers, in this or other processes, can read from the database at the
+    /// same time, even while a writer is updating it.  The database must have been created by a
+    /// [`SQLiteWriter`][].  An error is returned if the database does not have the current
+    /// version.
+    pub fn open_read_only<P: AsRef<Path>>(path: P) -> Result<Self> {
+        if !path.as_ref().exists() {
+            return Err(StorageError::MissingDatabase(
+                path.as_ref().to_string_lossy().to_string(),
+            ));
+        }
+        let conn = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY
+                | OpenFlags::SQLITE_OPEN_NO_MUTEX
+                | OpenFlags::SQLITE_OPEN_URI,
+        )?;
+        set_functions(&conn)?;
+        check_version(&conn)?;
+        Ok(Self::from_connection(conn))
+    }
+
+    fn from_connection(conn: Connection) -> Self {
+        Self {
             conn,
             loaded_graphs: HashSet::new(),
             loaded_node_paths: HashSet::new(),
@@ -452,7 +1583,7 @@ impl SQLiteReader {
             graph: StackGraph::new(),
             partials: PartialPaths::new(),
             db: Database::new(),
-        })
+        }
     }
 
     /// Clear all data that has been loaded into this reader instance.
@@ -487,6 +1618,64 @@ impl SQLiteReader {
         status_for_file(&self.conn, file, tag)
     }
 
+    /// Returns the source text of the file, if it was stored in the database using
+    /// [`SQLiteWriter::store_source_for_file`][].
+    pub fn source_for_file(&self, file: &str) -> Result<Option<String>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT value FROM sources WHERE file = ?")?;
+        let value = stmt
+            .query_row([file], |r| r.get::<_, Vec<u8>>(0))
+            .optional()?;
+        match value {
+            Some(value) => Ok(Some(
+                String::from_utf8_lossy(&decompress(value)?).into_owned(),
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the counts and sizes of the data stored for the file, or `None` if the file is not
+    /// in the database.  The stored graph and paths are not loaded to compute the summary.
+    pub fn file_summary(&self, file: &str) -> Result<Option<FileSummary>> {
+        let mut stmt = self.conn.prepare_cached(
+            r#"
+                SELECT graphs.tag, graphs.error, graphs.indexed_at,
+                       contents.node_count, contents.edge_count, LENGTH(contents.value),
+                       (SELECT COUNT(*) FROM file_paths WHERE hash = graphs.hash),
+                       (SELECT TOTAL(LENGTH(value)) FROM file_paths WHERE hash = graphs.hash),
+                       (SELECT COUNT(*) FROM root_paths WHERE hash = graphs.hash),
+                       (SELECT TOTAL(LENGTH(value)) FROM root_paths WHERE hash = graphs.hash),
+                       (SELECT LENGTH(value) FROM sources WHERE file = graphs.file),
+                       (SELECT COUNT(*) FROM graphs AS other WHERE other.hash = graphs.hash)
+                FROM graphs JOIN contents ON contents.hash = graphs.hash
+                WHERE graphs.file = ?
+            "#,
+        )?;
+        let summary = stmt
+            .query_row([file], |r| {
+                Ok(FileSummary {
+                    path: PathBuf::from(file),
+                    tag: r.get(0)?,
+                    status: r.get_ref(1)?.into(),
+                    indexed_at: r
+                        .get::<_, Option<u64>>(2)?
+                        .map(|secs| UNIX_EPOCH + Duration::from_secs(secs)),
+                    node_count: r.get(3)?,
+                    edge_count: r.get(4)?,
+                    graph_size: r.get(5)?,
+                    node_path_count: r.get(6)?,
+                    node_paths_size: r.get::<_, f64>(7)? as usize,
+                    root_path_count: r.get(8)?,
+                    root_paths_size: r.get::<_, f64>(9)? as usize,
+                    source_size: r.get(10)?,
+                    content_file_count: r.get(11)?,
+                })
+            })
+            .optional()?;
+        Ok(summary)
+    }
+
     /// Returns a [`Files`][] value that can be used to iterate over all files in the database.
     pub fn list_all<'a>(&'a mut self) -> Result<Files<'a, ()>> {
         self.conn
@@ -514,9 +1703,94 @@ impl SQLiteReader {
             .map_err(|e| e.into())
     }
 
+    /// Returns a [`FileErrors`][] value that can be used to iterate over the errors of all files
+    /// in the database for which indexing failed.
+    pub fn list_all_errors<'a>(&'a self) -> Result<FileErrors<'a, ()>> {
+        self.conn
+            .prepare(ERRORS_QUERY)
+            .map(|stmt| FileErrors(stmt, ()))
+            .map_err(|e| e.into())
+    }
+
+    /// Returns a [`FileErrors`][] value that can be used to iterate over the errors of all
+    /// descendants of a file or directory in the database for which indexing failed.
+    pub fn list_errors_for_file_or_directory<'a>(
+        &'a self,
+        file_or_directory: &Path,
+    ) -> Result<FileErrors<'a, [String; 1]>> {
+        let file_or_directory = file_or_directory.to_string_lossy().to_string();
+        self.conn
+            .prepare(&format!(
+                "{} WHERE path_descendant_of(errors.file, ?)",
+                ERRORS_QUERY
+            ))
+            .map(|stmt| FileErrors(stmt, [file_or_directory]))
+            .map_err(|e| e.into())
+    }
+
+    /// Returns a [`Files`][] value that can be used to iterate over all files in the database that
+    /// contain a definition of the given symbol.
+    pub fn files_defining<'a>(&'a self, symbol: &str) -> Result<Files<'a, [String; 2]>> {
+        Self::files_with_symbol_inner(&self.conn, symbol, DEFINITION_KIND)
+    }
+
+    /// Returns a [`Files`][] value that can be used to iterate over all files in the database that
+    /// contain a reference to the given symbol.
+    pub fn files_referencing<'a>(&'a self, symbol: &str) -> Result<Files<'a, [String; 2]>> {
+        Self::files_with_symbol_inner(&self.conn, symbol, REFERENCE_KIND)
+    }
+
+    /// Returns a [`Files`][] value that can be used to iterate over all files in the database
+    /// whose partial paths look up the given symbol at the root node, so that it can be defined in
+    /// another file.
+    pub fn files_importing<'a>(&'a self, symbol: &str) -> Result<Files<'a, [String; 2]>> {
+        Self::files_with_symbol_inner(&self.conn, symbol, IMPORT_KIND)
+    }
+
+    /// Returns the files, other than the given one, whose partial paths look up symbols at the
+    /// root node that the given file defines there.  References in these files may resolve to
+    /// definitions in the given file, and can therefore be affected when it changes.
+    pub fn files_affected_by(&self, file: &str) -> Result<Vec<PathBuf>> {
+        files_affected_by(&self.conn, file)
+    }
+
+    fn files_with_symbol_inner<'a>(
+        conn: &'a Connection,
+        symbol: &str,
+        kind: &str,
+    ) -> Result<Files<'a, [String; 2]>> {
+        conn.prepare(
+            r#"
+                SELECT graphs.file, graphs.tag, graphs.error FROM graphs
+                WHERE graphs.file IN (SELECT file FROM symbols WHERE symbol = ? AND kind = ?)
+            "#,
+        )
+        .map(|stmt| Files(stmt, [symbol.to_string(), kind.to_string()]))
+        .map_err(|e| e.into())
+    }
+
     /// Ensure the graph for the given file is loaded.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
     pub fn load_graph_for_file(&mut self, file: &str) -> Result<Handle<File>> {
-        Self::load_graph_for_file_inner(file, &mut self.graph, &mut self.loaded_graphs, &self.conn)
+        Self::load_graph_for_file_inner(
+            file,
+            &mut self.graph,
+            &mut self.loaded_graphs,
+            &self.conn,
+            &NoCancellation,
+        )
+    }
+
+    /// Ensure the graph for the given file is loaded, and return the node of that file with the
+    /// given [stable ID][crate::graph::StableNodeID], if it exists.  Stable IDs are not stored,
+    /// but computed from the loaded graph, so they are available for all indexed files.
+    pub fn node_for_stable_id(
+        &mut self,
+        file: &str,
+        id: StableNodeID,
+    ) -> Result<Option<Handle<Node>>> {
+        let file = self.load_graph_for_file(file)?;
+        Ok(self.graph.node_for_stable_id(file, id))
     }
 
     fn load_graph_for_file_inner(
@@ -524,21 +1798,72 @@ impl SQLiteReader {
         graph: &mut StackGraph,
         loaded_graphs: &mut HashSet<String>,
         conn: &Connection,
+        cancellation_flag: &dyn CancellationFlag,
     ) -> Result<Handle<File>> {
         copious_debugging!("--> Load graph for {}", file);
-        if !loaded_graphs.insert(file.to_string()) {
+        if loaded_graphs.contains(file) {
             copious_debugging!(" * Already loaded");
             return Ok(graph.get_file(file).expect("loaded file to exist"));
         }
         copious_debugging!(" * Load from database");
-        let mut stmt = conn.prepare_cached("SELECT value FROM graphs WHERE file = ?")?;
+        cancellation_flag.check("loading graph")?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT contents.value FROM graphs JOIN contents ON contents.hash = graphs.hash WHERE graphs.file = ?",
+        )?;
         let value = stmt.query_row([file], |row| row.get::<_, Vec<u8>>(0))?;
-        let (file_graph, _): (serde::StackGraph, usize) =
-            bincode::decode_from_slice(&value, BINCODE_CONFIG)?;
+        cancellation_flag.check("decoding graph")?;
+        let file_graph = decode_graph(file, value)?;
         file_graph.load_into(graph)?;
+        // only mark the graph as loaded once it is, so that cancelled loads can be retried
+        loaded_graphs.insert(file.to_string());
         Ok(graph.get_file(file).expect("loaded file to exist"))
     }
 
+    /// Ensure the graphs for the given files are loaded.  Graphs that are not loaded yet are
+    /// fetched using a few large queries instead of one query per file.  If many graphs are
+    /// fetched, they are decoded in parallel.  Returns the file handles in the same order as the
+    /// given files.
+    pub fn load_graphs_for_files(&mut self, files: &[&str]) -> Result<Vec<Handle<File>>> {
+        copious_debugging!("--> Load graphs for {} files", files.len());
+        let mut missing = files
+            .iter()
+            .copied()
+            .filter(|file| !self.loaded_graphs.contains(*file))
+            .collect::<Vec<_>>();
+        missing.sort_unstable();
+        missing.dedup();
+
+        for chunk in missing.chunks(MAX_FILES_PER_QUERY) {
+            let placeholders = vec!["?"; chunk.len()].join(",");
+            let mut stmt = self.conn.prepare(&format!(
+                "SELECT graphs.file, contents.value FROM graphs JOIN contents ON contents.hash = graphs.hash WHERE graphs.file IN ({})",
+                placeholders
+            ))?;
+            let values = stmt
+                .query_map(rusqlite::params_from_iter(chunk), |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?))
+                })?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            if values.len() < chunk.len() {
+                return Err(rusqlite::Error::QueryReturnedNoRows.into());
+            }
+            for file_graph in decode_graphs(values)? {
+                file_graph.load_into(&mut self.graph)?;
+            }
+            self.loaded_graphs
+                .extend(chunk.iter().map(|file| file.to_string()));
+        }
+
+        Ok(files
+            .iter()
+            .map(|file| self.graph.get_file(file).expect("loaded file to exist"))
+            .collect())
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(path = %file_or_directory.display()))
+    )]
     pub fn load_graphs_for_file_or_directory(
         &mut self,
         file_or_directory: &Path,
@@ -552,6 +1877,7 @@ impl SQLiteReader {
                 &mut self.graph,
                 &mut self.loaded_graphs,
                 &self.conn,
+                cancellation_flag,
             )?;
         }
         Ok(())
@@ -564,49 +1890,56 @@ impl SQLiteReader {
         cancellation_flag: &dyn CancellationFlag,
     ) -> Result<()> {
         copious_debugging!(" * Load extensions from node {}", node.display(&self.graph));
-        if !self.loaded_node_paths.insert(node) {
+        if self.loaded_node_paths.contains(&node) {
             copious_debugging!("   > Already loaded");
             return Ok(());
         }
+        cancellation_flag.check("loading node paths")?;
         let id = self.graph[node].id();
         let file = id.file().expect("file node required");
-        let file = self.graph[file].name();
-        let mut stmt = self
-            .conn
-            .prepare_cached("SELECT file,value from file_paths WHERE file = ? AND local_id = ?")?;
+        let file = self.graph[file].name().to_string();
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT graphs.file, file_paths.value FROM graphs JOIN file_paths ON file_paths.hash = graphs.hash WHERE graphs.file = ? AND file_paths.local_id = ?",
+        )?;
         let paths = stmt.query_map((file, id.local_id()), |row| {
             let file = row.get::<_, String>(0)?;
             let value = row.get::<_, Vec<u8>>(1)?;
             Ok((file, value))
         })?;
-        #[cfg_attr(not(feature = "copious-debugging"), allow(unused))]
-        let mut count = 0usize;
+        // paths are only added once all are loaded, so that a cancelled load can be retried
+        let mut loaded_paths = Vec::new();
         for path in paths {
             cancellation_flag.check("loading node paths")?;
             let (file, value) = path?;
+            let value = decompress(value)?;
             Self::load_graph_for_file_inner(
                 &file,
                 &mut self.graph,
                 &mut self.loaded_graphs,
                 &self.conn,
+                cancellation_flag,
             )?;
-            let (path, _): (serde::PartialPath, usize) =
+            let (mut path, _): (serde::PartialPath, usize) =
                 bincode::decode_from_slice(&value, BINCODE_CONFIG)?;
+            path.rename_file(CONTENT_FILE, &file);
             let path = path.to_partial_path(&mut self.graph, &mut self.partials)?;
             copious_debugging!(
                 "   > Loaded {}",
                 path.display(&self.graph, &mut self.partials)
             );
+            loaded_paths.push(path);
+        }
+        copious_debugging!("   > Loaded {}", loaded_paths.len());
+        for path in loaded_paths {
             self.db
                 .add_partial_path(&self.graph, &mut self.partials, path);
-            count += 1;
         }
-        copious_debugging!("   > Loaded {}", count);
+        self.loaded_node_paths.insert(node);
         Ok(())
     }
 
     /// Ensure the paths starting at the root and matching the given symbol stack are loaded.
-    fn load_paths_for_root(
+    pub(crate) fn load_paths_for_root(
         &mut self,
         symbol_stack: PartialSymbolStack,
         cancellation_flag: &dyn CancellationFlag,
@@ -622,46 +1955,148 @@ impl SQLiteReader {
                 " * Load extensions from root with prefix symbol stack {}",
                 symbol_stack
             );
-            if !self.loaded_root_paths.insert(symbol_stack.clone()) {
+            if self.loaded_root_paths.contains(&symbol_stack) {
                 copious_debugging!("   > Already loaded");
                 continue;
             }
-            let mut stmt = self
-                .conn
-                .prepare_cached("SELECT file,value from root_paths WHERE symbol_stack = ?")?;
-            let paths = stmt.query_map([symbol_stack], |row| {
+            cancellation_flag.check("loading root paths")?;
+            let mut stmt = self.conn.prepare_cached(
+                "SELECT graphs.file, root_paths.value FROM root_paths JOIN graphs ON graphs.hash = root_paths.hash WHERE root_paths.symbol_stack = ?",
+            )?;
+            let paths = stmt.query_map([&symbol_stack], |row| {
                 let file = row.get::<_, String>(0)?;
                 let value = row.get::<_, Vec<u8>>(1)?;
                 Ok((file, value))
             })?;
-            #[cfg_attr(not(feature = "copious-debugging"), allow(unused))]
-            let mut count = 0usize;
+            // paths are only added once all are loaded, so that a cancelled load can be retried
+            let mut loaded_paths = Vec::new();
             for path in paths {
                 cancellation_flag.check("loading root paths")?;
                 let (file, value) = path?;
+                let value = decompress(value)?;
                 Self::load_graph_for_file_inner(
                     &file,
                     &mut self.graph,
                     &mut self.loaded_graphs,
                     &self.conn,
+                    cancellation_flag,
                 )?;
-                let (path, _): (serde::PartialPath, usize) =
+                let (mut path, _): (serde::PartialPath, usize) =
                     bincode::decode_from_slice(&value, BINCODE_CONFIG)?;
+                path.rename_file(CONTENT_FILE, &file);
                 let path = path.to_partial_path(&mut self.graph, &mut self.partials)?;
                 copious_debugging!(
                     "   > Loaded {}",
                     path.display(&self.graph, &mut self.partials)
                 );
+                loaded_paths.push(path);
+            }
+            copious_debugging!("   > Loaded {}", loaded_paths.len());
+            for path in loaded_paths {
                 self.db
                     .add_partial_path(&self.graph, &mut self.partials, path);
-                count += 1;
             }
-            copious_debugging!("   > Loaded {}", count);
+            self.loaded_root_paths.insert(symbol_stack);
+        }
+        Ok(())
+    }
+
+    /// Ensure all partial paths from the root node are loaded whose symbol stack precondition
+    /// starts with the given prefix, and return them.  See
+    /// [`Database::find_candidate_partial_paths_from_root_with_prefix`][] for details.  The
+    /// returned handles refer to the database returned by [`get`][Self::get].
+    pub fn find_candidate_partial_paths_from_root_with_prefix<R>(
+        &mut self,
+        prefix: &SymbolStackPrefix,
+        cancellation_flag: &dyn CancellationFlag,
+        result: &mut R,
+    ) -> Result<()>
+    where
+        R: std::iter::Extend<Handle<PartialPath>>,
+    {
+        self.load_paths_for_root_with_prefix(prefix, cancellation_flag)?;
+        self.db.find_candidate_partial_paths_from_root_with_prefix(
+            &self.graph,
+            &mut self.partials,
+            prefix,
+            result,
+        );
+        Ok(())
+    }
+
+    fn load_paths_for_root_with_prefix(
+        &mut self,
+        prefix: &SymbolStackPrefix,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<()> {
+        copious_debugging!(" * Load extensions from root with prefix {}", prefix);
+        // Select the stored keys that start with the symbols before the first wildcard, and
+        // check the rest of the prefix for each key.
+        let mut pattern = String::new();
+        for (idx, symbol) in prefix.symbols.iter().enumerate() {
+            let symbol = match symbol {
+                Some(symbol) => symbol,
+                None => break,
+            };
+            if idx > 0 {
+                pattern += "\u{241F}";
+            }
+            pattern += &escape_glob(symbol);
+        }
+        pattern += "*";
+        cancellation_flag.check("loading root paths")?;
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT graphs.file, root_paths.symbol_stack, root_paths.value FROM root_paths JOIN graphs ON graphs.hash = root_paths.hash WHERE root_paths.symbol_stack GLOB ?",
+        )?;
+        let paths = stmt.query_map([pattern], |row| {
+            let file = row.get::<_, String>(0)?;
+            let symbol_stack = row.get::<_, String>(1)?;
+            let value = row.get::<_, Vec<u8>>(2)?;
+            Ok((file, symbol_stack, value))
+        })?;
+        // paths are only added once all are loaded, so that a cancelled load can be retried
+        let mut loaded_paths = Vec::new();
+        let mut loaded_symbol_stacks = HashSet::new();
+        for path in paths {
+            cancellation_flag.check("loading root paths")?;
+            let (file, symbol_stack, value) = path?;
+            let symbols = Some(symbol_stack.as_str())
+                .filter(|symbol_stack| !symbol_stack.is_empty())
+                .into_iter()
+                .flat_map(|symbol_stack| symbol_stack.split('\u{241F}'));
+            if !prefix.matches(symbols) || self.loaded_root_paths.contains(&symbol_stack) {
+                continue;
+            }
+            let value = decompress(value)?;
+            Self::load_graph_for_file_inner(
+                &file,
+                &mut self.graph,
+                &mut self.loaded_graphs,
+                &self.conn,
+                cancellation_flag,
+            )?;
+            let (mut path, _): (serde::PartialPath, usize) =
+                bincode::decode_from_slice(&value, BINCODE_CONFIG)?;
+            path.rename_file(CONTENT_FILE, &file);
+            let path = path.to_partial_path(&mut self.graph, &mut self.partials)?;
+            copious_debugging!(
+                "   > Loaded {}",
+                path.display(&self.graph, &mut self.partials)
+            );
+            loaded_paths.push(path);
+            loaded_symbol_stacks.insert(symbol_stack);
+        }
+        for path in loaded_paths {
+            self.db
+                .add_partial_path(&self.graph, &mut self.partials, path);
         }
+        // all paths with these symbol stacks are loaded now
+        self.loaded_root_paths.extend(loaded_symbol_stacks);
         Ok(())
     }
 
     /// Ensure all possible extensions for the given partial path are loaded.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
     pub fn load_partial_path_extensions(
         &mut self,
         path: &PartialPath,
@@ -680,6 +2115,174 @@ impl SQLiteReader {
         Ok(())
     }
 
+    /// Returns the partial paths that are stored for the given file, including paths that start at
+    /// the root node.  The graph of the file is loaded if necessary.  The paths are not added to
+    /// the path database of this reader, so that they do not duplicate paths that are loaded as
+    /// extensions during path stitching.
+    pub fn stored_partial_paths_for_file(
+        &mut self,
+        file: &str,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Vec<PartialPath>> {
+        Self::load_graph_for_file_inner(
+            file,
+            &mut self.graph,
+            &mut self.loaded_graphs,
+            &self.conn,
+            cancellation_flag,
+        )?;
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT file_paths.value FROM graphs JOIN file_paths ON file_paths.hash = graphs.hash WHERE graphs.file = ?1
+             UNION ALL
+             SELECT root_paths.value FROM graphs JOIN root_paths ON root_paths.hash = graphs.hash WHERE graphs.file = ?1",
+        )?;
+        let values = stmt.query_map([file], |row| row.get::<_, Vec<u8>>(0))?;
+        let mut paths = Vec::new();
+        for value in values {
+            cancellation_flag.check("loading stored paths")?;
+            let value = decompress(value?)?;
+            let (mut path, _): (serde::PartialPath, usize) =
+                bincode::decode_from_slice(&value, BINCODE_CONFIG)?;
+            path.rename_file(CONTENT_FILE, file);
+            paths.push(path.to_partial_path(&mut self.graph, &mut self.partials)?);
+        }
+        Ok(paths)
+    }
+
+    /// Check the consistency of the database, and return the problems that were found.  All rows
+    /// must belong to a stored file or content, and all stored graphs, paths, and sources must
+    /// decode.  Stored paths must only refer to nodes of the graph they were stored with, and the
+    /// symbols of a file must be defined or referenced in its graph, or imported or exported by
+    /// its paths.  Data that was loaded into this reader is not affected.
+    pub fn verify(&self, cancellation_flag: &dyn CancellationFlag) -> Result<Vec<DatabaseProblem>> {
+        let mut problems = Vec::new();
+
+        for (table, key, column, parent) in [
+            ("graphs", "file", "hash", "contents"),
+            ("file_paths", "hash", "hash", "contents"),
+            ("root_paths", "hash", "hash", "contents"),
+            ("symbols", "file", "file", "graphs"),
+            ("errors", "file", "file", "graphs"),
+            ("sources", "file", "file", "graphs"),
+        ] {
+            let mut stmt = self.conn.prepare(&format!(
+                "SELECT DISTINCT {1} FROM {0} WHERE {2} NOT IN (SELECT {2} FROM {3})",
+                table, key, column, parent
+            ))?;
+            for value in stmt.query_map([], |r| r.get::<_, String>(0))? {
+                problems.push(DatabaseProblem::new(
+                    table,
+                    value?,
+                    format!("refers to missing {} entry", parent),
+                ));
+            }
+        }
+
+        let mut contents_stmt = self.conn.prepare("SELECT hash, value FROM contents")?;
+        let mut paths_stmt = self.conn.prepare(
+            "SELECT 0, value FROM file_paths WHERE hash = ?1
+             UNION ALL
+             SELECT 1, value FROM root_paths WHERE hash = ?1",
+        )?;
+        let mut symbols_stmt = self.conn.prepare(
+            "SELECT symbols.file, symbols.symbol, symbols.kind
+             FROM symbols JOIN graphs ON symbols.file = graphs.file
+             WHERE graphs.hash = ?",
+        )?;
+        let mut contents = contents_stmt.query([])?;
+        while let Some(row) = contents.next()? {
+            cancellation_flag.check("verifying contents")?;
+            let hash = row.get::<_, String>(0)?;
+            let mut graph = StackGraph::new();
+            let loaded = decode_graph(CONTENT_FILE, row.get::<_, Vec<u8>>(1)?).and_then(|g| {
+                g.load_into(&mut graph)?;
+                Ok(())
+            });
+            if let Err(err) = loaded {
+                problems.push(DatabaseProblem::new(
+                    "contents",
+                    hash,
+                    format!("cannot load graph: {}", err),
+                ));
+                continue;
+            }
+
+            let mut symbols = HashSet::new();
+            let mut partials = PartialPaths::new();
+            let mut paths = paths_stmt.query([&hash])?;
+            while let Some(row) = paths.next()? {
+                let table = if row.get::<_, bool>(0)? {
+                    "root_paths"
+                } else {
+                    "file_paths"
+                };
+                let loaded = decompress(row.get::<_, Vec<u8>>(1)?).and_then(|value| {
+                    let (path, _): (serde::PartialPath, usize) =
+                        bincode::decode_from_slice(&value, BINCODE_CONFIG)?;
+                    path.to_partial_path(&mut graph, &mut partials)?;
+                    if let Some(symbol) = path.imported_symbol() {
+                        symbols.insert((symbol.to_string(), IMPORT_KIND));
+                    }
+                    if let Some(symbol) = path.exported_symbol() {
+                        symbols.insert((symbol.to_string(), EXPORT_KIND));
+                    }
+                    Ok(())
+                });
+                if let Err(err) = loaded {
+                    problems.push(DatabaseProblem::new(
+                        table,
+                        &hash,
+                        format!("cannot load path: {}", err),
+                    ));
+                }
+            }
+
+            if let Some(file) = graph.get_file(CONTENT_FILE) {
+                for node in graph.nodes_for_file(file) {
+                    let node = &graph[node];
+                    let kind = if node.is_definition() {
+                        DEFINITION_KIND
+                    } else if node.is_reference() {
+                        REFERENCE_KIND
+                    } else {
+                        continue;
+                    };
+                    if let Some(symbol) = node.symbol() {
+                        symbols.insert((graph[symbol].to_string(), kind));
+                    }
+                }
+            }
+            let mut rows = symbols_stmt.query([&hash])?;
+            while let Some(row) = rows.next()? {
+                let file = row.get::<_, String>(0)?;
+                let symbol = row.get::<_, String>(1)?;
+                let kind = row.get::<_, String>(2)?;
+                if !symbols.contains(&(symbol.clone(), kind.as_str())) {
+                    problems.push(DatabaseProblem::new(
+                        "symbols",
+                        file,
+                        format!("{} of `{}` is not in the graph", kind, symbol),
+                    ));
+                }
+            }
+        }
+
+        let mut stmt = self.conn.prepare("SELECT file, value FROM sources")?;
+        let mut sources = stmt.query([])?;
+        while let Some(row) = sources.next()? {
+            cancellation_flag.check("verifying sources")?;
+            if let Err(err) = decompress(row.get::<_, Vec<u8>>(1)?) {
+                problems.push(DatabaseProblem::new(
+                    "sources",
+                    row.get::<_, String>(0)?,
+                    format!("cannot decompress source: {}", err),
+                ));
+            }
+        }
+
+        Ok(problems)
+    }
+
     /// Get the stack graph, partial paths arena, and path database for the currently loaded data.
     pub fn get(&mut self) -> (&StackGraph, &mut PartialPaths, &mut Database) {
         (&self.graph, &mut self.partials, &mut self.db)
@@ -741,17 +2344,294 @@ impl ForwardCandidates<Handle<PartialPath>, PartialPath, Database, StorageError>
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncForwardCandidates<Handle<PartialPath>, PartialPath, Database, StorageError>
+    for SQLiteReader
+{
+}
+
+/// A pool of read-only readers for a database.  The pool can be shared between threads, so that
+/// many queries can be answered in parallel against one database.  Readers are opened on demand,
+/// and returned to the pool when they are dropped.  Returned readers keep the graphs and paths
+/// they have loaded, so that later queries can reuse them.
+pub struct SQLiteReaderPool {
+    path: PathBuf,
+    readers: Mutex<Vec<SQLiteReader>>,
+}
+
+impl SQLiteReaderPool {
+    /// Creates a pool for the given file database.  An error is returned if the database does
+    /// not exist, or if its version is not supported.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let reader = SQLiteReader::open_read_only(&path)?;
+        Ok(Self {
+            path: path.as_ref().to_path_buf(),
+            readers: Mutex::new(vec![reader]),
+        })
+    }
+
+    /// Returns a reader from the pool, opening a new one if all readers are in use.
+    pub fn get(&self) -> Result<PooledSQLiteReader<'_>> {
+        let reader = self.readers.lock().unwrap().pop();
+        let reader = match reader {
+            Some(reader) => reader,
+            None => SQLiteReader::open_read_only(&self.path)?,
+        };
+        Ok(PooledSQLiteReader {
+            pool: self,
+            reader: Some(reader),
+        })
+    }
+
+    /// Returns the number of readers that are currently not in use.
+    pub fn idle_count(&self) -> usize {
+        self.readers.lock().unwrap().len()
+    }
+}
+
+/// A reader that is borrowed from a [`SQLiteReaderPool`][], and is returned to it when dropped.
+pub struct PooledSQLiteReader<'a> {
+    pool: &'a SQLiteReaderPool,
+    reader: Option<SQLiteReader>,
+}
+
+impl Deref for PooledSQLiteReader<'_> {
+    type Target = SQLiteReader;
+
+    fn deref(&self) -> &SQLiteReader {
+        self.reader.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledSQLiteReader<'_> {
+    fn deref_mut(&mut self) -> &mut SQLiteReader {
+        self.reader.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledSQLiteReader<'_> {
+    fn drop(&mut self) {
+        if let Some(reader) = self.reader.take() {
+            if let Ok(mut readers) = self.pool.readers.lock() {
+                readers.push(reader);
+            }
+        }
+    }
+}
+
+/// Store the graph of the given file by the hash of its content.  The file in the graph is
+/// renamed to the content file.  Returns the hash, and whether the content was not stored before.
+fn store_content(
+    conn: &Connection,
+    graph: &mut serde::StackGraph,
+    file: &str,
+    compression_level: Option<i32>,
+) -> Result<(String, bool)> {
+    graph.rename_file(file, CONTENT_FILE);
+    let serialized = bincode::encode_to_vec(&*graph, BINCODE_CONFIG)?;
+    // The hash is computed before compression, so that the content is shared regardless of the
+    // compression level.
+    let hash = format!("{:x}", Sha1::digest(&serialized));
+    let mut stmt = conn.prepare_cached("SELECT 1 FROM contents WHERE hash=?")?;
+    if stmt.exists([&hash])? {
+        return Ok((hash, false));
+    }
+    let mut stmt = conn.prepare_cached("INSERT INTO contents (hash, value) VALUES (?, ?)")?;
+    stmt.execute((&hash, compress(serialized, compression_level)?))?;
+    Ok((hash, true))
+}
+
+/// Store symbols of the given kinds for the file.
+fn store_symbols(conn: &Connection, file: &str, symbols: BTreeSet<(&str, &str)>) -> Result<()> {
+    let mut stmt =
+        conn.prepare_cached("INSERT INTO symbols (file, symbol, kind) VALUES (?, ?, ?)")?;
+    for (symbol, kind) in symbols {
+        stmt.execute((file, symbol, kind))?;
+    }
+    Ok(())
+}
+
+/// Returns the files, other than the given one, that import symbols that the given file exports.
+fn files_affected_by(conn: &Connection, file: &str) -> Result<Vec<PathBuf>> {
+    let mut stmt = conn.prepare_cached(
+        r#"
+            SELECT DISTINCT imports.file FROM symbols AS exports
+            JOIN symbols AS imports ON imports.symbol = exports.symbol
+            WHERE exports.file = ?1 AND exports.kind = ?2
+              AND imports.kind = ?3 AND imports.file != ?1
+            ORDER BY imports.file
+        "#,
+    )?;
+    let files = stmt
+        .query_map((file, EXPORT_KIND, IMPORT_KIND), |r| {
+            r.get::<_, String>(0).map(PathBuf::from)
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(files)
+}
+
+/// Store the node and edge counts of the stored graph with the given hash.  Singleton nodes, such
+/// as the root node, are not counted.
+fn store_content_counts(conn: &Connection, hash: &str, graph: &serde::StackGraph) -> Result<()> {
+    let node_count = graph
+        .nodes
+        .data
+        .iter()
+        .filter(|node| node.id().file.is_some())
+        .count();
+    let mut stmt =
+        conn.prepare_cached("UPDATE contents SET node_count = ?, edge_count = ? WHERE hash = ?")?;
+    stmt.execute((node_count, graph.edges.data.len(), hash))?;
+    Ok(())
+}
+
+/// Returns the current time in seconds since the Unix epoch.
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+/// Escapes the characters of a string that have a special meaning in SQLite GLOB patterns.
+fn escape_glob(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '*' | '?' | '[' => {
+                escaped.push('[');
+                escaped.push(c);
+                escaped.push(']');
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The maximum number of files that are fetched in a single query, which keeps the number of query
+/// parameters below the limit of SQLite.
+const MAX_FILES_PER_QUERY: usize = 500;
+
+/// The minimum number of graphs for which decoding is spread over multiple threads.
+const MIN_GRAPHS_FOR_PARALLEL_DECODING: usize = 64;
+
+/// Decode a stored graph of the given file.
+fn decode_graph(file: &str, value: Vec<u8>) -> Result<serde::StackGraph> {
+    let value = decompress(value)?;
+    let (mut file_graph, _): (serde::StackGraph, usize) =
+        bincode::decode_from_slice(&value, BINCODE_CONFIG)?;
+    file_graph.rename_file(CONTENT_FILE, file);
+    Ok(file_graph)
+}
+
+/// Decode the stored graphs of the given files, using multiple threads if there are many.
+fn decode_graphs(values: Vec<(String, Vec<u8>)>) -> Result<Vec<serde::StackGraph>> {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    if threads == 1 || values.len() < MIN_GRAPHS_FOR_PARALLEL_DECODING {
+        return values
+            .into_iter()
+            .map(|(file, value)| decode_graph(&file, value))
+            .collect();
+    }
+    let chunk_size = values.len().div_ceil(threads);
+    let mut chunks = Vec::new();
+    let mut values = values.into_iter();
+    loop {
+        let chunk = values.by_ref().take(chunk_size).collect::<Vec<_>>();
+        if chunk.is_empty() {
+            break;
+        }
+        chunks.push(chunk);
+    }
+    std::thread::scope(|scope| {
+        let handles = chunks
+            .into_iter()
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .into_iter()
+                        .map(|(file, value)| decode_graph(&file, value))
+                        .collect::<Result<Vec<_>>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut graphs = Vec::new();
+        for handle in handles {
+            graphs.extend(handle.join().expect("decoding thread panicked")?);
+        }
+        Ok(graphs)
+    })
+}
+
+/// The magic number that starts every zstd frame.  Serialized graphs and paths never start with
+/// these bytes, which makes it possible to recognize compressed values.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Compress a serialized value, if a compression level is given.
+fn compress(value: Vec<u8>, compression_level: Option<i32>) -> Result<Vec<u8>> {
+    match compression_level {
+        Some(level) => Ok(zstd::encode_all(value.as_slice(), level)?),
+        None => Ok(value),
+    }
+}
+
+/// Decompress a stored value, if it is compressed.
+fn decompress(value: Vec<u8>) -> Result<Vec<u8>> {
+    if value.starts_with(&ZSTD_MAGIC) {
+        Ok(zstd::decode_all(value.as_slice())?)
+    } else {
+        Ok(value)
+    }
+}
+
+fn database_version(conn: &Connection) -> Result<usize> {
+    Ok(conn.query_row("SELECT version FROM metadata", [], |r| r.get::<_, usize>(0))?)
+}
+
+fn incompatible_version(version: usize) -> StorageError {
+    StorageError::IncompatibleVersion {
+        database: version,
+        supported: VERSION,
+    }
+}
+
 /// Check if the database has the version supported by this library version.
 fn check_version(conn: &Connection) -> Result<()> {
-    let version = conn.query_row("SELECT version FROM metadata", [], |r| r.get::<_, usize>(0))?;
+    let version = database_version(conn)?;
     if version != VERSION {
-        return Err(StorageError::IncorrectVersion(version));
+        return Err(incompatible_version(version));
     }
     Ok(())
 }
 
+/// Upgrade the database to the version supported by this library version.
+///
+/// This is an inner method, which does not wrap individual SQL statements in a transaction.
+fn migrate_inner(conn: &Connection) -> Result<()> {
+    let version = database_version(conn)?;
+    if version == VERSION {
+        return Ok(());
+    }
+    if version > VERSION {
+        return Err(incompatible_version(version));
+    }
+    let migrations = (version..VERSION)
+        .map(|from| MIGRATIONS.iter().find(|m| m.from == from))
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| incompatible_version(version))?;
+    for migration in migrations {
+        (migration.migrate)(conn)?;
+    }
+    conn.execute("UPDATE metadata SET version = ?", [VERSION])?;
+    Ok(())
+}
+
 fn set_pragmas_and_functions(conn: &Connection) -> Result<()> {
     conn.execute_batch(PRAGMAS)?;
+    set_functions(conn)
+}
+
+fn set_functions(conn: &Connection) -> Result<()> {
     conn.create_scalar_function(
         "path_descendant_of",
         2,
@@ -786,7 +2666,7 @@ fn status_for_file<T: AsRef<str>>(
             .optional()?
             .unwrap_or(FileStatus::Missing)
     } else {
-        let mut stmt = conn.prepare_cached("SELECT status FROM graphs WHERE file = ?")?;
+        let mut stmt = conn.prepare_cached("SELECT error FROM graphs WHERE file = ?")?;
         stmt.query_row([file], |r| r.get_ref(0).map(FileStatus::from))
             .optional()?
             .unwrap_or(FileStatus::Missing)
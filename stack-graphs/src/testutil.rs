@@ -0,0 +1,485 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Utilities for property-based testing of stack graphs and partial paths.
+//!
+//! This module provides [`proptest`][] strategies that generate stack graphs, partial symbol
+//! stacks, and partial paths, together with checkers for invariants that the partial paths of any
+//! stack graph must satisfy.  Language implementations can use the checkers to test the partial
+//! paths of the stack graphs that their rules produce, and the strategies to test them against
+//! arbitrary graphs.
+//!
+//! The strategies do not generate stack graph values directly, but _specs_ that describe them.
+//! Specs are plain values that proptest can print and shrink, and they can be built into the
+//! corresponding stack graph values using their `build` methods.  Specs for symbol stacks and
+//! partial paths do not refer to a particular graph, and select symbols, scopes, and edges from
+//! whatever graph they are built in.
+
+use proptest::prelude::*;
+use proptest::sample::Index;
+use thiserror::Error;
+
+use crate::arena::Handle;
+use crate::graph::Edge;
+use crate::graph::File;
+use crate::graph::Node;
+use crate::graph::StackGraph;
+use crate::partial::PartialPath;
+use crate::partial::PartialPaths;
+use crate::partial::PartialScopeStack;
+use crate::partial::PartialScopedSymbol;
+use crate::partial::PartialSymbolStack;
+use crate::partial::ScopeStackVariable;
+use crate::partial::SymbolStackVariable;
+use crate::paths::PathResolutionError;
+
+/// The symbols that generated graphs and symbol stacks use.  The alphabet is small, so that
+/// pushed and popped symbols are likely to match.
+pub const SYMBOLS: &[&str] = &["a", "b", "c"];
+
+//-------------------------------------------------------------------------------------------------
+// Graphs
+
+/// Describes the nodes and edges of a stack graph file.
+#[derive(Clone, Debug)]
+pub struct GraphSpec {
+    pub nodes: Vec<NodeSpec>,
+    pub edges: Vec<EdgeSpec>,
+}
+
+/// Describes a node of a stack graph file.  The root and jump to scope nodes can occur multiple
+/// times, and are all built as the corresponding singleton nodes.
+#[derive(Clone, Debug)]
+pub enum NodeSpec {
+    DropScopes,
+    JumpTo,
+    PopScopedSymbol { symbol: String, is_definition: bool },
+    PopSymbol { symbol: String, is_definition: bool },
+    PushScopedSymbol { symbol: String, is_reference: bool },
+    PushSymbol { symbol: String, is_reference: bool },
+    Root,
+    Scope { is_exported: bool },
+}
+
+/// Describes an edge between two nodes of a [`GraphSpec`][].  The source and sink select a node
+/// from the spec's nodes.
+#[derive(Clone, Debug)]
+pub struct EdgeSpec {
+    pub source: Index,
+    pub sink: Index,
+    pub precedence: i32,
+}
+
+impl GraphSpec {
+    /// Builds a stack graph that contains a single file described by this spec.
+    pub fn build(&self) -> StackGraph {
+        let mut graph = StackGraph::new();
+        let file = graph.add_file("test").unwrap();
+        self.load_into(&mut graph, file);
+        graph
+    }
+
+    /// Adds the nodes and edges described by this spec to a file of an existing stack graph, and
+    /// returns the node handles in the order of the spec's nodes.
+    ///
+    /// All push scoped symbol nodes attach an additional exported scope node of the file, which
+    /// is not included in the result.  Edges from jump to scope nodes are ignored.
+    pub fn load_into(&self, graph: &mut StackGraph, file: Handle<File>) -> Vec<Handle<Node>> {
+        let id = graph.new_node_id(file);
+        let attached_scope = graph.add_scope_node(id, true).unwrap();
+        let attached_scope = graph[attached_scope].id();
+        let mut nodes = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            let id = graph.new_node_id(file);
+            let handle = match node {
+                NodeSpec::DropScopes => graph.add_drop_scopes_node(id),
+                NodeSpec::JumpTo => Some(StackGraph::jump_to_node()),
+                NodeSpec::PopScopedSymbol {
+                    symbol,
+                    is_definition,
+                } => {
+                    let symbol = graph.add_symbol(symbol);
+                    graph.add_pop_scoped_symbol_node(id, symbol, *is_definition)
+                }
+                NodeSpec::PopSymbol {
+                    symbol,
+                    is_definition,
+                } => {
+                    let symbol = graph.add_symbol(symbol);
+                    graph.add_pop_symbol_node(id, symbol, *is_definition)
+                }
+                NodeSpec::PushScopedSymbol {
+                    symbol,
+                    is_reference,
+                } => {
+                    let symbol = graph.add_symbol(symbol);
+                    graph.add_push_scoped_symbol_node(id, symbol, attached_scope, *is_reference)
+                }
+                NodeSpec::PushSymbol {
+                    symbol,
+                    is_reference,
+                } => {
+                    let symbol = graph.add_symbol(symbol);
+                    graph.add_push_symbol_node(id, symbol, *is_reference)
+                }
+                NodeSpec::Root => Some(StackGraph::root_node()),
+                NodeSpec::Scope { is_exported } => graph.add_scope_node(id, *is_exported),
+            };
+            nodes.push(handle.unwrap());
+        }
+        if !nodes.is_empty() {
+            for edge in &self.edges {
+                let source = *edge.source.get(&nodes);
+                let sink = *edge.sink.get(&nodes);
+                if graph[source].is_jump_to() {
+                    continue;
+                }
+                graph.add_edge(source, sink, edge.precedence);
+            }
+        }
+        nodes
+    }
+}
+
+/// Returns a strategy that generates one of the [`SYMBOLS`][].
+pub fn arb_symbol() -> impl Strategy<Value = String> {
+    proptest::sample::select(SYMBOLS).prop_map(String::from)
+}
+
+/// Returns a strategy that generates node specs.
+pub fn arb_node() -> impl Strategy<Value = NodeSpec> {
+    prop_oneof![
+        Just(NodeSpec::DropScopes),
+        Just(NodeSpec::JumpTo),
+        (arb_symbol(), any::<bool>()).prop_map(|(symbol, is_definition)| {
+            NodeSpec::PopScopedSymbol {
+                symbol,
+                is_definition,
+            }
+        }),
+        (arb_symbol(), any::<bool>()).prop_map(|(symbol, is_definition)| NodeSpec::PopSymbol {
+            symbol,
+            is_definition,
+        }),
+        (arb_symbol(), any::<bool>()).prop_map(|(symbol, is_reference)| {
+            NodeSpec::PushScopedSymbol {
+                symbol,
+                is_reference,
+            }
+        }),
+        (arb_symbol(), any::<bool>()).prop_map(|(symbol, is_reference)| NodeSpec::PushSymbol {
+            symbol,
+            is_reference,
+        }),
+        Just(NodeSpec::Root),
+        any::<bool>().prop_map(|is_exported| NodeSpec::Scope { is_exported }),
+    ]
+}
+
+/// Returns a strategy that generates graph specs with at least one and at most `max_nodes` nodes,
+/// and at most twice as many edges.
+pub fn arb_graph(max_nodes: usize) -> impl Strategy<Value = GraphSpec> {
+    let max_nodes = max_nodes.max(1);
+    let edge =
+        (any::<Index>(), any::<Index>(), 0..2).prop_map(|(source, sink, precedence)| EdgeSpec {
+            source,
+            sink,
+            precedence,
+        });
+    (
+        proptest::collection::vec(arb_node(), 1..=max_nodes),
+        proptest::collection::vec(edge, 0..=2 * max_nodes),
+    )
+        .prop_map(|(nodes, edges)| GraphSpec { nodes, edges })
+}
+
+//-------------------------------------------------------------------------------------------------
+// Symbol stacks
+
+/// Describes a partial symbol stack.
+#[derive(Clone, Debug)]
+pub struct SymbolStackSpec {
+    pub symbols: Vec<ScopedSymbolSpec>,
+    pub has_variable: bool,
+}
+
+/// Describes a symbol of a partial symbol stack, with its optional attached scopes.
+#[derive(Clone, Debug)]
+pub struct ScopedSymbolSpec {
+    pub symbol: String,
+    pub scopes: Option<ScopeStackSpec>,
+}
+
+/// Describes a partial scope stack.  The scopes select from the exported scope nodes of the graph
+/// that the stack is built in, and are left out if the graph has none.
+#[derive(Clone, Debug)]
+pub struct ScopeStackSpec {
+    pub scopes: Vec<Index>,
+    pub has_variable: bool,
+}
+
+impl SymbolStackSpec {
+    /// Builds the partial symbol stack described by this spec.  Symbols are added to the graph if
+    /// it does not contain them yet.
+    pub fn build(&self, graph: &mut StackGraph, partials: &mut PartialPaths) -> PartialSymbolStack {
+        let mut stack = if self.has_variable {
+            PartialSymbolStack::from_variable(SymbolStackVariable::new(1).unwrap())
+        } else {
+            PartialSymbolStack::empty()
+        };
+        for symbol in &self.symbols {
+            let scopes = symbol
+                .scopes
+                .as_ref()
+                .map(|scopes| scopes.build(graph, partials));
+            let symbol = PartialScopedSymbol {
+                symbol: graph.add_symbol(&symbol.symbol),
+                scopes: scopes.into(),
+            };
+            stack.push_back(partials, symbol);
+        }
+        stack
+    }
+}
+
+impl ScopeStackSpec {
+    /// Builds the partial scope stack described by this spec.
+    pub fn build(&self, graph: &StackGraph, partials: &mut PartialPaths) -> PartialScopeStack {
+        let mut stack = if self.has_variable {
+            PartialScopeStack::from_variable(ScopeStackVariable::new(1).unwrap())
+        } else {
+            PartialScopeStack::empty()
+        };
+        let exported_scopes = graph
+            .iter_nodes()
+            .filter(|node| graph[*node].is_exported_scope())
+            .collect::<Vec<_>>();
+        if !exported_scopes.is_empty() {
+            for scope in &self.scopes {
+                stack.push_back(partials, *scope.get(&exported_scopes));
+            }
+        }
+        stack
+    }
+}
+
+/// Returns a strategy that generates partial scope stack specs with at most `max_len` scopes.
+pub fn arb_scope_stack(max_len: usize) -> impl Strategy<Value = ScopeStackSpec> {
+    (
+        proptest::collection::vec(any::<Index>(), 0..=max_len),
+        any::<bool>(),
+    )
+        .prop_map(|(scopes, has_variable)| ScopeStackSpec {
+            scopes,
+            has_variable,
+        })
+}
+
+/// Returns a strategy that generates partial symbol stack specs with at most `max_len` symbols.
+/// Attached scope stacks have at most one scope.
+pub fn arb_symbol_stack(max_len: usize) -> impl Strategy<Value = SymbolStackSpec> {
+    let symbol = (arb_symbol(), proptest::option::of(arb_scope_stack(1)))
+        .prop_map(|(symbol, scopes)| ScopedSymbolSpec { symbol, scopes });
+    (
+        proptest::collection::vec(symbol, 0..=max_len),
+        any::<bool>(),
+    )
+        .prop_map(|(symbols, has_variable)| SymbolStackSpec {
+            symbols,
+            has_variable,
+        })
+}
+
+//-------------------------------------------------------------------------------------------------
+// Partial paths
+
+/// Describes a partial path as a walk through a stack graph.  The start selects any node of the
+/// graph, and each step selects one of the outgoing edges of the current end node.  The walk
+/// stops early at nodes without outgoing edges, or if the selected edge cannot extend the path.
+/// It also stops before edges to jump to scope nodes that are resolved from the scope stack,
+/// because resolving them adds an edge to the path that is not an edge of the graph.
+#[derive(Clone, Debug)]
+pub struct PartialPathSpec {
+    pub start: Index,
+    pub steps: Vec<Index>,
+}
+
+impl PartialPathSpec {
+    /// Returns the start node and the edges of the walk described by this spec.
+    pub fn walk(
+        &self,
+        graph: &StackGraph,
+        partials: &mut PartialPaths,
+    ) -> (Handle<Node>, Vec<Edge>) {
+        let nodes = graph.iter_nodes().collect::<Vec<_>>();
+        let start = *self.start.get(&nodes);
+        let mut path = PartialPath::from_node(graph, partials, start);
+        let mut edges = Vec::new();
+        for step in &self.steps {
+            let outgoing = graph.outgoing_edges(path.end_node).collect::<Vec<_>>();
+            if outgoing.is_empty() {
+                break;
+            }
+            let edge = *step.get(&outgoing);
+            let mut extended = path.clone();
+            if extended.append(graph, partials, edge).is_err() || extended.end_node != edge.sink {
+                break;
+            }
+            path = extended;
+            edges.push(edge);
+        }
+        (start, edges)
+    }
+
+    /// Builds the partial path described by this spec.
+    pub fn build(&self, graph: &StackGraph, partials: &mut PartialPaths) -> PartialPath {
+        let (start, edges) = self.walk(graph, partials);
+        partial_path(graph, partials, start, &edges).unwrap()
+    }
+}
+
+/// Returns the partial path that starts at the given node and follows the given edges.
+pub fn partial_path(
+    graph: &StackGraph,
+    partials: &mut PartialPaths,
+    start: Handle<Node>,
+    edges: &[Edge],
+) -> Result<PartialPath, PathResolutionError> {
+    let mut path = PartialPath::from_node(graph, partials, start);
+    for edge in edges {
+        path.append(graph, partials, *edge)?;
+    }
+    Ok(path)
+}
+
+/// Returns a strategy that generates partial path specs with at most `max_len` edges.
+pub fn arb_partial_path(max_len: usize) -> impl Strategy<Value = PartialPathSpec> {
+    (
+        any::<Index>(),
+        proptest::collection::vec(any::<Index>(), 0..=max_len),
+    )
+        .prop_map(|(start, steps)| PartialPathSpec { start, steps })
+}
+
+//-------------------------------------------------------------------------------------------------
+// Invariants
+
+/// Describes a violated partial path invariant.  Paths are included in their display form.
+#[derive(Clone, Debug, Error)]
+pub enum InvariantViolation {
+    #[error("concatenating {a}, {b}, and {c} is not associative: left-associative concatenation gives {left}, but right-associative concatenation gives {right}")]
+    NotAssociative {
+        a: String,
+        b: String,
+        c: String,
+        left: String,
+        right: String,
+    },
+    #[error("{a} and {b} shadow each other")]
+    NotAntisymmetric { a: String, b: String },
+}
+
+/// Checks that concatenating three partial paths gives the same result regardless of how the
+/// concatenations are grouped.  Either both groupings must succeed and produce equal paths, or
+/// both must fail.  Variables of the paths are renamed so that they do not overlap.
+pub fn check_concatenation_associativity(
+    graph: &StackGraph,
+    partials: &mut PartialPaths,
+    a: &PartialPath,
+    b: &PartialPath,
+    c: &PartialPath,
+) -> Result<(), InvariantViolation> {
+    let mut b = b.clone();
+    b.ensure_no_overlapping_variables(partials, a);
+    let mut c = c.clone();
+    c.ensure_no_overlapping_variables(partials, a);
+    c.ensure_no_overlapping_variables(partials, &b);
+
+    let left = {
+        let mut ab = a.clone();
+        ab.concatenate(graph, partials, &b)
+            .and_then(|_| ab.concatenate(graph, partials, &c))
+            .map(|_| ab)
+    };
+    let right = {
+        let mut bc = b.clone();
+        bc.concatenate(graph, partials, &c).and_then(|_| {
+            let mut abc = a.clone();
+            abc.concatenate(graph, partials, &bc)?;
+            Ok(abc)
+        })
+    };
+
+    let associative = match (&left, &right) {
+        (Ok(left), Ok(right)) => {
+            left.equals(partials, right) && left.edges.equals(partials, right.edges)
+        }
+        (Err(_), Err(_)) => true,
+        _ => false,
+    };
+    if associative {
+        return Ok(());
+    }
+    let mut display_result = |result: &Result<PartialPath, PathResolutionError>| match result {
+        Ok(path) => display_path(graph, partials, path),
+        Err(err) => format!("{:?}", err),
+    };
+    let left = display_result(&left);
+    let right = display_result(&right);
+    Err(InvariantViolation::NotAssociative {
+        a: display_path(graph, partials, a),
+        b: display_path(graph, partials, &b),
+        c: display_path(graph, partials, &c),
+        left,
+        right,
+    })
+}
+
+/// Checks that two partial paths do not shadow each other.
+pub fn check_shadows_antisymmetry(
+    graph: &StackGraph,
+    partials: &mut PartialPaths,
+    a: &PartialPath,
+    b: &PartialPath,
+) -> Result<(), InvariantViolation> {
+    if a.shadows(partials, b) && b.shadows(partials, a) {
+        return Err(InvariantViolation::NotAntisymmetric {
+            a: display_path(graph, partials, a),
+            b: display_path(graph, partials, b),
+        });
+    }
+    Ok(())
+}
+
+/// Checks the invariants of a set of partial paths, such as the partial paths that were found in
+/// a file.  Shadowing is checked for all pairs of paths, and associativity for all triples of
+/// paths that can be concatenated in order.
+pub fn check_partial_path_invariants(
+    graph: &StackGraph,
+    partials: &mut PartialPaths,
+    paths: &[PartialPath],
+) -> Result<(), InvariantViolation> {
+    for a in paths {
+        for b in paths {
+            check_shadows_antisymmetry(graph, partials, a, b)?;
+            if a.end_node != b.start_node {
+                continue;
+            }
+            for c in paths {
+                if b.end_node != c.start_node {
+                    continue;
+                }
+                check_concatenation_associativity(graph, partials, a, b, c)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn display_path(graph: &StackGraph, partials: &mut PartialPaths, path: &PartialPath) -> String {
+    path.display(graph, partials).to_string()
+}
@@ -37,3 +37,15 @@ where
         },
     }
 }
+
+/// Returns the approximate number of bytes allocated by a hash map, not including any heap memory
+/// owned by its keys and values.
+pub(crate) fn hash_map_memory_usage<K, V, S>(map: &std::collections::HashMap<K, V, S>) -> usize {
+    map.capacity() * std::mem::size_of::<(K, V)>()
+}
+
+/// Returns the number of bytes allocated by a vector, not including any heap memory owned by its
+/// elements.
+pub(crate) fn vec_memory_usage<T>(vec: &Vec<T>) -> usize {
+    vec.capacity() * std::mem::size_of::<T>()
+}
@@ -0,0 +1,182 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Checks stack graphs for mistakes that are likely caused by bugs in the rules that created them.
+//!
+//! Such mistakes do not prevent a stack graph from being constructed, but only show up later,
+//! when references do not resolve as expected.  Validating a graph right after it is built points
+//! at the nodes that are the likely cause instead.  Findings are not necessarily errors.  For
+//! example, a symbol that is popped in one file can be pushed in another, so a finding about it
+//! can be spurious if the graph only contains a single file.
+
+use std::collections::HashSet;
+use std::fmt::Display;
+
+use lsp_positions::Span;
+
+use crate::arena::Handle;
+use crate::graph::Node;
+use crate::graph::NodeID;
+use crate::graph::StackGraph;
+use crate::graph::Symbol;
+
+/// A possible mistake in a stack graph.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ValidationFinding {
+    /// An edge whose source or sink is not a node of the graph.
+    MissingEdgeNode {
+        source: Handle<Node>,
+        sink: Handle<Node>,
+    },
+    /// A push scoped symbol node whose attached scope is not an exported scope node of the graph.
+    MissingAttachedScope { node: Handle<Node>, scope: NodeID },
+    /// A pop symbol node, which is not a definition, whose symbol is not pushed by any node of
+    /// the graph.
+    UnmatchedPop {
+        node: Handle<Node>,
+        symbol: Handle<Symbol>,
+    },
+    /// An exported scope node that is not attached by any push scoped symbol node of the graph.
+    UnusedExportedScope { node: Handle<Node> },
+    /// A definition without a source span.
+    DefinitionWithoutSourceInfo { node: Handle<Node> },
+}
+
+impl ValidationFinding {
+    /// Returns the node that this finding is about.  For edges, this is the source node.
+    pub fn node(&self) -> Handle<Node> {
+        match self {
+            Self::MissingEdgeNode { source, .. } => *source,
+            Self::MissingAttachedScope { node, .. } => *node,
+            Self::UnmatchedPop { node, .. } => *node,
+            Self::UnusedExportedScope { node } => *node,
+            Self::DefinitionWithoutSourceInfo { node } => *node,
+        }
+    }
+
+    pub fn display<'a>(&'a self, graph: &'a StackGraph) -> impl Display + 'a {
+        DisplayValidationFinding {
+            finding: self,
+            graph,
+        }
+    }
+}
+
+struct DisplayValidationFinding<'a> {
+    finding: &'a ValidationFinding,
+    graph: &'a StackGraph,
+}
+
+impl Display for DisplayValidationFinding<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let graph = self.graph;
+        match self.finding {
+            ValidationFinding::MissingEdgeNode { source, sink } => {
+                write!(
+                    f,
+                    "edge {:?} -> {:?} refers to a missing node",
+                    source, sink
+                )
+            }
+            ValidationFinding::MissingAttachedScope { node, scope } => write!(
+                f,
+                "{} attaches {}, which is not an exported scope",
+                graph[*node].display(graph),
+                scope.display(graph)
+            ),
+            ValidationFinding::UnmatchedPop { node, symbol } => write!(
+                f,
+                "{} pops symbol {}, which is never pushed",
+                graph[*node].display(graph),
+                symbol.display(graph)
+            ),
+            ValidationFinding::UnusedExportedScope { node } => write!(
+                f,
+                "{} is exported, but never attached to a symbol",
+                graph[*node].display(graph)
+            ),
+            ValidationFinding::DefinitionWithoutSourceInfo { node } => write!(
+                f,
+                "{} is a definition without a source span",
+                graph[*node].display(graph)
+            ),
+        }
+    }
+}
+
+impl StackGraph {
+    /// Checks this stack graph for possible mistakes, and returns what was found.
+    pub fn validate(&self) -> Vec<ValidationFinding> {
+        let nodes = self.iter_nodes().collect::<HashSet<_>>();
+        let mut pushed_symbols = HashSet::new();
+        let mut attached_scopes = HashSet::new();
+        for node in self.iter_nodes() {
+            match &self[node] {
+                Node::PushScopedSymbol(push) => {
+                    pushed_symbols.insert(push.symbol);
+                    attached_scopes.insert(push.scope);
+                }
+                Node::PushSymbol(push) => {
+                    pushed_symbols.insert(push.symbol);
+                }
+                _ => {}
+            }
+        }
+
+        let mut findings = Vec::new();
+        for node in self.iter_nodes() {
+            for edge in self.outgoing_edges(node) {
+                if !nodes.contains(&edge.sink) {
+                    findings.push(ValidationFinding::MissingEdgeNode {
+                        source: edge.source,
+                        sink: edge.sink,
+                    });
+                }
+            }
+            match &self[node] {
+                Node::PopScopedSymbol(pop)
+                    if !pop.is_definition && !pushed_symbols.contains(&pop.symbol) =>
+                {
+                    findings.push(ValidationFinding::UnmatchedPop {
+                        node,
+                        symbol: pop.symbol,
+                    });
+                }
+                Node::PopSymbol(pop)
+                    if !pop.is_definition && !pushed_symbols.contains(&pop.symbol) =>
+                {
+                    findings.push(ValidationFinding::UnmatchedPop {
+                        node,
+                        symbol: pop.symbol,
+                    });
+                }
+                Node::PushScopedSymbol(push)
+                    if !self
+                        .node_for_id(push.scope)
+                        .is_some_and(|scope| self[scope].is_exported_scope()) =>
+                {
+                    findings.push(ValidationFinding::MissingAttachedScope {
+                        node,
+                        scope: push.scope,
+                    });
+                }
+                Node::Scope(scope) if scope.is_exported && !attached_scopes.contains(&scope.id) => {
+                    findings.push(ValidationFinding::UnusedExportedScope { node });
+                }
+                _ => {}
+            }
+            if self[node].is_definition()
+                && self
+                    .source_info(node)
+                    .is_none_or(|source_info| source_info.span == Span::default())
+            {
+                findings.push(ValidationFinding::DefinitionWithoutSourceInfo { node });
+            }
+        }
+        findings
+    }
+}
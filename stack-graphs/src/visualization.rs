@@ -5,7 +5,16 @@
 // Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
 // ------------------------------------------------------------------------------------------------
 
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+
+use lsp_positions::Span;
+use serde_json::json;
 use serde_json::Error;
+use serde_json::Value;
 
 use crate::arena::Handle;
 use crate::graph::File;
@@ -14,7 +23,10 @@ use crate::graph::StackGraph;
 use crate::partial::PartialPath;
 use crate::partial::PartialPaths;
 use crate::serde::Filter;
+use crate::serde::ImplicationFilter;
 use crate::stitching::Database;
+use crate::ContentProvider;
+use crate::NoContent;
 
 static CSS: &'static str = include_str!("visualization/visualization.css");
 static D3: &'static str = include_str!("visualization/d3.min.js");
@@ -24,6 +36,14 @@ static JS: &'static str = include_str!("visualization/visualization.js");
 static PKG: &'static str = env!("CARGO_PKG_NAME");
 static VERSION: &'static str = env!("CARGO_PKG_VERSION");
 
+/// Graphs with more nodes than this are embedded in chunks per file, which are only loaded and
+/// rendered when the file is selected in the visualization.  Rendering all nodes of larger graphs
+/// at once makes the browser unresponsive.
+const LAZY_LOADING_NODE_COUNT: usize = 1000;
+
+/// Source lines that are longer than this number of characters are truncated in tooltips.
+const MAX_SOURCE_LINE_LENGTH: usize = 120;
+
 //-----------------------------------------------------------------------------
 // StackGraph
 
@@ -35,10 +55,29 @@ impl StackGraph {
         db: &mut Database,
         filter: &dyn Filter,
     ) -> Result<String, Error> {
+        let mut html = Vec::new();
+        self.write_html(&mut html, title, partials, db, filter, &mut NoContent)?;
+        Ok(String::from_utf8(html).expect("visualization is valid UTF-8"))
+    }
+
+    /// Writes the visualization to the given writer.  The graph and paths are serialized directly
+    /// into the writer, which avoids holding the complete visualization in memory.
+    ///
+    /// The source lines of nodes are read from the content provider, and shown in the tooltips of
+    /// the nodes.  Files for which the content provider returns no content, or an error, are
+    /// visualized without source lines.
+    pub fn write_html<W: Write>(
+        &self,
+        mut writer: W,
+        title: &str,
+        partials: &mut PartialPaths,
+        db: &mut Database,
+        filter: &dyn Filter,
+        content: &mut dyn ContentProvider,
+    ) -> Result<(), Error> {
         let filter = VisualizationFilter(filter);
-        let graph = serde_json::to_string(&self.to_serializable_filter(&filter))?;
-        let paths = serde_json::to_string(&db.to_serializable_filter(self, partials, &filter))?;
-        let html = format!(
+        write!(
+            writer,
             r#"
 <!DOCTYPE html>
 <html lang="en">
@@ -68,10 +107,44 @@ impl StackGraph {
 {JS}
 </script>
 
-<script type="text/javascript">
-  let graph = {graph};
-  let paths = {paths};
-</script>
+"#
+        )
+        .map_err(Error::io)?;
+
+        let node_count = self
+            .iter_nodes()
+            .filter(|node| ImplicationFilter(&filter).include_node(self, node))
+            .count();
+        let sources = self.source_lines(&filter, content);
+        if node_count > LAZY_LOADING_NODE_COUNT {
+            let graph = serde_json::to_value(self.to_serializable_filter(&filter))?;
+            let paths = serde_json::to_value(db.to_serializable_filter(self, partials, &filter))?;
+            write_chunked_data(&mut writer, graph, paths, sources)?;
+        } else {
+            writer
+                .write_all(b"<script type=\"text/javascript\">\n  let graph = ")
+                .map_err(Error::io)?;
+            serde_json::to_writer(
+                ScriptWriter::new(&mut writer),
+                &self.to_serializable_filter(&filter),
+            )?;
+            writer.write_all(b";\n  let paths = ").map_err(Error::io)?;
+            serde_json::to_writer(
+                ScriptWriter::new(&mut writer),
+                &db.to_serializable_filter(self, partials, &filter),
+            )?;
+            writer
+                .write_all(b";\n  let sources = ")
+                .map_err(Error::io)?;
+            serde_json::to_writer(ScriptWriter::new(&mut writer), &sources)?;
+            writer
+                .write_all(b";\n  let chunks = null;\n</script>")
+                .map_err(Error::io)?;
+        }
+
+        write!(
+            writer,
+            r#"
 
 <style>
   html, body, #container {{
@@ -89,14 +162,199 @@ impl StackGraph {
   </div>
   <script type="text/javascript">
     const container = d3.select("\#container");
-    new StackGraph(container, graph, paths, {{ version: "{PKG} {VERSION}" }});
+    new StackGraph(container, graph, paths, {{ version: "{PKG} {VERSION}" }}, chunks, sources);
   </script>
 </body>
 
 </html>
 "#
-        );
-        Ok(html)
+        )
+        .map_err(Error::io)?;
+        writer.flush().map_err(Error::io)
+    }
+
+    /// Returns the source lines that the spans of the included nodes start at, by file name and
+    /// zero-based line number.
+    fn source_lines(
+        &self,
+        filter: &dyn Filter,
+        content: &mut dyn ContentProvider,
+    ) -> BTreeMap<String, BTreeMap<usize, String>> {
+        let mut lines = BTreeMap::<Handle<File>, BTreeSet<usize>>::new();
+        for node in self.iter_nodes() {
+            if !ImplicationFilter(filter).include_node(self, &node) {
+                continue;
+            }
+            let file = match self[node].file() {
+                Some(file) => file,
+                None => continue,
+            };
+            match self.source_info(node) {
+                Some(source_info) if source_info.span != Span::default() => {
+                    lines
+                        .entry(file)
+                        .or_default()
+                        .insert(source_info.span.start.line);
+                }
+                _ => continue,
+            }
+        }
+
+        let mut sources = BTreeMap::new();
+        for (file, lines) in lines {
+            let name = self[file].name();
+            let text = match content.get(Path::new(name)) {
+                Ok(Some(text)) => text,
+                _ => continue,
+            };
+            let file_sources = text
+                .lines()
+                .enumerate()
+                .filter(|(line, _)| lines.contains(line))
+                .map(|(line, text)| {
+                    let text = text.trim();
+                    let text = match text.char_indices().nth(MAX_SOURCE_LINE_LENGTH) {
+                        Some((end, _)) => format!("{}…", &text[..end]),
+                        None => text.to_string(),
+                    };
+                    (line, text)
+                })
+                .collect();
+            sources.insert(name.to_string(), file_sources);
+        }
+        sources
+    }
+}
+
+/// Splits the serialized graph and paths into a chunk per file, each embedded in a separate script
+/// element that the browser does not parse until the visualization loads it.  Nodes without a
+/// file, and the edges and paths between them, are loaded immediately.  Edges belong to the file
+/// of their source node, or of their sink node if the source node has no file, and paths belong to
+/// the file of their start node.
+fn write_chunked_data<W: Write>(
+    writer: &mut W,
+    mut graph: Value,
+    mut paths: Value,
+    mut sources: BTreeMap<String, BTreeMap<usize, String>>,
+) -> Result<(), Error> {
+    fn file_of(id: &Value) -> Option<String> {
+        id["file"].as_str().map(str::to_string)
+    }
+
+    let files = graph["files"].take();
+    let mut chunks = BTreeMap::<Option<String>, Chunk>::new();
+    for node in take_array(&mut graph["nodes"]) {
+        let file = file_of(&node["id"]);
+        chunks.entry(file).or_default().nodes.push(node);
+    }
+    for edge in take_array(&mut graph["edges"]) {
+        let file = file_of(&edge["source"]).or_else(|| file_of(&edge["sink"]));
+        chunks.entry(file).or_default().edges.push(edge);
+    }
+    for path in take_array(&mut paths) {
+        let file = file_of(&path["start_node"]);
+        chunks.entry(file).or_default().paths.push(path);
+    }
+
+    let global = chunks.remove(&None).unwrap_or_default();
+    writer
+        .write_all(b"<script type=\"text/javascript\">\n  let graph = ")
+        .map_err(Error::io)?;
+    serde_json::to_writer(
+        ScriptWriter::new(&mut *writer),
+        &json!({ "files": files, "nodes": global.nodes, "edges": global.edges }),
+    )?;
+    writer.write_all(b";\n  let paths = ").map_err(Error::io)?;
+    serde_json::to_writer(ScriptWriter::new(&mut *writer), &global.paths)?;
+    let index = chunks
+        .keys()
+        .enumerate()
+        .map(|(i, file)| json!({ "file": file, "id": format!("sg-chunk-{}", i) }))
+        .collect::<Vec<_>>();
+    writer
+        .write_all(b";\n  let sources = {};\n  let chunks = ")
+        .map_err(Error::io)?;
+    serde_json::to_writer(ScriptWriter::new(&mut *writer), &index)?;
+    writer.write_all(b";\n</script>\n").map_err(Error::io)?;
+    for (i, (file, chunk)) in chunks.into_iter().enumerate() {
+        let file_sources = file
+            .and_then(|file| sources.remove(&file))
+            .unwrap_or_default();
+        write!(
+            writer,
+            "<script type=\"application/json\" id=\"sg-chunk-{}\">",
+            i
+        )
+        .map_err(Error::io)?;
+        serde_json::to_writer(
+            ScriptWriter::new(&mut *writer),
+            &json!({
+                "nodes": chunk.nodes,
+                "edges": chunk.edges,
+                "paths": chunk.paths,
+                "sources": file_sources,
+            }),
+        )?;
+        writer.write_all(b"</script>\n").map_err(Error::io)?;
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+struct Chunk {
+    nodes: Vec<Value>,
+    edges: Vec<Value>,
+    paths: Vec<Value>,
+}
+
+fn take_array(value: &mut Value) -> Vec<Value> {
+    match value.take() {
+        Value::Array(values) => values,
+        _ => Vec::new(),
+    }
+}
+
+/// A writer for JSON that is embedded in a script element.  A script element ends at the first
+/// occurrence of `</`, even inside of a string, so the slash is escaped, which is equivalent in
+/// JSON strings.
+struct ScriptWriter<W> {
+    inner: W,
+    after_lt: bool,
+}
+
+impl<W: Write> ScriptWriter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            after_lt: false,
+        }
+    }
+}
+
+impl<W: Write> Write for ScriptWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut start = 0;
+        for (i, b) in buf.iter().enumerate() {
+            let after_lt = if i == 0 {
+                self.after_lt
+            } else {
+                buf[i - 1] == b'<'
+            };
+            if *b == b'/' && after_lt {
+                self.inner.write_all(&buf[start..i])?;
+                self.inner.write_all(b"\\")?;
+                start = i;
+            }
+        }
+        self.inner.write_all(&buf[start..])?;
+        if let Some(last) = buf.last() {
+            self.after_lt = *last == b'<';
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
     }
 }
 
@@ -17,12 +17,22 @@ class StackGraph {
     static arrow_head_w = 16;
     static arrow_head_h = 8;
 
-    constructor(container, graph, paths, metadata) {
+    static viewport_padding = 200;
+    static max_listed_files = 100;
+
+    constructor(container, graph, paths, metadata, chunks, sources) {
         this.metadata = metadata;
 
         this.graph = graph;
         this.paths = paths;
+        this.sources = sources ?? {};
+        this.chunks = chunks ?? null;
+        this.loaded_chunks = new Set();
+        this.visible_files = new Set();
         this.compute_data();
+        if (this.is_lazy() && this.chunks.length > 0) {
+            this.show_file(this.chunks[0].file);
+        }
 
         this.current_node = null;
         this.current_edge = null;
@@ -34,22 +44,29 @@ class StackGraph {
     compute_data() {
         this.ID = {};
         this.N = [];
-        this.compute_node_data();
-        this.compute_path_data();
+        this.jumps = {};
+        this.compute_node_data(this.graph.nodes);
+        if (this.is_lazy()) {
+            this.load_path_nodes(this.paths);
+        }
+        this.compute_path_data(this.paths);
     }
 
-    compute_node_data() {
-        for (let i in graph.nodes) {
-            const node = graph.nodes[i];
+    compute_node_data(nodes) {
+        for (let node of nodes) {
+            const id = this.node_to_id_str(node);
+            if (this.ID.hasOwnProperty(id)) {
+                continue;
+            }
             node.paths = []
-            this.ID[this.node_to_id_str(node)] = i;
+            this.ID[id] = `${this.N.length}`;
             this.N.push(node);
         }
     }
 
-    compute_path_data() {
-        const jumps = {};
-        for (let path of this.paths) {
+    compute_path_data(paths) {
+        const jumps = this.jumps;
+        for (let path of paths) {
             const node_ids = path.edges.map((e) => e.source);
             node_ids.push(path.end_node);
             const nodes = {};
@@ -82,6 +99,85 @@ class StackGraph {
         }
     }
 
+    // ------------------------------------------------------------------------------------------------
+    // Lazy Loading
+    //
+    // Large graphs are embedded as a chunk per file, which is only parsed when it is needed. Only
+    // the nodes and edges of visible files are rendered.
+    //
+
+    is_lazy() {
+        return this.chunks !== null;
+    }
+
+    // Loads the nodes and edges of the file's chunk, and returns the chunk.
+    load_chunk_nodes(file) {
+        const chunk = this.chunks.find((chunk) => chunk.file === file);
+        if (chunk === undefined) {
+            return null;
+        }
+        if (chunk.data === undefined) {
+            chunk.data = JSON.parse(document.getElementById(chunk.id).textContent);
+            this.sources[chunk.file] = chunk.data.sources;
+            this.compute_node_data(chunk.data.nodes);
+            this.graph.nodes.push(...chunk.data.nodes);
+            this.graph.edges.push(...chunk.data.edges);
+        }
+        return chunk;
+    }
+
+    // Loads the nodes of all files that the paths pass through.
+    load_path_nodes(paths) {
+        const files = new Set();
+        for (let path of paths) {
+            for (let edge of path.edges) {
+                files.add(edge.source.file);
+            }
+            files.add(path.start_node.file);
+            files.add(path.end_node.file);
+        }
+        for (let file of files) {
+            if (file !== undefined && file !== null) {
+                this.load_chunk_nodes(file);
+            }
+        }
+    }
+
+    // Loads the file's chunk including its paths.
+    load_chunk(file) {
+        if (this.loaded_chunks.has(file)) {
+            return;
+        }
+        const chunk = this.load_chunk_nodes(file);
+        if (chunk === null) {
+            return;
+        }
+        this.loaded_chunks.add(file);
+        this.load_path_nodes(chunk.data.paths);
+        this.compute_path_data(chunk.data.paths);
+        this.paths.push(...chunk.data.paths);
+    }
+
+    show_file(file) {
+        this.load_chunk(file);
+        this.visible_files.add(file);
+    }
+
+    hide_file(file) {
+        this.visible_files.delete(file);
+    }
+
+    is_visible_node_id(id) {
+        return !id.hasOwnProperty('file') || id.file === null || this.visible_files.has(id.file);
+    }
+
+    visible_edges() {
+        if (!this.is_lazy()) {
+            return this.graph.edges;
+        }
+        return this.graph.edges.filter((edge) => this.is_visible_node_id(edge.source) && this.is_visible_node_id(edge.sink));
+    }
+
     compute_path_stacks(path) {
         let symbol_stack = null;
         let scope_stack = null;
@@ -146,11 +242,16 @@ class StackGraph {
             .attr('height', '100%');
         const background = svg.append("rect")
             .attr("class", "sg-background");
+        this.svg = svg;
         this.sg = svg.append('g').attr('class', 'sg');
+        this.transform = d3.zoomIdentity;
 
         // render UI
         this.render_help();
         this.render_tooltip();
+        if (this.is_lazy()) {
+            this.render_files();
+        }
         this.render_graph()
 
         // pan & zoom
@@ -158,9 +259,11 @@ class StackGraph {
             .on('start', (e) => {
                 background.classed("engaged", true);
             }).on('zoom', (e) => {
+                this.transform = e.transform;
                 this.sg.attr('transform', e.transform);
             }).on('end', (e) => {
                 background.classed("engaged", false);
+                this.cull_viewport();
             });
         background.call(zoom);
 
@@ -184,12 +287,19 @@ class StackGraph {
 
         const edge_group = this.sg.append("g");
         const node_group = this.sg.append("g");
+        this.node_selection = null;
+        this.edge_selection = null;
+
+        const graph_edges = this.visible_edges();
+        if (graph_edges.length === 0) {
+            return;
+        }
 
         const connect = d3.dagConnect()
             .sourceId((edge) => this.ID[this.node_id_to_str(edge.source)])
             .targetId((edge) => this.ID[this.node_id_to_str(edge.sink)])
             .decycle(true);
-        const dag = connect(this.graph.edges);
+        const dag = connect(graph_edges);
 
         // plot nodes
         const nodes = node_group
@@ -282,6 +392,28 @@ class StackGraph {
                 this.tooltip_mouseout(e);
             });
 
+        this.node_selection = nodes;
+        this.edge_selection = edges;
+        this.cull_viewport();
+    }
+
+    // Hides nodes and edges that are outside of the viewport, so that the browser does not have to
+    // draw them. Only done for large graphs, where drawing everything makes panning sluggish.
+    cull_viewport() {
+        if (!this.is_lazy() || this.node_selection === null) {
+            return;
+        }
+        const { width, height } = this.svg.node().getBoundingClientRect();
+        const [x0, y0] = this.transform.invert([-StackGraph.viewport_padding, -StackGraph.viewport_padding]);
+        const [x1, y1] = this.transform.invert([width + StackGraph.viewport_padding, height + StackGraph.viewport_padding]);
+        const overlaps = (l, t, r, b) => r >= x0 && l <= x1 && b >= y0 && t <= y1;
+        this.node_selection.style("display", (d) =>
+            overlaps(d.x - d.width, d.y - d.height, d.x + d.width, d.y + d.height) ? null : "none");
+        this.edge_selection.style("display", (d) => {
+            const xs = d.points.map((p) => p.x);
+            const ys = d.points.map((p) => p.y);
+            return overlaps(Math.min(...xs), Math.min(...ys), Math.max(...xs), Math.max(...ys)) ? null : "none";
+        });
     }
 
     render_node(node, g) {
@@ -729,6 +861,10 @@ class StackGraph {
         }
         if (this.node_has_source_info(node)) {
             tooltip.add_row("location", this.source_info_to_str(node.source_info));
+            const source = this.node_source_line(node);
+            if (source !== undefined) {
+                tooltip.add_row("source", source);
+            }
         }
         if (node.paths.length > 0) {
             tooltip.add_row("outgoing paths", `${node.paths.length}`);
@@ -768,6 +904,66 @@ class StackGraph {
             || (this.current_edge !== null && path.derived.edges.hasOwnProperty(this.edge_to_id_str(this.current_edge)));
     }
 
+    // ------------------------------------------------------------------------------------------------
+    // Files
+    //
+
+    render_files() {
+        const files = d3.select('body').append('div')
+            .attr('class', 'sg-files');
+        files.append('h1').text("Files");
+        const search = files.append('input')
+            .attr('type', 'search')
+            .attr('class', 'sg-files-search')
+            .attr('placeholder', "filter files");
+        const list = files.append('div')
+            .attr('class', 'sg-files-list');
+        const more = files.append('p')
+            .attr('class', 'sg-files-more');
+
+        const update = () => {
+            const query = search.property('value');
+            const matches = this.chunks
+                .map((chunk) => chunk.file)
+                .filter((file) => file.includes(query));
+            const listed = matches.slice(0, StackGraph.max_listed_files);
+            list.selectAll('*').remove();
+            for (let file of listed) {
+                const item = list.append('div');
+                item.append('input')
+                    .attr('type', 'checkbox')
+                    .attr('class', 'sg-toggle-input')
+                    .property('checked', this.visible_files.has(file))
+                    .on('change', (e) => {
+                        this.toggle_file(file, e.target.checked);
+                    });
+                item.append('span')
+                    .attr('class', 'sg-files-name')
+                    .attr('title', file)
+                    .text(file);
+            }
+            more.text(matches.length > listed.length ? `${matches.length - listed.length} more files` : "");
+        };
+        search.on('input', update);
+        update();
+    }
+
+    toggle_file(file, visible) {
+        if (this.paths_lock !== null) {
+            this.paths_nolight(this.paths_lock.node, this.paths_lock.path);
+            this.paths_lock = null;
+        }
+        this.current_node = null;
+        this.current_edge = null;
+        this.tooltip_update();
+        if (visible) {
+            this.show_file(file);
+        } else {
+            this.hide_file(file);
+        }
+        this.render_graph();
+    }
+
     // ------------------------------------------------------------------------------------------------
     // Help
     //
@@ -812,6 +1008,15 @@ class StackGraph {
             Path selection ends after cycling through all paths by clicking the node, or by pressing the <kbd>esc</kbd> key.
         `);
 
+        if (this.is_lazy()) {
+            help_content.append("h1").text("Files");
+            help_content.append("p").html(`
+                This graph is too large to show at once.
+                Select the files to show in the file list.
+                Edges between shown and hidden files are not shown.
+            `);
+        }
+
         help_content.append("p").attr("class", "sg-help-meta").html(`
             Toggle visibility of this help anytime by pressing <kbd>h</kbd>.
         `);
@@ -901,6 +1106,12 @@ class StackGraph {
         return `line ${line + 1} column ${column + 1}`;
     }
 
+    node_source_line(node) {
+        const file = node.id.file;
+        const line = node.source_info.span.start.line;
+        return this.sources[file]?.[line];
+    }
+
     source_info_is_empty(source_info) {
         return source_info.span.start.line === 0
             && source_info.span.start.column.utf8_offset === 0
@@ -8,6 +8,8 @@
 use stack_graphs::arena::Arena;
 use stack_graphs::arena::Deque;
 use stack_graphs::arena::DequeArena;
+use stack_graphs::arena::Handle;
+use stack_graphs::arena::HandleIndex;
 use stack_graphs::arena::List;
 use stack_graphs::arena::ListArena;
 use stack_graphs::arena::ReversibleList;
@@ -28,6 +30,24 @@ fn can_allocate_in_arena() {
     assert_ne!(arena.get(hello2), arena.get(there));
 }
 
+#[test]
+fn handles_are_as_wide_as_handle_index() {
+    assert_eq!(
+        std::mem::size_of::<Handle<String>>(),
+        std::mem::size_of::<HandleIndex>()
+    );
+    assert_eq!(
+        std::mem::size_of::<Option<Handle<String>>>(),
+        std::mem::size_of::<HandleIndex>()
+    );
+    let mut arena = Arena::new();
+    let hello = arena.add("hello".to_string());
+    let there = arena.add("there".to_string());
+    assert_eq!(hello.as_index(), 1);
+    assert_eq!(there.as_index(), 2);
+    assert_eq!(there.as_u32(), 2);
+}
+
 #[test]
 fn can_allocate_in_supplemental_arena() {
     let mut arena = Arena::<u32>::new();
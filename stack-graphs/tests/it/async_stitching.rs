@@ -0,0 +1,285 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use std::collections::BTreeSet;
+use std::collections::HashSet;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
+use std::task::Wake;
+use std::thread::Thread;
+
+use pretty_assertions::assert_eq;
+use stack_graphs::arena::Handle;
+use stack_graphs::graph::StackGraph;
+use stack_graphs::partial::PartialPath;
+use stack_graphs::partial::PartialPaths;
+use stack_graphs::stitching::AsyncForwardCandidates;
+use stack_graphs::stitching::Database;
+use stack_graphs::stitching::DatabaseCandidates;
+use stack_graphs::stitching::ForwardCandidates;
+use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::CancellationError;
+use stack_graphs::CancellationFlag;
+use stack_graphs::NoCancellation;
+
+use crate::test_graphs;
+
+/// Runs a future to completion on the current thread.
+fn block_on<F: Future>(future: F) -> F::Output {
+    struct ThreadWaker(Thread);
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+    let waker = Arc::new(ThreadWaker(std::thread::current())).into();
+    let mut cx = Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => std::thread::park(),
+        }
+    }
+}
+
+/// Does not compile if the value is not `Send`, which futures must be to run on multi-threaded
+/// executors.
+fn assert_send<T: Send>(_: &T) {}
+
+/// A future that is pending once, like a fetch from remote storage.
+struct Fetch(bool);
+
+impl Future for Fetch {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// Candidates that are lazily copied from a remote database into a local one.
+struct RemoteCandidates<'a> {
+    graph: &'a StackGraph,
+    partials: &'a mut PartialPaths,
+    remote: Database,
+    local: Database,
+    copied: HashSet<Handle<PartialPath>>,
+    fetches: usize,
+}
+
+impl RemoteCandidates<'_> {
+    fn copy_candidates(&mut self, path: &PartialPath) {
+        let mut handles = Vec::new();
+        self.remote
+            .find_candidate_partial_paths(self.graph, self.partials, path, &mut handles);
+        for handle in handles {
+            if self.copied.insert(handle) {
+                self.local
+                    .add_partial_path(self.graph, self.partials, self.remote[handle].clone());
+            }
+        }
+    }
+}
+
+impl ForwardCandidates<Handle<PartialPath>, PartialPath, Database, CancellationError>
+    for RemoteCandidates<'_>
+{
+    fn load_forward_candidates(
+        &mut self,
+        path: &PartialPath,
+        _cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<(), CancellationError> {
+        self.copy_candidates(path);
+        Ok(())
+    }
+
+    fn get_forward_candidates<R>(&mut self, path: &PartialPath, result: &mut R)
+    where
+        R: std::iter::Extend<Handle<PartialPath>>,
+    {
+        self.local
+            .find_candidate_partial_paths(self.graph, self.partials, path, result);
+    }
+
+    fn get_graph_partials_and_db(&mut self) -> (&StackGraph, &mut PartialPaths, &Database) {
+        (self.graph, self.partials, &self.local)
+    }
+}
+
+impl AsyncForwardCandidates<Handle<PartialPath>, PartialPath, Database, CancellationError>
+    for RemoteCandidates<'_>
+{
+    async fn load_forward_candidates_async(
+        &mut self,
+        path: &PartialPath,
+        _cancellation_flag: &(dyn CancellationFlag + Sync),
+    ) -> Result<(), CancellationError> {
+        Fetch(false).await;
+        self.fetches += 1;
+        self.copy_candidates(path);
+        Ok(())
+    }
+}
+
+/// Cancels once candidates are loaded.
+struct CancelWhenLoading;
+
+impl CancellationFlag for CancelWhenLoading {
+    fn check(&self, at: &'static str) -> Result<(), CancellationError> {
+        if at == "loading forward candidates" {
+            return Err(CancellationError(at));
+        }
+        Ok(())
+    }
+}
+
+fn minimal_paths(graph: &StackGraph, partials: &mut PartialPaths) -> Database {
+    let mut db = Database::new();
+    for file in graph.iter_files() {
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            graph,
+            partials,
+            file,
+            &NoCancellation,
+            |graph, partials, path| {
+                db.add_partial_path(graph, partials, path.clone());
+            },
+        )
+        .expect("should never be cancelled");
+    }
+    db
+}
+
+fn references(graph: &StackGraph) -> Vec<Handle<stack_graphs::graph::Node>> {
+    graph
+        .iter_nodes()
+        .filter(|handle| graph[*handle].is_reference())
+        .collect()
+}
+
+fn sync_complete_paths(graph: &StackGraph) -> BTreeSet<String> {
+    let mut partials = PartialPaths::new();
+    let mut db = minimal_paths(graph, &mut partials);
+    let mut results = BTreeSet::new();
+    ForwardPartialPathStitcher::find_all_complete_partial_paths(
+        &mut DatabaseCandidates::new(graph, &mut partials, &mut db),
+        references(graph),
+        &NoCancellation,
+        |graph, partials, path| {
+            results.insert(path.display(graph, partials).to_string());
+        },
+    )
+    .expect("should never be cancelled");
+    results
+}
+
+#[test]
+fn async_stitching_finds_same_paths_as_sync_stitching() {
+    let graph = test_graphs::class_field_through_function_parameter::new();
+    let mut partials = PartialPaths::new();
+    let mut db = minimal_paths(&graph, &mut partials);
+    let mut results = BTreeSet::new();
+    block_on(
+        ForwardPartialPathStitcher::find_all_complete_partial_paths_async(
+            &mut DatabaseCandidates::new(&graph, &mut partials, &mut db),
+            references(&graph),
+            &NoCancellation,
+            |graph, partials, path| {
+                results.insert(path.display(graph, partials).to_string());
+            },
+        ),
+    )
+    .expect("should never be cancelled");
+    assert!(!results.is_empty());
+    assert_eq!(sync_complete_paths(&graph), results);
+}
+
+#[test]
+fn async_stitching_awaits_lazily_loaded_candidates() {
+    let graph = test_graphs::class_field_through_function_parameter::new();
+    let mut partials = PartialPaths::new();
+    let remote = minimal_paths(&graph, &mut partials);
+    let mut candidates = RemoteCandidates {
+        graph: &graph,
+        partials: &mut partials,
+        remote,
+        local: Database::new(),
+        copied: HashSet::new(),
+        fetches: 0,
+    };
+    let mut results = BTreeSet::new();
+    block_on(
+        ForwardPartialPathStitcher::find_all_complete_partial_paths_async(
+            &mut candidates,
+            references(&graph),
+            &NoCancellation,
+            |graph, partials, path| {
+                results.insert(path.display(graph, partials).to_string());
+            },
+        ),
+    )
+    .expect("should never be cancelled");
+    assert!(candidates.fetches > 0);
+    assert_eq!(sync_complete_paths(&graph), results);
+}
+
+#[test]
+fn async_stitching_can_be_cancelled_while_loading_candidates() {
+    let graph = test_graphs::class_field_through_function_parameter::new();
+    let mut partials = PartialPaths::new();
+    let mut db = minimal_paths(&graph, &mut partials);
+    let result = block_on(
+        ForwardPartialPathStitcher::find_all_complete_partial_paths_async(
+            &mut DatabaseCandidates::new(&graph, &mut partials, &mut db),
+            references(&graph),
+            &CancelWhenLoading,
+            |_, _, _| {},
+        ),
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn async_stitching_futures_are_send() {
+    let graph = test_graphs::class_field_through_function_parameter::new();
+    let mut partials = PartialPaths::new();
+    let remote = minimal_paths(&graph, &mut partials);
+    let mut candidates = RemoteCandidates {
+        graph: &graph,
+        partials: &mut partials,
+        remote,
+        local: Database::new(),
+        copied: HashSet::new(),
+        fetches: 0,
+    };
+
+    let mut stitcher = {
+        let (graph, partials, _) = candidates.get_graph_partials_and_db();
+        ForwardPartialPathStitcher::from_starting_nodes(graph, partials, references(graph))
+    };
+    let next_batch = stitcher.next_batch_async(&mut candidates, &NoCancellation);
+    assert_send(&next_batch);
+    block_on(next_batch).expect("should never be cancelled");
+
+    let find_all = ForwardPartialPathStitcher::find_all_complete_partial_paths_async(
+        &mut candidates,
+        references(&graph),
+        &NoCancellation,
+        |_, _, _| {},
+    );
+    assert_send(&find_all);
+    block_on(find_all).expect("should never be cancelled");
+}
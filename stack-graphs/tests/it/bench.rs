@@ -0,0 +1,65 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use stack_graphs::bench::GraphShape;
+use stack_graphs::partial::PartialPaths;
+use stack_graphs::NoCancellation;
+
+fn shape(import_cycles: bool) -> GraphShape {
+    GraphShape {
+        files: 3,
+        fan_out: 2,
+        depth: 2,
+        references: 3,
+        import_cycles,
+    }
+}
+
+#[test]
+fn can_generate_graph_with_shape() {
+    let workload = shape(false).generate();
+    assert_eq!(3, workload.graph.iter_files().count());
+    assert_eq!(9, workload.references.len());
+    let definitions = workload
+        .graph
+        .iter_nodes()
+        .filter(|node| workload.graph[*node].is_definition())
+        .count();
+    // a module and 2 + 4 nested definitions in each file
+    assert_eq!(3 * 7, definitions);
+}
+
+#[test]
+fn references_resolve_in_all_imported_files() {
+    let workload = shape(false).generate();
+    let mut partials = PartialPaths::new();
+    let (mut db, measurement) = workload
+        .partial_paths(&mut partials, &NoCancellation)
+        .expect("Cannot find partial paths");
+    assert!(measurement.paths > 0);
+    let measurement = workload
+        .stitch(&mut partials, &mut db, &NoCancellation)
+        .expect("Cannot stitch paths");
+    // the references in the first file resolve in all three files, those in the last file only
+    // in the last file
+    assert_eq!(3 * (3 + 2 + 1), measurement.paths);
+}
+
+#[test]
+fn references_resolve_in_import_cycles() {
+    let workload = shape(true).generate();
+    let mut partials = PartialPaths::new();
+    let (mut db, _) = workload
+        .partial_paths(&mut partials, &NoCancellation)
+        .expect("Cannot find partial paths");
+    let measurement = workload
+        .stitch(&mut partials, &mut db, &NoCancellation)
+        .expect("Cannot stitch paths");
+    assert_eq!(3 * 3 * 3, measurement.paths);
+    // paths that go around the cycle are pruned
+    assert!(measurement.stats.similar_paths > 0);
+}
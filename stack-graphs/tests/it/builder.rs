@@ -0,0 +1,133 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use std::collections::BTreeSet;
+
+use pretty_assertions::assert_eq;
+use stack_graphs::builder::GraphBuilder;
+use stack_graphs::graph::StackGraph;
+use stack_graphs::partial::PartialPaths;
+use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::stitching::GraphEdgeCandidates;
+use stack_graphs::NoCancellation;
+
+fn edges(graph: &StackGraph) -> BTreeSet<String> {
+    graph
+        .iter_nodes()
+        .flat_map(|node| graph.outgoing_edges(node))
+        .map(|edge| {
+            format!(
+                "{} -{}-> {}",
+                graph[edge.source].display(graph),
+                edge.precedence,
+                graph[edge.sink].display(graph)
+            )
+        })
+        .collect()
+}
+
+fn complete_paths(graph: &StackGraph) -> BTreeSet<String> {
+    let mut partials = PartialPaths::new();
+    let mut results = BTreeSet::new();
+    let references = graph
+        .iter_nodes()
+        .filter(|handle| graph[*handle].is_reference());
+    ForwardPartialPathStitcher::find_all_complete_partial_paths(
+        &mut GraphEdgeCandidates::new(graph, &mut partials, None),
+        references,
+        &NoCancellation,
+        |graph, partials, path| {
+            results.insert(path.display(graph, partials).to_string());
+        },
+    )
+    .expect("should never be cancelled");
+    results
+}
+
+#[test]
+fn can_build_paths() {
+    let mut graph = StackGraph::new();
+    let mut builder = GraphBuilder::new(&mut graph);
+    builder
+        .file("a.py")
+        .from_root()
+        .pop("a")
+        .pop(".")
+        .defines("foo");
+    builder
+        .file("b.py")
+        .references("foo")
+        .scope("b.scope")
+        .push(".")
+        .push("a")
+        .root();
+
+    assert_eq!(
+        [
+            "[a.py(0) pop a] -0-> [a.py(1) pop .]",
+            "[a.py(1) pop .] -0-> [a.py(2) definition foo]",
+            "[b.py(0) reference foo] -0-> [b.py(1) scope]",
+            "[b.py(1) scope] -0-> [b.py(2) push .]",
+            "[b.py(2) push .] -0-> [b.py(3) push a]",
+            "[b.py(3) push a] -0-> [root]",
+            "[root] -0-> [a.py(0) pop a]",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<BTreeSet<_>>(),
+        edges(&graph)
+    );
+    assert_eq!(
+        ["<> () [b.py(0) reference foo] -> [a.py(2) definition foo] <> ()"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<BTreeSet<_>>(),
+        complete_paths(&graph)
+    );
+}
+
+#[test]
+fn can_connect_named_nodes() {
+    let mut graph = StackGraph::new();
+    let mut builder = GraphBuilder::new(&mut graph);
+    builder
+        .file("test.py")
+        .from_root()
+        .exported_scope("module")
+        .defines("x");
+    builder
+        .file("test.py")
+        .from("module")
+        .precedence(1)
+        .defines("y")
+        .label("y");
+    builder
+        .file("test.py")
+        .references("x")
+        .to("module")
+        .push_scoped("f", "call")
+        .drop_scopes();
+    builder.file("test.py").from("call").to("y");
+    assert!(builder.node("module").is_some());
+    assert!(builder.node("unknown").is_none());
+
+    assert_eq!(
+        [
+            "[root] -0-> [test.py(0) exported scope]",
+            "[test.py(0) exported scope] -0-> [test.py(1) definition x]",
+            "[test.py(0) exported scope] -1-> [test.py(2) definition y]",
+            "[test.py(3) reference x] -0-> [test.py(0) exported scope]",
+            "[test.py(0) exported scope] -0-> [test.py(5) push scoped f test.py(4)]",
+            "[test.py(5) push scoped f test.py(4)] -0-> [test.py(6) drop scopes]",
+            "[test.py(4) exported scope] -0-> [test.py(2) definition y]",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<BTreeSet<_>>(),
+        edges(&graph)
+    );
+}
@@ -0,0 +1,92 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2021, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use pretty_assertions::assert_eq;
+use stack_graphs::c::sg_file_handle;
+use stack_graphs::c::sg_node_handle;
+use stack_graphs::c::sg_partial_path;
+use stack_graphs::c::sg_partial_path_arena_find_partial_paths_in_file;
+use stack_graphs::c::sg_partial_path_arena_free;
+use stack_graphs::c::sg_partial_path_arena_new;
+use stack_graphs::c::sg_partial_path_edge_list;
+use stack_graphs::c::sg_partial_path_list_count;
+use stack_graphs::c::sg_partial_path_list_free;
+use stack_graphs::c::sg_partial_path_list_new;
+use stack_graphs::c::sg_partial_path_list_paths;
+use stack_graphs::c::sg_partial_scope_stack;
+use stack_graphs::c::sg_partial_symbol_stack;
+use stack_graphs::c::SG_LIST_EMPTY_HANDLE;
+use stack_graphs::partial::PartialPath;
+use stack_graphs::partial::PartialPathEdgeList;
+use stack_graphs::partial::PartialScopeStack;
+use stack_graphs::partial::PartialSymbolStack;
+
+use crate::c::test_graph::TestGraph;
+use crate::test_graphs;
+
+#[test]
+fn handles_are_64_bits_wide() {
+    assert_eq!(8, std::mem::size_of::<sg_file_handle>());
+    assert_eq!(8, std::mem::size_of::<sg_node_handle>());
+    assert_eq!(u64::MAX, SG_LIST_EMPTY_HANDLE);
+}
+
+#[test]
+fn partial_paths_have_same_layout_as_rust_types() {
+    assert_eq!(
+        std::mem::size_of::<PartialSymbolStack>(),
+        std::mem::size_of::<sg_partial_symbol_stack>()
+    );
+    assert_eq!(
+        std::mem::size_of::<PartialScopeStack>(),
+        std::mem::size_of::<sg_partial_scope_stack>()
+    );
+    assert_eq!(
+        std::mem::size_of::<PartialPathEdgeList>(),
+        std::mem::size_of::<sg_partial_path_edge_list>()
+    );
+    assert_eq!(
+        std::mem::size_of::<PartialPath>(),
+        std::mem::size_of::<sg_partial_path>()
+    );
+}
+
+#[test]
+fn can_find_partial_paths_in_file_with_large_handles() {
+    let graph: TestGraph = test_graphs::cyclic_imports_python::new();
+    let rust_graph = unsafe { &(*graph.graph).inner };
+    let file = rust_graph.get_file("main.py").expect("Missing file");
+
+    let partials = sg_partial_path_arena_new();
+    let path_list = sg_partial_path_list_new();
+    sg_partial_path_arena_find_partial_paths_in_file(
+        graph.graph,
+        partials,
+        file.as_index(),
+        path_list,
+        std::ptr::null(),
+    );
+
+    let paths = unsafe {
+        std::slice::from_raw_parts(
+            sg_partial_path_list_paths(path_list),
+            sg_partial_path_list_count(path_list),
+        )
+    };
+    assert!(!paths.is_empty());
+    // the padding of every deque must be initialized when paths are copied to C
+    for path in paths {
+        assert_eq!(0, path.symbol_stack_precondition.padding);
+        assert_eq!(0, path.symbol_stack_postcondition.padding);
+        assert_eq!(0, path.scope_stack_precondition.padding);
+        assert_eq!(0, path.scope_stack_postcondition.padding);
+        assert_eq!(0, path.edges.padding);
+    }
+
+    sg_partial_path_list_free(path_list);
+    sg_partial_path_arena_free(partials);
+}
@@ -6,13 +6,23 @@
 // ------------------------------------------------------------------------------------------------
 
 mod can_create_graph;
+#[cfg(not(feature = "large-handles"))]
 mod can_find_local_nodes;
+#[cfg(not(feature = "large-handles"))]
 mod can_find_partial_paths_in_file;
+#[cfg(not(feature = "large-handles"))]
 mod can_find_qualified_definitions_with_phased_partial_path_stitching;
 mod can_jump_to_definition;
+#[cfg(not(feature = "large-handles"))]
 mod can_jump_to_definition_with_phased_partial_path_stitching;
 mod files;
+#[cfg(feature = "large-handles")]
+mod large_handles;
+#[cfg(not(feature = "large-handles"))]
 mod nodes;
+#[cfg(not(feature = "large-handles"))]
 mod partial;
+#[cfg(all(feature = "storage", not(feature = "large-handles")))]
+mod storage;
 mod symbols;
 mod test_graph;
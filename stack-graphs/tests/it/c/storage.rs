@@ -0,0 +1,276 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use libc::c_char;
+use pretty_assertions::assert_eq;
+use stack_graphs::c::sg_file_handle;
+use stack_graphs::c::sg_file_status;
+use stack_graphs::c::sg_forward_partial_path_stitcher_free;
+use stack_graphs::c::sg_forward_partial_path_stitcher_from_nodes;
+use stack_graphs::c::sg_forward_partial_path_stitcher_process_next_phase;
+use stack_graphs::c::sg_partial_path_arena_find_partial_paths_in_file;
+use stack_graphs::c::sg_partial_path_arena_free;
+use stack_graphs::c::sg_partial_path_arena_new;
+use stack_graphs::c::sg_partial_path_list_count;
+use stack_graphs::c::sg_partial_path_list_free;
+use stack_graphs::c::sg_partial_path_list_new;
+use stack_graphs::c::sg_partial_path_list_paths;
+use stack_graphs::c::sg_storage_reader;
+use stack_graphs::c::sg_storage_reader_free;
+use stack_graphs::c::sg_storage_reader_load_graph_for_file;
+use stack_graphs::c::sg_storage_reader_load_partial_path_extensions;
+use stack_graphs::c::sg_storage_reader_open;
+use stack_graphs::c::sg_storage_reader_partial_path_arena;
+use stack_graphs::c::sg_storage_reader_partial_path_database;
+use stack_graphs::c::sg_storage_reader_stack_graph;
+use stack_graphs::c::sg_storage_reader_status_for_file;
+use stack_graphs::c::sg_storage_result;
+use stack_graphs::c::sg_storage_writer;
+use stack_graphs::c::sg_storage_writer_free;
+use stack_graphs::c::sg_storage_writer_open;
+use stack_graphs::c::sg_storage_writer_status_for_file;
+use stack_graphs::c::sg_storage_writer_store_error_for_file;
+use stack_graphs::c::sg_storage_writer_store_result_for_file;
+use stack_graphs::c::SG_NULL_HANDLE;
+use stack_graphs::partial::PartialPath;
+
+use crate::c::test_graph::TestGraph;
+use crate::test_graphs;
+
+fn database_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "stack-graphs-c-{}-{}.sqlite",
+        name,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn c_str(s: &str) -> (*const c_char, usize) {
+    (s.as_ptr() as *const c_char, s.len())
+}
+
+fn write_graph(graph: &TestGraph, db_path: &str) {
+    let rust_graph = unsafe { &(*graph.graph).inner };
+    let (path, path_len) = c_str(db_path);
+    let mut writer: *mut sg_storage_writer = std::ptr::null_mut();
+    assert_eq!(
+        sg_storage_result::SG_STORAGE_RESULT_SUCCESS,
+        sg_storage_writer_open(path, path_len, &mut writer)
+    );
+    let partials = sg_partial_path_arena_new();
+    for file in rust_graph.iter_files() {
+        let path_list = sg_partial_path_list_new();
+        sg_partial_path_arena_find_partial_paths_in_file(
+            graph.graph,
+            partials,
+            file.as_u32(),
+            path_list,
+            std::ptr::null(),
+        );
+        let (tag, tag_len) = c_str("tag");
+        assert_eq!(
+            sg_storage_result::SG_STORAGE_RESULT_SUCCESS,
+            sg_storage_writer_store_result_for_file(
+                writer,
+                graph.graph,
+                partials,
+                file.as_u32(),
+                tag,
+                tag_len,
+                sg_partial_path_list_count(path_list),
+                sg_partial_path_list_paths(path_list),
+            )
+        );
+        sg_partial_path_list_free(path_list);
+    }
+    sg_partial_path_arena_free(partials);
+    sg_storage_writer_free(writer);
+}
+
+fn writer_status_for_file(
+    writer: *mut sg_storage_writer,
+    file: &str,
+    tag: Option<&str>,
+) -> sg_file_status {
+    let (file, file_len) = c_str(file);
+    let (tag, tag_len) = tag.map(c_str).unwrap_or((std::ptr::null(), 0));
+    let mut status = sg_file_status::SG_FILE_STATUS_MISSING;
+    assert_eq!(
+        sg_storage_result::SG_STORAGE_RESULT_SUCCESS,
+        sg_storage_writer_status_for_file(writer, file, file_len, tag, tag_len, &mut status)
+    );
+    status
+}
+
+fn reader_status_for_file(
+    reader: *mut sg_storage_reader,
+    file: &str,
+    tag: Option<&str>,
+) -> sg_file_status {
+    let (file, file_len) = c_str(file);
+    let (tag, tag_len) = tag.map(c_str).unwrap_or((std::ptr::null(), 0));
+    let mut status = sg_file_status::SG_FILE_STATUS_MISSING;
+    assert_eq!(
+        sg_storage_result::SG_STORAGE_RESULT_SUCCESS,
+        sg_storage_reader_status_for_file(reader, file, file_len, tag, tag_len, &mut status)
+    );
+    status
+}
+
+#[test]
+fn can_store_and_query_file_status() {
+    let db_path = database_path("status");
+    let db_path = db_path.to_str().unwrap();
+    let graph: TestGraph = test_graphs::sequenced_import_star::new();
+    write_graph(&graph, db_path);
+
+    let (path, path_len) = c_str(db_path);
+    let mut writer: *mut sg_storage_writer = std::ptr::null_mut();
+    assert_eq!(
+        sg_storage_result::SG_STORAGE_RESULT_SUCCESS,
+        sg_storage_writer_open(path, path_len, &mut writer)
+    );
+    let (file, file_len) = c_str("broken.py");
+    let (tag, tag_len) = c_str("tag");
+    let (error, error_len) = c_str("parse error");
+    assert_eq!(
+        sg_storage_result::SG_STORAGE_RESULT_SUCCESS,
+        sg_storage_writer_store_error_for_file(
+            writer, file, file_len, tag, tag_len, error, error_len,
+        )
+    );
+    assert_eq!(
+        sg_file_status::SG_FILE_STATUS_INDEXED,
+        writer_status_for_file(writer, "main.py", None)
+    );
+    assert_eq!(
+        sg_file_status::SG_FILE_STATUS_ERROR,
+        writer_status_for_file(writer, "broken.py", Some("tag"))
+    );
+    assert_eq!(
+        sg_file_status::SG_FILE_STATUS_MISSING,
+        writer_status_for_file(writer, "main.py", Some("other"))
+    );
+    sg_storage_writer_free(writer);
+
+    let mut reader: *mut sg_storage_reader = std::ptr::null_mut();
+    assert_eq!(
+        sg_storage_result::SG_STORAGE_RESULT_SUCCESS,
+        sg_storage_reader_open(path, path_len, &mut reader)
+    );
+    assert_eq!(
+        sg_file_status::SG_FILE_STATUS_INDEXED,
+        reader_status_for_file(reader, "a.py", Some("tag"))
+    );
+    assert_eq!(
+        sg_file_status::SG_FILE_STATUS_MISSING,
+        reader_status_for_file(reader, "missing.py", None)
+    );
+    sg_storage_reader_free(reader);
+
+    let _ = std::fs::remove_file(db_path);
+}
+
+#[test]
+fn cannot_open_missing_database_for_reading() {
+    let db_path = database_path("missing");
+    let (path, path_len) = c_str(db_path.to_str().unwrap());
+    let mut reader: *mut sg_storage_reader = std::ptr::null_mut();
+    assert_eq!(
+        sg_storage_result::SG_STORAGE_RESULT_MISSING_DATABASE,
+        sg_storage_reader_open(path, path_len, &mut reader)
+    );
+    assert!(reader.is_null());
+}
+
+#[test]
+fn can_jump_to_definition_with_stored_partial_paths() {
+    let db_path = database_path("jump-to-definition");
+    let db_path = db_path.to_str().unwrap();
+    let graph: TestGraph = test_graphs::sequenced_import_star::new();
+    write_graph(&graph, db_path);
+
+    let (path, path_len) = c_str(db_path);
+    let mut reader: *mut sg_storage_reader = std::ptr::null_mut();
+    assert_eq!(
+        sg_storage_result::SG_STORAGE_RESULT_SUCCESS,
+        sg_storage_reader_open(path, path_len, &mut reader)
+    );
+    let (file, file_len) = c_str("main.py");
+    let mut file_handle: sg_file_handle = SG_NULL_HANDLE;
+    assert_eq!(
+        sg_storage_result::SG_STORAGE_RESULT_SUCCESS,
+        sg_storage_reader_load_graph_for_file(reader, file, file_len, &mut file_handle)
+    );
+    assert_ne!(SG_NULL_HANDLE, file_handle);
+
+    let graph = sg_storage_reader_stack_graph(reader);
+    let partials = sg_storage_reader_partial_path_arena(reader);
+    let db = sg_storage_reader_partial_path_database(reader);
+    let rust_graph = unsafe { &(*graph).inner };
+    let file = rust_graph.get_file("main.py").expect("Missing file");
+    assert_eq!(file.as_u32(), file_handle);
+    let references = rust_graph
+        .iter_nodes()
+        .filter(|handle| {
+            let node = &rust_graph[*handle];
+            node.is_in_file(file) && node.is_reference()
+        })
+        .collect::<Vec<_>>();
+
+    let stitcher = sg_forward_partial_path_stitcher_from_nodes(
+        graph,
+        partials,
+        references.len(),
+        references.as_ptr() as *const _,
+    );
+    let rust_stitcher = unsafe { &mut *stitcher };
+    let mut results = BTreeSet::new();
+    while !rust_stitcher.is_complete {
+        assert_eq!(
+            sg_storage_result::SG_STORAGE_RESULT_SUCCESS,
+            sg_storage_reader_load_partial_path_extensions(
+                reader,
+                rust_stitcher.previous_phase_partial_paths_length,
+                rust_stitcher.previous_phase_partial_paths,
+                std::ptr::null(),
+            )
+        );
+        let rust_graph = unsafe { &(*graph).inner };
+        let rust_partials = unsafe { &mut (*partials).inner };
+        let partial_paths_slice = unsafe {
+            std::slice::from_raw_parts(
+                rust_stitcher.previous_phase_partial_paths as *const PartialPath,
+                rust_stitcher.previous_phase_partial_paths_length,
+            )
+        };
+        for partial_path in partial_paths_slice {
+            if partial_path.is_complete(rust_graph) {
+                results.insert(partial_path.display(rust_graph, rust_partials).to_string());
+            }
+        }
+        sg_forward_partial_path_stitcher_process_next_phase(graph, partials, db, stitcher);
+    }
+    sg_forward_partial_path_stitcher_free(stitcher);
+    sg_storage_reader_free(reader);
+
+    let expected_partial_paths = [
+        "<> () [main.py(8) reference a] -> [a.py(0) definition a] <> ()",
+        "<> () [main.py(6) reference foo] -> [b.py(5) definition foo] <> ()",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect::<BTreeSet<_>>();
+    assert_eq!(expected_partial_paths, results);
+
+    let _ = std::fs::remove_file(db_path);
+}
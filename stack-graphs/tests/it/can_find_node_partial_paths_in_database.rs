@@ -9,6 +9,8 @@ use std::collections::BTreeSet;
 
 use pretty_assertions::assert_eq;
 use stack_graphs::arena::Handle;
+use stack_graphs::builder::GraphBuilder;
+use stack_graphs::graph::Node;
 use stack_graphs::graph::NodeID;
 use stack_graphs::graph::StackGraph;
 use stack_graphs::partial::PartialPath;
@@ -130,3 +132,115 @@ fn sequenced_import_star() {
     );
     // no references in b.py
 }
+
+#[test]
+fn duplicate_paths_are_stored_once() {
+    let graph = test_graphs::simple::new();
+    let file = graph.get_file("test.py").expect("Missing file");
+    let mut partials = PartialPaths::new();
+    let mut paths = Vec::new();
+    ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+        &graph,
+        &mut partials,
+        file,
+        &NoCancellation,
+        |_, _, path| paths.push(path.clone()),
+    )
+    .expect("should never be cancelled");
+
+    let mut db = Database::new();
+    let handles = paths
+        .iter()
+        .map(|path| db.add_partial_path(&graph, &mut partials, path.clone()))
+        .collect::<Vec<_>>();
+    let duplicates = paths
+        .iter()
+        .map(|path| db.add_partial_path(&graph, &mut partials, path.clone()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(handles, duplicates);
+    assert_eq!(paths.len(), db.iter_partial_paths().count());
+    assert_eq!(paths.len(), db.stats().duplicate_paths);
+    assert!(handles.iter().all(|handle| db.add_count(*handle) == 2));
+}
+
+#[test]
+fn same_path_added_twice_is_stored_once() {
+    let mut graph = StackGraph::new();
+    let mut builder = GraphBuilder::new(&mut graph);
+    builder
+        .file("test.py")
+        .exported_scope("start")
+        .pop("x")
+        .defines("y");
+    let start = builder.node("start").unwrap();
+    let mut partials = PartialPaths::new();
+    let path = path_along(&graph, &mut partials, start, 2);
+
+    let mut db = Database::new();
+    let first = db.add_partial_path(&graph, &mut partials, path.clone());
+    let second = db.add_partial_path(&graph, &mut partials, path);
+
+    assert_eq!(first, second);
+    assert_eq!(vec![first], db.iter_partial_paths().collect::<Vec<_>>());
+    assert_eq!(2, db.add_count(first));
+    assert_eq!(1, db.stats().duplicate_paths);
+}
+
+#[test]
+fn paths_that_only_differ_in_edges_are_stored_separately() {
+    let mut graph = StackGraph::new();
+    let mut builder = GraphBuilder::new(&mut graph);
+    builder
+        .file("test.py")
+        .exported_scope("start")
+        .scope("left")
+        .exported_scope("end");
+    builder
+        .file("test.py")
+        .from("start")
+        .scope("right")
+        .to("end");
+    let start = builder.node("start").unwrap();
+    let left = builder.node("left").unwrap();
+    let mut partials = PartialPaths::new();
+    let mut left_path = PartialPath::from_node(&graph, &mut partials, start);
+    let mut right_path = left_path.clone();
+    for edge in graph.outgoing_edges(start) {
+        let path = if edge.sink == left {
+            &mut left_path
+        } else {
+            &mut right_path
+        };
+        path.append(&graph, &mut partials, edge).unwrap();
+        let next_edge = graph.outgoing_edges(edge.sink).next().unwrap();
+        path.append(&graph, &mut partials, next_edge).unwrap();
+    }
+    assert!(left_path.equals(&mut partials, &right_path));
+
+    let mut db = Database::new();
+    let left_handle = db.add_partial_path(&graph, &mut partials, left_path);
+    let right_handle = db.add_partial_path(&graph, &mut partials, right_path);
+
+    assert_ne!(left_handle, right_handle);
+    assert_eq!(2, db.iter_partial_paths().count());
+    assert_eq!(1, db.add_count(left_handle));
+    assert_eq!(1, db.add_count(right_handle));
+    assert_eq!(0, db.stats().duplicate_paths);
+}
+
+/// Returns the partial path that follows the first outgoing edge of each node, starting at the
+/// given node, for the given number of edges.
+fn path_along(
+    graph: &StackGraph,
+    partials: &mut PartialPaths,
+    start: Handle<Node>,
+    length: usize,
+) -> PartialPath {
+    let mut path = PartialPath::from_node(graph, partials, start);
+    for _ in 0..length {
+        let edge = graph.outgoing_edges(path.end_node).next().unwrap();
+        path.append(graph, partials, edge).unwrap();
+    }
+    path
+}
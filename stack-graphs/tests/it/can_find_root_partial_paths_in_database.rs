@@ -10,6 +10,7 @@ use std::collections::BTreeSet;
 use controlled_option::ControlledOption;
 use pretty_assertions::assert_eq;
 use stack_graphs::arena::Handle;
+use stack_graphs::builder::GraphBuilder;
 use stack_graphs::graph::StackGraph;
 use stack_graphs::partial::PartialPath;
 use stack_graphs::partial::PartialPaths;
@@ -18,6 +19,7 @@ use stack_graphs::partial::PartialSymbolStack;
 use stack_graphs::stitching::Database;
 use stack_graphs::stitching::ForwardPartialPathStitcher;
 use stack_graphs::stitching::SymbolStackKey;
+use stack_graphs::stitching::SymbolStackPrefix;
 use stack_graphs::NoCancellation;
 
 use crate::test_graphs;
@@ -152,3 +154,116 @@ fn sequenced_import_star() {
         &["<b,%1> ($1) [root] -> [b.py(0) definition b] <%1> ($1)"],
     );
 }
+
+fn check_root_partial_paths_with_prefix(
+    graph: &StackGraph,
+    prefix: &[Option<&str>],
+    expected_partial_paths: &[&str],
+) {
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+    for file in graph.iter_files() {
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            graph,
+            &mut partials,
+            file,
+            &NoCancellation,
+            |graph, partials, path| {
+                db.add_partial_path(graph, partials, path.clone());
+            },
+        )
+        .expect("should never be cancelled");
+    }
+
+    let prefix = SymbolStackPrefix {
+        symbols: prefix.iter().map(|s| s.map(str::to_string)).collect(),
+    };
+    let mut results = Vec::<Handle<PartialPath>>::new();
+    db.find_candidate_partial_paths_from_root_with_prefix(
+        graph,
+        &mut partials,
+        &prefix,
+        &mut results,
+    );
+
+    let actual_partial_paths = results
+        .into_iter()
+        .map(|path| db[path].display(graph, &mut partials).to_string())
+        .collect::<BTreeSet<_>>();
+    let expected_partial_paths = expected_partial_paths
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<BTreeSet<_>>();
+    assert_eq!(
+        expected_partial_paths, actual_partial_paths,
+        "failed for prefix {}",
+        prefix
+    );
+}
+
+#[test]
+fn class_field_through_function_parameter_with_prefix() {
+    let graph = test_graphs::class_field_through_function_parameter::new();
+    check_root_partial_paths_with_prefix(
+        &graph,
+        &[Some("a")],
+        &["<a,%1> ($1) [root] -> [a.py(0) definition a] <%1> ($1)"],
+    );
+    check_root_partial_paths_with_prefix(
+        &graph,
+        &[None],
+        &[
+            "<__main__,%1> ($1) [root] -> [main.py(0) definition __main__] <%1> ($1)",
+            "<a,%1> ($1) [root] -> [a.py(0) definition a] <%1> ($1)",
+            "<b,%1> ($1) [root] -> [b.py(0) definition b] <%1> ($1)",
+        ],
+    );
+    check_root_partial_paths_with_prefix(&graph, &[Some("a"), None], &[]);
+    check_root_partial_paths_with_prefix(&graph, &[Some("c")], &[]);
+}
+
+#[test]
+fn preconditions_sharing_first_symbol_with_prefix() {
+    let mut graph = StackGraph::new();
+    let mut builder = GraphBuilder::new(&mut graph);
+    builder
+        .file("a.py")
+        .from_root()
+        .pop("a")
+        .pop(".")
+        .defines("x");
+    builder
+        .file("a.py")
+        .from_root()
+        .pop("a")
+        .pop(".")
+        .defines("y");
+    builder
+        .file("b.py")
+        .from_root()
+        .pop("b")
+        .pop(".")
+        .defines("x");
+    check_root_partial_paths_with_prefix(
+        &graph,
+        &[Some("a")],
+        &[
+            "<a.x,%1> ($1) [root] -> [a.py(2) definition x] <%1> ($1)",
+            "<a.y,%1> ($1) [root] -> [a.py(5) definition y] <%1> ($1)",
+        ],
+    );
+    check_root_partial_paths_with_prefix(
+        &graph,
+        &[Some("a"), None, Some("y")],
+        &["<a.y,%1> ($1) [root] -> [a.py(5) definition y] <%1> ($1)"],
+    );
+    check_root_partial_paths_with_prefix(
+        &graph,
+        &[None, None, Some("x")],
+        &[
+            "<a.x,%1> ($1) [root] -> [a.py(2) definition x] <%1> ($1)",
+            "<b.x,%1> ($1) [root] -> [b.py(2) definition x] <%1> ($1)",
+        ],
+    );
+    check_root_partial_paths_with_prefix(&graph, &[Some("unknown")], &[]);
+}
@@ -6,13 +6,21 @@
 // ------------------------------------------------------------------------------------------------
 
 use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::sync::Mutex;
 
 use pretty_assertions::assert_eq;
 use stack_graphs::graph::StackGraph;
 use stack_graphs::partial::PartialPaths;
+use stack_graphs::stitching::ChainedCandidates;
+use stack_graphs::stitching::CompletePathCache;
 use stack_graphs::stitching::Database;
 use stack_graphs::stitching::DatabaseCandidates;
+use stack_graphs::stitching::ForwardCandidates;
 use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::stitching::RejectionReason;
+use stack_graphs::stitching::StitchingEvent;
+use stack_graphs::stitching::StitchingTracer;
 use stack_graphs::NoCancellation;
 
 use crate::test_graphs;
@@ -136,3 +144,322 @@ fn sequenced_import_star() {
         ],
     );
 }
+
+#[test]
+fn class_field_through_function_parameter_with_chained_candidates() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let mut partials = PartialPaths::new();
+    let mut base_db = Database::new();
+    let mut main_db = Database::new();
+    let mut a_db = Database::new();
+
+    // The paths of each of main.py and a.py are in their own layered database, and the paths of
+    // the remaining files in the database of the wrapped candidates.
+    for file in graph.iter_files() {
+        let db = match graph[file].name() {
+            "main.py" => &mut main_db,
+            "a.py" => &mut a_db,
+            _ => &mut base_db,
+        };
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            &NoCancellation,
+            |graph, partials, path| {
+                db.add_partial_path(graph, partials, path.clone());
+            },
+        )
+        .expect("should never be cancelled");
+    }
+
+    let references = graph
+        .iter_nodes()
+        .filter(|handle| graph[*handle].is_reference());
+    let mut candidates =
+        ChainedCandidates::new(DatabaseCandidates::new(&graph, &mut partials, &mut base_db));
+    candidates.add_database(&mut main_db);
+    candidates.add_database(&mut a_db);
+    let mut complete_partial_paths = Vec::new();
+    ForwardPartialPathStitcher::find_all_complete_partial_paths(
+        &mut candidates,
+        references,
+        &NoCancellation,
+        |_, _, p| {
+            complete_partial_paths.push(p.clone());
+        },
+    )
+    .expect("should never be cancelled");
+    drop(candidates);
+    let results = complete_partial_paths
+        .into_iter()
+        .map(|partial_path| partial_path.display(&graph, &mut partials).to_string())
+        .collect::<BTreeSet<_>>();
+
+    let expected_partial_paths = [
+        "<> () [main.py(17) reference a] -> [a.py(0) definition a] <> ()",
+        "<> () [main.py(15) reference b] -> [b.py(0) definition b] <> ()",
+        "<> () [main.py(13) reference foo] -> [a.py(5) definition foo] <> ()",
+        "<> () [main.py(9) reference A] -> [b.py(5) definition A] <> ()",
+        "<> () [main.py(10) reference bar] -> [b.py(8) definition bar] <> ()",
+        "<> () [a.py(8) reference x] -> [a.py(14) definition x] <> ()",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect::<BTreeSet<_>>();
+    assert_eq!(expected_partial_paths, results);
+}
+
+#[test]
+fn chained_candidates_overlay_files_of_earlier_databases() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let mut partials = PartialPaths::new();
+    let mut base_db = Database::new();
+    let mut a_db = Database::new();
+
+    // The wrapped candidates have the paths of all files.  The layered database has the paths of
+    // a.py as if `foo` was removed from it, like an editor buffer with unsaved changes.
+    for file in graph.iter_files() {
+        let is_a = graph[file].name() == "a.py";
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            &NoCancellation,
+            |graph, partials, path| {
+                base_db.add_partial_path(graph, partials, path.clone());
+                if is_a
+                    && !path
+                        .display(graph, partials)
+                        .to_string()
+                        .contains("definition foo")
+                {
+                    a_db.add_partial_path(graph, partials, path.clone());
+                }
+            },
+        )
+        .expect("should never be cancelled");
+    }
+
+    let references = graph
+        .iter_nodes()
+        .filter(|handle| graph[*handle].is_reference());
+    let mut candidates =
+        ChainedCandidates::new(DatabaseCandidates::new(&graph, &mut partials, &mut base_db));
+    candidates.add_database(&mut a_db);
+    let mut complete_partial_paths = Vec::new();
+    ForwardPartialPathStitcher::find_all_complete_partial_paths(
+        &mut candidates,
+        references,
+        &NoCancellation,
+        |_, _, p| {
+            complete_partial_paths.push(p.clone());
+        },
+    )
+    .expect("should never be cancelled");
+    drop(candidates);
+    let results = complete_partial_paths
+        .into_iter()
+        .map(|partial_path| partial_path.display(&graph, &mut partials).to_string())
+        .collect::<BTreeSet<_>>();
+
+    // the reference to `foo`, and the reference to `bar` on the result of calling it, do not
+    // resolve, because the paths of a.py in the wrapped candidates are hidden by the layered
+    // database
+    let expected_partial_paths = [
+        "<> () [main.py(17) reference a] -> [a.py(0) definition a] <> ()",
+        "<> () [main.py(15) reference b] -> [b.py(0) definition b] <> ()",
+        "<> () [main.py(9) reference A] -> [b.py(5) definition A] <> ()",
+        "<> () [a.py(8) reference x] -> [a.py(14) definition x] <> ()",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect::<BTreeSet<_>>();
+    assert_eq!(expected_partial_paths, results);
+}
+
+#[test]
+fn can_find_complete_paths_in_batches() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+    for file in graph.iter_files() {
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            &NoCancellation,
+            |graph, partials, path| {
+                db.add_partial_path(graph, partials, path.clone());
+            },
+        )
+        .expect("should never be cancelled");
+    }
+
+    let references = graph
+        .iter_nodes()
+        .filter(|handle| graph[*handle].is_reference())
+        .collect::<Vec<_>>();
+    let mut expected = Vec::new();
+    ForwardPartialPathStitcher::find_all_complete_partial_paths(
+        &mut DatabaseCandidates::new(&graph, &mut partials, &mut db),
+        references.iter().copied(),
+        &NoCancellation,
+        |_, _, p| expected.push(p.clone()),
+    )
+    .expect("should never be cancelled");
+
+    let mut candidates = DatabaseCandidates::new(&graph, &mut partials, &mut db);
+    let mut stitcher = {
+        let (graph, partials, _) = candidates.get_graph_partials_and_db();
+        ForwardPartialPathStitcher::from_starting_nodes(graph, partials, references)
+    };
+    let mut batches = Vec::new();
+    while !stitcher.is_complete() {
+        let batch = stitcher
+            .next_batch(&mut candidates, &NoCancellation)
+            .expect("should never be cancelled");
+        batches.push(batch);
+    }
+
+    // paths are found in the same order as with the callback
+    let actual = batches.into_iter().flatten().collect::<Vec<_>>();
+    assert_eq!(expected.len(), actual.len());
+    for (expected, actual) in expected.iter().zip(actual.iter()) {
+        assert!(expected.equals(&mut partials, actual));
+    }
+}
+
+struct EventCollector(Arc<Mutex<Vec<&'static str>>>);
+
+impl StitchingTracer for EventCollector {
+    fn trace(&mut self, _graph: &StackGraph, _partials: &mut PartialPaths, event: StitchingEvent) {
+        let kind = match event {
+            StitchingEvent::PathQueued { .. } => "queued",
+            StitchingEvent::CandidatesFound { .. } => "candidates",
+            StitchingEvent::PathExtended { .. } => "extended",
+            StitchingEvent::PathRejected {
+                reason: RejectionReason::Cyclic,
+                ..
+            } => "cyclic",
+            StitchingEvent::PathRejected { .. } => "rejected",
+        };
+        self.0.lock().unwrap().push(kind);
+    }
+}
+
+#[test]
+fn can_trace_path_stitching() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+    for file in graph.iter_files() {
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            &NoCancellation,
+            |graph, partials, path| {
+                db.add_partial_path(graph, partials, path.clone());
+            },
+        )
+        .expect("should never be cancelled");
+    }
+
+    let references = graph
+        .iter_nodes()
+        .filter(|handle| graph[*handle].is_reference())
+        .collect::<Vec<_>>();
+    let reference_count = references.len();
+    let mut candidates = DatabaseCandidates::new(&graph, &mut partials, &mut db);
+    let mut stitcher = {
+        let (graph, partials, _) = candidates.get_graph_partials_and_db();
+        ForwardPartialPathStitcher::from_starting_nodes(graph, partials, references)
+    };
+    let events = Arc::new(Mutex::new(Vec::new()));
+    stitcher.set_tracer(Some(Box::new(EventCollector(events.clone()))));
+    let mut complete_paths = 0;
+    while !stitcher.is_complete() {
+        complete_paths += stitcher
+            .next_batch(&mut candidates, &NoCancellation)
+            .expect("should never be cancelled")
+            .len();
+    }
+    assert!(complete_paths > 0);
+    assert!(stitcher.take_tracer().is_some());
+
+    // every queued path is either a starting path or an extension of another path, and every
+    // queued path that is not rejected as cyclic has its candidates looked up
+    let events = events.lock().unwrap();
+    let count = |kind| events.iter().filter(|e| **e == kind).count();
+    assert_eq!(reference_count + count("extended"), count("queued"));
+    assert_eq!(count("queued"), count("candidates") + count("cyclic"));
+}
+
+#[test]
+fn can_reuse_cached_complete_paths() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+    for file in graph.iter_files() {
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            &NoCancellation,
+            |graph, partials, path| {
+                db.add_partial_path(graph, partials, path.clone());
+            },
+        )
+        .expect("should never be cancelled");
+    }
+
+    let references = graph
+        .iter_nodes()
+        .filter(|handle| graph[*handle].is_reference())
+        .collect::<Vec<_>>();
+    let mut expected = BTreeSet::new();
+    ForwardPartialPathStitcher::find_all_complete_partial_paths(
+        &mut DatabaseCandidates::new(&graph, &mut partials, &mut db),
+        references.iter().copied(),
+        &NoCancellation,
+        |graph, partials, p| {
+            expected.insert(p.display(graph, partials).to_string());
+        },
+    )
+    .expect("should never be cancelled");
+    assert!(!expected.is_empty());
+
+    let mut cache = CompletePathCache::new();
+    let mut actual = BTreeSet::new();
+    ForwardPartialPathStitcher::find_all_complete_partial_paths_with_cache(
+        &mut DatabaseCandidates::new(&graph, &mut partials, &mut db),
+        references.iter().copied(),
+        &mut cache,
+        &NoCancellation,
+        |graph, partials, p| {
+            actual.insert(p.display(graph, partials).to_string());
+        },
+    )
+    .expect("should never be cancelled");
+    assert_eq!(expected, actual);
+    assert!(!cache.is_empty());
+
+    // cached paths are found without consulting the database
+    let mut empty_db = Database::new();
+    let mut cached = BTreeSet::new();
+    ForwardPartialPathStitcher::find_all_complete_partial_paths_with_cache(
+        &mut DatabaseCandidates::new(&graph, &mut partials, &mut empty_db),
+        references.iter().copied(),
+        &mut cache,
+        &NoCancellation,
+        |graph, partials, p| {
+            cached.insert(p.display(graph, partials).to_string());
+        },
+    )
+    .expect("should never be cancelled");
+    assert_eq!(expected, cached);
+
+    cache.clear();
+    assert!(cache.is_empty());
+}
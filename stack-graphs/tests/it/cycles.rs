@@ -13,10 +13,15 @@ use stack_graphs::graph::StackGraph;
 use stack_graphs::partial::Cyclicity;
 use stack_graphs::partial::PartialPath;
 use stack_graphs::partial::PartialPaths;
+use stack_graphs::stitching::CyclicityConfig;
 use stack_graphs::stitching::Database;
 use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::stitching::GraphEdgeCandidates;
 use stack_graphs::stitching::GraphEdges;
+use stack_graphs::stitching::SimilarPathDetection;
+use stack_graphs::stitching::StitchingStats;
 use stack_graphs::CancelAfterDuration;
+use stack_graphs::NoCancellation;
 use std::time::Duration;
 
 use crate::util::*;
@@ -360,3 +365,67 @@ fn appending_eliminating_cycle_terminates() {
         assert_eq!(1, path_count);
     }
 }
+
+// ----------------------------------------------------------------------------
+// similar path detection
+
+/// Stitches all paths from a reference that reaches the same scope in four ways.  Two of the ways
+/// push the same symbol, and two push different symbols of the same length.
+fn similar_paths_stats(config: CyclicityConfig) -> StitchingStats {
+    let mut graph = StackGraph::new();
+    let file = graph.add_file("test").unwrap();
+    let foo_ref = create_push_symbol_node(&mut graph, file, "foo", true);
+    let end = create_scope_node(&mut graph, file, false);
+    for symbol in ["bar", "bar", "baz"] {
+        let s = create_scope_node(&mut graph, file, false);
+        let push = create_push_symbol_node(&mut graph, file, symbol, false);
+        graph.add_edge(foo_ref, s, 0);
+        graph.add_edge(s, push, 0);
+        graph.add_edge(push, end, 0);
+    }
+
+    let mut partials = PartialPaths::new();
+    let mut stitcher = ForwardPartialPathStitcher::from_starting_nodes(
+        &graph,
+        &mut partials,
+        std::iter::once(foo_ref),
+    );
+    stitcher.set_cyclicity_config(config);
+    let mut candidates = GraphEdgeCandidates::new(&graph, &mut partials, None);
+    while !stitcher.is_complete() {
+        stitcher
+            .next_batch(&mut candidates, &NoCancellation)
+            .expect("should never be cancelled");
+    }
+    stitcher.stats()
+}
+
+#[test]
+fn exact_similar_path_detection_prunes_equal_paths() {
+    let stats = similar_paths_stats(CyclicityConfig::default());
+    assert_eq!(1, stats.similar_paths);
+}
+
+#[test]
+fn approximate_similar_path_detection_prunes_paths_with_same_lengths() {
+    let stats = similar_paths_stats(CyclicityConfig {
+        similar_paths: SimilarPathDetection::Approximate,
+        ..CyclicityConfig::default()
+    });
+    assert_eq!(2, stats.similar_paths);
+}
+
+#[test]
+fn similar_path_detection_can_be_disabled() {
+    let stats = similar_paths_stats(CyclicityConfig {
+        similar_paths: SimilarPathDetection::Off,
+        ..CyclicityConfig::default()
+    });
+    assert_eq!(0, stats.similar_paths);
+
+    let stats = similar_paths_stats(CyclicityConfig {
+        max_similar_path_length: Some(2),
+        ..CyclicityConfig::default()
+    });
+    assert_eq!(0, stats.similar_paths);
+}
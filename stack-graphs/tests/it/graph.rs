@@ -8,7 +8,11 @@
 use std::collections::HashSet;
 
 use maplit::hashset;
+use stack_graphs::arena::Handle;
+use stack_graphs::graph::Node;
+use stack_graphs::graph::StableNodeID;
 use stack_graphs::graph::StackGraph;
+use stack_graphs::serde::FileFilter;
 
 use crate::test_graphs;
 use crate::test_graphs::CreateStackGraph;
@@ -39,6 +43,19 @@ fn can_create_symbols() {
     assert_ne!(empty1, a1);
 }
 
+#[test]
+fn can_create_case_insensitive_symbols() {
+    let mut graph = StackGraph::new();
+    let upper = graph.add_case_insensitive_symbol("Foo");
+    let lower = graph.add_case_insensitive_symbol("foo");
+    let plain = graph.add_symbol("foo");
+    let other = graph.add_symbol("Foo");
+    assert_eq!(upper, lower);
+    assert_eq!(upper, plain);
+    assert_ne!(upper, other);
+    assert_eq!("foo", &graph[upper]);
+}
+
 #[test]
 fn can_iterate_symbols() {
     let mut graph = StackGraph::new();
@@ -204,3 +221,220 @@ fn can_add_graph_to_empty_graph() {
         );
     }
 }
+
+/// Creates a graph for a single file, with a reference and a definition that are connected
+/// through the root node, and with source info, debug info, and metadata.
+fn file_graph(name: &str) -> StackGraph {
+    let mut graph = StackGraph::new();
+    let root = StackGraph::root_node();
+    let file = graph.file(name);
+    let sym_x = graph.symbol("x");
+    let sym_name = graph.symbol(name);
+    let ref_x = graph.reference(file, 1, sym_x);
+    let def_name = graph.definition(file, 2, sym_name);
+    graph.edge(ref_x, root);
+    graph.edge(root, def_name);
+    let syntax_type = graph.add_string("function");
+    graph.source_info_mut(def_name).syntax_type = syntax_type.into();
+    let (key, value) = (graph.add_string("file"), graph.add_string(name));
+    graph.node_debug_info_mut(def_name).add(key, value);
+    graph.node_metadata_mut(def_name).add(key, value);
+    graph.edge_debug_info_mut(root, def_name).add(key, value);
+    graph
+}
+
+fn display_edges(graph: &StackGraph) -> HashSet<String> {
+    graph
+        .iter_nodes()
+        .flat_map(|node| graph.outgoing_edges(node))
+        .map(|edge| {
+            format!(
+                "{} -> {}",
+                edge.source.display(graph),
+                edge.sink.display(graph)
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn can_add_selected_files_from_graph() {
+    let mut other = StackGraph::new();
+    for name in ["a.py", "b.py", "c.py"] {
+        other
+            .merge_disjoint(file_graph(name))
+            .expect("Merging graphs failed");
+    }
+    let filter = ["a.py", "c.py"]
+        .iter()
+        .map(|name| other.get_file(name).expect("Missing file"))
+        .collect::<FileFilter>();
+
+    let mut graph = StackGraph::new();
+    graph
+        .add_from_graph_filtered(&other, &filter)
+        .expect("Adding graph failed");
+
+    assert_eq!(
+        vec!["a.py", "c.py"],
+        graph
+            .iter_files()
+            .map(|file| graph[file].name())
+            .collect::<Vec<_>>()
+    );
+    let expected = ["a.py", "c.py"]
+        .iter()
+        .flat_map(|name| {
+            vec![
+                format!("[{}(1) reference x] -> [root]", name),
+                format!("[root] -> [{}(2) definition {}]", name, name),
+            ]
+        })
+        .collect::<HashSet<_>>();
+    assert_eq!(expected, display_edges(&graph));
+
+    // the remaining file can be added later
+    graph
+        .add_from_graph_filtered(&other, &FileFilter::single(other.get_file("b.py").unwrap()))
+        .expect("Adding graph failed");
+    assert_eq!(display_edges(&other), display_edges(&graph));
+}
+
+#[test]
+fn can_add_graph_with_renamed_files() {
+    let mut graph = StackGraph::new();
+    for prefix in ["python", "typescript"] {
+        graph
+            .add_from_graph_renamed(&file_graph("<builtins>"), |name| {
+                format!("{}:{}", prefix, name)
+            })
+            .expect("Adding graph failed");
+    }
+
+    assert_eq!(
+        vec!["python:<builtins>", "typescript:<builtins>"],
+        graph
+            .iter_files()
+            .map(|file| graph[file].name())
+            .collect::<Vec<_>>()
+    );
+    assert!(display_edges(&graph)
+        .contains("[root] -> [typescript:<builtins>(2) definition <builtins>]"));
+    assert!(graph
+        .add_from_graph_renamed(&file_graph("<builtins>"), |name| format!("python:{}", name))
+        .is_err());
+}
+
+#[test]
+fn can_merge_disjoint_graphs_built_on_threads() {
+    let names = ["a.py", "b.py", "c.py"];
+    let others = names
+        .iter()
+        .copied()
+        .map(|name| std::thread::spawn(move || file_graph(name)))
+        .collect::<Vec<_>>()
+        .into_iter()
+        .map(|thread| thread.join().expect("Thread failed"))
+        .collect::<Vec<_>>();
+
+    let mut expected = StackGraph::new();
+    let mut graph = StackGraph::new();
+    for g in [&mut expected, &mut graph] {
+        // make sure that the handles of the merged graphs differ from the original ones
+        g.add_symbol("unrelated");
+        g.add_string("unrelated");
+    }
+    for other in others {
+        expected
+            .add_from_graph(&other)
+            .expect("Adding graph failed");
+        graph.merge_disjoint(other).expect("Merging graph failed");
+    }
+
+    assert_eq!(display_edges(&expected), display_edges(&graph));
+    for name in names {
+        let file = graph.get_file(name).expect("Missing file");
+        assert_eq!(2, graph.nodes_for_file(file).count());
+        let def = graph
+            .nodes_for_file(file)
+            .find(|node| graph[*node].is_definition())
+            .expect("Missing definition");
+        assert_eq!(name, &graph[graph[def].symbol().unwrap()]);
+        let syntax_type = graph.source_info(def).unwrap().syntax_type.into_option();
+        assert_eq!(Some("function"), syntax_type.map(|s| &graph[s]));
+        let debug_info = graph.node_debug_info(def).unwrap();
+        assert_eq!(
+            Some(("file", name)),
+            debug_info
+                .iter()
+                .map(|e| (&graph[e.key], &graph[e.value]))
+                .next()
+        );
+        let metadata = graph.node_metadata(def).unwrap();
+        assert_eq!(
+            Some(("file", name)),
+            metadata
+                .iter()
+                .map(|e| (&graph[e.key], &graph[e.value]))
+                .next()
+        );
+        let edge_debug_info = graph.edge_debug_info(StackGraph::root_node(), def).unwrap();
+        assert_eq!(
+            Some(("file", name)),
+            edge_debug_info
+                .iter()
+                .map(|e| (&graph[e.key], &graph[e.value]))
+                .next()
+        );
+    }
+}
+
+#[test]
+fn cannot_merge_graphs_with_common_files() {
+    let mut graph = file_graph("a.py");
+    let mut other = file_graph("b.py");
+    other
+        .add_from_graph(&file_graph("a.py"))
+        .expect("Adding graph failed");
+    let file = graph.get_file("a.py").unwrap();
+    assert_eq!(Err(file), graph.merge_disjoint(other));
+    assert!(graph.get_file("b.py").is_none());
+}
+
+#[test]
+fn stable_node_ids_do_not_depend_on_insertion_order() {
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    let sym_a = graph.symbol("a");
+    let definition = graph.definition(file, 1, sym_a);
+    let scope1 = graph.internal_scope(file, 2);
+    let scope2 = graph.internal_scope(file, 3);
+    let ids = graph.stable_node_ids(file);
+
+    let mut other = StackGraph::new();
+    let other_file = other.file("test.py");
+    let sym_a = other.symbol("a");
+    let sym_b = other.symbol("b");
+    other.reference(other_file, 1, sym_b);
+    let other_definition = other.definition(other_file, 2, sym_a);
+    let other_scope1 = other.internal_scope(other_file, 3);
+    let other_scope2 = other.internal_scope(other_file, 4);
+    let other_ids = other.stable_node_ids(other_file);
+
+    let id = |ids: &[(Handle<Node>, StableNodeID)], node| {
+        ids.iter()
+            .find(|(n, _)| *n == node)
+            .map(|(_, id)| *id)
+            .expect("Missing stable ID")
+    };
+    assert_eq!(id(&ids, definition), id(&other_ids, other_definition));
+    assert_eq!(id(&ids, scope1), id(&other_ids, other_scope1));
+    assert_eq!(id(&ids, scope2), id(&other_ids, other_scope2));
+    // nodes with the same content have different IDs
+    assert_ne!(id(&ids, scope1), id(&ids, scope2));
+
+    let stable_id = id(&ids, scope2);
+    assert_eq!(Some(scope2), graph.node_for_stable_id(file, stable_id));
+    assert_eq!(Ok(stable_id), stable_id.to_string().parse::<StableNodeID>());
+    assert_eq!(16, stable_id.to_string().len());
+}
@@ -11,6 +11,11 @@
 pub mod test_graphs;
 
 mod arena;
+#[cfg(feature = "async")]
+mod async_stitching;
+#[cfg(feature = "bench")]
+mod bench;
+mod builder;
 mod c;
 mod can_create_graph;
 mod can_find_local_nodes;
@@ -21,7 +26,19 @@ mod can_jump_to_definition;
 mod can_jump_to_definition_with_forward_partial_path_stitching;
 mod cycles;
 mod graph;
+mod memory_usage;
+mod names;
 mod partial;
 #[cfg(feature = "serde")]
 mod serde;
+mod stats;
+#[cfg(feature = "storage")]
+mod storage;
+#[cfg(feature = "testutil")]
+mod testutil;
+#[cfg(feature = "tracing")]
+mod tracing;
 mod util;
+mod validation;
+#[cfg(feature = "visualization")]
+mod visualization;
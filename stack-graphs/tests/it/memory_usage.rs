@@ -0,0 +1,127 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use stack_graphs::graph::StackGraph;
+use stack_graphs::partial::PartialPaths;
+use stack_graphs::partial::PartialPathsCellCounts;
+use stack_graphs::stitching::Database;
+use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::NoCancellation;
+
+use crate::test_graphs;
+
+#[test]
+fn graph_memory_usage_grows_with_graph() {
+    let empty = StackGraph::new().memory_usage();
+    let graph: StackGraph = test_graphs::simple::new();
+    let usage = graph.memory_usage();
+    assert!(usage.strings > empty.strings);
+    assert!(usage.symbols > empty.symbols);
+    assert!(usage.files > empty.files);
+    assert!(usage.nodes > empty.nodes);
+    assert!(usage.edges > empty.edges);
+    assert_eq!(
+        usage.strings
+            + usage.symbols
+            + usage.files
+            + usage.nodes
+            + usage.source_info
+            + usage.edges
+            + usage.debug_info
+            + usage.metadata,
+        usage.total()
+    );
+}
+
+#[test]
+fn partial_paths_and_database_memory_usage_grows_with_paths() {
+    let graph: StackGraph = test_graphs::simple::new();
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+    assert_eq!(0, db.memory_usage().local_nodes);
+
+    for file in graph.iter_files() {
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            &NoCancellation,
+            |g, ps, p| {
+                db.add_partial_path(g, ps, p.clone());
+            },
+        )
+        .expect("Expect path finding to work");
+    }
+    db.find_local_nodes();
+
+    let partials_usage = partials.memory_usage();
+    assert!(partials_usage.symbol_stacks > 0);
+    assert!(partials_usage.edges > 0);
+    assert_eq!(
+        partials_usage.symbol_stacks + partials_usage.scope_stacks + partials_usage.edges,
+        partials_usage.total()
+    );
+
+    let db_usage = db.memory_usage();
+    assert!(db_usage.partial_paths > 0);
+    assert!(db_usage.local_nodes > 0);
+    assert!(db_usage.indices > 0);
+    assert_eq!(
+        db_usage.partial_paths
+            + db_usage.local_nodes
+            + db_usage.symbol_stack_keys
+            + db_usage.indices,
+        db_usage.total()
+    );
+}
+
+#[test]
+fn can_clear_and_shrink_partial_paths() {
+    let graph: StackGraph = test_graphs::simple::new();
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+    assert_eq!(0, partials.cell_counts().total());
+
+    let find_paths = |partials: &mut PartialPaths, db: &mut Database| {
+        for file in graph.iter_files() {
+            ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+                &graph,
+                partials,
+                file,
+                &NoCancellation,
+                |g, ps, p| {
+                    db.add_partial_path(g, ps, p.clone());
+                },
+            )
+            .expect("Expect path finding to work");
+        }
+    };
+
+    find_paths(&mut partials, &mut db);
+    let counts = partials.cell_counts();
+    assert!(counts.symbol_stacks > 0);
+    assert!(counts.edges > 0);
+    assert_eq!(
+        counts.symbol_stacks + counts.scope_stacks + counts.edges,
+        counts.total()
+    );
+    let paths = db.iter_partial_paths().count();
+    let usage = partials.memory_usage();
+
+    partials.clear();
+    db.clear();
+    assert_eq!(PartialPathsCellCounts::default(), partials.cell_counts());
+    assert_eq!(usage, partials.memory_usage());
+    assert_eq!(0, db.iter_partial_paths().count());
+
+    partials.shrink_to_fit();
+    assert!(partials.memory_usage().total() < usage.total());
+
+    find_paths(&mut partials, &mut db);
+    assert_eq!(counts, partials.cell_counts());
+    assert_eq!(paths, db.iter_partial_paths().count());
+}
@@ -0,0 +1,110 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use stack_graphs::graph::StackGraph;
+use stack_graphs::names::qualified_name;
+use stack_graphs::names::QualifiedName;
+use stack_graphs::names::ReplaceSeparator;
+use stack_graphs::partial::PartialPaths;
+use stack_graphs::stitching::Database;
+use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::NoCancellation;
+
+use crate::test_graphs;
+use crate::test_graphs::CreateStackGraph;
+
+fn find_qualified_name(graph: &StackGraph, file: &str, symbol: &str) -> Option<QualifiedName> {
+    let file = graph.get_file(file).expect("Missing file");
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+    ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+        graph,
+        &mut partials,
+        file,
+        &NoCancellation,
+        |g, ps, p| {
+            db.add_partial_path(g, ps, p.clone());
+        },
+    )
+    .expect("Expect path finding to work");
+
+    let definition = graph
+        .nodes_for_file(file)
+        .find(|node| {
+            graph[*node].is_definition() && graph[*node].symbol().map(|s| &graph[s]) == Some(symbol)
+        })
+        .expect("Missing definition");
+    qualified_name(graph, &mut partials, &db, definition)
+}
+
+fn check_qualified_name(
+    graph: &StackGraph,
+    file: &str,
+    symbol: &str,
+    expected_name: Option<&str>,
+    expected_enclosing_definitions: &[&str],
+) {
+    let name = find_qualified_name(graph, file, symbol);
+    assert_eq!(
+        expected_name,
+        name.as_ref()
+            .map(|name| name.display(graph).to_string())
+            .as_deref()
+    );
+    let enclosing_definitions = name
+        .map(|name| {
+            name.enclosing_definitions
+                .into_iter()
+                .map(|node| graph[graph[node].symbol().unwrap()].to_string())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    assert_eq!(expected_enclosing_definitions, enclosing_definitions);
+}
+
+#[test]
+fn can_compute_qualified_name_of_module_member() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    check_qualified_name(&graph, "a.py", "foo", Some("a.foo"), &["a"]);
+}
+
+#[test]
+fn can_compute_qualified_name_of_class_member() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    check_qualified_name(&graph, "b.py", "bar", Some("b.A.bar"), &["b", "A"]);
+}
+
+#[test]
+fn cannot_compute_qualified_name_of_unreachable_definition() {
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    let sym_x = graph.symbol("x");
+    let scope = graph.internal_scope(file, 0);
+    let x = graph.definition(file, 1, sym_x);
+    graph.edge(scope, x);
+    check_qualified_name(&graph, "test.py", "x", None, &[]);
+}
+
+#[test]
+fn can_render_qualified_name_with_custom_separator() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let name = find_qualified_name(&graph, "b.py", "bar").expect("Missing qualified name");
+    let separator = ReplaceSeparator {
+        symbol: ".",
+        separator: "::",
+    };
+    assert_eq!(vec!["b", "A", "bar"], name.components(&graph, &separator));
+    assert_eq!(
+        "b::A::bar",
+        name.display_with_separator(&graph, &separator).to_string()
+    );
+    // Without recognizing `.` as a separator symbol, it is a component of the name.
+    assert_eq!(
+        "b::.::A::.::bar",
+        name.display_with_separator(&graph, "::").to_string()
+    );
+}
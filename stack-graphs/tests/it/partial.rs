@@ -964,3 +964,22 @@ fn can_resolve_to_node() -> Result<(), PathResolutionError> {
 
     Ok(())
 }
+
+#[test]
+fn can_list_graph_edges_of_partial_path() {
+    let mut graph = StackGraph::new();
+    let file = graph.add_file("test").expect("");
+    let foo_ref = create_push_symbol_node(&mut graph, file, "foo", true);
+    let scope = create_scope_node(&mut graph, file, false);
+    let foo_def = create_pop_symbol_node(&mut graph, file, "foo", true);
+    let mut partials = PartialPaths::new();
+    let path = create_partial_path_and_edges(&mut graph, &mut partials, &[foo_ref, scope, foo_def])
+        .expect("");
+
+    let edges = path
+        .graph_edges(&graph, &mut partials)
+        .into_iter()
+        .map(|edge| (edge.source, edge.sink))
+        .collect::<Vec<_>>();
+    assert_eq!(vec![(foo_ref, scope), (scope, foo_def)], edges);
+}
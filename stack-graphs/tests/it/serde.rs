@@ -16,6 +16,7 @@ use stack_graphs::stitching::{Database, ForwardPartialPathStitcher};
 use stack_graphs::NoCancellation;
 
 use crate::test_graphs;
+use crate::test_graphs::CreateStackGraph;
 
 #[test]
 fn serde_json_stack_graph() {
@@ -55,6 +56,7 @@ fn serde_json_stack_graph() {
                     syntax_type: None,
                 }),
                 debug_info: Some(serde::DebugInfo { data: vec![] }),
+                metadata: None,
             }],
         },
         edges: serde::Edges {
@@ -1275,3 +1277,330 @@ fn can_serialize_partial_paths() {
     );
     assert_json_eq!(expected, actual);
 }
+
+#[test]
+fn can_roundtrip_node_metadata() {
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("test.py");
+    let symbol = graph.add_symbol("foo");
+    let id = graph.new_node_id(file);
+    let def = graph
+        .add_pop_symbol_node(id, symbol, true)
+        .expect("Cannot add node");
+    let key = graph.add_string("visibility");
+    let value = graph.add_string("public");
+    graph.node_metadata_mut(def).add(key, value);
+
+    let json = serde_json::to_string(&graph.to_serializable()).expect("Cannot serialize graph");
+    let serialized: serde::StackGraph =
+        serde_json::from_str(&json).expect("Cannot deserialize graph");
+    let mut loaded_graph = StackGraph::new();
+    serialized
+        .load_into(&mut loaded_graph)
+        .expect("Cannot load graph");
+
+    let loaded_def = serde::NodeID::from_node(&graph, def)
+        .to_node(&mut loaded_graph)
+        .expect("Missing node in loaded graph");
+    let metadata = loaded_graph
+        .node_metadata(loaded_def)
+        .expect("Missing metadata")
+        .iter()
+        .map(|e| {
+            (
+                loaded_graph[e.key].to_string(),
+                loaded_graph[e.value].to_string(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![("visibility".to_string(), "public".to_string())],
+        metadata
+    );
+}
+
+#[test]
+fn does_not_serialize_missing_node_metadata() {
+    let graph: StackGraph = test_graphs::simple::new();
+    let json = serde_json::to_value(graph.to_serializable()).expect("Cannot serialize graph");
+    let nodes = json["nodes"].as_array().expect("Missing nodes");
+    assert!(!nodes.is_empty());
+    for node in nodes {
+        assert!(node.get("metadata").is_none());
+    }
+}
+
+#[test]
+fn can_serialize_stable_node_ids() {
+    let graph: StackGraph = test_graphs::simple::new();
+    let file = graph.get_file("test.py").expect("Missing file");
+    let stable_ids = graph.to_serializable_stable_ids(&serde::FileFilter::single(file));
+    assert_eq!(graph.nodes_for_file(file).count(), stable_ids.data.len());
+
+    let json = serde_json::to_value(&stable_ids).expect("Cannot serialize stable IDs");
+    let entry = &json[0];
+    assert_eq!(json!("test.py"), entry["id"]["file"]);
+    let stable_id = entry["stable_id"]
+        .as_str()
+        .expect("Missing stable ID")
+        .parse::<graph::StableNodeID>()
+        .expect("Invalid stable ID");
+    let node = serde_json::from_value::<serde::NodeID>(entry["id"].clone())
+        .expect("Cannot deserialize node ID")
+        .to_node_id(&graph)
+        .expect("Invalid node ID");
+    assert_eq!(
+        graph.node_for_id(node),
+        graph.node_for_stable_id(file, stable_id)
+    );
+}
+
+#[test]
+fn can_reload_serialized_database_snapshot() {
+    let graph: StackGraph = test_graphs::simple::new();
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+    for file in graph.iter_files() {
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            &NoCancellation,
+            |g, ps, p| {
+                db.add_partial_path(g, ps, p.clone());
+            },
+        )
+        .expect("Expect path finding to work");
+    }
+    db.find_local_nodes();
+
+    let snapshot = db.to_serializable_snapshot(&graph, &mut partials);
+    let json = serde_json::to_string(&snapshot).expect("Cannot serialize snapshot");
+    let snapshot: serde::DatabaseSnapshot =
+        serde_json::from_str(&json).expect("Cannot deserialize snapshot");
+
+    let mut loaded_graph = StackGraph::new();
+    let mut loaded_partials = PartialPaths::new();
+    let mut loaded_db = Database::new();
+    snapshot
+        .load_into(&mut loaded_graph, &mut loaded_partials, &mut loaded_db)
+        .expect("Cannot load snapshot");
+
+    assert_eq!(
+        db.to_serializable(&graph, &mut partials),
+        loaded_db.to_serializable(&loaded_graph, &mut loaded_partials),
+    );
+    for node in graph.iter_nodes() {
+        let loaded_node = serde::NodeID::from_node(&graph, node)
+            .to_node(&mut loaded_graph)
+            .expect("Missing node in loaded graph");
+        assert_eq!(db.node_is_local(node), loaded_db.node_is_local(loaded_node));
+    }
+}
+
+#[test]
+fn cannot_load_database_snapshot_into_graph_with_same_files() {
+    let graph: StackGraph = test_graphs::simple::new();
+    let mut partials = PartialPaths::new();
+    let db = Database::new();
+    let snapshot = db.to_serializable_snapshot(&graph, &mut partials);
+
+    let mut loaded_graph: StackGraph = test_graphs::simple::new();
+    let mut loaded_db = Database::new();
+    assert_eq!(
+        Err(serde::Error::FileAlreadyPresent("test.py".to_string())),
+        snapshot.load_into(&mut loaded_graph, &mut partials, &mut loaded_db),
+    );
+}
+
+fn line_span(start_line: usize, end_line: usize) -> lsp_positions::Span {
+    let position = |line| lsp_positions::Position {
+        line,
+        ..Default::default()
+    };
+    lsp_positions::Span {
+        start: position(start_line),
+        end: position(end_line),
+    }
+}
+
+fn two_file_graph() -> StackGraph {
+    let mut graph = StackGraph::new();
+    let root = graph.root_node();
+    let sym = graph.symbol("x");
+    for name in ["a.py", "b.py"] {
+        let file = graph.file(name);
+        let outer = graph.definition(file, 1, sym);
+        graph.source_info_mut(outer).span = line_span(0, 1);
+        let scope = graph.internal_scope(file, 2);
+        let inner = graph.definition(file, 3, sym);
+        graph.source_info_mut(inner).span = line_span(5, 6);
+        graph.edge(root, outer);
+        graph.edge(outer, scope);
+        graph.edge(scope, inner);
+    }
+    graph
+}
+
+fn filtered_nodes(graph: &StackGraph, filter: &dyn serde::Filter) -> Vec<String> {
+    let json =
+        serde_json::to_value(graph.to_serializable_filter(filter)).expect("Cannot serialize graph");
+    json["nodes"]
+        .as_array()
+        .expect("Missing nodes")
+        .iter()
+        .map(|node| {
+            format!(
+                "{}:{}",
+                node["id"]["file"].as_str().unwrap_or("-"),
+                node["id"]["local_id"]
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn can_combine_filters() {
+    let graph = two_file_graph();
+    let a = graph.get_file("a.py").expect("Missing file");
+    let b = graph.get_file("b.py").expect("Missing file");
+
+    assert_eq!(
+        vec!["-:1", "-:2", "a.py:1", "a.py:2", "a.py:3"],
+        filtered_nodes(&graph, &serde::FileFilter::single(a)),
+    );
+    assert_eq!(
+        vec!["-:1", "-:2", "a.py:1", "a.py:2", "a.py:3", "b.py:1", "b.py:2", "b.py:3"],
+        filtered_nodes(
+            &graph,
+            &vec![a, b].into_iter().collect::<serde::FileFilter>()
+        ),
+    );
+    assert_eq!(
+        vec!["-:1", "-:2", "a.py:2", "a.py:3", "b.py:2", "b.py:3"],
+        filtered_nodes(&graph, &serde::SpanRangeFilter(line_span(3, 10))),
+    );
+    assert_eq!(
+        vec!["-:1", "-:2", "a.py:2", "a.py:3"],
+        filtered_nodes(
+            &graph,
+            &serde::And(
+                serde::FileFilter::single(a),
+                serde::SpanRangeFilter(line_span(3, 10))
+            )
+        ),
+    );
+    assert_eq!(
+        vec!["-:1", "-:2", "a.py:1", "a.py:2", "a.py:3", "b.py:2", "b.py:3"],
+        filtered_nodes(
+            &graph,
+            &serde::Or(
+                serde::FileFilter::single(a),
+                serde::SpanRangeFilter(line_span(3, 10))
+            )
+        ),
+    );
+    assert_eq!(
+        vec!["b.py:1", "b.py:2", "b.py:3"],
+        filtered_nodes(&graph, &serde::Not(serde::FileFilter::single(a))),
+    );
+    assert_eq!(
+        vec!["a.py:1", "b.py:1"],
+        filtered_nodes(
+            &graph,
+            &serde::Not(serde::SpanRangeFilter(line_span(3, 10)))
+        ),
+    );
+}
+
+#[test]
+fn can_filter_region_of_file() {
+    let graph = two_file_graph();
+    let a = graph.get_file("a.py").expect("Missing file");
+    let filter = serde::RegionFilter::new(&graph, a, 5..6);
+    assert_eq!(
+        vec!["-:1", "-:2", "a.py:2", "a.py:3"],
+        filtered_nodes(&graph, &filter),
+    );
+    let json = serde_json::to_value(graph.to_serializable_filter(&filter))
+        .expect("Cannot serialize graph");
+    assert_eq!(
+        json!([
+            {
+                "source": { "file": "a.py", "local_id": 2 },
+                "sink": { "file": "a.py", "local_id": 3 },
+                "precedence": 0,
+            },
+        ]),
+        json["edges"]
+    );
+
+    let filter = serde::RegionFilter::new(&graph, a, 0..1);
+    assert_eq!(
+        vec!["-:1", "-:2", "a.py:1", "a.py:2"],
+        filtered_nodes(&graph, &filter),
+    );
+}
+
+/// Creates a graph for a single file, adding the nodes and edges in the given order.
+fn ordered_graph(reverse: bool) -> StackGraph {
+    let mut graph = StackGraph::new();
+    let root = graph.root_node();
+    let file = graph.file("a.py");
+    let x = graph.symbol("x");
+    let y = graph.symbol("y");
+    let mut symbols = vec![(x, 0), (y, 2)];
+    if reverse {
+        symbols.reverse();
+    }
+    for (local_id, (symbol, line)) in symbols.into_iter().enumerate() {
+        let definition = graph.definition(file, local_id as u32, symbol);
+        graph.source_info_mut(definition).span = line_span(line, line + 1);
+        graph.edge(root, definition);
+    }
+    graph
+}
+
+#[test]
+fn normalized_graphs_do_not_depend_on_insertion_order() {
+    let mut left = ordered_graph(false).to_serializable();
+    let mut right = ordered_graph(true).to_serializable();
+    assert_ne!(left, right);
+    left.normalize();
+    right.normalize();
+    assert_eq!(left, right);
+    assert!(left.diff(&right).is_empty());
+}
+
+#[test]
+fn can_diff_graphs() {
+    let mut left = two_file_graph().to_serializable();
+    left.normalize();
+    let mut graph = two_file_graph();
+    let c = graph.file("c.py");
+    let sym = graph.symbol("x");
+    let definition = graph.definition(c, 1, sym);
+    let root = graph.root_node();
+    graph.edge(root, definition);
+    let mut right = graph.to_serializable();
+    right.normalize();
+
+    let diff = left.diff(&right);
+    assert_eq!(vec!["c.py"], diff.added_files);
+    assert!(diff.removed_files.is_empty());
+    assert_eq!(
+        vec![&serde::NodeID {
+            file: Some("c.py".to_string()),
+            local_id: 0,
+        }],
+        diff.added_nodes.iter().map(|n| n.id()).collect::<Vec<_>>()
+    );
+    assert!(diff.removed_nodes.is_empty());
+    assert_eq!(1, diff.added_edges.len());
+    assert!(diff.removed_edges.is_empty());
+
+    let diff = right.diff(&left);
+    assert_eq!(vec!["c.py"], diff.removed_files);
+    assert_eq!(1, diff.removed_nodes.len());
+    assert_eq!(1, diff.removed_edges.len());
+}
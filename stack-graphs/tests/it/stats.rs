@@ -0,0 +1,55 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use stack_graphs::stats::FrequencyDistribution;
+
+#[test]
+fn empty_distribution_has_no_quantiles() {
+    let dist = FrequencyDistribution::<usize>::new();
+    assert_eq!(0, dist.count());
+    assert_eq!(0, dist.unique());
+    assert!(dist.quantiles(&[0.0, 0.5, 1.0]).is_empty());
+    assert_eq!(None, dist.min());
+    assert_eq!(None, dist.max());
+}
+
+#[test]
+fn can_compute_quantiles() {
+    let mut dist = FrequencyDistribution::new();
+    for value in [5, 1, 3, 3, 2, 4, 3, 10] {
+        dist.record(value);
+    }
+    assert_eq!(8, dist.count());
+    assert_eq!(6, dist.unique());
+    assert_eq!(3, dist.frequency(&3));
+    assert_eq!(0, dist.frequency(&7));
+    assert_eq!(
+        vec![&1, &2, &3, &4, &10],
+        dist.quantiles(&[0.0, 0.25, 0.5, 0.75, 1.0])
+    );
+    assert_eq!(Some(&1), dist.min());
+    assert_eq!(Some(&10), dist.max());
+}
+
+#[test]
+fn can_add_distributions() {
+    let mut left = FrequencyDistribution::new();
+    left.record("a");
+    left.record("b");
+    let mut right = FrequencyDistribution::new();
+    right.record("b");
+    right.record("c");
+
+    left += &right;
+    assert_eq!(4, left.count());
+    assert_eq!(3, left.unique());
+    assert_eq!(2, left.frequency(&"b"));
+
+    left += right;
+    assert_eq!(6, left.count());
+    assert_eq!(2, left.frequency(&"c"));
+}
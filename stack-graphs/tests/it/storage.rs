@@ -0,0 +1,1530 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use stack_graphs::arena::Handle;
+use stack_graphs::graph::File;
+
+use stack_graphs::graph::StackGraph;
+use stack_graphs::partial::PartialPaths;
+use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::stitching::SymbolStackPrefix;
+use stack_graphs::storage::DatabaseProblem;
+use stack_graphs::storage::FileError;
+use stack_graphs::storage::FileErrorSpan;
+use stack_graphs::storage::FileErrors;
+use stack_graphs::storage::FileStatus;
+use stack_graphs::storage::SQLiteReader;
+use stack_graphs::storage::SQLiteReaderPool;
+use stack_graphs::storage::SQLiteWriter;
+use stack_graphs::storage::SQLiteWriterOptions;
+use stack_graphs::storage::StorageError;
+use stack_graphs::storage::Synchronous;
+use stack_graphs::storage::BINCODE_CONFIG;
+use stack_graphs::CancellationError;
+use stack_graphs::CancellationFlag;
+use stack_graphs::NoCancellation;
+
+use crate::test_graphs;
+use crate::test_graphs::CreateStackGraph;
+
+fn database_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "stack-graphs-storage-{}-{}.sqlite",
+        name,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn write_database(path: &PathBuf) -> Vec<String> {
+    let mut writer = SQLiteWriter::open(path).expect("Cannot open writer");
+    store_files(&mut writer)
+}
+
+fn store_files(writer: &mut SQLiteWriter) -> Vec<String> {
+    let graph: StackGraph = test_graphs::simple::new();
+    let mut partials = PartialPaths::new();
+    let mut files = Vec::new();
+    for file in graph.iter_files() {
+        let mut paths = Vec::new();
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            &NoCancellation,
+            |_, _, p| paths.push(p.clone()),
+        )
+        .expect("Expect path finding to work");
+        writer
+            .store_result_for_file(&graph, file, "tag", &mut partials, &paths)
+            .expect("Cannot store result");
+        files.push(graph[file].name().to_string());
+    }
+    files
+}
+
+#[test]
+fn can_read_database_from_multiple_threads() {
+    let path = database_path("pool");
+    let files = write_database(&path);
+
+    let pool = SQLiteReaderPool::open(&path).expect("Cannot open pool");
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                let mut reader = pool.get().expect("Cannot get reader");
+                for file in &files {
+                    let handle = reader.load_graph_for_file(file).expect("Cannot load graph");
+                    let (graph, _, _) = reader.get();
+                    assert_eq!(file, graph[handle].name());
+                }
+            });
+        }
+    });
+    assert!(pool.idle_count() >= 1);
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn can_query_file_status_without_tag() {
+    let path = database_path("status-without-tag");
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("main.py");
+    let mut partials = PartialPaths::new();
+    let mut writer = SQLiteWriter::open(&path).expect("Cannot open writer");
+    writer
+        .store_result_for_file(&graph, file, "tag", &mut partials, std::iter::empty())
+        .expect("Cannot store result");
+    writer
+        .store_error_for_file(Path::new("broken.py"), "tag", "parse error")
+        .expect("Cannot store error");
+
+    assert!(matches!(
+        writer.status_for_file("main.py", None),
+        Ok(FileStatus::Indexed)
+    ));
+    assert!(matches!(
+        writer.status_for_file("broken.py", None),
+        Ok(FileStatus::Error(error)) if error == "parse error"
+    ));
+    assert!(matches!(
+        writer.status_for_file("missing.py", None),
+        Ok(FileStatus::Missing)
+    ));
+
+    let mut reader = writer.into_reader();
+    assert!(matches!(
+        reader.status_for_file("main.py", None::<&str>),
+        Ok(FileStatus::Indexed)
+    ));
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn can_read_database_while_writer_is_open() {
+    let path = database_path("read-only");
+    let files = write_database(&path);
+
+    let _writer = SQLiteWriter::open(&path).expect("Cannot open writer");
+    let mut reader = SQLiteReader::open_read_only(&path).expect("Cannot open reader");
+    for file in &files {
+        reader.load_graph_for_file(file).expect("Cannot load graph");
+    }
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn can_load_again_after_clearing_reader() {
+    let path = database_path("clear");
+    let files = write_database(&path);
+
+    let mut reader = SQLiteReader::open(&path).expect("Cannot open reader");
+    for _ in 0..2 {
+        for file in &files {
+            let handle = reader.load_graph_for_file(file).expect("Cannot load graph");
+            let paths = reader
+                .stored_partial_paths_for_file(file, &NoCancellation)
+                .expect("Cannot load paths");
+            assert!(!paths.is_empty());
+            let (graph, _, _) = reader.get();
+            assert_eq!(file, graph[handle].name());
+        }
+        reader.clear();
+    }
+    drop(reader);
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn cannot_open_missing_database_read_only() {
+    let path = database_path("missing");
+    assert!(matches!(
+        SQLiteReader::open_read_only(&path),
+        Err(StorageError::MissingDatabase(_))
+    ));
+    assert!(matches!(
+        SQLiteReaderPool::open(&path),
+        Err(StorageError::MissingDatabase(_))
+    ));
+}
+
+#[test]
+fn batched_writes_are_visible_after_flush() {
+    let path = database_path("batch");
+    let mut writer = SQLiteWriter::open_with_options(
+        &path,
+        SQLiteWriterOptions {
+            synchronous: Synchronous::Normal,
+            batch_size: 100,
+            ..SQLiteWriterOptions::default()
+        },
+    )
+    .expect("Cannot open writer");
+    let files = store_files(&mut writer);
+
+    let mut reader = SQLiteReader::open_read_only(&path).expect("Cannot open reader");
+    for file in &files {
+        assert!(matches!(
+            reader
+                .status_for_file(file, None::<&str>)
+                .expect("Cannot get status"),
+            FileStatus::Missing
+        ));
+    }
+
+    writer.flush().expect("Cannot flush writer");
+    for file in &files {
+        assert!(matches!(
+            reader
+                .status_for_file(file, None::<&str>)
+                .expect("Cannot get status"),
+            FileStatus::Indexed
+        ));
+    }
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn batched_writes_are_committed_on_drop() {
+    let path = database_path("batch-drop");
+    {
+        let mut writer = SQLiteWriter::open_with_options(
+            &path,
+            SQLiteWriterOptions {
+                batch_size: 100,
+                ..SQLiteWriterOptions::default()
+            },
+        )
+        .expect("Cannot open writer");
+        store_files(&mut writer);
+    }
+    let mut reader = SQLiteReader::open_read_only(&path).expect("Cannot open reader");
+    assert_ne!(
+        0,
+        reader
+            .list_all()
+            .expect("Cannot list files")
+            .try_iter()
+            .expect("Cannot iterate files")
+            .count()
+    );
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn batched_writes_are_committed_when_converting_into_reader() {
+    let path = database_path("batch-into-reader");
+    let mut writer = SQLiteWriter::open_with_options(
+        &path,
+        SQLiteWriterOptions {
+            batch_size: 100,
+            ..SQLiteWriterOptions::default()
+        },
+    )
+    .expect("Cannot open writer");
+    let files = store_files(&mut writer);
+
+    let mut reader = writer.into_reader();
+    let mut other_reader = SQLiteReader::open_read_only(&path).expect("Cannot open reader");
+    for file in &files {
+        for reader in [&mut reader, &mut other_reader] {
+            assert!(matches!(
+                reader
+                    .status_for_file(file, None::<&str>)
+                    .expect("Cannot get status"),
+                FileStatus::Indexed
+            ));
+        }
+    }
+    let _ = std::fs::remove_file(&path);
+}
+
+/// Writes a database in the format used before graphs and paths were stored by content hash.
+/// Databases before version 7 do not have a symbols table.
+fn write_legacy_database(path: &PathBuf, version: usize) -> Vec<String> {
+    let graph: StackGraph = test_graphs::simple::new();
+    let mut partials = PartialPaths::new();
+    let conn = rusqlite::Connection::open(path).expect("Cannot open database");
+    conn.execute_batch(
+        r#"
+            CREATE TABLE metadata (version INTEGER NOT NULL) STRICT;
+            CREATE TABLE graphs (file TEXT PRIMARY KEY, tag TEXT NOT NULL, error TEXT, value BLOB NOT NULL) STRICT;
+            CREATE TABLE file_paths (file TEXT NOT NULL, local_id INTEGER NOT NULL, value BLOB NOT NULL) STRICT;
+            CREATE TABLE root_paths (file TEXT NOT NULL, symbol_stack TEXT NOT NULL, value BLOB NOT NULL) STRICT;
+        "#,
+    )
+    .expect("Cannot create tables");
+    if version >= 7 {
+        conn.execute_batch(
+            "CREATE TABLE symbols (file TEXT NOT NULL, symbol TEXT NOT NULL, kind TEXT NOT NULL) STRICT;",
+        )
+        .expect("Cannot create tables");
+    }
+    conn.execute("INSERT INTO metadata (version) VALUES (?)", [version])
+        .expect("Cannot set version");
+    let mut files = Vec::new();
+    for file in graph.iter_files() {
+        let file_str = graph[file].name();
+        let file_graph = stack_graphs::serde::StackGraph::from_graph_filter(
+            &graph,
+            &stack_graphs::serde::FileFilter::single(file),
+        );
+        conn.execute(
+            "INSERT INTO graphs (file, tag, value) VALUES (?, ?, ?)",
+            (
+                file_str,
+                "tag",
+                bincode::encode_to_vec(&file_graph, BINCODE_CONFIG).expect("Cannot encode graph"),
+            ),
+        )
+        .expect("Cannot store graph");
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            &NoCancellation,
+            |graph, partials, path| {
+                let start_node = graph[path.start_node].id();
+                let value = bincode::encode_to_vec(
+                    stack_graphs::serde::PartialPath::from_partial_path(graph, partials, path),
+                    BINCODE_CONFIG,
+                )
+                .expect("Cannot encode path");
+                // the test graph has no paths that start at the root node
+                assert!(!start_node.is_root());
+                conn.execute(
+                    "INSERT INTO file_paths (file, local_id, value) VALUES (?, ?, ?)",
+                    (file_str, start_node.local_id(), value),
+                )
+                .expect("Cannot store path");
+            },
+        )
+        .expect("Expect path finding to work");
+        files.push(file_str.to_string());
+    }
+    files
+}
+
+fn set_database_version(path: &PathBuf, version: usize) {
+    let conn = rusqlite::Connection::open(path).expect("Cannot open database");
+    conn.execute("UPDATE metadata SET version = ?", [version])
+        .expect("Cannot set version");
+}
+
+/// Upgrades the database by opening a writer, because readers do not upgrade databases.
+fn upgrade_database(path: &PathBuf) {
+    SQLiteWriter::open(path).expect("Cannot upgrade database");
+}
+
+fn database_version(path: &PathBuf) -> usize {
+    let conn = rusqlite::Connection::open(path).expect("Cannot open database");
+    conn.query_row("SELECT version FROM metadata", [], |r| r.get(0))
+        .expect("Cannot get version")
+}
+
+fn stored_graph_count(path: &PathBuf) -> usize {
+    let conn = rusqlite::Connection::open(path).expect("Cannot open database");
+    conn.query_row("SELECT COUNT(*) FROM graphs", [], |r| r.get(0))
+        .expect("Cannot count graphs")
+}
+
+#[test]
+fn can_upgrade_older_database() {
+    let path = database_path("upgrade");
+    let files = write_legacy_database(&path, 6);
+
+    // readers never upgrade databases
+    assert!(matches!(
+        SQLiteReader::open(&path),
+        Err(StorageError::IncompatibleVersion { database: 6, .. })
+    ));
+    assert!(matches!(
+        SQLiteReader::open_read_only(&path),
+        Err(StorageError::IncompatibleVersion { database: 6, .. })
+    ));
+    assert_eq!(6, database_version(&path));
+
+    let mut writer = SQLiteWriter::open(&path).expect("Cannot open writer");
+    for file in &files {
+        assert!(matches!(
+            writer
+                .status_for_file(file, None)
+                .expect("Cannot get status"),
+            FileStatus::Indexed
+        ));
+    }
+    drop(writer);
+    SQLiteReader::open(&path).expect("Cannot open upgraded database");
+    SQLiteReader::open_read_only(&path).expect("Cannot open upgraded database");
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn cannot_upgrade_database_with_incompatible_encoding() {
+    let path = database_path("incompatible-encoding");
+    let files = write_legacy_database(&path, 5);
+
+    // stored results cannot be converted, and are not discarded
+    assert!(matches!(
+        SQLiteWriter::open(&path),
+        Err(StorageError::IncompatibleVersion { database: 5, .. })
+    ));
+    assert!(matches!(
+        SQLiteReader::open(&path),
+        Err(StorageError::IncompatibleVersion { database: 5, .. })
+    ));
+    assert_eq!(5, database_version(&path));
+    assert_eq!(files.len(), stored_graph_count(&path));
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn cannot_open_newer_database() {
+    let path = database_path("newer");
+    write_database(&path);
+    set_database_version(&path, 1000);
+
+    assert!(matches!(
+        SQLiteWriter::open(&path),
+        Err(StorageError::IncompatibleVersion { database: 1000, .. })
+    ));
+    assert!(matches!(
+        SQLiteReader::open(&path),
+        Err(StorageError::IncompatibleVersion { database: 1000, .. })
+    ));
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn cannot_upgrade_database_without_migrations() {
+    let path = database_path("unsupported");
+    write_database(&path);
+    set_database_version(&path, 1);
+
+    assert!(matches!(
+        SQLiteWriter::open(&path),
+        Err(StorageError::IncompatibleVersion { database: 1, .. })
+    ));
+    let _ = std::fs::remove_file(&path);
+}
+
+fn files_defining(reader: &SQLiteReader, symbol: &str) -> Vec<String> {
+    reader
+        .files_defining(symbol)
+        .expect("Cannot query symbols")
+        .try_iter()
+        .expect("Cannot iterate files")
+        .map(|file| file.expect("Cannot read file").path.display().to_string())
+        .collect()
+}
+
+fn files_referencing(reader: &SQLiteReader, symbol: &str) -> Vec<String> {
+    reader
+        .files_referencing(symbol)
+        .expect("Cannot query symbols")
+        .try_iter()
+        .expect("Cannot iterate files")
+        .map(|file| file.expect("Cannot read file").path.display().to_string())
+        .collect()
+}
+
+#[test]
+fn can_find_files_by_symbol() {
+    let path = database_path("symbols");
+    let files = write_database(&path);
+
+    let reader = SQLiteReader::open_read_only(&path).expect("Cannot open reader");
+    assert_eq!(files, files_defining(&reader, "x"));
+    assert_eq!(files, files_referencing(&reader, "x"));
+    assert!(files_defining(&reader, "y").is_empty());
+    drop(reader);
+
+    let mut writer = SQLiteWriter::open(&path).expect("Cannot open writer");
+    writer.clean_all().expect("Cannot clean database");
+    let reader = writer.into_reader();
+    assert!(files_defining(&reader, "x").is_empty());
+    assert!(files_referencing(&reader, "x").is_empty());
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn can_populate_symbols_when_upgrading_database() {
+    let path = database_path("upgrade-symbols");
+    let files = write_legacy_database(&path, 6);
+
+    upgrade_database(&path);
+    let reader = SQLiteReader::open(&path).expect("Cannot open reader");
+    assert_eq!(files, files_defining(&reader, "x"));
+    assert_eq!(files, files_referencing(&reader, "x"));
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn can_upgrade_database_to_content_storage() {
+    let path = database_path("upgrade-contents");
+    let files = write_legacy_database(&path, 7);
+
+    upgrade_database(&path);
+    let mut reader = SQLiteReader::open(&path).expect("Cannot open reader");
+    for file in &files {
+        let handle = reader.load_graph_for_file(file).expect("Cannot load graph");
+        let (graph, _, _) = reader.get();
+        assert_eq!(file, graph[handle].name());
+        assert!(graph.nodes_for_file(handle).count() > 0);
+    }
+    drop(reader);
+
+    let conn = rusqlite::Connection::open(&path).expect("Cannot open database");
+    let count = |table: &str| -> usize {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |r| r.get(0))
+            .expect("Cannot count rows")
+    };
+    assert_eq!(files.len(), count("contents"));
+    assert!(count("file_paths") > 0);
+    let _ = std::fs::remove_file(&path);
+}
+
+/// Adds a file with a reference to and a definition of `x`, which are connected through the
+/// root node.  Files created by this function have the same content.
+fn add_file_with_global_definition(graph: &mut StackGraph, name: &str) -> Handle<File> {
+    let root = graph.root_node();
+    let sym_x = graph.symbol("x");
+    let file = graph.file(name);
+    let ref_x = graph.reference(file, 1, sym_x);
+    let def_x = graph.definition(file, 2, sym_x);
+    graph.edge(ref_x, root);
+    graph.edge(root, def_x);
+    file
+}
+
+fn store_file(writer: &mut SQLiteWriter, graph: &StackGraph, file: Handle<File>) {
+    let mut partials = PartialPaths::new();
+    let mut paths = Vec::new();
+    ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+        graph,
+        &mut partials,
+        file,
+        &NoCancellation,
+        |_, _, p| paths.push(p.clone()),
+    )
+    .expect("Expect path finding to work");
+    writer
+        .store_result_for_file(graph, file, "tag", &mut partials, &paths)
+        .expect("Cannot store result");
+}
+
+#[test]
+fn files_with_same_content_share_storage() {
+    let path = database_path("contents");
+    let mut graph = StackGraph::new();
+    let file_a = add_file_with_global_definition(&mut graph, "a.py");
+    let file_b = add_file_with_global_definition(&mut graph, "b.py");
+
+    let mut writer = SQLiteWriter::open(&path).expect("Cannot open writer");
+    store_file(&mut writer, &graph, file_a);
+    store_file(&mut writer, &graph, file_b);
+
+    let conn = rusqlite::Connection::open(&path).expect("Cannot open database");
+    let count = |table: &str| -> usize {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |r| r.get(0))
+            .expect("Cannot count rows")
+    };
+    assert_eq!(2, count("graphs"));
+    assert_eq!(1, count("contents"));
+
+    // the reference in one file resolves to the definitions in both files
+    let mut reader = SQLiteReader::open_read_only(&path).expect("Cannot open reader");
+    let file = reader
+        .load_graph_for_file("a.py")
+        .expect("Cannot load graph");
+    let (graph, _, _) = reader.get();
+    let references = graph
+        .nodes_for_file(file)
+        .filter(|n| graph[*n].is_reference())
+        .collect::<Vec<_>>();
+    let mut definitions = Vec::new();
+    ForwardPartialPathStitcher::find_all_complete_partial_paths(
+        &mut reader,
+        references,
+        &NoCancellation,
+        |graph, _, path| {
+            let file = graph[path.end_node].file().expect("definition in file");
+            definitions.push(graph[file].name().to_string());
+        },
+    )
+    .expect("Cannot find paths");
+    definitions.sort();
+    assert_eq!(vec!["a.py", "b.py"], definitions);
+    drop(reader);
+
+    writer
+        .clean_file(Path::new("a.py"))
+        .expect("Cannot clean file");
+    assert_eq!(1, count("contents"));
+    writer
+        .clean_file(Path::new("b.py"))
+        .expect("Cannot clean file");
+    assert_eq!(0, count("contents"));
+    assert_eq!(0, count("root_paths"));
+    let _ = std::fs::remove_file(&path);
+}
+
+/// Adds a file with a reference to `x`, which is looked up at the root node.
+fn add_file_with_global_reference(graph: &mut StackGraph, name: &str) -> Handle<File> {
+    let root = graph.root_node();
+    let sym_x = graph.symbol("x");
+    let file = graph.file(name);
+    let ref_x = graph.reference(file, 1, sym_x);
+    graph.edge(ref_x, root);
+    file
+}
+
+/// Adds a file with a definition of `x`, which is defined at the root node.
+fn add_file_with_global_definition_only(graph: &mut StackGraph, name: &str) -> Handle<File> {
+    let root = graph.root_node();
+    let sym_x = graph.symbol("x");
+    let file = graph.file(name);
+    let def_x = graph.definition(file, 1, sym_x);
+    graph.edge(root, def_x);
+    file
+}
+
+fn files_importing(reader: &SQLiteReader, symbol: &str) -> Vec<String> {
+    reader
+        .files_importing(symbol)
+        .expect("Cannot query symbols")
+        .try_iter()
+        .expect("Cannot iterate files")
+        .map(|file| file.expect("Cannot read file").path.display().to_string())
+        .collect()
+}
+
+fn files_affected_by(reader: &SQLiteReader, file: &str) -> Vec<String> {
+    reader
+        .files_affected_by(file)
+        .expect("Cannot query affected files")
+        .into_iter()
+        .map(|path| path.display().to_string())
+        .collect()
+}
+
+#[test]
+fn can_find_files_affected_by_changes() {
+    let path = database_path("affected");
+    let mut graph = StackGraph::new();
+    let file_a = add_file_with_global_definition_only(&mut graph, "a.py");
+    let file_b = add_file_with_global_reference(&mut graph, "b.py");
+    let file_c = add_file_with_global_definition(&mut graph, "c.py");
+
+    let mut writer = SQLiteWriter::open(&path).expect("Cannot open writer");
+    store_file(&mut writer, &graph, file_a);
+    store_file(&mut writer, &graph, file_b);
+    store_file(&mut writer, &graph, file_c);
+    assert_eq!(
+        vec![PathBuf::from("b.py"), PathBuf::from("c.py")],
+        writer
+            .files_affected_by("a.py")
+            .expect("Cannot query affected files")
+    );
+
+    let reader = SQLiteReader::open_read_only(&path).expect("Cannot open reader");
+    assert_eq!(vec!["b.py", "c.py"], files_importing(&reader, "x"));
+    assert_eq!(vec!["b.py", "c.py"], files_affected_by(&reader, "a.py"));
+    assert!(files_affected_by(&reader, "b.py").is_empty());
+    assert_eq!(vec!["b.py"], files_affected_by(&reader, "c.py"));
+    assert!(files_affected_by(&reader, "d.py").is_empty());
+    drop(reader);
+
+    writer
+        .clean_file(Path::new("a.py"))
+        .expect("Cannot clean file");
+    let reader = writer.into_reader();
+    assert!(files_affected_by(&reader, "a.py").is_empty());
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn can_populate_root_symbols_when_upgrading_database() {
+    let path = database_path("upgrade-root-symbols");
+    let mut graph = StackGraph::new();
+    let file_a = add_file_with_global_definition_only(&mut graph, "a.py");
+    let file_b = add_file_with_global_reference(&mut graph, "b.py");
+    let mut writer = SQLiteWriter::open(&path).expect("Cannot open writer");
+    store_file(&mut writer, &graph, file_a);
+    store_file(&mut writer, &graph, file_b);
+    drop(writer);
+
+    let conn = rusqlite::Connection::open(&path).expect("Cannot open database");
+    conn.execute_batch(
+        r#"
+            DELETE FROM symbols WHERE kind IN ('import', 'export');
+            UPDATE metadata SET version = 11;
+        "#,
+    )
+    .expect("Cannot downgrade database");
+    drop(conn);
+
+    upgrade_database(&path);
+    let reader = SQLiteReader::open(&path).expect("Cannot open reader");
+    assert_eq!(vec!["b.py"], files_importing(&reader, "x"));
+    assert_eq!(vec!["b.py"], files_affected_by(&reader, "a.py"));
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn can_find_root_paths_by_symbol_stack_prefix() {
+    let path = database_path("root-prefix");
+    let mut graph = StackGraph::new();
+    let file_a = add_file_with_global_definition(&mut graph, "a.py");
+    let mut writer = SQLiteWriter::open(&path).expect("Cannot open writer");
+    store_file(&mut writer, &graph, file_a);
+
+    let mut reader = writer.into_reader();
+    let mut find = |symbols: &[Option<&str>]| {
+        let prefix = SymbolStackPrefix {
+            symbols: symbols.iter().map(|s| s.map(str::to_string)).collect(),
+        };
+        let mut paths = Vec::new();
+        reader
+            .find_candidate_partial_paths_from_root_with_prefix(
+                &prefix,
+                &NoCancellation,
+                &mut paths,
+            )
+            .expect("Cannot find paths");
+        let (graph, _, db) = reader.get();
+        paths
+            .into_iter()
+            .map(|p| {
+                graph[db[p].end_node]
+                    .file()
+                    .map(|f| graph[f].name().to_string())
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(vec![Some("a.py".to_string())], find(&[Some("x")]));
+    assert_eq!(vec![Some("a.py".to_string())], find(&[None]));
+    assert!(find(&[Some("y")]).is_empty());
+    assert!(find(&[Some("x"), None]).is_empty());
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn can_load_stored_partial_paths_for_file() {
+    let path = database_path("stored-paths");
+    let mut writer = SQLiteWriter::open(&path).expect("Cannot open writer");
+    let graph = test_graphs::simple::new();
+    let file = graph.get_file("test.py").expect("Missing file");
+    let mut partials = PartialPaths::new();
+    let mut paths = Vec::new();
+    ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+        &graph,
+        &mut partials,
+        file,
+        &NoCancellation,
+        |_, _, p| paths.push(p.clone()),
+    )
+    .expect("Expect path finding to work");
+    writer
+        .store_result_for_file(&graph, file, "tag", &mut partials, &paths)
+        .expect("Cannot store result");
+    let mut expected = paths
+        .iter()
+        .map(|p| p.display(&graph, &mut partials).to_string())
+        .collect::<Vec<_>>();
+    expected.sort();
+    assert!(!expected.is_empty());
+
+    let mut reader = writer.into_reader();
+    let stored = reader
+        .stored_partial_paths_for_file("test.py", &NoCancellation)
+        .expect("Cannot load stored paths");
+    let (graph, partials, db) = reader.get();
+    let mut actual = stored
+        .iter()
+        .map(|p| p.display(graph, partials).to_string())
+        .collect::<Vec<_>>();
+    actual.sort();
+    assert_eq!(expected, actual);
+    // stored paths are not added to the database used for stitching
+    assert_eq!(0, db.iter_partial_paths().count());
+}
+
+#[test]
+fn can_read_compressed_database() {
+    let path = database_path("compressed");
+    let mut writer = SQLiteWriter::open_with_options(
+        &path,
+        SQLiteWriterOptions {
+            compression_level: Some(3),
+            ..SQLiteWriterOptions::default()
+        },
+    )
+    .expect("Cannot open writer");
+    let files = store_files(&mut writer);
+    drop(writer);
+
+    // files stored without compression can be read alongside compressed ones
+    let mut graph = StackGraph::new();
+    let file = add_file_with_global_definition(&mut graph, "uncompressed.py");
+    let mut writer = SQLiteWriter::open(&path).expect("Cannot open writer");
+    store_file(&mut writer, &graph, file);
+    drop(writer);
+
+    let mut reader = SQLiteReader::open_read_only(&path).expect("Cannot open reader");
+    for file in files.iter().map(String::as_str).chain(["uncompressed.py"]) {
+        let handle = reader.load_graph_for_file(file).expect("Cannot load graph");
+        let (graph, _, _) = reader.get();
+        assert_eq!(file, graph[handle].name());
+        assert!(graph.nodes_for_file(handle).count() > 0);
+    }
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn can_find_nodes_by_stable_id() {
+    let path = database_path("stable-ids");
+    write_database(&path);
+    let graph: StackGraph = test_graphs::simple::new();
+    let file = graph.get_file("test.py").expect("Missing file");
+
+    let mut reader = SQLiteReader::open_read_only(&path).expect("Cannot open reader");
+    for (node, stable_id) in graph.stable_node_ids(file) {
+        let loaded_node = reader
+            .node_for_stable_id("test.py", stable_id)
+            .expect("Cannot load graph")
+            .expect("Missing node");
+        let (loaded_graph, _, _) = reader.get();
+        assert_eq!(
+            graph[node].id().local_id(),
+            loaded_graph[loaded_node].id().local_id()
+        );
+    }
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn can_load_graphs_for_many_files() {
+    let path = database_path("bulk");
+    let mut graph = StackGraph::new();
+    let mut writer = SQLiteWriter::open_with_options(
+        &path,
+        SQLiteWriterOptions {
+            batch_size: 100,
+            ..SQLiteWriterOptions::default()
+        },
+    )
+    .expect("Cannot open writer");
+    let mut files = Vec::new();
+    for i in 0..100 {
+        let file = add_file_with_global_definition(&mut graph, &format!("file{}.py", i));
+        store_file(&mut writer, &graph, file);
+        files.push(graph[file].name().to_string());
+    }
+    drop(writer);
+
+    let mut reader = SQLiteReader::open_read_only(&path).expect("Cannot open reader");
+    reader
+        .load_graph_for_file(&files[0])
+        .expect("Cannot load graph");
+    let mut requested = files.iter().map(String::as_str).collect::<Vec<_>>();
+    requested.push(&files[1]);
+    let handles = reader
+        .load_graphs_for_files(&requested)
+        .expect("Cannot load graphs");
+    let (graph, _, _) = reader.get();
+    assert_eq!(requested.len(), handles.len());
+    for (file, handle) in requested.iter().zip(handles) {
+        assert_eq!(*file, graph[handle].name());
+        assert_eq!(2, graph.nodes_for_file(handle).count());
+    }
+
+    assert!(reader.load_graphs_for_files(&["missing.py"]).is_err());
+    let _ = std::fs::remove_file(&path);
+}
+
+fn list_errors(reader: &SQLiteReader, path: Option<&str>) -> Vec<(String, FileError)> {
+    let mut errors = match path {
+        Some(path) => collect_errors(
+            reader
+                .list_errors_for_file_or_directory(Path::new(path))
+                .expect("Cannot list errors"),
+        ),
+        None => collect_errors(reader.list_all_errors().expect("Cannot list errors")),
+    };
+    errors.sort_by(|a, b| a.0.cmp(&b.0));
+    errors
+}
+
+fn collect_errors<P: rusqlite::Params + Clone>(
+    mut errors: FileErrors<P>,
+) -> Vec<(String, FileError)> {
+    errors
+        .try_iter()
+        .expect("Cannot list errors")
+        .map(|e| {
+            let e = e.expect("Cannot read error");
+            (e.path.to_string_lossy().to_string(), e.error)
+        })
+        .collect()
+}
+
+#[test]
+fn can_list_structured_errors() {
+    let path = database_path("errors");
+    let mut graph = StackGraph::new();
+    let file_a = add_file_with_global_definition(&mut graph, "a.py");
+
+    let parse_error = FileError::new("parse", "parsing failed").with_span(FileErrorSpan {
+        start_line: 2,
+        start_column: 4,
+        end_line: 2,
+        end_column: 7,
+    });
+    let mut writer = SQLiteWriter::open(&path).expect("Cannot open writer");
+    writer
+        .store_file_error_for_file(Path::new("a.py"), "tag", &parse_error)
+        .expect("Cannot store error");
+    writer
+        .store_error_for_file(Path::new("b.py"), "tag", "parsing timed out")
+        .expect("Cannot store error");
+    assert!(matches!(
+        writer.status_for_file("a.py", None).expect("Cannot get status"),
+        FileStatus::Error(error) if error == "parsing failed"
+    ));
+
+    let reader = SQLiteReader::open_read_only(&path).expect("Cannot open reader");
+    assert_eq!(
+        vec![
+            ("a.py".to_string(), parse_error.clone()),
+            (
+                "b.py".to_string(),
+                FileError::new("unknown", "parsing timed out")
+            ),
+        ],
+        list_errors(&reader, None)
+    );
+    assert_eq!(
+        vec![("a.py".to_string(), parse_error)],
+        list_errors(&reader, Some("a.py"))
+    );
+    drop(reader);
+
+    // storing a result for a file removes its error
+    store_file(&mut writer, &graph, file_a);
+    let reader = writer.into_reader();
+    assert_eq!(
+        vec!["b.py".to_string()],
+        list_errors(&reader, None)
+            .into_iter()
+            .map(|(file, _)| file)
+            .collect::<Vec<_>>()
+    );
+    drop(reader);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn can_populate_errors_when_upgrading_database() {
+    let path = database_path("upgrade-errors");
+    write_legacy_database(&path, 7);
+    let conn = rusqlite::Connection::open(&path).expect("Cannot open database");
+    conn.execute(
+        "INSERT INTO graphs (file, tag, error, value) VALUES (?, ?, ?, ?)",
+        (
+            "broken.py",
+            "tag",
+            "parsing failed",
+            bincode::encode_to_vec(stack_graphs::serde::StackGraph::default(), BINCODE_CONFIG)
+                .expect("Cannot encode graph"),
+        ),
+    )
+    .expect("Cannot store error");
+    drop(conn);
+
+    upgrade_database(&path);
+    let reader = SQLiteReader::open(&path).expect("Cannot open reader");
+    assert_eq!(
+        vec![(
+            "broken.py".to_string(),
+            FileError::new("unknown", "parsing failed")
+        )],
+        list_errors(&reader, None)
+    );
+    drop(reader);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn can_store_and_load_sources() {
+    let path = database_path("sources");
+    let mut graph = StackGraph::new();
+    let file = add_file_with_global_definition(&mut graph, "a.py");
+
+    let mut writer = SQLiteWriter::open(&path).expect("Cannot open writer");
+    store_file(&mut writer, &graph, file);
+    writer
+        .store_source_for_file(Path::new("a.py"), "x = 1\n")
+        .expect("Cannot store source");
+
+    let reader = SQLiteReader::open_read_only(&path).expect("Cannot open reader");
+    assert_eq!(
+        Some("x = 1\n".to_string()),
+        reader.source_for_file("a.py").expect("Cannot load source")
+    );
+    assert_eq!(
+        None,
+        reader.source_for_file("b.py").expect("Cannot load source")
+    );
+
+    writer
+        .clean_file(Path::new("a.py"))
+        .expect("Cannot clean file");
+    assert_eq!(
+        None,
+        reader.source_for_file("a.py").expect("Cannot load source")
+    );
+    drop(reader);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn can_summarize_stored_files() {
+    let path = database_path("summary");
+    let mut graph = StackGraph::new();
+    let file_a = add_file_with_global_definition(&mut graph, "a.py");
+    let file_b = add_file_with_global_definition(&mut graph, "b.py");
+
+    let mut writer = SQLiteWriter::open(&path).expect("Cannot open writer");
+    store_file(&mut writer, &graph, file_a);
+    store_file(&mut writer, &graph, file_b);
+    writer
+        .store_source_for_file(Path::new("a.py"), "x = 1\n")
+        .expect("Cannot store source");
+    writer
+        .store_error_for_file(Path::new("c.py"), "tag", "parsing failed")
+        .expect("Cannot store error");
+
+    let reader = SQLiteReader::open_read_only(&path).expect("Cannot open reader");
+    let summary = reader
+        .file_summary("a.py")
+        .expect("Cannot summarize file")
+        .expect("Missing summary");
+    assert_eq!("tag", summary.tag);
+    assert!(matches!(summary.status, FileStatus::Indexed));
+    assert!(summary.indexed_at.is_some());
+    assert_eq!(graph.nodes_for_file(file_a).count(), summary.node_count);
+    assert_eq!(2, summary.edge_count);
+    // the reference has a path to the root node, and the definition a path from it
+    assert_eq!(1, summary.node_path_count);
+    assert_eq!(1, summary.root_path_count);
+    assert!(summary.graph_size > 0);
+    assert!(summary.node_paths_size > 0);
+    assert!(summary.root_paths_size > 0);
+    assert!(summary.source_size.is_some());
+    assert_eq!(2, summary.content_file_count);
+    assert_eq!(
+        summary.graph_size
+            + summary.node_paths_size
+            + summary.root_paths_size
+            + summary.source_size.unwrap(),
+        summary.total_size()
+    );
+
+    let summary = reader
+        .file_summary("b.py")
+        .expect("Cannot summarize file")
+        .expect("Missing summary");
+    assert_eq!(None, summary.source_size);
+
+    let summary = reader
+        .file_summary("c.py")
+        .expect("Cannot summarize file")
+        .expect("Missing summary");
+    assert!(matches!(summary.status, FileStatus::Error(_)));
+    assert_eq!(0, summary.node_count);
+    assert_eq!(0, summary.root_path_count);
+    assert_eq!(1, summary.content_file_count);
+
+    assert!(reader
+        .file_summary("d.py")
+        .expect("Cannot summarize file")
+        .is_none());
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn can_populate_summary_counts_when_upgrading_database() {
+    let path = database_path("upgrade-summary");
+    let files = write_database(&path);
+    let conn = rusqlite::Connection::open(&path).expect("Cannot open database");
+    conn.execute_batch(
+        r#"
+            ALTER TABLE contents DROP COLUMN node_count;
+            ALTER TABLE contents DROP COLUMN edge_count;
+            ALTER TABLE graphs DROP COLUMN indexed_at;
+            UPDATE metadata SET version = 10;
+        "#,
+    )
+    .expect("Cannot downgrade database");
+    drop(conn);
+
+    upgrade_database(&path);
+    let mut reader = SQLiteReader::open(&path).expect("Cannot open reader");
+    for file in &files {
+        let summary = reader
+            .file_summary(file)
+            .expect("Cannot summarize file")
+            .expect("Missing summary");
+        assert_eq!(None, summary.indexed_at);
+        let handle = reader.load_graph_for_file(file).expect("Cannot load graph");
+        let (graph, _, _) = reader.get();
+        assert_eq!(graph.nodes_for_file(handle).count(), summary.node_count);
+        assert!(summary.edge_count > 0);
+    }
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn can_merge_databases() {
+    let path = database_path("merge");
+    let other_path = database_path("merge-other");
+    let mut graph = StackGraph::new();
+    let file_a = add_file_with_global_definition(&mut graph, "a.py");
+    let file_b = add_file_with_global_definition(&mut graph, "b.py");
+
+    let mut writer = SQLiteWriter::open(&path).expect("Cannot open writer");
+    store_file(&mut writer, &graph, file_a);
+    writer
+        .store_error_for_file(Path::new("b.py"), "old", "parsing failed")
+        .expect("Cannot store error");
+
+    let mut other = SQLiteWriter::open(&other_path).expect("Cannot open writer");
+    store_file(&mut other, &graph, file_a);
+    store_file(&mut other, &graph, file_b);
+    other
+        .store_error_for_file(Path::new("c.py"), "tag", "parsing timed out")
+        .expect("Cannot store error");
+    drop(other);
+
+    // a.py has the same tag in both databases, and is not imported
+    assert_eq!(2, writer.merge_from(&other_path).expect("Cannot merge"));
+    assert!(matches!(
+        writer
+            .status_for_file("b.py", Some("tag"))
+            .expect("Cannot get status"),
+        FileStatus::Indexed
+    ));
+    assert!(matches!(
+        writer
+            .status_for_file("c.py", None)
+            .expect("Cannot get status"),
+        FileStatus::Error(_)
+    ));
+
+    let reader = writer.into_reader();
+    let mut files = files_defining(&reader, "x");
+    files.sort();
+    assert_eq!(vec!["a.py", "b.py"], files);
+    assert_eq!(
+        vec!["c.py".to_string()],
+        list_errors(&reader, None)
+            .into_iter()
+            .map(|(file, _)| file)
+            .collect::<Vec<_>>()
+    );
+    drop(reader);
+
+    let conn = rusqlite::Connection::open(&path).expect("Cannot open database");
+    let count = |table: &str| -> usize {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |r| r.get(0))
+            .expect("Cannot count rows")
+    };
+    assert_eq!(3, count("graphs"));
+    assert!(count("root_paths") > 0);
+    drop(conn);
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(&other_path);
+}
+
+#[test]
+fn cannot_merge_database_with_other_version() {
+    let path = database_path("merge-version");
+    let other_path = database_path("merge-version-other");
+    let files = write_legacy_database(&other_path, 6);
+
+    let mut writer = SQLiteWriter::open(&path).expect("Cannot open writer");
+    assert!(matches!(
+        writer.merge_from(&other_path),
+        Err(StorageError::IncompatibleVersion { database: 6, .. })
+    ));
+    // the other database is not upgraded
+    assert_eq!(6, database_version(&other_path));
+    assert_eq!(files.len(), stored_graph_count(&other_path));
+    assert_eq!(0, stored_graph_count(&path));
+    drop(writer);
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(&other_path);
+}
+
+#[test]
+fn can_merge_databases_with_different_column_order() {
+    let path = database_path("merge-columns");
+    let other_path = database_path("merge-columns-other");
+    let mut graph = StackGraph::new();
+    let file_a = add_file_with_global_definition(&mut graph, "a.py");
+
+    let mut other = SQLiteWriter::open(&other_path).expect("Cannot open writer");
+    store_file(&mut other, &graph, file_a);
+    drop(other);
+    let conn = rusqlite::Connection::open(&other_path).expect("Cannot open database");
+    conn.execute_batch(
+        r#"
+            ALTER TABLE symbols RENAME TO symbols_old;
+            CREATE TABLE symbols (kind TEXT NOT NULL, symbol TEXT NOT NULL, file TEXT NOT NULL) STRICT;
+            INSERT INTO symbols (kind, symbol, file) SELECT kind, symbol, file FROM symbols_old;
+            DROP TABLE symbols_old;
+        "#,
+    )
+    .expect("Cannot reorder columns");
+    drop(conn);
+
+    let mut writer = SQLiteWriter::open(&path).expect("Cannot open writer");
+    assert_eq!(1, writer.merge_from(&other_path).expect("Cannot merge"));
+    let reader = writer.into_reader();
+    assert_eq!(vec!["a.py"], files_defining(&reader, "x"));
+    drop(reader);
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(&other_path);
+}
+
+#[test]
+fn can_export_and_import_directory() {
+    let path = database_path("export");
+    let export_path = database_path("export-subset");
+    let import_path = database_path("export-import");
+    let mut graph = StackGraph::new();
+    let file_a = add_file_with_global_definition(&mut graph, "/src/dep/a.py");
+    let file_b = add_file_with_global_definition(&mut graph, "/src/app/b.py");
+
+    let mut writer = SQLiteWriter::open(&path).expect("Cannot open writer");
+    store_file(&mut writer, &graph, file_a);
+    store_file(&mut writer, &graph, file_b);
+    writer
+        .store_error_for_file(Path::new("/src/dep/c.py"), "tag", "parsing failed")
+        .expect("Cannot store error");
+    assert_eq!(
+        2,
+        writer
+            .export_file_or_directory(Path::new("/src/dep"), &export_path)
+            .expect("Cannot export")
+    );
+    drop(writer);
+
+    let mut importer = SQLiteWriter::open(&import_path).expect("Cannot open writer");
+    assert_eq!(2, importer.merge_from(&export_path).expect("Cannot import"));
+    let mut reader = importer.into_reader();
+    assert_eq!(vec!["/src/dep/a.py"], files_defining(&reader, "x"));
+    assert_eq!(
+        vec!["/src/dep/c.py".to_string()],
+        list_errors(&reader, None)
+            .into_iter()
+            .map(|(file, _)| file)
+            .collect::<Vec<_>>()
+    );
+    let file = reader
+        .load_graph_for_file("/src/dep/a.py")
+        .expect("Cannot load graph");
+    let (graph, _, _) = reader.get();
+    assert!(graph.nodes_for_file(file).count() > 0);
+    drop(reader);
+    for path in [&path, &export_path, &import_path] {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+#[test]
+fn can_prune_stale_files() {
+    let path = database_path("prune");
+    let mut graph = StackGraph::new();
+    let file_a = add_file_with_global_definition(&mut graph, "a.py");
+    let file_b = add_file_with_global_definition(&mut graph, "b.py");
+
+    let mut writer = SQLiteWriter::open(&path).expect("Cannot open writer");
+    store_file(&mut writer, &graph, file_a);
+    store_file(&mut writer, &graph, file_b);
+    assert_eq!(
+        1,
+        writer
+            .prune_files(|entry| entry.path == Path::new("a.py"))
+            .expect("Cannot prune")
+    );
+    assert!(matches!(
+        writer.status_for_file("a.py", None),
+        Ok(FileStatus::Missing)
+    ));
+    assert!(matches!(
+        writer.status_for_file("b.py", None),
+        Ok(FileStatus::Indexed)
+    ));
+
+    // the content that the pruned file shared is kept
+    writer.vacuum().expect("Cannot vacuum");
+    let reader = writer.into_reader();
+    assert_eq!(vec!["b.py"], files_defining(&reader, "x"));
+    assert_eq!(
+        Vec::<DatabaseProblem>::new(),
+        reader.verify(&NoCancellation).expect("Cannot verify")
+    );
+    drop(reader);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn can_vacuum_database() {
+    let path = database_path("vacuum");
+    let mut writer = SQLiteWriter::open(&path).expect("Cannot open writer");
+    store_files(&mut writer);
+    writer.flush().expect("Cannot flush");
+    let conn = rusqlite::Connection::open(&path).expect("Cannot open database");
+    conn.pragma_update(None, "foreign_keys", false)
+        .expect("Cannot disable foreign keys");
+    conn.execute(
+        "INSERT INTO symbols (file, symbol, kind) VALUES ('gone.py', 'x', 'definition')",
+        [],
+    )
+    .expect("Cannot insert symbol");
+    drop(conn);
+
+    writer.clean_all().expect("Cannot clean");
+    writer.vacuum().expect("Cannot vacuum");
+    drop(writer);
+
+    let conn = rusqlite::Connection::open(&path).expect("Cannot open database");
+    let count = |table: &str| -> usize {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |r| r.get(0))
+            .expect("Cannot count rows")
+    };
+    assert_eq!(0, count("symbols"));
+    assert_eq!(0, count("contents"));
+    let free_pages: usize = conn
+        .query_row("PRAGMA freelist_count", [], |r| r.get(0))
+        .expect("Cannot count free pages");
+    assert_eq!(0, free_pages);
+    drop(conn);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn can_verify_database() {
+    let path = database_path("verify");
+    let mut graph = StackGraph::new();
+    let file_a = add_file_with_global_definition(&mut graph, "a.py");
+    let file_b = graph.file("b.py");
+    let sym_y = graph.symbol("y");
+    let def_y = graph.definition(file_b, 1, sym_y);
+    let root = graph.root_node();
+    graph.edge(root, def_y);
+
+    let mut writer = SQLiteWriter::open(&path).expect("Cannot open writer");
+    store_file(&mut writer, &graph, file_a);
+    store_file(&mut writer, &graph, file_b);
+    let reader = writer.into_reader();
+    assert_eq!(
+        Vec::<DatabaseProblem>::new(),
+        reader.verify(&NoCancellation).expect("Cannot verify")
+    );
+    drop(reader);
+
+    let conn = rusqlite::Connection::open(&path).expect("Cannot open database");
+    conn.pragma_update(None, "foreign_keys", false)
+        .expect("Cannot disable foreign keys");
+    let hash: String = conn
+        .query_row("SELECT hash FROM graphs WHERE file = 'a.py'", [], |r| {
+            r.get(0)
+        })
+        .expect("Cannot get hash");
+    conn.execute(
+        "UPDATE contents SET value = x'00ff' WHERE hash = ?",
+        [&hash],
+    )
+    .expect("Cannot corrupt content");
+    conn.execute(
+        "INSERT INTO symbols (file, symbol, kind) VALUES ('b.py', 'z', 'reference')",
+        [],
+    )
+    .expect("Cannot insert symbol");
+    conn.execute(
+        "INSERT INTO errors (file, kind, message) VALUES ('gone.py', 'unknown', 'failed')",
+        [],
+    )
+    .expect("Cannot insert error");
+    drop(conn);
+
+    let reader = SQLiteReader::open(&path).expect("Cannot open reader");
+    let problems = reader.verify(&NoCancellation).expect("Cannot verify");
+    let mut problems = problems
+        .iter()
+        .map(|p| (p.table, p.key.as_str()))
+        .collect::<Vec<_>>();
+    problems.sort();
+    assert_eq!(
+        vec![
+            ("contents", hash.as_str()),
+            ("errors", "gone.py"),
+            ("symbols", "b.py"),
+        ],
+        problems
+    );
+    drop(reader);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn concurrent_writers_report_contention() {
+    let path = database_path("contended");
+    let mut graph = StackGraph::new();
+    let file_a = add_file_with_global_definition(&mut graph, "a.py");
+    let file_b = add_file_with_global_definition(&mut graph, "b.py");
+
+    let mut first = SQLiteWriter::open_with_options(
+        &path,
+        SQLiteWriterOptions {
+            batch_size: 10,
+            ..SQLiteWriterOptions::default()
+        },
+    )
+    .expect("Cannot open writer");
+    let mut second = SQLiteWriter::open_with_options(
+        &path,
+        SQLiteWriterOptions {
+            busy_timeout: Some(Duration::from_millis(50)),
+            ..SQLiteWriterOptions::default()
+        },
+    )
+    .expect("Cannot open writer");
+
+    // the first writer holds the lock until its batch is committed
+    store_file(&mut first, &graph, file_a);
+    assert!(matches!(
+        second.store_error_for_file(Path::new("c.py"), "tag", "parsing failed"),
+        Err(StorageError::Contended)
+    ));
+
+    first.flush().expect("Cannot flush");
+    store_file(&mut second, &graph, file_b);
+    drop(first);
+    drop(second);
+
+    let reader = SQLiteReader::open_read_only(&path).expect("Cannot open reader");
+    let mut files = files_defining(&reader, "x");
+    files.sort();
+    assert_eq!(vec!["a.py", "b.py"], files);
+    drop(reader);
+    let _ = std::fs::remove_file(&path);
+}
+
+/// Cancels at every check after the given number of checks.
+struct CancelAfterChecks(std::cell::Cell<usize>);
+
+impl CancellationFlag for CancelAfterChecks {
+    fn check(&self, at: &'static str) -> Result<(), CancellationError> {
+        match self.0.get() {
+            0 => Err(CancellationError(at)),
+            n => {
+                self.0.set(n - 1);
+                Ok(())
+            }
+        }
+    }
+}
+
+fn complete_paths(
+    reader: &mut SQLiteReader,
+    files: &[String],
+    cancellation_flag: &dyn CancellationFlag,
+) -> Result<BTreeSet<String>, StorageError> {
+    for file in files {
+        reader.load_graph_for_file(file)?;
+    }
+    let (graph, _, _) = reader.get();
+    let references = graph
+        .iter_nodes()
+        .filter(|node| graph[*node].is_reference())
+        .collect::<Vec<_>>();
+    let mut paths = BTreeSet::new();
+    ForwardPartialPathStitcher::find_all_complete_partial_paths(
+        reader,
+        references,
+        cancellation_flag,
+        |graph, partials, path| {
+            paths.insert(path.display(graph, partials).to_string());
+        },
+    )?;
+    Ok(paths)
+}
+
+#[test]
+fn can_load_again_after_cancelled_load() {
+    let path = database_path("cancelled");
+    let files = write_database(&path);
+
+    let mut reader = SQLiteReader::open(&path).expect("Cannot open reader");
+    let expected = complete_paths(&mut reader, &files, &NoCancellation).expect("Cannot query");
+    assert!(!expected.is_empty());
+    drop(reader);
+
+    // cancel at every possible point, and check that loading again finds all paths
+    let mut cancelled = 0;
+    for checks in 0.. {
+        let mut reader = SQLiteReader::open(&path).expect("Cannot open reader");
+        let flag = CancelAfterChecks(std::cell::Cell::new(checks));
+        match complete_paths(&mut reader, &files, &flag) {
+            Ok(paths) => {
+                assert_eq!(expected, paths);
+                break;
+            }
+            Err(StorageError::Cancelled(_)) => cancelled += 1,
+            Err(err) => panic!("Unexpected error: {}", err),
+        }
+        let paths = complete_paths(&mut reader, &files, &NoCancellation).expect("Cannot query");
+        assert_eq!(expected, paths);
+    }
+    assert!(cancelled > 0);
+
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,143 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use proptest::prelude::*;
+use stack_graphs::graph::StackGraph;
+use stack_graphs::partial::PartialPaths;
+use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::testutil::*;
+use stack_graphs::NoCancellation;
+
+use crate::test_graphs;
+
+fn check_graph(graph: &StackGraph) {
+    let mut partials = PartialPaths::new();
+    let mut paths = Vec::new();
+    for file in graph.iter_files() {
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            graph,
+            &mut partials,
+            file,
+            &NoCancellation,
+            |_, _, path| paths.push(path.clone()),
+        )
+        .expect("should never be cancelled");
+    }
+    assert!(!paths.is_empty());
+    if let Err(e) = check_partial_path_invariants(graph, &mut partials, &paths) {
+        panic!("{}", e);
+    }
+}
+
+#[test]
+fn partial_paths_of_test_graphs_satisfy_invariants() {
+    check_graph(&test_graphs::class_field_through_function_parameter::new());
+    check_graph(&test_graphs::cyclic_imports_python::new());
+    check_graph(&test_graphs::cyclic_imports_rust::new());
+    check_graph(&test_graphs::sequenced_import_star::new());
+    check_graph(&test_graphs::simple::new());
+}
+
+#[test]
+fn path_does_not_shadow_itself() {
+    let graph = test_graphs::simple::new();
+    let mut partials = PartialPaths::new();
+    let mut paths = Vec::new();
+    for file in graph.iter_files() {
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            &NoCancellation,
+            |_, _, path| paths.push(path.clone()),
+        )
+        .expect("should never be cancelled");
+    }
+    let path = paths
+        .iter()
+        .find(|path| !path.edges.is_empty())
+        .expect("Missing path with edges");
+    check_shadows_antisymmetry(&graph, &mut partials, path, path).expect("Unexpected violation");
+}
+
+proptest! {
+    #[test]
+    fn generated_graphs_have_spec_nodes(spec in arb_graph(8)) {
+        let mut graph = StackGraph::new();
+        let file = graph.add_file("test").unwrap();
+        let nodes = spec.load_into(&mut graph, file);
+        prop_assert_eq!(spec.nodes.len(), nodes.len());
+    }
+
+    #[test]
+    fn generated_partial_paths_follow_walks(graph in arb_graph(8), path in arb_partial_path(6)) {
+        let graph = graph.build();
+        let mut partials = PartialPaths::new();
+        let (start, edges) = path.walk(&graph, &mut partials);
+        let path = path.build(&graph, &mut partials);
+        prop_assert_eq!(start, path.start_node);
+        prop_assert_eq!(edges.len(), path.edges.len());
+        prop_assert_eq!(edges.last().map_or(start, |edge| edge.sink), path.end_node);
+    }
+
+    #[test]
+    fn generated_symbol_stacks_are_ordered_consistently(
+        graph in arb_graph(4),
+        a in arb_symbol_stack(3),
+        b in arb_symbol_stack(3),
+    ) {
+        let mut graph = graph.build();
+        let mut partials = PartialPaths::new();
+        let a = a.build(&mut graph, &mut partials);
+        let b = b.build(&mut graph, &mut partials);
+        prop_assert!(a.equals(&mut partials, a));
+        let ab = a.cmp(&graph, &mut partials, b);
+        let ba = b.cmp(&graph, &mut partials, a);
+        prop_assert_eq!(ab, ba.reverse());
+        prop_assert_eq!(ab.is_eq(), a.equals(&mut partials, b));
+    }
+
+    #[test]
+    fn shadowing_is_antisymmetric(
+        graph in arb_graph(8),
+        a in arb_partial_path(4),
+        b in arb_partial_path(4),
+    ) {
+        let graph = graph.build();
+        let mut partials = PartialPaths::new();
+        let a = a.build(&graph, &mut partials);
+        let b = b.build(&graph, &mut partials);
+        if let Err(e) = check_shadows_antisymmetry(&graph, &mut partials, &a, &b) {
+            return Err(TestCaseError::fail(e.to_string()));
+        }
+    }
+
+    #[test]
+    fn concatenation_is_associative(
+        graph in arb_graph(8),
+        path in arb_partial_path(9),
+        first in any::<prop::sample::Index>(),
+        second in any::<prop::sample::Index>(),
+    ) {
+        let graph = graph.build();
+        let mut partials = PartialPaths::new();
+        let (start, edges) = path.walk(&graph, &mut partials);
+        // split the walk into three consecutive segments
+        let mut cuts = [first.index(edges.len() + 1), second.index(edges.len() + 1)];
+        cuts.sort();
+        let mut segment = |from: usize, to: usize| {
+            let start = if from == 0 { start } else { edges[from - 1].sink };
+            partial_path(&graph, &mut partials, start, &edges[from..to]).unwrap()
+        };
+        let a = segment(0, cuts[0]);
+        let b = segment(cuts[0], cuts[1]);
+        let c = segment(cuts[1], edges.len());
+        if let Err(e) = check_concatenation_associativity(&graph, &mut partials, &a, &b, &c) {
+            return Err(TestCaseError::fail(e.to_string()));
+        }
+    }
+}
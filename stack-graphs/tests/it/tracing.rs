@@ -0,0 +1,91 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use pretty_assertions::assert_eq;
+use stack_graphs::partial::PartialPaths;
+use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::NoCancellation;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use tracing::field::Field;
+use tracing::field::Visit;
+use tracing::span;
+use tracing::Event;
+use tracing::Metadata;
+use tracing::Subscriber;
+
+use crate::test_graphs;
+
+/// A subscriber that records the names and fields of all spans that are created.
+#[derive(Default)]
+struct SpanRecorder {
+    spans: Arc<Mutex<Vec<String>>>,
+    next_id: AtomicU64,
+}
+
+struct FieldRecorder<'a>(&'a mut String);
+
+impl Visit for FieldRecorder<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.push_str(&format!(" {}={}", field.name(), value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0.push_str(&format!(" {}={:?}", field.name(), value));
+    }
+}
+
+impl Subscriber for SpanRecorder {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+        let mut description = span.metadata().name().to_string();
+        span.record(&mut FieldRecorder(&mut description));
+        self.spans.lock().unwrap().push(description);
+        span::Id::from_u64(self.next_id.fetch_add(1, Ordering::SeqCst) + 1)
+    }
+
+    fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, _span: &span::Id) {}
+
+    fn exit(&self, _span: &span::Id) {}
+}
+
+#[test]
+fn stitching_creates_spans() {
+    let graph = test_graphs::simple::new();
+    let file = graph.get_file("test.py").expect("Missing file");
+    let recorder = SpanRecorder::default();
+    let spans = recorder.spans.clone();
+    tracing::subscriber::with_default(recorder, || {
+        let mut partials = PartialPaths::new();
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            &NoCancellation,
+            |_, _, _| {},
+        )
+        .expect("should never be cancelled");
+    });
+    let spans = spans.lock().unwrap();
+    assert_eq!(
+        "find_minimal_partial_path_set_in_file file=test.py",
+        spans[0]
+    );
+    assert!(spans[1..].iter().all(|span| span == "process_next_phase"));
+    assert!(spans.len() > 1);
+}
@@ -0,0 +1,89 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use lsp_positions::Position;
+use lsp_positions::Span;
+use stack_graphs::graph::NodeID;
+use stack_graphs::graph::StackGraph;
+use stack_graphs::validation::ValidationFinding;
+
+use crate::test_graphs::CreateStackGraph;
+
+fn span(line: usize) -> Span {
+    let position = |column| Position {
+        line,
+        column: lsp_positions::Offset {
+            utf8_offset: column,
+            utf16_offset: column,
+            grapheme_offset: column,
+        },
+        containing_line: 0..0,
+        trimmed_line: 0..0,
+    };
+    Span {
+        start: position(0),
+        end: position(1),
+    }
+}
+
+#[test]
+fn valid_graph_has_no_findings() {
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    let root = graph.root_node();
+    let sym_a = graph.symbol("a");
+    let sym_call = graph.symbol("()");
+    let definition = graph.definition(file, 1, sym_a);
+    graph.source_info_mut(definition).span = span(0);
+    let call = graph.pop_scoped_symbol(file, 2, sym_call);
+    let reference = graph.reference(file, 3, sym_a);
+    let push_call = graph.push_scoped_symbol(file, 4, sym_call, file, 5);
+    graph.exported_scope(file, 5);
+    graph.edge(root, definition);
+    graph.edge(definition, call);
+    graph.edge(reference, push_call);
+    graph.edge(push_call, root);
+
+    assert_eq!(Vec::<ValidationFinding>::new(), graph.validate());
+}
+
+#[test]
+fn can_find_mistakes() {
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    let root = graph.root_node();
+    let sym_a = graph.symbol("a");
+    let sym_dot = graph.symbol(".");
+    let definition = graph.definition(file, 1, sym_a);
+    let pop_dot = graph.pop_symbol(file, 2, sym_dot);
+    let push_a = graph.push_scoped_symbol(file, 3, sym_a, file, 4);
+    let unused = graph.exported_scope(file, 5);
+    graph.edge(root, pop_dot);
+    graph.edge(pop_dot, definition);
+    graph.edge(push_a, root);
+
+    let findings = graph.validate();
+    assert_eq!(
+        vec![
+            ValidationFinding::DefinitionWithoutSourceInfo { node: definition },
+            ValidationFinding::UnmatchedPop {
+                node: pop_dot,
+                symbol: sym_dot,
+            },
+            ValidationFinding::MissingAttachedScope {
+                node: push_a,
+                scope: NodeID::new_in_file(file, 4),
+            },
+            ValidationFinding::UnusedExportedScope { node: unused },
+        ],
+        findings
+    );
+    assert_eq!(
+        "[test.py(2) pop .] pops symbol ., which is never pushed",
+        findings[1].display(&graph).to_string()
+    );
+}
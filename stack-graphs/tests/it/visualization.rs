@@ -0,0 +1,143 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use lsp_positions::Position;
+use lsp_positions::Span;
+use stack_graphs::graph::StackGraph;
+use stack_graphs::partial::PartialPaths;
+use stack_graphs::serde::NoFilter;
+use stack_graphs::stitching::Database;
+use stack_graphs::NoContent;
+
+use crate::test_graphs;
+use crate::test_graphs::CreateStackGraph;
+
+fn to_html(graph: &StackGraph) -> String {
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+    graph
+        .to_html_string("test", &mut partials, &mut db, &NoFilter)
+        .expect("Cannot visualize graph")
+}
+
+/// Returns the parsed content of the script element with the given ID.
+fn chunk(html: &str, id: &str) -> serde_json::Value {
+    let start = html
+        .find(&format!(r#"id="{}">"#, id))
+        .unwrap_or_else(|| panic!("Missing chunk {}", id));
+    let content = &html[start + id.len() + 6..];
+    let end = content.find("</script>").expect("Unterminated chunk");
+    serde_json::from_str(&content[..end]).expect("Invalid chunk")
+}
+
+#[test]
+fn small_graphs_are_not_chunked() {
+    let graph = test_graphs::simple::new();
+    let html = to_html(&graph);
+    assert!(html.contains("let chunks = null;"));
+    assert!(!html.contains("sg-chunk-"));
+}
+
+#[test]
+fn can_write_html() {
+    let mut graph = StackGraph::new();
+    let root = graph.root_node();
+    let file = graph.file("</script>.py");
+    let symbol = graph.symbol("</\"</");
+    let node = graph.definition(file, 0, symbol);
+    graph.edge(root, node);
+
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+    let mut html = Vec::new();
+    graph
+        .write_html(
+            &mut html,
+            "test",
+            &mut partials,
+            &mut db,
+            &NoFilter,
+            &mut NoContent,
+        )
+        .expect("Cannot visualize graph");
+    let html = String::from_utf8(html).unwrap();
+    assert_eq!(to_html(&graph), html);
+    assert!(html.contains(r#""<\/script>.py""#));
+    assert!(html.contains(r#""<\/\"<\/""#));
+}
+
+#[test]
+fn can_embed_source_lines() {
+    let mut graph = StackGraph::new();
+    let root = graph.root_node();
+    let file = graph.file("test.py");
+    let symbol = graph.symbol("x");
+    let node = graph.definition(file, 0, symbol);
+    graph.source_info_mut(node).span = Span {
+        start: Position {
+            line: 1,
+            ..Default::default()
+        },
+        end: Position {
+            line: 1,
+            ..Default::default()
+        },
+    };
+    graph.edge(root, node);
+
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+    let mut html = Vec::new();
+    graph
+        .write_html(
+            &mut html,
+            "test",
+            &mut partials,
+            &mut db,
+            &NoFilter,
+            &mut Some("import y\n    x = 1  \n"),
+        )
+        .expect("Cannot visualize graph");
+    let html = String::from_utf8(html).unwrap();
+    assert!(html.contains(r#"let sources = {"test.py":{"1":"x = 1"}};"#));
+    assert!(to_html(&graph).contains("let sources = {};"));
+}
+
+#[test]
+fn large_graphs_are_chunked_by_file() {
+    let mut graph = StackGraph::new();
+    let root = graph.root_node();
+    let names = ["a.py", "b.py", "</script>.py"];
+    for name in names {
+        let file = graph.file(name);
+        let mut prev = root;
+        for local_id in 0..400 {
+            let node = graph.internal_scope(file, local_id);
+            graph.edge(prev, node);
+            prev = node;
+        }
+    }
+
+    let html = to_html(&graph);
+    assert!(!html.contains("let chunks = null;"));
+    assert!(!html.contains("</script>.py"));
+    let mut files = Vec::new();
+    for index in 0..names.len() {
+        let chunk = chunk(&html, &format!("sg-chunk-{}", index));
+        let nodes = chunk["nodes"].as_array().unwrap();
+        let file = nodes[0]["id"]["file"].as_str().unwrap().to_string();
+        assert_eq!(400, nodes.len());
+        assert!(nodes.iter().all(|node| node["id"]["file"] == file.as_str()));
+        assert_eq!(400, chunk["edges"].as_array().unwrap().len());
+        files.push(file);
+    }
+    files.sort();
+    let mut expected = names.to_vec();
+    expected.sort();
+    assert_eq!(expected, files);
+    assert!(!html.contains(&format!("sg-chunk-{}", names.len())));
+}
@@ -57,10 +57,14 @@
 //! }
 //! ```
 
+pub mod bench;
 pub mod clean;
+pub mod coverage;
 pub mod database;
+pub mod export;
 pub mod index;
 pub mod init;
+pub mod lint;
 pub mod load;
 #[cfg(feature = "lsp")]
 pub mod lsp;
@@ -69,6 +73,7 @@ pub mod parse;
 pub mod query;
 pub mod status;
 pub mod test;
+pub mod unused;
 pub mod util;
 pub mod visualize;
 
@@ -77,10 +82,15 @@ pub mod path_loading {
 
     use clap::Subcommand;
 
+    use crate::cli::bench::BenchArgs;
     use crate::cli::clean::CleanArgs;
+    use crate::cli::coverage::CoverageArgs;
     use crate::cli::database::DatabaseArgs;
+    use crate::cli::database::DatabaseCommandArgs;
+    use crate::cli::export::ExportArgs;
     use crate::cli::index::IndexArgs;
     use crate::cli::init::InitArgs;
+    use crate::cli::lint::LintArgs;
     use crate::cli::load::PathLoaderArgs;
     #[cfg(feature = "lsp")]
     use crate::cli::lsp::LspArgs;
@@ -89,13 +99,19 @@ pub mod path_loading {
     use crate::cli::r#match::MatchArgs;
     use crate::cli::status::StatusArgs;
     use crate::cli::test::TestArgs;
+    use crate::cli::unused::UnusedArgs;
     use crate::cli::visualize::VisualizeArgs;
 
     #[derive(Subcommand)]
     pub enum Subcommands {
+        Bench(Bench),
         Clean(Clean),
+        Coverage(Coverage),
+        Database(Database),
+        Export(Export),
         Index(Index),
         Init(Init),
+        Lint(Lint),
         #[cfg(feature = "lsp")]
         Lsp(Lsp),
         Match(Match),
@@ -103,15 +119,21 @@ pub mod path_loading {
         Query(Query),
         Status(Status),
         Test(Test),
+        Unused(Unused),
         Visualize(Visualize),
     }
 
     impl Subcommands {
         pub fn run(self, default_db_path: PathBuf) -> anyhow::Result<()> {
             match self {
+                Self::Bench(cmd) => cmd.run(default_db_path),
                 Self::Clean(cmd) => cmd.run(default_db_path),
+                Self::Coverage(cmd) => cmd.run(default_db_path),
+                Self::Database(cmd) => cmd.run(default_db_path),
+                Self::Export(cmd) => cmd.run(default_db_path),
                 Self::Index(cmd) => cmd.run(default_db_path),
                 Self::Init(cmd) => cmd.run(),
+                Self::Lint(cmd) => cmd.run(),
                 #[cfg(feature = "lsp")]
                 Self::Lsp(cmd) => cmd.run(default_db_path),
                 Self::Match(cmd) => cmd.run(),
@@ -119,11 +141,28 @@ pub mod path_loading {
                 Self::Query(cmd) => cmd.run(default_db_path),
                 Self::Status(cmd) => cmd.run(default_db_path),
                 Self::Test(cmd) => cmd.run(),
+                Self::Unused(cmd) => cmd.run(default_db_path),
                 Self::Visualize(cmd) => cmd.run(default_db_path),
             }
         }
     }
 
+    /// Replay definition queries against the indexing database, and report their latency.
+    #[derive(clap::Parser)]
+    pub struct Bench {
+        #[clap(flatten)]
+        db_args: DatabaseArgs,
+        #[clap(flatten)]
+        bench_args: BenchArgs,
+    }
+
+    impl Bench {
+        pub fn run(self, default_db_path: PathBuf) -> anyhow::Result<()> {
+            let db_path = self.db_args.get_or(default_db_path);
+            self.bench_args.run(&db_path)
+        }
+    }
+
     /// Clean the indexing database.
     #[derive(clap::Parser)]
     pub struct Clean {
@@ -140,6 +179,54 @@ pub mod path_loading {
         }
     }
 
+    /// Report how many references in the indexing database resolve to definitions.
+    #[derive(clap::Parser)]
+    pub struct Coverage {
+        #[clap(flatten)]
+        db_args: DatabaseArgs,
+        #[clap(flatten)]
+        coverage_args: CoverageArgs,
+    }
+
+    impl Coverage {
+        pub fn run(self, default_db_path: PathBuf) -> anyhow::Result<()> {
+            let db_path = self.db_args.get_or(default_db_path);
+            self.coverage_args.run(&db_path)
+        }
+    }
+
+    /// Manage the indexing database.
+    #[derive(clap::Parser)]
+    pub struct Database {
+        #[clap(flatten)]
+        db_args: DatabaseArgs,
+        #[clap(flatten)]
+        database_args: DatabaseCommandArgs,
+    }
+
+    impl Database {
+        pub fn run(self, default_db_path: PathBuf) -> anyhow::Result<()> {
+            let db_path = self.db_args.get_or(default_db_path);
+            self.database_args.run(&db_path)
+        }
+    }
+
+    /// Export data derived from the indexing database.
+    #[derive(clap::Parser)]
+    pub struct Export {
+        #[clap(flatten)]
+        db_args: DatabaseArgs,
+        #[clap(flatten)]
+        export_args: ExportArgs,
+    }
+
+    impl Export {
+        pub fn run(self, default_db_path: PathBuf) -> anyhow::Result<()> {
+            let db_path = self.db_args.get_or(default_db_path);
+            self.export_args.run(&db_path)
+        }
+    }
+
     /// Index source files into the database.
     #[derive(clap::Parser)]
     pub struct Index {
@@ -172,6 +259,22 @@ pub mod path_loading {
         }
     }
 
+    /// Check the stack graphs of source files for likely mistakes in the stack graph rules.
+    #[derive(clap::Parser)]
+    pub struct Lint {
+        #[clap(flatten)]
+        load_args: PathLoaderArgs,
+        #[clap(flatten)]
+        lint_args: LintArgs,
+    }
+
+    impl Lint {
+        pub fn run(self) -> anyhow::Result<()> {
+            let loader = self.load_args.get()?;
+            self.lint_args.run(loader)
+        }
+    }
+
     /// Run an LSP server.
     #[cfg(feature = "lsp")]
     #[derive(clap::Parser)]
@@ -273,6 +376,22 @@ pub mod path_loading {
         }
     }
 
+    /// List exported definitions that no reference in the indexing database resolves to.
+    #[derive(clap::Parser)]
+    pub struct Unused {
+        #[clap(flatten)]
+        db_args: DatabaseArgs,
+        #[clap(flatten)]
+        unused_args: UnusedArgs,
+    }
+
+    impl Unused {
+        pub fn run(self, default_db_path: PathBuf) -> anyhow::Result<()> {
+            let db_path = self.db_args.get_or(default_db_path);
+            self.unused_args.run(&db_path)
+        }
+    }
+
     /// Visualize command
     #[derive(clap::Parser)]
     pub struct Visualize {
@@ -295,10 +414,15 @@ pub mod provided_languages {
 
     use clap::Subcommand;
 
+    use crate::cli::bench::BenchArgs;
     use crate::cli::clean::CleanArgs;
+    use crate::cli::coverage::CoverageArgs;
     use crate::cli::database::DatabaseArgs;
+    use crate::cli::database::DatabaseCommandArgs;
+    use crate::cli::export::ExportArgs;
     use crate::cli::index::IndexArgs;
     use crate::cli::init::InitArgs;
+    use crate::cli::lint::LintArgs;
     use crate::cli::load::LanguageConfigurationsLoaderArgs;
     #[cfg(feature = "lsp")]
     use crate::cli::lsp::LspArgs;
@@ -307,14 +431,20 @@ pub mod provided_languages {
     use crate::cli::r#match::MatchArgs;
     use crate::cli::status::StatusArgs;
     use crate::cli::test::TestArgs;
+    use crate::cli::unused::UnusedArgs;
     use crate::cli::visualize::VisualizeArgs;
     use crate::loader::LanguageConfiguration;
 
     #[derive(Subcommand)]
     pub enum Subcommands {
+        Bench(Bench),
         Clean(Clean),
+        Coverage(Coverage),
+        Database(Database),
+        Export(Export),
         Index(Index),
         Init(Init),
+        Lint(Lint),
         #[cfg(feature = "lsp")]
         Lsp(Lsp),
         Match(Match),
@@ -322,6 +452,7 @@ pub mod provided_languages {
         Query(Query),
         Status(Status),
         Test(Test),
+        Unused(Unused),
         Visualize(Visualize),
     }
 
@@ -332,9 +463,14 @@ pub mod provided_languages {
             configurations: Vec<LanguageConfiguration>,
         ) -> anyhow::Result<()> {
             match self {
+                Self::Bench(cmd) => cmd.run(default_db_path),
                 Self::Clean(cmd) => cmd.run(default_db_path),
+                Self::Coverage(cmd) => cmd.run(default_db_path),
+                Self::Database(cmd) => cmd.run(default_db_path),
+                Self::Export(cmd) => cmd.run(default_db_path),
                 Self::Index(cmd) => cmd.run(default_db_path, configurations),
                 Self::Init(cmd) => cmd.run(),
+                Self::Lint(cmd) => cmd.run(configurations),
                 #[cfg(feature = "lsp")]
                 Self::Lsp(cmd) => cmd.run(default_db_path, configurations),
                 Self::Match(cmd) => cmd.run(configurations),
@@ -342,11 +478,28 @@ pub mod provided_languages {
                 Self::Query(cmd) => cmd.run(default_db_path),
                 Self::Status(cmd) => cmd.run(default_db_path),
                 Self::Test(cmd) => cmd.run(configurations),
+                Self::Unused(cmd) => cmd.run(default_db_path),
                 Self::Visualize(cmd) => cmd.run(default_db_path),
             }
         }
     }
 
+    /// Replay definition queries against the indexing database, and report their latency.
+    #[derive(clap::Parser)]
+    pub struct Bench {
+        #[clap(flatten)]
+        db_args: DatabaseArgs,
+        #[clap(flatten)]
+        bench_args: BenchArgs,
+    }
+
+    impl Bench {
+        pub fn run(self, default_db_path: PathBuf) -> anyhow::Result<()> {
+            let db_path = self.db_args.get_or(default_db_path);
+            self.bench_args.run(&db_path)
+        }
+    }
+
     /// Clean the indexing database.
     #[derive(clap::Parser)]
     pub struct Clean {
@@ -363,6 +516,54 @@ pub mod provided_languages {
         }
     }
 
+    /// Report how many references in the indexing database resolve to definitions.
+    #[derive(clap::Parser)]
+    pub struct Coverage {
+        #[clap(flatten)]
+        db_args: DatabaseArgs,
+        #[clap(flatten)]
+        coverage_args: CoverageArgs,
+    }
+
+    impl Coverage {
+        pub fn run(self, default_db_path: PathBuf) -> anyhow::Result<()> {
+            let db_path = self.db_args.get_or(default_db_path);
+            self.coverage_args.run(&db_path)
+        }
+    }
+
+    /// Manage the indexing database.
+    #[derive(clap::Parser)]
+    pub struct Database {
+        #[clap(flatten)]
+        db_args: DatabaseArgs,
+        #[clap(flatten)]
+        database_args: DatabaseCommandArgs,
+    }
+
+    impl Database {
+        pub fn run(self, default_db_path: PathBuf) -> anyhow::Result<()> {
+            let db_path = self.db_args.get_or(default_db_path);
+            self.database_args.run(&db_path)
+        }
+    }
+
+    /// Export data derived from the indexing database.
+    #[derive(clap::Parser)]
+    pub struct Export {
+        #[clap(flatten)]
+        db_args: DatabaseArgs,
+        #[clap(flatten)]
+        export_args: ExportArgs,
+    }
+
+    impl Export {
+        pub fn run(self, default_db_path: PathBuf) -> anyhow::Result<()> {
+            let db_path = self.db_args.get_or(default_db_path);
+            self.export_args.run(&db_path)
+        }
+    }
+
     /// Index source files into the database.
     #[derive(clap::Parser)]
     pub struct Index {
@@ -399,6 +600,22 @@ pub mod provided_languages {
         }
     }
 
+    /// Check the stack graphs of source files for likely mistakes in the stack graph rules.
+    #[derive(clap::Parser)]
+    pub struct Lint {
+        #[clap(flatten)]
+        load_args: LanguageConfigurationsLoaderArgs,
+        #[clap(flatten)]
+        lint_args: LintArgs,
+    }
+
+    impl Lint {
+        pub fn run(self, configurations: Vec<LanguageConfiguration>) -> anyhow::Result<()> {
+            let loader = self.load_args.get(configurations)?;
+            self.lint_args.run(loader)
+        }
+    }
+
     /// Run an LSP server.
     #[cfg(feature = "lsp")]
     #[derive(clap::Parser)]
@@ -504,6 +721,22 @@ pub mod provided_languages {
         }
     }
 
+    /// List exported definitions that no reference in the indexing database resolves to.
+    #[derive(clap::Parser)]
+    pub struct Unused {
+        #[clap(flatten)]
+        db_args: DatabaseArgs,
+        #[clap(flatten)]
+        unused_args: UnusedArgs,
+    }
+
+    impl Unused {
+        pub fn run(self, default_db_path: PathBuf) -> anyhow::Result<()> {
+            let db_path = self.db_args.get_or(default_db_path);
+            self.unused_args.run(&db_path)
+        }
+    }
+
     /// Visualize command
     #[derive(clap::Parser)]
     pub struct Visualize {
@@ -0,0 +1,184 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use clap::Args;
+use clap::ValueHint;
+use serde_json::json;
+use stack_graphs::stitching::StitchingStats;
+use stack_graphs::storage::SQLiteReader;
+use std::fs::File;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::cli::query::Querier;
+use crate::cli::query::ReportFormat;
+use crate::cli::util::reporter::ConsoleReporter;
+use crate::cli::util::PositionEncoding;
+use crate::cli::util::SourcePosition;
+use crate::NoCancellation;
+
+#[derive(Args)]
+pub struct BenchArgs {
+    /// File with the reference source positions to query, one position per line, formatted as
+    /// for `query definition`, or `-` to read them from standard input.
+    #[clap(value_name = "POSITIONS_FILE", value_hint = ValueHint::FilePath)]
+    pub positions_file: PathBuf,
+
+    /// Number of times all queries are replayed.
+    #[clap(
+        long,
+        short = 'n',
+        value_name = "N",
+        default_value_t = 10,
+        value_parser = clap::value_parser!(u64).range(1..),
+    )]
+    pub iterations: u64,
+
+    /// Clear the data loaded from the database before every iteration, so that loading graphs
+    /// and paths is measured in every iteration, instead of only in the first.
+    #[clap(long)]
+    pub cold: bool,
+
+    /// Unit of the columns of the source positions.
+    #[clap(long, value_enum, default_value_t = PositionEncoding::Grapheme)]
+    pub position_encoding: PositionEncoding,
+
+    /// Format of the report.
+    #[clap(long, value_enum, default_value_t = ReportFormat::Text)]
+    pub format: ReportFormat,
+}
+
+impl BenchArgs {
+    pub fn run(self, db_path: &Path) -> anyhow::Result<()> {
+        let references = self.read_positions()?;
+        let mut db = SQLiteReader::open(db_path)?;
+        let reporter = ConsoleReporter::none();
+
+        let mut latencies = Vec::new();
+        let mut iterations = Vec::new();
+        for _ in 0..self.iterations {
+            if self.cold {
+                db.clear();
+            }
+            let mut querier = Querier::new(&mut db, &reporter);
+            let iteration_start = Instant::now();
+            for reference in &references {
+                let start = Instant::now();
+                querier.definitions(reference.clone(), &NoCancellation)?;
+                latencies.push(start.elapsed());
+            }
+            iterations.push(Iteration {
+                duration: iteration_start.elapsed(),
+                stats: querier.stats(),
+            });
+        }
+        latencies.sort();
+
+        match self.format {
+            ReportFormat::Text => {
+                println!(
+                    "{} queries, {} iterations",
+                    references.len(),
+                    iterations.len()
+                );
+                println!(
+                    "latency: min {}, p50 {}, p90 {}, p99 {}, max {}",
+                    millis(percentile(&latencies, 0.0)),
+                    millis(percentile(&latencies, 50.0)),
+                    millis(percentile(&latencies, 90.0)),
+                    millis(percentile(&latencies, 99.0)),
+                    millis(percentile(&latencies, 100.0)),
+                );
+                for (idx, iteration) in iterations.iter().enumerate() {
+                    println!(
+                        "iteration {}: {}, pruned {} cyclic and {} similar paths",
+                        idx + 1,
+                        millis(iteration.duration),
+                        iteration.stats.cyclic_paths,
+                        iteration.stats.similar_paths,
+                    );
+                }
+            }
+            ReportFormat::Json => {
+                let report = json!({
+                    "queries": references.len(),
+                    "latency_ms": {
+                        "min": percentile(&latencies, 0.0).as_secs_f64() * 1000.0,
+                        "p50": percentile(&latencies, 50.0).as_secs_f64() * 1000.0,
+                        "p90": percentile(&latencies, 90.0).as_secs_f64() * 1000.0,
+                        "p99": percentile(&latencies, 99.0).as_secs_f64() * 1000.0,
+                        "max": percentile(&latencies, 100.0).as_secs_f64() * 1000.0,
+                    },
+                    "iterations": iterations
+                        .iter()
+                        .map(|iteration| json!({
+                            "duration_ms": iteration.duration.as_secs_f64() * 1000.0,
+                            "cyclic_paths": iteration.stats.cyclic_paths,
+                            "similar_paths": iteration.stats.similar_paths,
+                        }))
+                        .collect::<Vec<_>>(),
+                });
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            }
+        }
+        Ok(())
+    }
+
+    fn read_positions(&self) -> anyhow::Result<Vec<SourcePosition>> {
+        let reader: Box<dyn BufRead> = if self.positions_file == Path::new("-") {
+            Box::new(std::io::stdin().lock())
+        } else {
+            Box::new(BufReader::new(File::open(&self.positions_file)?))
+        };
+        let mut references = Vec::new();
+        for (line_number, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let mut reference = line.parse::<SourcePosition>().map_err(|e| {
+                anyhow::anyhow!(
+                    "{}:{}: {}",
+                    self.positions_file.display(),
+                    line_number + 1,
+                    e
+                )
+            })?;
+            reference.encoding = self.position_encoding;
+            // files may be missing if the database was copied from another machine
+            if reference.path.exists() {
+                reference.canonicalize()?;
+            }
+            references.push(reference);
+        }
+        Ok(references)
+    }
+}
+
+/// The time and path stitching statistics of one replay of all queries.
+struct Iteration {
+    duration: Duration,
+    stats: StitchingStats,
+}
+
+/// Returns the given percentile of sorted latencies, using the nearest rank.
+fn percentile(latencies: &[Duration], percentile: f64) -> Duration {
+    if latencies.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (percentile / 100.0 * latencies.len() as f64).ceil() as usize;
+    latencies[rank.clamp(1, latencies.len()) - 1]
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.3} ms", duration.as_secs_f64() * 1000.0)
+}
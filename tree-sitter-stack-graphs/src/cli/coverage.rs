@@ -0,0 +1,146 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use clap::Args;
+use clap::ValueHint;
+use serde_json::json;
+use stack_graphs::storage::SQLiteReader;
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::cli::query::Querier;
+use crate::cli::query::ReportFormat;
+use crate::cli::util::indexed_files;
+use crate::cli::util::reporter::ConsoleReporter;
+use crate::NoCancellation;
+
+#[derive(Args)]
+pub struct CoverageArgs {
+    /// Source file or directory paths.  If none are given, all indexed files are included.
+    #[clap(value_name = "SOURCE_PATH", value_hint = ValueHint::AnyPath)]
+    pub source_paths: Vec<PathBuf>,
+
+    /// Maximum number of unresolved symbols to list, most frequent first.
+    #[clap(long, value_name = "COUNT", default_value_t = 20)]
+    pub unresolved_symbols: usize,
+
+    /// Format of the report.
+    #[clap(long, value_enum, default_value_t = ReportFormat::Text)]
+    pub format: ReportFormat,
+}
+
+impl CoverageArgs {
+    pub fn run(self, db_path: &Path) -> anyhow::Result<()> {
+        let mut db = SQLiteReader::open(db_path)?;
+        let files = indexed_files(&mut db, &self.source_paths)?;
+        let reporter = ConsoleReporter::none();
+        let mut querier = Querier::new(&mut db, &reporter);
+
+        let mut file_coverage = Vec::new();
+        let mut unresolved_symbols = HashMap::<String, usize>::new();
+        for file in files {
+            let mut coverage = Coverage::default();
+            for result in querier.file_definitions(&file, &NoCancellation)? {
+                coverage.references += 1;
+                if result.targets.is_empty() {
+                    if let Some(symbol) = result.symbol {
+                        *unresolved_symbols.entry(symbol).or_default() += 1;
+                    }
+                } else {
+                    coverage.resolved += 1;
+                }
+            }
+            file_coverage.push((file, coverage));
+        }
+
+        let mut total = Coverage::default();
+        for (_, coverage) in &file_coverage {
+            total.references += coverage.references;
+            total.resolved += coverage.resolved;
+        }
+        let mut unresolved_symbols = unresolved_symbols.into_iter().collect::<Vec<_>>();
+        unresolved_symbols
+            .sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+        unresolved_symbols.truncate(self.unresolved_symbols);
+
+        match self.format {
+            ReportFormat::Text => {
+                for (file, coverage) in &file_coverage {
+                    println!("{}: {}", file.display(), coverage);
+                }
+                println!("total: {} in {} files", total, file_coverage.len());
+                if !unresolved_symbols.is_empty() {
+                    println!("most frequent unresolved symbols:");
+                    for (symbol, count) in &unresolved_symbols {
+                        println!("{:>8} {}", count, symbol);
+                    }
+                }
+            }
+            ReportFormat::Json => {
+                let report = json!({
+                    "files": file_coverage
+                        .iter()
+                        .map(|(file, coverage)| {
+                            let mut value = coverage.to_json();
+                            value["path"] = file.to_string_lossy().into();
+                            value
+                        })
+                        .collect::<Vec<_>>(),
+                    "total": total.to_json(),
+                    "unresolved_symbols": unresolved_symbols
+                        .iter()
+                        .map(|(symbol, count)| json!({ "symbol": symbol, "count": count }))
+                        .collect::<Vec<_>>(),
+                });
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The number of references in a set of files, and how many of them resolve to a definition.
+#[derive(Default)]
+struct Coverage {
+    references: usize,
+    resolved: usize,
+}
+
+impl Coverage {
+    fn unresolved(&self) -> usize {
+        self.references - self.resolved
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        json!({
+            "references": self.references,
+            "resolved": self.resolved,
+            "unresolved": self.unresolved(),
+        })
+    }
+}
+
+impl std::fmt::Display for Coverage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} references, {} resolved, {} unresolved",
+            self.references,
+            self.resolved,
+            self.unresolved()
+        )?;
+        if self.references > 0 {
+            write!(
+                f,
+                " ({:.1}% resolved)",
+                100.0 * self.resolved as f64 / self.references as f64
+            )?;
+        }
+        Ok(())
+    }
+}
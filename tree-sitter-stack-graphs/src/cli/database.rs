@@ -7,8 +7,22 @@
 
 use anyhow::anyhow;
 use clap::Args;
+use clap::Parser;
+use clap::Subcommand;
+use clap::ValueEnum;
 use clap::ValueHint;
+use serde_json::json;
+use stack_graphs::storage::FileSummary;
+use stack_graphs::storage::SQLiteReader;
+use stack_graphs::storage::SQLiteWriter;
+use stack_graphs::NoCancellation;
+use std::path::Path;
 use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+use crate::cli::query::ReportFormat;
+use crate::cli::util::sha1;
+use crate::cli::util::ExistingPathBufValueParser;
 
 #[derive(Args)]
 pub struct DatabaseArgs {
@@ -38,3 +52,382 @@ pub fn default_user_database_path_for_crate(crate_name: &str) -> anyhow::Result<
         )),
     }
 }
+
+#[derive(Args)]
+pub struct DatabaseCommandArgs {
+    #[clap(subcommand)]
+    command: DatabaseCommand,
+}
+
+impl DatabaseCommandArgs {
+    pub fn run(self, db_path: &Path) -> anyhow::Result<()> {
+        match self.command {
+            DatabaseCommand::Export(cmd) => cmd.run(db_path),
+            DatabaseCommand::Import(cmd) => cmd.run(db_path),
+            DatabaseCommand::Merge(cmd) => cmd.run(db_path),
+            DatabaseCommand::Prune(cmd) => cmd.run(db_path),
+            DatabaseCommand::Usage(cmd) => cmd.run(db_path),
+            DatabaseCommand::Vacuum(cmd) => cmd.run(db_path),
+            DatabaseCommand::Verify(cmd) => cmd.run(db_path),
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum DatabaseCommand {
+    Export(Export),
+    Import(Import),
+    Merge(Merge),
+    Prune(Prune),
+    Usage(Usage),
+    Vacuum(Vacuum),
+    Verify(Verify),
+}
+
+/// Export the indexing data of files under a directory into a standalone database, which can be
+/// imported into other databases.
+#[derive(Parser)]
+pub struct Export {
+    /// Source file or directory path for which to export indexing data.
+    #[clap(
+        value_name = "SOURCE_PATH",
+        value_hint = ValueHint::AnyPath,
+        value_parser = ExistingPathBufValueParser,
+    )]
+    pub source_path: PathBuf,
+
+    /// Path of the database to export to. Existing data in this database is removed.
+    #[clap(
+        value_name = "EXPORT_DATABASE_PATH",
+        value_hint = ValueHint::FilePath,
+    )]
+    pub export_database: PathBuf,
+
+    #[clap(long, short = 'v')]
+    pub verbose: bool,
+}
+
+impl Export {
+    pub fn run(self, db_path: &Path) -> anyhow::Result<()> {
+        let mut db = SQLiteWriter::open(db_path)?;
+        let source_path = self.source_path.canonicalize()?;
+        let count = db.export_file_or_directory(&source_path, &self.export_database)?;
+        if self.verbose {
+            println!(
+                "exported {} files to {}",
+                count,
+                self.export_database.display()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Import databases created by `database export` into the database. Files that are missing from
+/// the database, or that were indexed with different content, are imported.
+#[derive(Parser)]
+pub struct Import {
+    /// Paths of the exported databases to import.
+    #[clap(
+        value_name = "EXPORT_DATABASE_PATH",
+        required = true,
+        value_hint = ValueHint::FilePath,
+        value_parser = ExistingPathBufValueParser,
+    )]
+    pub databases: Vec<PathBuf>,
+
+    #[clap(long, short = 'v')]
+    pub verbose: bool,
+}
+
+impl Import {
+    pub fn run(self, db_path: &Path) -> anyhow::Result<()> {
+        let mut db = SQLiteWriter::open(db_path)?;
+        for database in &self.databases {
+            let count = db.merge_from(database)?;
+            if self.verbose {
+                println!("imported {} files from {}", count, database.display());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Merge other indexing databases into the database. Files that are missing from the database, or
+/// that were indexed with different content, are imported from the other databases.
+#[derive(Parser)]
+pub struct Merge {
+    /// Paths of the databases to merge.
+    #[clap(
+        value_name = "OTHER_DATABASE_PATH",
+        required = true,
+        value_hint = ValueHint::FilePath,
+        value_parser = ExistingPathBufValueParser,
+    )]
+    pub databases: Vec<PathBuf>,
+
+    #[clap(long, short = 'v')]
+    pub verbose: bool,
+}
+
+impl Merge {
+    pub fn run(self, db_path: &Path) -> anyhow::Result<()> {
+        let mut db = SQLiteWriter::open(db_path)?;
+        for database in &self.databases {
+            let count = db.merge_from(database)?;
+            if self.verbose {
+                println!("merged {} files from {}", count, database.display());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Remove the indexing data of files that no longer exist, or that changed since they were
+/// indexed, from the database.  Builtins are never removed.
+#[derive(Parser)]
+pub struct Prune {
+    #[clap(long, short = 'v')]
+    pub verbose: bool,
+}
+
+impl Prune {
+    pub fn run(self, db_path: &Path) -> anyhow::Result<()> {
+        let mut db = SQLiteWriter::open(db_path)?;
+        let count = db.prune_files(|entry| {
+            // builtins are stored with an empty tag, and have no source file
+            if entry.tag.is_empty() {
+                return false;
+            }
+            let is_stale = match std::fs::read_to_string(&entry.path) {
+                Ok(source) => sha1(&source) != entry.tag,
+                Err(_) => true,
+            };
+            if is_stale && self.verbose {
+                println!("pruned {}", entry.path.display());
+            }
+            is_stale
+        })?;
+        if self.verbose {
+            println!("pruned {} files", count);
+        }
+        Ok(())
+    }
+}
+
+/// List the files with the most data in the database, with the number of nodes, edges, and
+/// partial paths stored for them.  Files with large graphs make the database bigger, and files
+/// with many partial paths make queries that load them slower.
+#[derive(Parser)]
+pub struct Usage {
+    /// Source file or directory paths. All files in the database are listed if none are given.
+    #[clap(
+        value_name = "SOURCE_PATH",
+        value_hint = ValueHint::AnyPath,
+        value_parser = ExistingPathBufValueParser,
+    )]
+    pub source_paths: Vec<PathBuf>,
+
+    /// Number of files that are listed, or 0 to list all files.
+    #[clap(long, value_name = "N", default_value_t = 20)]
+    pub top: usize,
+
+    /// Order in which files are listed, from largest to smallest.
+    #[clap(long, value_enum, default_value_t = UsageOrder::Size)]
+    pub sort: UsageOrder,
+
+    /// Format of the report.
+    #[clap(long, value_enum, default_value_t = ReportFormat::Text)]
+    pub format: ReportFormat,
+}
+
+/// Order of the files listed by `database usage`.
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum UsageOrder {
+    /// Order by the total size of the stored graph, partial paths, and source.
+    Size,
+    /// Order by the number of stored partial paths.
+    Paths,
+    /// Order by the number of nodes in the stored graph.
+    Nodes,
+}
+
+impl UsageOrder {
+    fn key(self, summary: &FileSummary) -> usize {
+        match self {
+            Self::Size => summary.total_size(),
+            Self::Paths => summary.node_path_count + summary.root_path_count,
+            Self::Nodes => summary.node_count,
+        }
+    }
+}
+
+impl Usage {
+    pub fn run(self, db_path: &Path) -> anyhow::Result<()> {
+        let mut db = SQLiteReader::open(db_path)?;
+        let mut files = Vec::new();
+        if self.source_paths.is_empty() {
+            for entry in db.list_all()?.try_iter()? {
+                files.push(entry?.path);
+            }
+        } else {
+            for source_path in &self.source_paths {
+                let source_path = source_path.canonicalize()?;
+                for entry in db.list_file_or_directory(&source_path)?.try_iter()? {
+                    files.push(entry?.path);
+                }
+            }
+        }
+        let mut summaries = Vec::new();
+        for file in &files {
+            if let Some(summary) = db.file_summary(&file.to_string_lossy())? {
+                summaries.push(summary);
+            }
+        }
+        let file_count = summaries.len();
+        let total_size = summaries.iter().map(FileSummary::total_size).sum::<usize>();
+        summaries.sort_by(|a, b| {
+            self.sort
+                .key(b)
+                .cmp(&self.sort.key(a))
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        if self.top > 0 {
+            summaries.truncate(self.top);
+        }
+
+        match self.format {
+            ReportFormat::Text => {
+                print_usage(&summaries);
+                println!("total: {} files, {} bytes", file_count, total_size);
+            }
+            ReportFormat::Json => {
+                let report = json!({
+                    "files": summaries.iter().map(summary_to_json).collect::<Vec<_>>(),
+                    "summary": { "files": file_count, "size": total_size },
+                });
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn print_usage(summaries: &[FileSummary]) {
+    let header = [
+        "Size",
+        "Graph",
+        "Paths",
+        "Nodes",
+        "Edges",
+        "Node paths",
+        "Root paths",
+        "Shared by",
+    ];
+    let rows = summaries
+        .iter()
+        .map(|s| {
+            [
+                s.total_size(),
+                s.graph_size,
+                s.node_paths_size + s.root_paths_size,
+                s.node_count,
+                s.edge_count,
+                s.node_path_count,
+                s.root_path_count,
+                s.content_file_count,
+            ]
+            .map(|value| value.to_string())
+        })
+        .collect::<Vec<_>>();
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    for (cell, width) in header.iter().zip(widths) {
+        print!("{:>w$}  ", cell, w = width);
+    }
+    println!("File");
+    for (row, summary) in rows.iter().zip(summaries) {
+        for (cell, width) in row.iter().zip(widths) {
+            print!("{:>w$}  ", cell, w = width);
+        }
+        println!("{}", summary.path.display());
+    }
+}
+
+fn summary_to_json(summary: &FileSummary) -> serde_json::Value {
+    json!({
+        "path": summary.path,
+        "tag": summary.tag,
+        "indexed_at": summary
+            .indexed_at
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs()),
+        "size": summary.total_size(),
+        "nodes": summary.node_count,
+        "edges": summary.edge_count,
+        "node_paths": summary.node_path_count,
+        "root_paths": summary.root_path_count,
+        "graph_size": summary.graph_size,
+        "node_paths_size": summary.node_paths_size,
+        "root_paths_size": summary.root_paths_size,
+        "source_size": summary.source_size,
+        "content_files": summary.content_file_count,
+    })
+}
+
+/// Remove data that does not belong to any file from the database, and shrink the database file.
+#[derive(Parser)]
+pub struct Vacuum {
+    #[clap(long, short = 'v')]
+    pub verbose: bool,
+}
+
+impl Vacuum {
+    pub fn run(self, db_path: &Path) -> anyhow::Result<()> {
+        let size_before = std::fs::metadata(db_path).map(|m| m.len()).unwrap_or(0);
+        SQLiteWriter::open(db_path)?.vacuum()?;
+        if self.verbose {
+            let size_after = std::fs::metadata(db_path)?.len();
+            println!(
+                "vacuumed {} from {} to {} bytes",
+                db_path.display(),
+                size_before,
+                size_after
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Check that the data in the database is consistent and can be decoded.  Problems are listed,
+/// and the command fails if any are found.
+#[derive(Parser)]
+pub struct Verify {
+    #[clap(long, short = 'v')]
+    pub verbose: bool,
+}
+
+impl Verify {
+    pub fn run(self, db_path: &Path) -> anyhow::Result<()> {
+        let db = SQLiteReader::open(db_path)?;
+        let problems = db.verify(&NoCancellation)?;
+        for problem in &problems {
+            println!("{}", problem);
+        }
+        if !problems.is_empty() {
+            return Err(anyhow!(
+                "found {} problems in {}",
+                problems.len(),
+                db_path.display()
+            ));
+        }
+        if self.verbose {
+            println!("verified {}", db_path.display());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,626 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use clap::Args;
+use clap::Parser;
+use clap::Subcommand;
+use clap::ValueEnum;
+use clap::ValueHint;
+use serde::Serialize;
+use stack_graphs::storage::SQLiteReader;
+#[cfg(any(feature = "parquet", feature = "scip"))]
+use std::fs::File;
+#[cfg(feature = "scip")]
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::cli::query::InfoResult;
+use crate::cli::query::Querier;
+use crate::cli::util::indexed_files;
+use crate::cli::util::output_writer;
+use crate::cli::util::reporter::ConsoleReporter;
+use crate::cli::util::SourceSpan;
+use crate::lsif::LsifExporter;
+use crate::NoCancellation;
+
+#[derive(Args)]
+pub struct ExportArgs {
+    #[clap(subcommand)]
+    target: ExportTarget,
+}
+
+impl ExportArgs {
+    pub fn run(self, db_path: &Path) -> anyhow::Result<()> {
+        match self.target {
+            ExportTarget::Xrefs(cmd) => cmd.run(db_path),
+            ExportTarget::Lsif(cmd) => cmd.run(db_path),
+            #[cfg(feature = "scip")]
+            ExportTarget::Scip(cmd) => cmd.run(db_path),
+            ExportTarget::Tags(cmd) => cmd.run(db_path),
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum ExportTarget {
+    Xrefs(Xrefs),
+    Lsif(Lsif),
+    #[cfg(feature = "scip")]
+    Scip(Scip),
+    Tags(Tags),
+}
+
+/// Export a table of the references in the database and the definitions they resolve to.
+#[derive(Parser)]
+pub struct Xrefs {
+    /// Source file or directory paths.  If none are given, all indexed files are exported.
+    #[clap(value_name = "SOURCE_PATH", value_hint = ValueHint::AnyPath)]
+    pub source_paths: Vec<PathBuf>,
+
+    /// Format of the table.  Each row contains a reference and one of its definitions.  References
+    /// without definitions have a row with empty definition columns.  Positions are one-based.
+    #[clap(long, value_enum, default_value_t = XrefsFormat::Csv)]
+    pub format: XrefsFormat,
+
+    /// File to write the table to.  The table is written to standard output if no file is given.
+    #[clap(long, short = 'o', value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub output: Option<PathBuf>,
+}
+
+/// Format of cross-reference tables.
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum XrefsFormat {
+    Csv,
+    Jsonl,
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+impl Xrefs {
+    pub fn run(self, db_path: &Path) -> anyhow::Result<()> {
+        let mut table: Box<dyn XrefsTable> = match self.format {
+            XrefsFormat::Csv => Box::new(csv::Writer::from_writer(output_writer(&self.output)?)),
+            XrefsFormat::Jsonl => Box::new(output_writer(&self.output)?),
+            #[cfg(feature = "parquet")]
+            XrefsFormat::Parquet => match &self.output {
+                Some(path) => Box::new(parquet::ParquetTable::new(File::create(path)?)),
+                None => {
+                    return Err(anyhow::anyhow!(
+                        "Parquet tables can only be written to a file"
+                    ))
+                }
+            },
+        };
+
+        let mut db = SQLiteReader::open(db_path)?;
+        let files = indexed_files(&mut db, &self.source_paths)?;
+        // keep standard output clean if the table is written to it
+        let reporter = match self.output {
+            Some(_) => ConsoleReporter::details(),
+            None => ConsoleReporter::none(),
+        };
+        let mut querier = Querier::new(&mut db, &reporter);
+        for file in files {
+            for result in querier.file_definition_info(&file, &NoCancellation)? {
+                for row in XrefRow::from_result(result) {
+                    table.write(row)?;
+                }
+            }
+        }
+        table.finish()
+    }
+}
+
+/// Export the references in the database and the definitions they resolve to as an LSIF dump.
+#[derive(Parser)]
+pub struct Lsif {
+    /// Source file or directory paths.  If none are given, all indexed files are exported.
+    #[clap(value_name = "SOURCE_PATH", value_hint = ValueHint::AnyPath)]
+    pub source_paths: Vec<PathBuf>,
+
+    /// Root directory of the project.  Defaults to the current directory.
+    #[clap(long, value_name = "DIR", value_hint = ValueHint::DirPath)]
+    pub project_root: Option<PathBuf>,
+
+    /// File to write the dump to.  The dump is written to standard output if no file is given.
+    #[clap(long, short = 'o', value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub output: Option<PathBuf>,
+}
+
+impl Lsif {
+    pub fn run(self, db_path: &Path) -> anyhow::Result<()> {
+        let project_root = project_root(&self.project_root)?;
+        let mut exporter = LsifExporter::new(&project_root);
+
+        let mut db = SQLiteReader::open(db_path)?;
+        let files = indexed_files(&mut db, &self.source_paths)?;
+        // keep standard output clean if the dump is written to it
+        let reporter = match self.output {
+            Some(_) => ConsoleReporter::details(),
+            None => ConsoleReporter::none(),
+        };
+        let mut querier = Querier::new(&mut db, &reporter);
+        for file in files {
+            for result in querier.file_definitions(&file, &NoCancellation)? {
+                exporter.add_reference(
+                    &result.source.path,
+                    &result.source.span,
+                    result
+                        .targets
+                        .iter()
+                        .map(|target| (target.span.path.as_path(), &target.span.span)),
+                );
+            }
+        }
+        exporter.write(output_writer(&self.output)?)?;
+        Ok(())
+    }
+}
+
+/// Export the references in the database and the definitions they resolve to as a SCIP index.
+#[cfg(feature = "scip")]
+#[derive(Parser)]
+pub struct Scip {
+    /// Source file or directory paths.  If none are given, all indexed files are exported.
+    #[clap(value_name = "SOURCE_PATH", value_hint = ValueHint::AnyPath)]
+    pub source_paths: Vec<PathBuf>,
+
+    /// Root directory of the project.  Defaults to the current directory.  Files outside of the
+    /// project root are not included in the index.
+    #[clap(long, value_name = "DIR", value_hint = ValueHint::DirPath)]
+    pub project_root: Option<PathBuf>,
+
+    /// File to write the index to.
+    #[clap(
+        long,
+        short = 'o',
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        default_value = "index.scip"
+    )]
+    pub output: PathBuf,
+}
+
+#[cfg(feature = "scip")]
+impl Scip {
+    pub fn run(self, db_path: &Path) -> anyhow::Result<()> {
+        use crate::scip::ScipDefinition;
+        use crate::scip::ScipExporter;
+
+        let project_root = project_root(&self.project_root)?;
+        let mut exporter = ScipExporter::new(&project_root);
+
+        let mut db = SQLiteReader::open(db_path)?;
+        let files = indexed_files(&mut db, &self.source_paths)?;
+        let reporter = ConsoleReporter::details();
+        let mut querier = Querier::new(&mut db, &reporter);
+        for file in files {
+            for result in querier.file_definition_info(&file, &NoCancellation)? {
+                exporter.add_reference(
+                    &result.source.path,
+                    &result.source.span,
+                    result.targets.iter().map(|target| ScipDefinition {
+                        path: &target.target.span.path,
+                        span: &target.target.span.span,
+                        display_name: result.symbol.as_deref(),
+                        qualified_name: &target.qualified_name_components,
+                        syntax_type: target.syntax_type.as_deref(),
+                    }),
+                );
+            }
+        }
+        exporter.write(BufWriter::new(File::create(&self.output)?))?;
+        Ok(())
+    }
+}
+
+/// Export a tags file of the definitions in the database, in the format of universal-ctags.
+#[derive(Parser)]
+pub struct Tags {
+    /// Source file or directory paths.  If none are given, all indexed files are exported.
+    #[clap(value_name = "SOURCE_PATH", value_hint = ValueHint::AnyPath)]
+    pub source_paths: Vec<PathBuf>,
+
+    /// File to write the tags to.  The tags are written to standard output if no file is given.
+    /// File names in the tags are relative to the directory of the tags file, or to the current
+    /// directory if the tags are written to standard output.
+    #[clap(long, short = 'o', value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub output: Option<PathBuf>,
+}
+
+impl Tags {
+    pub fn run(self, db_path: &Path) -> anyhow::Result<()> {
+        let current_dir = std::env::current_dir()?;
+        let base_dir = match &self.output {
+            Some(output) => current_dir
+                .join(output)
+                .parent()
+                .map_or(current_dir.clone(), Path::to_path_buf),
+            None => current_dir,
+        };
+
+        let mut db = SQLiteReader::open(db_path)?;
+        let files = indexed_files(&mut db, &self.source_paths)?;
+        let mut tags = Vec::new();
+        for file in files {
+            let file_name = file.to_string_lossy();
+            let source = match std::fs::read_to_string(&file) {
+                Ok(source) => Some(source),
+                Err(_) => db.source_for_file(&file_name)?,
+            };
+            let tag_path = pathdiff::diff_paths(&file, &base_dir).unwrap_or_else(|| file.clone());
+            db.load_graph_for_file(&file_name)?;
+            let (graph, _, _) = db.get();
+            let handle = match graph.get_file(&file_name) {
+                Some(handle) => handle,
+                None => continue,
+            };
+            for node in graph.nodes_for_file(handle) {
+                if !graph[node].is_definition() {
+                    continue;
+                }
+                let (symbol, source_info) = match (graph[node].symbol(), graph.source_info(node)) {
+                    (Some(symbol), Some(source_info)) => (symbol, source_info),
+                    _ => continue,
+                };
+                let start = &source_info.span.start;
+                tags.push(Tag {
+                    name: graph[symbol].to_string(),
+                    path: tag_path.to_string_lossy().to_string(),
+                    line: start.line + 1,
+                    pattern: source
+                        .as_ref()
+                        .and_then(|source| source.get(start.containing_line.clone()))
+                        .map(|line| line.trim_end_matches('\r').to_string()),
+                    kind: source_info
+                        .syntax_type
+                        .into_option()
+                        .map(|syntax_type| graph[syntax_type].to_string()),
+                });
+            }
+        }
+        tags.sort();
+        tags.dedup();
+
+        let mut writer = output_writer(&self.output)?;
+        writeln!(
+            writer,
+            "!_TAG_FILE_FORMAT\t2\t/extended format; --format=1 will not append ;\" to lines/"
+        )?;
+        writeln!(
+            writer,
+            "!_TAG_FILE_SORTED\t1\t/0=unsorted, 1=sorted, 2=foldcase/"
+        )?;
+        writeln!(writer, "!_TAG_PROGRAM_NAME\t{}\t//", env!("CARGO_PKG_NAME"))?;
+        writeln!(
+            writer,
+            "!_TAG_PROGRAM_VERSION\t{}\t//",
+            env!("CARGO_PKG_VERSION")
+        )?;
+        for tag in tags {
+            tag.write(&mut writer)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// An entry of a tags file.  Entries are ordered by name, as required for sorted tags files.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct Tag {
+    name: String,
+    path: String,
+    line: usize,
+    /// The source line of the definition, if the source is available.
+    pattern: Option<String>,
+    kind: Option<String>,
+}
+
+impl Tag {
+    fn write(&self, writer: &mut dyn Write) -> std::io::Result<()> {
+        write!(
+            writer,
+            "{}\t{}\t",
+            Self::sanitize(&self.name),
+            Self::sanitize(&self.path)
+        )?;
+        // definitions are found by their source line, or by line number if the source is not
+        // available
+        match &self.pattern {
+            Some(pattern) => write!(writer, "/^{}$/;\"", Self::escape_pattern(pattern))?,
+            None => write!(writer, "{};\"", self.line)?,
+        }
+        if let Some(kind) = &self.kind {
+            write!(writer, "\tkind:{}", Self::escape_field_value(kind))?;
+        }
+        writeln!(writer, "\tline:{}", self.line)
+    }
+
+    /// Replaces the tabs and line breaks in a tag name or file name, which cannot be escaped, by
+    /// spaces, because they would separate the fields and entries of the tags file.
+    fn sanitize(value: &str) -> String {
+        value.replace(['\t', '\r', '\n'], " ")
+    }
+
+    /// Escapes a source line for use in an anchored search pattern.  Backslashes and the slashes
+    /// that delimit the pattern are escaped, as are a `^` at the start and a `$` at the end of the
+    /// line, which would otherwise be read as anchors.  Tags are searched for with `nomagic`, so no
+    /// other characters are special.
+    fn escape_pattern(line: &str) -> String {
+        let mut pattern = line.replace('\\', "\\\\").replace('/', "\\/");
+        if pattern.starts_with('^') {
+            pattern.insert(0, '\\');
+        }
+        if pattern.ends_with('$') {
+            pattern.insert(pattern.len() - 1, '\\');
+        }
+        pattern
+    }
+
+    /// Escapes the value of an extension field, as described by the extended tags file format.
+    fn escape_field_value(value: &str) -> String {
+        value
+            .replace('\\', "\\\\")
+            .replace('\t', "\\t")
+            .replace('\r', "\\r")
+            .replace('\n', "\\n")
+    }
+}
+
+fn project_root(project_root: &Option<PathBuf>) -> anyhow::Result<PathBuf> {
+    Ok(match project_root {
+        Some(project_root) => project_root.canonicalize()?,
+        None => std::env::current_dir()?,
+    })
+}
+
+/// A row of the cross-reference table.
+#[derive(Serialize)]
+struct XrefRow {
+    reference_path: String,
+    reference_start_line: usize,
+    reference_start_column: usize,
+    reference_end_line: usize,
+    reference_end_column: usize,
+    definition_path: Option<String>,
+    definition_start_line: Option<usize>,
+    definition_start_column: Option<usize>,
+    definition_end_line: Option<usize>,
+    definition_end_column: Option<usize>,
+    symbol: Option<String>,
+    qualified_name: Option<String>,
+}
+
+impl XrefRow {
+    fn from_result(result: InfoResult) -> Vec<XrefRow> {
+        let reference = Position::from_span(&result.source);
+        let row = |definition: Option<Position>, qualified_name: Option<String>| XrefRow {
+            reference_path: reference.path.clone(),
+            reference_start_line: reference.start_line,
+            reference_start_column: reference.start_column,
+            reference_end_line: reference.end_line,
+            reference_end_column: reference.end_column,
+            definition_path: definition.as_ref().map(|d| d.path.clone()),
+            definition_start_line: definition.as_ref().map(|d| d.start_line),
+            definition_start_column: definition.as_ref().map(|d| d.start_column),
+            definition_end_line: definition.as_ref().map(|d| d.end_line),
+            definition_end_column: definition.as_ref().map(|d| d.end_column),
+            symbol: result.symbol.clone(),
+            qualified_name,
+        };
+        if result.targets.is_empty() {
+            return vec![row(None, None)];
+        }
+        result
+            .targets
+            .iter()
+            .map(|target| {
+                row(
+                    Some(Position::from_span(&target.target.span)),
+                    target.qualified_name.clone(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// The one-based position of a span.
+struct Position {
+    path: String,
+    start_line: usize,
+    start_column: usize,
+    end_line: usize,
+    end_column: usize,
+}
+
+impl Position {
+    fn from_span(span: &SourceSpan) -> Self {
+        Self {
+            path: span.path.to_string_lossy().to_string(),
+            start_line: span.span.start.line + 1,
+            start_column: span.span.start.column.grapheme_offset + 1,
+            end_line: span.span.end.line + 1,
+            end_column: span.span.end.column.grapheme_offset + 1,
+        }
+    }
+}
+
+/// A cross-reference table in one of the supported formats.
+trait XrefsTable {
+    fn write(&mut self, row: XrefRow) -> anyhow::Result<()>;
+
+    /// Writes any buffered rows.  Must be called after the last row was written.
+    fn finish(self: Box<Self>) -> anyhow::Result<()>;
+}
+
+impl<W: Write> XrefsTable for csv::Writer<W> {
+    fn write(&mut self, row: XrefRow) -> anyhow::Result<()> {
+        Ok(self.serialize(row)?)
+    }
+
+    fn finish(mut self: Box<Self>) -> anyhow::Result<()> {
+        Ok(self.flush()?)
+    }
+}
+
+impl XrefsTable for Box<dyn Write> {
+    fn write(&mut self, row: XrefRow) -> anyhow::Result<()> {
+        serde_json::to_writer(&mut *self, &row)?;
+        Ok(writeln!(self)?)
+    }
+
+    fn finish(mut self: Box<Self>) -> anyhow::Result<()> {
+        Ok(self.flush()?)
+    }
+}
+
+#[cfg(feature = "parquet")]
+mod parquet {
+    use ::parquet::data_type::ByteArray;
+    use ::parquet::data_type::ByteArrayType;
+    use ::parquet::data_type::DataType;
+    use ::parquet::data_type::Int64Type;
+    use ::parquet::file::properties::WriterProperties;
+    use ::parquet::file::writer::SerializedColumnWriter;
+    use ::parquet::file::writer::SerializedFileWriter;
+    use ::parquet::schema::parser::parse_message_type;
+    use std::fs::File;
+    use std::sync::Arc;
+
+    use super::XrefRow;
+    use super::XrefsTable;
+
+    const SCHEMA: &str = "
+        message xrefs {
+            REQUIRED BYTE_ARRAY reference_path (UTF8);
+            REQUIRED INT64 reference_start_line;
+            REQUIRED INT64 reference_start_column;
+            REQUIRED INT64 reference_end_line;
+            REQUIRED INT64 reference_end_column;
+            OPTIONAL BYTE_ARRAY definition_path (UTF8);
+            OPTIONAL INT64 definition_start_line;
+            OPTIONAL INT64 definition_start_column;
+            OPTIONAL INT64 definition_end_line;
+            OPTIONAL INT64 definition_end_column;
+            OPTIONAL BYTE_ARRAY symbol (UTF8);
+            OPTIONAL BYTE_ARRAY qualified_name (UTF8);
+        }
+    ";
+
+    /// Buffers the rows of the table, and writes them as a single row group when finished.
+    pub(super) struct ParquetTable {
+        file: File,
+        rows: Vec<XrefRow>,
+    }
+
+    impl ParquetTable {
+        pub(super) fn new(file: File) -> Self {
+            Self {
+                file,
+                rows: Vec::new(),
+            }
+        }
+    }
+
+    impl XrefsTable for ParquetTable {
+        fn write(&mut self, row: XrefRow) -> anyhow::Result<()> {
+            self.rows.push(row);
+            Ok(())
+        }
+
+        fn finish(self: Box<Self>) -> anyhow::Result<()> {
+            let schema = Arc::new(parse_message_type(SCHEMA)?);
+            let properties = Arc::new(WriterProperties::builder().build());
+            let mut writer = SerializedFileWriter::new(self.file, schema, properties)?;
+            let mut row_group = writer.next_row_group()?;
+            let rows = &self.rows;
+            let text = |f: fn(&XrefRow) -> Option<&str>| -> Vec<Option<ByteArray>> {
+                rows.iter().map(|r| f(r).map(ByteArray::from)).collect()
+            };
+            let integer = |f: fn(&XrefRow) -> Option<usize>| -> Vec<Option<i64>> {
+                rows.iter().map(|r| f(r).map(|v| v as i64)).collect()
+            };
+            let mut column_index = 0;
+            while let Some(mut column) = row_group.next_column()? {
+                match column_index {
+                    0 => write_column::<ByteArrayType>(
+                        &mut column,
+                        text(|r| Some(&r.reference_path)),
+                    )?,
+                    1 => write_column::<Int64Type>(
+                        &mut column,
+                        integer(|r| Some(r.reference_start_line)),
+                    )?,
+                    2 => write_column::<Int64Type>(
+                        &mut column,
+                        integer(|r| Some(r.reference_start_column)),
+                    )?,
+                    3 => write_column::<Int64Type>(
+                        &mut column,
+                        integer(|r| Some(r.reference_end_line)),
+                    )?,
+                    4 => write_column::<Int64Type>(
+                        &mut column,
+                        integer(|r| Some(r.reference_end_column)),
+                    )?,
+                    5 => write_column::<ByteArrayType>(
+                        &mut column,
+                        text(|r| r.definition_path.as_deref()),
+                    )?,
+                    6 => write_column::<Int64Type>(
+                        &mut column,
+                        integer(|r| r.definition_start_line),
+                    )?,
+                    7 => write_column::<Int64Type>(
+                        &mut column,
+                        integer(|r| r.definition_start_column),
+                    )?,
+                    8 => {
+                        write_column::<Int64Type>(&mut column, integer(|r| r.definition_end_line))?
+                    }
+                    9 => write_column::<Int64Type>(
+                        &mut column,
+                        integer(|r| r.definition_end_column),
+                    )?,
+                    10 => {
+                        write_column::<ByteArrayType>(&mut column, text(|r| r.symbol.as_deref()))?
+                    }
+                    11 => write_column::<ByteArrayType>(
+                        &mut column,
+                        text(|r| r.qualified_name.as_deref()),
+                    )?,
+                    _ => unreachable!("schema has 12 columns"),
+                }
+                column.close()?;
+                column_index += 1;
+            }
+            row_group.close()?;
+            writer.close()?;
+            Ok(())
+        }
+    }
+
+    fn write_column<T: DataType>(
+        column: &mut SerializedColumnWriter,
+        values: Vec<Option<T::T>>,
+    ) -> anyhow::Result<()> {
+        let writer = column.typed::<T>();
+        let def_levels = values
+            .iter()
+            .map(|value| value.is_some() as i16)
+            .collect::<Vec<_>>();
+        let values = values.into_iter().flatten().collect::<Vec<_>>();
+        if writer.get_descriptor().max_def_level() > 0 {
+            writer.write_batch(&values, Some(&def_levels), None)?;
+        } else {
+            writer.write_batch(&values, None, None)?;
+        }
+        Ok(())
+    }
+}
@@ -6,24 +6,40 @@
 // ------------------------------------------------------------------------------------------------
 
 use clap::Args;
+use clap::ValueEnum;
 use clap::ValueHint;
+use serde_json::json;
 use stack_graphs::arena::Handle;
 use stack_graphs::graph::File;
 use stack_graphs::graph::StackGraph;
 use stack_graphs::partial::PartialPaths;
+use stack_graphs::stats::FrequencyDistribution;
+use stack_graphs::stitching::Database;
 use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::storage::FileError;
+use stack_graphs::storage::FileErrorSpan;
 use stack_graphs::storage::FileStatus;
 use stack_graphs::storage::SQLiteWriter;
+use stack_graphs::storage::SQLiteWriterOptions;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::io::BufWriter;
+use std::io::Read;
 use std::path::Path;
 use std::path::PathBuf;
 use std::time::Duration;
+use std::time::Instant;
 use thiserror::Error;
 use tree_sitter_graph::Variables;
 
 use crate::cli::util::duration_from_seconds_str;
-use crate::cli::util::iter_files_and_directories;
+use crate::cli::util::iter_filtered_files;
+use crate::cli::util::iter_filtered_files_and_directories;
+use crate::cli::util::reporter::with_progress;
 use crate::cli::util::reporter::ConsoleReporter;
+use crate::cli::util::reporter::FileInfo;
+use crate::cli::util::reporter::JsonLinesReporter;
 use crate::cli::util::reporter::Level;
 use crate::cli::util::reporter::Reporter;
 use crate::cli::util::sha1;
@@ -31,8 +47,12 @@ use crate::cli::util::wait_for_input;
 use crate::cli::util::BuildErrorWithSource;
 use crate::cli::util::CLIFileReporter;
 use crate::cli::util::ExistingPathBufValueParser;
+use crate::cli::util::PathFilter;
+use crate::cli::util::PathGlob;
 use crate::loader::FileLanguageConfigurations;
 use crate::loader::FileReader;
+use crate::loader::LanguageConfiguration;
+use crate::loader::LoadError;
 use crate::loader::Loader;
 use crate::BuildError;
 use crate::CancelAfterDuration;
@@ -40,16 +60,49 @@ use crate::CancellationFlag;
 use crate::NoCancellation;
 
 #[derive(Args)]
+#[clap(after_help = r#"GLOBALS:
+    Globals for a source file are read from .sg-globals.json files in the
+    directories from the source path argument down to the file, and from a
+    FILE.sg-globals.json file next to the file. The lint and lsp commands read
+    the same files. The test command does not, because test files declare their
+    globals inline with "--- global: NAME=VALUE ---" lines. Other commands do not
+    build stack graphs from source files, and are not affected by globals.
+"#)]
 pub struct IndexArgs {
     /// Source file or directory paths to index.
     #[clap(
         value_name = "SOURCE_PATH",
-        required = true,
+        required_unless_present_any = ["files_from", "affected_by"],
         value_hint = ValueHint::AnyPath,
         value_parser = ExistingPathBufValueParser,
     )]
     pub source_paths: Vec<PathBuf>,
 
+    /// Index the files listed in the given file, or in standard input if the file is `-`, for
+    /// example the output of `git ls-files`. Files are listed one per line, relative to the
+    /// current directory, and are indexed after the source paths.
+    #[clap(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub files_from: Option<PathBuf>,
+
+    /// Files listed by --files-from are separated by NUL characters instead of newlines, for
+    /// example the output of `git ls-files -z`.
+    #[clap(long, short = '0', requires = "files_from")]
+    pub null: bool,
+
+    /// Index the given changed files, and the files in the database with references that may
+    /// resolve to definitions in the changed files, before or after the change. Such files are
+    /// found through the symbols that their partial paths look up at the root node. They are
+    /// indexed again even if they did not change. Changed files that no longer exist are removed
+    /// from the database.
+    #[clap(
+        long,
+        value_name = "FILE",
+        num_args = 1..,
+        conflicts_with_all = ["source_paths", "files_from", "continue_from"],
+        value_hint = ValueHint::FilePath,
+    )]
+    pub affected_by: Vec<PathBuf>,
+
     /// Continue indexing from the given file.
     #[clap(
         long,
@@ -59,6 +112,22 @@ pub struct IndexArgs {
     )]
     pub continue_from: Option<PathBuf>,
 
+    /// Only index files that match the given glob. Globs are matched against paths relative to
+    /// the source path argument. Globs without a `/` match any path component, `*` does not match
+    /// `/`, and `**` does. Can be given multiple times.
+    #[clap(long = "include", value_name = "GLOB")]
+    pub include: Vec<PathGlob>,
+
+    /// Do not index files that match the given glob, such as generated code or vendored
+    /// directories. Globs are interpreted as for --include. Can be given multiple times.
+    #[clap(long = "exclude", value_name = "GLOB")]
+    pub exclude: Vec<PathGlob>,
+
+    /// Do not index files that are ignored by .gitignore or .ignore files, or are in .git
+    /// directories. Files given as source paths are always indexed.
+    #[clap(long)]
+    pub respect_gitignore: bool,
+
     #[clap(long, short = 'v')]
     pub verbose: bool,
 
@@ -70,6 +139,25 @@ pub struct IndexArgs {
     #[clap(long)]
     pub hide_error_details: bool,
 
+    /// Format of the progress output.  With `jsonl`, one JSON object is printed per line when a
+    /// file is started, and when it succeeded, failed, was canceled, or was skipped.  Results
+    /// include the elapsed time, the number of nodes and paths of indexed files, and the kind of
+    /// error of failed files.
+    #[clap(long, value_enum, default_value_t = ProgressFormat::Text)]
+    pub progress_format: ProgressFormat,
+
+    /// Print statistics after indexing: the distributions of the number of nodes, edges, and
+    /// partial paths per indexed file, of the time spent building stack graphs and computing
+    /// partial paths, and of the memory used by the stack graph, partial paths, and path database
+    /// of each file.
+    #[clap(long)]
+    pub stats: bool,
+
+    /// Write the statistics of every indexed file, and their distributions, to the given file as
+    /// JSON.
+    #[clap(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub stats_json: Option<PathBuf>,
+
     /// Maximum runtime per file in seconds.
     #[clap(
         long,
@@ -81,18 +169,56 @@ pub struct IndexArgs {
     /// Wait for user input before starting analysis. Useful for profiling.
     #[clap(long)]
     pub wait_at_start: bool,
+
+    /// Index the default builtins of the languages of indexed files.
+    #[clap(long)]
+    pub index_builtins: bool,
+
+    /// Index the named builtins layer of the languages of indexed files. Implies --index-builtins.
+    #[clap(long = "builtins-layer", value_name = "NAME")]
+    pub builtins_layers: Vec<String>,
+
+    /// Number of files to commit to the database together. Larger batches speed up indexing, but
+    /// up to this many files are lost if indexing is interrupted.
+    #[clap(long, value_name = "FILES", default_value_t = 1)]
+    pub batch_size: usize,
+
+    /// Compress stored graphs and paths with the given zstd compression level. Higher levels
+    /// result in smaller databases, but slow down indexing.
+    #[clap(long, value_name = "LEVEL", value_parser = clap::value_parser!(i32).range(1..=22))]
+    pub compression_level: Option<i32>,
+
+    /// Store the compressed source text of indexed files in the database, so that queries can
+    /// show excerpts when the files are not available, for example when the database is copied to
+    /// another machine.
+    #[clap(long)]
+    pub store_sources: bool,
 }
 
 impl IndexArgs {
     pub fn new(source_paths: Vec<PathBuf>) -> Self {
         Self {
             source_paths,
+            files_from: None,
+            null: false,
+            affected_by: Vec::new(),
             force: false,
             continue_from: None,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            respect_gitignore: false,
             verbose: false,
             hide_error_details: false,
+            progress_format: ProgressFormat::Text,
+            stats: false,
+            stats_json: None,
             max_file_time: None,
             wait_at_start: false,
+            index_builtins: false,
+            builtins_layers: Vec::new(),
+            batch_size: 1,
+            compression_level: None,
+            store_sources: false,
         }
     }
 
@@ -100,18 +226,87 @@ impl IndexArgs {
         if self.wait_at_start {
             wait_for_input()?;
         }
-        let mut db = SQLiteWriter::open(&db_path)?;
-        let reporter = self.get_reporter();
-        let mut indexer = Indexer::new(&mut db, &mut loader, &reporter);
-        indexer.force = self.force;
-        indexer.max_file_time = self.max_file_time;
-
+        let mut db = SQLiteWriter::open_with_options(
+            &db_path,
+            SQLiteWriterOptions {
+                batch_size: self.batch_size,
+                compression_level: self.compression_level,
+                ..SQLiteWriterOptions::default()
+            },
+        )?;
         let source_paths = self
             .source_paths
-            .into_iter()
+            .iter()
             .map(|p| p.canonicalize())
             .collect::<std::result::Result<Vec<_>, _>>()?;
-        indexer.index_all(source_paths, self.continue_from, &NoCancellation)?;
+        let filter = PathFilter {
+            include: self.include.clone(),
+            exclude: self.exclude.clone(),
+            respect_gitignore: self.respect_gitignore,
+        };
+        let current_dir = std::env::current_dir()?.canonicalize()?;
+        let listed_files = match &self.files_from {
+            Some(files_from) => read_file_list(files_from, self.null)?
+                .into_iter()
+                .map(|path| {
+                    current_dir.join(&path).canonicalize().map_err(|e| {
+                        anyhow::anyhow!("{}: {}: {}", files_from.display(), path.display(), e)
+                    })
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?,
+            None => Vec::new(),
+        };
+        let listed_files =
+            iter_filtered_files(&current_dir, listed_files, filter.clone()).collect::<Vec<_>>();
+        let reporter: Box<dyn Reporter> = match self.progress_format {
+            // the number of affected files is not known in advance
+            ProgressFormat::Text if !self.affected_by.is_empty() => Box::new(self.get_reporter()),
+            ProgressFormat::Text => with_progress(self.get_reporter(), || {
+                iter_filtered_files_and_directories(&source_paths, filter.clone()).count()
+                    + listed_files.len()
+            }),
+            ProgressFormat::Jsonl => Box::new(JsonLinesReporter::new()),
+        };
+        let mut indexer = Indexer::new(&mut db, &mut loader, reporter.as_ref());
+        indexer.force = self.force;
+        indexer.max_file_time = self.max_file_time;
+        indexer.store_sources = self.store_sources;
+        indexer.filter = filter;
+        if self.index_builtins || !self.builtins_layers.is_empty() {
+            indexer.builtins_layers = Some(self.builtins_layers);
+        }
+        if self.stats || self.stats_json.is_some() {
+            indexer.stats = Some(IndexingStats::default());
+        }
+
+        if self.affected_by.is_empty() {
+            let source_files =
+                iter_filtered_files_and_directories(&source_paths, indexer.filter.clone());
+            indexer.index_entries(
+                source_files.chain(listed_files),
+                self.continue_from,
+                &NoCancellation,
+            )?;
+        } else {
+            let changed_files = self
+                .affected_by
+                .iter()
+                .map(|path| canonicalize_file(&current_dir.join(path)))
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            indexer.index_affected_by(&current_dir, changed_files, &NoCancellation)?;
+        }
+        let stats = indexer.stats.take();
+        db.flush()?;
+
+        if let Some(stats) = stats {
+            if self.stats {
+                stats.print();
+            }
+            if let Some(stats_json) = &self.stats_json {
+                let file = BufWriter::new(std::fs::File::create(stats_json)?);
+                serde_json::to_writer_pretty(file, &stats.to_json())?;
+            }
+        }
         Ok(())
     }
 
@@ -141,6 +336,27 @@ impl IndexArgs {
     }
 }
 
+/// Returns the canonical path of a file.  If the file does not exist anymore, only its parent
+/// directory is canonicalized.
+fn canonicalize_file(path: &Path) -> std::io::Result<PathBuf> {
+    if path.exists() {
+        return path.canonicalize();
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => Ok(parent.canonicalize()?.join(name)),
+        _ => Ok(path.to_path_buf()),
+    }
+}
+
+/// Format of the progress output of the index command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ProgressFormat {
+    /// Human-readable status lines, with a progress bar if standard output is a terminal.
+    Text,
+    /// One JSON object per line for every file event.
+    Jsonl,
+}
+
 pub struct Indexer<'a> {
     db: &'a mut SQLiteWriter,
     loader: &'a mut Loader,
@@ -149,6 +365,16 @@ pub struct Indexer<'a> {
     pub force: bool,
     /// Maximum time per file.
     pub max_file_time: Option<Duration>,
+    /// Index the builtins of the languages of indexed files, including the given builtins layers.
+    /// Builtins are not indexed if `None`.
+    pub builtins_layers: Option<Vec<String>>,
+    /// Store the source text of indexed files in the database.
+    pub store_sources: bool,
+    /// Only index files that are selected by this filter.
+    pub filter: PathFilter,
+    /// Statistics of the indexed files.  Statistics are only collected if this is `Some`.
+    pub stats: Option<IndexingStats>,
+    indexed_builtins: HashSet<String>,
 }
 
 impl<'a> Indexer<'a> {
@@ -163,13 +389,18 @@ impl<'a> Indexer<'a> {
             reporter,
             force: false,
             max_file_time: None,
+            builtins_layers: None,
+            store_sources: false,
+            filter: PathFilter::default(),
+            stats: None,
+            indexed_builtins: HashSet::new(),
         }
     }
 
     pub fn index_all<P, IP, Q>(
         &mut self,
         source_paths: IP,
-        mut continue_from: Option<Q>,
+        continue_from: Option<Q>,
         cancellation_flag: &dyn CancellationFlag,
     ) -> Result<()>
     where
@@ -177,7 +408,64 @@ impl<'a> Indexer<'a> {
         IP: IntoIterator<Item = P>,
         Q: AsRef<Path>,
     {
-        for (source_root, source_path, strict) in iter_files_and_directories(source_paths) {
+        let entries = iter_filtered_files_and_directories(source_paths, self.filter.clone());
+        self.index_entries(entries, continue_from, cancellation_flag)
+    }
+
+    /// Index the given changed files, and the files that may be affected by the changes, because
+    /// their partial paths look up symbols at the root node that the changed files define there,
+    /// before or after the change.  Affected files are always indexed, and are given relative to
+    /// the source root.  Changed files that do not exist anymore are removed from the database.
+    pub fn index_affected_by(
+        &mut self,
+        source_root: &Path,
+        changed_files: Vec<PathBuf>,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<()> {
+        let mut affected_files = BTreeSet::new();
+        for changed_file in &changed_files {
+            affected_files.extend(self.db.files_affected_by(&changed_file.to_string_lossy())?);
+        }
+        let mut existing_files = Vec::new();
+        for changed_file in changed_files {
+            if changed_file.exists() {
+                existing_files.push(changed_file);
+            } else {
+                self.db.clean_file(&changed_file)?;
+            }
+        }
+        let entries = iter_filtered_files(source_root, &existing_files, self.filter.clone());
+        self.index_entries(entries, None::<&Path>, cancellation_flag)?;
+        for changed_file in &existing_files {
+            affected_files.extend(self.db.files_affected_by(&changed_file.to_string_lossy())?);
+        }
+
+        // unchanged files that are affected are indexed again, and files that were removed
+        // since they were indexed are skipped
+        let affected_files = affected_files
+            .into_iter()
+            .filter(|path| !existing_files.contains(path) && path.exists())
+            .map(|path| (source_root.to_path_buf(), path, false))
+            .collect::<Vec<_>>();
+        let force = std::mem::replace(&mut self.force, true);
+        let result = self.index_entries(affected_files, None::<&Path>, cancellation_flag);
+        self.force = force;
+        result
+    }
+
+    /// Index the given files, which are given as triples of source root, source path, and whether
+    /// the file was given explicitly, as returned by `iter_files_and_directories`.
+    fn index_entries<IE, Q>(
+        &mut self,
+        entries: IE,
+        mut continue_from: Option<Q>,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<()>
+    where
+        IE: IntoIterator<Item = (PathBuf, PathBuf, bool)>,
+        Q: AsRef<Path>,
+    {
+        for (source_root, source_path, strict) in entries {
             let mut file_status = CLIFileReporter::new(self.reporter, &source_path);
             cancellation_flag.check("indexing all files")?;
             self.index_file(
@@ -237,9 +525,12 @@ impl<'a> Indexer<'a> {
                 file_status.assert_reported();
                 ok
             }
-            err @ Err(_) => {
+            Err(err) => {
+                if file_status.is_processing() {
+                    file_status.info(FileInfo::error(err.kind()));
+                }
                 file_status.failure_if_processing("error", Some(&format!("Error analyzing file {}. To continue analysis from this file later, add: --continue-from {}", source_path.display(), source_path.display())));
-                err
+                Err(err)
             }
         }
     }
@@ -268,18 +559,30 @@ impl<'a> Indexer<'a> {
         {
             Ok(lcs) if !lcs.has_some() => {
                 if missing_is_error {
+                    file_status.info(FileInfo::error("unsupported"));
                     file_status.failure("not supported", None);
                 }
                 return Ok(());
             }
             Ok(lcs) => lcs,
-            Err(crate::loader::LoadError::Cancelled(_)) => {
+            Err(LoadError::Cancelled(_)) => {
+                file_status.info(FileInfo::error("timeout"));
                 file_status.warning("language loading timed out", None);
                 return Ok(());
             }
             Err(e) => return Err(IndexError::LoadError(e)),
         };
 
+        if let (Some(lc), Some(builtins_layers)) = (lcs.primary, &self.builtins_layers) {
+            Self::index_builtins(
+                self.db,
+                &mut self.indexed_builtins,
+                lc,
+                builtins_layers,
+                cancellation_flag,
+            )?;
+        }
+
         let source = file_reader.get(source_path)?;
         let tag = sha1(source);
 
@@ -306,6 +609,14 @@ impl<'a> Indexer<'a> {
             }
         };
 
+        let mut globals = Variables::new();
+        if let Err(err) = Loader::load_globals_from_sidecars(source_root, source_path, &mut globals)
+        {
+            file_status.info(FileInfo::error("globals"));
+            file_status.failure("failed to load globals", Some(&err.to_string()));
+            return Err(IndexError::LoadError(err));
+        }
+
         let file_cancellation_flag = CancelAfterDuration::from_option(self.max_file_time);
         let cancellation_flag = cancellation_flag | file_cancellation_flag.as_ref();
 
@@ -316,39 +627,60 @@ impl<'a> Indexer<'a> {
             .add_file(&source_path.to_string_lossy())
             .expect("file not present in empty graph");
 
+        let build_start = Instant::now();
         let result = Self::build_stack_graph(
             &mut graph,
             file,
-            source_root,
-            source_path,
-            &source,
+            SourceFile {
+                source_root,
+                source_path,
+                source: &source,
+                globals: &globals,
+            },
             lcs,
             &cancellation_flag,
         );
         if let Err(err) = result {
             match err.inner {
                 BuildError::Cancelled(_) => {
+                    file_status.info(FileInfo::error("timeout"));
                     file_status.warning("parsing timed out", None);
-                    self.db
-                        .store_error_for_file(source_path, &tag, "parsing timed out")?;
-                    return Ok(());
-                }
-                BuildError::ParseErrors { .. } => {
-                    file_status.failure("parsing failed", Some(&err.display_pretty()));
-                    self.db.store_error_for_file(
+                    self.db.store_file_error_for_file(
                         source_path,
                         &tag,
-                        &format!("parsing failed: {}", err.inner),
+                        &FileError::new("timeout", "parsing timed out"),
                     )?;
                     return Ok(());
                 }
+                BuildError::ParseErrors(ref parse_errors) => {
+                    file_status.info(FileInfo::error("parse"));
+                    file_status.failure("parsing failed", Some(&err.display_pretty()));
+                    let mut error =
+                        FileError::new("parse", format!("parsing failed: {}", err.inner));
+                    if let Some(parse_error) = parse_errors.errors().first() {
+                        let node = parse_error.node();
+                        error = error.with_span(FileErrorSpan {
+                            start_line: node.start_position().row,
+                            start_column: node.start_position().column,
+                            end_line: node.end_position().row,
+                            end_column: node.end_position().column,
+                        });
+                    }
+                    self.db
+                        .store_file_error_for_file(source_path, &tag, &error)?;
+                    return Ok(());
+                }
                 _ => {
+                    file_status.info(FileInfo::error("build"));
                     file_status.failure("failed to build stack graph", Some(&err.display_pretty()));
                     return Err(IndexError::StackGraph);
                 }
             }
         };
 
+        let build_time = build_start.elapsed();
+
+        let stitch_start = Instant::now();
         let mut partials = PartialPaths::new();
         let mut paths = Vec::new();
         match ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
@@ -362,38 +694,73 @@ impl<'a> Indexer<'a> {
         ) {
             Ok(_) => {}
             Err(_) => {
+                file_status.info(FileInfo::error("timeout"));
                 file_status.warning("path computation timed out", None);
-                self.db.store_error_for_file(
+                self.db.store_file_error_for_file(
                     source_path,
                     &tag,
-                    &format!("path computation timed out"),
+                    &FileError::new("timeout", "path computation timed out"),
                 )?;
                 return Ok(());
             }
         }
+        let stitch_time = stitch_start.elapsed();
 
         self.db
             .store_result_for_file(&graph, file, &tag, &mut partials, &paths)?;
+        if self.store_sources {
+            self.db.store_source_for_file(source_path, source)?;
+        }
 
+        let node_count = graph.nodes_for_file(file).count();
+        if let Some(stats) = &mut self.stats {
+            // the database is only built to measure the memory that querying the paths would use
+            let mut db = Database::new();
+            for path in &paths {
+                db.add_partial_path(&graph, &mut partials, path.clone());
+            }
+            stats.record(FileStats {
+                path: source_path.to_path_buf(),
+                nodes: node_count,
+                edges: graph
+                    .iter_nodes()
+                    .map(|node| graph.outgoing_edges(node).count())
+                    .sum(),
+                paths: paths.len(),
+                build_time,
+                stitch_time,
+                graph_bytes: graph.memory_usage().total(),
+                partials_bytes: partials.memory_usage().total(),
+                database_bytes: db.memory_usage().total(),
+            });
+        }
+        file_status.info(FileInfo {
+            node_count: Some(node_count),
+            path_count: Some(paths.len()),
+            error_kind: None,
+        });
         file_status.success(success_status, None);
 
         Ok(())
     }
 
-    fn build_stack_graph<'b>(
+    pub(crate) fn build_stack_graph<'b>(
         graph: &mut StackGraph,
         file: Handle<File>,
-        source_root: &Path,
-        source_path: &Path,
-        source: &'b str,
+        source_file: SourceFile<'_, 'b>,
         lcs: FileLanguageConfigurations<'b>,
         cancellation_flag: &dyn CancellationFlag,
     ) -> std::result::Result<(), BuildErrorWithSource<'b>> {
+        let SourceFile {
+            source_root,
+            source_path,
+            source,
+            globals,
+        } = source_file;
         let relative_source_path = source_path.strip_prefix(source_root).unwrap();
         if let Some(lc) = lcs.primary {
-            let globals = Variables::new();
             lc.sgl
-                .build_stack_graph_into(graph, file, source, &globals, cancellation_flag)
+                .build_stack_graph_into(graph, file, source, globals, cancellation_flag)
                 .map_err(|inner| BuildErrorWithSource {
                     inner,
                     source_path: source_path.to_path_buf(),
@@ -420,6 +787,73 @@ impl<'a> Indexer<'a> {
                 tsg_str: "",
             })?;
         }
+        for producer in lcs.producers {
+            producer
+                .build_stack_graph_into(
+                    graph,
+                    file,
+                    relative_source_path,
+                    source,
+                    globals,
+                    cancellation_flag,
+                )
+                .map_err(|inner| BuildErrorWithSource {
+                    inner,
+                    source_path: source_path.to_path_buf(),
+                    source_str: source,
+                    tsg_path: PathBuf::new(),
+                    tsg_str: "",
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Index the builtins of the given language, unless they were indexed before by this indexer.
+    /// Different languages may use the same file names for their builtins, so builtins files are
+    /// stored under a name that includes the language and builtins layer, see
+    /// [`builtins_file_name`].
+    fn index_builtins(
+        db: &mut SQLiteWriter,
+        indexed_builtins: &mut HashSet<String>,
+        lc: &LanguageConfiguration,
+        builtins_layers: &[String],
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<()> {
+        let mut layers = vec![(None, &lc.builtins)];
+        for layer in builtins_layers {
+            match lc.builtins_layers.get(layer) {
+                Some(builtins) => layers.push((Some(layer.as_str()), builtins)),
+                None => {
+                    return Err(IndexError::LoadError(LoadError::UnknownBuiltinsLayer(
+                        layer.clone(),
+                    )))
+                }
+            }
+        }
+        for (layer, builtins) in layers {
+            let mut graph = StackGraph::new();
+            graph
+                .add_from_graph_renamed(builtins, |file| builtins_file_name(lc, layer, file))
+                .expect("builtins are added to an empty graph");
+            for file in graph.iter_files() {
+                if !indexed_builtins.insert(graph[file].name().to_string()) {
+                    continue;
+                }
+                let mut partials = PartialPaths::new();
+                let mut paths = Vec::new();
+                ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+                    &graph,
+                    &mut partials,
+                    file,
+                    &cancellation_flag,
+                    |_g, _ps, p| {
+                        paths.push(p.clone());
+                    },
+                )
+                .map_err(|err| IndexError::Cancelled(err.0))?;
+                db.store_result_for_file(&graph, file, "", &mut partials, &paths)?;
+            }
+        }
         Ok(())
     }
 
@@ -440,12 +874,177 @@ impl<'a> Indexer<'a> {
     }
 }
 
+/// Returns the name under which a builtins file of the given language is stored in the database.
+/// The name consists of the language scope, or its file types if it has no scope, the builtins
+/// layer, which is empty for the default builtins, and the name of the file, separated by colons.
+fn builtins_file_name(lc: &LanguageConfiguration, layer: Option<&str>, file: &str) -> String {
+    let language = match &lc.scope {
+        Some(scope) => scope.clone(),
+        None => lc.file_types.join(","),
+    };
+    format!("{}:{}:{}", language, layer.unwrap_or(""), file)
+}
+
+/// A source file to build a stack graph for.
+pub(crate) struct SourceFile<'a, 'b> {
+    /// The root directory that the source path is relative to.
+    pub source_root: &'a Path,
+    pub source_path: &'a Path,
+    pub source: &'b str,
+    /// The globals for the file, such as the ones read from sidecar files.
+    pub globals: &'a Variables<'a>,
+}
+
+/// Statistics of an indexed file.
+#[derive(Clone, Debug)]
+pub struct FileStats {
+    pub path: PathBuf,
+    /// Number of nodes in the stack graph of the file.
+    pub nodes: usize,
+    /// Number of edges in the stack graph of the file.
+    pub edges: usize,
+    /// Number of partial paths in the minimal set of partial paths of the file.
+    pub paths: usize,
+    /// Time spent building the stack graph of the file.
+    pub build_time: Duration,
+    /// Time spent computing the partial paths of the file.
+    pub stitch_time: Duration,
+    /// Approximate number of bytes used by the stack graph of the file.
+    pub graph_bytes: usize,
+    /// Approximate number of bytes used by the partial paths of the file.
+    pub partials_bytes: usize,
+    /// Approximate number of bytes used by a path database containing the partial paths of the
+    /// file.
+    pub database_bytes: usize,
+}
+
+/// Statistics of all files indexed by an [`Indexer`][], and their distributions.  Times are
+/// recorded in microseconds.
+#[derive(Clone, Debug, Default)]
+pub struct IndexingStats {
+    pub files: Vec<FileStats>,
+    pub nodes: FrequencyDistribution<usize>,
+    pub edges: FrequencyDistribution<usize>,
+    pub paths: FrequencyDistribution<usize>,
+    pub build_time: FrequencyDistribution<u128>,
+    pub stitch_time: FrequencyDistribution<u128>,
+    pub graph_bytes: FrequencyDistribution<usize>,
+    pub partials_bytes: FrequencyDistribution<usize>,
+    pub database_bytes: FrequencyDistribution<usize>,
+}
+
+impl IndexingStats {
+    const QUANTILES: [(&'static str, f64); 5] = [
+        ("min", 0.0),
+        ("p50", 0.5),
+        ("p90", 0.9),
+        ("p99", 0.99),
+        ("max", 1.0),
+    ];
+
+    pub fn record(&mut self, file: FileStats) {
+        self.nodes.record(file.nodes);
+        self.edges.record(file.edges);
+        self.paths.record(file.paths);
+        self.build_time.record(file.build_time.as_micros());
+        self.stitch_time.record(file.stitch_time.as_micros());
+        self.graph_bytes.record(file.graph_bytes);
+        self.partials_bytes.record(file.partials_bytes);
+        self.database_bytes.record(file.database_bytes);
+        self.files.push(file);
+    }
+
+    fn times(&self) -> [(&'static str, &FrequencyDistribution<u128>); 2] {
+        [
+            ("build_ms", &self.build_time),
+            ("stitch_ms", &self.stitch_time),
+        ]
+    }
+
+    fn counts(&self) -> [(&'static str, &FrequencyDistribution<usize>); 6] {
+        [
+            ("nodes", &self.nodes),
+            ("edges", &self.edges),
+            ("paths", &self.paths),
+            ("graph_bytes", &self.graph_bytes),
+            ("partials_bytes", &self.partials_bytes),
+            ("database_bytes", &self.database_bytes),
+        ]
+    }
+
+    fn print(&self) {
+        println!("indexing statistics ({} files)", self.files.len());
+        print!("{:<20}{:>12}", "", "total");
+        for (name, _) in Self::QUANTILES {
+            print!("{:>10}", name);
+        }
+        println!();
+        let quantiles = Self::QUANTILES.map(|(_, q)| q);
+        for (name, dist) in self.counts() {
+            print!(
+                "  {:<18}{:>12}",
+                name,
+                dist.iter().map(|(v, c)| v * c).sum::<usize>()
+            );
+            for value in dist.quantiles(&quantiles) {
+                print!("{:>10}", value);
+            }
+            println!();
+        }
+        for (name, dist) in self.times() {
+            let total = dist.iter().map(|(v, c)| v * c as u128).sum::<u128>();
+            print!("  {:<18}{:>12.1}", name, total as f64 / 1000.0);
+            for value in dist.quantiles(&quantiles) {
+                print!("{:>10.1}", *value as f64 / 1000.0);
+            }
+            println!();
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let quantiles = Self::QUANTILES.map(|(_, q)| q);
+        let mut summary = serde_json::Map::new();
+        summary.insert("files".into(), json!(self.files.len()));
+        for (name, dist) in self.counts() {
+            let mut value = json!({
+                "total": dist.iter().map(|(v, c)| v * c).sum::<usize>(),
+            });
+            for ((q_name, _), q_value) in Self::QUANTILES.iter().zip(dist.quantiles(&quantiles)) {
+                value[q_name] = json!(q_value);
+            }
+            summary.insert(name.into(), value);
+        }
+        for (name, dist) in self.times() {
+            let total = dist.iter().map(|(v, c)| v * c as u128).sum::<u128>();
+            let mut value = json!({ "total": total as f64 / 1000.0 });
+            for ((q_name, _), q_value) in Self::QUANTILES.iter().zip(dist.quantiles(&quantiles)) {
+                value[q_name] = json!(*q_value as f64 / 1000.0);
+            }
+            summary.insert(name.into(), value);
+        }
+        json!({
+            "files": self.files.iter().map(|file| json!({
+                "path": file.path.to_string_lossy(),
+                "nodes": file.nodes,
+                "edges": file.edges,
+                "paths": file.paths,
+                "build_ms": file.build_time.as_micros() as f64 / 1000.0,
+                "stitch_ms": file.stitch_time.as_micros() as f64 / 1000.0,
+                "graph_bytes": file.graph_bytes,
+                "partials_bytes": file.partials_bytes,
+                "database_bytes": file.database_bytes,
+            })).collect::<Vec<_>>(),
+            "summary": summary,
+        })
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum IndexError {
     #[error("cancelled at {0}")]
     Cancelled(&'static str),
     #[error("failed to load language")]
-    LoadError(#[source] crate::loader::LoadError<'static>),
+    LoadError(#[source] LoadError<'static>),
     #[error("failed to read file")]
     ReadError(#[from] std::io::Error),
     #[error("failed to build stank graph")]
@@ -454,6 +1053,19 @@ pub enum IndexError {
     StorageError(#[from] stack_graphs::storage::StorageError),
 }
 
+impl IndexError {
+    /// Returns the kind of error, as reported to machine-readable reporters.
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Cancelled(_) => "cancelled",
+            Self::LoadError(_) => "load",
+            Self::ReadError(_) => "read",
+            Self::StackGraph => "build",
+            Self::StorageError(_) => "storage",
+        }
+    }
+}
+
 impl From<crate::CancellationError> for IndexError {
     fn from(value: crate::CancellationError) -> Self {
         Self::Cancelled(value.0)
@@ -461,3 +1073,32 @@ impl From<crate::CancellationError> for IndexError {
 }
 
 type Result<T> = std::result::Result<T, IndexError>;
+
+/// Reads a list of files from the given file, or from standard input if the file is `-`.  Files are
+/// separated by newlines, or by NUL characters if `null` is set.  Empty entries are skipped.
+fn read_file_list(files_from: &Path, null: bool) -> anyhow::Result<Vec<PathBuf>> {
+    let content = if files_from == Path::new("-") {
+        let mut content = Vec::new();
+        std::io::stdin().lock().read_to_end(&mut content)?;
+        content
+    } else {
+        std::fs::read(files_from)?
+    };
+    let separator = if null { b'\0' } else { b'\n' };
+    content
+        .split(|b| *b == separator)
+        .map(|entry| {
+            if null {
+                entry
+            } else {
+                entry.strip_suffix(b"\r").unwrap_or(entry)
+            }
+        })
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            std::str::from_utf8(entry).map(PathBuf::from).map_err(|_| {
+                anyhow::anyhow!("{}: file path is not valid UTF-8", files_from.display())
+            })
+        })
+        .collect()
+}
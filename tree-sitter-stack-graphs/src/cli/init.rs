@@ -14,6 +14,7 @@ use clap::error::ErrorKind;
 use clap::Args;
 use clap::ValueHint;
 use dialoguer::Input;
+use dialoguer::MultiSelect;
 use dialoguer::Select;
 use dialoguer::Validator;
 use indoc::printdoc;
@@ -27,11 +28,21 @@ use std::path::Path;
 use std::path::PathBuf;
 use time::OffsetDateTime;
 
+use self::grammar::Grammar;
+use self::grammar::GrammarSource;
 use self::license::*;
 
+pub mod grammar;
 mod license;
 
 const TSSG_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// The version of the stack-graphs dependency of generated binding packages. This must be
+/// compatible with the version that tree-sitter-stack-graphs depends on.
+const STACK_GRAPHS_VERSION: &str = "0.12";
+const PYO3_VERSION: &str = "0.21";
+const NAPI_VERSION: &str = "2.16";
+
+const DEFAULT_COMMENT_PREFIX: &str = "//";
 
 static VALID_CRATE_NAME: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"^[a-zA-Z_-][a-zA-Z0-9_-]*$").unwrap());
@@ -51,20 +62,22 @@ pub struct InitArgs {
     )]
     pub project_path: PathBuf,
 
-    /// Disable console interaction. All input values must be provided through the appropriate options.
+    /// Disable console interaction. All input values must be provided through the appropriate options,
+    /// or be detected from the grammar given by --from-grammar. The language name, identifier, and
+    /// file extension, and the grammar crate version are required.
+    #[clap(long)]
+    pub non_interactive: bool,
+
+    /// Generate the project for an existing Tree-sitter grammar, given by the path of the grammar
+    /// directory, the name of an npm package in node_modules, or the name of a crate in the local
+    /// Cargo registry. The language and grammar dependency settings are detected from the grammar,
+    /// and the generated TSG lists the node kinds of the grammar.
     #[clap(
         long,
-        requires("language_name"),
-        requires("language_id"),
-        requires("language_file_extension"),
-        // crate_name is optional
-        // crate_version is optional
-        // author is optional
-        // license is optional
-        // grammar_crate_name
-        requires("grammar_crate_version")
+        value_name = "NAME_OR_PATH",
+        conflicts_with("grammar_crate_name")
     )]
-    pub non_interactive: bool,
+    pub from_grammar: Option<String>,
 
     /// Name of the target language.
     #[clap(long)]
@@ -102,6 +115,22 @@ pub struct InitArgs {
     #[clap(long, value_parser = RegexValidator(&VALID_DEPENDENCY_VERSION))]
     pub grammar_crate_version: Option<String>,
 
+    /// Generate a package with Python bindings in the python directory, which is built with maturin.
+    #[clap(long)]
+    pub python_bindings: bool,
+
+    /// Generate a package with Node.js bindings in the node directory, which is built with napi-rs.
+    #[clap(long)]
+    pub node_bindings: bool,
+
+    /// Generate example tests with assertions, and an example builtins source.
+    #[clap(long)]
+    pub example_tests: bool,
+
+    /// Line comment prefix of the target language, used for the example tests. Default: //
+    #[clap(long, requires("example_tests"))]
+    pub comment_prefix: Option<String>,
+
     /// Generate a project that is meant to be part of the official stack-graphs repository.
     /// Instead of the project path, the repository root must be specified. The project path,
     /// license, and dependencies will follow the repository conventions.
@@ -137,8 +166,19 @@ impl InitArgs {
             license,
             grammar_crate_name: self.grammar_crate_name,
             grammar_crate_version: self.grammar_crate_version.unwrap_or_default(),
+            python_bindings: self.python_bindings,
+            node_bindings: self.node_bindings,
+            example_tests: self.example_tests,
+            comment_prefix: self.comment_prefix,
+            grammar: None,
             internal: self.internal,
         };
+        if let Some(name_or_path) = &self.from_grammar {
+            config.set_grammar(Grammar::find(name_or_path)?);
+        }
+        if self.non_interactive {
+            config.check_complete()?;
+        }
         if !self.non_interactive && !Self::interactive(&self.project_path, &mut config)? {
             return Ok(());
         }
@@ -352,6 +392,57 @@ impl InitArgs {
             }
         };
 
+        if let Some(grammar) = &config.grammar {
+            printdoc! {r#"
+
+                Using the Tree-sitter grammar {} from {}.
+                "#,
+                grammar.crate_name,
+                grammar.path.display(),
+            };
+        } else {
+            Self::read_grammar_from_console(config)?;
+        }
+
+        printdoc! {r#"
+
+            Select additional packages and files to generate. Binding packages make the
+            definitions available from Python, using maturin, or from Node.js, using napi-rs.
+            Example tests show how to write test assertions. Press SPACE to toggle a selection.
+            "#
+        };
+        let selected = MultiSelect::new()
+            .with_prompt("Additional files")
+            .items_checked(&[
+                ("Python bindings", config.python_bindings),
+                ("Node.js bindings", config.node_bindings),
+                ("Example tests", config.example_tests),
+            ])
+            .interact()?;
+        config.python_bindings = selected.contains(&0);
+        config.node_bindings = selected.contains(&1);
+        config.example_tests = selected.contains(&2);
+
+        if config.example_tests {
+            printdoc! {r#"
+
+                Give the line comment prefix for {}. Assertions in the example tests are
+                written in line comments.
+                "#,
+                config.language_name,
+            };
+            config.comment_prefix = Some(
+                Input::new()
+                    .with_prompt("Line comment prefix")
+                    .with_initial_text(config.comment_prefix())
+                    .interact_text()?,
+            );
+        }
+
+        Ok(())
+    }
+
+    fn read_grammar_from_console(config: &mut ProjectSettings) -> anyhow::Result<()> {
         printdoc! {r#"
 
             Give the crate name for the Tree-sitter grammar that is to be used for
@@ -394,6 +485,11 @@ struct ProjectSettings<'a> {
     license: Option<License<'a>>,
     grammar_crate_name: Option<String>,
     grammar_crate_version: String,
+    python_bindings: bool,
+    node_bindings: bool,
+    example_tests: bool,
+    comment_prefix: Option<String>,
+    grammar: Option<Grammar>,
     internal: bool,
 }
 
@@ -426,6 +522,104 @@ impl ProjectSettings<'_> {
         self.grammar_crate_name().replace("-", "_")
     }
 
+    /// Uses the given grammar as the grammar dependency. Language settings that were not given
+    /// are detected from the grammar.
+    fn set_grammar(&mut self, grammar: Grammar) {
+        if self.language_id.is_empty() {
+            self.language_id = grammar
+                .name
+                .clone()
+                .filter(|name| VALID_CRATE_NAME.is_match(name))
+                .unwrap_or_else(|| {
+                    grammar
+                        .crate_name
+                        .trim_start_matches("tree-sitter-")
+                        .to_string()
+                });
+        }
+        if self.language_name.is_empty() {
+            let mut chars = self.language_id.chars();
+            self.language_name = chars
+                .next()
+                .map(|c| c.to_uppercase().chain(chars).collect())
+                .unwrap_or_default();
+        }
+        if self.language_file_extension.is_empty() {
+            self.language_file_extension = grammar
+                .file_types
+                .iter()
+                .find(|file_type| VALID_CRATE_NAME.is_match(file_type))
+                .unwrap_or(&self.language_id)
+                .clone();
+        }
+        self.grammar_crate_name = Some(grammar.crate_name.clone());
+        if grammar.source == GrammarSource::Registry && self.grammar_crate_version.is_empty() {
+            self.grammar_crate_version = grammar.crate_version.clone();
+        }
+        self.grammar = Some(grammar);
+    }
+
+    /// Returns the grammar if it is depended on by path instead of by version.
+    fn grammar_by_path(&self) -> Option<&Grammar> {
+        self.grammar
+            .as_ref()
+            .filter(|grammar| grammar.source != GrammarSource::Registry)
+    }
+
+    /// Checks that all required settings are given, for when they are not read from the console.
+    fn check_complete(&self) -> anyhow::Result<()> {
+        let mut missing = Vec::new();
+        if self.language_name.is_empty() {
+            missing.push("--language-name");
+        }
+        if self.language_id.is_empty() {
+            missing.push("--language-id");
+        }
+        if self.language_file_extension.is_empty() {
+            missing.push("--language-file-extension");
+        }
+        if self.grammar_by_path().is_none() && self.grammar_crate_version.is_empty() {
+            missing.push("--grammar-crate-version");
+        }
+        if !missing.is_empty() {
+            return Err(anyhow!(
+                "Missing required options for --non-interactive: {}",
+                missing.join(", ")
+            ));
+        }
+        Ok(())
+    }
+
+    fn scope(&self) -> String {
+        self.grammar
+            .as_ref()
+            .and_then(|grammar| grammar.scope.clone())
+            .unwrap_or_else(|| format!("source.{}", self.language_file_extension))
+    }
+
+    /// Returns the grammar dependency specification. Grammars that are depended on by path are
+    /// referred to relative to the project directory, which must exist.
+    fn grammar_dependency(&self, project_path: &Path) -> std::io::Result<String> {
+        match self.grammar_by_path() {
+            Some(grammar) => {
+                let project_path = project_path.canonicalize()?;
+                let path = pathdiff::diff_paths(&grammar.path, &project_path)
+                    .unwrap_or_else(|| grammar.path.clone());
+                Ok(format!(
+                    r#"{{ path = "{}" }}"#,
+                    path.to_string_lossy().replace('\\', "/")
+                ))
+            }
+            None => Ok(format!(r#""{}""#, self.grammar_crate_version)),
+        }
+    }
+
+    fn comment_prefix(&self) -> String {
+        self.comment_prefix
+            .clone()
+            .unwrap_or_else(|| DEFAULT_COMMENT_PREFIX.to_string())
+    }
+
     fn license_author(&self) -> String {
         self.author
             .clone()
@@ -449,6 +643,18 @@ impl ProjectSettings<'_> {
         self.generate_builtins_cfg(project_path)?;
         self.generate_test(project_path)?;
         self.generate_gitignore(project_path)?;
+        if self.example_tests {
+            fs::create_dir_all(project_path.join("test/examples"))?;
+            self.generate_example_tests(project_path)?;
+        }
+        if self.python_bindings {
+            fs::create_dir_all(project_path.join("python/src"))?;
+            self.generate_python_bindings(project_path)?;
+        }
+        if self.node_bindings {
+            fs::create_dir_all(project_path.join("node/src"))?;
+            self.generate_node_bindings(project_path)?;
+        }
         Ok(())
     }
 
@@ -551,8 +757,6 @@ impl ProjectSettings<'_> {
             ``` sh
             $ cargo run --features cli -- test -V --output-mode=always TESTFILES...
             ```
-
-            Go to https://crates.io/crates/tree-sitter-stack-graphs for links to examples and documentation.
             "####,
             self.language_name,
             self.language_name, self.grammar_crate_name(),
@@ -564,6 +768,58 @@ impl ProjectSettings<'_> {
             self.crate_name(),
             self.language_file_extension,
         }?;
+        if self.example_tests {
+            writedoc! {file, r####"
+
+                ### Example Tests
+
+                The `test/examples` directory contains example tests that show how to write assertions. The examples end in `.skip`, which excludes them from test runs. Adapt the code in the examples to {} and remove the `.skip` extension once the stack graph rules support definitions and references.
+                "####,
+                self.language_name,
+            }?;
+        }
+        if self.python_bindings {
+            writedoc! {file, r####"
+
+                ### Python Bindings
+
+                The `python` directory contains a Python package with bindings for this project. The package has a `build_graph` function that returns the stack graph of a source file as JSON.
+
+                Build the package and install it into the current Python environment using [maturin][]:
+
+                ``` sh
+                $ cd python
+                $ maturin develop
+                ```
+
+                [maturin]: https://www.maturin.rs/
+                "####,
+            }?;
+        }
+        if self.node_bindings {
+            writedoc! {file, r####"
+
+                ### Node.js Bindings
+
+                The `node` directory contains a Node.js package with bindings for this project. The package has a `buildGraph` function that returns the stack graph of a source file as JSON.
+
+                Build the package using [napi-rs][]:
+
+                ``` sh
+                $ cd node
+                $ npm install
+                $ npm run build
+                ```
+
+                [napi-rs]: https://napi.rs/
+                "####,
+            }?;
+        }
+        writedoc! {file, r####"
+
+            Go to https://crates.io/crates/tree-sitter-stack-graphs for links to examples and documentation.
+            "####,
+        }?;
         Ok(())
     }
 
@@ -632,14 +888,8 @@ impl ProjectSettings<'_> {
         if let Some(license) = &self.license {
             writeln!(file, r#"license = "{}""#, license.0)?;
         }
-        let tssg_dep_fields = if self.internal {
-            format!(
-                r#"version = "{}", path = "../../tree-sitter-stack-graphs""#,
-                TSSG_VERSION
-            )
-        } else {
-            format!(r#"version = "{}""#, TSSG_VERSION)
-        };
+        let tssg_dep_fields =
+            self.repository_dependency_fields("tree-sitter-stack-graphs", TSSG_VERSION, "../..");
         writedoc! {file, r#"
             edition = "2018"
 
@@ -664,7 +914,7 @@ impl ProjectSettings<'_> {
             anyhow = {{ version = "1.0", optional = true }}
             clap = {{ version = "4", optional = true, features = ["derive"] }}
             tree-sitter-stack-graphs = {{ {} }}
-            {} = "{}"
+            {} = {}
 
             [dev-dependencies]
             anyhow = "1.0"
@@ -672,12 +922,25 @@ impl ProjectSettings<'_> {
             "#,
             self.crate_name(),
             tssg_dep_fields,
-            self.grammar_crate_name(), self.grammar_crate_version,
+            self.grammar_crate_name(), self.grammar_dependency(project_path)?,
             tssg_dep_fields,
         }?;
         Ok(())
     }
 
+    /// Returns the fields of a dependency on a crate from the stack-graphs repository. Internal
+    /// projects depend on the crate by path, relative to the repository root at `root_path`.
+    fn repository_dependency_fields(&self, name: &str, version: &str, root_path: &str) -> String {
+        if self.internal {
+            format!(
+                r#"version = "{}", path = "{}/{}""#,
+                version, root_path, name
+            )
+        } else {
+            format!(r#"version = "{}""#, version)
+        }
+    }
+
     fn generate_rust_bin(&self, project_path: &Path) -> anyhow::Result<()> {
         let mut file = File::create(project_path.join("rust/bin.rs"))?;
         self.write_license_header(&mut file, "// ")?;
@@ -747,7 +1010,7 @@ impl ProjectSettings<'_> {
             ) -> Result<LanguageConfiguration, LoadError> {{
                 LanguageConfiguration::from_sources(
                     {}::language(),
-                    Some(String::from("source.{}")),
+                    Some(String::from("{}")),
                     None,
                     vec![String::from("{}")],
                     STACK_GRAPHS_TSG_PATH.into(),
@@ -765,7 +1028,7 @@ impl ProjectSettings<'_> {
             self.language_file_extension,
             self.language_file_extension,
             self.grammar_package_name(),
-            self.language_file_extension,
+            self.scope(),
             self.language_file_extension,
         }?;
         Ok(())
@@ -836,17 +1099,85 @@ impl ProjectSettings<'_> {
             ;; Stack Graph Rules
             ;; ^^^^^^^^^^^^^^^^^
 
-            ; Have fun!
             "#,
             self.language_name,
         }?;
+        if let Some(root) = self.grammar.as_ref().and_then(|g| g.root_node_kind()) {
+            writedoc! {file, r#"
+                ({}) @root {{
+                  node @root.lexical_scope
+                  edge @root.lexical_scope -> ROOT_NODE
+                }}
+
+                "#,
+                root.name,
+            }?;
+        }
+        writeln!(file, "; Have fun!")?;
+        if let Some(grammar) = &self.grammar {
+            self.write_node_kinds(&mut file, grammar)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the named node kinds of the grammar as a reference for writing stanzas.
+    fn write_node_kinds(&self, file: &mut File, grammar: &Grammar) -> anyhow::Result<()> {
+        if grammar.node_kinds.is_empty() {
+            return Ok(());
+        }
+        writedoc! {file, r#"
+
+            ;; Node Kinds
+            ;; ^^^^^^^^^^
+
+            ;; The {} grammar has the following named node kinds. Supertypes match any of
+            ;; their subtypes in queries.
+            ;;
+            "#,
+            grammar.crate_name,
+        }?;
+        let width = grammar
+            .node_kinds
+            .iter()
+            .map(|kind| kind.name.len())
+            .max()
+            .unwrap_or(0);
+        for kind in &grammar.node_kinds {
+            let mut description = Vec::new();
+            if kind.is_root {
+                description.push("root".to_string());
+            }
+            if kind.is_supertype {
+                description.push("supertype".to_string());
+            }
+            if !kind.fields.is_empty() {
+                description.push(format!("fields: {}", kind.fields.join(", ")));
+            }
+            let line = format!(
+                ";;   {:width$}  {}",
+                kind.name,
+                description.join("; "),
+                width = width
+            );
+            writeln!(file, "{}", line.trim_end())?;
+        }
         Ok(())
     }
 
     fn generate_builtins_src(&self, project_path: &Path) -> anyhow::Result<()> {
-        File::create(
+        let mut file = File::create(
             project_path.join("src/builtins.".to_string() + &self.language_file_extension),
         )?;
+        if self.example_tests {
+            let c = self.comment_prefix();
+            writedoc! {file, r#"
+                {c} Builtins for {}. Definitions in this file, such as the definitions of functions
+                {c} that are always available, are loaded for every file.
+                "#,
+                self.language_name,
+                c = c,
+            }?;
+        }
         Ok(())
     }
 
@@ -864,6 +1195,289 @@ impl ProjectSettings<'_> {
         Ok(())
     }
 
+    /// Returns an assertion comment with the carret at the given column, or directly after the
+    /// comment prefix if the prefix does not leave room for that.
+    fn assertion_comment(&self, column: usize, assertion: &str) -> String {
+        let prefix = self.comment_prefix();
+        let padding = column.saturating_sub(prefix.chars().count()).max(1);
+        format!("{}{}^ {}", prefix, " ".repeat(padding), assertion)
+    }
+
+    fn generate_example_tests(&self, project_path: &Path) -> anyhow::Result<()> {
+        let c = self.comment_prefix();
+
+        let mut file = File::create(project_path.join(
+            "test/examples/definitions.".to_string() + &self.language_file_extension + ".skip",
+        ))?;
+        writedoc! {file, r#"
+            {c} Example test with assertions. Adapt the code to {} and remove the .skip
+            {c} extension once the stack graph rules support definitions and references.
+
+            example_value = 42
+            {}
+
+            print(example_value)
+            {}
+            {}
+            "#,
+            self.language_name,
+            self.assertion_comment(4, "defines: example_value"),
+            self.assertion_comment(10, "refers: example_value"),
+            self.assertion_comment(10, "defined: 4"),
+            c = c,
+        }?;
+
+        let mut file = File::create(project_path.join(
+            "test/examples/builtins.".to_string() + &self.language_file_extension + ".skip",
+        ))?;
+        writedoc! {file, r#"
+            {c} Example test for builtins. The builtins in src/builtins.{} are loaded for every
+            {c} test, so that references to builtins resolve without definitions in the test.
+            {c} Definitions outside of the test are ignored by defined assertions.
+
+            print(42)
+            {}
+            "#,
+            self.language_file_extension,
+            self.assertion_comment(2, "refers: print"),
+            c = c,
+        }?;
+
+        Ok(())
+    }
+
+    fn generate_python_bindings(&self, project_path: &Path) -> anyhow::Result<()> {
+        let mut file = File::create(project_path.join("python/Cargo.toml"))?;
+        writedoc! {file, r#"
+            [package]
+            name = "{}-python"
+            version = "{}"
+            description = "Python bindings for the stack graphs definition for {}"
+            edition = "2018"
+            publish = false
+
+            # The bindings are built separately from the project, using maturin.
+            [workspace]
+
+            [lib]
+            crate-type = ["cdylib"]
+            test = false
+
+            [features]
+            # Enabled by maturin when building the Python extension module.
+            extension-module = ["pyo3/extension-module"]
+
+            [dependencies]
+            pyo3 = "{}"
+            serde_json = "1.0"
+            stack-graphs = {{ {}, features = ["serde"] }}
+            tree-sitter-stack-graphs = {{ {} }}
+            {} = {{ path = ".." }}
+            "#,
+            self.crate_name(),
+            self.crate_version(),
+            self.language_name,
+            PYO3_VERSION,
+            self.repository_dependency_fields("stack-graphs", STACK_GRAPHS_VERSION, "../../.."),
+            self.repository_dependency_fields("tree-sitter-stack-graphs", TSSG_VERSION, "../../.."),
+            self.crate_name(),
+        }?;
+
+        let mut file = File::create(project_path.join("python/pyproject.toml"))?;
+        writedoc! {file, r#"
+            [build-system]
+            requires = ["maturin>=1.0,<2.0"]
+            build-backend = "maturin"
+
+            [project]
+            name = "{}"
+            description = "Python bindings for the stack graphs definition for {}"
+            "#,
+            self.crate_name(),
+            self.language_name,
+        }?;
+        if let Some(license) = &self.license {
+            writeln!(file, r#"license = {{ text = "{}" }}"#, license.0)?;
+        }
+        writedoc! {file, r#"
+            requires-python = ">=3.7"
+            dynamic = ["version"]
+
+            [tool.maturin]
+            module-name = "{}"
+            features = ["extension-module"]
+            "#,
+            self.package_name(),
+        }?;
+
+        let mut file = File::create(project_path.join("python/src/lib.rs"))?;
+        self.write_license_header(&mut file, "// ")?;
+        writedoc! {file, r#"
+            use pyo3::exceptions::PyValueError;
+            use pyo3::prelude::*;
+            use stack_graphs::graph::StackGraph;
+            use tree_sitter_stack_graphs::NoCancellation;
+            use tree_sitter_stack_graphs::Variables;
+
+            /// Builds the stack graph of a source file, and returns it as JSON.
+            #[pyfunction]
+            fn build_graph(path: &str, source: &str) -> PyResult<String> {{
+                let lc = {}::try_language_configuration(&NoCancellation).map_err(py_error)?;
+                let mut graph = StackGraph::new();
+                let file = graph.get_or_create_file(path);
+                lc.sgl
+                    .build_stack_graph_into(&mut graph, file, source, &Variables::new(), &NoCancellation)
+                    .map_err(py_error)?;
+                serde_json::to_string(&graph.to_serializable()).map_err(py_error)
+            }}
+
+            fn py_error<E: std::fmt::Display>(err: E) -> PyErr {{
+                PyValueError::new_err(err.to_string())
+            }}
+
+            /// The `{}` Python module.
+            #[pymodule]
+            #[pyo3(name = "{}")]
+            fn python_module(module: &Bound<'_, PyModule>) -> PyResult<()> {{
+                module.add_function(wrap_pyfunction!(build_graph, module)?)?;
+                Ok(())
+            }}
+            "#,
+            self.package_name(),
+            self.package_name(),
+            self.package_name(),
+        }?;
+
+        let mut file = File::create(project_path.join("python/.gitignore"))?;
+        writedoc! {file, r#"
+            /Cargo.lock
+            /target
+            "#,
+        }?;
+
+        Ok(())
+    }
+
+    fn generate_node_bindings(&self, project_path: &Path) -> anyhow::Result<()> {
+        let mut file = File::create(project_path.join("node/Cargo.toml"))?;
+        writedoc! {file, r#"
+            [package]
+            name = "{}-node"
+            version = "{}"
+            description = "Node.js bindings for the stack graphs definition for {}"
+            edition = "2018"
+            publish = false
+
+            # The bindings are built separately from the project, using napi-rs.
+            [workspace]
+
+            [lib]
+            crate-type = ["cdylib"]
+            test = false
+
+            [dependencies]
+            napi = {{ version = "{}", default-features = false, features = ["napi4"] }}
+            napi-derive = "{}"
+            serde_json = "1.0"
+            stack-graphs = {{ {}, features = ["serde"] }}
+            tree-sitter-stack-graphs = {{ {} }}
+            {} = {{ path = ".." }}
+
+            [build-dependencies]
+            napi-build = "2"
+            "#,
+            self.crate_name(),
+            self.crate_version(),
+            self.language_name,
+            NAPI_VERSION,
+            NAPI_VERSION,
+            self.repository_dependency_fields("stack-graphs", STACK_GRAPHS_VERSION, "../../.."),
+            self.repository_dependency_fields("tree-sitter-stack-graphs", TSSG_VERSION, "../../.."),
+            self.crate_name(),
+        }?;
+
+        let mut package = serde_json::json!({
+            "name": self.crate_name(),
+            "version": self.crate_version(),
+            "description": format!("Node.js bindings for the stack graphs definition for {}", self.language_name),
+            "main": "index.js",
+            "types": "index.d.ts",
+            "files": [
+                "index.js",
+                "index.d.ts",
+                format!("{}.*.node", self.package_name()),
+            ],
+            "napi": {
+                "name": self.package_name(),
+            },
+            "scripts": {
+                "build": "napi build --platform --release",
+                "build:debug": "napi build --platform",
+            },
+            "devDependencies": {
+                "@napi-rs/cli": "^2.18.0",
+            },
+        });
+        if let Some(license) = &self.license {
+            package["license"] = license.0.as_ref().into();
+        }
+        let mut file = File::create(project_path.join("node/package.json"))?;
+        serde_json::to_writer_pretty(&mut file, &package)?;
+        writeln!(file)?;
+
+        let mut file = File::create(project_path.join("node/build.rs"))?;
+        self.write_license_header(&mut file, "// ")?;
+        writedoc! {file, r#"
+            fn main() {{
+                napi_build::setup();
+            }}
+            "#,
+        }?;
+
+        let mut file = File::create(project_path.join("node/src/lib.rs"))?;
+        self.write_license_header(&mut file, "// ")?;
+        writedoc! {file, r#"
+            use napi::Error;
+            use napi::Result;
+            use napi_derive::napi;
+            use stack_graphs::graph::StackGraph;
+            use tree_sitter_stack_graphs::NoCancellation;
+            use tree_sitter_stack_graphs::Variables;
+
+            /// Builds the stack graph of a source file, and returns it as JSON.
+            #[napi]
+            pub fn build_graph(path: String, source: String) -> Result<String> {{
+                let lc = {}::try_language_configuration(&NoCancellation).map_err(js_error)?;
+                let mut graph = StackGraph::new();
+                let file = graph.get_or_create_file(&path);
+                lc.sgl
+                    .build_stack_graph_into(&mut graph, file, &source, &Variables::new(), &NoCancellation)
+                    .map_err(js_error)?;
+                serde_json::to_string(&graph.to_serializable()).map_err(js_error)
+            }}
+
+            fn js_error<E: std::fmt::Display>(err: E) -> Error {{
+                Error::from_reason(err.to_string())
+            }}
+            "#,
+            self.package_name(),
+        }?;
+
+        let mut file = File::create(project_path.join("node/.gitignore"))?;
+        writedoc! {file, r#"
+            /Cargo.lock
+            /node_modules
+            /package-lock.json
+            /target
+            *.node
+            index.d.ts
+            index.js
+            "#,
+        }?;
+
+        Ok(())
+    }
+
     fn generate_gitignore(&self, project_path: &Path) -> anyhow::Result<()> {
         let mut file = File::create(project_path.join(".gitignore"))?;
         writedoc! {file, r#"
@@ -888,6 +1502,9 @@ impl std::fmt::Display for ProjectSettings<'_> {
             Project license            : {}
             Grammar dependency name    : {}
             Grammar dependency version : {}
+            Python bindings            : {}
+            Node.js bindings           : {}
+            Example tests              : {}
 
             "##,
             self.language_name,
@@ -898,11 +1515,29 @@ impl std::fmt::Display for ProjectSettings<'_> {
             self.author.clone().unwrap_or_default(),
             self.license.as_ref().map_or("", |l| &l.0),
             self.grammar_crate_name(),
-            self.grammar_crate_version,
+            match self.grammar_by_path() {
+                Some(grammar) => format!("path {}", grammar.path.display()),
+                None => self.grammar_crate_version.clone(),
+            },
+            yes_no(self.python_bindings),
+            yes_no(self.node_bindings),
+            if self.example_tests {
+                format!("yes (comment prefix {})", self.comment_prefix())
+            } else {
+                yes_no(false).to_string()
+            },
         }
     }
 }
 
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
 #[derive(Clone)]
 struct RegexValidator<'a>(&'a Regex);
 
@@ -0,0 +1,303 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Detects existing Tree-sitter grammars that projects can be generated from.
+
+use anyhow::anyhow;
+use anyhow::Context;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// A Tree-sitter grammar, found in a local directory, an npm package, or the local Cargo registry.
+pub struct Grammar {
+    /// The directory containing the grammar.
+    pub path: PathBuf,
+    pub source: GrammarSource,
+    pub crate_name: String,
+    pub crate_version: String,
+    /// The name of the grammar, as defined in `tree-sitter.json` or `src/grammar.json`.
+    pub name: Option<String>,
+    pub scope: Option<String>,
+    pub file_types: Vec<String>,
+    /// The named node kinds of the grammar, as defined in `src/node-types.json`.
+    pub node_kinds: Vec<NodeKind>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GrammarSource {
+    /// A crate in the local Cargo registry, which is depended on by version.
+    Registry,
+    /// A package in a `node_modules` directory, which is depended on by path.
+    Npm,
+    /// A local directory, which is depended on by path.
+    Local,
+}
+
+pub struct NodeKind {
+    pub name: String,
+    pub fields: Vec<String>,
+    pub is_supertype: bool,
+    /// Whether this is the kind of the root node of syntax trees.
+    pub is_root: bool,
+}
+
+impl Grammar {
+    /// Finds the grammar with the given name or path. A path to an existing directory is used as
+    /// is. Otherwise, the name is looked up as an npm package in the `node_modules` directories of
+    /// the current directory and its ancestors, and then as a crate in the local Cargo registry,
+    /// where the latest version is used.
+    pub fn find(name_or_path: &str) -> anyhow::Result<Self> {
+        let current_dir = std::env::current_dir()?;
+        let cargo_home = std::env::var_os("CARGO_HOME")
+            .map(PathBuf::from)
+            .or_else(|| dirs::home_dir().map(|home| home.join(".cargo")));
+        Self::find_in(name_or_path, &current_dir, cargo_home.as_deref())
+    }
+
+    /// Finds the grammar with the given name or path, like [`find`][Self::find], but resolves
+    /// paths and looks up npm packages from the given directory instead of the current directory,
+    /// and looks up crates in the registry of the given Cargo home directory.
+    pub fn find_in(
+        name_or_path: &str,
+        dir: &Path,
+        cargo_home: Option<&Path>,
+    ) -> anyhow::Result<Self> {
+        let path = dir.join(name_or_path);
+        if path.is_dir() {
+            return Self::from_dir(&path, GrammarSource::Local);
+        }
+        if let Some(path) = find_npm_package(name_or_path, dir) {
+            return Self::from_dir(&path, GrammarSource::Npm);
+        }
+        if let Some(path) = cargo_home.and_then(|home| find_registry_crate(name_or_path, home)) {
+            return Self::from_dir(&path, GrammarSource::Registry);
+        }
+        Err(anyhow!(
+            "Cannot find grammar {}. Give the path of a grammar directory, the name of an npm package in node_modules, or the name of a crate in the local Cargo registry.",
+            name_or_path
+        ))
+    }
+
+    fn from_dir(path: &Path, source: GrammarSource) -> anyhow::Result<Self> {
+        let path = path.canonicalize()?;
+
+        let cargo_toml_path = path.join("Cargo.toml");
+        let cargo_toml = fs::read_to_string(&cargo_toml_path).with_context(|| {
+            format!(
+                "Grammar {} has no Cargo.toml, which is required to depend on it",
+                path.display()
+            )
+        })?;
+        let cargo_toml = toml::from_str::<CargoToml>(&cargo_toml)
+            .with_context(|| format!("Cannot parse {}", cargo_toml_path.display()))?;
+
+        // Grammars generated by recent versions of Tree-sitter describe themselves in
+        // tree-sitter.json, older ones in src/grammar.json and package.json.
+        let tree_sitter_json = read_json::<TreeSitterJson>(&path.join("tree-sitter.json"))?
+            .and_then(|t| t.grammars.into_iter().next());
+        let (name, scope, file_types) = match tree_sitter_json {
+            Some(grammar) => (Some(grammar.name), grammar.scope, grammar.file_types),
+            None => {
+                let name =
+                    read_json::<GrammarJson>(&path.join("src/grammar.json"))?.map(|g| g.name);
+                let (scope, file_types) = read_json::<PackageJson>(&path.join("package.json"))?
+                    .and_then(|p| p.tree_sitter.into_iter().next())
+                    .map_or((None, Vec::new()), |c| (c.scope, c.file_types));
+                (name, scope, file_types)
+            }
+        };
+
+        let node_types =
+            read_json::<Vec<NodeTypeJson>>(&path.join("src/node-types.json"))?.unwrap_or_default();
+        let node_kinds = node_kinds(node_types);
+
+        Ok(Self {
+            path,
+            source,
+            crate_name: cargo_toml.package.name,
+            crate_version: cargo_toml.package.version,
+            name,
+            scope,
+            file_types,
+            node_kinds,
+        })
+    }
+
+    /// Returns the kind of the root node of syntax trees, if it can be determined.
+    pub fn root_node_kind(&self) -> Option<&NodeKind> {
+        self.node_kinds.iter().find(|k| k.is_root)
+    }
+}
+
+fn find_npm_package(name: &str, dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join("node_modules").join(name))
+        .find(|path| path.is_dir())
+}
+
+fn find_registry_crate(name: &str, cargo_home: &Path) -> Option<PathBuf> {
+    let prefix = format!("{}-", name);
+    let mut latest: Option<(Vec<u64>, PathBuf)> = None;
+    for registry in fs::read_dir(cargo_home.join("registry/src"))
+        .ok()?
+        .flatten()
+    {
+        for entry in fs::read_dir(registry.path())
+            .into_iter()
+            .flatten()
+            .flatten()
+        {
+            let file_name = entry.file_name();
+            let version = match file_name.to_str().and_then(|n| n.strip_prefix(&prefix)) {
+                Some(version) => version,
+                None => continue,
+            };
+            // Names that the crate name is a prefix of, such as tree-sitter-javascript for
+            // tree-sitter-java, and pre-releases, do not parse as a version.
+            let version = match version
+                .split('.')
+                .map(|part| part.parse::<u64>())
+                .collect::<Result<Vec<_>, _>>()
+            {
+                Ok(version) => version,
+                Err(_) => continue,
+            };
+            match &latest {
+                Some((latest_version, _)) if *latest_version >= version => {}
+                _ => latest = Some((version, entry.path())),
+            }
+        }
+    }
+    latest.map(|(_, path)| path)
+}
+
+/// Reads a JSON file, or returns `None` if the file does not exist.
+fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> anyhow::Result<Option<T>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path)?;
+    let value = serde_json::from_str(&content)
+        .with_context(|| format!("Cannot parse {}", path.display()))?;
+    Ok(Some(value))
+}
+
+/// Returns the named node kinds. The root node kind is marked explicitly by recent versions of
+/// Tree-sitter. Otherwise, it is the only node kind with children that does not appear as the
+/// child of any other node kind.
+fn node_kinds(node_types: Vec<NodeTypeJson>) -> Vec<NodeKind> {
+    let has_explicit_root = node_types.iter().any(|t| t.root);
+    let mut referenced = HashSet::new();
+    for node_type in &node_types {
+        let children = node_type
+            .fields
+            .values()
+            .chain(node_type.children.iter())
+            .flat_map(|f| f.types.iter());
+        for child in children.chain(node_type.subtypes.iter().flatten()) {
+            referenced.insert(child.kind.clone());
+        }
+    }
+    let is_root_candidate = |t: &NodeTypeJson| {
+        t.subtypes.is_none()
+            && (!t.fields.is_empty() || t.children.is_some())
+            && !referenced.contains(&t.kind)
+    };
+    let unique_root_candidate = if has_explicit_root {
+        None
+    } else {
+        let mut candidates = node_types
+            .iter()
+            .filter(|t| t.named && is_root_candidate(t));
+        match (candidates.next(), candidates.next()) {
+            (Some(candidate), None) => Some(candidate.kind.clone()),
+            _ => None,
+        }
+    };
+
+    node_types
+        .into_iter()
+        .filter(|t| t.named)
+        .map(|t| NodeKind {
+            is_root: t.root || unique_root_candidate.as_ref() == Some(&t.kind),
+            is_supertype: t.subtypes.is_some(),
+            fields: t.fields.into_keys().collect(),
+            name: t.kind,
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct CargoToml {
+    package: CargoPackage,
+}
+
+#[derive(Deserialize)]
+struct CargoPackage {
+    name: String,
+    version: String,
+}
+
+#[derive(Deserialize)]
+struct GrammarJson {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct TreeSitterJson {
+    #[serde(default)]
+    grammars: Vec<TreeSitterJsonGrammar>,
+}
+
+#[derive(Deserialize)]
+struct TreeSitterJsonGrammar {
+    name: String,
+    scope: Option<String>,
+    #[serde(default, rename = "file-types")]
+    file_types: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct PackageJson {
+    #[serde(default, rename = "tree-sitter")]
+    tree_sitter: Vec<PackageJsonGrammar>,
+}
+
+#[derive(Deserialize)]
+struct PackageJsonGrammar {
+    scope: Option<String>,
+    #[serde(default, rename = "file-types")]
+    file_types: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct NodeTypeJson {
+    #[serde(rename = "type")]
+    kind: String,
+    named: bool,
+    #[serde(default)]
+    root: bool,
+    #[serde(default)]
+    fields: BTreeMap<String, NodeTypeChildren>,
+    children: Option<NodeTypeChildren>,
+    subtypes: Option<Vec<NodeTypeRef>>,
+}
+
+#[derive(Deserialize)]
+struct NodeTypeChildren {
+    types: Vec<NodeTypeRef>,
+}
+
+#[derive(Deserialize)]
+struct NodeTypeRef {
+    #[serde(rename = "type")]
+    kind: String,
+}
@@ -0,0 +1,180 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use anyhow::anyhow;
+use clap::Args;
+use clap::ValueHint;
+use stack_graphs::graph::StackGraph;
+use std::path::Path;
+use std::path::PathBuf;
+use tree_sitter_graph::Variables;
+
+use crate::cli::index::Indexer;
+use crate::cli::index::SourceFile;
+use crate::cli::util::iter_filtered_files_and_directories;
+use crate::cli::util::reporter::with_progress;
+use crate::cli::util::reporter::ConsoleReporter;
+use crate::cli::util::reporter::Level;
+use crate::cli::util::CLIFileReporter;
+use crate::cli::util::ExistingPathBufValueParser;
+use crate::cli::util::PathFilter;
+use crate::loader::FileReader;
+use crate::loader::Loader;
+use crate::NoCancellation;
+
+#[derive(Args)]
+#[clap(after_help = r#"FINDINGS:
+    Files are checked one at a time, so symbols that are pushed or scopes that
+    are attached in other files are reported as well. Such findings can be
+    expected, depending on the language.
+"#)]
+pub struct LintArgs {
+    /// Source file or directory paths to check.
+    #[clap(
+        value_name = "SOURCE_PATH",
+        required = true,
+        value_hint = ValueHint::AnyPath,
+        value_parser = ExistingPathBufValueParser,
+    )]
+    pub source_paths: Vec<PathBuf>,
+
+    /// Do not check files that are ignored by .gitignore or .ignore files, or are in .git
+    /// directories. Files given as source paths are always checked.
+    #[clap(long)]
+    pub respect_gitignore: bool,
+
+    /// Show files without findings.
+    #[clap(long, short = 'v')]
+    pub verbose: bool,
+}
+
+impl LintArgs {
+    pub fn run(self, mut loader: Loader) -> anyhow::Result<()> {
+        let filter = PathFilter {
+            respect_gitignore: self.respect_gitignore,
+            ..PathFilter::default()
+        };
+        let source_paths = self
+            .source_paths
+            .iter()
+            .map(|p| p.canonicalize())
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let source_files =
+            iter_filtered_files_and_directories(&source_paths, filter).collect::<Vec<_>>();
+        let reporter = with_progress(self.get_reporter(), || source_files.len());
+        let mut failed_files = 0;
+        for (source_root, source_path, strict) in &source_files {
+            let mut file_status = CLIFileReporter::new(reporter.as_ref(), source_path);
+            match self.lint_file(
+                source_root,
+                source_path,
+                *strict,
+                &mut loader,
+                &mut file_status,
+            ) {
+                Ok(true) => {}
+                Ok(false) => failed_files += 1,
+                Err(err) => {
+                    file_status.failure_if_processing("error", Some(&err));
+                    failed_files += 1;
+                }
+            }
+            file_status.assert_reported();
+        }
+        if failed_files > 0 {
+            return Err(anyhow!("{} files have findings or failed", failed_files));
+        }
+        Ok(())
+    }
+
+    fn get_reporter(&self) -> ConsoleReporter {
+        ConsoleReporter {
+            skipped_level: Level::None,
+            succeeded_level: if self.verbose {
+                Level::Summary
+            } else {
+                Level::None
+            },
+            failed_level: Level::Details,
+            canceled_level: Level::Details,
+        }
+    }
+
+    /// Checks a file, and returns whether it has no findings.
+    fn lint_file(
+        &self,
+        source_root: &Path,
+        source_path: &Path,
+        missing_is_error: bool,
+        loader: &mut Loader,
+        file_status: &mut CLIFileReporter,
+    ) -> anyhow::Result<bool> {
+        let mut file_reader = FileReader::new();
+        let lcs = loader.load_for_file(source_path, &mut file_reader, &NoCancellation)?;
+        if !lcs.has_some() {
+            if missing_is_error {
+                file_status.failure("not supported", None);
+                return Ok(false);
+            }
+            return Ok(true);
+        }
+        let source = file_reader.get(source_path)?;
+
+        let mut globals = Variables::new();
+        Loader::load_globals_from_sidecars(source_root, source_path, &mut globals)?;
+
+        file_status.processing();
+
+        let mut graph = StackGraph::new();
+        let file = graph
+            .add_file(&source_path.to_string_lossy())
+            .expect("file not present in empty graph");
+        if let Err(err) = Indexer::build_stack_graph(
+            &mut graph,
+            file,
+            SourceFile {
+                source_root,
+                source_path,
+                source,
+                globals: &globals,
+            },
+            lcs,
+            &NoCancellation,
+        ) {
+            file_status.failure("failed to build stack graph", Some(&err.display_pretty()));
+            return Ok(false);
+        }
+
+        let findings = graph.validate();
+        if findings.is_empty() {
+            file_status.success("no findings", None);
+            return Ok(true);
+        }
+        let details = findings
+            .iter()
+            .map(|finding| {
+                let node = finding.node();
+                match graph
+                    .source_info(node)
+                    .filter(|source_info| source_info.span != Default::default())
+                {
+                    Some(source_info) => format!(
+                        "{}:{}:{}: {}",
+                        source_path.display(),
+                        source_info.span.start.line + 1,
+                        source_info.span.start.column.grapheme_offset + 1,
+                        finding.display(&graph)
+                    ),
+                    None => finding.display(&graph).to_string(),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        file_status.failure(&format!("{} findings", findings.len()), Some(&details));
+        Ok(false)
+    }
+}
@@ -13,11 +13,30 @@ use crate::loader::LanguageConfiguration;
 use crate::loader::LoadError;
 use crate::loader::LoadPath;
 use crate::loader::Loader;
+use crate::loader::LoaderConfig;
 use crate::loader::DEFAULT_BUILTINS_PATHS;
+use crate::loader::DEFAULT_CONFIG_FILE_NAME;
 use crate::loader::DEFAULT_TSG_PATHS;
+use crate::NoCancellation;
 
 #[derive(Args)]
 pub struct PathLoaderArgs {
+    /// The loader configuration file that declares the languages to use.
+    #[clap(
+        long,
+        value_name = "CONFIG_PATH",
+        conflicts_with_all = ["tsg", "builtins", "grammar"],
+    )]
+    pub config: Option<PathBuf>,
+
+    /// Use the `stack-graphs.toml` loader configuration file in the current directory or its
+    /// closest ancestor.
+    #[clap(
+        long,
+        conflicts_with_all = ["config", "tsg", "builtins", "grammar"],
+    )]
+    pub find_config: bool,
+
     /// The TSG file to use for stack graph construction.
     /// If the file extension is omitted, `.tsg` is implicitly added.
     #[clap(long, value_name = "TSG_PATH")]
@@ -37,19 +56,53 @@ pub struct PathLoaderArgs {
     /// See https://tree-sitter.github.io/tree-sitter/syntax-highlighting#basics for details.
     #[clap(long, value_name = "SCOPE")]
     pub scope: Option<String>,
+
+    /// Do not cache builtins in the user cache directory.
+    /// Caching can also be disabled by setting the TREE_SITTER_STACK_GRAPHS_NO_CACHE environment variable.
+    #[clap(long)]
+    pub no_cache: bool,
 }
 
+/// Environment variable that disables caching if it is set.
+static NO_CACHE_ENV_VAR: &str = "TREE_SITTER_STACK_GRAPHS_NO_CACHE";
+
 impl PathLoaderArgs {
     pub fn new() -> Self {
         Self {
+            config: None,
+            find_config: false,
             tsg: None,
             builtins: None,
             grammar: Vec::new(),
             scope: None,
+            no_cache: false,
         }
     }
 
     pub fn get(&self) -> Result<Loader, LoadError<'static>> {
+        let cache_dir = self.cache_dir();
+        let config_path = if self.find_config {
+            let current_dir = std::env::current_dir()?;
+            Some(LoaderConfig::find(&current_dir).ok_or_else(|| {
+                LoadError::NoLanguagesFound(format!(
+                    "because there is no {} in {} or its ancestors",
+                    DEFAULT_CONFIG_FILE_NAME,
+                    current_dir.display(),
+                ))
+            })?)
+        } else {
+            self.config.clone()
+        };
+        if let Some(config_path) = config_path {
+            let config = LoaderConfig::from_path(&config_path)?;
+            return Loader::from_config(
+                &config,
+                self.scope.clone(),
+                cache_dir.as_deref(),
+                &NoCancellation,
+            );
+        }
+
         let tsg_paths = match &self.tsg {
             Some(tsg_path) => vec![LoadPath::Regular(tsg_path.clone())],
             None => DEFAULT_TSG_PATHS.clone(),
@@ -59,7 +112,7 @@ impl PathLoaderArgs {
             None => DEFAULT_BUILTINS_PATHS.clone(),
         };
 
-        let loader = if !self.grammar.is_empty() {
+        let mut loader = if !self.grammar.is_empty() {
             Loader::from_paths(
                 self.grammar.clone(),
                 self.scope.clone(),
@@ -77,8 +130,16 @@ impl PathLoaderArgs {
                 builtins_paths,
             )?
         };
+        loader.set_cache_dir(cache_dir);
         Ok(loader)
     }
+
+    fn cache_dir(&self) -> Option<PathBuf> {
+        if self.no_cache || std::env::var_os(NO_CACHE_ENV_VAR).is_some() {
+            return None;
+        }
+        dirs::cache_dir().map(|dir| dir.join("tree-sitter-stack-graphs"))
+    }
 }
 
 /// CLI arguments for creating a path based loader.
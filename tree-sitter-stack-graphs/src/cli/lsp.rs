@@ -34,6 +34,7 @@ use crate::cli::query::QueryError;
 use crate::cli::util::duration_from_milliseconds_str;
 use crate::cli::util::duration_from_seconds_str;
 use crate::cli::util::reporter::Reporter;
+use crate::cli::util::PositionEncoding;
 use crate::cli::util::SourcePosition;
 use crate::cli::util::SourceSpan;
 use crate::loader::Loader;
@@ -151,12 +152,12 @@ impl Backend {
         (sender, cancellation_flag)
     }
 
-    /// Opens or creates the database. If the database exists with an incompatible
-    /// version, it is recreated.
+    /// Opens or creates the database. If the database exists with a version that
+    /// cannot be upgraded, it is recreated.
     async fn ensure_compatible_database(&self) -> Result<()> {
         match SQLiteWriter::open(&self.db_path) {
             Ok(_) => {}
-            Err(StorageError::IncorrectVersion(_)) => {
+            Err(StorageError::IncompatibleVersion { .. }) => {
                 self.logger
                     .error(format!(
                         "Recreating database with new version {}",
@@ -266,7 +267,7 @@ impl Backend {
     }
 
     async fn definitions(&self, reference: SourcePosition) -> Vec<SourceSpan> {
-        let mut db = match SQLiteReader::open(&self.db_path) {
+        let mut db = match SQLiteReader::open_read_only(&self.db_path) {
             Ok(db) => db,
             Err(err) => {
                 self.logger
@@ -291,7 +292,11 @@ impl Backend {
             querier.definitions(reference, cancellation_flag.as_ref())
         };
         match result {
-            Ok(result) => result.into_iter().flat_map(|r| r.targets).collect(),
+            Ok(result) => result
+                .into_iter()
+                .flat_map(|r| r.targets)
+                .map(|t| t.span)
+                .collect(),
             Err(QueryError::Cancelled(at)) => {
                 self.logger
                     .error(format!("query timed out at {}", at,))
@@ -439,7 +444,13 @@ impl LanguageServer for Backend {
         };
         let line = params.text_document_position_params.position.line as usize;
         let column = params.text_document_position_params.position.character as usize;
-        let reference = SourcePosition { path, line, column };
+        let reference = SourcePosition {
+            path,
+            line,
+            column,
+            end_column: None,
+            encoding: PositionEncoding::Utf16,
+        };
         let locations = self
             .definitions(reference)
             .await
@@ -7,13 +7,31 @@
 
 use anyhow::anyhow;
 use clap::Args;
+use clap::ValueEnum;
 use clap::ValueHint;
+use serde_json::json;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
+use tree_sitter::Node;
 use tree_sitter::Parser;
+use tree_sitter::Point;
+use tree_sitter::Tree;
 use tree_sitter_graph::parse_error::ParseError;
+use tree_sitter_graph::parse_error::TreeWithParseErrorVec;
 
+use crate::cli::util::iter_filtered_files_and_directories;
+use crate::cli::util::output_writer;
+use crate::cli::util::reporter::with_progress;
+use crate::cli::util::reporter::ConsoleReporter;
+use crate::cli::util::reporter::Level;
+use crate::cli::util::CLIFileReporter;
 use crate::cli::util::ExistingPathBufValueParser;
+use crate::cli::util::PathFilter;
 use crate::loader::FileReader;
 use crate::loader::Loader;
 use crate::util::DisplayParseErrorsPretty;
@@ -21,7 +39,7 @@ use crate::BuildError;
 
 #[derive(Args)]
 pub struct ParseArgs {
-    /// Source file path to parse.
+    /// Source file path to parse.  May be a directory if --summary is given.
     #[clap(
         value_name = "SOURCE_PATH",
         required = true,
@@ -29,10 +47,49 @@ pub struct ParseArgs {
         value_parser = ExistingPathBufValueParser,
     )]
     pub source_path: PathBuf,
+
+    /// Format of the parse tree.  Lines and columns are one-based, and byte offsets zero-based.
+    /// Files with parse errors are written as well, with the error and missing nodes flagged.
+    #[clap(long, value_enum, default_value_t = TreeFormat::Sexp)]
+    pub format: TreeFormat,
+
+    /// File to write the parse tree to.  The tree is written to standard output if no file is
+    /// given.
+    #[clap(long, short = 'o', value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub output: Option<PathBuf>,
+
+    /// Parse all files in the source path instead of writing a parse tree, and report the number
+    /// of files, files with parse errors, and error nodes per language, together with the files
+    /// with the most error nodes.  Languages are named after the file extensions of their files.
+    #[clap(long, conflicts_with("format"))]
+    pub summary: bool,
+
+    /// Number of files with the most error nodes that are listed in the summary.
+    #[clap(long, value_name = "N", default_value_t = 10, requires("summary"))]
+    pub top: usize,
+
+    /// Do not parse files that are ignored by .gitignore or .ignore files, or are in .git
+    /// directories, when summarizing a directory.
+    #[clap(long, requires("summary"))]
+    pub respect_gitignore: bool,
+}
+
+/// Format of parse trees.
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum TreeFormat {
+    /// S-expressions of the named nodes, with their field names and line and column ranges.
+    Sexp,
+    /// A JSON object of all nodes, with their field names and byte, line, and column ranges.
+    Json,
+    /// An XML document of all nodes, with their field names and byte, line, and column ranges.
+    Xml,
 }
 
 impl ParseArgs {
     pub fn run(self, mut loader: Loader) -> anyhow::Result<()> {
+        if self.summary {
+            return self.run_summary(loader);
+        }
         let mut file_reader = FileReader::new();
         let lang =
             match loader.load_tree_sitter_language_for_file(&self.source_path, &mut file_reader)? {
@@ -40,8 +97,219 @@ impl ParseArgs {
                 None => return Err(anyhow!("No stack graph language found")),
             };
         let source = file_reader.get(&self.source_path)?;
-        let tree = parse(lang, &self.source_path, source)?;
-        print_tree(tree);
+        let parse_errors = ParseError::into_all(parse_with_errors(lang, source)?);
+        let has_errors = !parse_errors.errors().is_empty();
+        if has_errors {
+            print_parse_errors(&parse_errors, &self.source_path, source);
+        }
+        let tree = parse_errors.into_tree();
+        let mut writer = output_writer(&self.output)?;
+        match self.format {
+            TreeFormat::Sexp => write_sexp(&mut writer, &tree)?,
+            TreeFormat::Json => write_json(&mut writer, &self.source_path, &tree)?,
+            TreeFormat::Xml => write_xml(&mut writer, &self.source_path, &tree)?,
+        }
+        writer.flush()?;
+        if has_errors {
+            return Err(anyhow!(
+                "Failed to parse file {}",
+                self.source_path.display()
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl ParseArgs {
+    fn run_summary(self, mut loader: Loader) -> anyhow::Result<()> {
+        let filter = PathFilter {
+            respect_gitignore: self.respect_gitignore,
+            ..PathFilter::default()
+        };
+        let source_files =
+            iter_filtered_files_and_directories(&[&self.source_path], filter).collect::<Vec<_>>();
+        let reporter = with_progress(
+            ConsoleReporter {
+                skipped_level: Level::None,
+                succeeded_level: Level::None,
+                failed_level: Level::Details,
+                canceled_level: Level::None,
+            },
+            || source_files.len(),
+        );
+        let mut summary = ParseSummary::default();
+        for (_, source_path, _) in &source_files {
+            let mut file_status = CLIFileReporter::new(reporter.as_ref(), source_path);
+            if let Err(err) = summary.add_file(source_path, &mut loader, &mut file_status) {
+                file_status.failure_if_processing("error", Some(&err));
+                summary.failed_files += 1;
+            }
+            file_status.assert_reported();
+        }
+        let mut writer = output_writer(&self.output)?;
+        summary.write(&mut writer, self.top)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Parse statistics of a set of files, per language.
+#[derive(Default)]
+struct ParseSummary {
+    languages: HashMap<tree_sitter::Language, LanguageSummary>,
+    /// Files with error nodes, with their number of error nodes and total number of nodes.
+    error_files: Vec<(PathBuf, usize, usize)>,
+    /// Files that could not be read or parsed.
+    failed_files: usize,
+    /// Files that no language was found for.
+    unsupported_files: usize,
+}
+
+#[derive(Default)]
+struct LanguageSummary {
+    extensions: BTreeSet<String>,
+    files: usize,
+    error_files: usize,
+    nodes: usize,
+    error_nodes: usize,
+}
+
+impl LanguageSummary {
+    fn name(&self) -> String {
+        self.extensions
+            .iter()
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl ParseSummary {
+    fn add_file(
+        &mut self,
+        path: &Path,
+        loader: &mut Loader,
+        file_status: &mut CLIFileReporter,
+    ) -> anyhow::Result<()> {
+        let mut file_reader = FileReader::new();
+        let language = match loader.load_tree_sitter_language_for_file(path, &mut file_reader)? {
+            Some(language) => language,
+            None => {
+                self.unsupported_files += 1;
+                file_status.skipped("not supported", None);
+                return Ok(());
+            }
+        };
+        file_status.processing();
+        let source = file_reader.get(path)?;
+        let tree = parse_with_errors(language, source)?;
+
+        let mut nodes = 0;
+        let mut error_nodes = 0;
+        walk_tree(&tree, |visit| {
+            if let Visit::Enter(node, _, _) = visit {
+                nodes += 1;
+                if node.is_error() || node.is_missing() {
+                    error_nodes += 1;
+                }
+            }
+            Ok(())
+        })?;
+
+        let summary = self.languages.entry(language).or_default();
+        summary.extensions.insert(
+            path.extension()
+                .or_else(|| path.file_name())
+                .map_or(String::new(), |e| e.to_string_lossy().to_string()),
+        );
+        summary.files += 1;
+        summary.nodes += nodes;
+        summary.error_nodes += error_nodes;
+        if error_nodes > 0 {
+            summary.error_files += 1;
+            self.error_files
+                .push((path.to_path_buf(), error_nodes, nodes));
+            file_status.success("parse errors", None);
+        } else {
+            file_status.success("success", None);
+        }
+        Ok(())
+    }
+
+    fn write<W: Write>(&mut self, writer: &mut W, top: usize) -> std::io::Result<()> {
+        fn density(error_nodes: usize, nodes: usize) -> String {
+            format!("{:.2}%", 100.0 * error_nodes as f64 / nodes.max(1) as f64)
+        }
+
+        let mut languages = self.languages.values().collect::<Vec<_>>();
+        languages.sort_by_key(|l| l.name());
+        let header = [
+            "Language",
+            "Files",
+            "Files with errors",
+            "Error nodes",
+            "Error node density",
+        ];
+        let rows = languages
+            .iter()
+            .map(|l| {
+                [
+                    l.name(),
+                    l.files.to_string(),
+                    l.error_files.to_string(),
+                    l.error_nodes.to_string(),
+                    density(l.error_nodes, l.nodes),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let mut widths = header.map(str::len);
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        write!(writer, "{:<w$}", header[0], w = widths[0])?;
+        for (cell, width) in header.iter().zip(widths).skip(1) {
+            write!(writer, "  {:>w$}", cell, w = width)?;
+        }
+        writeln!(writer)?;
+        for row in &rows {
+            write!(writer, "{:<w$}", row[0], w = widths[0])?;
+            for (cell, width) in row.iter().zip(widths).skip(1) {
+                write!(writer, "  {:>w$}", cell, w = width)?;
+            }
+            writeln!(writer)?;
+        }
+        if self.unsupported_files > 0 {
+            writeln!(
+                writer,
+                "{} files without a language were skipped.",
+                self.unsupported_files
+            )?;
+        }
+        if self.failed_files > 0 {
+            writeln!(
+                writer,
+                "{} files could not be read or parsed.",
+                self.failed_files
+            )?;
+        }
+
+        if top > 0 && !self.error_files.is_empty() {
+            self.error_files
+                .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            writeln!(writer)?;
+            writeln!(writer, "Files with the most error nodes:")?;
+            for (path, error_nodes, nodes) in self.error_files.iter().take(top) {
+                writeln!(
+                    writer,
+                    "  {:>6}  {:>7}  {}",
+                    error_nodes,
+                    density(*error_nodes, *nodes),
+                    path.display()
+                )?;
+            }
+        }
         Ok(())
     }
 }
@@ -51,86 +319,248 @@ pub(super) fn parse(
     path: &Path,
     source: &str,
 ) -> anyhow::Result<tree_sitter::Tree> {
-    let mut parser = Parser::new();
-    parser.set_language(language)?;
-    let tree = parser.parse(source, None).ok_or(BuildError::ParseError)?;
-    let parse_errors = ParseError::into_all(tree);
+    let parse_errors = ParseError::into_all(parse_with_errors(language, source)?);
     if parse_errors.errors().len() > 0 {
-        eprintln!(
-            "{}",
-            DisplayParseErrorsPretty {
-                parse_errors: &parse_errors,
-                path: path,
-                source: &source,
-                max_errors: crate::MAX_PARSE_ERRORS,
-            }
-        );
+        print_parse_errors(&parse_errors, path, source);
         return Err(anyhow!("Failed to parse file {}", path.display()));
     }
     Ok(parse_errors.into_tree())
 }
 
-// From: https://github.com/tree-sitter/tree-sitter/blob/master/cli/src/parse.rs
-pub(super) fn print_tree(tree: tree_sitter::Tree) {
-    let mut cursor = tree.walk();
+fn parse_with_errors(language: tree_sitter::Language, source: &str) -> anyhow::Result<Tree> {
+    let mut parser = Parser::new();
+    parser.set_language(language)?;
+    Ok(parser.parse(source, None).ok_or(BuildError::ParseError)?)
+}
 
-    let mut needs_newline = false;
-    let mut indent_level = 0;
-    let mut did_visit_children = false;
+fn print_parse_errors(parse_errors: &TreeWithParseErrorVec, path: &Path, source: &str) {
+    eprintln!(
+        "{}",
+        DisplayParseErrorsPretty {
+            parse_errors,
+            path,
+            source,
+            max_errors: crate::MAX_PARSE_ERRORS,
+        }
+    );
+}
+
+enum Visit<'a> {
+    Enter(Node<'a>, Option<&'static str>, usize),
+    Leave(Node<'a>, usize),
+}
+
+/// Visits all nodes of the tree in depth-first order, together with their field names and depth.
+fn walk_tree<F>(tree: &Tree, mut visit: F) -> std::io::Result<()>
+where
+    F: FnMut(Visit) -> std::io::Result<()>,
+{
+    let mut cursor = tree.walk();
+    let mut depth = 0;
     loop {
-        let node = cursor.node();
-        let is_named = node.is_named();
-        if did_visit_children {
-            if is_named {
-                print!(")");
-                needs_newline = true;
-            }
+        visit(Visit::Enter(cursor.node(), cursor.field_name(), depth))?;
+        if cursor.goto_first_child() {
+            depth += 1;
+            continue;
+        }
+        loop {
+            visit(Visit::Leave(cursor.node(), depth))?;
             if cursor.goto_next_sibling() {
-                did_visit_children = false;
-            } else if cursor.goto_parent() {
-                did_visit_children = true;
-                indent_level -= 1;
-            } else {
                 break;
             }
-        } else {
-            if is_named {
+            if !cursor.goto_parent() {
+                return Ok(());
+            }
+            depth -= 1;
+        }
+    }
+}
+
+// After: https://github.com/tree-sitter/tree-sitter/blob/master/cli/src/parse.rs
+fn write_sexp<W: Write>(writer: &mut W, tree: &Tree) -> std::io::Result<()> {
+    // missing nodes are included, even if they are anonymous, to show where they are expected
+    let is_shown = |node: Node| node.is_named() || node.is_missing();
+    let mut needs_newline = false;
+    walk_tree(tree, |visit| {
+        match visit {
+            Visit::Enter(node, field_name, depth) if is_shown(node) => {
                 if needs_newline {
-                    print!("\n");
+                    writeln!(writer)?;
                 }
-                for _ in 0..indent_level {
-                    print!("  ");
+                for _ in 0..depth {
+                    write!(writer, "  ")?;
                 }
-                if let Some(field_name) = cursor.field_name() {
-                    print!("{}: ", field_name);
+                if let Some(field_name) = field_name {
+                    write!(writer, "{}: ", field_name)?;
                 }
-                print_node(node, false);
+                write_node(writer, node, false)?;
                 needs_newline = true;
             }
-            if cursor.goto_first_child() {
-                did_visit_children = false;
-                indent_level += 1;
-            } else {
-                did_visit_children = true;
+            Visit::Leave(node, _) if is_shown(node) => {
+                write!(writer, ")")?;
+                needs_newline = true;
             }
+            _ => {}
         }
-    }
-    cursor.reset(tree.root_node());
-    println!("");
+        Ok(())
+    })?;
+    writeln!(writer)
 }
 
 pub(super) fn print_node(node: tree_sitter::Node, close: bool) {
+    write_node(&mut std::io::stdout(), node, close).expect("cannot write to standard output");
+}
+
+fn write_node<W: Write>(writer: &mut W, node: Node, close: bool) -> std::io::Result<()> {
     let start = node.start_position();
     let end = node.end_position();
-    print!(
-        "({} [{}:{} - {}:{}]",
-        node.kind(),
+    write!(writer, "(")?;
+    if node.is_missing() {
+        write!(writer, "MISSING ")?;
+    }
+    if node.is_missing() && !node.is_named() {
+        // quote missing tokens, which are often punctuation
+        write!(writer, "{:?}", node.kind())?;
+    } else {
+        write!(writer, "{}", node.kind())?;
+    }
+    write!(
+        writer,
+        " [{}:{} - {}:{}]",
         start.row + 1,
         start.column + 1,
         end.row + 1,
         end.column + 1
-    );
+    )?;
     if close {
-        print!(")");
+        write!(writer, ")")?;
+    }
+    Ok(())
+}
+
+fn write_json<W: Write>(writer: &mut W, path: &Path, tree: &Tree) -> anyhow::Result<()> {
+    fn position(point: Point) -> Value {
+        json!({ "line": point.row + 1, "column": point.column + 1 })
+    }
+
+    // the tree is built iteratively, because deeply nested trees would overflow the stack
+    let mut stack: Vec<Value> = Vec::new();
+    let mut root = None;
+    walk_tree(tree, |visit| {
+        match visit {
+            Visit::Enter(node, field_name, _) => {
+                let mut value = json!({
+                    "kind": node.kind(),
+                    "named": node.is_named(),
+                    "start_byte": node.start_byte(),
+                    "end_byte": node.end_byte(),
+                    "start": position(node.start_position()),
+                    "end": position(node.end_position()),
+                    "children": [],
+                });
+                if let Some(field_name) = field_name {
+                    value["field"] = field_name.into();
+                }
+                if node.is_error() {
+                    value["error"] = true.into();
+                }
+                if node.is_missing() {
+                    value["missing"] = true.into();
+                }
+                stack.push(value);
+            }
+            Visit::Leave(_, _) => {
+                let value = stack.pop().expect("entered node");
+                match stack.last_mut() {
+                    Some(parent) => parent["children"]
+                        .as_array_mut()
+                        .expect("children array")
+                        .push(value),
+                    None => root = Some(value),
+                }
+            }
+        }
+        Ok(())
+    })?;
+    serde_json::to_writer(
+        &mut *writer,
+        &json!({
+            "path": path.to_string_lossy(),
+            "has_error": tree.root_node().has_error(),
+            "root": root,
+        }),
+    )?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+fn write_xml<W: Write>(writer: &mut W, path: &Path, tree: &Tree) -> anyhow::Result<()> {
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        writer,
+        r#"<tree path="{}" has_error="{}">"#,
+        xml_escape(&path.to_string_lossy()),
+        tree.root_node().has_error()
+    )?;
+    walk_tree(tree, |visit| {
+        match visit {
+            Visit::Enter(node, field_name, depth) => {
+                let start = node.start_position();
+                let end = node.end_position();
+                write!(writer, "{}", "  ".repeat(depth + 1))?;
+                write!(writer, r#"<node kind="{}""#, xml_escape(node.kind()))?;
+                if let Some(field_name) = field_name {
+                    write!(writer, r#" field="{}""#, field_name)?;
+                }
+                write!(writer, r#" named="{}""#, node.is_named())?;
+                if node.is_error() {
+                    write!(writer, r#" error="true""#)?;
+                }
+                if node.is_missing() {
+                    write!(writer, r#" missing="true""#)?;
+                }
+                write!(
+                    writer,
+                    r#" start_byte="{}" end_byte="{}" start_line="{}" start_column="{}" end_line="{}" end_column="{}""#,
+                    node.start_byte(),
+                    node.end_byte(),
+                    start.row + 1,
+                    start.column + 1,
+                    end.row + 1,
+                    end.column + 1,
+                )?;
+                if node.child_count() == 0 {
+                    writeln!(writer, "/>")?;
+                } else {
+                    writeln!(writer, ">")?;
+                }
+            }
+            Visit::Leave(node, depth) => {
+                if node.child_count() > 0 {
+                    writeln!(writer, "{}</node>", "  ".repeat(depth + 1))?;
+                }
+            }
+        }
+        Ok(())
+    })?;
+    writeln!(writer, "</tree>")?;
+    Ok(())
+}
+
+fn xml_escape(value: &str) -> Cow<'_, str> {
+    if !value.contains(['<', '>', '&', '"', '\'']) {
+        return Cow::Borrowed(value);
+    }
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
     }
+    Cow::Owned(escaped)
 }
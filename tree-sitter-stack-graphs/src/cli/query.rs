@@ -8,10 +8,35 @@
 use clap::Args;
 use clap::Parser;
 use clap::Subcommand;
+use clap::ValueEnum;
 use clap::ValueHint;
+use lsp_positions::Span;
+use stack_graphs::arena::Handle;
+use stack_graphs::graph::Node;
+use stack_graphs::graph::StackGraph;
+use stack_graphs::names::qualified_name;
+use stack_graphs::partial::PartialPath;
+use stack_graphs::partial::PartialPaths;
+use stack_graphs::stitching::Database;
+use stack_graphs::stitching::ForwardCandidates;
 use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::stitching::GraphEdgeCandidates;
+use stack_graphs::stitching::RejectionReason;
+use stack_graphs::stitching::StitchingEvent;
+use stack_graphs::stitching::StitchingStats;
+use stack_graphs::stitching::StitchingTracer;
+use stack_graphs::stitching::SymbolStackPrefix;
 use stack_graphs::storage::FileStatus;
 use stack_graphs::storage::SQLiteReader;
+use stack_graphs::storage::StorageError;
+use std::collections::hash_map::Entry;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
 use thiserror::Error;
@@ -21,6 +46,7 @@ use crate::cli::util::reporter::ConsoleReporter;
 use crate::cli::util::reporter::Reporter;
 use crate::cli::util::sha1;
 use crate::cli::util::wait_for_input;
+use crate::cli::util::PositionEncoding;
 use crate::cli::util::SourcePosition;
 use crate::cli::util::SourceSpan;
 use crate::loader::FileReader;
@@ -33,6 +59,21 @@ pub struct QueryArgs {
     #[clap(long)]
     pub wait_at_start: bool,
 
+    /// Print the memory used by the loaded stack graph, partial paths, and database after
+    /// querying. Useful for diagnosing memory usage.
+    #[clap(long)]
+    pub stats: bool,
+
+    /// Write the events of path stitching to this file, as JSON lines.  Useful for debugging why a
+    /// reference resolves the way it does.
+    #[clap(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub trace: Option<PathBuf>,
+
+    /// Only accept definitions in files under this directory, for example to exclude the
+    /// definitions in vendored dependencies.  Can be given multiple times.
+    #[clap(long, value_name = "PATH", value_hint = ValueHint::DirPath)]
+    pub within: Vec<PathBuf>,
+
     #[clap(subcommand)]
     target: Target,
 }
@@ -43,45 +84,279 @@ impl QueryArgs {
             wait_for_input()?;
         }
         let mut db = SQLiteReader::open(&db_path)?;
-        self.target.run(&mut db)
+        let tracer = match &self.trace {
+            Some(path) => Some(Box::new(JsonLinesTracer::new(File::create(path)?))
+                as Box<dyn StitchingTracer + Send>),
+            None => None,
+        };
+        let within = self
+            .within
+            .iter()
+            .map(|path| path.canonicalize())
+            .collect::<std::io::Result<Vec<_>>>()?;
+        self.target.run(&mut db, tracer, within)?;
+        if self.stats {
+            print_memory_usage(&mut db);
+        }
+        Ok(())
+    }
+}
+
+fn print_memory_usage(db: &mut SQLiteReader) {
+    let (graph, partials, db) = db.get();
+    let graph = graph.memory_usage();
+    let partials = partials.memory_usage();
+    let db = db.memory_usage();
+    println!("memory usage (bytes)");
+    let rows = [
+        ("stack graph", graph.total(), 0),
+        ("strings", graph.strings, 1),
+        ("symbols", graph.symbols, 1),
+        ("files", graph.files, 1),
+        ("nodes", graph.nodes, 1),
+        ("source info", graph.source_info, 1),
+        ("edges", graph.edges, 1),
+        ("debug info", graph.debug_info, 1),
+        ("metadata", graph.metadata, 1),
+        ("partial paths", partials.total(), 0),
+        ("symbol stacks", partials.symbol_stacks, 1),
+        ("scope stacks", partials.scope_stacks, 1),
+        ("edges", partials.edges, 1),
+        ("database", db.total(), 0),
+        ("paths", db.partial_paths, 1),
+        ("local nodes", db.local_nodes, 1),
+        ("symbol stack keys", db.symbol_stack_keys, 1),
+        ("indices", db.indices, 1),
+    ];
+    for (name, bytes, depth) in rows {
+        let indent = 2 * (depth + 1);
+        println!(
+            "{:indent$}{:<width$}{:>12}",
+            "",
+            name,
+            bytes,
+            indent = indent,
+            width = 24 - indent
+        );
     }
 }
 
 #[derive(Subcommand)]
 pub enum Target {
     Definition(Definition),
+    Info(Info),
+    ResolveFile(ResolveFile),
+    Complete(Complete),
 }
 
 impl Target {
-    pub fn run(self, db: &mut SQLiteReader) -> anyhow::Result<()> {
-        let reporter = ConsoleReporter::details();
+    pub fn run(
+        self,
+        db: &mut SQLiteReader,
+        tracer: Option<Box<dyn StitchingTracer + Send>>,
+        within: Vec<PathBuf>,
+    ) -> anyhow::Result<()> {
+        let reporter = match &self {
+            // keep the output parseable
+            Self::ResolveFile(cmd) if cmd.format == ReportFormat::Json => ConsoleReporter::none(),
+            _ => ConsoleReporter::details(),
+        };
         let mut querier = Querier::new(db, &reporter);
+        querier.set_tracer(tracer);
+        querier.set_within(within);
         match self {
             Self::Definition(cmd) => cmd.run(&mut querier),
+            Self::Info(cmd) => cmd.run(&mut querier),
+            Self::ResolveFile(cmd) => cmd.run(&mut querier),
+            Self::Complete(cmd) => cmd.run(&mut querier),
         }
     }
 }
 
 #[derive(Parser)]
 pub struct Definition {
-    /// Reference source positions, formatted as PATH:LINE:COLUMN.
+    /// Reference source positions, formatted as PATH:LINE:COLUMN.  Use PATH:LINE:COLUMN-COLUMN
+    /// to find the references that overlap a range of columns, or PATH:LINE to find the
+    /// references that overlap a line.
     #[clap(
         value_name = "SOURCE_POSITION",
-        required = true,
+        required_unless_present = "positions_file",
         value_hint = ValueHint::AnyPath,
         value_parser,
     )]
     pub references: Vec<SourcePosition>,
+
+    /// Read reference source positions from this file, one position per line, or from
+    /// standard input if the file is `-`.  Results are printed as soon as they are found, so that
+    /// many positions can be queried without starting a process for each.
+    #[clap(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub positions_file: Option<PathBuf>,
+
+    /// Unit of the columns of the source positions.
+    #[clap(long, value_enum, default_value_t = PositionEncoding::Grapheme)]
+    pub position_encoding: PositionEncoding,
+
+    /// Explain the ranking of the definitions, and list candidate definitions that were rejected
+    /// because they are shadowed by another definition.
+    #[clap(long)]
+    pub explain: bool,
+
+    /// Stop looking for definitions of a reference as soon as this many are found, and show at
+    /// most this many definitions that are not shadowed.
+    #[clap(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    pub limit: Option<u64>,
+
+    /// List the TSG stanzas that created the edges of the path to each definition, in the order
+    /// in which the path traverses them.  Useful for finding the rules that cause a reference to
+    /// resolve the way it does.
+    #[clap(long)]
+    pub explain_rules: bool,
 }
 
 impl Definition {
     pub fn run(self, querier: &mut Querier) -> anyhow::Result<()> {
         let cancellation_flag = NoCancellation;
-        let mut file_reader = FileReader::new();
-        for mut reference in self.references {
+        let mut sources = SourceReader::new();
+        querier.set_max_results(self.limit.map(|limit| limit as usize));
+        querier.set_explain_rules(self.explain_rules);
+        for reference in &self.references {
+            self.query(reference.clone(), querier, &mut sources, &cancellation_flag)?;
+        }
+        if let Some(positions_file) = &self.positions_file {
+            let reader: Box<dyn BufRead> = if positions_file == Path::new("-") {
+                Box::new(std::io::stdin().lock())
+            } else {
+                Box::new(BufReader::new(File::open(positions_file)?))
+            };
+            for (line_number, line) in reader.lines().enumerate() {
+                let line = line?;
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                let reference = line.parse::<SourcePosition>().map_err(|e| {
+                    anyhow::anyhow!("{}:{}: {}", positions_file.display(), line_number + 1, e)
+                })?;
+                self.query(reference, querier, &mut sources, &cancellation_flag)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn query(
+        &self,
+        mut reference: SourcePosition,
+        querier: &mut Querier,
+        sources: &mut SourceReader,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> anyhow::Result<()> {
+        reference.encoding = self.position_encoding;
+        // files may be missing if the database was copied from another machine
+        if reference.path.exists() {
             reference.canonicalize()?;
+        }
+
+        let results = querier.definitions(reference.clone(), cancellation_flag)?;
+        let numbered = results.len() > 1;
+        let indent = if numbered { 6 } else { 0 };
+        if numbered {
+            println!("found {} references at position", results.len());
+        }
+        for (
+            idx,
+            QueryResult {
+                source: reference,
+                targets: definitions,
+                shadowed,
+                ..
+            },
+        ) in results.into_iter().enumerate()
+        {
+            print_reference(sources, querier, numbered, idx, &reference, indent);
+            print_definition_count(definitions.len(), indent);
+            if self.explain && definitions.len() > 1 {
+                println!(
+                    "{}definitions in the same file come first, then definitions with shorter paths",
+                    " ".repeat(indent)
+                );
+            }
+            for QueryTarget {
+                span: definition,
+                metadata,
+                rules,
+            } in definitions.into_iter()
+            {
+                print_excerpt(sources, querier, &definition, indent);
+                for (key, value) in metadata {
+                    println!("{}{}: {}", " ".repeat(indent + 2), key, value);
+                }
+                if self.explain_rules {
+                    if rules.is_empty() {
+                        println!("{}path traverses no known rules", " ".repeat(indent + 2));
+                    } else {
+                        println!("{}path traverses rules", " ".repeat(indent + 2));
+                    }
+                    for rule in rules {
+                        println!("{}  {}", " ".repeat(indent + 2), rule);
+                    }
+                }
+            }
+            if self.explain {
+                match shadowed.len() {
+                    0 => println!("{}rejected no shadowed definitions", " ".repeat(indent)),
+                    1 => println!("{}rejected shadowed definition", " ".repeat(indent)),
+                    n => println!("{}rejected {} shadowed definitions", " ".repeat(indent), n),
+                }
+                for ShadowedTarget {
+                    target,
+                    shadowed_by,
+                } in shadowed.into_iter()
+                {
+                    print_excerpt(sources, querier, &target.span, indent);
+                    println!(
+                        "{}shadowed by definition at {}:{}:{}",
+                        " ".repeat(indent + 2),
+                        shadowed_by.span.path.display(),
+                        shadowed_by.span.first_line() + 1,
+                        shadowed_by.span.span.start.column.grapheme_offset + 1
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+pub struct Info {
+    /// Reference source positions, formatted as PATH:LINE:COLUMN.  Use PATH:LINE:COLUMN-COLUMN
+    /// to find the references that overlap a range of columns, or PATH:LINE to find the
+    /// references that overlap a line.
+    #[clap(
+        value_name = "SOURCE_POSITION",
+        required = true,
+        value_hint = ValueHint::AnyPath,
+        value_parser,
+    )]
+    pub references: Vec<SourcePosition>,
+
+    /// Unit of the columns of the source positions.
+    #[clap(long, value_enum, default_value_t = PositionEncoding::Grapheme)]
+    pub position_encoding: PositionEncoding,
+}
+
+impl Info {
+    pub fn run(self, querier: &mut Querier) -> anyhow::Result<()> {
+        let cancellation_flag = NoCancellation;
+        let mut sources = SourceReader::new();
+        for mut reference in self.references {
+            reference.encoding = self.position_encoding;
+            // files may be missing if the database was copied from another machine
+            if reference.path.exists() {
+                reference.canonicalize()?;
+            }
 
-            let results = querier.definitions(reference.clone(), &cancellation_flag)?;
+            let results = querier.definition_info(reference.clone(), &cancellation_flag)?;
             let numbered = results.len() > 1;
             let indent = if numbered { 6 } else { 0 };
             if numbered {
@@ -89,58 +364,429 @@ impl Definition {
             }
             for (
                 idx,
-                QueryResult {
+                InfoResult {
                     source: reference,
                     targets: definitions,
+                    ..
                 },
             ) in results.into_iter().enumerate()
             {
-                if numbered {
-                    println!("{:4}: queried reference", idx);
-                } else {
-                    println!("queried reference");
+                print_reference(&mut sources, querier, numbered, idx, &reference, indent);
+                print_definition_count(definitions.len(), indent);
+                for definition in definitions.into_iter() {
+                    print_excerpt(&mut sources, querier, &definition.target.span, indent);
+                    let indent = " ".repeat(indent + 2);
+                    if let Some(syntax_type) = &definition.syntax_type {
+                        println!("{}syntax type: {}", indent, syntax_type);
+                    }
+                    if let Some(qualified_name) = &definition.qualified_name {
+                        println!("{}qualified name: {}", indent, qualified_name);
+                    }
+                    if !definition.enclosing_definitions.is_empty() {
+                        println!("{}enclosing definitions:", indent);
+                        for (symbol, span) in &definition.enclosing_definitions {
+                            println!(
+                                "{}  {} at {}:{}:{}",
+                                indent,
+                                symbol,
+                                span.path.display(),
+                                span.first_line() + 1,
+                                span.span.start.column.grapheme_offset + 1
+                            );
+                        }
+                    }
+                    if !definition.target.metadata.is_empty() {
+                        println!("{}metadata:", indent);
+                        for (key, value) in &definition.target.metadata {
+                            println!("{}  {}: {}", indent, key, value);
+                        }
+                    }
                 }
-                println!(
-                    "{}",
-                    Excerpt::from_source(
-                        &reference.path,
-                        file_reader.get(&reference.path).unwrap_or_default(),
-                        reference.first_line(),
-                        reference.first_line_column_range(),
-                        indent
-                    )
-                );
-                match definitions.len() {
-                    0 => println!("{}has no definitions", " ".repeat(indent)),
-                    1 => println!("{}has definition", " ".repeat(indent)),
-                    n => println!("{}has {} definitions", " ".repeat(indent), n),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+pub struct Complete {
+    /// Reference source positions, formatted as PATH:LINE:COLUMN.  Use PATH:LINE:COLUMN-COLUMN
+    /// to find the references that overlap a range of columns, or PATH:LINE to find the
+    /// references that overlap a line.  The symbols that are visible at each reference are
+    /// listed, regardless of the symbol of the reference, so that a partially typed identifier
+    /// can be completed.
+    #[clap(
+        value_name = "SOURCE_POSITION",
+        required = true,
+        value_hint = ValueHint::AnyPath,
+        value_parser,
+    )]
+    pub references: Vec<SourcePosition>,
+
+    /// Unit of the columns of the source positions.
+    #[clap(long, value_enum, default_value_t = PositionEncoding::Grapheme)]
+    pub position_encoding: PositionEncoding,
+
+    /// Maximum number of phases of path stitching, both within the file of the reference and
+    /// through the paths stored for other files.  Symbols that are only reachable through longer
+    /// chains of scopes and imports are not found.
+    #[clap(long, value_name = "N", default_value_t = 64)]
+    pub depth: usize,
+}
+
+impl Complete {
+    pub fn run(self, querier: &mut Querier) -> anyhow::Result<()> {
+        let cancellation_flag = NoCancellation;
+        let mut sources = SourceReader::new();
+        for mut reference in self.references {
+            reference.encoding = self.position_encoding;
+            // files may be missing if the database was copied from another machine
+            if reference.path.exists() {
+                reference.canonicalize()?;
+            }
+
+            let results = querier.completions(reference.clone(), self.depth, &cancellation_flag)?;
+            let numbered = results.len() > 1;
+            let indent = if numbered { 6 } else { 0 };
+            if numbered {
+                println!("found {} references at position", results.len());
+            }
+            for (
+                idx,
+                CompletionResult {
+                    source: reference,
+                    completions,
+                    ..
+                },
+            ) in results.into_iter().enumerate()
+            {
+                print_reference(&mut sources, querier, numbered, idx, &reference, indent);
+                match completions.len() {
+                    0 => println!("{}has no completions", " ".repeat(indent)),
+                    1 => println!("{}has completion", " ".repeat(indent)),
+                    n => println!("{}has {} completions", " ".repeat(indent), n),
                 }
-                for definition in definitions.into_iter() {
+                for Completion { name, targets } in completions {
+                    println!("{}  {}", " ".repeat(indent), name);
+                    for target in targets {
+                        print_excerpt(&mut sources, querier, &target.span, indent + 4);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+pub struct ResolveFile {
+    /// Path of the file whose references are resolved.
+    #[clap(value_name = "SOURCE_PATH", value_hint = ValueHint::AnyPath)]
+    pub source_path: PathBuf,
+
+    /// Format of the report.  Positions are reported as one-based lines and columns.
+    #[clap(long, value_enum, default_value_t = ReportFormat::Text)]
+    pub format: ReportFormat,
+}
+
+/// Format of reports.
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    Text,
+    Json,
+}
+
+impl ResolveFile {
+    pub fn run(self, querier: &mut Querier) -> anyhow::Result<()> {
+        let cancellation_flag = NoCancellation;
+        let mut source_path = self.source_path;
+        // files may be missing if the database was copied from another machine
+        if source_path.exists() {
+            source_path = source_path.canonicalize()?;
+        }
+
+        let results = querier.file_definitions(&source_path, &cancellation_flag)?;
+        match self.format {
+            ReportFormat::Text => {
+                for result in &results {
                     println!(
-                        "{}",
-                        Excerpt::from_source(
-                            &definition.path,
-                            file_reader.get(&definition.path).unwrap_or_default(),
-                            definition.first_line(),
-                            definition.first_line_column_range(),
-                            indent
-                        )
+                        "{}:{}:{}: {}",
+                        result.source.path.display(),
+                        result.source.first_line() + 1,
+                        result.source.span.start.column.grapheme_offset + 1,
+                        match result.targets.len() {
+                            0 => "no definitions".to_string(),
+                            1 => "1 definition".to_string(),
+                            n => format!("{} definitions", n),
+                        }
                     );
+                    for target in &result.targets {
+                        println!(
+                            "  {}:{}:{}",
+                            target.span.path.display(),
+                            target.span.first_line() + 1,
+                            target.span.span.start.column.grapheme_offset + 1
+                        );
+                    }
                 }
+                let resolved = results.iter().filter(|r| !r.targets.is_empty()).count();
+                println!("resolved {} of {} references", resolved, results.len());
+            }
+            ReportFormat::Json => {
+                let references = results
+                    .iter()
+                    .map(|result| {
+                        let mut reference = span_to_json(&result.source);
+                        reference["definitions"] = result
+                            .targets
+                            .iter()
+                            .map(|target| span_to_json(&target.span))
+                            .collect();
+                        reference["shadowed"] = result
+                            .shadowed
+                            .iter()
+                            .map(|shadowed| span_to_json(&shadowed.target.span))
+                            .collect();
+                        reference
+                    })
+                    .collect::<Vec<_>>();
+                let report = serde_json::json!({
+                    "path": source_path.to_string_lossy(),
+                    "references": references,
+                });
+                println!("{}", serde_json::to_string_pretty(&report)?);
             }
         }
         Ok(())
     }
 }
 
+pub(crate) fn span_to_json(span: &SourceSpan) -> serde_json::Value {
+    serde_json::json!({
+        "path": span.path.to_string_lossy(),
+        "start_line": span.span.start.line + 1,
+        "start_column": span.span.start.column.grapheme_offset + 1,
+        "end_line": span.span.end.line + 1,
+        "end_column": span.span.end.column.grapheme_offset + 1,
+    })
+}
+
+fn print_reference(
+    sources: &mut SourceReader,
+    querier: &Querier,
+    numbered: bool,
+    idx: usize,
+    reference: &SourceSpan,
+    indent: usize,
+) {
+    if numbered {
+        println!("{:4}: queried reference", idx);
+    } else {
+        println!("queried reference");
+    }
+    print_excerpt(sources, querier, reference, indent);
+}
+
+fn print_definition_count(count: usize, indent: usize) {
+    match count {
+        0 => println!("{}has no definitions", " ".repeat(indent)),
+        1 => println!("{}has definition", " ".repeat(indent)),
+        n => println!("{}has {} definitions", " ".repeat(indent), n),
+    }
+}
+
+fn print_excerpt(sources: &mut SourceReader, querier: &Querier, span: &SourceSpan, indent: usize) {
+    println!(
+        "{}",
+        Excerpt::from_source(
+            &span.path,
+            sources.get(querier, &span.path),
+            span.first_line(),
+            span.first_line_column_range(),
+            indent
+        )
+    );
+}
+
+/// Reads the sources of files for excerpts.  Files that cannot be read from the file system, for
+/// example because the database was copied from another machine, are read from the sources stored
+/// in the database, if they were indexed with `--store-sources`.
+struct SourceReader {
+    file_reader: FileReader,
+    stored: HashMap<PathBuf, String>,
+}
+
+impl SourceReader {
+    fn new() -> Self {
+        Self {
+            file_reader: FileReader::new(),
+            stored: HashMap::new(),
+        }
+    }
+
+    fn get(&mut self, querier: &Querier, path: &Path) -> &str {
+        if self.file_reader.get(path).is_ok() {
+            return self.file_reader.get(path).unwrap_or_default();
+        }
+        self.stored.entry(path.to_path_buf()).or_insert_with(|| {
+            querier
+                .stored_source(path)
+                .ok()
+                .flatten()
+                .unwrap_or_default()
+        })
+    }
+}
+
+/// Writes the events of path stitching as JSON lines.  Tracing stops at the first write error.
+struct JsonLinesTracer {
+    writer: Option<BufWriter<File>>,
+}
+
+impl JsonLinesTracer {
+    fn new(file: File) -> Self {
+        Self {
+            writer: Some(BufWriter::new(file)),
+        }
+    }
+}
+
+impl StitchingTracer for JsonLinesTracer {
+    fn trace(&mut self, graph: &StackGraph, partials: &mut PartialPaths, event: StitchingEvent) {
+        let writer = match &mut self.writer {
+            Some(writer) => writer,
+            None => return,
+        };
+        let value = match event {
+            StitchingEvent::PathQueued { path } => serde_json::json!({
+                "event": "path_queued",
+                "path": path.display(graph, partials).to_string(),
+            }),
+            StitchingEvent::CandidatesFound { path, count } => serde_json::json!({
+                "event": "candidates_found",
+                "path": path.display(graph, partials).to_string(),
+                "count": count,
+            }),
+            StitchingEvent::PathExtended {
+                path,
+                extension,
+                extended,
+            } => {
+                // the edges that the extension added, with the rules that created them
+                let edges = extended
+                    .graph_edges(graph, partials)
+                    .into_iter()
+                    .skip(path.edges.len())
+                    .map(|edge| {
+                        let debug_info = graph
+                            .edge_debug_info(edge.source, edge.sink)
+                            .into_iter()
+                            .flat_map(|debug_info| debug_info.iter())
+                            .map(|entry| {
+                                (
+                                    graph[entry.key].to_string(),
+                                    serde_json::Value::from(&graph[entry.value]),
+                                )
+                            })
+                            .collect::<serde_json::Map<_, _>>();
+                        serde_json::json!({
+                            "source": graph[edge.source].display(graph).to_string(),
+                            "sink": graph[edge.sink].display(graph).to_string(),
+                            "debug_info": debug_info,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                serde_json::json!({
+                    "event": "path_extended",
+                    "path": path.display(graph, partials).to_string(),
+                    "extension": extension.display(graph, partials).to_string(),
+                    "extended": extended.display(graph, partials).to_string(),
+                    "edges": edges,
+                })
+            }
+            StitchingEvent::PathRejected { path, reason } => {
+                let mut value = serde_json::json!({
+                    "event": "path_rejected",
+                    "path": path.display(graph, partials).to_string(),
+                    "reason": reason.to_string(),
+                });
+                if let RejectionReason::IncompatibleExtension { extension, .. } = reason {
+                    value["extension"] = extension.display(graph, partials).to_string().into();
+                }
+                value
+            }
+        };
+        if let Err(err) = writeln!(writer, "{}", value) {
+            eprintln!("Stopped writing trace: {}", err);
+            self.writer = None;
+        }
+    }
+}
+
 pub struct Querier<'a> {
     db: &'a mut SQLiteReader,
     reporter: &'a dyn Reporter,
+    max_results: Option<usize>,
+    within: Vec<PathBuf>,
+    explain_rules: bool,
+    tracer: Option<Box<dyn StitchingTracer + Send>>,
+    stats: StitchingStats,
 }
 
 impl<'a> Querier<'a> {
     pub fn new(db: &'a mut SQLiteReader, reporter: &'a dyn Reporter) -> Self {
-        Self { db, reporter }
+        Self {
+            db,
+            reporter,
+            max_results: None,
+            within: Vec::new(),
+            explain_rules: false,
+            tracer: None,
+            stats: StitchingStats::default(),
+        }
+    }
+
+    /// Returns the number of paths that were pruned during path stitching, summed over all
+    /// queries of this querier.
+    pub fn stats(&self) -> StitchingStats {
+        self.stats
+    }
+
+    /// Sets the tracer that receives the events of path stitching for all queries, or disables
+    /// tracing if `None` is given.
+    pub fn set_tracer(&mut self, tracer: Option<Box<dyn StitchingTracer + Send>>) {
+        self.tracer = tracer;
+    }
+
+    /// Sets the maximum number of definitions that are returned for each reference.  Path
+    /// stitching stops as soon as enough complete paths are found, instead of exploring all paths.
+    /// The paths that are found are checked for shadowing and ranked before they are limited.
+    /// Because not all paths are explored, the definitions that are found are not necessarily the
+    /// best ranked ones, and definitions can be missing from the shadowing checks.  By default,
+    /// all paths are found.
+    pub fn set_max_results(&mut self, max_results: Option<usize>) {
+        self.max_results = max_results;
+    }
+
+    /// Restricts the definitions that are found to those in files under the given directories.
+    /// Paths to other definitions are ignored as soon as they are found, so they do not count
+    /// towards the [maximum number of results][Self::set_max_results], and do not shadow other
+    /// definitions.  By default, or if no directories are given, definitions in all files are
+    /// found.
+    pub fn set_within(&mut self, directories: Vec<PathBuf>) {
+        self.within = directories;
+    }
+
+    /// Sets whether the [rules][QueryTarget::rules] of the definitions that are found are
+    /// computed.  By default, they are not.
+    pub fn set_explain_rules(&mut self, explain_rules: bool) {
+        self.explain_rules = explain_rules;
+    }
+
+    /// Returns the source of the file stored in the database, if it was indexed with stored
+    /// sources.
+    pub fn stored_source(&self, path: &Path) -> Result<Option<String>> {
+        Ok(self.db.source_for_file(&path.to_string_lossy())?)
     }
 
     pub fn definitions(
@@ -148,94 +794,306 @@ impl<'a> Querier<'a> {
         reference: SourcePosition,
         cancellation_flag: &dyn CancellationFlag,
     ) -> Result<Vec<QueryResult>> {
-        let log_path = PathBuf::from(reference.to_string());
+        let results = self.resolve(reference, cancellation_flag)?;
+        Ok(self.query_results(results))
+    }
 
-        let mut file_reader = FileReader::new();
-        let tag = file_reader.get(&reference.path).ok().map(sha1);
-        match self
-            .db
-            .status_for_file(&reference.path.to_string_lossy(), tag.as_ref())?
-        {
-            FileStatus::Indexed => {}
-            _ => {
-                self.reporter.started(&log_path);
-                self.reporter.failed(&log_path, "file not indexed", None);
-                return Ok(Vec::default());
+    /// Finds the definitions of all references in the given file.  References are returned in the
+    /// order in which they appear in the file.
+    pub fn file_definitions(
+        &mut self,
+        path: &Path,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Vec<QueryResult>> {
+        let results = self.resolve_file(path, cancellation_flag)?;
+        Ok(self.query_results(results))
+    }
+
+    /// Finds the definitions of all references in the given file, together with the information
+    /// returned by [`definition_info`][Self::definition_info].  References are returned in the
+    /// order in which they appear in the file.
+    pub fn file_definition_info(
+        &mut self,
+        path: &Path,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Vec<InfoResult>> {
+        let results = self.resolve_file(path, cancellation_flag)?;
+        self.info_results(results, cancellation_flag)
+    }
+
+    fn resolve_file(
+        &mut self,
+        path: &Path,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Vec<Resolution>> {
+        self.resolve_in_file(
+            path,
+            path,
+            |graph| {
+                let mut references = graph
+                    .get_file(&path.to_string_lossy())
+                    .into_iter()
+                    .flat_map(|file| graph.nodes_for_file(file))
+                    .filter(|node| graph[*node].is_reference())
+                    .filter_map(|node| Some((node, graph.source_info(node)?.span.clone())))
+                    .collect::<Vec<_>>();
+                references
+                    .sort_by_key(|(_, span)| (span.start.line, span.start.column.grapheme_offset));
+                references
+            },
+            "no references in file",
+            cancellation_flag,
+        )
+    }
+
+    fn query_results(&mut self, results: Vec<Resolution>) -> Vec<QueryResult> {
+        let (graph, _, _) = self.db.get();
+        results
+            .into_iter()
+            .map(|resolution| QueryResult {
+                source: resolution.reference,
+                symbol: resolution.symbol,
+                targets: resolution
+                    .definitions
+                    .into_iter()
+                    .zip(resolution.rules)
+                    .filter_map(|(node, rules)| {
+                        Some(QueryTarget {
+                            rules,
+                            ..query_target(graph, node)?
+                        })
+                    })
+                    .collect(),
+                shadowed: resolution
+                    .shadowed
+                    .into_iter()
+                    .filter_map(|(node, shadowed_by)| {
+                        Some(ShadowedTarget {
+                            target: query_target(graph, node)?,
+                            shadowed_by: query_target(graph, shadowed_by)?,
+                        })
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Finds the definitions of the references at the given position, together with their syntax
+    /// type, qualified name, and enclosing definitions.  Qualified names are computed from the
+    /// partial paths in the file of each definition.
+    pub fn definition_info(
+        &mut self,
+        reference: SourcePosition,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Vec<InfoResult>> {
+        let results = self.resolve(reference, cancellation_flag)?;
+        self.info_results(results, cancellation_flag)
+    }
+
+    fn info_results(
+        &mut self,
+        results: Vec<Resolution>,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Vec<InfoResult>> {
+        let (graph, partials, _) = self.db.get();
+        let mut file_paths = HashMap::new();
+        let mut info_results = Vec::new();
+        for resolution in results {
+            let mut targets = Vec::new();
+            for node in resolution.definitions {
+                if let Some(info) =
+                    describe_definition(graph, partials, &mut file_paths, node, cancellation_flag)?
+                {
+                    targets.push(info);
+                }
             }
+            info_results.push(InfoResult {
+                source: resolution.reference,
+                symbol: resolution.symbol,
+                targets,
+            });
         }
+        Ok(info_results)
+    }
 
-        self.reporter.started(&log_path);
+    /// Returns the definitions in the given file, together with the information returned by
+    /// [`definition_info`][Self::definition_info].  Definitions are returned in the order in
+    /// which they appear in the file, and definitions without a source span are left out.
+    pub fn definitions_in_file(
+        &mut self,
+        path: &Path,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Vec<DefinitionInfo>> {
+        self.db.load_graph_for_file(&path.to_string_lossy())?;
+        let (graph, partials, _) = self.db.get();
+        let mut definitions = graph
+            .get_file(&path.to_string_lossy())
+            .into_iter()
+            .flat_map(|file| graph.nodes_for_file(file))
+            .filter(|node| graph[*node].is_definition())
+            .filter_map(|node| Some((node, graph.source_info(node)?.span.clone())))
+            .collect::<Vec<_>>();
+        definitions.sort_by_key(|(_, span)| (span.start.line, span.start.column.grapheme_offset));
+        let mut file_paths = HashMap::new();
+        let mut infos = Vec::new();
+        for (node, _) in definitions {
+            if let Some(info) =
+                describe_definition(graph, partials, &mut file_paths, node, cancellation_flag)?
+            {
+                infos.push(info);
+            }
+        }
+        Ok(infos)
+    }
 
-        self.db
-            .load_graph_for_file(&reference.path.to_string_lossy())?;
+    /// Resolves the references at the given position, and returns the definitions found for each.
+    /// Definitions are ranked, such that definitions in the same file as the reference come first,
+    /// and definitions with shorter paths come before those with longer paths.
+    fn resolve(
+        &mut self,
+        reference: SourcePosition,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Vec<Resolution>> {
+        let log_path = PathBuf::from(reference.to_string());
+        self.resolve_in_file(
+            &reference.path,
+            &log_path,
+            |graph| reference.iter_references(graph).collect(),
+            "no references at location",
+            cancellation_flag,
+        )
+    }
+
+    /// Resolves the references in the given file that are selected by the `references` closure,
+    /// which is called once the graph of the file is loaded.
+    fn resolve_in_file<F>(
+        &mut self,
+        path: &Path,
+        log_path: &Path,
+        references: F,
+        no_references_message: &str,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Vec<Resolution>>
+    where
+        F: FnOnce(&StackGraph) -> Vec<(Handle<Node>, Span)>,
+    {
+        if !self.load_indexed_file(path, log_path)? {
+            return Ok(Vec::default());
+        }
         let (graph, _, _) = self.db.get();
 
-        let starting_nodes = reference.iter_references(graph).collect::<Vec<_>>();
+        let starting_nodes = references(graph);
         if starting_nodes.is_empty() {
             self.reporter
-                .cancelled(&log_path, "no references at location", None);
+                .cancelled(log_path, no_references_message, None);
             return Ok(Vec::default());
         }
 
         let mut result = Vec::new();
         for (node, span) in starting_nodes {
             let reference_span = SourceSpan {
-                path: reference.path.clone(),
+                path: path.to_path_buf(),
                 span,
             };
 
             let mut reference_paths = Vec::new();
-            if let Err(err) = ForwardPartialPathStitcher::find_all_complete_partial_paths(
-                self.db,
-                std::iter::once(node),
-                &cancellation_flag,
-                |_g, _ps, p| {
-                    reference_paths.push(p.clone());
-                },
-            ) {
-                self.reporter.failed(&log_path, "query timed out", None);
+            let mut stitcher = {
+                let (graph, partials, _) = self.db.get();
+                ForwardPartialPathStitcher::from_starting_nodes(
+                    graph,
+                    partials,
+                    std::iter::once(node),
+                )
+            };
+            stitcher.set_tracer(self.tracer.take());
+            let mut error = None;
+            while !stitcher.is_complete()
+                && !matches!(self.max_results, Some(max_results) if reference_paths.len() >= max_results)
+            {
+                match stitcher.next_batch(self.db, &cancellation_flag) {
+                    Ok(paths) => {
+                        let (graph, _, _) = self.db.get();
+                        let within = &self.within;
+                        reference_paths.extend(
+                            paths
+                                .into_iter()
+                                .filter(|path| is_within(graph, path.end_node, within)),
+                        )
+                    }
+                    Err(err) => {
+                        error = Some(err);
+                        break;
+                    }
+                }
+            }
+            self.tracer = stitcher.take_tracer();
+            let stats = stitcher.stats();
+            self.stats.cyclic_paths += stats.cyclic_paths;
+            self.stats.similar_paths += stats.similar_paths;
+            if let Some(err) = error {
+                self.reporter.failed(log_path, "query timed out", None);
                 return Err(err.into());
             }
-
             let (graph, partials, _) = self.db.get();
             let mut actual_paths = Vec::new();
+            let mut shadowed = Vec::new();
             for reference_path in &reference_paths {
                 if let Err(err) = cancellation_flag.check("shadowing") {
-                    self.reporter.failed(&log_path, "query timed out", None);
+                    self.reporter.failed(log_path, "query timed out", None);
                     return Err(err.into());
                 }
-                if reference_paths
+                match reference_paths
                     .iter()
-                    .all(|other| !other.shadows(partials, reference_path))
+                    .find(|other| other.shadows(partials, reference_path))
                 {
-                    actual_paths.push(reference_path.clone());
+                    Some(other) => shadowed.push((reference_path.end_node, other.end_node)),
+                    None => actual_paths.push(reference_path.clone()),
                 }
             }
 
+            let reference_file = graph[node].file();
+            actual_paths.sort_by_key(|path| {
+                (
+                    graph[path.end_node].file() != reference_file,
+                    path.edges.len(),
+                )
+            });
+            // shadowed definitions are rejected before the results are limited, so that the
+            // limit does not keep a definition that is shadowed by one that is cut off
+            if let Some(max_results) = self.max_results {
+                actual_paths.truncate(max_results);
+            }
+            let explain_rules = self.explain_rules;
+            let rules = actual_paths
+                .iter()
+                .map(|path| {
+                    if explain_rules {
+                        path_rules(graph, partials, path)
+                    } else {
+                        Vec::new()
+                    }
+                })
+                .collect::<Vec<_>>();
             let definitions = actual_paths
                 .into_iter()
-                .filter_map(|path| {
-                    let span = match graph.source_info(path.end_node) {
-                        Some(p) => p.span.clone(),
-                        None => return None,
-                    };
-                    let path = match graph[path.end_node].id().file() {
-                        Some(f) => PathBuf::from(graph[f].name()),
-                        None => return None,
-                    };
-                    Some(SourceSpan { path, span })
-                })
+                .map(|path| path.end_node)
                 .collect::<Vec<_>>();
 
-            result.push(QueryResult {
-                source: reference_span,
-                targets: definitions,
+            result.push(Resolution {
+                reference: reference_span,
+                symbol: graph[node].symbol().map(|symbol| graph[symbol].to_string()),
+                definitions,
+                rules,
+                shadowed,
             });
         }
 
-        let count: usize = result.iter().map(|r| r.targets.len()).sum();
+        let count: usize = result
+            .iter()
+            .map(|resolution| resolution.definitions.len())
+            .sum();
         self.reporter.succeeded(
-            &log_path,
+            log_path,
             &format!(
                 "found {} definitions for {} references",
                 count,
@@ -246,6 +1104,297 @@ impl<'a> Querier<'a> {
 
         Ok(result)
     }
+
+    /// Loads the graph of the given file, and returns whether it was loaded.  Files that are not
+    /// indexed, or that changed since they were indexed, are reported as failed.
+    fn load_indexed_file(&mut self, path: &Path, log_path: &Path) -> Result<bool> {
+        let mut file_reader = FileReader::new();
+        let tag = file_reader.get(path).ok().map(sha1);
+        match self
+            .db
+            .status_for_file(&path.to_string_lossy(), tag.as_ref())?
+        {
+            FileStatus::Indexed => {}
+            _ => {
+                self.reporter.started(log_path);
+                self.reporter.failed(log_path, "file not indexed", None);
+                return Ok(false);
+            }
+        }
+
+        self.reporter.started(log_path);
+
+        self.db.load_graph_for_file(&path.to_string_lossy())?;
+        Ok(true)
+    }
+
+    /// Finds the symbols that are visible at the references at the given position, together with
+    /// their definitions.  Paths are stitched from the scopes in which the references are
+    /// resolved, leaving out the symbols of the references themselves, so that any definition
+    /// that such a path reaches without further symbols is visible at the reference.  Stitching
+    /// runs for at most `depth` phases within the file of the position, and for at most `depth`
+    /// phases through the paths stored for the root node and other files.
+    pub fn completions(
+        &mut self,
+        reference: SourcePosition,
+        depth: usize,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Vec<CompletionResult>> {
+        let log_path = PathBuf::from(reference.to_string());
+        if !self.load_indexed_file(&reference.path, &log_path)? {
+            return Ok(Vec::default());
+        }
+        let (graph, _, _) = self.db.get();
+
+        let references = reference.iter_references(graph).collect::<Vec<_>>();
+        if references.is_empty() {
+            self.reporter
+                .cancelled(&log_path, "no references at location", None);
+            return Ok(Vec::default());
+        }
+
+        let mut result = Vec::new();
+        for (node, span) in references {
+            let paths = match self.complete_reference(node, depth, cancellation_flag) {
+                Ok(paths) => paths,
+                Err(err) => {
+                    self.reporter.failed(&log_path, "query timed out", None);
+                    return Err(err);
+                }
+            };
+
+            // keep the shortest path to each definition, and rank the definitions like those of
+            // query results
+            let (graph, _, _) = self.db.get();
+            let mut definitions: HashMap<Handle<Node>, usize> = HashMap::new();
+            for path in paths {
+                if !is_within(graph, path.end_node, &self.within) {
+                    continue;
+                }
+                let length = definitions.entry(path.end_node).or_insert(usize::MAX);
+                *length = (*length).min(path.edges.len());
+            }
+            let reference_file = graph[node].file();
+            let mut definitions = definitions.into_iter().collect::<Vec<_>>();
+            definitions.sort_by_key(|(definition, length)| {
+                (
+                    graph[*definition].file() != reference_file,
+                    *length,
+                    *definition,
+                )
+            });
+
+            let mut completions: BTreeMap<String, Vec<QueryTarget>> = BTreeMap::new();
+            for (definition, _) in definitions {
+                let name = match graph[definition].symbol() {
+                    Some(symbol) => graph[symbol].to_string(),
+                    None => continue,
+                };
+                if let Some(target) = query_target(graph, definition) {
+                    completions.entry(name).or_default().push(target);
+                }
+            }
+
+            result.push(CompletionResult {
+                source: SourceSpan {
+                    path: reference.path.clone(),
+                    span,
+                },
+                symbol: graph[node].symbol().map(|symbol| graph[symbol].to_string()),
+                completions: completions
+                    .into_iter()
+                    .map(|(name, targets)| Completion { name, targets })
+                    .collect(),
+            });
+        }
+
+        let count: usize = result.iter().map(|result| result.completions.len()).sum();
+        self.reporter.succeeded(
+            &log_path,
+            &format!(
+                "found {} completions for {} references",
+                count,
+                result.len()
+            ),
+            None,
+        );
+
+        Ok(result)
+    }
+
+    /// Returns the paths from the scopes of the reference to the definitions that are visible at
+    /// the reference.  The paths are stitched from the edges of the file of the reference until
+    /// they reach the root node, and from the stored partial paths after that.
+    fn complete_reference(
+        &mut self,
+        reference: Handle<Node>,
+        depth: usize,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Vec<PartialPath>> {
+        let mut completions = Vec::new();
+
+        let (graph, partials, _) = self.db.get();
+        let (mut root_paths, initial_paths): (Vec<_>, Vec<_>) = graph
+            .outgoing_edges(reference)
+            .map(|edge| PartialPath::from_node(graph, partials, edge.sink))
+            .partition(|path| graph[path.end_node].is_root());
+        let mut stitcher =
+            ForwardPartialPathStitcher::from_partial_paths(graph, partials, initial_paths);
+        for _ in 0..depth {
+            if stitcher.is_complete() {
+                break;
+            }
+            cancellation_flag.check("completing in file")?;
+            stitcher.process_next_phase(
+                &mut GraphEdgeCandidates::new(graph, partials, None),
+                |graph, _, path| !graph[path.end_node].is_root() && is_completion_prefix(path),
+            );
+            for path in stitcher.previous_phase_partial_paths() {
+                if is_completion(graph, partials, path) {
+                    completions.push(path.clone());
+                } else if graph[path.end_node].is_root() && is_completion_prefix(path) {
+                    root_paths.push(path.clone());
+                }
+            }
+        }
+
+        let mut stitcher = {
+            let (graph, partials, _) = self.db.get();
+            ForwardPartialPathStitcher::from_partial_paths(graph, partials, root_paths)
+        };
+        stitcher.set_tracer(self.tracer.take());
+        let mut candidates = CompletionCandidates { db: self.db };
+        let mut error: Option<QueryError> = None;
+        for _ in 0..depth {
+            if stitcher.is_complete() {
+                break;
+            }
+            if let Err(err) = cancellation_flag.check("completing from root") {
+                error = Some(err.into());
+                break;
+            }
+            if let Err(err) = stitcher
+                .previous_phase_partial_paths()
+                .try_for_each(|path| candidates.load_forward_candidates(path, &cancellation_flag))
+            {
+                error = Some(err.into());
+                break;
+            }
+            stitcher.process_next_phase(&mut candidates, |_, _, path| is_completion_prefix(path));
+            let (graph, partials, _) = candidates.get_graph_partials_and_db();
+            for path in stitcher.previous_phase_partial_paths() {
+                if is_completion(graph, partials, path) {
+                    completions.push(path.clone());
+                }
+            }
+        }
+        self.tracer = stitcher.take_tracer();
+        let stats = stitcher.stats();
+        self.stats.cyclic_paths += stats.cyclic_paths;
+        self.stats.similar_paths += stats.similar_paths;
+        if let Some(err) = error {
+            return Err(err);
+        }
+
+        Ok(completions)
+    }
+}
+
+/// Candidates for the paths of completion queries, which are loaded from the database.  Paths
+/// that end at the root node, and whose symbol stack ends in the symbols that are not known yet,
+/// are extended with all stored root paths whose precondition is compatible with the known
+/// symbols, including those that continue with any other symbols.
+struct CompletionCandidates<'a> {
+    db: &'a mut SQLiteReader,
+}
+
+impl CompletionCandidates<'_> {
+    /// Returns the known symbols of the symbol stack of the path, if the path ends at the root
+    /// node, and the symbol stack is not completely known.
+    fn root_prefix(&mut self, path: &PartialPath) -> Option<SymbolStackPrefix> {
+        let (graph, partials, _) = self.db.get();
+        if !graph[path.end_node].is_root() || !path.symbol_stack_postcondition.has_variable() {
+            return None;
+        }
+        Some(SymbolStackPrefix {
+            symbols: path
+                .symbol_stack_postcondition
+                .iter(partials)
+                .map(|symbol| Some(graph[symbol.symbol].to_string()))
+                .collect(),
+        })
+    }
+}
+
+impl ForwardCandidates<Handle<PartialPath>, PartialPath, Database, StorageError>
+    for CompletionCandidates<'_>
+{
+    fn load_forward_candidates(
+        &mut self,
+        path: &PartialPath,
+        cancellation_flag: &dyn stack_graphs::CancellationFlag,
+    ) -> std::result::Result<(), StorageError> {
+        self.db
+            .load_partial_path_extensions(path, cancellation_flag)?;
+        if let Some(prefix) = self.root_prefix(path) {
+            // the loaded paths are looked up again when the candidates are requested
+            self.db.find_candidate_partial_paths_from_root_with_prefix(
+                &prefix,
+                cancellation_flag,
+                &mut Vec::new(),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn get_forward_candidates<R>(&mut self, path: &PartialPath, result: &mut R)
+    where
+        R: std::iter::Extend<Handle<PartialPath>>,
+    {
+        let mut candidates = Vec::new();
+        self.db.get_forward_candidates(path, &mut candidates);
+        if let Some(prefix) = self.root_prefix(path) {
+            let (graph, partials, db) = self.db.get();
+            db.find_candidate_partial_paths_from_root_with_prefix(
+                graph,
+                partials,
+                &prefix,
+                &mut candidates,
+            );
+        }
+        // paths whose precondition consists of exactly the known symbols are found twice
+        candidates.sort();
+        candidates.dedup();
+        result.extend(candidates);
+    }
+
+    fn get_graph_partials_and_db(&mut self) -> (&StackGraph, &mut PartialPaths, &Database) {
+        let (graph, partials, db) = self.db.get();
+        (graph, partials, db)
+    }
+}
+
+/// Returns whether the path pops at most one symbol that it did not push itself, which is the
+/// symbol that is being completed.
+fn is_completion_prefix(path: &PartialPath) -> bool {
+    path.symbol_stack_precondition.len() <= 1
+}
+
+/// Returns whether the path ends at a definition of the symbol that is being completed, and
+/// leaves none of the symbols that it pushed on the stack.
+fn is_completion(graph: &StackGraph, partials: &mut PartialPaths, path: &PartialPath) -> bool {
+    if !graph[path.end_node].is_definition()
+        || path.symbol_stack_precondition.len() != 1
+        || !path.symbol_stack_postcondition.can_match_empty()
+    {
+        return false;
+    }
+    let symbol = path
+        .symbol_stack_precondition
+        .iter(partials)
+        .next()
+        .map(|symbol| symbol.symbol);
+    symbol.is_some() && symbol == graph[path.end_node].symbol()
 }
 
 #[derive(Debug, Error)]
@@ -255,7 +1404,17 @@ pub enum QueryError {
     #[error("failed to read file")]
     ReadError(#[from] std::io::Error),
     #[error(transparent)]
-    StorageError(#[from] stack_graphs::storage::StorageError),
+    StorageError(StorageError),
+}
+
+impl From<StorageError> for QueryError {
+    fn from(value: StorageError) -> Self {
+        match value {
+            // loads from storage can be cancelled too, which is reported like other cancellations
+            StorageError::Cancelled(at) => Self::Cancelled(at),
+            err => Self::StorageError(err),
+        }
+    }
 }
 
 impl From<stack_graphs::CancellationError> for QueryError {
@@ -272,7 +1431,221 @@ impl From<crate::CancellationError> for QueryError {
 
 pub struct QueryResult {
     pub source: SourceSpan,
-    pub targets: Vec<SourceSpan>,
+    /// The symbol of the reference.
+    pub symbol: Option<String>,
+    /// The definitions of the reference, ranked such that definitions in the same file as the
+    /// reference come first, and definitions with shorter paths come before longer ones.
+    pub targets: Vec<QueryTarget>,
+    /// The candidate definitions that were rejected because they are shadowed by another one.
+    pub shadowed: Vec<ShadowedTarget>,
+}
+
+/// A candidate definition that was rejected because the path to it is shadowed by the path to
+/// another definition.
+pub struct ShadowedTarget {
+    pub target: QueryTarget,
+    pub shadowed_by: QueryTarget,
+}
+
+/// The definitions found for a reference, before they are converted to query results.
+struct Resolution {
+    reference: SourceSpan,
+    symbol: Option<String>,
+    definitions: Vec<Handle<Node>>,
+    /// The rules traversed by the path to each definition, in the same order as the definitions.
+    rules: Vec<Vec<String>>,
+    /// Pairs of rejected definitions and the definitions that shadow them.
+    shadowed: Vec<(Handle<Node>, Handle<Node>)>,
+}
+
+/// A definition found by a query.
+pub struct QueryTarget {
+    pub span: SourceSpan,
+    /// The metadata attached to the definition node, as key-value pairs.
+    pub metadata: Vec<(String, String)>,
+    /// The TSG stanzas that created the edges of the path to the definition, formatted as
+    /// `TSG_PATH:LINE`, in the order in which the path traverses them.  Empty unless the querier
+    /// [explains rules][Querier::set_explain_rules], and for definitions that are not the result
+    /// of a query, such as shadowing or enclosing definitions.
+    pub rules: Vec<String>,
+}
+
+/// The definitions found for a reference, with information about each definition.
+pub struct InfoResult {
+    pub source: SourceSpan,
+    /// The symbol of the reference.
+    pub symbol: Option<String>,
+    pub targets: Vec<DefinitionInfo>,
+}
+
+/// The symbols that are visible at a reference, found by a completion query.
+pub struct CompletionResult {
+    pub source: SourceSpan,
+    /// The symbol of the reference.
+    pub symbol: Option<String>,
+    /// The visible symbols, ordered by name.
+    pub completions: Vec<Completion>,
+}
+
+/// A symbol that is visible at a reference.
+pub struct Completion {
+    pub name: String,
+    /// The definitions of the symbol, ranked such that definitions in the same file as the
+    /// reference come first, and definitions with shorter paths come before longer ones.
+    pub targets: Vec<QueryTarget>,
+}
+
+/// Information about a definition found by a query.
+pub struct DefinitionInfo {
+    pub target: QueryTarget,
+    /// The symbol of the definition.
+    pub symbol: Option<String>,
+    /// The syntax type of the definition, if the TSG rules set it.
+    pub syntax_type: Option<String>,
+    /// The qualified name of the definition, if it can be reached from the root node.
+    pub qualified_name: Option<String>,
+    /// The components of the qualified name of the definition, outermost first.  Empty if the
+    /// definition has no qualified name.
+    pub qualified_name_components: Vec<String>,
+    /// The symbols and source spans of the definitions enclosing this one, outermost first.
+    pub enclosing_definitions: Vec<(String, SourceSpan)>,
+}
+
+/// Returns information about the definition, or `None` if it has no source span.  The partial
+/// paths of the file of the definition, which are needed for its qualified name, are cached in
+/// `file_paths`.
+fn describe_definition(
+    graph: &StackGraph,
+    partials: &mut PartialPaths,
+    file_paths: &mut HashMap<Handle<stack_graphs::graph::File>, Database>,
+    node: Handle<Node>,
+    cancellation_flag: &dyn CancellationFlag,
+) -> Result<Option<DefinitionInfo>> {
+    let target = match query_target(graph, node) {
+        Some(target) => target,
+        None => return Ok(None),
+    };
+    let syntax_type = graph
+        .source_info(node)
+        .and_then(|info| info.syntax_type.into_option())
+        .map(|syntax_type| graph[syntax_type].to_string());
+    let file = graph[node]
+        .file()
+        .expect("definition must belong to a file");
+    let paths = match file_paths.entry(file) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => {
+            let mut paths = Database::new();
+            ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+                graph,
+                partials,
+                file,
+                &cancellation_flag,
+                |g, ps, p| {
+                    paths.add_partial_path(g, ps, p.clone());
+                },
+            )?;
+            entry.insert(paths)
+        }
+    };
+    let name = qualified_name(graph, partials, paths, node);
+    Ok(Some(DefinitionInfo {
+        target,
+        symbol: graph[node].symbol().map(|symbol| graph[symbol].to_string()),
+        syntax_type,
+        qualified_name: name.as_ref().map(|name| name.display(graph).to_string()),
+        qualified_name_components: name
+            .as_ref()
+            .map(|name| {
+                name.components(graph, ".")
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        enclosing_definitions: name
+            .map(|name| {
+                name.enclosing_definitions
+                    .into_iter()
+                    .filter_map(|node| {
+                        let symbol = graph[graph[node].symbol()?].to_string();
+                        Some((symbol, query_target(graph, node)?.span))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }))
+}
+
+/// Returns whether the node belongs to a file under one of the directories, or whether no
+/// directories are given.
+fn is_within(graph: &StackGraph, node: Handle<Node>, directories: &[PathBuf]) -> bool {
+    if directories.is_empty() {
+        return true;
+    }
+    graph[node].file().is_some_and(|file| {
+        let path = Path::new(graph[file].name());
+        directories
+            .iter()
+            .any(|directory| path.starts_with(directory))
+    })
+}
+
+fn query_target(graph: &StackGraph, node: Handle<Node>) -> Option<QueryTarget> {
+    let span = match graph.source_info(node) {
+        Some(p) => p.span.clone(),
+        None => return None,
+    };
+    let file = match graph[node].id().file() {
+        Some(f) => PathBuf::from(graph[f].name()),
+        None => return None,
+    };
+    let metadata = graph
+        .node_metadata(node)
+        .map(|metadata| {
+            metadata
+                .iter()
+                .map(|e| (graph[e.key].to_string(), graph[e.value].to_string()))
+                .collect()
+        })
+        .unwrap_or_default();
+    Some(QueryTarget {
+        span: SourceSpan { path: file, span },
+        metadata,
+        rules: Vec::new(),
+    })
+}
+
+/// Returns the TSG stanzas that created the edges of the path, in the order in which the path
+/// traverses them.  Consecutive edges that were created by the same stanza are listed once, and
+/// edges without stanza debug info are skipped.
+fn path_rules(graph: &StackGraph, partials: &mut PartialPaths, path: &PartialPath) -> Vec<String> {
+    let mut rules: Vec<String> = Vec::new();
+    for edge in path.graph_edges(graph, partials) {
+        let debug_info = match graph.edge_debug_info(edge.source, edge.sink) {
+            Some(debug_info) => debug_info,
+            None => continue,
+        };
+        let value = |key: &str| {
+            debug_info
+                .iter()
+                .find(|entry| &graph[entry.key] == key)
+                .map(|entry| &graph[entry.value])
+        };
+        let (tsg_path, tsg_stanza) = match (value("tsg_path"), value("tsg_stanza")) {
+            (Some(tsg_path), Some(tsg_stanza)) => (tsg_path, tsg_stanza),
+            _ => continue,
+        };
+        let rule = format!(
+            "{}:{}",
+            tsg_path,
+            tsg_stanza.strip_prefix("line ").unwrap_or(tsg_stanza)
+        );
+        if rules.last() != Some(&rule) {
+            rules.push(rule);
+        }
+    }
+    rules
 }
 
 type Result<T> = std::result::Result<T, QueryError>;
@@ -8,14 +8,20 @@
 use clap::ArgGroup;
 use clap::Args;
 use clap::ValueHint;
+use serde_json::json;
 use stack_graphs::storage::FileEntry;
+use stack_graphs::storage::FileErrorEntry;
 use stack_graphs::storage::FileStatus;
 use stack_graphs::storage::SQLiteReader;
+use std::collections::BTreeMap;
 use std::path::Path;
 use std::path::PathBuf;
 
+use crate::cli::query::ReportFormat;
 use crate::cli::util::reporter::ConsoleReporter;
 use crate::cli::util::reporter::Reporter;
+use crate::cli::util::PathFilter;
+use crate::cli::util::PathGlob;
 
 #[derive(Args)]
 #[clap(group(
@@ -35,24 +41,116 @@ pub struct StatusArgs {
     #[clap(long, short = 'a')]
     pub all: bool,
 
+    /// Only show files for which indexing failed, with the kind and location of their errors.
+    #[clap(long)]
+    pub failed: bool,
+
+    /// Only show files that match the given glob. Globs are matched against paths relative to
+    /// the source path argument, or to the current directory if --all is given, and are
+    /// interpreted as for the index command. Can be given multiple times.
+    #[clap(long = "include", value_name = "GLOB")]
+    pub include: Vec<PathGlob>,
+
+    /// Do not show files that match the given glob. Can be given multiple times.
+    #[clap(long = "exclude", value_name = "GLOB")]
+    pub exclude: Vec<PathGlob>,
+
+    /// Format of the report. Both formats end with a summary of the number of files by status,
+    /// in total and by language, where languages are distinguished by file extension.
+    #[clap(long, value_enum, default_value_t = ReportFormat::Text)]
+    pub format: ReportFormat,
+
     #[clap(long, short = 'v')]
     pub verbose: bool,
 }
 
 impl StatusArgs {
     pub fn run(self, db_path: &Path) -> anyhow::Result<()> {
-        let reporter = self.get_reporter();
         let mut db = SQLiteReader::open(&db_path)?;
-        if self.all {
-            let mut files = db.list_all()?;
-            let mut entries = files.try_iter()?;
-            self.status(&mut entries, &reporter)?;
+        let filter = PathFilter {
+            include: self.include.clone(),
+            exclude: self.exclude.clone(),
+            respect_gitignore: false,
+        };
+        let source_paths = if self.all {
+            vec![None]
         } else {
-            for source_path in &self.source_paths {
-                let source_path = source_path.canonicalize()?;
-                let mut files = db.list_file_or_directory(&source_path)?;
-                let mut entries = files.try_iter()?;
-                self.status(&mut entries, &reporter)?;
+            self.source_paths
+                .iter()
+                .map(|p| p.canonicalize().map(Some))
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        let mut files = Vec::new();
+        let mut errors = Vec::new();
+        for source_path in source_paths {
+            let source_root = match &source_path {
+                Some(source_path) if source_path.is_dir() => source_path.clone(),
+                Some(source_path) => source_path
+                    .parent()
+                    .map(Path::to_path_buf)
+                    .unwrap_or_default(),
+                None => std::env::current_dir()?.canonicalize()?,
+            };
+            let is_selected =
+                |path: &Path| filter.matches(path.strip_prefix(&source_root).unwrap_or(path));
+            if self.failed {
+                let entries = match &source_path {
+                    Some(source_path) => db
+                        .list_errors_for_file_or_directory(source_path)?
+                        .try_iter()?
+                        .collect::<Result<Vec<_>, _>>()?,
+                    None => db
+                        .list_all_errors()?
+                        .try_iter()?
+                        .collect::<Result<Vec<_>, _>>()?,
+                };
+                errors.extend(entries.into_iter().filter(|e| is_selected(&e.path)));
+            } else {
+                let entries = match &source_path {
+                    Some(source_path) => db
+                        .list_file_or_directory(source_path)?
+                        .try_iter()?
+                        .collect::<Result<Vec<_>, _>>()?,
+                    None => db.list_all()?.try_iter()?.collect::<Result<Vec<_>, _>>()?,
+                };
+                // source paths without any files in the database are reported as missing
+                if let (Some(source_path), true) = (source_path, entries.is_empty()) {
+                    files.push(FileEntry {
+                        path: source_path,
+                        tag: String::new(),
+                        status: FileStatus::Missing,
+                    });
+                }
+                files.extend(entries.into_iter().filter(|e| is_selected(&e.path)));
+            }
+        }
+
+        let mut summary = Summary::new(std::fs::metadata(db_path)?.len());
+        for entry in &files {
+            summary.add(&entry.path, &entry.status);
+        }
+        for entry in &errors {
+            summary.add_failed(&entry.path);
+        }
+
+        match self.format {
+            ReportFormat::Text => {
+                let reporter = self.get_reporter();
+                self.status(&files, &reporter);
+                self.errors(&errors, &reporter);
+                summary.print();
+            }
+            ReportFormat::Json => {
+                let report = json!({
+                    "files": files
+                        .iter()
+                        .map(file_entry_to_json)
+                        .chain(errors.iter().map(file_error_entry_to_json))
+                        .collect::<Vec<_>>(),
+                    "summary": summary.to_json(),
+                });
+                println!("{}", serde_json::to_string_pretty(&report)?);
             }
         }
         Ok(())
@@ -66,13 +164,8 @@ impl StatusArgs {
         }
     }
 
-    fn status(
-        &self,
-        entries: &mut impl Iterator<Item = stack_graphs::storage::Result<FileEntry>>,
-        reporter: &dyn Reporter,
-    ) -> anyhow::Result<()> {
+    fn status(&self, entries: &[FileEntry], reporter: &dyn Reporter) {
         for entry in entries {
-            let entry = entry?;
             reporter.started(&entry.path);
             match &entry.status {
                 FileStatus::Missing => {
@@ -86,6 +179,140 @@ impl StatusArgs {
                 }
             }
         }
-        Ok(())
+    }
+
+    fn errors(&self, entries: &[FileErrorEntry], reporter: &dyn Reporter) {
+        for entry in entries {
+            reporter.started(&entry.path);
+            let status = match &entry.error.span {
+                Some(span) => format!(
+                    "{} error at {}:{}",
+                    entry.error.kind,
+                    span.start_line + 1,
+                    span.start_column + 1
+                ),
+                None => format!("{} error", entry.error.kind),
+            };
+            reporter.failed(&entry.path, &status, Some(&entry.error.message));
+        }
+    }
+}
+
+fn file_entry_to_json(entry: &FileEntry) -> serde_json::Value {
+    match &entry.status {
+        FileStatus::Missing => json!({ "path": entry.path, "status": "missing" }),
+        FileStatus::Indexed => json!({ "path": entry.path, "status": "indexed" }),
+        FileStatus::Error(error) => {
+            json!({ "path": entry.path, "status": "failed", "error": error })
+        }
+    }
+}
+
+fn file_error_entry_to_json(entry: &FileErrorEntry) -> serde_json::Value {
+    json!({
+        "path": entry.path,
+        "status": "failed",
+        "error": entry.error.message,
+        "kind": entry.error.kind,
+        "span": entry.error.span.as_ref().map(|span| json!({
+            "start": { "line": span.start_line, "column": span.start_column },
+            "end": { "line": span.end_line, "column": span.end_column },
+        })),
+    })
+}
+
+/// The number of files by status.
+#[derive(Default)]
+struct StatusCounts {
+    indexed: usize,
+    failed: usize,
+    missing: usize,
+}
+
+impl StatusCounts {
+    fn to_json(&self) -> serde_json::Value {
+        json!({
+            "indexed": self.indexed,
+            "failed": self.failed,
+            "missing": self.missing,
+        })
+    }
+}
+
+impl std::fmt::Display for StatusCounts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} indexed, {} failed, {} missing",
+            self.indexed, self.failed, self.missing
+        )
+    }
+}
+
+/// The number of files by status, in total and by language, and the size of the database.
+struct Summary {
+    total: StatusCounts,
+    languages: BTreeMap<String, StatusCounts>,
+    database_size: u64,
+}
+
+impl Summary {
+    fn new(database_size: u64) -> Self {
+        Self {
+            total: StatusCounts::default(),
+            languages: BTreeMap::new(),
+            database_size,
+        }
+    }
+
+    fn add(&mut self, path: &Path, status: &FileStatus) {
+        let language = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let counts = self.languages.entry(language).or_default();
+        match status {
+            FileStatus::Missing => {
+                self.total.missing += 1;
+                counts.missing += 1;
+            }
+            FileStatus::Indexed => {
+                self.total.indexed += 1;
+                counts.indexed += 1;
+            }
+            FileStatus::Error(_) => {
+                self.total.failed += 1;
+                counts.failed += 1;
+            }
+        }
+    }
+
+    fn add_failed(&mut self, path: &Path) {
+        self.add(path, &FileStatus::Error(String::new()));
+    }
+
+    fn print(&self) {
+        println!("total: {}", self.total);
+        for (language, counts) in &self.languages {
+            let language = if language.is_empty() {
+                "(no extension)"
+            } else {
+                language
+            };
+            println!("  {}: {}", language, counts);
+        }
+        println!("database size: {} bytes", self.database_size);
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let mut value = self.total.to_json();
+        value["languages"] = self
+            .languages
+            .iter()
+            .map(|(language, counts)| (language.clone(), counts.to_json()))
+            .collect::<serde_json::Map<_, _>>()
+            .into();
+        value["database_size"] = self.database_size.into();
+        value
     }
 }
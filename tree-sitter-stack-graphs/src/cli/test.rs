@@ -6,38 +6,45 @@
 // ------------------------------------------------------------------------------------------------
 
 use anyhow::anyhow;
+use anyhow::Context;
 use clap::Args;
 use clap::ValueEnum;
 use clap::ValueHint;
 use itertools::Itertools;
-use stack_graphs::arena::Handle;
-use stack_graphs::graph::File;
 use stack_graphs::graph::StackGraph;
 use stack_graphs::partial::PartialPaths;
+use stack_graphs::serde::FileFilter;
 use stack_graphs::serde::Filter;
 use stack_graphs::stitching::Database;
 use stack_graphs::stitching::DatabaseCandidates;
 use stack_graphs::stitching::ForwardPartialPathStitcher;
+use std::fs::File;
+use std::io::BufWriter;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 use tree_sitter_graph::Variables;
 
 use crate::cli::util::duration_from_seconds_str;
-use crate::cli::util::iter_files_and_directories;
+use crate::cli::util::iter_filtered_files_and_directories;
+use crate::cli::util::reporter::with_progress;
 use crate::cli::util::reporter::ConsoleReporter;
 use crate::cli::util::reporter::Level;
 use crate::cli::util::CLIFileReporter;
 use crate::cli::util::ExistingPathBufValueParser;
+use crate::cli::util::PathFilter;
 use crate::cli::util::PathSpec;
 use crate::loader::ContentProvider;
 use crate::loader::FileReader;
 use crate::loader::LanguageConfiguration;
 use crate::loader::Loader;
 use crate::test::Test;
+use crate::test::TestFragment;
 use crate::test::TestResult;
 use crate::CancelAfterDuration;
 use crate::CancellationFlag;
+use crate::GraphProducer;
 
 #[derive(Args)]
 #[clap(after_help = r#"PATH SPECIFICATIONS:
@@ -80,6 +87,11 @@ pub struct TestArgs {
     #[clap(long)]
     pub show_skipped: bool,
 
+    /// Do not run tests that are ignored by .gitignore or .ignore files, or are in .git
+    /// directories. Files given as test paths are always run.
+    #[clap(long)]
+    pub respect_gitignore: bool,
+
     /// Save graph for tests matching output mode.
     /// Takes an optional path specification argument for the output file.
     /// [default: %n.graph.json]
@@ -119,6 +131,20 @@ pub struct TestArgs {
     )]
     pub save_visualization: Option<PathSpec>,
 
+    /// Compare the graph of each test against a golden graph, and fail the test if they differ.
+    /// Graphs are compared after normalizing node order and removing debug info.
+    /// Golden graphs can be created with --save-graph and --output-mode=always.
+    /// Takes an optional path specification argument for the golden file.
+    /// [default: %r/%d/%n.graph.json]
+    #[clap(
+        long,
+        value_name = "PATH_SPEC",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "%r/%d/%n.graph.json"
+    )]
+    pub check_graph: Option<PathSpec>,
+
     /// Controls when graphs, paths, or visualization are saved.
     #[clap(
         long,
@@ -127,10 +153,20 @@ pub struct TestArgs {
     )]
     pub output_mode: OutputMode,
 
-    /// Do not load builtins for tests.
+    /// Do not load builtins for any test. Individual tests can opt out of builtins with a
+    /// `--- builtins: none ---` directive.
     #[clap(long)]
     pub no_builtins: bool,
 
+    /// Load the named builtins layer for tests, in addition to the default builtins
+    /// and any layers selected by the tests themselves.
+    #[clap(
+        long = "builtins-layer",
+        value_name = "NAME",
+        conflicts_with = "no_builtins"
+    )]
+    pub builtins_layers: Vec<String>,
+
     /// Maximum runtime per test in seconds.
     #[clap(
         long,
@@ -163,20 +199,29 @@ impl TestArgs {
             quiet: false,
             hide_error_details: false,
             show_skipped: false,
+            respect_gitignore: false,
             save_graph: None,
             save_paths: None,
             save_visualization: None,
+            check_graph: None,
             output_mode: OutputMode::OnFailure,
             no_builtins: false,
+            builtins_layers: Vec::new(),
             max_test_time: None,
         }
     }
 
     pub fn run(self, mut loader: Loader) -> anyhow::Result<()> {
-        let reporter = self.get_reporter();
+        let filter = PathFilter {
+            respect_gitignore: self.respect_gitignore,
+            ..PathFilter::default()
+        };
+        let test_files = iter_filtered_files_and_directories(self.test_paths.clone(), filter)
+            .collect::<Vec<_>>();
+        let reporter = with_progress(self.get_reporter(), || test_files.len());
         let mut total_result = TestResult::new();
-        for (test_root, test_path, _) in iter_files_and_directories(self.test_paths.clone()) {
-            let mut file_status = CLIFileReporter::new(&reporter, &test_path);
+        for (test_root, test_path, _) in test_files {
+            let mut file_status = CLIFileReporter::new(reporter.as_ref(), &test_path);
             let test_result =
                 self.run_test(&test_root, &test_path, &mut loader, &mut file_status)?;
             file_status.assert_reported();
@@ -242,13 +287,11 @@ impl TestArgs {
             test_path.to_path_buf()
         };
         let mut file_reader = MappingFileReader::new(&load_path, test_path);
-        let lc = match loader
-            .load_for_file(&load_path, &mut file_reader, cancellation_flag.as_ref())?
-            .primary
-        {
-            Some(lc) => lc,
-            None => return Ok(TestResult::new()),
-        };
+        let lcs = loader.load_for_file(&load_path, &mut file_reader, cancellation_flag.as_ref())?;
+        let lc = lcs.primary;
+        if lc.is_none() && lcs.producers.is_empty() {
+            return Ok(TestResult::new());
+        }
 
         if test_path.components().any(|c| match c {
             std::path::Component::Normal(name) => (name.as_ref() as &Path)
@@ -264,18 +307,42 @@ impl TestArgs {
 
         let source = file_reader.get(test_path)?;
         let default_fragment_path = test_path.strip_prefix(test_root).unwrap();
-        let mut test = Test::from_source(test_path, source, default_fragment_path)?;
-        if !self.no_builtins {
-            self.load_builtins_into(&lc, &mut test.graph)?;
+        let mut test = Test::from_source_with_comments(
+            test_path,
+            source,
+            default_fragment_path,
+            lc.map_or(&[], |lc| &lc.test_comments),
+        )?;
+        if let Some(lc) = lc {
+            if !self.no_builtins && !test.no_builtins {
+                self.load_builtins_into(lc, &test.builtins_layers, &mut test.graph)?;
+            }
         }
         let mut globals = Variables::new();
-        for test_fragment in &test.fragments {
-            let result = if let Some(fa) = test_fragment
-                .path
-                .file_name()
-                .and_then(|file_name| lc.special_files.get(&file_name.to_string_lossy()))
-            {
-                let mut all_paths = test.fragments.iter().map(|f| f.path.as_path());
+        for test_fragment in test.fragments.iter().chain(&test.includes) {
+            let special_file = lc.and_then(|lc| {
+                test_fragment
+                    .path
+                    .file_name()
+                    .and_then(|file_name| lc.special_files.get(&file_name.to_string_lossy()))
+            });
+            let matching_lc = match lc {
+                Some(lc)
+                    if lc.matches_file(
+                        &test_fragment.path,
+                        &mut Some(test_fragment.source.as_ref()),
+                    )? =>
+                {
+                    Some(lc)
+                }
+                _ => None,
+            };
+            let result = if let Some(fa) = special_file {
+                let mut all_paths = test
+                    .fragments
+                    .iter()
+                    .chain(&test.includes)
+                    .map(|f| f.path.as_path());
                 fa.build_stack_graph_into(
                     &mut test.graph,
                     test_fragment.file,
@@ -285,10 +352,7 @@ impl TestArgs {
                     &test_fragment.globals,
                     cancellation_flag.as_ref(),
                 )
-            } else if lc.matches_file(
-                &test_fragment.path,
-                &mut Some(test_fragment.source.as_ref()),
-            )? {
+            } else if let Some(lc) = matching_lc {
                 globals.clear();
                 test_fragment.add_globals_to(&mut globals);
                 lc.sgl.build_stack_graph_into(
@@ -298,6 +362,19 @@ impl TestArgs {
                     &globals,
                     cancellation_flag.as_ref(),
                 )
+            } else if let Some(producer) =
+                Self::find_producer(&lcs.producers, &test_fragment.path, &test_fragment.source)?
+            {
+                globals.clear();
+                test_fragment.add_globals_to(&mut globals);
+                producer.build_stack_graph_into(
+                    &mut test.graph,
+                    test_fragment.file,
+                    &test_fragment.path,
+                    &test_fragment.source,
+                    &globals,
+                    cancellation_flag.as_ref(),
+                )
             } else {
                 return Err(anyhow!(
                     "Test fragment {} not supported by language of test file {}",
@@ -307,15 +384,22 @@ impl TestArgs {
             };
             match result {
                 Err(err) => {
+                    // fragments share the line numbers of the test file, but include files do not
+                    let (error_path, error_source) =
+                        if test.includes.iter().any(|i| i.file == test_fragment.file) {
+                            (test_fragment.path.as_path(), test_fragment.source.as_str())
+                        } else {
+                            (test.path.as_path(), source)
+                        };
                     file_status.failure(
                         "failed to build stack graph",
                         Some(&format!(
                             "{}",
                             err.display_pretty(
-                                &test.path,
-                                source,
-                                lc.sgl.tsg_path(),
-                                lc.sgl.tsg_source(),
+                                error_path,
+                                error_source,
+                                lc.map_or(Path::new(""), |lc| lc.sgl.tsg_path()),
+                                lc.map_or("", |lc| lc.sgl.tsg_source()),
                             )
                         )),
                     );
@@ -337,17 +421,26 @@ impl TestArgs {
                 },
             )?;
         }
-        let result = test.run(&mut partials, &mut db, cancellation_flag.as_ref())?;
+        let mut result = test.run(&mut partials, &mut db, cancellation_flag.as_ref())?;
+        let files = test
+            .fragments
+            .iter()
+            .map(|f| f.file)
+            .collect::<FileFilter>();
+        if let Some(spec) = &self.check_graph {
+            let golden_path = spec.format(test_root, test_path);
+            let golden = self.load_graph(&golden_path)?;
+            result.absorb(test.check_graph(&files, &golden_path, golden));
+        }
         let success = result.failure_count() == 0;
         let outputs = if self.output_mode.test(!success) {
-            let files = test.fragments.iter().map(|f| f.file).collect::<Vec<_>>();
             self.save_output(
                 test_root,
                 test_path,
-                &test.graph,
+                &test,
                 &mut partials,
                 &mut db,
-                &|_: &StackGraph, h: &Handle<File>| files.contains(h),
+                &files,
                 success,
                 cancellation_flag.as_ref(),
             )?
@@ -380,25 +473,45 @@ impl TestArgs {
     fn load_builtins_into(
         &self,
         lc: &LanguageConfiguration,
+        test_layers: &[String],
         graph: &mut StackGraph,
     ) -> anyhow::Result<()> {
-        if let Err(h) = graph.add_from_graph(&lc.builtins) {
-            return Err(anyhow!("Duplicate builtin file {}", &graph[h]));
-        }
+        let layers = self
+            .builtins_layers
+            .iter()
+            .chain(test_layers)
+            .unique()
+            .collect::<Vec<_>>();
+        lc.load_builtins_into(graph, &layers)?;
         Ok(())
     }
 
+    /// Returns the first graph producer that matches the given test fragment.
+    fn find_producer(
+        producers: &[Arc<dyn GraphProducer + Send + Sync>],
+        path: &Path,
+        source: &str,
+    ) -> anyhow::Result<Option<Arc<dyn GraphProducer + Send + Sync>>> {
+        for producer in producers {
+            if producer.matches_file(path, &mut Some(source))? {
+                return Ok(Some(producer.clone()));
+            }
+        }
+        Ok(None)
+    }
+
     fn save_output(
         &self,
         test_root: &Path,
         test_path: &Path,
-        graph: &StackGraph,
+        test: &Test,
         partials: &mut PartialPaths,
         db: &mut Database,
         filter: &dyn Filter,
         success: bool,
         cancellation_flag: &dyn CancellationFlag,
     ) -> anyhow::Result<Vec<String>> {
+        let graph = &test.graph;
         let mut outputs = Vec::with_capacity(3);
         let save_graph = self
             .save_graph
@@ -442,7 +555,7 @@ impl TestArgs {
         }
 
         if let Some(path) = save_visualization {
-            self.save_visualization(&path, graph, partials, &mut db, filter, &test_path)?;
+            self.save_visualization(&path, test, partials, &mut db, filter, &test_path)?;
             if !success || !self.quiet {
                 outputs.push(format!(
                     "{}: visualization at {}",
@@ -468,6 +581,17 @@ impl TestArgs {
         Ok(())
     }
 
+    /// Loads a golden graph, or returns `None` if it does not exist.
+    fn load_graph(&self, path: &Path) -> anyhow::Result<Option<stack_graphs::serde::StackGraph>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let json = std::fs::read_to_string(path)?;
+        let graph = serde_json::from_str(&json)
+            .with_context(|| format!("Cannot parse golden graph {}", path.display()))?;
+        Ok(Some(graph))
+    }
+
     fn compute_paths(
         &self,
         graph: &StackGraph,
@@ -516,17 +640,24 @@ impl TestArgs {
     fn save_visualization(
         &self,
         path: &Path,
-        graph: &StackGraph,
+        test: &Test,
         paths: &mut PartialPaths,
         db: &mut Database,
         filter: &dyn Filter,
         test_path: &Path,
     ) -> anyhow::Result<()> {
-        let html = graph.to_html_string(&format!("{}", test_path.display()), paths, db, filter)?;
         if let Some(dir) = path.parent() {
             std::fs::create_dir_all(dir)?;
         }
-        std::fs::write(&path, html)?;
+        let file = BufWriter::new(File::create(path)?);
+        test.graph.write_html(
+            file,
+            &format!("{}", test_path.display()),
+            paths,
+            db,
+            filter,
+            &mut FragmentContent(&test.fragments),
+        )?;
         Ok(())
     }
 }
@@ -561,3 +692,16 @@ impl ContentProvider for MappingFileReader<'_> {
         self.get(path).map(Some)
     }
 }
+
+/// Provides the sources of test fragments by their paths.
+struct FragmentContent<'a>(&'a [TestFragment]);
+
+impl ContentProvider for FragmentContent<'_> {
+    fn get(&mut self, path: &Path) -> std::io::Result<Option<&str>> {
+        Ok(self
+            .0
+            .iter()
+            .find(|fragment| fragment.path == path)
+            .map(|fragment| fragment.source.as_str()))
+    }
+}
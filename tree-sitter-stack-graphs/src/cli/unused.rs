@@ -0,0 +1,157 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use clap::Args;
+use clap::ValueEnum;
+use clap::ValueHint;
+use lsp_positions::Span;
+use serde_json::json;
+use stack_graphs::storage::SQLiteReader;
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::cli::query::span_to_json;
+use crate::cli::query::DefinitionInfo;
+use crate::cli::query::Querier;
+use crate::cli::query::ReportFormat;
+use crate::cli::util::indexed_files;
+use crate::cli::util::reporter::ConsoleReporter;
+use crate::NoCancellation;
+
+#[derive(Args)]
+#[clap(after_help = r#"RESOLUTION:
+    All references in the database are resolved to find the definitions that
+    are used, which can take a while for large databases. Definitions that are
+    only used from files that are not indexed, or that changed since they were
+    indexed, are reported as unused.
+"#)]
+pub struct UnusedArgs {
+    /// Source file or directory paths whose definitions are checked.  If none are given, the
+    /// definitions of all indexed files are checked.  References in all indexed files are
+    /// resolved, regardless of these paths.
+    #[clap(value_name = "SOURCE_PATH", value_hint = ValueHint::AnyPath)]
+    pub source_paths: Vec<PathBuf>,
+
+    /// Which definitions are checked.  Exported definitions are those that can be reached from
+    /// the root node, and therefore have a qualified name.
+    #[clap(long, value_enum, default_value_t = Visibility::Exported)]
+    pub visibility: Visibility,
+
+    /// Only check definitions with this syntax type.  Can be given multiple times.
+    #[clap(long, value_name = "TYPE")]
+    pub syntax_type: Vec<String>,
+
+    /// Format of the report.  Positions are reported as one-based lines and columns.
+    #[clap(long, value_enum, default_value_t = ReportFormat::Text)]
+    pub format: ReportFormat,
+}
+
+/// Which definitions are checked for references.
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum Visibility {
+    Exported,
+    All,
+}
+
+impl UnusedArgs {
+    pub fn run(self, db_path: &Path) -> anyhow::Result<()> {
+        let mut db = SQLiteReader::open(db_path)?;
+        let files = indexed_files(&mut db, &[])?;
+        let checked_files = indexed_files(&mut db, &self.source_paths)?;
+        let reporter = ConsoleReporter::none();
+        let mut querier = Querier::new(&mut db, &reporter);
+
+        let mut used = HashSet::<(PathBuf, Span)>::new();
+        for file in &files {
+            for result in querier.file_definitions(file, &NoCancellation)? {
+                used.extend(
+                    result
+                        .targets
+                        .into_iter()
+                        .map(|target| (target.span.path, target.span.span)),
+                );
+            }
+        }
+
+        let mut checked = 0;
+        let mut unused = Vec::new();
+        for file in &checked_files {
+            for definition in querier.definitions_in_file(file, &NoCancellation)? {
+                if !self.is_checked(&definition) {
+                    continue;
+                }
+                checked += 1;
+                let span = &definition.target.span;
+                if !used.contains(&(span.path.clone(), span.span.clone())) {
+                    unused.push(definition);
+                }
+            }
+        }
+
+        match self.format {
+            ReportFormat::Text => {
+                for definition in &unused {
+                    let span = &definition.target.span;
+                    let name = definition
+                        .qualified_name
+                        .as_ref()
+                        .or(definition.symbol.as_ref())
+                        .map(String::as_str)
+                        .unwrap_or_default();
+                    print!(
+                        "{}:{}:{}: {}",
+                        span.path.display(),
+                        span.first_line() + 1,
+                        span.span.start.column.grapheme_offset + 1,
+                        name
+                    );
+                    if let Some(syntax_type) = &definition.syntax_type {
+                        print!(" ({})", syntax_type);
+                    }
+                    println!();
+                }
+                println!(
+                    "found {} unused of {} checked definitions in {} files",
+                    unused.len(),
+                    checked,
+                    checked_files.len()
+                );
+            }
+            ReportFormat::Json => {
+                let report = json!({
+                    "definitions": unused
+                        .iter()
+                        .map(|definition| {
+                            let mut value = span_to_json(&definition.target.span);
+                            value["symbol"] = definition.symbol.clone().into();
+                            value["qualified_name"] = definition.qualified_name.clone().into();
+                            value["syntax_type"] = definition.syntax_type.clone().into();
+                            value
+                        })
+                        .collect::<Vec<_>>(),
+                    "checked": checked,
+                    "unused": unused.len(),
+                });
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns whether the definition is selected by the visibility and syntax type filters.
+    fn is_checked(&self, definition: &DefinitionInfo) -> bool {
+        if self.visibility == Visibility::Exported && definition.qualified_name.is_none() {
+            return false;
+        }
+        self.syntax_type.is_empty()
+            || definition
+                .syntax_type
+                .as_ref()
+                .is_some_and(|syntax_type| self.syntax_type.contains(syntax_type))
+    }
+}
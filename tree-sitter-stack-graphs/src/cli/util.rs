@@ -12,14 +12,22 @@ use clap::builder::TypedValueParser;
 use clap::error::ContextKind;
 use clap::error::ContextValue;
 use clap::error::ErrorKind;
+use clap::ValueEnum;
+use globset::GlobMatcher;
+use ignore::WalkBuilder;
+use lsp_positions::Offset;
 use lsp_positions::Span;
 use sha1::Digest;
 use sha1::Sha1;
 use stack_graphs::arena::Handle;
 use stack_graphs::graph::Node;
 use stack_graphs::graph::StackGraph;
+use stack_graphs::storage::FileStatus;
+use stack_graphs::storage::SQLiteReader;
 use std::ffi::OsStr;
 use std::ffi::OsString;
+use std::fs::File;
+use std::io::BufWriter;
 use std::io::Write;
 use std::ops::Range;
 use std::path::Path;
@@ -27,7 +35,9 @@ use std::path::PathBuf;
 use std::time::Duration;
 use walkdir::WalkDir;
 
+use self::reporter::FileInfo;
 use self::reporter::Reporter;
+use crate::util::compile_glob;
 
 pub mod reporter;
 
@@ -178,8 +188,13 @@ pub struct SourcePosition {
     pub path: PathBuf,
     /// Position line (0-based)
     pub line: usize,
-    /// Position column (0-based grapheme)
+    /// Position column (0-based, in units of the encoding)
     pub column: usize,
+    /// End column (0-based, inclusive), if the position is a range of columns.  A position for a
+    /// whole line has an end column of `usize::MAX`.
+    pub end_column: Option<usize>,
+    /// Encoding of the columns
+    pub encoding: PositionEncoding,
 }
 
 impl SourcePosition {
@@ -207,11 +222,12 @@ impl SourcePosition {
             })
     }
 
+    /// Returns whether the span overlaps the position, or the range of columns of the position.
     fn within_span(&self, span: &lsp_positions::Span) -> bool {
-        ((span.start.line < self.line)
-            || (span.start.line == self.line && span.start.column.grapheme_offset <= self.column))
-            && ((span.end.line == self.line && span.end.column.grapheme_offset >= self.column)
-                || (span.end.line > self.line))
+        let start = (span.start.line, self.encoding.column(&span.start.column));
+        let end = (span.end.line, self.encoding.column(&span.end.column));
+        let end_column = self.end_column.unwrap_or(self.column);
+        start <= (self.line, end_column) && end >= (self.line, self.column)
     }
 
     pub fn canonicalize(&mut self) -> std::io::Result<()> {
@@ -222,69 +238,130 @@ impl SourcePosition {
 
 impl std::fmt::Display for SourcePosition {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}:{}:{}",
-            self.path.display(),
-            self.line + 1,
-            self.column + 1
-        )
+        write!(f, "{}:{}", self.path.display(), self.line + 1)?;
+        match self.end_column {
+            Some(usize::MAX) => Ok(()),
+            Some(end_column) => write!(f, ":{}-{}", self.column + 1, end_column + 1),
+            None => write!(f, ":{}", self.column + 1),
+        }
     }
 }
 
 impl std::str::FromStr for SourcePosition {
     type Err = anyhow::Error;
 
+    /// Parses positions in the formats `PATH:LINE:COLUMN`, `PATH:LINE:COLUMN-COLUMN` for a range
+    /// of columns, and `PATH:LINE` for a whole line.  Lines and columns are 1-based.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const FORMAT: &str = "PATH:LINE[:COLUMN[-COLUMN]]";
         let mut values = s.split(':');
         let path = match values.next() {
             Some(path) => PathBuf::from(path),
-            None => return Err(anyhow!("Missing path in expected format PATH:LINE:COLUMN")),
+            None => return Err(anyhow!("Missing path in expected format {}", FORMAT)),
         };
         let line = match values.next() {
-            Some(line) => {
-                let line = usize::from_str(line).map_err(|_| {
-                    anyhow!(
-                        "Expected line to be a number, got {} in expected format PATH:LINE:COLUMN",
-                        line
-                    )
-                })?;
-                if line == 0 {
-                    return Err(anyhow!(
-                        "Line numbers are 1-based, got 0 in expected format PATH:LINE:COLUMN"
-                    ));
-                }
-                line - 1
-            }
-            None => {
-                return Err(anyhow!(
-                    "Missing line and column numbers in expected format PATH:LINE:COLUMN"
-                ))
-            }
+            Some(line) => parse_one_based("line", line, FORMAT)?,
+            None => return Err(anyhow!("Missing line number in expected format {}", FORMAT)),
         };
-        let column = match values.next() {
-            Some(column) => {
-                let column = usize::from_str(column)
-                    .map_err(|_| anyhow!("Expected column to be a number, got {} in expected format PATH:LINE:COLUMN", column))?;
-                if column == 0 {
-                    return Err(anyhow!(
-                        "Column numbers are 1-based, got 0 in expected format PATH:LINE:COLUMN"
-                    ));
+        let (column, end_column) = match values.next() {
+            Some(columns) => match columns.split_once('-') {
+                Some((column, end_column)) => {
+                    let column = parse_one_based("column", column, FORMAT)?;
+                    let end_column = parse_one_based("column", end_column, FORMAT)?;
+                    if end_column < column {
+                        return Err(anyhow!(
+                            "End column must not be before start column, got {} in expected format {}",
+                            columns,
+                            FORMAT
+                        ));
+                    }
+                    (column, Some(end_column))
                 }
-                column - 1
-            }
-            None => {
-                return Err(anyhow!(
-                    "Missing column number in expected format PATH:LINE:COLUMN"
-                ))
-            }
+                None => (parse_one_based("column", columns, FORMAT)?, None),
+            },
+            None => (0, Some(usize::MAX)),
         };
         if values.next().is_some() {
             return Err(anyhow!(
-                "Found unexpected components in expected format PATH:LINE:COLUMN"
+                "Found unexpected components in expected format {}",
+                FORMAT
             ));
         }
-        Ok(Self { path, line, column })
+        Ok(Self {
+            path,
+            line,
+            column,
+            end_column,
+            encoding: PositionEncoding::default(),
+        })
+    }
+}
+
+/// Parses a 1-based number, and returns it 0-based.
+fn parse_one_based(name: &str, value: &str, format: &str) -> anyhow::Result<usize> {
+    let number = value.parse::<usize>().map_err(|_| {
+        anyhow!(
+            "Expected {} to be a number, got {} in expected format {}",
+            name,
+            value,
+            format
+        )
+    })?;
+    if number == 0 {
+        return Err(anyhow!(
+            "{}{} numbers are 1-based, got 0 in expected format {}",
+            name[..1].to_uppercase(),
+            &name[1..],
+            format
+        ));
+    }
+    Ok(number - 1)
+}
+
+/// The unit in which the columns of source positions are given.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub enum PositionEncoding {
+    /// UTF-8 code units, which are bytes.
+    #[value(alias = "byte")]
+    Utf8,
+    /// UTF-16 code units, which are used by the Language Server Protocol.
+    Utf16,
+    /// Grapheme clusters, which are what users perceive as characters.
+    #[default]
+    Grapheme,
+}
+
+impl PositionEncoding {
+    /// Returns the column of the offset in this encoding.
+    pub fn column(&self, offset: &Offset) -> usize {
+        match self {
+            Self::Utf8 => offset.utf8_offset,
+            Self::Utf16 => offset.utf16_offset,
+            Self::Grapheme => offset.grapheme_offset,
+        }
+    }
+
+    /// Converts a column in the given line from this encoding to another encoding.  Returns `None`
+    /// if the column is past the end of the line, or does not fall on a character boundary.  If
+    /// either encoding counts graphemes, the column must fall on a grapheme cluster boundary.
+    pub fn convert_column(&self, line: &str, column: usize, to: PositionEncoding) -> Option<usize> {
+        // Offset::all_chars gives characters inside a grapheme cluster the grapheme column of the
+        // next cluster, so offsets are computed from the prefix of the line instead.  An offset is
+        // at a cluster boundary if splitting the line there does not change its clusters.
+        let check_graphemes = *self == Self::Grapheme || to == Self::Grapheme;
+        let graphemes = Offset::string_length(line).grapheme_offset;
+        line.char_indices()
+            .map(|(utf8_offset, _)| utf8_offset)
+            .chain(std::iter::once(line.len()))
+            .map(|utf8_offset| Offset::string_length(&line[..utf8_offset]))
+            .filter(|offset| {
+                !check_graphemes
+                    || offset.grapheme_offset
+                        + Offset::string_length(&line[offset.utf8_offset..]).grapheme_offset
+                        == graphemes
+            })
+            .find(|offset| self.column(offset) == column)
+            .map(|offset| to.column(&offset))
     }
 }
 
@@ -328,9 +405,104 @@ pub(crate) fn duration_from_milliseconds_str(s: &str) -> Result<Duration, anyhow
     Ok(Duration::new(seconds, nano_seconds))
 }
 
+/// A glob that is matched against file paths relative to their source root.
+///
+/// The wildcard `*` matches any sequence of characters except `/`, `**` matches any sequence of
+/// path components, `?` matches any single character except `/`, `[...]` matches any of the
+/// enclosed characters (or, if the first character is `!`, any character that is not enclosed),
+/// and `{a,b}` matches any of the comma-separated alternatives.  Globs that contain a `/` are
+/// matched against the whole relative path, while globs without a `/` are matched against every
+/// path component, so that `vendor` matches a `vendor` directory at any depth.  A glob matches
+/// all files in a directory that it matches.
+#[derive(Clone, Debug)]
+pub struct PathGlob {
+    glob: String,
+    matcher: GlobMatcher,
+}
+
+impl PathGlob {
+    /// Returns whether the given path, relative to its source root, matches this glob.
+    pub fn matches(&self, relative_path: &Path) -> bool {
+        relative_path
+            .ancestors()
+            .filter(|path| !path.as_os_str().is_empty())
+            .any(|path| self.matcher.is_match(path))
+    }
+}
+
+impl std::str::FromStr for PathGlob {
+    type Err = anyhow::Error;
+
+    fn from_str(glob: &str) -> Result<Self, Self::Err> {
+        let (anchored, pattern) = match glob.strip_prefix('/') {
+            Some(pattern) => (true, pattern),
+            None => (glob.contains('/'), glob),
+        };
+        let pattern = pattern.trim_end_matches('/');
+        if pattern.is_empty() {
+            return Err(anyhow!("Empty glob"));
+        }
+        let pattern = if anchored {
+            pattern.to_string()
+        } else {
+            format!("**/{}", pattern)
+        };
+        Ok(Self {
+            glob: glob.to_string(),
+            matcher: compile_glob(&pattern)
+                .map_err(|err| anyhow!("Invalid glob {}: {}", glob, err))?,
+        })
+    }
+}
+
+impl std::fmt::Display for PathGlob {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.glob.fmt(f)
+    }
+}
+
+/// Selects files by their path relative to their source root.  Files are selected if they match
+/// any of the included globs, or if there are none, and do not match any of the excluded globs.
+#[derive(Clone, Debug, Default)]
+pub struct PathFilter {
+    pub include: Vec<PathGlob>,
+    pub exclude: Vec<PathGlob>,
+    /// Skip files in directories that are ignored by `.gitignore` or `.ignore` files, as well as
+    /// `.git` directories.  Files that are given explicitly are never ignored.
+    pub respect_gitignore: bool,
+}
+
+impl PathFilter {
+    /// Returns whether the file with the given path, relative to its source root, is selected.
+    pub fn matches(&self, relative_path: &Path) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|g| g.matches(relative_path)))
+            && !self.excludes(relative_path)
+    }
+
+    /// Returns whether the given path, relative to its source root, is excluded.  Excluded
+    /// directories do not have to be visited, because all files in them are excluded as well.
+    fn excludes(&self, relative_path: &Path) -> bool {
+        self.exclude.iter().any(|g| g.matches(relative_path))
+    }
+}
+
 pub fn iter_files_and_directories<'a, P, IP>(
     paths: IP,
 ) -> impl Iterator<Item = (PathBuf, PathBuf, bool)> + 'a
+where
+    P: AsRef<Path> + 'a,
+    IP: IntoIterator<Item = P> + 'a,
+{
+    iter_filtered_files_and_directories(paths, PathFilter::default())
+}
+
+/// Iterates over the given files, and the files in the given directories, that are selected by
+/// the filter.  Paths are matched against the filter relative to their source root, which is the
+/// directory argument that contains them, or the parent directory of a file argument.
+pub fn iter_filtered_files_and_directories<'a, P, IP>(
+    paths: IP,
+    filter: PathFilter,
+) -> impl Iterator<Item = (PathBuf, PathBuf, bool)> + 'a
 where
     P: AsRef<Path> + 'a,
     IP: IntoIterator<Item = P> + 'a,
@@ -338,22 +510,28 @@ where
     paths
         .into_iter()
         .filter_map(
-            |source_path| -> Option<Box<dyn Iterator<Item = (PathBuf, PathBuf, bool)>>> {
+            move |source_path| -> Option<Box<dyn Iterator<Item = (PathBuf, PathBuf, bool)>>> {
                 if source_path.as_ref().is_dir() {
-                    let source_root = source_path;
-                    let paths = WalkDir::new(&source_root)
-                        .follow_links(true)
-                        .sort_by_file_name()
-                        .into_iter()
-                        .filter_map(|e| e.ok())
-                        .filter(|e| e.file_type().is_file())
-                        .map(move |e| (source_root.as_ref().to_path_buf(), e.into_path(), false));
+                    let source_root = source_path.as_ref().to_path_buf();
+                    let file_filter = filter.clone();
+                    let paths = walk_directory(&source_root, filter.clone())
+                        .filter(move |path| {
+                            file_filter.matches(path.strip_prefix(&source_root).unwrap_or(path))
+                        })
+                        .map(move |path| (source_path.as_ref().to_path_buf(), path, false));
                     Some(Box::new(paths))
                 } else {
                     let source_root = source_path
                         .as_ref()
                         .parent()
                         .expect("expect file to have parent");
+                    let relative_path = source_path
+                        .as_ref()
+                        .strip_prefix(source_root)
+                        .unwrap_or(source_path.as_ref());
+                    if !filter.matches(relative_path) {
+                        return None;
+                    }
                     Some(Box::new(std::iter::once((
                         source_root.to_path_buf(),
                         source_path.as_ref().to_path_buf(),
@@ -365,6 +543,70 @@ where
         .flatten()
 }
 
+/// Iterates over the given files that are selected by the filter, such as files listed by a build
+/// system.  Files are matched against the filter relative to the given source root if they are in
+/// it, and relative to their parent directory otherwise, which is also returned as their source
+/// root.  Ignore files are not taken into account, because the files are given explicitly.
+pub fn iter_filtered_files<'a, P, IP>(
+    source_root: &'a Path,
+    paths: IP,
+    filter: PathFilter,
+) -> impl Iterator<Item = (PathBuf, PathBuf, bool)> + 'a
+where
+    P: AsRef<Path> + 'a,
+    IP: IntoIterator<Item = P> + 'a,
+{
+    paths.into_iter().filter_map(move |path| {
+        let path = path.as_ref();
+        let source_root = if path.starts_with(source_root) {
+            source_root
+        } else {
+            path.parent().expect("expect file to have parent")
+        };
+        let relative_path = path.strip_prefix(source_root).unwrap_or(path);
+        if !filter.matches(relative_path) {
+            return None;
+        }
+        Some((source_root.to_path_buf(), path.to_path_buf(), true))
+    })
+}
+
+/// Lists the files in a directory, in order of their names.  Directories that are excluded by the
+/// filter are not visited, and neither are ignored directories if the filter respects ignore files.
+fn walk_directory(source_root: &Path, filter: PathFilter) -> Box<dyn Iterator<Item = PathBuf>> {
+    let respect_gitignore = filter.respect_gitignore;
+    let root = source_root.to_path_buf();
+    let is_excluded_dir = move |path: &Path, depth: usize| {
+        depth > 0 && filter.excludes(path.strip_prefix(&root).unwrap_or(path))
+    };
+    if respect_gitignore {
+        let paths = WalkBuilder::new(source_root)
+            .follow_links(true)
+            .sort_by_file_name(|a, b| a.cmp(b))
+            .hidden(false)
+            .require_git(false)
+            .filter_entry(move |e| {
+                !e.file_type().is_some_and(|t| t.is_dir())
+                    || !(e.file_name() == ".git" || is_excluded_dir(e.path(), e.depth()))
+            })
+            .build()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_some_and(|t| t.is_file()))
+            .map(|e| e.into_path());
+        Box::new(paths)
+    } else {
+        let paths = WalkDir::new(source_root)
+            .follow_links(true)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(move |e| !e.file_type().is_dir() || !is_excluded_dir(e.path(), e.depth()))
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .map(|e| e.into_path());
+        Box::new(paths)
+    }
+}
+
 /// Wraps a reporter and ensures that reporter is called properly without requiring
 /// the caller of the wrapper to be overly careful about which methods must be called
 /// in which order
@@ -432,12 +674,21 @@ impl<'a> CLIFileReporter<'a> {
         self.status_logged = true;
     }
 
+    pub(super) fn is_processing(&self) -> bool {
+        self.path_logged && !self.status_logged
+    }
+
+    pub(super) fn info(&mut self, info: FileInfo) {
+        self.ensure_started();
+        self.reporter.info(self.path, &info);
+    }
+
     pub(super) fn failure_if_processing(
         &mut self,
         status: &str,
         details: Option<&dyn std::fmt::Display>,
     ) {
-        if !self.path_logged || self.status_logged {
+        if !self.is_processing() {
             return;
         }
         self.failure(status, details);
@@ -450,6 +701,34 @@ impl<'a> CLIFileReporter<'a> {
     }
 }
 
+/// Returns the indexed files in the given source paths, or all indexed files in the database if
+/// no source paths are given.
+pub(crate) fn indexed_files(
+    db: &mut SQLiteReader,
+    source_paths: &[PathBuf],
+) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    if source_paths.is_empty() {
+        for entry in db.list_all()?.try_iter()? {
+            let entry = entry?;
+            if matches!(entry.status, FileStatus::Indexed) {
+                files.push(entry.path);
+            }
+        }
+    } else {
+        for source_path in source_paths {
+            let source_path = source_path.canonicalize()?;
+            for entry in db.list_file_or_directory(&source_path)?.try_iter()? {
+                let entry = entry?;
+                if matches!(entry.status, FileStatus::Indexed) {
+                    files.push(entry.path);
+                }
+            }
+        }
+    }
+    Ok(files)
+}
+
 pub(crate) fn sha1(value: &str) -> String {
     let mut hasher = Sha1::new();
     hasher.update(value);
@@ -495,3 +774,11 @@ impl std::fmt::Display for DisplayBuildErrorPretty<'_> {
         )
     }
 }
+
+/// Returns a writer for the given output file, or for standard output if no file is given.
+pub fn output_writer(output: &Option<PathBuf>) -> anyhow::Result<Box<dyn Write>> {
+    Ok(match output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(std::io::stdout()),
+    })
+}
@@ -7,8 +7,17 @@
 
 use colored::ColoredString;
 use colored::Colorize;
+use indicatif::ProgressBar;
+use indicatif::ProgressDrawTarget;
+use indicatif::ProgressStyle;
+use serde_json::json;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::IsTerminal;
 use std::io::Write;
 use std::path::Path;
+use std::path::PathBuf;
+use std::time::Instant;
 
 /// Trait that supports reporting file processing status.
 ///
@@ -35,6 +44,31 @@ pub trait Reporter {
 
     /// File could not be processed and was canceled.
     fn cancelled(&self, path: &Path, summary: &str, details: Option<&dyn std::fmt::Display>);
+
+    /// Additional information about a file that is being processed.  If called, it is called
+    /// after [`started`] and before the result is reported.  Ignored by default.
+    fn info(&self, _path: &Path, _info: &FileInfo) {}
+}
+
+/// Machine-readable information about a processed file, in addition to its status.
+#[derive(Clone, Debug, Default)]
+pub struct FileInfo {
+    /// Number of nodes in the stack graph of the file.
+    pub node_count: Option<usize>,
+    /// Number of partial paths computed for the file.
+    pub path_count: Option<usize>,
+    /// Kind of error if the file failed or was canceled, such as `parse` or `timeout`.
+    pub error_kind: Option<&'static str>,
+}
+
+impl FileInfo {
+    /// Returns information about a file that failed or was canceled with the given kind of error.
+    pub fn error(kind: &'static str) -> Self {
+        Self {
+            error_kind: Some(kind),
+            ..Self::default()
+        }
+    }
 }
 
 /// An enum describing the level of detail that should be reported.
@@ -176,3 +210,205 @@ impl Reporter for ConsoleReporter {
         )
     }
 }
+
+/// A reporter that shows a progress bar for a known number of files.  Results are forwarded to
+/// another reporter, and printed above the progress bar.
+pub struct ProgressReporter<R> {
+    inner: R,
+    bar: ProgressBar,
+}
+
+impl<R: Reporter> ProgressReporter<R> {
+    pub fn new(inner: R, file_count: usize) -> Self {
+        let bar =
+            ProgressBar::with_draw_target(Some(file_count as u64), ProgressDrawTarget::stdout());
+        bar.set_style(
+            ProgressStyle::with_template("[{elapsed_precise}] {bar:40} {pos}/{len} {wide_msg}")
+                .expect("template should be valid"),
+        );
+        Self { inner, bar }
+    }
+
+    /// Forwards a result to the inner reporter, and advances the progress bar.
+    fn finished(&self, report: impl FnOnce(&R)) {
+        self.bar.suspend(|| report(&self.inner));
+        self.bar.inc(1);
+    }
+}
+
+impl<R: Reporter> Reporter for ProgressReporter<R> {
+    fn skipped(&self, path: &Path, summary: &str, details: Option<&dyn std::fmt::Display>) {
+        self.finished(|inner| inner.skipped(path, summary, details));
+    }
+
+    fn started(&self, path: &Path) {
+        // the inner reporter is started together with the result, so that its output is not
+        // interrupted by the progress bar
+        self.bar.set_message(path.display().to_string());
+    }
+
+    fn succeeded(&self, path: &Path, summary: &str, details: Option<&dyn std::fmt::Display>) {
+        self.finished(|inner| {
+            inner.started(path);
+            inner.succeeded(path, summary, details);
+        });
+    }
+
+    fn failed(&self, path: &Path, summary: &str, details: Option<&dyn std::fmt::Display>) {
+        self.finished(|inner| {
+            inner.started(path);
+            inner.failed(path, summary, details);
+        });
+    }
+
+    fn cancelled(&self, path: &Path, summary: &str, details: Option<&dyn std::fmt::Display>) {
+        self.finished(|inner| {
+            inner.started(path);
+            inner.cancelled(path, summary, details);
+        });
+    }
+
+    fn info(&self, path: &Path, info: &FileInfo) {
+        self.inner.info(path, info);
+    }
+}
+
+impl<R> Drop for ProgressReporter<R> {
+    fn drop(&mut self) {
+        self.bar.finish_and_clear();
+    }
+}
+
+/// A reporter that emits the file processing status as [`tracing`][] events, so that it can be
+/// collected by any tracing subscriber.  Failed files are reported as errors, canceled files as
+/// warnings, and other statuses as info.  Details are included as the `details` field.
+#[cfg(feature = "tracing")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TracingReporter;
+
+#[cfg(feature = "tracing")]
+impl Reporter for TracingReporter {
+    fn skipped(&self, path: &Path, summary: &str, details: Option<&dyn std::fmt::Display>) {
+        let details = details.map(|d| d.to_string());
+        tracing::info!(path = %path.display(), details, "{}", summary);
+    }
+
+    fn started(&self, path: &Path) {
+        tracing::debug!(path = %path.display(), "started");
+    }
+
+    fn succeeded(&self, path: &Path, summary: &str, details: Option<&dyn std::fmt::Display>) {
+        let details = details.map(|d| d.to_string());
+        tracing::info!(path = %path.display(), details, "{}", summary);
+    }
+
+    fn failed(&self, path: &Path, summary: &str, details: Option<&dyn std::fmt::Display>) {
+        let details = details.map(|d| d.to_string());
+        tracing::error!(path = %path.display(), details, "{}", summary);
+    }
+
+    fn cancelled(&self, path: &Path, summary: &str, details: Option<&dyn std::fmt::Display>) {
+        let details = details.map(|d| d.to_string());
+        tracing::warn!(path = %path.display(), details, "{}", summary);
+    }
+}
+
+/// A reporter that prints one JSON object per line to standard output for every status, so that
+/// processing can be tracked by other programs.  Every object has an `event` field, which is one
+/// of `skipped`, `started`, `succeeded`, `failed`, or `canceled`, and a `path` field.  Results
+/// also have a `status` summary, the time in milliseconds since the file was started as
+/// `elapsed_ms`, and the fields of any [`FileInfo`][] that was reported for the file.
+#[derive(Default)]
+pub struct JsonLinesReporter {
+    started: RefCell<HashMap<PathBuf, Instant>>,
+    info: RefCell<HashMap<PathBuf, FileInfo>>,
+}
+
+impl JsonLinesReporter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn print_result(
+        &self,
+        event: &str,
+        path: &Path,
+        summary: &str,
+        details: Option<&dyn std::fmt::Display>,
+    ) {
+        let mut value = json!({
+            "event": event,
+            "path": path.to_string_lossy(),
+            "status": summary,
+        });
+        if let Some(started) = self.started.borrow_mut().remove(path) {
+            value["elapsed_ms"] = json!(started.elapsed().as_micros() as f64 / 1000.0);
+        }
+        if let Some(info) = self.info.borrow_mut().remove(path) {
+            if let Some(node_count) = info.node_count {
+                value["nodes"] = json!(node_count);
+            }
+            if let Some(path_count) = info.path_count {
+                value["paths"] = json!(path_count);
+            }
+            if let Some(error_kind) = info.error_kind {
+                value["error_kind"] = json!(error_kind);
+            }
+        }
+        if let Some(details) = details {
+            value["details"] = json!(details.to_string());
+        }
+        self.print(&value);
+    }
+
+    fn print(&self, value: &serde_json::Value) {
+        let mut stdout = std::io::stdout().lock();
+        writeln!(stdout, "{}", value).expect("write should succeed");
+        stdout.flush().expect("flush should succeed");
+    }
+}
+
+impl Reporter for JsonLinesReporter {
+    fn skipped(&self, path: &Path, summary: &str, details: Option<&dyn std::fmt::Display>) {
+        self.print_result("skipped", path, summary, details);
+    }
+
+    fn started(&self, path: &Path) {
+        self.started
+            .borrow_mut()
+            .insert(path.to_path_buf(), Instant::now());
+        self.print(&json!({ "event": "started", "path": path.to_string_lossy() }));
+    }
+
+    fn succeeded(&self, path: &Path, summary: &str, details: Option<&dyn std::fmt::Display>) {
+        self.print_result("succeeded", path, summary, details);
+    }
+
+    fn failed(&self, path: &Path, summary: &str, details: Option<&dyn std::fmt::Display>) {
+        self.print_result("failed", path, summary, details);
+    }
+
+    fn cancelled(&self, path: &Path, summary: &str, details: Option<&dyn std::fmt::Display>) {
+        self.print_result("canceled", path, summary, details);
+    }
+
+    fn info(&self, path: &Path, info: &FileInfo) {
+        self.info
+            .borrow_mut()
+            .insert(path.to_path_buf(), info.clone());
+    }
+}
+
+/// Returns the given reporter wrapped in a [`ProgressReporter`][] if standard output is a
+/// terminal.  The number of files is only computed if a progress bar is shown.
+pub fn with_progress<R, F>(reporter: R, file_count: F) -> Box<dyn Reporter>
+where
+    R: Reporter + 'static,
+    F: FnOnce() -> usize,
+{
+    if std::io::stdout().is_terminal() {
+        Box::new(ProgressReporter::new(reporter, file_count()))
+    } else {
+        Box::new(reporter)
+    }
+}
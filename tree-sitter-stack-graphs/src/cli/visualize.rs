@@ -6,15 +6,23 @@
 // ------------------------------------------------------------------------------------------------
 
 use clap::Args;
+use clap::ValueEnum;
 use clap::ValueHint;
+use stack_graphs::serde::Filter;
 use stack_graphs::serde::NoFilter;
+use stack_graphs::serde::RegionFilter;
 use stack_graphs::stitching::Database;
 use stack_graphs::stitching::ForwardPartialPathStitcher;
 use stack_graphs::storage::SQLiteReader;
 use stack_graphs::NoCancellation;
+use std::fs::File;
+use std::io::BufWriter;
+use std::ops::Range;
 use std::path::Path;
 use std::path::PathBuf;
 
+use crate::loader::FileReader;
+
 /// Visualize database
 #[derive(Args)]
 #[clap(after_help = r#"LIMITATIONS:
@@ -30,6 +38,17 @@ pub struct VisualizeArgs {
     )]
     pub source_paths: Vec<PathBuf>,
 
+    /// Paths that are included in the visualization.
+    #[clap(long, value_enum, default_value_t = VisualizePaths::Complete)]
+    pub paths: VisualizePaths,
+
+    /// Only visualize the part of the graph around a range of lines, given as FIRST or
+    /// FIRST:LAST, using one-based, inclusive line numbers.  Includes the nodes whose source span
+    /// overlaps the lines, and the nodes that are connected to them.  Requires a single source
+    /// file.
+    #[clap(long, value_name = "LINES", value_parser = parse_lines)]
+    pub lines: Option<Range<usize>>,
+
     #[clap(
         long,
         short = 'o',
@@ -40,36 +59,108 @@ pub struct VisualizeArgs {
     pub output: PathBuf,
 }
 
+/// Paths that are included in a visualization.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum VisualizePaths {
+    /// Complete paths from the references in the given files, found by path stitching.
+    Complete,
+    /// The partial paths that are stored in the database for the given files.  As for all paths,
+    /// only those from references to definitions or jump to scope nodes are shown.
+    Stored,
+    /// No paths, only the stack graphs of the given files.
+    None,
+}
+
 impl VisualizeArgs {
     pub fn run(self, db_path: &Path) -> anyhow::Result<()> {
         let cancellation_flag = &NoCancellation;
         let mut db = SQLiteReader::open(&db_path)?;
+        let mut files = Vec::new();
         for source_path in &self.source_paths {
             let source_path = source_path.canonicalize()?;
             db.load_graphs_for_file_or_directory(&source_path, cancellation_flag)?;
+            for entry in db.list_file_or_directory(&source_path)?.try_iter()? {
+                files.push(entry?.path);
+            }
+        }
+        let mut paths_db = Database::new();
+        match self.paths {
+            VisualizePaths::Complete => {
+                let (graph, _, _) = db.get();
+                let starting_nodes = graph
+                    .iter_nodes()
+                    .filter(|n| graph[*n].is_reference())
+                    .collect::<Vec<_>>();
+                ForwardPartialPathStitcher::find_all_complete_partial_paths(
+                    &mut db,
+                    starting_nodes,
+                    cancellation_flag,
+                    |g, ps, p| {
+                        paths_db.add_partial_path(g, ps, p.clone());
+                    },
+                )?;
+            }
+            VisualizePaths::Stored => {
+                for file in &files {
+                    let paths = db.stored_partial_paths_for_file(
+                        &file.to_string_lossy(),
+                        cancellation_flag,
+                    )?;
+                    let (graph, partials, _) = db.get();
+                    for path in paths {
+                        paths_db.add_partial_path(graph, partials, path);
+                    }
+                }
+            }
+            VisualizePaths::None => {}
         }
-        let (graph, _, _) = db.get();
-        let starting_nodes = graph
-            .iter_nodes()
-            .filter(|n| graph[*n].is_reference())
-            .collect::<Vec<_>>();
-        let mut complete_paths_db = Database::new();
-        ForwardPartialPathStitcher::find_all_complete_partial_paths(
-            &mut db,
-            starting_nodes,
-            cancellation_flag,
-            |g, ps, p| {
-                complete_paths_db.add_partial_path(g, ps, p.clone());
-            },
-        )?;
         let (graph, partials, _) = db.get();
-        let html =
-            graph.to_html_string("stack-graph", partials, &mut complete_paths_db, &NoFilter)?;
+        let filter: Box<dyn Filter> = match &self.lines {
+            Some(lines) => {
+                let file = match files.as_slice() {
+                    [file] => graph.get_file(&file.to_string_lossy()),
+                    _ => None,
+                };
+                let file = file.ok_or_else(|| {
+                    anyhow::anyhow!("Visualizing lines requires a single indexed source file")
+                })?;
+                Box::new(RegionFilter::new(graph, file, lines.clone()))
+            }
+            None => Box::new(NoFilter),
+        };
         if let Some(dir) = self.output.parent() {
             std::fs::create_dir_all(dir)?;
         }
-        std::fs::write(&self.output, html)?;
+        let file = BufWriter::new(File::create(&self.output)?);
+        graph.write_html(
+            file,
+            "stack-graph",
+            partials,
+            &mut paths_db,
+            filter.as_ref(),
+            &mut FileReader::new(),
+        )?;
         println!("Visualization at {}", self.output.display());
         Ok(())
     }
 }
+
+/// Parses a one-based, inclusive line range of the form FIRST or FIRST:LAST into a zero-based,
+/// end-exclusive range.
+fn parse_lines(value: &str) -> Result<Range<usize>, String> {
+    let parse = |line: &str| match line.parse::<usize>() {
+        Ok(line) if line > 0 => Ok(line),
+        _ => Err(format!("invalid line number `{}`", line)),
+    };
+    let (first, last) = match value.split_once(':') {
+        Some((first, last)) => (parse(first)?, parse(last)?),
+        None => {
+            let line = parse(value)?;
+            (line, line)
+        }
+    };
+    if last < first {
+        return Err(format!("last line {} is before first line {}", last, first));
+    }
+    Ok(first - 1..last)
+}
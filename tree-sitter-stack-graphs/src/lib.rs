@@ -94,6 +94,19 @@
 //! }
 //! ```
 //!
+//! Symbols are matched case-sensitively by default.  Languages that resolve names case-insensitively
+//! can set the optional `case_insensitive` attribute on these nodes.  The symbol is then stored in
+//! case-folded form, so that it matches all other case-insensitive symbols that only differ in case.
+//! Make sure to set the attribute on both definitions and references, for example using an attribute
+//! shorthand.
+//!
+//! ``` skip
+//! (identifier) @id {
+//!   node new_node
+//!   attr (new_node) type = "push_symbol", symbol = (source-text @id), case_insensitive
+//! }
+//! ```
+//!
 //! Node types `pop_symbol` and `pop_scoped_symbol` allow an optional `is_definition` attribute, which
 //! marks that node as a proper definition.  Node types `push_symbol` and `push_scoped_symbol` allow
 //! an optional `is_reference` attribute, which marks the node as a proper reference.  When `is_definition`
@@ -248,6 +261,22 @@
 //! }
 //! ```
 //!
+//! ### Attaching metadata to nodes
+//!
+//! Information about definitions that tools should be able to show to users, such as documentation,
+//! type annotations, or visibility, can be attached to nodes using `metadata_*` attributes.  These
+//! work like debug attributes: the key is derived from the attribute name, and the value is the string
+//! representation of the attribute value.  Metadata is stored with the stack graph, and returned
+//! together with the definitions found by queries.
+//!
+//! ``` skip
+//! (function_definition name: (identifier) @id) @func {
+//!   node def
+//!   attr (def) type = "pop_symbol", symbol = (source-text @id), source_node = @func, is_definition
+//!   attr (def) metadata_visibility = "public"
+//! }
+//! ```
+//!
 //! ### Working with paths
 //!
 //! Built-in path functions are available to compute symbols that depend on path information, such as
@@ -293,6 +322,19 @@
 //! }
 //! ```
 //!
+//! ### Custom functions
+//!
+//! Language authors that need functions beyond the built-in set, for example to apply
+//! project-specific name mangling, can register them from Rust using
+//! [`StackGraphLanguage::add_function`][]. Custom functions are called just like built-in ones.
+//!
+//! ### Build observers
+//!
+//! Embedders can register a [`BuildObserver`][] using [`StackGraphLanguage::add_build_observer`][]
+//! to run code right before and after the rules are executed for a file. Observers receive the
+//! stack graph and the file, and can for example inject synthetic nodes before the rules run, or
+//! validate the nodes and edges that were created. An error returned by an observer fails the build.
+//!
 //! ## Using this crate from Rust
 //!
 //! If you need very fine-grained control over how to use the resulting stack graphs, you can
@@ -343,6 +385,7 @@ use stack_graphs::graph::File;
 use stack_graphs::graph::Node;
 use stack_graphs::graph::NodeID;
 use stack_graphs::graph::StackGraph;
+use stack_graphs::graph::Symbol;
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::collections::HashSet;
@@ -358,6 +401,7 @@ use std::time::Instant;
 use thiserror::Error;
 use tree_sitter::Parser;
 use tree_sitter_graph::functions::Functions;
+use tree_sitter_graph::graph::Attributes;
 use tree_sitter_graph::graph::Edge;
 use tree_sitter_graph::graph::Graph;
 use tree_sitter_graph::graph::GraphNode;
@@ -375,6 +419,10 @@ pub mod ci;
 pub mod cli;
 pub mod functions;
 pub mod loader;
+pub mod lsif;
+pub mod pipeline;
+#[cfg(feature = "scip")]
+pub mod scip;
 pub mod test;
 mod util;
 
@@ -393,12 +441,14 @@ static SCOPE_TYPE: &'static str = "scope";
 
 // Node attribute names
 static DEBUG_ATTR_PREFIX: &'static str = "debug_";
+static CASE_INSENSITIVE_ATTR: &str = "case_insensitive";
 static DEFINIENS_NODE_ATTR: &'static str = "definiens_node";
 static EMPTY_SOURCE_SPAN_ATTR: &'static str = "empty_source_span";
 static IS_DEFINITION_ATTR: &'static str = "is_definition";
 static IS_ENDPOINT_ATTR: &'static str = "is_endpoint";
 static IS_EXPORTED_ATTR: &'static str = "is_exported";
 static IS_REFERENCE_ATTR: &'static str = "is_reference";
+static METADATA_ATTR_PREFIX: &str = "metadata_";
 static SCOPE_ATTR: &'static str = "scope";
 static SOURCE_NODE_ATTR: &'static str = "source_node";
 static SYMBOL_ATTR: &'static str = "symbol";
@@ -410,6 +460,7 @@ static POP_SCOPED_SYMBOL_ATTRS: Lazy<HashSet<&'static str>> = Lazy::new(|| {
     HashSet::from([
         TYPE_ATTR,
         SYMBOL_ATTR,
+        CASE_INSENSITIVE_ATTR,
         IS_DEFINITION_ATTR,
         DEFINIENS_NODE_ATTR,
         SYNTAX_TYPE_ATTR,
@@ -419,15 +470,29 @@ static POP_SYMBOL_ATTRS: Lazy<HashSet<&'static str>> = Lazy::new(|| {
     HashSet::from([
         TYPE_ATTR,
         SYMBOL_ATTR,
+        CASE_INSENSITIVE_ATTR,
         IS_DEFINITION_ATTR,
         DEFINIENS_NODE_ATTR,
         SYNTAX_TYPE_ATTR,
     ])
 });
-static PUSH_SCOPED_SYMBOL_ATTRS: Lazy<HashSet<&'static str>> =
-    Lazy::new(|| HashSet::from([TYPE_ATTR, SYMBOL_ATTR, SCOPE_ATTR, IS_REFERENCE_ATTR]));
-static PUSH_SYMBOL_ATTRS: Lazy<HashSet<&'static str>> =
-    Lazy::new(|| HashSet::from([TYPE_ATTR, SYMBOL_ATTR, IS_REFERENCE_ATTR]));
+static PUSH_SCOPED_SYMBOL_ATTRS: Lazy<HashSet<&'static str>> = Lazy::new(|| {
+    HashSet::from([
+        TYPE_ATTR,
+        SYMBOL_ATTR,
+        CASE_INSENSITIVE_ATTR,
+        SCOPE_ATTR,
+        IS_REFERENCE_ATTR,
+    ])
+});
+static PUSH_SYMBOL_ATTRS: Lazy<HashSet<&'static str>> = Lazy::new(|| {
+    HashSet::from([
+        TYPE_ATTR,
+        SYMBOL_ATTR,
+        CASE_INSENSITIVE_ATTR,
+        IS_REFERENCE_ATTR,
+    ])
+});
 static SCOPE_ATTRS: Lazy<HashSet<&'static str>> =
     Lazy::new(|| HashSet::from([TYPE_ATTR, IS_EXPORTED_ATTR, IS_ENDPOINT_ATTR]));
 
@@ -446,6 +511,7 @@ pub struct StackGraphLanguage {
     tsg_path: PathBuf,
     tsg_source: std::borrow::Cow<'static, str>,
     functions: Functions,
+    observers: Vec<Arc<dyn BuildObserver + Send + Sync>>,
 }
 
 impl StackGraphLanguage {
@@ -462,6 +528,7 @@ impl StackGraphLanguage {
             tsg_path: PathBuf::from("<tsg>"),
             tsg_source: Cow::from(String::new()),
             functions: Self::default_functions(),
+            observers: Vec::new(),
         }
     }
 
@@ -479,6 +546,7 @@ impl StackGraphLanguage {
             tsg_path: PathBuf::from("<missing tsg path>"),
             tsg_source: Cow::from(tsg_source.to_string()),
             functions: Self::default_functions(),
+            observers: Vec::new(),
         })
     }
 
@@ -507,10 +575,35 @@ impl StackGraphLanguage {
         functions
     }
 
+    /// Returns a mutable reference to the functions that are available to the TSG stack graph
+    /// construction rules. The functions include the `tree-sitter-graph` standard library and
+    /// the [path functions][crate::functions::path].
     pub fn functions_mut(&mut self) -> &mut tree_sitter_graph::functions::Functions {
         &mut self.functions
     }
 
+    /// Registers an additional function that can be called from the TSG stack graph construction
+    /// rules. A function that was previously registered under the same name, including any of the
+    /// built-in functions, is replaced.
+    ///
+    /// Functions are resolved when the rules are executed, so functions can be added after the
+    /// rules have been parsed, as long as it happens before any stack graphs are built.
+    pub fn add_function<F>(&mut self, name: &str, function: F)
+    where
+        F: tree_sitter_graph::functions::Function + Send + Sync + 'static,
+    {
+        self.functions.add(name.into(), function);
+    }
+
+    /// Registers an observer that is called before and after the TSG stack graph construction
+    /// rules are executed for a file. Observers are called in the order in which they were added.
+    pub fn add_build_observer<O>(&mut self, observer: O)
+    where
+        O: BuildObserver + Send + Sync + 'static,
+    {
+        self.observers.push(Arc::new(observer));
+    }
+
     pub fn language(&self) -> tree_sitter::Language {
         self.language
     }
@@ -610,6 +703,10 @@ impl<'a> Builder<'a> {
     }
 
     /// Executes this builder.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(file = %self.stack_graph[self.file]))
+    )]
     pub fn build(
         mut self,
         globals: &'a Variables<'a>,
@@ -634,6 +731,12 @@ impl<'a> Builder<'a> {
         }
         let tree = parse_errors.into_tree();
 
+        for observer in &self.sgl.observers {
+            observer
+                .before_build(self.stack_graph, self.file, self.source)
+                .map_err(BuildError::ObserverError)?;
+        }
+
         let mut globals = Variables::nested(globals);
         if globals.get(&ROOT_NODE_VAR.into()).is_none() {
             let root_node = self.inject_node(NodeID::root());
@@ -681,7 +784,15 @@ impl<'a> Builder<'a> {
             &(cancellation_flag as &dyn CancellationFlag),
         )?;
 
-        self.load(cancellation_flag)
+        self.load(cancellation_flag)?;
+
+        for observer in &self.sgl.observers {
+            observer
+                .after_build(self.stack_graph, self.file, self.source)
+                .map_err(BuildError::ObserverError)?;
+        }
+
+        Ok(())
     }
 
     /// Create a graph node to represent the stack graph node. It is the callers responsibility to
@@ -826,6 +937,8 @@ pub enum BuildError {
     LanguageError(#[from] tree_sitter::LanguageError),
     #[error("Expected exported symbol scope in {0}, got {1}")]
     SymbolScopeError(String, String),
+    #[error(transparent)]
+    ObserverError(Box<dyn std::error::Error + Send + Sync>),
 }
 
 impl From<stack_graphs::CancellationError> for BuildError {
@@ -893,7 +1006,8 @@ impl std::fmt::Display for DisplayBuildErrorPretty<'_> {
 }
 
 impl<'a> Builder<'a> {
-    fn load(mut self, cancellation_flag: &dyn CancellationFlag) -> Result<(), BuildError> {
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    fn load(&mut self, cancellation_flag: &dyn CancellationFlag) -> Result<(), BuildError> {
         let cancellation_flag: &dyn stack_graphs::CancellationFlag = &cancellation_flag;
 
         // By default graph ids are used for stack graph local_ids. A remapping is computed
@@ -932,6 +1046,7 @@ impl<'a> Builder<'a> {
             };
             self.load_source_info(node_ref, handle)?;
             self.load_node_debug_info(node_ref, handle)?;
+            self.load_node_metadata(node_ref, handle)?;
         }
 
         for node in self.stack_graph.nodes_for_file(self.file) {
@@ -958,6 +1073,7 @@ impl<'a> Builder<'a> {
                     .add_edge(source_handle, sink_handle, precedence);
                 Self::load_edge_debug_info(
                     &mut self.stack_graph,
+                    self.sgl,
                     source_handle,
                     sink_handle,
                     edge,
@@ -1045,7 +1161,8 @@ impl<'a> Builder<'a> {
             Some(symbol) => self.load_symbol(symbol)?,
             None => return Err(BuildError::MissingSymbol(node_ref)),
         };
-        let symbol = self.stack_graph.add_symbol(&symbol);
+        let case_insensitive = self.load_flag(node, CASE_INSENSITIVE_ATTR)?;
+        let symbol = Self::add_symbol(self.stack_graph, &symbol, case_insensitive);
         let id = self.node_id_for_graph_node(node_ref);
         let is_definition = self.load_flag(node, IS_DEFINITION_ATTR)?;
         self.verify_attributes(node, POP_SCOPED_SYMBOL_TYPE, &POP_SCOPED_SYMBOL_ATTRS);
@@ -1065,7 +1182,8 @@ impl<'a> Builder<'a> {
             Some(symbol) => self.load_symbol(symbol)?,
             None => return Err(BuildError::MissingSymbol(node_ref)),
         };
-        let symbol = self.stack_graph.add_symbol(&symbol);
+        let case_insensitive = self.load_flag(node, CASE_INSENSITIVE_ATTR)?;
+        let symbol = Self::add_symbol(self.stack_graph, &symbol, case_insensitive);
         let id = self.node_id_for_graph_node(node_ref);
         let is_definition = self.load_flag(node, IS_DEFINITION_ATTR)?;
         self.verify_attributes(node, POP_SYMBOL_TYPE, &POP_SYMBOL_ATTRS);
@@ -1088,7 +1206,8 @@ impl<'a> Builder<'a> {
             Some(symbol) => self.load_symbol(symbol)?,
             None => return Err(BuildError::MissingSymbol(node_ref)),
         };
-        let symbol = self.stack_graph.add_symbol(&symbol);
+        let case_insensitive = self.load_flag(node, CASE_INSENSITIVE_ATTR)?;
+        let symbol = Self::add_symbol(self.stack_graph, &symbol, case_insensitive);
         let id = self.node_id_for_graph_node(node_ref);
         let scope = match node.attributes.get(SCOPE_ATTR) {
             Some(scope) => self.node_id_for_graph_node(scope.as_graph_node_ref()?),
@@ -1108,7 +1227,8 @@ impl<'a> Builder<'a> {
             Some(symbol) => self.load_symbol(symbol)?,
             None => return Err(BuildError::MissingSymbol(node_ref)),
         };
-        let symbol = self.stack_graph.add_symbol(&symbol);
+        let case_insensitive = self.load_flag(node, CASE_INSENSITIVE_ATTR)?;
+        let symbol = Self::add_symbol(self.stack_graph, &symbol, case_insensitive);
         let id = self.node_id_for_graph_node(node_ref);
         let is_reference = self.load_flag(node, IS_REFERENCE_ATTR)?;
         self.verify_attributes(node, PUSH_SYMBOL_TYPE, &PUSH_SYMBOL_ATTRS);
@@ -1135,6 +1255,18 @@ impl<'a> Builder<'a> {
         }
     }
 
+    fn add_symbol(
+        stack_graph: &mut StackGraph,
+        symbol: &str,
+        case_insensitive: bool,
+    ) -> Handle<Symbol> {
+        if case_insensitive {
+            stack_graph.add_case_insensitive_symbol(symbol)
+        } else {
+            stack_graph.add_symbol(symbol)
+        }
+    }
+
     fn load_flag(&self, node: &GraphNode, attribute: &str) -> Result<bool, BuildError> {
         match node.attributes.get(attribute) {
             Some(value) => value.as_boolean().map_err(|_| {
@@ -1200,44 +1332,45 @@ impl<'a> Builder<'a> {
         node_handle: Handle<Node>,
     ) -> Result<(), BuildError> {
         let node = &self.graph[node_ref];
-        for (name, value) in node.attributes.iter() {
-            let name = name.to_string();
-            if name.starts_with(DEBUG_ATTR_PREFIX) {
-                let value = match value {
-                    Value::String(value) => value.clone(),
-                    value => value.to_string(),
-                };
-                let key = self
-                    .stack_graph
-                    .add_string(&name[DEBUG_ATTR_PREFIX.len()..]);
-                let value = self.stack_graph.add_string(&value);
-                self.stack_graph
-                    .node_debug_info_mut(node_handle)
-                    .add(key, value);
-            }
+        for (name, value) in debug_attributes(self.sgl, &node.attributes) {
+            let key = self.stack_graph.add_string(&name);
+            let value = self.stack_graph.add_string(&value);
+            self.stack_graph
+                .node_debug_info_mut(node_handle)
+                .add(key, value);
+        }
+        Ok(())
+    }
+
+    fn load_node_metadata(
+        &mut self,
+        node_ref: GraphNodeRef,
+        node_handle: Handle<Node>,
+    ) -> Result<(), BuildError> {
+        let node = &self.graph[node_ref];
+        for (name, value) in prefixed_attributes(&node.attributes, METADATA_ATTR_PREFIX) {
+            let key = self.stack_graph.add_string(&name);
+            let value = self.stack_graph.add_string(&value);
+            self.stack_graph
+                .node_metadata_mut(node_handle)
+                .add(key, value);
         }
         Ok(())
     }
 
     fn load_edge_debug_info(
         stack_graph: &mut StackGraph,
+        sgl: &StackGraphLanguage,
         source_handle: Handle<Node>,
         sink_handle: Handle<Node>,
         edge: &Edge,
     ) -> Result<(), BuildError> {
-        for (name, value) in edge.attributes.iter() {
-            let name = name.to_string();
-            if name.starts_with(DEBUG_ATTR_PREFIX) {
-                let value = match value {
-                    Value::String(value) => value.clone(),
-                    value => value.to_string(),
-                };
-                let key = stack_graph.add_string(&name[DEBUG_ATTR_PREFIX.len()..]);
-                let value = stack_graph.add_string(&value);
-                stack_graph
-                    .edge_debug_info_mut(source_handle, sink_handle)
-                    .add(key, value);
-            }
+        for (name, value) in debug_attributes(sgl, &edge.attributes) {
+            let key = stack_graph.add_string(&name);
+            let value = stack_graph.add_string(&value);
+            stack_graph
+                .edge_debug_info_mut(source_handle, sink_handle)
+                .add(key, value);
         }
         Ok(())
     }
@@ -1254,6 +1387,7 @@ impl<'a> Builder<'a> {
                 && id != SOURCE_NODE_ATTR
                 && id != EMPTY_SOURCE_SPAN_ATTR
                 && !id.starts_with(DEBUG_ATTR_PREFIX)
+                && !id.starts_with(METADATA_ATTR_PREFIX)
             {
                 eprintln!("Unexpected attribute {} on node of type {}", id, node_type);
             }
@@ -1261,6 +1395,112 @@ impl<'a> Builder<'a> {
     }
 }
 
+/// Returns the names, without the prefix, and values of the attributes with the given prefix.
+/// Attributes are sorted by name, so that the resulting stack graph does not depend on the
+/// iteration order of the attributes.
+fn prefixed_attributes(attributes: &Attributes, prefix: &str) -> Vec<(String, String)> {
+    let mut result = attributes
+        .iter()
+        .filter_map(|(name, value)| {
+            let name = name.as_str().strip_prefix(prefix)?;
+            let value = match value {
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+            Some((name.to_string(), value))
+        })
+        .collect::<Vec<_>>();
+    result.sort();
+    result
+}
+
+/// Returns the debug attributes of a node or edge, together with the TSG file and the stanza
+/// that created it.  The stanza is found using the TSG location that is added to the debug
+/// attributes during execution.
+fn debug_attributes(sgl: &StackGraphLanguage, attributes: &Attributes) -> Vec<(String, String)> {
+    let mut result = prefixed_attributes(attributes, DEBUG_ATTR_PREFIX);
+    let row = result
+        .iter()
+        .find(|(name, _)| name == "tsg_location")
+        .and_then(|(_, value)| {
+            // the location is formatted as `line {} column {}`
+            value
+                .strip_prefix("line ")?
+                .split(' ')
+                .next()?
+                .parse::<usize>()
+                .ok()
+        })
+        .map(|line| line - 1);
+    result.push(("tsg_path".to_string(), sgl.tsg_path.display().to_string()));
+    if let Some(stanza) = row.and_then(|row| {
+        sgl.tsg
+            .stanzas
+            .iter()
+            .find(|s| s.range.start.row <= row && row <= s.range.end.row)
+    }) {
+        result.push((
+            "tsg_stanza".to_string(),
+            format!("line {}", stanza.range.start.row + 1),
+        ));
+    }
+    result.sort();
+    result
+}
+
+/// Observes the construction of stack graphs by a [`StackGraphLanguage`][]. Observers are
+/// registered with [`StackGraphLanguage::add_build_observer`][].
+pub trait BuildObserver {
+    /// Called after the source was parsed, and before the TSG rules are executed. Nodes that are
+    /// added to the file here do not conflict with the nodes created by the rules.
+    fn before_build(
+        &self,
+        _stack_graph: &mut StackGraph,
+        _file: Handle<File>,
+        _source: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+
+    /// Called after the nodes and edges created by the TSG rules were added to the stack graph.
+    fn after_build(
+        &self,
+        _stack_graph: &mut StackGraph,
+        _file: Handle<File>,
+        _source: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+}
+
+/// Builds stack graphs for the files it recognizes. Graph producers make it possible to analyze
+/// languages without a tree-sitter grammar, for example using a compiler plugin or a handwritten
+/// analyzer. Producers are registered with [`Loader::add_graph_producer`][], and are used for
+/// every file they match, in addition to any language configuration that matches the file.
+///
+/// [`Loader::add_graph_producer`]: crate::loader::Loader::add_graph_producer
+pub trait GraphProducer {
+    /// Returns whether this producer builds stack graphs for the given file.
+    fn matches_file(
+        &self,
+        path: &Path,
+        content: &mut dyn stack_graphs::ContentProvider,
+    ) -> std::io::Result<bool>;
+
+    /// Construct stack graph for the given file. Implementations must assume that nodes
+    /// for the given file may already exist, and make sure to prevent node id conflicts,
+    /// for example by using `StackGraph::new_node_id`.
+    fn build_stack_graph_into(
+        &self,
+        stack_graph: &mut StackGraph,
+        file: Handle<File>,
+        path: &Path,
+        source: &str,
+        globals: &Variables,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<(), BuildError>;
+}
+
 pub trait FileAnalyzer {
     /// Construct stack graph for the given file. Implementations must assume that nodes
     /// for the given file may already exist, and make sure to prevent node id conflicts,
@@ -7,12 +7,25 @@
 
 //! Defines file loader for stack graph languages
 
+use globset::GlobMatcher;
 use ini::Ini;
 use itertools::Itertools;
 use once_cell::sync::Lazy;
 use regex::Regex;
+#[cfg(feature = "cli")]
+use serde::Deserialize;
+#[cfg(feature = "cli")]
+use sha1::Digest;
+#[cfg(feature = "cli")]
+use sha1::Sha1;
+use stack_graphs::arena::Handle;
+use stack_graphs::graph::File;
 use stack_graphs::graph::StackGraph;
+#[cfg(feature = "cli")]
+use stack_graphs::serde::StackGraph as SerdeStackGraph;
 use std::borrow::Cow;
+#[cfg(feature = "cli")]
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::path::Path;
@@ -26,24 +39,44 @@ use tree_sitter_loader::Config as TsConfig;
 use tree_sitter_loader::LanguageConfiguration as TSLanguageConfiguration;
 use tree_sitter_loader::Loader as TsLoader;
 
+use crate::test::CommentSyntax;
+use crate::util::compile_glob;
+use crate::BuildError;
 use crate::CancellationFlag;
 use crate::FileAnalyzer;
+use crate::GraphProducer;
 use crate::StackGraphLanguage;
 
+pub use stack_graphs::ContentProvider;
+
 pub static DEFAULT_TSG_PATHS: Lazy<Vec<LoadPath>> =
     Lazy::new(|| vec![LoadPath::Grammar("queries/stack-graphs".into())]);
 pub static DEFAULT_BUILTINS_PATHS: Lazy<Vec<LoadPath>> =
     Lazy::new(|| vec![LoadPath::Grammar("queries/builtins".into())]);
 
+/// File name of sidecar files that define globals for all files in a directory and its subdirectories.
+pub static DIRECTORY_GLOBALS_FILE_NAME: &str = ".sg-globals.json";
+/// Suffix that is appended to a file name to get the name of the sidecar file that defines globals for that file.
+pub static FILE_GLOBALS_SUFFIX: &str = ".sg-globals.json";
+
 /// Data type that holds all information to recognize and analyze files for a language
 pub struct LanguageConfiguration {
     pub language: Language,
     pub scope: Option<String>,
     pub content_regex: Option<Regex>,
+    /// Regular expression matched against the first line of files that are not matched by file
+    /// type, such as scripts without file extension that start with a shebang line.
+    pub first_line_regex: Option<Regex>,
     pub file_types: Vec<String>,
     pub sgl: StackGraphLanguage,
     pub builtins: StackGraph,
+    /// Named builtins layers, such as framework stubs, that can be selectively loaded in addition
+    /// to the default `builtins`.
+    pub builtins_layers: HashMap<String, StackGraph>,
     pub special_files: FileAnalyzers,
+    /// Comment syntax in which test assertions and directives are written. If empty, they are
+    /// recognized anywhere in a line of a test file.
+    pub test_comments: Vec<CommentSyntax>,
 }
 
 impl LanguageConfiguration {
@@ -94,13 +127,63 @@ impl LanguageConfiguration {
             language,
             scope,
             content_regex,
+            first_line_regex: None,
             file_types,
             sgl,
             builtins,
+            builtins_layers: HashMap::new(),
             special_files,
+            test_comments: Vec::new(),
         })
     }
 
+    /// Add a named builtins layer from the given source and optional configuration. The path is
+    /// used as the name of the builtins file in the layer's stack graph, and must be different
+    /// from the paths of the default builtins and other layers. A layer previously added under
+    /// the same name is replaced.
+    pub fn add_builtins_layer<'a>(
+        &mut self,
+        name: String,
+        path: &Path,
+        source: &'a str,
+        config: Option<&str>,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<(), LoadError<'a>> {
+        let mut graph = StackGraph::new();
+        Loader::load_builtins_into(
+            &self.sgl,
+            path,
+            Cow::from(source),
+            config.unwrap_or_default(),
+            &mut graph,
+            cancellation_flag,
+        )?;
+        self.builtins_layers.insert(name, graph);
+        Ok(())
+    }
+
+    /// Load the default builtins, followed by the given builtins layers, into the stack graph.
+    pub fn load_builtins_into<S: AsRef<str>>(
+        &self,
+        graph: &mut StackGraph,
+        layers: &[S],
+    ) -> Result<(), LoadError<'static>> {
+        let mut builtins = vec![&self.builtins];
+        for layer in layers {
+            let layer = layer.as_ref();
+            match self.builtins_layers.get(layer) {
+                Some(layer) => builtins.push(layer),
+                None => return Err(LoadError::UnknownBuiltinsLayer(layer.to_string())),
+            }
+        }
+        for builtins in builtins {
+            if let Err(file) = graph.add_from_graph(builtins) {
+                return Err(LoadError::DuplicateBuiltinsFile(graph[file].to_string()));
+            }
+        }
+        Ok(())
+    }
+
     // Extracted from tree_sitter_loader::Loader::language_configuration_for_file_name
     fn best_for_file<'a>(
         languages: &'a Vec<LanguageConfiguration>,
@@ -119,6 +202,18 @@ impl LanguageConfiguration {
                 }
             }
         }
+        if best.is_some() {
+            return Ok(best);
+        }
+        for language in languages {
+            if let Some(score) = matches_file_first_line(&language.first_line_regex, path, content)?
+            {
+                if score > best_score {
+                    best_score = score;
+                    best = Some(language);
+                }
+            }
+        }
         Ok(best)
     }
 
@@ -127,19 +222,52 @@ impl LanguageConfiguration {
         path: &Path,
         content: &mut dyn ContentProvider,
     ) -> std::io::Result<bool> {
-        matches_file(&self.file_types, &self.content_regex, path, content).map(|l| l.is_some())
+        if matches_file(&self.file_types, &self.content_regex, path, content)?.is_some() {
+            return Ok(true);
+        }
+        matches_file_first_line(&self.first_line_regex, path, content).map(|l| l.is_some())
+    }
+}
+
+impl GraphProducer for LanguageConfiguration {
+    fn matches_file(
+        &self,
+        path: &Path,
+        content: &mut dyn ContentProvider,
+    ) -> std::io::Result<bool> {
+        LanguageConfiguration::matches_file(self, path, content)
+    }
+
+    fn build_stack_graph_into(
+        &self,
+        stack_graph: &mut StackGraph,
+        file: Handle<File>,
+        _path: &Path,
+        source: &str,
+        globals: &Variables,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<(), BuildError> {
+        self.sgl
+            .build_stack_graph_into(stack_graph, file, source, globals, cancellation_flag)
     }
 }
 
+/// Analyzers for special files, such as package manifests, identified by their file name.
+///
+/// Analyzers are added for exact file names using [`Self::add`][], or for file name globs, such
+/// as `*.gemspec` or `requirements*.txt`, using [`Self::add_glob`][]. Exact file names take
+/// precedence over globs, and globs are tried in the order they were added.
 #[derive(Clone, Default)]
 pub struct FileAnalyzers {
     file_analyzers: HashMap<String, Arc<dyn FileAnalyzer + Send + Sync>>,
+    glob_analyzers: Vec<(GlobMatcher, Arc<dyn FileAnalyzer + Send + Sync>)>,
 }
 
 impl FileAnalyzers {
     pub fn new() -> Self {
         FileAnalyzers {
             file_analyzers: HashMap::new(),
+            glob_analyzers: Vec::new(),
         }
     }
 
@@ -152,8 +280,30 @@ impl FileAnalyzers {
         self
     }
 
+    /// Add an analyzer for all files with a name that matches the given glob. Globs support the
+    /// wildcards `*`, which matches any sequence of characters, `?`, which matches any single
+    /// character, `[...]`, which matches any of the enclosed characters (or, if the first
+    /// character is `!`, any character that is not enclosed), and `{a,b}`, which matches any of
+    /// the comma-separated alternatives. An error is returned if the glob is invalid, which
+    /// loaders report as [`LoadError::InvalidGlob`][].
+    pub fn add_glob(
+        mut self,
+        glob: &str,
+        analyzer: impl FileAnalyzer + Send + Sync + 'static,
+    ) -> Result<Self, globset::Error> {
+        let glob = compile_glob(glob)?;
+        self.glob_analyzers.push((glob, Arc::new(analyzer)));
+        Ok(self)
+    }
+
     pub fn get(&self, file_name: &str) -> Option<Arc<dyn FileAnalyzer + Send + Sync>> {
-        self.file_analyzers.get(file_name).cloned()
+        if let Some(analyzer) = self.file_analyzers.get(file_name) {
+            return Some(analyzer.clone());
+        }
+        self.glob_analyzers
+            .iter()
+            .find(|(glob, _)| glob.is_match(file_name))
+            .map(|(_, analyzer)| analyzer.clone())
     }
 }
 
@@ -190,7 +340,10 @@ impl LoadPath {
 /// are always optional.
 ///
 /// Previously loaded languages are cached in the loader, so subsequent loads are fast.
-pub struct Loader(LoaderImpl);
+pub struct Loader {
+    inner: LoaderImpl,
+    producers: Vec<Arc<dyn GraphProducer + Send + Sync>>,
+}
 
 enum LoaderImpl {
     Paths(PathLoader),
@@ -204,14 +357,18 @@ impl Loader {
         tsg_paths: Vec<LoadPath>,
         builtins_paths: Vec<LoadPath>,
     ) -> Result<Self, LoadError<'static>> {
-        Ok(Self(LoaderImpl::Paths(PathLoader {
-            loader: SupplementedTsLoader::new()?,
-            paths,
-            scope,
-            tsg_paths,
-            builtins_paths,
-            cache: Vec::new(),
-        })))
+        Ok(Self {
+            inner: LoaderImpl::Paths(PathLoader {
+                loader: SupplementedTsLoader::new()?,
+                paths,
+                scope,
+                tsg_paths,
+                builtins_paths,
+                cache_dir: None,
+                cache: Vec::new(),
+            }),
+            producers: Vec::new(),
+        })
     }
 
     pub fn from_tree_sitter_configuration(
@@ -220,14 +377,18 @@ impl Loader {
         tsg_paths: Vec<LoadPath>,
         builtins_paths: Vec<LoadPath>,
     ) -> Result<Self, LoadError<'static>> {
-        Ok(Self(LoaderImpl::Paths(PathLoader {
-            loader: SupplementedTsLoader::new()?,
-            paths: PathLoader::config_paths(config)?,
-            scope,
-            tsg_paths,
-            builtins_paths,
-            cache: Vec::new(),
-        })))
+        Ok(Self {
+            inner: LoaderImpl::Paths(PathLoader {
+                loader: SupplementedTsLoader::new()?,
+                paths: PathLoader::config_paths(config)?,
+                scope,
+                tsg_paths,
+                builtins_paths,
+                cache_dir: None,
+                cache: Vec::new(),
+            }),
+            producers: Vec::new(),
+        })
     }
 
     pub fn from_language_configurations(
@@ -238,9 +399,41 @@ impl Loader {
             .into_iter()
             .filter(|lc| scope.is_none() || lc.scope == scope)
             .collect();
-        Ok(Self(LoaderImpl::Provided(LanguageConfigurationsLoader {
-            configurations,
-        })))
+        Ok(Self {
+            inner: LoaderImpl::Provided(LanguageConfigurationsLoader { configurations }),
+            producers: Vec::new(),
+        })
+    }
+
+    /// Create a loader for the languages declared in the given loader configuration. All declared
+    /// languages are loaded eagerly. If a cache directory is given, it is used to cache builtins,
+    /// as described for [`Self::set_cache_dir`][].
+    #[cfg(feature = "cli")]
+    pub fn from_config(
+        config: &LoaderConfig,
+        scope: Option<String>,
+        cache_dir: Option<&Path>,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Self, LoadError<'static>> {
+        let configurations = config.load(cache_dir, cancellation_flag)?;
+        Self::from_language_configurations(configurations, scope)
+    }
+
+    /// Set the directory that is used to cache builtins. Building the builtins of a language can be
+    /// expensive, and cached builtins are reused as long as the TSG, builtins source, and builtins
+    /// configuration are unchanged. Only builtins are cached. The TSG itself is parsed and checked
+    /// on every load, because compiled tree-sitter queries cannot be persisted. Caching is disabled
+    /// if no directory is set, which is the default.
+    ///
+    /// This only affects loaders that load languages from paths, as provided language configurations
+    /// have already been loaded. Caching requires serialization of stack graphs, and is therefore
+    /// only available with the `cli` feature.
+    #[cfg(feature = "cli")]
+    pub fn set_cache_dir(&mut self, cache_dir: Option<PathBuf>) {
+        match &mut self.inner {
+            LoaderImpl::Paths(loader) => loader.cache_dir = cache_dir,
+            LoaderImpl::Provided(_) => {}
+        }
     }
 
     /// Load a Tree-sitter language for the given file. Loading is based on the loader configuration and the given file path.
@@ -251,7 +444,7 @@ impl Loader {
         path: &Path,
         content: &mut dyn ContentProvider,
     ) -> Result<Option<tree_sitter::Language>, LoadError<'static>> {
-        match &mut self.0 {
+        match &mut self.inner {
             LoaderImpl::Paths(loader) => loader.load_tree_sitter_language_for_file(path, content),
             LoaderImpl::Provided(loader) => {
                 loader.load_tree_sitter_language_for_file(path, content)
@@ -260,16 +453,33 @@ impl Loader {
     }
 
     /// Load a stack graph language for the given file. Loading is based on the loader configuration and the given file path.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(path = %path.display()))
+    )]
     pub fn load_for_file<'a>(
         &'a mut self,
         path: &Path,
         content: &mut dyn ContentProvider,
         cancellation_flag: &dyn CancellationFlag,
     ) -> Result<FileLanguageConfigurations<'a>, LoadError<'static>> {
-        match &mut self.0 {
-            LoaderImpl::Paths(loader) => loader.load_for_file(path, content, cancellation_flag),
-            LoaderImpl::Provided(loader) => loader.load_for_file(path, content),
+        let mut lcs = match &mut self.inner {
+            LoaderImpl::Paths(loader) => loader.load_for_file(path, content, cancellation_flag)?,
+            LoaderImpl::Provided(loader) => loader.load_for_file(path, content)?,
+        };
+        for producer in &self.producers {
+            if producer.matches_file(path, content)? {
+                lcs.producers.push(producer.clone());
+            }
         }
+        Ok(lcs)
+    }
+
+    /// Add a graph producer, which builds stack graphs for the files it matches. Producers are
+    /// returned by [`Self::load_for_file`][] for matching files, in the order they were added,
+    /// regardless of whether a language configuration matches the file as well.
+    pub fn add_graph_producer(&mut self, producer: impl GraphProducer + Send + Sync + 'static) {
+        self.producers.push(Arc::new(producer));
     }
 
     pub fn load_globals_from_config_path(
@@ -291,6 +501,63 @@ impl Loader {
         Self::load_globals_from_config(&conf, globals)
     }
 
+    /// Load globals for the given source file from sidecar files. Directory sidecars, named
+    /// [`DIRECTORY_GLOBALS_FILE_NAME`][], are read for every directory from the source root down
+    /// to the directory containing the file. A file sidecar, named after the source file with
+    /// [`FILE_GLOBALS_SUFFIX`][] appended, is read last. Values from later sidecars override
+    /// values from earlier ones, as well as any values already present in `globals`.
+    ///
+    /// Sidecar files contain a JSON object, where every value must be a string.
+    pub fn load_globals_from_sidecars(
+        source_root: &Path,
+        source_path: &Path,
+        globals: &mut Variables,
+    ) -> Result<(), LoadError<'static>> {
+        let mut sidecar_paths = source_path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(source_root))
+            .map(|dir| dir.join(DIRECTORY_GLOBALS_FILE_NAME))
+            .collect::<Vec<_>>();
+        sidecar_paths.reverse();
+        let mut file_sidecar_path = source_path.as_os_str().to_os_string();
+        file_sidecar_path.push(FILE_GLOBALS_SUFFIX);
+        sidecar_paths.push(file_sidecar_path.into());
+
+        for sidecar_path in sidecar_paths {
+            if !sidecar_path.is_file() {
+                continue;
+            }
+            let sidecar = std::fs::read_to_string(&sidecar_path)?;
+            Self::load_globals_from_sidecar_str(&sidecar, globals).map_err(|err| {
+                LoadError::Reader(
+                    format!("Invalid globals in {}: {}", sidecar_path.display(), err).into(),
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    fn load_globals_from_sidecar_str(
+        sidecar: &str,
+        globals: &mut Variables,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let values = match serde_json::from_str(sidecar)? {
+            serde_json::Value::Object(values) => values,
+            _ => return Err("expected a JSON object".into()),
+        };
+        for (name, value) in values {
+            let value = match value {
+                serde_json::Value::String(value) => value,
+                _ => return Err(format!("value of {} is not a string", name).into()),
+            };
+            let name = name.as_str().into();
+            globals.remove(&name);
+            globals.add(name, value.into())?;
+        }
+        Ok(())
+    }
+
     fn load_tsg<'a>(
         language: Language,
         tsg_source: Cow<'a, str>,
@@ -352,11 +619,13 @@ pub struct FileLanguageConfigurations<'a> {
         &'a LanguageConfiguration,
         Arc<dyn FileAnalyzer + Send + Sync>,
     )>,
+    /// Any graph producers that were added to the loader and match the file.
+    pub producers: Vec<Arc<dyn GraphProducer + Send + Sync>>,
 }
 
 impl FileLanguageConfigurations<'_> {
     pub fn has_some(&self) -> bool {
-        self.primary.is_some() || !self.secondary.is_empty()
+        self.primary.is_some() || !self.secondary.is_empty() || !self.producers.is_empty()
     }
 }
 
@@ -398,6 +667,26 @@ pub enum LoadError<'a> {
     },
     #[error(transparent)]
     TreeSitter(anyhow::Error),
+    #[error("Unknown builtins layer {0}")]
+    UnknownBuiltinsLayer(String),
+    #[error("Duplicate builtins file {0}")]
+    DuplicateBuiltinsFile(String),
+    #[error(transparent)]
+    InvalidGlob(#[from] globset::Error),
+    #[cfg(feature = "cli")]
+    #[error("Invalid loader configuration {}: {inner}", path.display())]
+    InvalidLoaderConfig {
+        path: PathBuf,
+        #[source]
+        inner: Box<toml::de::Error>,
+    },
+    #[cfg(feature = "cli")]
+    #[error("Invalid first line regex: {inner}")]
+    InvalidFirstLineRegex {
+        regex: String,
+        #[source]
+        inner: regex::Error,
+    },
 }
 
 impl LoadError<'_> {
@@ -480,7 +769,11 @@ impl LanguageConfigurationsLoader {
                 secondary.push((language, fa));
             }
         }
-        Ok(FileLanguageConfigurations { primary, secondary })
+        Ok(FileLanguageConfigurations {
+            primary,
+            secondary,
+            producers: Vec::new(),
+        })
     }
 }
 
@@ -493,6 +786,7 @@ struct PathLoader {
     scope: Option<String>,
     tsg_paths: Vec<LoadPath>,
     builtins_paths: Vec<LoadPath>,
+    cache_dir: Option<PathBuf>,
     cache: Vec<(Language, LanguageConfiguration)>,
 }
 
@@ -549,27 +843,14 @@ impl PathLoader {
         let index = match index {
             Some(index) => index,
             None => {
-                let tsg = self.load_tsg_from_paths(&language)?;
-                let sgl = StackGraphLanguage::new(language.language, tsg);
-
-                let mut builtins = StackGraph::new();
-                self.load_builtins_from_paths_into(
-                    &language,
-                    &sgl,
-                    &mut builtins,
+                let lc = Self::load_language_configuration(
+                    language,
+                    &self.tsg_paths,
+                    &self.builtins_paths,
+                    self.cache_dir.as_deref(),
                     cancellation_flag,
                 )?;
-
-                let lc = LanguageConfiguration {
-                    language: language.language,
-                    scope: language.scope,
-                    content_regex: language.content_regex,
-                    file_types: language.file_types,
-                    sgl,
-                    builtins,
-                    special_files: FileAnalyzers::new(),
-                };
-                self.cache.push((language.language, lc));
+                self.cache.push((lc.language, lc));
 
                 self.cache.len() - 1
             }
@@ -578,6 +859,7 @@ impl PathLoader {
         Ok(FileLanguageConfigurations {
             primary: Some(lc),
             secondary: Vec::default(),
+            producers: Vec::default(),
         })
     }
 
@@ -641,41 +923,91 @@ impl PathLoader {
         Ok(None)
     }
 
-    // Load the TSG file for the given language and path
-    fn load_tsg_from_paths(
-        &self,
+    // Load the language configuration for the given language, using the given TSG and builtins paths
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(scope = ?language.scope))
+    )]
+    fn load_language_configuration(
+        language: SupplementedLanguage,
+        tsg_paths: &[LoadPath],
+        builtins_paths: &[LoadPath],
+        cache_dir: Option<&Path>,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<LanguageConfiguration, LoadError<'static>> {
+        let sgl = Self::load_sgl_from_paths(tsg_paths, &language)?;
+
+        let mut builtins = StackGraph::new();
+        Self::load_builtins_from_paths_into(
+            builtins_paths,
+            &language,
+            &sgl,
+            &mut builtins,
+            cache_dir,
+            cancellation_flag,
+        )?;
+
+        let builtins_layers = Self::load_builtins_layers_from_paths(
+            builtins_paths,
+            &language,
+            &sgl,
+            cache_dir,
+            cancellation_flag,
+        )?;
+
+        Ok(LanguageConfiguration {
+            language: language.language,
+            scope: language.scope,
+            content_regex: language.content_regex,
+            first_line_regex: language.first_line_regex,
+            file_types: language.file_types,
+            sgl,
+            builtins,
+            builtins_layers,
+            special_files: FileAnalyzers::new(),
+            test_comments: Vec::new(),
+        })
+    }
+
+    // Load the stack graph language from the TSG file for the given language and path
+    fn load_sgl_from_paths(
+        tsg_paths: &[LoadPath],
         language: &SupplementedLanguage,
-    ) -> Result<TsgFile, LoadError<'static>> {
-        for tsg_path in &self.tsg_paths {
+    ) -> Result<StackGraphLanguage, LoadError<'static>> {
+        for tsg_path in tsg_paths {
             let mut tsg_path = tsg_path.get_for_grammar(&language.root_path);
             if tsg_path.extension().is_none() {
                 tsg_path.set_extension("tsg");
             }
             if tsg_path.exists() {
-                let tsg_source = std::fs::read_to_string(tsg_path)?;
-                return Loader::load_tsg(language.language, Cow::from(tsg_source));
+                let tsg_source = std::fs::read_to_string(&tsg_path)?;
+                let tsg = Loader::load_tsg(language.language, Cow::from(tsg_source.clone()))?;
+                let mut sgl = StackGraphLanguage::new(language.language, tsg);
+                sgl.set_tsg_info(tsg_path, Cow::from(tsg_source));
+                return Ok(sgl);
             }
         }
         return Err(LoadError::NoTsgFound);
     }
 
     // Builtins are loaded from queries/builtins.EXT and an optional queries/builtins.cfg configuration.
-    // In the future, we may extend this to support builtins spread over multiple files queries/builtins/NAME.EXT
-    // and optional corresponding configuration files queries/builtins/NAME.cfg.
+    // Builtins layers are loaded separately, see load_builtins_layers_from_paths.
     fn load_builtins_from_paths_into(
-        &self,
+        builtins_paths: &[LoadPath],
         language: &SupplementedLanguage,
         sgl: &StackGraphLanguage,
         graph: &mut StackGraph,
+        cache_dir: Option<&Path>,
         cancellation_flag: &dyn CancellationFlag,
     ) -> Result<(), LoadError<'static>> {
-        for builtins_path in &self.builtins_paths {
+        for builtins_path in builtins_paths {
             let mut builtins_path = builtins_path.get_for_grammar(&language.root_path);
             if builtins_path.exists() && !builtins_path.is_dir() {
                 return Self::load_builtins_from_path_into(
                     sgl,
                     &builtins_path,
                     graph,
+                    cache_dir,
                     cancellation_flag,
                 );
             }
@@ -686,6 +1018,7 @@ impl PathLoader {
                         sgl,
                         &builtins_path,
                         graph,
+                        cache_dir,
                         cancellation_flag,
                     );
                 }
@@ -694,10 +1027,59 @@ impl PathLoader {
         Ok(())
     }
 
+    // Builtins layers are loaded from queries/builtins/NAME.EXT and optional corresponding configuration
+    // files queries/builtins/NAME.cfg. Layers are taken from the first builtins path that is a directory.
+    fn load_builtins_layers_from_paths(
+        builtins_paths: &[LoadPath],
+        language: &SupplementedLanguage,
+        sgl: &StackGraphLanguage,
+        cache_dir: Option<&Path>,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<HashMap<String, StackGraph>, LoadError<'static>> {
+        let mut layers = HashMap::new();
+        for builtins_path in builtins_paths {
+            let mut layers_path = builtins_path.get_for_grammar(&language.root_path);
+            if !layers_path.is_dir() {
+                layers_path.set_extension("");
+            }
+            if !layers_path.is_dir() {
+                continue;
+            }
+            for entry in std::fs::read_dir(&layers_path)? {
+                let layer_path = entry?.path();
+                if layer_path.is_dir() {
+                    continue;
+                }
+                let (name, extension) = match (
+                    layer_path.file_stem().and_then(OsStr::to_str),
+                    layer_path.extension().and_then(OsStr::to_str),
+                ) {
+                    (Some(name), Some(extension)) => (name, extension),
+                    _ => continue,
+                };
+                if !language.file_types.iter().any(|t| t == extension) {
+                    continue;
+                }
+                let mut graph = StackGraph::new();
+                Self::load_builtins_from_path_into(
+                    sgl,
+                    &layer_path,
+                    &mut graph,
+                    cache_dir,
+                    cancellation_flag,
+                )?;
+                layers.insert(name.to_string(), graph);
+            }
+            break;
+        }
+        Ok(layers)
+    }
+
     fn load_builtins_from_path_into(
         sgl: &StackGraphLanguage,
         builtins_path: &Path,
         graph: &mut StackGraph,
+        cache_dir: Option<&Path>,
         cancellation_flag: &dyn CancellationFlag,
     ) -> Result<(), LoadError<'static>> {
         let source = std::fs::read_to_string(builtins_path.clone())?;
@@ -708,6 +1090,28 @@ impl PathLoader {
         } else {
             "".into()
         };
+        // caching requires serialization of stack graphs, which is only enabled for the CLI
+        #[cfg(feature = "cli")]
+        if let Some(cache_dir) = cache_dir {
+            let key = Self::builtins_cache_key(sgl, builtins_path, &source, &config);
+            let cache_path = cache_dir.join(format!("{}.json", key));
+            if Self::load_cached_builtins_into(&cache_path, graph) {
+                return Ok(());
+            }
+            Loader::load_builtins_into(
+                sgl,
+                builtins_path,
+                Cow::from(source),
+                &config,
+                graph,
+                cancellation_flag,
+            )?;
+            // failing to cache the builtins is not fatal, they will be rebuilt next time
+            let _ = Self::store_cached_builtins(&cache_path, graph);
+            return Ok(());
+        }
+        #[cfg(not(feature = "cli"))]
+        let _ = cache_dir;
         Loader::load_builtins_into(
             sgl,
             builtins_path,
@@ -715,7 +1119,314 @@ impl PathLoader {
             &config,
             graph,
             cancellation_flag,
-        )
+        )?;
+        Ok(())
+    }
+
+    // Cached builtins are keyed by a hash of everything that determines the builtins graph:
+    // the crate version, the grammar, the TSG source, and the builtins path, source, and
+    // configuration.
+    #[cfg(feature = "cli")]
+    fn builtins_cache_key(
+        sgl: &StackGraphLanguage,
+        builtins_path: &Path,
+        source: &str,
+        config: &str,
+    ) -> String {
+        let mut hasher = Sha1::new();
+        for value in [
+            env!("CARGO_PKG_VERSION"),
+            &Self::grammar_fingerprint(&sgl.language()),
+            sgl.tsg_source(),
+            &builtins_path.to_string_lossy(),
+            source,
+            config,
+        ] {
+            hasher.update((value.len() as u64).to_le_bytes());
+            hasher.update(value);
+        }
+        hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    // The grammar is identified by its ABI version, and its node kind and field tables, which
+    // change whenever the grammar is regenerated with different rules.
+    #[cfg(feature = "cli")]
+    fn grammar_fingerprint(language: &Language) -> String {
+        let mut fingerprint = format!("version {}\n", language.version());
+        for id in 0..language.node_kind_count() as u16 {
+            fingerprint += &format!(
+                "kind {} {:?} {} {}\n",
+                id,
+                language.node_kind_for_id(id),
+                language.node_kind_is_named(id),
+                language.node_kind_is_visible(id),
+            );
+        }
+        for id in 1..=language.field_count() as u16 {
+            fingerprint += &format!("field {} {:?}\n", id, language.field_name_for_id(id));
+        }
+        fingerprint
+    }
+
+    // Load cached builtins into the graph, returning whether the cache could be used.
+    #[cfg(feature = "cli")]
+    fn load_cached_builtins_into(cache_path: &Path, graph: &mut StackGraph) -> bool {
+        let cached = match std::fs::read(cache_path)
+            .ok()
+            .and_then(|cached| serde_json::from_slice::<SerdeStackGraph>(&cached).ok())
+        {
+            Some(cached) => cached,
+            None => return false,
+        };
+        let mut builtins = StackGraph::new();
+        if cached.load_into(&mut builtins).is_err() {
+            return false;
+        }
+        graph.add_from_graph(&builtins).is_ok()
+    }
+
+    #[cfg(feature = "cli")]
+    fn store_cached_builtins(cache_path: &Path, graph: &StackGraph) -> std::io::Result<()> {
+        if let Some(cache_dir) = cache_path.parent() {
+            std::fs::create_dir_all(cache_dir)?;
+        }
+        let cached = serde_json::to_vec(&graph.to_serializable())?;
+        // write to a temporary file first, so concurrent readers never see partial files
+        let mut tmp_path = cache_path.to_path_buf();
+        tmp_path.set_extension(format!("{}.tmp", std::process::id()));
+        std::fs::write(&tmp_path, cached)?;
+        std::fs::rename(&tmp_path, cache_path)
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// configuration file
+
+/// The default file name of loader configuration files.
+#[cfg(feature = "cli")]
+pub static DEFAULT_CONFIG_FILE_NAME: &str = "stack-graphs.toml";
+
+/// A loader configuration, which declares the languages that should be loaded. Configurations are
+/// typically read from a `stack-graphs.toml` file, which looks as follows:
+///
+/// ``` toml
+/// [[language]]
+/// grammar = "vendor/tree-sitter-javascript"
+/// scope = "source.js"
+/// tsg = "stack-graphs/javascript.tsg"
+/// builtins = "stack-graphs/builtins.js"
+/// file-types = ["js", "mjs"]
+/// first-line-regex = "^#!.*\\bnode\\b"
+/// line-comments = ["//"]
+/// block-comments = [["/*", "*/"]]
+///
+/// [language.special-files."package.json"]
+/// grammar = "vendor/tree-sitter-json"
+/// tsg = "stack-graphs/package-json.tsg"
+/// ```
+///
+/// Only the `grammar` field is required. If `scope` is set, the language with that scope is selected
+/// from the grammar, otherwise the first language is used. If `tsg` or `builtins` are omitted, the
+/// default locations relative to the grammar are used, as in [`Loader::from_paths`][]. If `file-types`
+/// or `first-line-regex` are omitted, the values declared by the grammar are used. Special files, which are file name
+/// globs as supported by [`FileAnalyzers::add_glob`][], are analyzed using the given TSG file with the grammar
+/// of the special file, or the language grammar if the special file does not specify one.
+/// If `line-comments` or `block-comments` are given, test assertions and directives are only
+/// recognized in comments of that syntax.
+///
+/// Relative paths are resolved against the directory containing the configuration file.
+#[cfg(feature = "cli")]
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LoaderConfig {
+    #[serde(default, rename = "language")]
+    pub languages: Vec<LanguageConfig>,
+}
+
+/// The configuration of a single language in a [`LoaderConfig`][].
+#[cfg(feature = "cli")]
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct LanguageConfig {
+    pub grammar: PathBuf,
+    pub scope: Option<String>,
+    pub tsg: Option<PathBuf>,
+    pub builtins: Option<PathBuf>,
+    pub file_types: Option<Vec<String>>,
+    pub first_line_regex: Option<String>,
+    #[serde(default)]
+    pub line_comments: Vec<String>,
+    #[serde(default)]
+    pub block_comments: Vec<(String, String)>,
+    #[serde(default)]
+    pub special_files: BTreeMap<String, SpecialFileConfig>,
+}
+
+/// The configuration of a special file of a [`LanguageConfig`][].
+#[cfg(feature = "cli")]
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpecialFileConfig {
+    pub grammar: Option<PathBuf>,
+    pub scope: Option<String>,
+    pub tsg: PathBuf,
+}
+
+#[cfg(feature = "cli")]
+impl LoaderConfig {
+    /// Read a loader configuration from the given file.
+    pub fn from_path(path: &Path) -> Result<Self, LoadError<'static>> {
+        let source = std::fs::read_to_string(path)?;
+        let base_path = path.parent().unwrap_or(Path::new(""));
+        Self::from_str(&source, base_path).map_err(|err| match err {
+            LoadError::InvalidLoaderConfig { inner, .. } => LoadError::InvalidLoaderConfig {
+                path: path.to_path_buf(),
+                inner,
+            },
+            err => err,
+        })
+    }
+
+    /// Parse a loader configuration from the given source. Relative paths are resolved against
+    /// the given base path.
+    pub fn from_str(source: &str, base_path: &Path) -> Result<Self, LoadError<'static>> {
+        let mut config: Self =
+            toml::from_str(source).map_err(|inner| LoadError::InvalidLoaderConfig {
+                path: PathBuf::from("<config>"),
+                inner: Box::new(inner),
+            })?;
+        for language in &mut config.languages {
+            language.grammar = base_path.join(&language.grammar);
+            language.tsg = language.tsg.as_ref().map(|p| base_path.join(p));
+            language.builtins = language.builtins.as_ref().map(|p| base_path.join(p));
+            for special_file in language.special_files.values_mut() {
+                special_file.grammar = special_file.grammar.as_ref().map(|p| base_path.join(p));
+                special_file.tsg = base_path.join(&special_file.tsg);
+            }
+        }
+        Ok(config)
+    }
+
+    /// Find a configuration file with the default name in the given directory or any of its ancestors.
+    pub fn find(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .map(|dir| dir.join(DEFAULT_CONFIG_FILE_NAME))
+            .find(|path| path.is_file())
+    }
+
+    /// Load the language configurations declared in this configuration. If a cache directory is
+    /// given, it is used to cache builtins, as described for [`Loader::set_cache_dir`][].
+    pub fn load(
+        &self,
+        cache_dir: Option<&Path>,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Vec<LanguageConfiguration>, LoadError<'static>> {
+        let mut loader = SupplementedTsLoader::new()?;
+        let mut configurations = Vec::new();
+        for config in &self.languages {
+            let mut language =
+                Self::select_language(&mut loader, &config.grammar, config.scope.as_deref())?;
+            if let Some(file_types) = &config.file_types {
+                language.file_types = file_types.clone();
+            }
+            if let Some(first_line_regex) = &config.first_line_regex {
+                language.first_line_regex =
+                    Some(Regex::new(first_line_regex).map_err(|inner| {
+                        LoadError::InvalidFirstLineRegex {
+                            regex: first_line_regex.clone(),
+                            inner,
+                        }
+                    })?);
+            }
+            let tsg_paths = match &config.tsg {
+                Some(tsg_path) => vec![LoadPath::Regular(tsg_path.clone())],
+                None => DEFAULT_TSG_PATHS.clone(),
+            };
+            let builtins_paths = match &config.builtins {
+                Some(builtins_path) => vec![LoadPath::Regular(builtins_path.clone())],
+                None => DEFAULT_BUILTINS_PATHS.clone(),
+            };
+            let mut lc = PathLoader::load_language_configuration(
+                language,
+                &tsg_paths,
+                &builtins_paths,
+                cache_dir,
+                cancellation_flag,
+            )?;
+            lc.test_comments = config
+                .line_comments
+                .iter()
+                .map(|prefix| CommentSyntax::Line(prefix.clone()))
+                .chain(
+                    config
+                        .block_comments
+                        .iter()
+                        .map(|(open, close)| CommentSyntax::Block(open.clone(), close.clone())),
+                )
+                .collect();
+            for (file_name, special_file) in &config.special_files {
+                let grammar = special_file.grammar.as_ref().unwrap_or(&config.grammar);
+                let scope = match &special_file.grammar {
+                    Some(_) => special_file.scope.as_deref(),
+                    None => special_file.scope.as_deref().or(config.scope.as_deref()),
+                };
+                let language = Self::select_language(&mut loader, grammar, scope)?;
+                let sgl = PathLoader::load_sgl_from_paths(
+                    &[LoadPath::Regular(special_file.tsg.clone())],
+                    &language,
+                )?;
+                lc.special_files = lc
+                    .special_files
+                    .add_glob(file_name, StackGraphLanguageAnalyzer(sgl))?;
+            }
+            configurations.push(lc);
+        }
+        Ok(configurations)
+    }
+
+    fn select_language(
+        loader: &mut SupplementedTsLoader,
+        grammar: &Path,
+        scope: Option<&str>,
+    ) -> Result<SupplementedLanguage, LoadError<'static>> {
+        match loader.languages_at_path(grammar, scope)?.first() {
+            Some(language) => Ok((*language).clone()),
+            None => Err(LoadError::NoLanguagesFound(format!(
+                "at {}{}",
+                grammar.display(),
+                scope.map_or(String::default(), |s| format!(" for scope {}", s)),
+            ))),
+        }
+    }
+}
+
+/// A file analyzer that uses a stack graph language to analyze files. The globals passed
+/// to the analyzer are made available to the TSG rules.
+pub struct StackGraphLanguageAnalyzer(pub StackGraphLanguage);
+
+impl FileAnalyzer for StackGraphLanguageAnalyzer {
+    fn build_stack_graph_into(
+        &self,
+        stack_graph: &mut StackGraph,
+        file: Handle<File>,
+        _path: &Path,
+        source: &str,
+        _all_paths: &mut dyn Iterator<Item = &Path>,
+        globals: &HashMap<String, String>,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<(), BuildError> {
+        let mut variables = Variables::new();
+        for (name, value) in globals {
+            variables
+                .add(name.as_str().into(), value.as_str().into())
+                .expect("globals are unique");
+        }
+        self.0
+            .build_stack_graph_into(stack_graph, file, source, &variables, cancellation_flag)
     }
 }
 
@@ -735,7 +1446,7 @@ impl SupplementedTsLoader {
         &mut self,
         path: &Path,
         scope: Option<&str>,
-    ) -> Result<Vec<&SupplementedLanguage>, LoadError> {
+    ) -> Result<Vec<&SupplementedLanguage>, LoadError<'static>> {
         if !self.1.contains_key(path) {
             let languages = self
                 .0
@@ -762,6 +1473,7 @@ struct SupplementedLanguage {
     pub language: Language,
     pub scope: Option<String>,
     pub content_regex: Option<Regex>,
+    pub first_line_regex: Option<Regex>,
     pub file_types: Vec<String>,
     pub root_path: PathBuf,
 }
@@ -788,8 +1500,20 @@ impl SupplementedLanguage {
     ) -> std::io::Result<Option<&'a SupplementedLanguage>> {
         let mut best_score = -1isize;
         let mut best = None;
-        for language in languages {
+        for language in &languages {
             if let Some(score) = language.matches_file(path, content)? {
+                if score > best_score {
+                    best_score = score;
+                    best = Some(*language);
+                }
+            }
+        }
+        if best.is_some() {
+            return Ok(best);
+        }
+        for language in languages {
+            if let Some(score) = matches_file_first_line(&language.first_line_regex, path, content)?
+            {
                 if score > best_score {
                     best_score = score;
                     best = Some(language);
@@ -805,6 +1529,7 @@ impl From<(Language, &TSLanguageConfiguration<'_>)> for SupplementedLanguage {
         Self {
             scope: config.scope.clone(),
             content_regex: config.content_regex.clone(),
+            first_line_regex: config._first_line_regex.clone(),
             file_types: config.file_types.clone(),
             root_path: config.root_path.clone(),
             language,
@@ -844,8 +1569,28 @@ pub fn matches_file(
     Ok(Some(0isize))
 }
 
-pub trait ContentProvider {
-    fn get(&mut self, path: &Path) -> std::io::Result<Option<&str>>;
+/// Score a file by matching the first line of its content against the given regex. This is meant
+/// for files that are not matched by file type, such as scripts without file extension that start
+/// with a shebang line. Files that cannot be read as text do not match.
+pub fn matches_file_first_line(
+    first_line_regex: &Option<Regex>,
+    path: &Path,
+    content: &mut dyn ContentProvider,
+) -> std::io::Result<Option<isize>> {
+    let first_line_regex = match first_line_regex {
+        Some(first_line_regex) => first_line_regex,
+        None => return Ok(None),
+    };
+    let content = match content.get(path) {
+        Ok(Some(content)) => content,
+        Ok(None) => return Ok(None),
+        Err(err) if err.kind() == std::io::ErrorKind::InvalidData => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let first_line = content.lines().next().unwrap_or_default();
+    Ok(first_line_regex
+        .find(first_line)
+        .map(|mat| (mat.end() - mat.start()) as isize))
 }
 
 /// FileReader reads files from the filesystem and caches the most recently read file.
@@ -872,9 +1617,3 @@ impl ContentProvider for FileReader {
         self.get(path).map(Some)
     }
 }
-
-impl ContentProvider for Option<&str> {
-    fn get(&mut self, _path: &Path) -> std::io::Result<Option<&str>> {
-        Ok(self.clone())
-    }
-}
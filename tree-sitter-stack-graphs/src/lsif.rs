@@ -0,0 +1,261 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Exports resolved references in the [LSIF][] format, so that they can be used by code hosts and
+//! editors that support LSIF dumps.
+//!
+//! References and the definitions they resolve to are collected in an [`LsifExporter`][], which
+//! writes them as an LSIF dump of JSON lines.  Ranges use zero-based lines and UTF-16 character
+//! offsets, like the Language Server Protocol.
+//!
+//! [LSIF]: https://microsoft.github.io/language-server-protocol/specifications/lsif/0.6.0/specification/
+
+use lsp_positions::Span;
+use serde_json::json;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// The LSIF version of the dumps written by [`LsifExporter`][].
+pub const LSIF_VERSION: &str = "0.6.0";
+
+/// Collects references and their definitions, and writes them as an LSIF dump.
+pub struct LsifExporter {
+    project_root: PathBuf,
+    ranges: BTreeMap<RangeKey, RangeInfo>,
+}
+
+/// Identifies a range in a document.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+struct RangeKey {
+    path: PathBuf,
+    start: (usize, usize),
+    end: (usize, usize),
+}
+
+impl RangeKey {
+    fn new(path: &Path, span: &Span) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            start: (span.start.line, span.start.column.utf16_offset),
+            end: (span.end.line, span.end.column.utf16_offset),
+        }
+    }
+}
+
+#[derive(Default)]
+struct RangeInfo {
+    /// The definitions of the range, if it is a reference.
+    definitions: BTreeSet<RangeKey>,
+    /// The references to the range, if it is a definition.
+    references: BTreeSet<RangeKey>,
+    is_definition: bool,
+}
+
+impl LsifExporter {
+    /// Creates a new exporter for a project in the given root directory.
+    pub fn new(project_root: &Path) -> Self {
+        Self {
+            project_root: project_root.to_path_buf(),
+            ranges: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a reference together with the definitions it resolves to.  References without
+    /// definitions are included in the dump as ranges without results.
+    pub fn add_reference<'a, I>(&mut self, path: &Path, span: &Span, definitions: I)
+    where
+        I: IntoIterator<Item = (&'a Path, &'a Span)>,
+    {
+        let reference = RangeKey::new(path, span);
+        for (definition_path, definition_span) in definitions {
+            let definition = RangeKey::new(definition_path, definition_span);
+            let info = self.ranges.entry(definition.clone()).or_default();
+            info.is_definition = true;
+            info.references.insert(reference.clone());
+            self.ranges
+                .entry(reference.clone())
+                .or_default()
+                .definitions
+                .insert(definition);
+        }
+        self.ranges.entry(reference).or_default();
+    }
+
+    /// Writes the LSIF dump of all references that were added, as JSON lines.
+    pub fn write<W: Write>(&self, writer: W) -> std::io::Result<()> {
+        let mut dump = Dump { writer, next_id: 1 };
+        dump.vertex(json!({
+            "label": "metaData",
+            "version": LSIF_VERSION,
+            "projectRoot": file_uri(&self.project_root),
+            "positionEncoding": "utf-16",
+            "toolInfo": {
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+        }))?;
+
+        // documents and their ranges
+        let mut document_ids = BTreeMap::new();
+        let mut range_ids = BTreeMap::new();
+        let mut ranges = self.ranges.keys().peekable();
+        while let Some(first) = ranges.peek() {
+            let path = first.path.clone();
+            let document_id = dump.vertex(json!({
+                "label": "document",
+                "uri": file_uri(&path),
+                "languageId": path.extension().map(|e| e.to_string_lossy()).unwrap_or_default(),
+            }))?;
+            let mut contained = Vec::new();
+            while let Some(range) = ranges.next_if(|range| range.path == path) {
+                let range_id = dump.vertex(json!({
+                    "label": "range",
+                    "start": { "line": range.start.0, "character": range.start.1 },
+                    "end": { "line": range.end.0, "character": range.end.1 },
+                }))?;
+                range_ids.insert(range, range_id);
+                contained.push(range_id);
+            }
+            dump.element(
+                "edge",
+                json!({ "label": "contains", "outV": document_id, "inVs": contained }),
+            )?;
+            document_ids.insert(path, document_id);
+        }
+
+        // definitions share a result set with the references that only resolve to them
+        let mut result_sets = BTreeMap::new();
+        for (range, info) in self.ranges.iter().filter(|(_, info)| info.is_definition) {
+            let result_set = dump.vertex(json!({ "label": "resultSet" }))?;
+            dump.edge("next", range_ids[range], result_set)?;
+            result_sets.insert(range, result_set);
+
+            let definition_result = dump.vertex(json!({ "label": "definitionResult" }))?;
+            dump.edge("textDocument/definition", result_set, definition_result)?;
+            dump.item(definition_result, &[range], &range_ids, &document_ids, None)?;
+
+            let reference_result = dump.vertex(json!({ "label": "referenceResult" }))?;
+            dump.edge("textDocument/references", result_set, reference_result)?;
+            dump.item(
+                reference_result,
+                &[range],
+                &range_ids,
+                &document_ids,
+                Some("definitions"),
+            )?;
+            dump.item(
+                reference_result,
+                &info.references.iter().collect::<Vec<_>>(),
+                &range_ids,
+                &document_ids,
+                Some("references"),
+            )?;
+        }
+
+        // references to several definitions get their own result set
+        for (range, info) in self.ranges.iter() {
+            if info.is_definition || info.definitions.is_empty() {
+                continue;
+            }
+            if info.definitions.len() == 1 {
+                let definition = info.definitions.iter().next().unwrap();
+                dump.edge("next", range_ids[range], result_sets[definition])?;
+                continue;
+            }
+            let result_set = dump.vertex(json!({ "label": "resultSet" }))?;
+            dump.edge("next", range_ids[range], result_set)?;
+            let definition_result = dump.vertex(json!({ "label": "definitionResult" }))?;
+            dump.edge("textDocument/definition", result_set, definition_result)?;
+            dump.item(
+                definition_result,
+                &info.definitions.iter().collect::<Vec<_>>(),
+                &range_ids,
+                &document_ids,
+                None,
+            )?;
+        }
+        dump.writer.flush()
+    }
+}
+
+struct Dump<W> {
+    writer: W,
+    next_id: usize,
+}
+
+impl<W: Write> Dump<W> {
+    fn element(&mut self, element_type: &str, mut value: Value) -> std::io::Result<usize> {
+        let id = self.next_id;
+        self.next_id += 1;
+        value["id"] = id.into();
+        value["type"] = element_type.into();
+        serde_json::to_writer(&mut self.writer, &value)?;
+        writeln!(self.writer)?;
+        Ok(id)
+    }
+
+    fn vertex(&mut self, value: Value) -> std::io::Result<usize> {
+        self.element("vertex", value)
+    }
+
+    fn edge(&mut self, label: &str, out_v: usize, in_v: usize) -> std::io::Result<usize> {
+        self.element(
+            "edge",
+            json!({ "label": label, "outV": out_v, "inV": in_v }),
+        )
+    }
+
+    /// Writes item edges from a result to ranges, one for each document that contains some of
+    /// the ranges.
+    fn item(
+        &mut self,
+        result: usize,
+        ranges: &[&RangeKey],
+        range_ids: &BTreeMap<&RangeKey, usize>,
+        document_ids: &BTreeMap<PathBuf, usize>,
+        property: Option<&str>,
+    ) -> std::io::Result<()> {
+        let mut by_document = BTreeMap::<usize, Vec<usize>>::new();
+        for range in ranges {
+            by_document
+                .entry(document_ids[&range.path])
+                .or_default()
+                .push(range_ids[range]);
+        }
+        for (document, in_vs) in by_document {
+            let mut value = json!({
+                "label": "item",
+                "outV": result,
+                "inVs": in_vs,
+                "document": document,
+            });
+            if let Some(property) = property {
+                value["property"] = property.into();
+            }
+            self.element("edge", value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the `file://` URI of a path, percent-encoding characters that are not allowed in URIs.
+pub(crate) fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
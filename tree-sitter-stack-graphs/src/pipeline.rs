@@ -0,0 +1,263 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Builds stack graphs and minimal partial paths for a set of files, so that they are ready for
+//! queries.
+//!
+//! Use the pipeline as follows:
+//!
+//! ``` no_run
+//! use std::path::PathBuf;
+//! use tree_sitter_stack_graphs::loader::Loader;
+//! use tree_sitter_stack_graphs::pipeline::AnalysisPipeline;
+//! use tree_sitter_stack_graphs::NoCancellation;
+//!
+//! fn main() -> anyhow::Result<()> {
+//!     let language_configurations = vec![/* add your language configurations here */];
+//!     let loader = Loader::from_language_configurations(language_configurations, None)?;
+//!     let mut pipeline = AnalysisPipeline::new(loader);
+//!     pipeline.add_file(PathBuf::from("main.py"), "import os\n".to_string());
+//!     let analysis = pipeline.run(&NoCancellation)?;
+//!     // query analysis.graph, using analysis.partials and analysis.database
+//!     Ok(())
+//! }
+//! ```
+//!
+//! The builtins of the languages of the analyzed files are added to the graph, unless disabled
+//! with [`AnalysisPipeline::set_load_builtins`][]. Embedders that persist partial paths, for
+//! example in a database, can register a hook with [`AnalysisPipeline::on_file_analyzed`][],
+//! which is called with the partial paths of every file in the graph.
+
+use stack_graphs::arena::Handle;
+use stack_graphs::graph::File;
+use stack_graphs::graph::StackGraph;
+use stack_graphs::partial::PartialPath;
+use stack_graphs::partial::PartialPaths;
+use stack_graphs::stitching::Database;
+use stack_graphs::stitching::ForwardPartialPathStitcher;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+use thiserror::Error;
+use tree_sitter::Language;
+use tree_sitter_graph::Variables;
+
+use crate::loader::LoadError;
+use crate::loader::Loader;
+use crate::BuildError;
+use crate::CancellationFlag;
+
+/// A hook that is called with the minimal partial paths of a file, after they are computed.
+pub type FileAnalyzedHook = Box<
+    dyn FnMut(
+        &StackGraph,
+        Handle<File>,
+        &mut PartialPaths,
+        &[PartialPath],
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>,
+>;
+
+/// Builds the stack graph and minimal partial paths for the files added to it.
+pub struct AnalysisPipeline {
+    loader: Loader,
+    files: Vec<(PathBuf, String)>,
+    load_builtins: bool,
+    builtins_layers: Vec<String>,
+    on_file_analyzed: Option<FileAnalyzedHook>,
+}
+
+impl AnalysisPipeline {
+    /// Creates a pipeline that uses the given loader to find the languages of files.
+    pub fn new(loader: Loader) -> Self {
+        Self {
+            loader,
+            files: Vec::new(),
+            load_builtins: true,
+            builtins_layers: Vec::new(),
+            on_file_analyzed: None,
+        }
+    }
+
+    /// Adds a file with the given path and content to the next run of the pipeline.
+    pub fn add_file(&mut self, path: PathBuf, source: String) -> &mut Self {
+        self.files.push((path, source));
+        self
+    }
+
+    /// Sets whether the builtins of the languages of the analyzed files are loaded.
+    /// Builtins are loaded by default.
+    pub fn set_load_builtins(&mut self, load_builtins: bool) -> &mut Self {
+        self.load_builtins = load_builtins;
+        self
+    }
+
+    /// Adds a named builtins layer, which is loaded in addition to the default builtins of
+    /// every language.
+    pub fn add_builtins_layer(&mut self, name: String) -> &mut Self {
+        if !self.builtins_layers.contains(&name) {
+            self.builtins_layers.push(name);
+        }
+        self
+    }
+
+    /// Sets a hook that is called with the minimal partial paths of every file in the graph,
+    /// including builtins files, such as for storing them in a database. An error returned by the
+    /// hook aborts the run.
+    pub fn on_file_analyzed<F>(&mut self, hook: F) -> &mut Self
+    where
+        F: FnMut(
+                &StackGraph,
+                Handle<File>,
+                &mut PartialPaths,
+                &[PartialPath],
+            ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+            + 'static,
+    {
+        self.on_file_analyzed = Some(Box::new(hook));
+        self
+    }
+
+    /// Builds the stack graph of all added files, and computes their minimal partial paths. The
+    /// added files are removed from the pipeline, so that it can be reused for another run.
+    /// Files that are not supported by any language are skipped.
+    pub fn run(
+        &mut self,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Analysis, AnalysisError> {
+        let files = std::mem::take(&mut self.files);
+        let mut graph = StackGraph::new();
+        let mut skipped = Vec::new();
+        let mut languages_with_builtins = HashSet::<Language>::new();
+        for (path, source) in &files {
+            let lcs =
+                self.loader
+                    .load_for_file(path, &mut Some(source.as_str()), cancellation_flag)?;
+            if !lcs.has_some() {
+                skipped.push(path.clone());
+                continue;
+            }
+            if let Some(lc) = lcs.primary {
+                if self.load_builtins && languages_with_builtins.insert(lc.language) {
+                    lc.load_builtins_into(&mut graph, &self.builtins_layers)?;
+                }
+            }
+            let file = graph
+                .add_file(&path.to_string_lossy())
+                .map_err(|_| AnalysisError::DuplicateFile(path.clone()))?;
+            let build_error = |inner| AnalysisError::Build {
+                path: path.clone(),
+                inner,
+            };
+            if let Some(lc) = lcs.primary {
+                lc.sgl
+                    .build_stack_graph_into(
+                        &mut graph,
+                        file,
+                        source,
+                        &Variables::new(),
+                        cancellation_flag,
+                    )
+                    .map_err(build_error)?;
+            }
+            for (_, fa) in &lcs.secondary {
+                fa.build_stack_graph_into(
+                    &mut graph,
+                    file,
+                    path,
+                    source,
+                    &mut files.iter().map(|(path, _)| path.as_path()),
+                    &HashMap::new(),
+                    cancellation_flag,
+                )
+                .map_err(build_error)?;
+            }
+            for producer in &lcs.producers {
+                producer
+                    .build_stack_graph_into(
+                        &mut graph,
+                        file,
+                        path,
+                        source,
+                        &Variables::new(),
+                        cancellation_flag,
+                    )
+                    .map_err(build_error)?;
+            }
+        }
+
+        let mut partials = PartialPaths::new();
+        let mut database = Database::new();
+        for file in graph.iter_files() {
+            let mut paths = Vec::new();
+            ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+                &graph,
+                &mut partials,
+                file,
+                &cancellation_flag,
+                |_, _, path| paths.push(path.clone()),
+            )
+            .map_err(|err| AnalysisError::Cancelled(err.0))?;
+            if let Some(hook) = &mut self.on_file_analyzed {
+                hook(&graph, file, &mut partials, &paths).map_err(AnalysisError::Hook)?;
+            }
+            for path in paths {
+                database.add_partial_path(&graph, &mut partials, path);
+            }
+        }
+
+        Ok(Analysis {
+            graph,
+            partials,
+            database,
+            skipped,
+        })
+    }
+}
+
+/// The result of running an [`AnalysisPipeline`][].
+pub struct Analysis {
+    /// The stack graph of the analyzed files and their builtins.
+    pub graph: StackGraph,
+    pub partials: PartialPaths,
+    /// A database containing the minimal partial paths of all files in the graph.
+    pub database: Database,
+    /// The paths of files that were not supported by any language.
+    pub skipped: Vec<PathBuf>,
+}
+
+impl Analysis {
+    /// Returns the graph file for the given path, if it was analyzed.
+    pub fn file(&self, path: &Path) -> Option<Handle<File>> {
+        self.graph.get_file(&path.to_string_lossy())
+    }
+}
+
+/// An error that can occur while running an [`AnalysisPipeline`][].
+#[derive(Debug, Error)]
+pub enum AnalysisError {
+    #[error("Failed to build stack graph for {}", .path.display())]
+    Build {
+        path: PathBuf,
+        #[source]
+        inner: BuildError,
+    },
+    #[error("Cancelled at {0}")]
+    Cancelled(&'static str),
+    #[error("Duplicate file {}", .0.display())]
+    DuplicateFile(PathBuf),
+    #[error(transparent)]
+    Hook(Box<dyn std::error::Error + Send + Sync>),
+    #[error(transparent)]
+    Load(Box<LoadError<'static>>),
+}
+
+impl From<LoadError<'static>> for AnalysisError {
+    fn from(value: LoadError<'static>) -> Self {
+        Self::Load(Box::new(value))
+    }
+}
@@ -0,0 +1,317 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Exports resolved references in the [SCIP][] format, so that they can be uploaded to code
+//! intelligence platforms that support SCIP indexes.
+//!
+//! References and the definitions they resolve to are collected in a [`ScipExporter`][], which
+//! writes them as a protobuf encoded SCIP index.  Definitions with a qualified name get a global
+//! symbol derived from that name, while other definitions get a symbol that is local to their
+//! document.  Ranges use zero-based lines and UTF-16 character offsets.
+//!
+//! [SCIP]: https://github.com/sourcegraph/scip/blob/main/scip.proto
+
+use lsp_positions::Span;
+use prost::Message;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::lsif::file_uri;
+
+/// The scheme of the global symbols in indexes written by [`ScipExporter`][].
+pub const SYMBOL_SCHEME: &str = "stack-graphs";
+
+/// The subset of the SCIP protobuf messages that is written by [`ScipExporter`][].  Field numbers
+/// follow `scip.proto`.
+pub mod proto {
+    /// A SCIP index.
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Index {
+        #[prost(message, optional, tag = "1")]
+        pub metadata: Option<Metadata>,
+        #[prost(message, repeated, tag = "2")]
+        pub documents: Vec<Document>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Metadata {
+        #[prost(int32, tag = "1")]
+        pub version: i32,
+        #[prost(message, optional, tag = "2")]
+        pub tool_info: Option<ToolInfo>,
+        #[prost(string, tag = "3")]
+        pub project_root: String,
+        #[prost(int32, tag = "4")]
+        pub text_document_encoding: i32,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ToolInfo {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        #[prost(string, tag = "2")]
+        pub version: String,
+        #[prost(string, repeated, tag = "3")]
+        pub arguments: Vec<String>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Document {
+        #[prost(string, tag = "1")]
+        pub relative_path: String,
+        #[prost(message, repeated, tag = "2")]
+        pub occurrences: Vec<Occurrence>,
+        #[prost(message, repeated, tag = "3")]
+        pub symbols: Vec<SymbolInformation>,
+        #[prost(string, tag = "4")]
+        pub language: String,
+        #[prost(int32, tag = "6")]
+        pub position_encoding: i32,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Occurrence {
+        /// The start line, start character, end line, and end character of the occurrence.  The
+        /// end line is omitted if it is equal to the start line.
+        #[prost(int32, repeated, tag = "1")]
+        pub range: Vec<i32>,
+        #[prost(string, tag = "2")]
+        pub symbol: String,
+        #[prost(int32, tag = "3")]
+        pub symbol_roles: i32,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct SymbolInformation {
+        #[prost(string, tag = "1")]
+        pub symbol: String,
+        #[prost(string, tag = "6")]
+        pub display_name: String,
+    }
+
+    /// The `UTF8` text encoding.
+    pub const TEXT_ENCODING_UTF8: i32 = 1;
+    /// The `UTF16CodeUnitOffsetFromLineStart` position encoding.
+    pub const POSITION_ENCODING_UTF16: i32 = 2;
+    /// The `Definition` symbol role.
+    pub const SYMBOL_ROLE_DEFINITION: i32 = 0x1;
+}
+
+/// A definition that a reference resolves to.
+pub struct ScipDefinition<'a> {
+    pub path: &'a Path,
+    pub span: &'a Span,
+    /// The name shown for the definition, typically its symbol.
+    pub display_name: Option<&'a str>,
+    /// The components of the qualified name of the definition, outermost first.  Definitions
+    /// without a qualified name get a local symbol.
+    pub qualified_name: &'a [String],
+    /// The syntax type of the definition, if the TSG rules set it.
+    pub syntax_type: Option<&'a str>,
+}
+
+/// Collects references and their definitions, and writes them as a SCIP index.
+pub struct ScipExporter {
+    project_root: PathBuf,
+    definitions: BTreeMap<RangeKey, DefinitionInfo>,
+    references: BTreeMap<RangeKey, BTreeSet<RangeKey>>,
+}
+
+/// Identifies a range in a document.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+struct RangeKey {
+    path: PathBuf,
+    start: (usize, usize),
+    end: (usize, usize),
+}
+
+impl RangeKey {
+    fn new(path: &Path, span: &Span) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            start: (span.start.line, span.start.column.utf16_offset),
+            end: (span.end.line, span.end.column.utf16_offset),
+        }
+    }
+
+    fn range(&self) -> Vec<i32> {
+        let mut range = vec![self.start.0 as i32, self.start.1 as i32];
+        if self.end.0 != self.start.0 {
+            range.push(self.end.0 as i32);
+        }
+        range.push(self.end.1 as i32);
+        range
+    }
+}
+
+struct DefinitionInfo {
+    display_name: Option<String>,
+    global_symbol: Option<String>,
+}
+
+impl ScipExporter {
+    /// Creates a new exporter for a project in the given root directory.  Documents are indexed
+    /// by their path relative to the project root.
+    pub fn new(project_root: &Path) -> Self {
+        Self {
+            project_root: project_root.to_path_buf(),
+            definitions: BTreeMap::new(),
+            references: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a reference together with the definitions it resolves to.  References without
+    /// definitions are not included in the index.
+    pub fn add_reference<'a, I>(&mut self, path: &Path, span: &Span, definitions: I)
+    where
+        I: IntoIterator<Item = ScipDefinition<'a>>,
+    {
+        let reference = RangeKey::new(path, span);
+        for definition in definitions {
+            let key = RangeKey::new(definition.path, definition.span);
+            self.definitions
+                .entry(key.clone())
+                .or_insert_with(|| DefinitionInfo {
+                    display_name: definition.display_name.map(str::to_string),
+                    global_symbol: (!definition.qualified_name.is_empty())
+                        .then(|| global_symbol(definition.qualified_name, definition.syntax_type)),
+                });
+            self.references
+                .entry(reference.clone())
+                .or_default()
+                .insert(key);
+        }
+    }
+
+    /// Returns the SCIP index of all references that were added.  Documents outside of the
+    /// project root are not included, and neither are occurrences of local symbols from other
+    /// documents, which cannot be expressed in SCIP.
+    pub fn index(&self) -> proto::Index {
+        // local symbols are numbered per document, in order of their definitions
+        let mut symbols = BTreeMap::new();
+        let mut local_counts = BTreeMap::<&Path, usize>::new();
+        for (key, info) in &self.definitions {
+            let symbol = match &info.global_symbol {
+                Some(symbol) => symbol.clone(),
+                None => {
+                    let count = local_counts.entry(&key.path).or_default();
+                    *count += 1;
+                    format!("local {}", *count - 1)
+                }
+            };
+            symbols.insert(key, symbol);
+        }
+
+        let mut documents = BTreeMap::<&Path, proto::Document>::new();
+        for (key, info) in &self.definitions {
+            let symbol = &symbols[key];
+            if let Some(document) = self.document(&mut documents, &key.path) {
+                document.occurrences.push(proto::Occurrence {
+                    range: key.range(),
+                    symbol: symbol.clone(),
+                    symbol_roles: proto::SYMBOL_ROLE_DEFINITION,
+                });
+                document.symbols.push(proto::SymbolInformation {
+                    symbol: symbol.clone(),
+                    display_name: info.display_name.clone().unwrap_or_default(),
+                });
+            }
+        }
+        for (reference, definitions) in &self.references {
+            if let Some(document) = self.document(&mut documents, &reference.path) {
+                for definition in definitions {
+                    let is_local = self.definitions[definition].global_symbol.is_none();
+                    if is_local && definition.path != reference.path {
+                        continue;
+                    }
+                    let symbol = &symbols[definition];
+                    document.occurrences.push(proto::Occurrence {
+                        range: reference.range(),
+                        symbol: symbol.clone(),
+                        symbol_roles: 0,
+                    });
+                }
+            }
+        }
+
+        proto::Index {
+            metadata: Some(proto::Metadata {
+                version: 0,
+                tool_info: Some(proto::ToolInfo {
+                    name: env!("CARGO_PKG_NAME").to_string(),
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                    arguments: Vec::new(),
+                }),
+                project_root: file_uri(&self.project_root),
+                text_document_encoding: proto::TEXT_ENCODING_UTF8,
+            }),
+            documents: documents.into_values().collect(),
+        }
+    }
+
+    /// Returns the document for the given path, or `None` if the path is outside of the project
+    /// root.
+    fn document<'a, 'p>(
+        &self,
+        documents: &'a mut BTreeMap<&'p Path, proto::Document>,
+        path: &'p Path,
+    ) -> Option<&'a mut proto::Document> {
+        let relative_path = path.strip_prefix(&self.project_root).ok()?;
+        Some(documents.entry(path).or_insert_with(|| proto::Document {
+            relative_path: relative_path.to_string_lossy().to_string(),
+            position_encoding: proto::POSITION_ENCODING_UTF16,
+            ..Default::default()
+        }))
+    }
+
+    /// Writes the SCIP index of all references that were added.
+    pub fn write<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writer.write_all(&self.index().encode_to_vec())?;
+        writer.flush()
+    }
+}
+
+/// Returns the global symbol for a definition with the given qualified name.  The last component
+/// gets a descriptor that matches the syntax type of the definition, while enclosing components
+/// are written as namespaces, because their kind is not known.
+pub fn global_symbol(qualified_name: &[String], syntax_type: Option<&str>) -> String {
+    let mut symbol = format!("{} . . . ", SYMBOL_SCHEME);
+    let (name, enclosing) = match qualified_name.split_last() {
+        Some(split) => split,
+        None => return symbol,
+    };
+    for component in enclosing {
+        push_name(&mut symbol, component);
+        symbol.push('/');
+    }
+    push_name(&mut symbol, name);
+    symbol.push_str(match syntax_type {
+        Some("function" | "method") => "().",
+        Some("class" | "enum" | "interface" | "struct" | "trait" | "type") => "#",
+        Some("module" | "namespace" | "package") => "/",
+        _ => ".",
+    });
+    symbol
+}
+
+/// Appends a descriptor name, escaping it with backticks if it is not a simple identifier.
+fn push_name(symbol: &mut String, name: &str) {
+    let is_simple = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '+' | '-' | '$'));
+    if is_simple {
+        symbol.push_str(name);
+    } else {
+        symbol.push('`');
+        symbol.push_str(&name.replace('`', "``"));
+        symbol.push('`');
+    }
+}
@@ -28,6 +28,33 @@
 //! Consecutive lines with assertions all apply to the last source line without an assertion.
 //! In the example, both assertions refer to positions on line 3.
 //!
+//! By default, assertions and directives are recognized anywhere in a line. If the comment syntax
+//! of the language is given, as a list of [`CommentSyntax`][] values, they are only recognized in
+//! lines that consist of a single comment, such as `-- ^ defined: 3` for SQL, or
+//! `<!-- ^ defined: 3 -->` for HTML.
+//!
+//! A line can contain assertions for several columns, and a column can be given explicitly as
+//! a one-based number after `@` instead of with a caret. The assertions in the example above could
+//! also be written as:
+//!
+//! ``` skip
+//! print(foo, bar)
+//! #     ^ defined: 1   ^ refers: bar
+//! # @7 refers: foo
+//! ```
+//!
+//! A `defined` assertion can also constrain the paths to the definitions, by listing the files
+//! that the paths must pass through after `via`:
+//!
+//! ``` skip
+//! print(foo)
+//! #     ^ defined: 5 via b.py
+//! ```
+//!
+//! This checks that re-exports and import chains are traversed as intended. Paths that reach the
+//! definitions without passing through all listed files are reported as unexpected. The files are
+//! the paths of test fragments or include files.
+//!
 //! The following assertions are supported:
 //!
 //!  - `defined`: takes a comma-separated list of line numbers, and expects a reference at this
@@ -56,6 +83,44 @@
 //! to a fragment.
 //!
 //! Any content before the first fragment header of the file is ignored, and will not be part of the test.
+//!
+//! ## Builtins layers
+//!
+//! Tests can load named builtins layers, in addition to the default builtins, using a `builtins`
+//! directive with a comma-separated list of layer names:
+//!
+//! ``` skip
+//! # --- builtins: django, pytest ---
+//! ```
+//!
+//! The directive applies to the whole test, and may appear multiple times.
+//!
+//! A test can opt out of loading any builtins, including the default builtins, with the special
+//! name `none`, which cannot be combined with layer names:
+//!
+//! ``` skip
+//! # --- builtins: none ---
+//! ```
+//!
+//! ## Include files
+//!
+//! Shared files can be added to the test graph using an `include` directive, instead of copying
+//! their content into every test:
+//!
+//! ``` skip
+//! # --- include: fixtures/stdlib.py ---
+//! ```
+//!
+//! The path is resolved relative to the directory of the test file, and is used as the file name
+//! in the stack graph. Include files cannot contain assertions, and resolutions to definitions in
+//! include files are not checked by `defined` assertions. Like `builtins`, the directive applies
+//! to the whole test, and may appear multiple times.
+//!
+//! ## Golden graphs
+//!
+//! Besides the assertions, the graph of a test can be compared against a previously saved golden
+//! graph using [`Test::check_graph`]. This catches changes to the shape of the graph that do not
+//! change resolution results.
 
 use itertools::Itertools;
 use lsp_positions::Position;
@@ -73,6 +138,8 @@ use stack_graphs::graph::Node;
 use stack_graphs::graph::SourceInfo;
 use stack_graphs::graph::StackGraph;
 use stack_graphs::partial::PartialPaths;
+use stack_graphs::serde::Filter;
+use stack_graphs::serde::GraphDiff;
 use stack_graphs::stitching::Database;
 use std::collections::HashMap;
 use std::path::Path;
@@ -90,8 +157,21 @@ static PATH_REGEX: Lazy<Regex> =
     Lazy::new(|| Regex::new(r#"---\s*path:\s*([^\s]+)\s*---"#).unwrap());
 static GLOBAL_REGEX: Lazy<Regex> =
     Lazy::new(|| Regex::new(r#"---\s*global:\s*([^\s]+)=([^\s]+)\s*---"#).unwrap());
-static ASSERTION_REGEX: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r#"(\^)\s*(\w+):\s*([^\s,]+(?:\s*,\s*[^\s,]+)*)?"#).unwrap());
+static INCLUDE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"---\s*include:\s*([^\s]+)\s*---"#).unwrap());
+static BUILTINS_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"---\s*builtins:\s*([^\s,]+(?:\s*,\s*[^\s,]+)*)\s*---"#).unwrap());
+// Values cannot start with a caret or an explicit column, so that they do not swallow the next
+// assertion on the same line.
+static ASSERTION_REGEX: Lazy<Regex> = Lazy::new(|| {
+    let value = r#"(?:[^\s,^@][^\s,]*|@[^\s,0-9][^\s,]*)"#;
+    Regex::new(&format!(
+        r#"(?:(\^)|@(\d+))\s*(\w+):\s*({value}(?:\s*,\s*{value})*)?"#
+    ))
+    .unwrap()
+});
+static VIA_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"^\s+via\s+([^\s,]+(?:\s*,\s*[^\s,]+)*)"#).unwrap());
 static LINE_NUMBER_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\d+"#).unwrap());
 static NAME_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r#"[^\s,]+"#).unwrap());
 
@@ -103,7 +183,10 @@ pub enum TestError {
     DuplicatePath(usize, String),
     GlobalBeforeFirstFragment(usize),
     InvalidAssertion(usize, String),
+    InvalidBuiltins(usize),
     InvalidColumn(usize, usize, usize),
+    InvalidInclude(usize, String, #[source] std::io::Error),
+    UnknownViaFile(usize, String),
 }
 
 impl std::fmt::Display for TestError {
@@ -133,6 +216,11 @@ impl std::fmt::Display for TestError {
             Self::InvalidAssertion(line, assertion) => {
                 write!(f, "Invalid assertion {} on line {}", assertion, line + 1)
             }
+            Self::InvalidBuiltins(line) => write!(
+                f,
+                "Builtins none cannot be combined with builtins layers on line {}",
+                line + 1
+            ),
             Self::InvalidColumn(line, column, regular_line) => write!(
                 f,
                 "Assertion on line {} refers to missing column {} on line {}",
@@ -140,15 +228,68 @@ impl std::fmt::Display for TestError {
                 column + 1,
                 regular_line + 1
             ),
+            Self::UnknownViaFile(line, path) => {
+                write!(f, "Unknown file {} after via on line {}", path, line + 1)
+            }
+            Self::InvalidInclude(line, path, _) => {
+                write!(f, "Cannot read include {} on line {}", path, line + 1)
+            }
         }
     }
 }
 
+/// A comment syntax of a language, in which test assertions and directives are written.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CommentSyntax {
+    /// A line comment, starting with the given prefix, such as `--` in SQL.
+    Line(String),
+    /// A block comment on a single line, between the given delimiters, such as `<!--` and `-->`
+    /// in HTML.
+    Block(String, String),
+}
+
+impl CommentSyntax {
+    /// Returns the offset and text of the comment, if the line consists of a comment of this
+    /// syntax.
+    fn text<'a>(&self, line: &'a str) -> Option<(usize, &'a str)> {
+        let trimmed = line.trim_start();
+        let start = line.len() - trimmed.len();
+        match self {
+            Self::Line(prefix) => {
+                let text = trimmed.strip_prefix(prefix.as_str())?;
+                Some((start + prefix.len(), text))
+            }
+            Self::Block(open, close) => {
+                let text = trimmed
+                    .trim_end()
+                    .strip_prefix(open.as_str())?
+                    .strip_suffix(close.as_str())?;
+                Some((start + open.len(), text))
+            }
+        }
+    }
+}
+
+/// Returns the offset and text of the comment on the line, using the first matching comment
+/// syntax. If no comment syntax is given, the whole line is returned.
+fn comment_text<'a>(comments: &[CommentSyntax], line: &'a str) -> Option<(usize, &'a str)> {
+    if comments.is_empty() {
+        return Some((0, line));
+    }
+    comments.iter().find_map(|comment| comment.text(line))
+}
+
 /// A stack graph test
 pub struct Test {
     pub path: PathBuf,
     pub fragments: Vec<TestFragment>,
     pub graph: StackGraph,
+    /// Names of builtins layers that should be loaded for this test.
+    pub builtins_layers: Vec<String>,
+    /// Whether no builtins, including the default builtins, should be loaded for this test.
+    pub no_builtins: bool,
+    /// Files included in this test. These fragments never contain assertions.
+    pub includes: Vec<TestFragment>,
 }
 
 /// A fragment from a stack graph test
@@ -164,11 +305,60 @@ pub struct TestFragment {
 impl Test {
     /// Creates a test from source. If the test contains no `path` sections,
     /// the default fragment path is used for the test's single test fragment.
+    /// Include files are read from the file system, relative to the test path.
     pub fn from_source(
         path: &Path,
         source: &str,
         default_fragment_path: &Path,
     ) -> Result<Self, TestError> {
+        Self::from_source_with_includes(path, source, default_fragment_path, |include_path| {
+            std::fs::read_to_string(include_path)
+        })
+    }
+
+    /// Creates a test from source, like [`Test::from_source`], but uses the given function to
+    /// read include files. The function is called with the include path resolved relative to the
+    /// test path.
+    pub fn from_source_with_includes<F>(
+        path: &Path,
+        source: &str,
+        default_fragment_path: &Path,
+        read_include: F,
+    ) -> Result<Self, TestError>
+    where
+        F: FnMut(&Path) -> std::io::Result<String>,
+    {
+        Self::parse(path, source, default_fragment_path, &[], read_include)
+    }
+
+    /// Creates a test from source, like [`Test::from_source`], but only recognizes assertions and
+    /// directives in comments of the given syntax. If no comment syntax is given, they are
+    /// recognized anywhere in a line.
+    pub fn from_source_with_comments(
+        path: &Path,
+        source: &str,
+        default_fragment_path: &Path,
+        comments: &[CommentSyntax],
+    ) -> Result<Self, TestError> {
+        Self::parse(
+            path,
+            source,
+            default_fragment_path,
+            comments,
+            |include_path| std::fs::read_to_string(include_path),
+        )
+    }
+
+    fn parse<F>(
+        path: &Path,
+        source: &str,
+        default_fragment_path: &Path,
+        comments: &[CommentSyntax],
+        mut read_include: F,
+    ) -> Result<Self, TestError>
+    where
+        F: FnMut(&Path) -> std::io::Result<String>,
+    {
         let mut graph = StackGraph::new();
         let mut fragments = Vec::new();
         let mut have_fragments = false;
@@ -179,11 +369,16 @@ impl Test {
         let mut prev_source = String::new();
         let mut line_files = Vec::new();
         let mut line_count = 0;
+        let mut builtins_layers = Vec::new();
+        let mut no_builtins = false;
+        let mut include_paths = Vec::new();
         for (current_line_number, current_line) in
             PositionedSubstring::lines_iter(source).enumerate()
         {
             line_count += 1;
-            if let Some(m) = PATH_REGEX.captures_iter(current_line.content).next() {
+            let directive =
+                comment_text(comments, current_line.content).map_or("", |(_, text)| text);
+            if let Some(m) = PATH_REGEX.captures_iter(directive).next() {
                 // in a test with fragments, any content before the first fragment is
                 // ignored, so that the file name of the test does not interfere with
                 // the file names of the fragments
@@ -217,7 +412,28 @@ impl Test {
                 current_globals = HashMap::new();
 
                 Self::push_whitespace_for(&current_line, &mut current_source);
-            } else if let Some(m) = GLOBAL_REGEX.captures_iter(current_line.content).next() {
+            } else if let Some(m) = BUILTINS_REGEX.captures_iter(directive).next() {
+                for layer in NAME_REGEX.find_iter(m.get(1).unwrap().as_str()) {
+                    let layer = layer.as_str().to_string();
+                    if layer == "none" {
+                        no_builtins = true;
+                    } else if !builtins_layers.contains(&layer) {
+                        builtins_layers.push(layer);
+                    }
+                }
+                if no_builtins && !builtins_layers.is_empty() {
+                    return Err(TestError::InvalidBuiltins(current_line_number));
+                }
+
+                Self::push_whitespace_for(&current_line, &mut current_source);
+            } else if let Some(m) = INCLUDE_REGEX.captures_iter(directive).next() {
+                let include_path = PathBuf::from(m.get(1).unwrap().as_str());
+                if !include_paths.iter().any(|(_, p)| *p == include_path) {
+                    include_paths.push((current_line_number, include_path));
+                }
+
+                Self::push_whitespace_for(&current_line, &mut current_source);
+            } else if let Some(m) = GLOBAL_REGEX.captures_iter(directive).next() {
                 have_globals = true;
                 let global_name = m.get(1).unwrap().as_str();
                 let global_value = m.get(2).unwrap().as_str();
@@ -259,15 +475,39 @@ impl Test {
             });
         }
 
+        let include_dir = path.parent().unwrap_or(Path::new(""));
+        let mut includes = Vec::new();
+        for (line, include_path) in include_paths {
+            let source = read_include(&include_dir.join(&include_path)).map_err(|err| {
+                TestError::InvalidInclude(line, format!("{}", include_path.display()), err)
+            })?;
+            let file = graph
+                .add_file(&include_path.to_string_lossy())
+                .map_err(|_| {
+                    TestError::DuplicatePath(line, format!("{}", include_path.display()))
+                })?;
+            includes.push(TestFragment {
+                file,
+                path: include_path,
+                source,
+                assertions: Vec::new(),
+                globals: HashMap::new(),
+            });
+        }
+
         for fragment in &mut fragments {
-            fragment
-                .parse_assertions(&mut graph, |line| line_files.get(line).cloned().flatten())?;
+            fragment.parse_assertions(&mut graph, comments, |line| {
+                line_files.get(line).cloned().flatten()
+            })?;
         }
 
         Ok(Self {
             path: path.to_path_buf(),
             fragments,
             graph,
+            builtins_layers,
+            no_builtins,
+            includes,
         })
     }
 
@@ -292,7 +532,12 @@ impl Test {
 
 impl TestFragment {
     /// Parse assertions in the source.
-    fn parse_assertions<F>(&mut self, graph: &mut StackGraph, line_file: F) -> Result<(), TestError>
+    fn parse_assertions<F>(
+        &mut self,
+        graph: &mut StackGraph,
+        comments: &[CommentSyntax],
+        line_file: F,
+    ) -> Result<(), TestError>
     where
         F: Fn(usize) -> Option<Handle<File>>,
     {
@@ -305,83 +550,130 @@ impl TestFragment {
         for (current_line_number, current_line) in
             PositionedSubstring::lines_iter(&self.source).enumerate()
         {
-            if let Some(m) = ASSERTION_REGEX.captures_iter(current_line.content).next() {
-                // assertion line
+            let (comment_offset, comment) =
+                comment_text(comments, current_line.content).unwrap_or((0, ""));
+            let assertion_matches = ASSERTION_REGEX.captures_iter(comment).collect::<Vec<_>>();
+            if !assertion_matches.is_empty() {
+                // assertion line, which may contain assertions for several columns
                 let last_regular_line = last_regular_line.as_ref().ok_or_else(|| {
                     TestError::AssertionRefersToNonSourceLine(current_line_number)
                 })?;
                 let last_regular_line_number = last_regular_line_number.unwrap();
 
-                let carret_match = m.get(1).unwrap();
-                let assertion_match = m.get(2).unwrap();
-                let values_match = m.get(3);
+                for m in assertion_matches {
+                    let assertion_match = m.get(3).unwrap();
+                    let values_match = m.get(4);
 
-                let column_utf8_offset = carret_match.start();
-                let column_grapheme_offset = current_line_span_calculator
-                    .for_line_and_column(
-                        current_line_number,
-                        current_line.utf8_bounds.start,
-                        column_utf8_offset,
-                    )
-                    .column
-                    .grapheme_offset;
-                if column_grapheme_offset >= last_regular_line.grapheme_length {
-                    return Err(TestError::InvalidColumn(
-                        current_line_number,
-                        column_grapheme_offset,
-                        last_regular_line_number,
-                    ));
-                }
-                let position = last_regular_line_span_calculator.for_line_and_grapheme(
-                    last_regular_line_number,
-                    last_regular_line.utf8_bounds.start,
-                    column_grapheme_offset,
-                );
-                let source = AssertionSource {
-                    file: self.file,
-                    position,
-                };
-
-                match assertion_match.as_str() {
-                    DEFINED => {
-                        let mut targets = Vec::new();
-                        for line in LINE_NUMBER_REGEX
-                            .find_iter(values_match.map(|m| m.as_str()).unwrap_or(""))
-                        {
-                            let line = line.as_str().parse::<usize>().unwrap() - 1;
-                            let file = line_file(line).ok_or(
-                                TestError::AssertionRefersToNonSourceLine(current_line_number),
-                            )?;
-                            targets.push(AssertionTarget { file, line });
+                    let column_grapheme_offset = match (m.get(1), m.get(2)) {
+                        (Some(carret_match), _) => {
+                            current_line_span_calculator
+                                .for_line_and_column(
+                                    current_line_number,
+                                    current_line.utf8_bounds.start,
+                                    comment_offset + carret_match.start(),
+                                )
+                                .column
+                                .grapheme_offset
                         }
-                        self.assertions.push(Assertion::Defined { source, targets });
-                    }
-                    DEFINES => {
-                        let mut symbols = Vec::new();
-                        for name in
-                            NAME_REGEX.find_iter(values_match.map(|m| m.as_str()).unwrap_or(""))
-                        {
-                            let symbol = graph.add_symbol(name.as_str());
-                            symbols.push(symbol);
-                        }
-                        self.assertions.push(Assertion::Defines { source, symbols });
-                    }
-                    REFERS => {
-                        let mut symbols = Vec::new();
-                        for name in
-                            NAME_REGEX.find_iter(values_match.map(|m| m.as_str()).unwrap_or(""))
-                        {
-                            let symbol = graph.add_symbol(name.as_str());
-                            symbols.push(symbol);
+                        (None, Some(column_match)) => {
+                            // explicit columns are one-based, like the columns in error messages
+                            match column_match.as_str().parse::<usize>() {
+                                Ok(column) if column > 0 => column - 1,
+                                _ => {
+                                    return Err(TestError::InvalidAssertion(
+                                        current_line_number,
+                                        m.get(0).unwrap().as_str().to_string(),
+                                    ))
+                                }
+                            }
                         }
-                        self.assertions.push(Assertion::Refers { source, symbols });
+                        (None, None) => unreachable!("assertions start with a caret or a column"),
+                    };
+                    if column_grapheme_offset >= last_regular_line.grapheme_length {
+                        return Err(TestError::InvalidColumn(
+                            current_line_number,
+                            column_grapheme_offset,
+                            last_regular_line_number,
+                        ));
                     }
-                    _ => {
+                    let position = last_regular_line_span_calculator.for_line_and_grapheme(
+                        last_regular_line_number,
+                        last_regular_line.utf8_bounds.start,
+                        column_grapheme_offset,
+                    );
+                    let source = AssertionSource {
+                        file: self.file,
+                        position,
+                    };
+
+                    let via = VIA_REGEX
+                        .captures(&comment[m.get(0).unwrap().end()..])
+                        .map(|via_match| {
+                            NAME_REGEX
+                                .find_iter(via_match.get(1).unwrap().as_str())
+                                .map(|name| {
+                                    graph.get_file(name.as_str()).ok_or_else(|| {
+                                        TestError::UnknownViaFile(
+                                            current_line_number,
+                                            name.as_str().to_string(),
+                                        )
+                                    })
+                                })
+                                .collect::<Result<Vec<_>, _>>()
+                        })
+                        .transpose()?;
+                    if via.is_some() && assertion_match.as_str() != DEFINED {
                         return Err(TestError::InvalidAssertion(
                             current_line_number,
-                            assertion_match.as_str().to_string(),
+                            format!("{} with via", assertion_match.as_str()),
                         ));
                     }
+
+                    match assertion_match.as_str() {
+                        DEFINED => {
+                            let mut targets = Vec::new();
+                            for line in LINE_NUMBER_REGEX
+                                .find_iter(values_match.map(|m| m.as_str()).unwrap_or(""))
+                            {
+                                let line = line.as_str().parse::<usize>().unwrap() - 1;
+                                let file = line_file(line).ok_or(
+                                    TestError::AssertionRefersToNonSourceLine(current_line_number),
+                                )?;
+                                targets.push(AssertionTarget { file, line });
+                            }
+                            self.assertions.push(Assertion::Defined {
+                                source,
+                                targets,
+                                via: via.unwrap_or_default(),
+                            });
+                        }
+                        DEFINES => {
+                            let mut symbols = Vec::new();
+                            for name in
+                                NAME_REGEX.find_iter(values_match.map(|m| m.as_str()).unwrap_or(""))
+                            {
+                                let symbol = graph.add_symbol(name.as_str());
+                                symbols.push(symbol);
+                            }
+                            self.assertions.push(Assertion::Defines { source, symbols });
+                        }
+                        REFERS => {
+                            let mut symbols = Vec::new();
+                            for name in
+                                NAME_REGEX.find_iter(values_match.map(|m| m.as_str()).unwrap_or(""))
+                            {
+                                let symbol = graph.add_symbol(name.as_str());
+                                symbols.push(symbol);
+                            }
+                            self.assertions.push(Assertion::Refers { source, symbols });
+                        }
+                        _ => {
+                            return Err(TestError::InvalidAssertion(
+                                current_line_number,
+                                assertion_match.as_str().to_string(),
+                            ));
+                        }
+                    }
                 }
             } else {
                 // regular source line
@@ -477,6 +769,8 @@ pub enum TestFailure {
         references: Vec<String>,
         missing_lines: Vec<usize>,
         unexpected_lines: HashMap<String, Vec<Option<usize>>>,
+        /// Files that paths to the expected definitions must pass through.
+        via: Vec<String>,
     },
     IncorrectDefinitions {
         path: PathBuf,
@@ -490,6 +784,15 @@ pub enum TestFailure {
         missing_symbols: Vec<String>,
         unexpected_symbols: Vec<String>,
     },
+    IncorrectGraph {
+        path: PathBuf,
+        golden_path: PathBuf,
+        diff: GraphDiff,
+    },
+    MissingGraph {
+        path: PathBuf,
+        golden_path: PathBuf,
+    },
     Cancelled(stack_graphs::CancellationError),
 }
 
@@ -511,6 +814,7 @@ impl std::fmt::Display for TestFailure {
                 references,
                 missing_lines,
                 unexpected_lines,
+                via,
             } => {
                 write!(
                     f,
@@ -523,6 +827,9 @@ impl std::fmt::Display for TestFailure {
                 for reference in references {
                     write!(f, " ‘{}’", reference)?;
                 }
+                if !via.is_empty() {
+                    write!(f, " via {}", via.iter().format(", "))?;
+                }
                 if !missing_lines.is_empty() {
                     write!(
                         f,
@@ -610,6 +917,45 @@ impl std::fmt::Display for TestFailure {
                 }
                 Ok(())
             }
+            Self::IncorrectGraph {
+                path,
+                golden_path,
+                diff,
+            } => {
+                write!(
+                    f,
+                    "{}: graph differs from {}",
+                    path.display(),
+                    golden_path.display()
+                )?;
+                for file in &diff.removed_files {
+                    write!(f, "\n  - file {}", file)?;
+                }
+                for file in &diff.added_files {
+                    write!(f, "\n  + file {}", file)?;
+                }
+                for node in &diff.removed_nodes {
+                    write!(f, "\n  - node {:?}", node)?;
+                }
+                for node in &diff.added_nodes {
+                    write!(f, "\n  + node {:?}", node)?;
+                }
+                for edge in &diff.removed_edges {
+                    write!(f, "\n  - edge {:?}", edge)?;
+                }
+                for edge in &diff.added_edges {
+                    write!(f, "\n  + edge {:?}", edge)?;
+                }
+                Ok(())
+            }
+            Self::MissingGraph { path, golden_path } => {
+                write!(
+                    f,
+                    "{}: golden graph {} does not exist",
+                    path.display(),
+                    golden_path.display()
+                )
+            }
             Self::Cancelled(err) => write!(f, "{}", err),
         }
     }
@@ -640,6 +986,46 @@ impl Test {
         Ok(result)
     }
 
+    /// Compares the graph of the test, restricted to the nodes and edges included by the filter,
+    /// against a golden graph. Both graphs are normalized, so that only changes in the content of
+    /// the graph are reported, and not changes in the order in which nodes are created. Debug info
+    /// is ignored, because it refers to locations in the TSG file. A missing golden graph, given
+    /// as `None`, fails the check. The comparison counts as a single assertion in the result.
+    pub fn check_graph(
+        &self,
+        filter: &dyn Filter,
+        golden_path: &Path,
+        golden: Option<stack_graphs::serde::StackGraph>,
+    ) -> TestResult {
+        let mut result = TestResult::new();
+        let mut golden = match golden {
+            Some(golden) => golden,
+            None => {
+                result.add_failure(TestFailure::MissingGraph {
+                    path: self.path.clone(),
+                    golden_path: golden_path.to_path_buf(),
+                });
+                return result;
+            }
+        };
+        golden.strip_debug_info();
+        golden.normalize();
+        let mut actual = self.graph.to_serializable_filter(filter);
+        actual.strip_debug_info();
+        actual.normalize();
+        let diff = golden.diff(&actual);
+        if diff.is_empty() {
+            result.add_success();
+        } else {
+            result.add_failure(TestFailure::IncorrectGraph {
+                path: self.path.clone(),
+                golden_path: golden_path.to_path_buf(),
+                diff,
+            });
+        }
+        result
+    }
+
     /// Construct a TestFailure from an AssertionError.
     fn from_error(&self, err: AssertionError) -> Result<(), TestFailure> {
         match err {
@@ -652,6 +1038,7 @@ impl Test {
                 references,
                 missing_targets,
                 unexpected_paths,
+                via,
             } => {
                 let references = references
                     .into_iter()
@@ -693,6 +1080,7 @@ impl Test {
                     references,
                     missing_lines,
                     unexpected_lines,
+                    via: via.into_iter().map(|f| self.graph[f].to_string()).collect(),
                 })
             }
             AssertionError::IncorrectDefinitions {
@@ -5,6 +5,8 @@
 // Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
 // ------------------------------------------------------------------------------------------------
 
+use globset::GlobBuilder;
+use globset::GlobMatcher;
 use std::marker::PhantomData;
 use std::path::Path;
 use std::sync::atomic::AtomicUsize;
@@ -15,6 +17,15 @@ use tree_sitter_graph::parse_error::TreeWithParseErrorVec;
 
 use crate::CancellationFlag;
 
+/// Compiles a glob.  All globs in this crate use this syntax, in which the wildcards `*` and `?`
+/// never match `/`, so that only `**` matches across directories.
+pub(crate) fn compile_glob(glob: &str) -> Result<GlobMatcher, globset::Error> {
+    Ok(GlobBuilder::new(glob)
+        .literal_separator(true)
+        .build()?
+        .compile_matcher())
+}
+
 pub struct DisplayParseErrorsPretty<'a> {
     pub parse_errors: &'a TreeWithParseErrorVec,
     pub path: &'a Path,
@@ -5,8 +5,17 @@
 // Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
 // ------------------------------------------------------------------------------------------------
 
+use stack_graphs::arena::Handle;
+use stack_graphs::graph::File;
 use stack_graphs::graph::StackGraph;
+use tree_sitter_graph::functions::Function;
+use tree_sitter_graph::functions::Parameters;
+use tree_sitter_graph::graph::Graph;
+use tree_sitter_graph::graph::Value;
+use tree_sitter_graph::ExecutionError;
 use tree_sitter_graph::Variables;
+use tree_sitter_stack_graphs::BuildError;
+use tree_sitter_stack_graphs::BuildObserver;
 use tree_sitter_stack_graphs::NoCancellation;
 use tree_sitter_stack_graphs::StackGraphLanguage;
 
@@ -72,3 +81,132 @@ fn can_support_injected_nodes() {
         &["[test.py(1) scope] -0-> [test.py(0) exported scope]"],
     );
 }
+
+#[test]
+fn can_call_custom_functions() {
+    struct Mangle;
+
+    impl Function for Mangle {
+        fn call(
+            &self,
+            _graph: &mut Graph,
+            _source: &str,
+            parameters: &mut dyn Parameters,
+        ) -> Result<Value, ExecutionError> {
+            let name = parameters.param()?.into_string()?;
+            parameters.finish()?;
+            Ok(format!("_Z{}", name).into())
+        }
+    }
+
+    let tsg = r#"
+    (identifier) @id {
+      node result
+      attr (result) type = "pop_symbol", symbol = (mangle (source-text @id)), is_definition
+    }
+    "#;
+    let python = "a";
+
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("test.py");
+
+    let mut language = StackGraphLanguage::from_str(tree_sitter_python::language(), tsg).unwrap();
+    language.add_function("mangle", Mangle);
+
+    let globals = Variables::new();
+    language
+        .build_stack_graph_into(&mut graph, file, python, &globals, &NoCancellation)
+        .expect("Failed to build graph");
+
+    check_stack_graph_nodes(&graph, file, &["[test.py(0) definition _Za]"]);
+}
+
+#[test]
+fn can_add_nodes_in_build_observer() {
+    struct AddModuleScope;
+
+    impl BuildObserver for AddModuleScope {
+        fn before_build(
+            &self,
+            stack_graph: &mut StackGraph,
+            file: Handle<File>,
+            _source: &str,
+        ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            let node_id = stack_graph.new_node_id(file);
+            stack_graph.add_scope_node(node_id, true);
+            Ok(())
+        }
+    }
+
+    let tsg = r#"
+    (module)@mod {
+      node @mod.lexical_scope
+    }
+    "#;
+    let python = "pass";
+
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("test.py");
+
+    let mut language = StackGraphLanguage::from_str(tree_sitter_python::language(), tsg).unwrap();
+    language.add_build_observer(AddModuleScope);
+
+    let globals = Variables::new();
+    language
+        .build_stack_graph_into(&mut graph, file, python, &globals, &NoCancellation)
+        .expect("Failed to build graph");
+
+    check_stack_graph_nodes(
+        &graph,
+        file,
+        &["[test.py(0) exported scope]", "[test.py(1) scope]"],
+    );
+}
+
+#[test]
+fn can_fail_build_in_build_observer() {
+    struct RequireDefinitions;
+
+    impl BuildObserver for RequireDefinitions {
+        fn after_build(
+            &self,
+            stack_graph: &mut StackGraph,
+            file: Handle<File>,
+            _source: &str,
+        ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            if !stack_graph
+                .nodes_for_file(file)
+                .any(|node| stack_graph[node].is_definition())
+            {
+                return Err(format!("No definitions in {}", stack_graph[file]).into());
+            }
+            Ok(())
+        }
+    }
+
+    let tsg = r#"
+    (identifier) @id {
+      node result
+      attr (result) type = "pop_symbol", symbol = (source-text @id), is_definition
+    }
+    "#;
+
+    let mut language = StackGraphLanguage::from_str(tree_sitter_python::language(), tsg).unwrap();
+    language.add_build_observer(RequireDefinitions);
+
+    let globals = Variables::new();
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("a.py");
+    language
+        .build_stack_graph_into(&mut graph, file, "a", &globals, &NoCancellation)
+        .expect("Failed to build graph");
+
+    let file = graph.get_or_create_file("b.py");
+    match language.build_stack_graph_into(&mut graph, file, "pass", &globals, &NoCancellation) {
+        Err(BuildError::ObserverError(err)) => {
+            assert_eq!("No definitions in b.py", err.to_string())
+        }
+        Err(err) => panic!("Unexpected error: {}", err),
+        Ok(_) => panic!("Build unexpectedly succeeded."),
+    }
+}
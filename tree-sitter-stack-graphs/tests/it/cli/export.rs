@@ -0,0 +1,193 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use pretty_assertions::assert_eq;
+use stack_graphs::arena::Handle;
+use stack_graphs::graph::File;
+use stack_graphs::graph::StackGraph;
+use stack_graphs::partial::PartialPaths;
+use stack_graphs::storage::SQLiteWriter;
+use std::ops::Range;
+use std::path::Path;
+use std::path::PathBuf;
+use tree_sitter_stack_graphs::cli::export::Tags;
+use tree_sitter_stack_graphs::cli::export::Xrefs;
+use tree_sitter_stack_graphs::cli::export::XrefsFormat;
+use tree_sitter_stack_graphs::cli::index::Indexer;
+use tree_sitter_stack_graphs::cli::util::reporter::ConsoleReporter;
+use tree_sitter_stack_graphs::loader::FileAnalyzers;
+use tree_sitter_stack_graphs::loader::LanguageConfiguration;
+use tree_sitter_stack_graphs::loader::Loader;
+use tree_sitter_stack_graphs::NoCancellation;
+
+use super::test_dir;
+
+static XREFS_TSG: &str = r#"
+  global ROOT_NODE
+  (module (expression_statement (assignment left:(identifier)@name))) {
+      node @name.def
+      attr (@name.def) type = "pop_symbol", symbol = (source-text @name), source_node = @name, is_definition
+      edge ROOT_NODE -> @name.def
+  }
+  (module (expression_statement (identifier)@name)) {
+      node @name.ref
+      attr (@name.ref) type = "push_symbol", symbol = (source-text @name), source_node = @name, is_reference
+      edge @name.ref -> ROOT_NODE
+  }
+"#;
+
+fn add_definition(
+    graph: &mut StackGraph,
+    file: Handle<File>,
+    symbol: &str,
+    line: usize,
+    containing_line: Range<usize>,
+    kind: Option<&str>,
+) {
+    let symbol = graph.add_symbol(symbol);
+    let id = graph.new_node_id(file);
+    let node = graph.add_pop_symbol_node(id, symbol, true).unwrap();
+    graph.add_edge(StackGraph::root_node(), node, 0);
+    let syntax_type = kind.map(|kind| graph.add_string(kind));
+    let source_info = graph.source_info_mut(node);
+    source_info.span.start.line = line;
+    source_info.span.start.containing_line = containing_line;
+    source_info.syntax_type = syntax_type.into();
+}
+
+/// Indexes a file with a resolved and an unresolved reference, and exports its cross-references
+/// in the given format.
+fn export_xrefs(name: &str, format: XrefsFormat) -> (PathBuf, String) {
+    let dir = test_dir(name);
+    std::fs::write(dir.join("a.py"), "x = 1\nx\ny\n").unwrap();
+    let language = LanguageConfiguration::from_sources(
+        tree_sitter_python::language(),
+        Some("source.py".into()),
+        None,
+        vec!["py".into()],
+        PathBuf::from("test.tsg"),
+        XREFS_TSG,
+        None,
+        None,
+        FileAnalyzers::new(),
+        &NoCancellation,
+    )
+    .expect("Expected language configuration to load");
+    let mut loader = Loader::from_language_configurations(vec![language], None)
+        .expect("Expected loader to succeed");
+
+    let db_path = dir.join("index.sqlite");
+    let mut db = SQLiteWriter::open(&db_path).expect("Cannot open writer");
+    let reporter = ConsoleReporter::none();
+    Indexer::new(&mut db, &mut loader, &reporter)
+        .index_all(vec![dir.join("a.py")], None::<&Path>, &NoCancellation)
+        .expect("Expected indexing to succeed");
+    drop(db);
+
+    let xrefs_path = dir.join("xrefs");
+    Xrefs {
+        source_paths: vec![],
+        format,
+        output: Some(xrefs_path.clone()),
+    }
+    .run(&db_path)
+    .expect("Expected export to succeed");
+    let xrefs = std::fs::read_to_string(&xrefs_path).expect("Cannot read xrefs");
+    (dir, xrefs)
+}
+
+#[test]
+fn can_export_xrefs_as_csv() {
+    let (dir, xrefs) = export_xrefs("export-xrefs-csv", XrefsFormat::Csv);
+    let path = dir.join("a.py").to_string_lossy().to_string();
+    let expected = format!(
+        concat!(
+            "reference_path,reference_start_line,reference_start_column,reference_end_line,",
+            "reference_end_column,definition_path,definition_start_line,definition_start_column,",
+            "definition_end_line,definition_end_column,symbol,qualified_name\n",
+            "{0},2,1,2,2,{0},1,1,1,2,x,x\n",
+            "{0},3,1,3,2,,,,,,y,\n",
+        ),
+        path
+    );
+    assert_eq!(expected, xrefs);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn can_export_xrefs_as_jsonl() {
+    let (dir, xrefs) = export_xrefs("export-xrefs-jsonl", XrefsFormat::Jsonl);
+    let path = serde_json::to_string(&dir.join("a.py").to_string_lossy()).unwrap();
+    let expected = format!(
+        concat!(
+            "{{\"reference_path\":{0},\"reference_start_line\":2,\"reference_start_column\":1,",
+            "\"reference_end_line\":2,\"reference_end_column\":2,\"definition_path\":{0},",
+            "\"definition_start_line\":1,\"definition_start_column\":1,",
+            "\"definition_end_line\":1,\"definition_end_column\":2,\"symbol\":\"x\",",
+            "\"qualified_name\":\"x\"}}\n",
+            "{{\"reference_path\":{0},\"reference_start_line\":3,\"reference_start_column\":1,",
+            "\"reference_end_line\":3,\"reference_end_column\":2,\"definition_path\":null,",
+            "\"definition_start_line\":null,\"definition_start_column\":null,",
+            "\"definition_end_line\":null,\"definition_end_column\":null,\"symbol\":\"y\",",
+            "\"qualified_name\":null}}\n",
+        ),
+        path
+    );
+    assert_eq!(expected, xrefs);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn can_export_escaped_tags() {
+    let dir = test_dir("export-tags");
+    let source = "^x = 1\ny = '/\\\\'$\n";
+    std::fs::write(dir.join("a.py"), source).unwrap();
+
+    let mut graph = StackGraph::new();
+    let a = graph.get_or_create_file(dir.join("a.py").to_str().unwrap());
+    add_definition(&mut graph, a, "x", 0, 0..6, Some("variable"));
+    add_definition(&mut graph, a, "a\tb", 1, 7..17, Some("odd\tkind"));
+    // the source of this file is neither on disk nor in the database
+    let b = graph.get_or_create_file(dir.join("b.py").to_str().unwrap());
+    add_definition(&mut graph, b, "c", 0, 0..5, None);
+
+    let db_path = dir.join("index.sqlite");
+    let mut db = SQLiteWriter::open(&db_path).expect("Cannot open writer");
+    let mut partials = PartialPaths::new();
+    db.store_result_for_file(&graph, a, "", &mut partials, &[])
+        .expect("Cannot store file");
+    db.store_result_for_file(&graph, b, "", &mut partials, &[])
+        .expect("Cannot store file");
+    drop(db);
+
+    let tags_path = dir.join("tags");
+    Tags {
+        source_paths: vec![],
+        output: Some(tags_path.clone()),
+    }
+    .run(&db_path)
+    .expect("Expected export to succeed");
+
+    let tags = std::fs::read_to_string(&tags_path).expect("Cannot read tags");
+    let expected = format!(
+        concat!(
+            "!_TAG_FILE_FORMAT\t2\t/extended format; --format=1 will not append ;\" to lines/\n",
+            "!_TAG_FILE_SORTED\t1\t/0=unsorted, 1=sorted, 2=foldcase/\n",
+            "!_TAG_PROGRAM_NAME\ttree-sitter-stack-graphs\t//\n",
+            "!_TAG_PROGRAM_VERSION\t{}\t//\n",
+            "a b\ta.py\t/^y = '\\/\\\\\\\\'\\$$/;\"\tkind:odd\\tkind\tline:2\n",
+            "c\tb.py\t1;\"\tline:1\n",
+            "x\ta.py\t/^\\^x = 1$/;\"\tkind:variable\tline:1\n",
+        ),
+        env!("CARGO_PKG_VERSION")
+    );
+    assert_eq!(expected, tags);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,131 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use pretty_assertions::assert_eq;
+use stack_graphs::storage::SQLiteWriter;
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::path::PathBuf;
+use tree_sitter_stack_graphs::cli::index::Indexer;
+use tree_sitter_stack_graphs::cli::index::IndexingStats;
+use tree_sitter_stack_graphs::cli::util::reporter::ConsoleReporter;
+use tree_sitter_stack_graphs::loader::FileAnalyzers;
+use tree_sitter_stack_graphs::loader::LanguageConfiguration;
+use tree_sitter_stack_graphs::loader::Loader;
+use tree_sitter_stack_graphs::NoCancellation;
+
+use super::test_dir;
+
+static TSG: &str = r#"
+  global ROOT_NODE
+  (module (expression_statement (assignment left:(identifier)@name))) {
+      node @name.def
+      attr (@name.def) type = "pop_symbol", symbol = (source-text @name), source_node = @name, is_definition
+      edge ROOT_NODE -> @name.def
+  }
+"#;
+
+fn language(scope: &str, file_type: &str, builtins: &'static str) -> LanguageConfiguration {
+    LanguageConfiguration::from_sources(
+        tree_sitter_python::language(),
+        Some(scope.into()),
+        None,
+        vec![file_type.into()],
+        PathBuf::from("test.tsg"),
+        TSG,
+        Some((PathBuf::from("builtins"), builtins)),
+        None,
+        FileAnalyzers::new(),
+        &NoCancellation,
+    )
+    .expect("Expected language configuration to load")
+}
+
+#[test]
+fn can_index_builtins_of_multiple_languages() {
+    let dir = test_dir("index-builtins");
+    std::fs::write(dir.join("a.py"), "x = 1\n").unwrap();
+    std::fs::write(dir.join("b.pyi"), "y = 1\n").unwrap();
+    let mut loader = Loader::from_language_configurations(
+        vec![
+            language("source.py", "py", "print = 0\n"),
+            language("source.pyi", "pyi", "len = 0\n"),
+        ],
+        None,
+    )
+    .expect("Expected loader to succeed");
+    let mut db = SQLiteWriter::open(dir.join("index.sqlite")).expect("Cannot open writer");
+
+    let reporter = ConsoleReporter::none();
+    let mut indexer = Indexer::new(&mut db, &mut loader, &reporter);
+    indexer.builtins_layers = Some(Vec::new());
+    indexer
+        .index_all(
+            vec![dir.join("a.py"), dir.join("b.pyi")],
+            None::<&Path>,
+            &NoCancellation,
+        )
+        .expect("Expected indexing to succeed");
+
+    let mut reader = db.into_reader();
+    let files = reader
+        .list_all()
+        .unwrap()
+        .try_iter()
+        .unwrap()
+        .map(|entry| entry.unwrap().path.to_string_lossy().into_owned())
+        .filter(|file| file.ends_with("<builtins>"))
+        .collect::<BTreeSet<_>>();
+    assert_eq!(
+        ["source.py::<builtins>", "source.pyi::<builtins>"]
+            .iter()
+            .map(|file| file.to_string())
+            .collect::<BTreeSet<_>>(),
+        files
+    );
+
+    let file = reader
+        .load_graph_for_file("source.pyi::<builtins>")
+        .expect("Cannot load graph");
+    let (graph, _, _) = reader.get();
+    let symbols = graph
+        .nodes_for_file(file)
+        .filter_map(|node| graph[node].symbol())
+        .map(|symbol| graph[symbol].to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(vec!["len".to_string()], symbols);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn can_collect_memory_usage_of_indexed_files() {
+    let dir = test_dir("index-stats");
+    std::fs::write(dir.join("a.py"), "x = 1\ny = 2\n").unwrap();
+    let mut loader =
+        Loader::from_language_configurations(vec![language("source.py", "py", "")], None)
+            .expect("Expected loader to succeed");
+    let mut db = SQLiteWriter::open(dir.join("index.sqlite")).expect("Cannot open writer");
+
+    let reporter = ConsoleReporter::none();
+    let mut indexer = Indexer::new(&mut db, &mut loader, &reporter);
+    indexer.stats = Some(IndexingStats::default());
+    indexer
+        .index_all(vec![dir.join("a.py")], None::<&Path>, &NoCancellation)
+        .expect("Expected indexing to succeed");
+
+    let stats = indexer.stats.take().expect("Missing statistics");
+    assert_eq!(1, stats.files.len());
+    let file = &stats.files[0];
+    assert_eq!(2, file.paths);
+    assert!(file.graph_bytes > 0);
+    assert!(file.partials_bytes > 0);
+    assert!(file.database_bytes > 0);
+    assert_eq!(1, stats.graph_bytes.frequency(&file.graph_bytes));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,472 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use pretty_assertions::assert_eq;
+use std::path::Path;
+use std::path::PathBuf;
+use tree_sitter_stack_graphs::cli::init::grammar::Grammar;
+use tree_sitter_stack_graphs::cli::init::grammar::GrammarSource;
+use tree_sitter_stack_graphs::cli::init::InitArgs;
+use tree_sitter_stack_graphs::loader::FileAnalyzers;
+use tree_sitter_stack_graphs::loader::LanguageConfiguration;
+use tree_sitter_stack_graphs::NoCancellation;
+
+use super::test_dir;
+
+/// The node types of a small grammar, whose node kinds are a subset of the kinds of the Python
+/// grammar.  The root node kind is not marked explicitly.
+static NODE_TYPES: &str = r#"[
+  {
+    "type": "module",
+    "named": true,
+    "fields": {},
+    "children": { "multiple": true, "required": false, "types": [{ "type": "expression_statement", "named": true }] }
+  },
+  {
+    "type": "expression_statement",
+    "named": true,
+    "fields": {},
+    "children": { "multiple": false, "required": true, "types": [{ "type": "expression", "named": true }] }
+  },
+  {
+    "type": "expression",
+    "named": true,
+    "subtypes": [{ "type": "identifier", "named": true }, { "type": "assignment", "named": true }]
+  },
+  {
+    "type": "assignment",
+    "named": true,
+    "fields": {
+      "left": { "multiple": false, "required": true, "types": [{ "type": "identifier", "named": true }] },
+      "right": { "multiple": false, "required": true, "types": [{ "type": "expression", "named": true }] }
+    }
+  },
+  { "type": "identifier", "named": true },
+  { "type": "=", "named": false }
+]"#;
+
+/// Creates a grammar directory with the given crate name and version, and the given files.
+fn grammar_dir(dir: &Path, crate_name: &str, crate_version: &str, files: &[(&str, &str)]) {
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::write(
+        dir.join("Cargo.toml"),
+        format!(
+            "[package]\nname = \"{}\"\nversion = \"{}\"\n",
+            crate_name, crate_version
+        ),
+    )
+    .unwrap();
+    for (name, content) in files {
+        std::fs::write(dir.join(name), content).unwrap();
+    }
+}
+
+/// Returns the node kinds of the grammar as `(name, fields, is_supertype, is_root)`.
+fn node_kinds(grammar: &Grammar) -> Vec<(String, Vec<String>, bool, bool)> {
+    grammar
+        .node_kinds
+        .iter()
+        .map(|k| (k.name.clone(), k.fields.clone(), k.is_supertype, k.is_root))
+        .collect()
+}
+
+fn find_grammar(name_or_path: &str, dir: &Path, cargo_home: Option<&Path>) -> Grammar {
+    match Grammar::find_in(name_or_path, dir, cargo_home) {
+        Ok(grammar) => grammar,
+        Err(err) => panic!("Cannot find grammar {}: {:#}", name_or_path, err),
+    }
+}
+
+#[test]
+fn can_find_grammar_by_path() {
+    let dir = test_dir("init-grammar-path");
+    grammar_dir(
+        &dir.join("grammars/fixture"),
+        "tree-sitter-fixture",
+        "0.2.0",
+        &[],
+    );
+
+    let grammar = find_grammar("grammars/fixture", &dir, None);
+    assert_eq!(GrammarSource::Local, grammar.source);
+    assert_eq!(
+        dir.join("grammars/fixture").canonicalize().unwrap(),
+        grammar.path
+    );
+    assert_eq!("tree-sitter-fixture", grammar.crate_name);
+    assert_eq!("0.2.0", grammar.crate_version);
+    assert_eq!(None, grammar.name);
+    assert!(grammar.node_kinds.is_empty());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn can_find_grammar_in_node_modules_of_ancestor_directories() {
+    let dir = test_dir("init-grammar-npm");
+    grammar_dir(
+        &dir.join("node_modules/tree-sitter-fixture"),
+        "tree-sitter-fixture",
+        "0.2.0",
+        &[],
+    );
+    let project_dir = dir.join("project/nested");
+    std::fs::create_dir_all(&project_dir).unwrap();
+
+    let grammar = find_grammar("tree-sitter-fixture", &project_dir, None);
+    assert_eq!(GrammarSource::Npm, grammar.source);
+    assert_eq!(
+        dir.join("node_modules/tree-sitter-fixture")
+            .canonicalize()
+            .unwrap(),
+        grammar.path
+    );
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn can_find_latest_grammar_in_cargo_registry() {
+    let dir = test_dir("init-grammar-registry");
+    let cargo_home = dir.join("cargo");
+    let registry = cargo_home.join("registry/src/index.crates.io-0123456789abcdef");
+    for (name, version) in [
+        ("tree-sitter-fixture", "0.9.0"),
+        ("tree-sitter-fixture", "0.10.1"),
+        ("tree-sitter-fixture", "0.11.0-rc.1"),
+        ("tree-sitter-fixture-extras", "1.0.0"),
+    ] {
+        grammar_dir(
+            &registry.join(format!("{}-{}", name, version)),
+            name,
+            version,
+            &[],
+        );
+    }
+    let project_dir = dir.join("project");
+    std::fs::create_dir_all(&project_dir).unwrap();
+
+    let grammar = find_grammar("tree-sitter-fixture", &project_dir, Some(&cargo_home));
+    assert_eq!(GrammarSource::Registry, grammar.source);
+    assert_eq!("tree-sitter-fixture", grammar.crate_name);
+    assert_eq!("0.10.1", grammar.crate_version);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn cannot_find_missing_grammar() {
+    let dir = test_dir("init-grammar-missing");
+    let cargo_home = dir.join("cargo");
+    std::fs::create_dir_all(cargo_home.join("registry/src")).unwrap();
+    assert!(Grammar::find_in("tree-sitter-missing", &dir, Some(&cargo_home)).is_err());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn can_read_grammar_settings_from_package_json() {
+    let dir = test_dir("init-grammar-package-json");
+    grammar_dir(
+        &dir,
+        "tree-sitter-fixture",
+        "0.2.0",
+        &[
+            ("src/grammar.json", r#"{ "name": "fixture", "rules": {} }"#),
+            (
+                "package.json",
+                r#"{ "name": "tree-sitter-fixture", "tree-sitter": [{ "scope": "source.fixture", "file-types": ["fix", "fixture"] }] }"#,
+            ),
+        ],
+    );
+
+    let grammar = find_grammar(".", &dir, None);
+    assert_eq!(Some("fixture".to_string()), grammar.name);
+    assert_eq!(Some("source.fixture".to_string()), grammar.scope);
+    assert_eq!(vec!["fix", "fixture"], grammar.file_types);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn can_read_grammar_settings_from_tree_sitter_json() {
+    let dir = test_dir("init-grammar-tree-sitter-json");
+    grammar_dir(
+        &dir,
+        "tree-sitter-fixture",
+        "0.2.0",
+        &[
+            (
+                "tree-sitter.json",
+                r#"{ "grammars": [{ "name": "fixture", "scope": "source.fix", "path": ".", "file-types": ["fix"] }], "metadata": { "version": "0.2.0" } }"#,
+            ),
+            // ignored, because tree-sitter.json takes precedence
+            ("src/grammar.json", r#"{ "name": "outdated", "rules": {} }"#),
+            (
+                "package.json",
+                r#"{ "name": "tree-sitter-fixture", "tree-sitter": [{ "scope": "source.outdated" }] }"#,
+            ),
+        ],
+    );
+
+    let grammar = find_grammar(".", &dir, None);
+    assert_eq!(Some("fixture".to_string()), grammar.name);
+    assert_eq!(Some("source.fix".to_string()), grammar.scope);
+    assert_eq!(vec!["fix"], grammar.file_types);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn can_read_node_kinds_from_node_types_json() {
+    let dir = test_dir("init-grammar-node-kinds");
+    grammar_dir(
+        &dir,
+        "tree-sitter-fixture",
+        "0.2.0",
+        &[("src/node-types.json", NODE_TYPES)],
+    );
+
+    let grammar = find_grammar(".", &dir, None);
+    let no_fields = Vec::<String>::new;
+    assert_eq!(
+        vec![
+            ("module".to_string(), no_fields(), false, true),
+            (
+                "expression_statement".to_string(),
+                no_fields(),
+                false,
+                false
+            ),
+            ("expression".to_string(), no_fields(), true, false),
+            (
+                "assignment".to_string(),
+                vec!["left".to_string(), "right".to_string()],
+                false,
+                false
+            ),
+            ("identifier".to_string(), no_fields(), false, false),
+        ],
+        node_kinds(&grammar)
+    );
+    assert_eq!(
+        Some("module"),
+        grammar.root_node_kind().map(|k| k.name.as_str())
+    );
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn can_read_explicit_root_node_kind_from_node_types_json() {
+    let dir = test_dir("init-grammar-explicit-root");
+    // the explicit root takes precedence over module, which looks like the root
+    let node_types = NODE_TYPES.replacen(
+        r#""type": "expression_statement",
+    "named": true,"#,
+        r#""type": "expression_statement",
+    "named": true,
+    "root": true,"#,
+        1,
+    );
+    grammar_dir(
+        &dir,
+        "tree-sitter-fixture",
+        "0.2.0",
+        &[("src/node-types.json", &node_types)],
+    );
+
+    let grammar = find_grammar(".", &dir, None);
+    assert_eq!(
+        Some("expression_statement"),
+        grammar.root_node_kind().map(|k| k.name.as_str())
+    );
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn cannot_read_ambiguous_root_node_kind_from_node_types_json() {
+    let dir = test_dir("init-grammar-ambiguous-root");
+    // a second node kind with children that no other node kind contains
+    let node_types = NODE_TYPES.replacen(
+        "[",
+        r#"[
+  {
+    "type": "script",
+    "named": true,
+    "fields": {},
+    "children": { "multiple": true, "required": false, "types": [{ "type": "identifier", "named": true }] }
+  },"#,
+        1,
+    );
+    grammar_dir(
+        &dir,
+        "tree-sitter-fixture",
+        "0.2.0",
+        &[("src/node-types.json", &node_types)],
+    );
+
+    let grammar = find_grammar(".", &dir, None);
+    assert!(grammar.root_node_kind().is_none());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn can_generate_project_from_grammar() {
+    let dir = test_dir("init-generate");
+    let grammar_path = dir.join("tree-sitter-fixture");
+    grammar_dir(
+        &grammar_path,
+        "tree-sitter-fixture",
+        "0.2.0",
+        &[
+            ("src/grammar.json", r#"{ "name": "fixture", "rules": {} }"#),
+            (
+                "package.json",
+                r#"{ "name": "tree-sitter-fixture", "tree-sitter": [{ "scope": "source.fixture", "file-types": ["fix"] }] }"#,
+            ),
+            ("src/node-types.json", NODE_TYPES),
+        ],
+    );
+    let project_path = dir.join("project");
+
+    InitArgs {
+        project_path: project_path.clone(),
+        non_interactive: true,
+        from_grammar: Some(grammar_path.to_string_lossy().into_owned()),
+        language_name: None,
+        language_id: None,
+        language_file_extension: None,
+        crate_name: None,
+        crate_version: None,
+        author: None,
+        license: Some("MIT".to_string()),
+        grammar_crate_name: None,
+        grammar_crate_version: None,
+        python_bindings: false,
+        node_bindings: false,
+        example_tests: false,
+        comment_prefix: None,
+        internal: false,
+    }
+    .run()
+    .expect("Expected project generation to succeed");
+
+    // the language settings are detected from the grammar, which is depended on by path
+    let cargo_toml = read_toml(&project_path.join("Cargo.toml"));
+    assert_eq!(
+        Some("tree-sitter-stack-graphs-fixture"),
+        cargo_toml["package"]["name"].as_str()
+    );
+    assert_eq!(
+        Some("../tree-sitter-fixture"),
+        cargo_toml["dependencies"]["tree-sitter-fixture"]["path"].as_str()
+    );
+    assert!(project_path.join("src/builtins.fix").is_file());
+    assert!(project_path.join("test/test.fix").is_file());
+
+    // the generated rules connect the root node kind of the grammar, and load for a language with
+    // the same node kinds
+    let tsg = std::fs::read_to_string(project_path.join("src/stack-graphs.tsg")).unwrap();
+    assert!(tsg.contains("(module) @root {"));
+    assert!(tsg.contains(";;   assignment            fields: left, right"));
+    LanguageConfiguration::from_sources(
+        tree_sitter_python::language(),
+        Some("source.fixture".into()),
+        None,
+        vec!["fix".into()],
+        PathBuf::from("src/stack-graphs.tsg"),
+        &tsg,
+        None,
+        None,
+        FileAnalyzers::new(),
+        &NoCancellation,
+    )
+    .expect("Expected generated rules to load");
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn can_generate_project_with_bindings_and_example_tests() {
+    let dir = test_dir("init-generate-bindings");
+    let project_path = dir.join("project");
+
+    InitArgs {
+        project_path: project_path.clone(),
+        non_interactive: true,
+        from_grammar: None,
+        language_name: Some("Fixture".to_string()),
+        language_id: Some("fixture".to_string()),
+        language_file_extension: Some("fix".to_string()),
+        crate_name: None,
+        crate_version: None,
+        author: None,
+        license: Some("MIT".to_string()),
+        grammar_crate_name: None,
+        grammar_crate_version: Some("0.2".to_string()),
+        python_bindings: true,
+        node_bindings: true,
+        example_tests: true,
+        comment_prefix: Some("#".to_string()),
+        internal: false,
+    }
+    .run()
+    .expect("Expected project generation to succeed");
+
+    for file in [
+        "python/src/lib.rs",
+        "node/build.rs",
+        "node/src/lib.rs",
+        "src/builtins.fix",
+        "test/examples/definitions.fix.skip",
+        "test/examples/builtins.fix.skip",
+    ] {
+        assert!(project_path.join(file).is_file(), "missing {}", file);
+    }
+
+    // the binding packages depend on the project by path
+    for manifest in ["python/Cargo.toml", "node/Cargo.toml"] {
+        let cargo_toml = read_toml(&project_path.join(manifest));
+        assert_eq!(
+            Some(".."),
+            cargo_toml["dependencies"]["tree-sitter-stack-graphs-fixture"]["path"].as_str(),
+            "dependency of {}",
+            manifest
+        );
+    }
+    let pyproject_toml = read_toml(&project_path.join("python/pyproject.toml"));
+    assert_eq!(
+        Some("maturin"),
+        pyproject_toml["build-system"]["build-backend"].as_str()
+    );
+    let package_json = std::fs::read_to_string(project_path.join("node/package.json")).unwrap();
+    let package_json = serde_json::from_str::<serde_json::Value>(&package_json)
+        .expect("Expected node/package.json to be valid JSON");
+    assert_eq!(
+        Some("tree-sitter-stack-graphs-fixture"),
+        package_json["name"].as_str()
+    );
+
+    // example tests use the comment prefix for their assertions
+    let example =
+        std::fs::read_to_string(project_path.join("test/examples/definitions.fix.skip")).unwrap();
+    assert!(example.contains("\n#   ^ defines: example_value\n"));
+    let builtins = std::fs::read_to_string(project_path.join("src/builtins.fix")).unwrap();
+    assert!(builtins.starts_with("# Builtins for Fixture."));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+fn read_toml(path: &Path) -> toml::Value {
+    let content = std::fs::read_to_string(path).unwrap();
+    match toml::from_str(&content) {
+        Ok(value) => value,
+        Err(err) => panic!("Expected {} to be valid TOML: {}", path.display(), err),
+    }
+}
@@ -0,0 +1,209 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use pretty_assertions::assert_eq;
+use stack_graphs::storage::SQLiteReader;
+use stack_graphs::storage::SQLiteWriter;
+use std::path::Path;
+use std::path::PathBuf;
+use tree_sitter_stack_graphs::cli::index::Indexer;
+use tree_sitter_stack_graphs::cli::query::Querier;
+use tree_sitter_stack_graphs::cli::query::QueryResult;
+use tree_sitter_stack_graphs::cli::util::reporter::ConsoleReporter;
+use tree_sitter_stack_graphs::cli::util::SourcePosition;
+use tree_sitter_stack_graphs::cli::util::SourceSpan;
+use tree_sitter_stack_graphs::loader::FileAnalyzers;
+use tree_sitter_stack_graphs::loader::LanguageConfiguration;
+use tree_sitter_stack_graphs::loader::Loader;
+use tree_sitter_stack_graphs::NoCancellation;
+
+use super::test_dir;
+
+/// Variables are visible in the module scope, which also sees the functions of all modules.
+/// Definitions of strings take precedence over definitions of integers, and shadow them.
+static TSG: &str = r#"
+  global ROOT_NODE
+  (module)@mod {
+      node @mod.scope
+      edge @mod.scope -> ROOT_NODE
+  }
+  (module (function_definition name:(identifier)@name)) {
+      node @name.def
+      attr (@name.def) type = "pop_symbol", symbol = (source-text @name), source_node = @name, is_definition
+      edge ROOT_NODE -> @name.def
+  }
+  (module (expression_statement (assignment left:(identifier)@name right:(integer))))@mod {
+      node @name.def
+      attr (@name.def) type = "pop_symbol", symbol = (source-text @name), source_node = @name, is_definition
+      edge @mod.scope -> @name.def
+  }
+  (module (expression_statement (assignment left:(identifier)@name right:(string))))@mod {
+      node @name.def
+      attr (@name.def) type = "pop_symbol", symbol = (source-text @name), source_node = @name, is_definition
+      edge @mod.scope -> @name.def
+      attr (@mod.scope -> @name.def) precedence = 1
+  }
+  (module (expression_statement (identifier)@name))@mod {
+      node @name.ref
+      attr (@name.ref) type = "push_symbol", symbol = (source-text @name), source_node = @name, is_reference
+      edge @name.ref -> @mod.scope
+  }
+"#;
+
+/// Indexes the given sources in a new test directory, and returns the canonical path of the first
+/// file and of the database.
+fn index_sources(name: &str, sources: &[(&str, &str)]) -> (PathBuf, PathBuf) {
+    let dir = test_dir(name).canonicalize().unwrap();
+    for (file_name, source) in sources {
+        std::fs::write(dir.join(file_name), source).unwrap();
+    }
+    let file_paths = sources
+        .iter()
+        .map(|(file_name, _)| dir.join(file_name))
+        .collect::<Vec<_>>();
+    let language = LanguageConfiguration::from_sources(
+        tree_sitter_python::language(),
+        Some("source.py".into()),
+        None,
+        vec!["py".into()],
+        PathBuf::from("test.tsg"),
+        TSG,
+        None,
+        None,
+        FileAnalyzers::new(),
+        &NoCancellation,
+    )
+    .expect("Expected language configuration to load");
+    let mut loader = Loader::from_language_configurations(vec![language], None)
+        .expect("Expected loader to succeed");
+
+    let db_path = dir.join("index.sqlite");
+    let mut db = SQLiteWriter::open(&db_path).expect("Cannot open writer");
+    let reporter = ConsoleReporter::none();
+    Indexer::new(&mut db, &mut loader, &reporter)
+        .index_all(&file_paths, None::<&Path>, &NoCancellation)
+        .expect("Expected indexing to succeed");
+    (file_paths[0].clone(), db_path)
+}
+
+/// File name and one-based line of a definition.
+type Location = (String, usize);
+
+/// Finds the definitions of the reference at the given one-based line and column, and returns the
+/// file names and one-based lines of the definitions and of the rejected shadowed definitions.
+fn definition_lines(
+    file_path: &Path,
+    db_path: &Path,
+    line: usize,
+    column: usize,
+    limit: Option<usize>,
+) -> (Vec<Location>, Vec<Location>) {
+    let mut db = SQLiteReader::open(db_path).expect("Cannot open reader");
+    let reporter = ConsoleReporter::none();
+    let mut querier = Querier::new(&mut db, &reporter);
+    querier.set_max_results(limit);
+    let reference = format!("{}:{}:{}", file_path.display(), line, column)
+        .parse::<SourcePosition>()
+        .expect("Invalid position");
+    let results = querier
+        .definitions(reference, &NoCancellation)
+        .expect("Expected query to succeed");
+    assert_eq!(1, results.len());
+    let QueryResult {
+        targets, shadowed, ..
+    } = results.into_iter().next().unwrap();
+    let location = |span: &SourceSpan| {
+        (
+            span.path.file_name().unwrap().to_string_lossy().to_string(),
+            span.span.start.line + 1,
+        )
+    };
+    (
+        targets
+            .iter()
+            .map(|target| location(&target.span))
+            .collect(),
+        shadowed
+            .iter()
+            .map(|shadowed| location(&shadowed.target.span))
+            .collect(),
+    )
+}
+
+#[test]
+fn limit_is_applied_after_shadowing() {
+    let (file_path, db_path) = index_sources("query-limit", &[("a.py", "x = 1\nx = 'one'\nx\n")]);
+
+    let expected = (vec![("a.py".to_string(), 2)], vec![("a.py".to_string(), 1)]);
+    assert_eq!(expected, definition_lines(&file_path, &db_path, 3, 1, None));
+    assert_eq!(
+        expected,
+        definition_lines(&file_path, &db_path, 3, 1, Some(1))
+    );
+
+    let _ = std::fs::remove_dir_all(file_path.parent().unwrap());
+}
+
+#[test]
+fn definitions_are_ranked_by_file_and_path_length() {
+    let (file_path, db_path) = index_sources(
+        "query-ranking",
+        &[
+            ("a.py", "def x():\n    pass\nx = 1\nx\n"),
+            ("b.py", "def x():\n    pass\n"),
+        ],
+    );
+
+    assert_eq!(
+        (
+            vec![
+                ("a.py".to_string(), 3),
+                ("a.py".to_string(), 1),
+                ("b.py".to_string(), 1),
+            ],
+            vec![],
+        ),
+        definition_lines(&file_path, &db_path, 4, 1, None)
+    );
+
+    let _ = std::fs::remove_dir_all(file_path.parent().unwrap());
+}
+
+#[test]
+fn can_explain_ranking_of_definitions() {
+    let (file_path, db_path) = index_sources(
+        "query-explain",
+        &[("a.py", "def x():\n    pass\nx = 1\nx\n")],
+    );
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_tree-sitter-stack-graphs"))
+        .env("NO_COLOR", "1")
+        .arg("query")
+        .arg("--database")
+        .arg(&db_path)
+        .arg("definition")
+        .arg("--explain")
+        .arg(format!("{}:4:1", file_path.display()))
+        .output()
+        .expect("Expected query to run");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    let explanation = stdout
+        .find("has 2 definitions\ndefinitions in the same file come first, then definitions with shorter paths\n")
+        .expect("Missing ranking explanation");
+    let variable = stdout
+        .find(&format!("{}:3:1:", file_path.display()))
+        .expect("Missing variable definition");
+    let function = stdout
+        .find(&format!("{}:1:5:", file_path.display()))
+        .expect("Missing function definition");
+    assert!(explanation < variable && variable < function);
+    assert!(stdout.contains("rejected no shadowed definitions"));
+
+    let _ = std::fs::remove_dir_all(file_path.parent().unwrap());
+}
@@ -0,0 +1,370 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use lsp_positions::Offset;
+use lsp_positions::Position;
+use lsp_positions::Span;
+use pretty_assertions::assert_eq;
+use stack_graphs::graph::StackGraph;
+use std::path::Path;
+use std::path::PathBuf;
+use tree_sitter_stack_graphs::cli::util::iter_filtered_files;
+use tree_sitter_stack_graphs::cli::util::iter_filtered_files_and_directories;
+use tree_sitter_stack_graphs::cli::util::PathFilter;
+use tree_sitter_stack_graphs::cli::util::PathGlob;
+use tree_sitter_stack_graphs::cli::util::PositionEncoding;
+use tree_sitter_stack_graphs::cli::util::SourcePosition;
+
+use super::test_dir;
+
+const ENCODINGS: [PositionEncoding; 3] = [
+    PositionEncoding::Utf8,
+    PositionEncoding::Utf16,
+    PositionEncoding::Grapheme,
+];
+
+/// Checks that every pair of encodings converts the columns of each position into each other.
+/// Columns are given as `[utf8, utf16, grapheme]`.
+fn check_convert_columns(line: &str, columns: &[[usize; 3]]) {
+    for column in columns {
+        for (from, from_column) in ENCODINGS.iter().zip(column) {
+            for (to, to_column) in ENCODINGS.iter().zip(column) {
+                assert_eq!(
+                    Some(*to_column),
+                    from.convert_column(line, *from_column, *to),
+                    "converting {:?} column {} to {:?} in {:?}",
+                    from,
+                    from_column,
+                    to,
+                    line,
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn can_convert_columns_of_multi_byte_characters() {
+    // é is two bytes and one UTF-16 code unit, 😀 is four bytes and two UTF-16 code units
+    check_convert_columns(
+        "aé😀b",
+        &[[0, 0, 0], [1, 1, 1], [3, 2, 2], [7, 4, 3], [8, 5, 4]],
+    );
+}
+
+#[test]
+fn can_convert_columns_of_grapheme_clusters() {
+    // e followed by a combining acute accent is one grapheme of three bytes
+    check_convert_columns("e\u{301}x", &[[0, 0, 0], [3, 2, 1], [4, 3, 2]]);
+    check_convert_columns("xe\u{301}", &[[0, 0, 0], [1, 1, 1], [4, 3, 2]]);
+    // a family emoji is one grapheme of three astral-plane characters joined by zero-width joiners
+    check_convert_columns(
+        "👨\u{200d}👩\u{200d}👧!",
+        &[[0, 0, 0], [18, 8, 1], [19, 9, 2]],
+    );
+}
+
+#[test]
+fn cannot_convert_columns_inside_characters() {
+    let line = "aé😀b";
+    // inside the bytes of é and 😀
+    assert_eq!(
+        None,
+        PositionEncoding::Utf8.convert_column(line, 2, PositionEncoding::Utf16)
+    );
+    assert_eq!(
+        None,
+        PositionEncoding::Utf8.convert_column(line, 5, PositionEncoding::Grapheme)
+    );
+    // between the surrogates of 😀
+    assert_eq!(
+        None,
+        PositionEncoding::Utf16.convert_column(line, 3, PositionEncoding::Utf8)
+    );
+    // past the end of the line
+    for encoding in &ENCODINGS {
+        assert_eq!(
+            None,
+            encoding.convert_column(line, 9, PositionEncoding::Utf8)
+        );
+    }
+}
+
+#[test]
+fn cannot_convert_columns_inside_grapheme_clusters_to_graphemes() {
+    let line = "e\u{301}x";
+    assert_eq!(
+        None,
+        PositionEncoding::Utf8.convert_column(line, 1, PositionEncoding::Grapheme)
+    );
+    assert_eq!(
+        None,
+        PositionEncoding::Utf16.convert_column(line, 1, PositionEncoding::Grapheme)
+    );
+    // the accent is a character of its own in the other encodings
+    assert_eq!(
+        Some(1),
+        PositionEncoding::Utf8.convert_column(line, 1, PositionEncoding::Utf16)
+    );
+}
+
+#[test]
+fn can_find_references_at_columns_of_each_encoding() {
+    // a reference to 😀 in the line "é 😀 b"
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("test.py");
+    let symbol = graph.add_symbol("😀");
+    let id = graph.new_node_id(file);
+    let node = graph.add_push_symbol_node(id, symbol, true).unwrap();
+    let position = |utf8_offset, utf16_offset, grapheme_offset| Position {
+        line: 0,
+        column: Offset {
+            utf8_offset,
+            utf16_offset,
+            grapheme_offset,
+        },
+        containing_line: 0..9,
+        trimmed_line: 0..9,
+    };
+    graph.source_info_mut(node).span = Span {
+        start: position(3, 2, 2),
+        end: position(7, 4, 3),
+    };
+
+    // columns of é, 😀, and b, as [utf8, utf16, grapheme]
+    let columns = [[0, 0, 0], [3, 2, 2], [8, 5, 4]];
+    let expected = [false, true, false];
+    for (column, expected) in columns.iter().zip(&expected) {
+        for (encoding, column) in ENCODINGS.iter().zip(column) {
+            let source_position = SourcePosition {
+                path: PathBuf::from("test.py"),
+                line: 0,
+                column: *column,
+                end_column: None,
+                encoding: *encoding,
+            };
+            let references = source_position
+                .iter_references(&graph)
+                .map(|(node, _)| node)
+                .collect::<Vec<_>>();
+            assert_eq!(
+                *expected,
+                references == vec![node],
+                "finding references at {:?} column {}",
+                encoding,
+                column,
+            );
+        }
+    }
+}
+
+fn glob(glob: &str) -> PathGlob {
+    glob.parse().expect("Expected valid glob")
+}
+
+fn filter(include: &[&str], exclude: &[&str]) -> PathFilter {
+    PathFilter {
+        include: include.iter().map(|g| glob(g)).collect(),
+        exclude: exclude.iter().map(|g| glob(g)).collect(),
+        respect_gitignore: false,
+    }
+}
+
+/// Creates the given files in a new source directory.  The source directory is nested in a
+/// directory called `vendor`, to check that globs are matched relative to the source root.
+fn source_dir(name: &str, files: &[&str]) -> PathBuf {
+    let dir = test_dir(name).join("vendor").join("project");
+    for file in files {
+        let path = dir.join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, "").unwrap();
+    }
+    dir
+}
+
+/// Returns the files in the source directory that are selected by the filter, relative to the
+/// source directory.  Checks that the same files are selected whether or not ignore files are
+/// respected, because the directory does not contain any.
+fn selected_files(dir: &Path, filter: &PathFilter) -> Vec<String> {
+    let list_files = |respect_gitignore| {
+        let filter = PathFilter {
+            respect_gitignore,
+            ..filter.clone()
+        };
+        iter_filtered_files_and_directories(vec![dir], filter)
+            .map(|(source_root, path, _)| {
+                assert_eq!(dir, source_root);
+                let path = path.strip_prefix(dir).unwrap();
+                path.components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/")
+            })
+            .collect::<Vec<_>>()
+    };
+    let files = list_files(false);
+    assert_eq!(files, list_files(true));
+    files
+}
+
+#[test]
+fn can_match_globs() {
+    let cases: &[(&str, &str, bool)] = &[
+        // globs without a slash match any path component
+        ("*.py", "a.py", true),
+        ("*.py", "src/a.py", true),
+        ("*.py", "a.pyi", false),
+        ("vendor", "vendor/lib/a.py", true),
+        ("vendor", "src/vendor/a.py", true),
+        ("vendor", "vendored/a.py", false),
+        ("?.py", "a.py", true),
+        ("?.py", "ab.py", false),
+        ("[ab].py", "b.py", true),
+        ("[!ab].py", "b.py", false),
+        ("[!ab].py", "c.py", true),
+        // globs with a slash match the whole path
+        ("src/*.py", "src/a.py", true),
+        ("src/*.py", "lib/src/a.py", false),
+        ("src/*.py", "src/lib/a.py", false),
+        ("src/**/*.py", "src/lib/a.py", true),
+        ("src/**/*.py", "src/a.py", true),
+        ("**/test_*.py", "a/b/test_c.py", true),
+        ("/vendor", "vendor/a.py", true),
+        ("/vendor", "src/vendor/a.py", false),
+        ("build/", "build/a.py", true),
+        // alternatives, and regex set operators in character classes, which match literally
+        ("*.{js,ts}", "lib/a.ts", true),
+        ("*.{js,ts}", "lib/a.py", false),
+        ("[&&]-[--]-[~~].py", "&---~.py", true),
+        ("[&&]-[--]-[~~].py", "a---~.py", false),
+    ];
+    for (pattern, path, expected) in cases {
+        assert_eq!(
+            *expected,
+            glob(pattern).matches(Path::new(path)),
+            "matching {} against {}",
+            pattern,
+            path
+        );
+    }
+}
+
+#[test]
+fn cannot_parse_empty_globs() {
+    assert!("".parse::<PathGlob>().is_err());
+    assert!("/".parse::<PathGlob>().is_err());
+}
+
+#[test]
+fn cannot_parse_invalid_globs() {
+    assert!("[a.py".parse::<PathGlob>().is_err());
+    assert!("src/{a,b.py".parse::<PathGlob>().is_err());
+}
+
+#[test]
+fn excluded_globs_take_precedence_over_included_globs() {
+    let paths = ["a.py", "a_test.py", "lib/b.py", "lib/b.js"];
+    let cases: &[(&[&str], &[&str], &[&str])] = &[
+        (&[], &[], &["a.py", "a_test.py", "lib/b.py", "lib/b.js"]),
+        (&["*.py"], &[], &["a.py", "a_test.py", "lib/b.py"]),
+        (&[], &["*_test.py"], &["a.py", "lib/b.py", "lib/b.js"]),
+        (&["*.py"], &["*_test.py"], &["a.py", "lib/b.py"]),
+        (&["lib"], &["*.js"], &["lib/b.py"]),
+        (&["*_test.py"], &["*.py"], &[]),
+    ];
+    for (include, exclude, expected) in cases {
+        let filter = filter(include, exclude);
+        let selected = paths
+            .iter()
+            .filter(|path| filter.matches(Path::new(path)))
+            .copied()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            expected.to_vec(),
+            selected,
+            "including {:?} and excluding {:?}",
+            include,
+            exclude
+        );
+    }
+}
+
+#[test]
+fn excluded_directories_are_skipped() {
+    let dir = source_dir(
+        "filter-excluded-directories",
+        &[
+            "a.py",
+            "build/b.py",
+            "build/out/c.py",
+            "src/build/d.py",
+            "src/e.py",
+            "src/builder.py",
+        ],
+    );
+    assert_eq!(
+        vec!["a.py", "src/builder.py", "src/e.py"],
+        selected_files(&dir, &filter(&[], &["build"]))
+    );
+    assert_eq!(
+        vec!["a.py", "src/build/d.py", "src/builder.py", "src/e.py"],
+        selected_files(&dir, &filter(&[], &["/build"]))
+    );
+    // an included glob does not bring back files in excluded directories
+    assert_eq!(
+        vec!["src/builder.py", "src/e.py"],
+        selected_files(&dir, &filter(&["src"], &["build"]))
+    );
+    let _ = std::fs::remove_dir_all(dir.parent().unwrap().parent().unwrap());
+}
+
+#[test]
+fn globs_are_matched_relative_to_the_source_root() {
+    let dir = source_dir("filter-source-root", &["a.py", "project/b.py", "src/c.py"]);
+    // the source root is in a directory called vendor, which does not exclude its files
+    assert_eq!(
+        vec!["a.py", "project/b.py", "src/c.py"],
+        selected_files(&dir, &filter(&[], &["vendor"]))
+    );
+    // anchored globs match from the source root, not from the directory of the source root
+    assert_eq!(
+        vec!["a.py", "src/c.py"],
+        selected_files(&dir, &filter(&[], &["/project"]))
+    );
+    assert_eq!(
+        vec!["src/c.py"],
+        selected_files(&dir, &filter(&["/src/*.py"], &[]))
+    );
+
+    // files that are given explicitly are matched relative to their parent directory
+    let files = iter_filtered_files_and_directories(
+        vec![dir.join("src/c.py"), dir.join("project/b.py")],
+        filter(&["/c.py"], &[]),
+    )
+    .map(|(source_root, path, _)| (source_root, path))
+    .collect::<Vec<_>>();
+    assert_eq!(vec![(dir.join("src"), dir.join("src/c.py"))], files);
+
+    // files given by a build system are matched relative to the source root they are in
+    let outside = dir.parent().unwrap().join("d.py");
+    std::fs::write(&outside, "").unwrap();
+    let files = iter_filtered_files(
+        &dir,
+        vec![dir.join("src/c.py"), dir.join("a.py"), outside.clone()],
+        filter(&["/src", "/d.py"], &[]),
+    )
+    .map(|(source_root, path, _)| (source_root, path))
+    .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            (dir.clone(), dir.join("src/c.py")),
+            (dir.parent().unwrap().to_path_buf(), outside),
+        ],
+        files
+    );
+    let _ = std::fs::remove_dir_all(dir.parent().unwrap().parent().unwrap());
+}
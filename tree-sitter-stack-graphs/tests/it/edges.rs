@@ -119,3 +119,39 @@ fn can_create_edges_to_singleton_nodes() {
         ],
     );
 }
+
+#[test]
+fn can_find_rule_that_created_edge() {
+    let tsg = r#"
+      (module) {}
+
+      (identifier) @id {
+         node source
+         attr (source) type = "pop_symbol", symbol = (source-text @id), is_definition
+         node sink
+         attr (sink) type = "push_symbol", symbol = (source-text @id), is_reference
+         edge source -> sink
+      }
+    "#;
+    let python = "a";
+    let (graph, _) = build_stack_graph(python, tsg).expect("Could not load stack graph");
+    let source = graph
+        .iter_nodes()
+        .find(|node| graph[*node].is_definition())
+        .expect("Missing definition");
+    let edge = graph.outgoing_edges(source).next().expect("Missing edge");
+    let debug_info = graph
+        .edge_debug_info(edge.source, edge.sink)
+        .expect("Missing debug info")
+        .iter()
+        .map(|entry| (graph[entry.key].to_string(), graph[entry.value].to_string()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            ("tsg_location".to_string(), "line 9 column 10".to_string()),
+            ("tsg_path".to_string(), "<missing tsg path>".to_string()),
+            ("tsg_stanza".to_string(), "line 4".to_string()),
+        ],
+        debug_info
+    );
+}
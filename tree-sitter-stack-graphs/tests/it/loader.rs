@@ -7,11 +7,22 @@
 
 use once_cell::sync::Lazy;
 use pretty_assertions::assert_eq;
+use regex::Regex;
+use stack_graphs::arena::Handle;
+use stack_graphs::graph::File;
 use stack_graphs::graph::StackGraph;
+use std::collections::HashMap;
+use std::path::Path;
 use std::path::PathBuf;
+use tree_sitter_graph::Variables;
 use tree_sitter_stack_graphs::loader::FileAnalyzers;
 use tree_sitter_stack_graphs::loader::LanguageConfiguration;
 use tree_sitter_stack_graphs::loader::Loader;
+#[cfg(feature = "cli")]
+use tree_sitter_stack_graphs::loader::LoaderConfig;
+use tree_sitter_stack_graphs::BuildError;
+use tree_sitter_stack_graphs::CancellationFlag;
+use tree_sitter_stack_graphs::FileAnalyzer;
 use tree_sitter_stack_graphs::NoCancellation;
 use tree_sitter_stack_graphs::StackGraphLanguage;
 
@@ -31,10 +42,13 @@ fn can_load_from_provided_language_configuration() {
         language: language,
         scope: Some("source.py".into()),
         content_regex: None,
+        first_line_regex: None,
         file_types: vec!["py".into()],
         sgl,
         builtins: StackGraph::new(),
+        builtins_layers: HashMap::new(),
         special_files: FileAnalyzers::new(),
+        test_comments: Vec::new(),
     };
     let mut loader =
         Loader::from_language_configurations(vec![lc], None).expect("Expected loader to succeed");
@@ -49,3 +63,232 @@ fn can_load_from_provided_language_configuration() {
         .expect("Expected loading stack graph language to succeed");
     assert_eq!(lc.primary.map(|lc| lc.language), Some(language));
 }
+
+#[test]
+fn can_load_globals_from_sidecars() {
+    let root = std::env::temp_dir().join(format!("sg-sidecars-{}", std::process::id()));
+    let package = root.join("package");
+    std::fs::create_dir_all(&package).unwrap();
+    std::fs::write(
+        root.join(".sg-globals.json"),
+        r#"{ "PROJECT": "root", "MODULE_ROOT": "." }"#,
+    )
+    .unwrap();
+    std::fs::write(
+        package.join(".sg-globals.json"),
+        r#"{ "MODULE_ROOT": "package" }"#,
+    )
+    .unwrap();
+    std::fs::write(
+        package.join("test.py.sg-globals.json"),
+        r#"{ "FILE_KIND": "test" }"#,
+    )
+    .unwrap();
+
+    let mut globals = Variables::new();
+    let result = Loader::load_globals_from_sidecars(&root, &package.join("test.py"), &mut globals);
+    std::fs::remove_dir_all(&root).unwrap();
+    result.expect("Expected loading globals to succeed");
+
+    let get = |name: &str| globals.get(&name.into()).map(|v| v.to_string());
+    assert_eq!(get("PROJECT"), Some("root".to_string()));
+    assert_eq!(get("MODULE_ROOT"), Some("package".to_string()));
+    assert_eq!(get("FILE_KIND"), Some("test".to_string()));
+}
+
+struct NoopAnalyzer;
+
+impl FileAnalyzer for NoopAnalyzer {
+    fn build_stack_graph_into(
+        &self,
+        _stack_graph: &mut StackGraph,
+        _file: Handle<File>,
+        _path: &Path,
+        _source: &str,
+        _all_paths: &mut dyn Iterator<Item = &Path>,
+        _globals: &HashMap<String, String>,
+        _cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<(), BuildError> {
+        Ok(())
+    }
+}
+
+#[test]
+fn can_match_special_files_by_pattern() {
+    let special_files = FileAnalyzers::new()
+        .add("package.json".into(), NoopAnalyzer)
+        .add_glob("*.gemspec", NoopAnalyzer)
+        .and_then(|s| s.add_glob("requirements*.txt", NoopAnalyzer))
+        .and_then(|s| s.add_glob("setup.[!p]y", NoopAnalyzer))
+        .and_then(|s| s.add_glob("[&&]-[--]-[~~].{js,ts}", NoopAnalyzer))
+        .expect("Expected valid globs");
+
+    assert!(special_files.get("package.json").is_some());
+    assert!(special_files.get("package.jsonc").is_none());
+    assert!(special_files.get("foo.gemspec").is_some());
+    assert!(special_files.get("foo.gemspec.bak").is_none());
+    assert!(special_files.get("requirements.txt").is_some());
+    assert!(special_files.get("requirements-dev.txt").is_some());
+    assert!(special_files.get("requirements.txt.orig").is_none());
+    assert!(special_files.get("setup.cy").is_some());
+    assert!(special_files.get("setup.py").is_none());
+    // regex set operators in character classes are matched literally
+    assert!(special_files.get("&---~.ts").is_some());
+    assert!(special_files.get("a---~.ts").is_none());
+}
+
+#[test]
+fn cannot_add_special_files_with_invalid_glob() {
+    assert!(FileAnalyzers::new()
+        .add_glob("setup.[py", NoopAnalyzer)
+        .is_err());
+}
+
+#[test]
+fn can_load_builtins_layers() {
+    let language = tree_sitter_python::language();
+    let tsg = r#"
+      (module) {
+        node def
+        attr (def) type = "pop_symbol", symbol = "x", is_definition
+      }
+    "#;
+    let mut lc = LanguageConfiguration::from_sources(
+        language,
+        Some("source.py".into()),
+        None,
+        vec!["py".into()],
+        PathBuf::from("stack-graphs.tsg"),
+        tsg,
+        Some((PathBuf::from("builtins.py"), "pass")),
+        None,
+        FileAnalyzers::new(),
+        &NoCancellation,
+    )
+    .expect("Expected loading language configuration to succeed");
+    lc.add_builtins_layer(
+        "framework".into(),
+        Path::new("<builtins:framework>"),
+        "pass",
+        None,
+        &NoCancellation,
+    )
+    .expect("Expected loading builtins layer to succeed");
+
+    let mut graph = StackGraph::new();
+    lc.load_builtins_into(&mut graph, &["framework"])
+        .expect("Expected loading builtins to succeed");
+    let files = graph
+        .iter_files()
+        .map(|f| graph[f].name().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(vec!["<builtins>", "<builtins:framework>"], files);
+
+    let mut graph = StackGraph::new();
+    assert!(lc.load_builtins_into(&mut graph, &["unknown"]).is_err());
+}
+
+#[test]
+#[cfg(feature = "cli")]
+fn can_parse_loader_config() {
+    let config = r#"
+      [[language]]
+      grammar = "vendor/tree-sitter-python"
+      tsg = "stack-graphs/python.tsg"
+      file-types = ["py", "pyi"]
+      line-comments = ["//"]
+      block-comments = [["<!--", "-->"]]
+
+      [language.special-files."requirements*.txt"]
+      grammar = "/opt/tree-sitter-requirements"
+      tsg = "stack-graphs/requirements.tsg"
+
+      [[language]]
+      grammar = "vendor/tree-sitter-javascript"
+      scope = "source.js"
+    "#;
+    let config = LoaderConfig::from_str(config, Path::new("project"))
+        .expect("Expected parsing loader config to succeed");
+
+    assert_eq!(2, config.languages.len());
+    let python = &config.languages[0];
+    assert_eq!(
+        PathBuf::from("project/vendor/tree-sitter-python"),
+        python.grammar
+    );
+    assert_eq!(None, python.scope);
+    assert_eq!(
+        Some(PathBuf::from("project/stack-graphs/python.tsg")),
+        python.tsg
+    );
+    assert_eq!(None, python.builtins);
+    assert_eq!(
+        Some(vec!["py".to_string(), "pyi".to_string()]),
+        python.file_types
+    );
+    assert_eq!(vec!["//".to_string()], python.line_comments);
+    assert_eq!(
+        vec![("<!--".to_string(), "-->".to_string())],
+        python.block_comments
+    );
+    let requirements = &python.special_files["requirements*.txt"];
+    assert_eq!(
+        Some(PathBuf::from("/opt/tree-sitter-requirements")),
+        requirements.grammar
+    );
+    assert_eq!(
+        PathBuf::from("project/stack-graphs/requirements.tsg"),
+        requirements.tsg
+    );
+    let javascript = &config.languages[1];
+    assert_eq!(Some("source.js".to_string()), javascript.scope);
+    assert!(javascript.line_comments.is_empty());
+    assert!(javascript.block_comments.is_empty());
+    assert!(javascript.special_files.is_empty());
+}
+
+#[test]
+#[cfg(feature = "cli")]
+fn cannot_parse_loader_config_with_unknown_fields() {
+    let config = r#"
+      [[language]]
+      grammar = "vendor/tree-sitter-python"
+      queries = "queries"
+    "#;
+    assert!(LoaderConfig::from_str(config, Path::new("")).is_err());
+}
+
+#[test]
+fn can_load_for_file_by_first_line() {
+    let language = tree_sitter_python::language();
+    let sgl = StackGraphLanguage::from_str(language, &TSG).unwrap();
+    let lc = LanguageConfiguration {
+        language,
+        scope: Some("source.py".into()),
+        content_regex: None,
+        first_line_regex: Some(Regex::new(r"^#!.*\bpython[0-9.]*\b").unwrap()),
+        file_types: vec!["py".into()],
+        sgl,
+        builtins: StackGraph::new(),
+        builtins_layers: HashMap::new(),
+        special_files: FileAnalyzers::new(),
+        test_comments: Vec::new(),
+    };
+    let mut loader =
+        Loader::from_language_configurations(vec![lc], None).expect("Expected loader to succeed");
+
+    let path = PathBuf::from("bin/script");
+    let lc = loader
+        .load_for_file(
+            &path,
+            &mut Some("#!/usr/bin/env python3\npass\n"),
+            &NoCancellation,
+        )
+        .expect("Expected loading stack graph language to succeed");
+    assert_eq!(lc.primary.map(|lc| lc.language), Some(language));
+
+    let lc = loader
+        .load_for_file(&path, &mut Some("#!/bin/sh\ntrue\n"), &NoCancellation)
+        .expect("Expected loading stack graph language to succeed");
+    assert!(lc.primary.is_none());
+}
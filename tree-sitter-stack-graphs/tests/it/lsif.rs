@@ -0,0 +1,161 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use lsp_positions::Span;
+use pretty_assertions::assert_eq;
+use serde_json::Value;
+use std::path::Path;
+use tree_sitter_stack_graphs::lsif::LsifExporter;
+
+fn span(line: usize, start: usize, end: usize) -> Span {
+    let mut span = Span::default();
+    span.start.line = line;
+    span.start.column.utf16_offset = start;
+    span.end.line = line;
+    span.end.column.utf16_offset = end;
+    span
+}
+
+fn dump(exporter: &LsifExporter) -> Vec<Value> {
+    let mut output = Vec::new();
+    exporter.write(&mut output).unwrap();
+    String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+fn find<'a>(elements: &'a [Value], label: &str) -> Vec<&'a Value> {
+    elements.iter().filter(|e| e["label"] == label).collect()
+}
+
+/// Returns the element that the edge with the given label leaves from the given vertex to.
+fn follow<'a>(elements: &'a [Value], from: &Value, label: &str) -> &'a Value {
+    let edge = elements
+        .iter()
+        .find(|e| e["label"] == label && e["outV"] == from["id"])
+        .unwrap();
+    elements.iter().find(|e| e["id"] == edge["inV"]).unwrap()
+}
+
+fn range_at(elements: &[Value], line: usize, character: usize) -> &Value {
+    find(elements, "range")
+        .into_iter()
+        .find(|r| r["start"]["line"] == line && r["start"]["character"] == character)
+        .unwrap()
+}
+
+#[test]
+fn can_export_references_to_single_definition() {
+    let mut exporter = LsifExporter::new(Path::new("/project"));
+    let definition = span(0, 4, 7);
+    exporter.add_reference(
+        Path::new("/project/a.py"),
+        &span(3, 0, 3),
+        [(Path::new("/project/a.py"), &definition)],
+    );
+    exporter.add_reference(
+        Path::new("/project/b.py"),
+        &span(1, 2, 5),
+        [(Path::new("/project/a.py"), &definition)],
+    );
+    let elements = dump(&exporter);
+
+    let metadata = &elements[0];
+    assert_eq!("metaData", metadata["label"]);
+    assert_eq!("file:///project", metadata["projectRoot"]);
+    assert_eq!("utf-16", metadata["positionEncoding"]);
+
+    let documents = find(&elements, "document")
+        .iter()
+        .map(|d| d["uri"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec!["file:///project/a.py", "file:///project/b.py"],
+        documents
+    );
+    assert_eq!(3, find(&elements, "range").len());
+
+    // both references share the result set of the definition
+    let result_set = follow(&elements, range_at(&elements, 0, 4), "next");
+    assert_eq!(
+        result_set,
+        follow(&elements, range_at(&elements, 3, 0), "next")
+    );
+    assert_eq!(
+        result_set,
+        follow(&elements, range_at(&elements, 1, 2), "next")
+    );
+
+    let definition_result = follow(&elements, result_set, "textDocument/definition");
+    let items = elements
+        .iter()
+        .filter(|e| e["label"] == "item" && e["outV"] == definition_result["id"])
+        .collect::<Vec<_>>();
+    assert_eq!(1, items.len());
+    assert_eq!(
+        serde_json::json!([range_at(&elements, 0, 4)["id"]]),
+        items[0]["inVs"]
+    );
+
+    let reference_result = follow(&elements, result_set, "textDocument/references");
+    let reference_items = elements
+        .iter()
+        .filter(|e| e["label"] == "item" && e["outV"] == reference_result["id"])
+        .map(|e| {
+            (
+                e["property"].as_str().unwrap(),
+                e["inVs"].as_array().unwrap().len(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![("definitions", 1), ("references", 1), ("references", 1)],
+        reference_items
+    );
+}
+
+#[test]
+fn can_export_references_to_multiple_definitions() {
+    let mut exporter = LsifExporter::new(Path::new("/project"));
+    let first = span(0, 0, 3);
+    let second = span(1, 0, 3);
+    exporter.add_reference(
+        Path::new("/project/a.py"),
+        &span(2, 0, 3),
+        [
+            (Path::new("/project/a.py"), &first),
+            (Path::new("/project/a.py"), &second),
+        ],
+    );
+    let elements = dump(&exporter);
+
+    // the reference has its own result set with both definitions
+    let result_set = follow(&elements, range_at(&elements, 2, 0), "next");
+    assert_ne!(
+        result_set,
+        follow(&elements, range_at(&elements, 0, 0), "next")
+    );
+    let definition_result = follow(&elements, result_set, "textDocument/definition");
+    let item = elements
+        .iter()
+        .find(|e| e["label"] == "item" && e["outV"] == definition_result["id"])
+        .unwrap();
+    assert_eq!(2, item["inVs"].as_array().unwrap().len());
+}
+
+#[test]
+fn can_export_unresolved_references() {
+    let mut exporter = LsifExporter::new(Path::new("/my project"));
+    exporter.add_reference(Path::new("/my project/a.py"), &span(0, 0, 3), []);
+    let elements = dump(&exporter);
+
+    assert_eq!("file:///my%20project", elements[0]["projectRoot"]);
+    assert_eq!(1, find(&elements, "range").len());
+    assert_eq!(0, find(&elements, "next").len());
+}
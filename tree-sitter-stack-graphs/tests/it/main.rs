@@ -14,9 +14,15 @@ use tree_sitter_stack_graphs::NoCancellation;
 use tree_sitter_stack_graphs::StackGraphLanguage;
 
 mod builder;
+#[cfg(feature = "cli")]
+mod cli;
 mod edges;
 mod loader;
+mod lsif;
 mod nodes;
+mod pipeline;
+#[cfg(feature = "scip")]
+mod scip;
 mod test;
 
 pub(self) fn build_stack_graph(
@@ -47,6 +47,18 @@ fn can_create_definition_node() {
     build_and_check_stack_graph_nodes(python, tsg, &["[test.py(0) definition a]"]);
 }
 
+#[test]
+fn can_create_case_insensitive_definition_node() {
+    let tsg = r#"
+      (identifier) @id {
+         node result
+         attr (result) type = "pop_symbol", symbol = (source-text @id), is_definition, case_insensitive
+      }
+    "#;
+    let python = "Ab";
+    build_and_check_stack_graph_nodes(python, tsg, &["[test.py(0) definition ab]"]);
+}
+
 #[test]
 fn cannot_create_definition_node_without_symbol() {
     let tsg = r#"
@@ -357,3 +369,29 @@ fn can_set_syntax_type() {
         .unwrap_or("MISSING");
     assert_eq!("function", syntax_type)
 }
+
+#[test]
+fn can_set_metadata() {
+    let tsg = r#"
+      (function_definition name: (identifier) @name) {
+         node result
+         attr (result) type = "pop_symbol", symbol = (source-text @name), is_definition
+         attr (result) metadata_visibility = "public", metadata_arity = 0
+      }
+    "#;
+    let python = r#"
+      def foo():
+        pass
+    "#;
+
+    let (graph, file) = build_stack_graph(python, tsg).unwrap();
+    let node_handle = graph.nodes_for_file(file).next().unwrap();
+    let mut metadata = graph
+        .node_metadata(node_handle)
+        .unwrap()
+        .iter()
+        .map(|e| (&graph[e.key], &graph[e.value]))
+        .collect::<Vec<_>>();
+    metadata.sort();
+    assert_eq!(vec![("arity", "0"), ("visibility", "public")], metadata)
+}
@@ -0,0 +1,253 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use once_cell::sync::Lazy;
+use pretty_assertions::assert_eq;
+use stack_graphs::arena::Handle;
+use stack_graphs::builder::GraphBuilder;
+use stack_graphs::graph::File;
+use stack_graphs::graph::StackGraph;
+use stack_graphs::stitching::DatabaseCandidates;
+use stack_graphs::stitching::ForwardPartialPathStitcher;
+use std::cell::RefCell;
+use std::ffi::OsStr;
+use std::path::Path;
+use std::path::PathBuf;
+use std::rc::Rc;
+use tree_sitter_stack_graphs::loader::ContentProvider;
+use tree_sitter_stack_graphs::loader::FileAnalyzers;
+use tree_sitter_stack_graphs::loader::LanguageConfiguration;
+use tree_sitter_stack_graphs::loader::Loader;
+use tree_sitter_stack_graphs::pipeline::Analysis;
+use tree_sitter_stack_graphs::pipeline::AnalysisError;
+use tree_sitter_stack_graphs::pipeline::AnalysisPipeline;
+use tree_sitter_stack_graphs::BuildError;
+use tree_sitter_stack_graphs::CancellationFlag;
+use tree_sitter_stack_graphs::GraphProducer;
+use tree_sitter_stack_graphs::NoCancellation;
+use tree_sitter_stack_graphs::Variables;
+
+static TSG: Lazy<String> = Lazy::new(|| {
+    r#"
+      global ROOT_NODE
+      (module) @mod {
+          node @mod.lexical_in
+          node @mod.lexical_out
+          edge @mod.lexical_in -> ROOT_NODE
+          edge ROOT_NODE -> @mod.lexical_out
+      }
+      (module (_)@stmt) @mod {
+          node @stmt.lexical_in
+          node @stmt.lexical_out
+          edge @stmt.lexical_in -> @mod.lexical_in
+          edge @mod.lexical_out -> @stmt.lexical_out
+      }
+      (expression_statement (assignment left:(identifier)@name))@stmt {
+          node @name.def
+          attr (@name.def) type = "pop_symbol", symbol = (source-text @name), source_node = @name, is_definition
+          edge @stmt.lexical_out -> @name.def
+      }
+      (expression_statement (identifier)@name)@stmt {
+          node @name.ref
+          attr (@name.ref) type = "push_symbol", symbol = (source-text @name), source_node = @name, is_reference
+          edge @name.ref -> @stmt.lexical_in
+      }
+    "#
+    .to_string()
+});
+
+fn loader() -> Loader {
+    let lc = LanguageConfiguration::from_sources(
+        tree_sitter_python::language(),
+        Some("source.py".into()),
+        None,
+        vec!["py".into()],
+        PathBuf::from("test.tsg"),
+        &TSG,
+        Some((PathBuf::from("builtins.py"), "print = 0\n")),
+        None,
+        FileAnalyzers::new(),
+        &NoCancellation,
+    )
+    .expect("Expected language configuration to load");
+    Loader::from_language_configurations(vec![lc], None).expect("Expected loader to succeed")
+}
+
+fn pipeline() -> AnalysisPipeline {
+    AnalysisPipeline::new(loader())
+}
+
+/// Produces graphs for `.words` files, which define the words on every line at the root.
+struct WordsProducer;
+
+impl GraphProducer for WordsProducer {
+    fn matches_file(
+        &self,
+        path: &Path,
+        _content: &mut dyn ContentProvider,
+    ) -> std::io::Result<bool> {
+        Ok(path.extension() == Some(OsStr::new("words")))
+    }
+
+    fn build_stack_graph_into(
+        &self,
+        stack_graph: &mut StackGraph,
+        file: Handle<File>,
+        _path: &Path,
+        source: &str,
+        _globals: &Variables,
+        _cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<(), BuildError> {
+        let name = stack_graph[file].name().to_string();
+        let mut builder = GraphBuilder::new(stack_graph);
+        for word in source.lines() {
+            builder.file(&name).from_root().defines(word);
+        }
+        Ok(())
+    }
+}
+
+/// Returns the files of the definitions that the references in the given file resolve to.
+fn resolve_references_in(analysis: &mut Analysis, path: &str) -> Vec<String> {
+    let file = analysis.file(Path::new(path)).expect("Expected file");
+    let graph = &analysis.graph;
+    let references = graph
+        .nodes_for_file(file)
+        .filter(|node| graph[*node].is_reference());
+    let mut definition_files = Vec::new();
+    ForwardPartialPathStitcher::find_all_complete_partial_paths(
+        &mut DatabaseCandidates::new(graph, &mut analysis.partials, &mut analysis.database),
+        references,
+        &stack_graphs::NoCancellation,
+        |graph, _, path| {
+            let file = graph[path.end_node]
+                .file()
+                .expect("Expected definition in file");
+            definition_files.push(graph[file].name().to_string());
+        },
+    )
+    .expect("should never be cancelled");
+    definition_files.sort();
+    definition_files
+}
+
+#[test]
+fn can_resolve_references_across_files() {
+    let mut pipeline = pipeline();
+    pipeline
+        .add_file(PathBuf::from("a.py"), "x = 1\n".into())
+        .add_file(PathBuf::from("b.py"), "x\nprint\n".into());
+    let mut analysis = pipeline
+        .run(&NoCancellation)
+        .expect("Expected analysis to succeed");
+    assert!(analysis.skipped.is_empty());
+    assert_eq!(
+        vec!["<builtins>", "a.py"],
+        resolve_references_in(&mut analysis, "b.py")
+    );
+}
+
+#[test]
+fn can_analyze_without_builtins() {
+    let mut pipeline = pipeline();
+    pipeline
+        .set_load_builtins(false)
+        .add_file(PathBuf::from("b.py"), "print\n".into());
+    let mut analysis = pipeline
+        .run(&NoCancellation)
+        .expect("Expected analysis to succeed");
+    assert_eq!(None, analysis.file(Path::new("<builtins>")));
+    assert!(resolve_references_in(&mut analysis, "b.py").is_empty());
+}
+
+#[test]
+fn skips_unsupported_files() {
+    let mut pipeline = pipeline();
+    pipeline
+        .add_file(PathBuf::from("a.py"), "x = 1\n".into())
+        .add_file(PathBuf::from("notes.txt"), "x\n".into());
+    let analysis = pipeline
+        .run(&NoCancellation)
+        .expect("Expected analysis to succeed");
+    assert_eq!(vec![PathBuf::from("notes.txt")], analysis.skipped);
+    assert_eq!(None, analysis.file(Path::new("notes.txt")));
+}
+
+#[test]
+fn calls_hook_for_every_file() {
+    let analyzed = Rc::new(RefCell::new(Vec::new()));
+    let mut pipeline = pipeline();
+    {
+        let analyzed = analyzed.clone();
+        pipeline.on_file_analyzed(move |graph, file, _, paths| {
+            analyzed
+                .borrow_mut()
+                .push((graph[file].name().to_string(), !paths.is_empty()));
+            Ok(())
+        });
+    }
+    pipeline.add_file(PathBuf::from("a.py"), "x = 1\n".into());
+    pipeline
+        .run(&NoCancellation)
+        .expect("Expected analysis to succeed");
+    assert_eq!(
+        vec![("<builtins>".to_string(), true), ("a.py".to_string(), true)],
+        *analyzed.borrow()
+    );
+
+    // the pipeline can be reused, and the hook is called for new files only
+    analyzed.borrow_mut().clear();
+    pipeline.set_load_builtins(false);
+    pipeline.add_file(PathBuf::from("b.py"), "x\n".into());
+    pipeline
+        .run(&NoCancellation)
+        .expect("Expected analysis to succeed");
+    assert_eq!(vec![("b.py".to_string(), true)], *analyzed.borrow());
+}
+
+#[test]
+fn hook_errors_abort_the_run() {
+    let mut pipeline = pipeline();
+    pipeline
+        .on_file_analyzed(|_, _, _, _| Err("storage failed".into()))
+        .add_file(PathBuf::from("a.py"), "x = 1\n".into());
+    match pipeline.run(&NoCancellation) {
+        Err(AnalysisError::Hook(err)) => assert_eq!("storage failed", err.to_string()),
+        Err(err) => panic!("Unexpected error: {}", err),
+        Ok(_) => panic!("Analysis unexpectedly succeeded."),
+    }
+}
+
+#[test]
+fn cannot_analyze_duplicate_files() {
+    let mut pipeline = pipeline();
+    pipeline
+        .add_file(PathBuf::from("a.py"), "x = 1\n".into())
+        .add_file(PathBuf::from("a.py"), "y = 1\n".into());
+    if pipeline.run(&NoCancellation).is_ok() {
+        panic!("Analysis unexpectedly succeeded.");
+    }
+}
+
+#[test]
+fn can_analyze_files_with_graph_producers() {
+    let mut loader = loader();
+    loader.add_graph_producer(WordsProducer);
+    let mut pipeline = AnalysisPipeline::new(loader);
+    pipeline
+        .set_load_builtins(false)
+        .add_file(PathBuf::from("a.words"), "x\ny\n".into())
+        .add_file(PathBuf::from("b.py"), "x\n".into());
+    let mut analysis = pipeline
+        .run(&NoCancellation)
+        .expect("Expected analysis to succeed");
+    assert!(analysis.skipped.is_empty());
+    assert_eq!(
+        vec!["a.words"],
+        resolve_references_in(&mut analysis, "b.py")
+    );
+}
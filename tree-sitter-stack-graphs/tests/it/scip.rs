@@ -0,0 +1,141 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use lsp_positions::Span;
+use pretty_assertions::assert_eq;
+use prost::Message;
+use std::path::Path;
+use tree_sitter_stack_graphs::scip::global_symbol;
+use tree_sitter_stack_graphs::scip::proto;
+use tree_sitter_stack_graphs::scip::ScipDefinition;
+use tree_sitter_stack_graphs::scip::ScipExporter;
+
+fn span(line: usize, start: usize, end: usize) -> Span {
+    let mut span = Span::default();
+    span.start.line = line;
+    span.start.column.utf16_offset = start;
+    span.end.line = line;
+    span.end.column.utf16_offset = end;
+    span
+}
+
+fn definition<'a>(
+    path: &'a str,
+    span: &'a Span,
+    qualified_name: &'a [String],
+    syntax_type: Option<&'a str>,
+) -> ScipDefinition<'a> {
+    ScipDefinition {
+        path: Path::new(path),
+        span,
+        display_name: Some("foo"),
+        qualified_name,
+        syntax_type,
+    }
+}
+
+fn occurrences(document: &proto::Document) -> Vec<(Vec<i32>, &str, i32)> {
+    document
+        .occurrences
+        .iter()
+        .map(|o| (o.range.clone(), o.symbol.as_str(), o.symbol_roles))
+        .collect()
+}
+
+#[test]
+fn can_create_global_symbols() {
+    let name = vec!["mod".to_string(), "foo".to_string()];
+    assert_eq!(
+        "stack-graphs . . . mod/foo().",
+        global_symbol(&name, Some("function"))
+    );
+    assert_eq!(
+        "stack-graphs . . . mod/foo#",
+        global_symbol(&name, Some("class"))
+    );
+    assert_eq!("stack-graphs . . . mod/foo.", global_symbol(&name, None));
+    let name = vec!["my mod".to_string(), "`foo`".to_string()];
+    assert_eq!(
+        "stack-graphs . . . `my mod`/```foo```.",
+        global_symbol(&name, None)
+    );
+}
+
+#[test]
+fn can_export_global_and_local_definitions() {
+    let mut exporter = ScipExporter::new(Path::new("/project"));
+    let qualified_name = vec!["a".to_string(), "foo".to_string()];
+    let global = span(0, 4, 7);
+    let local = span(1, 0, 3);
+    exporter.add_reference(
+        Path::new("/project/a.py"),
+        &span(2, 0, 3),
+        [
+            definition("/project/a.py", &global, &qualified_name, Some("function")),
+            definition("/project/a.py", &local, &[], None),
+        ],
+    );
+    exporter.add_reference(
+        Path::new("/project/b.py"),
+        &span(0, 0, 3),
+        [
+            definition("/project/a.py", &global, &qualified_name, Some("function")),
+            definition("/project/a.py", &local, &[], None),
+        ],
+    );
+
+    let mut output = Vec::new();
+    exporter.write(&mut output).unwrap();
+    let index = proto::Index::decode(output.as_slice()).unwrap();
+
+    let metadata = index.metadata.unwrap();
+    assert_eq!("file:///project", metadata.project_root);
+    assert_eq!(2, index.documents.len());
+
+    let a = &index.documents[0];
+    assert_eq!("a.py", a.relative_path);
+    assert_eq!(proto::POSITION_ENCODING_UTF16, a.position_encoding);
+    assert_eq!(
+        vec![
+            (vec![0, 4, 7], "stack-graphs . . . a/foo().", 1),
+            (vec![1, 0, 3], "local 0", 1),
+            (vec![2, 0, 3], "stack-graphs . . . a/foo().", 0),
+            (vec![2, 0, 3], "local 0", 0),
+        ],
+        occurrences(a)
+    );
+    assert_eq!(
+        vec!["stack-graphs . . . a/foo().", "local 0"],
+        a.symbols
+            .iter()
+            .map(|s| s.symbol.as_str())
+            .collect::<Vec<_>>()
+    );
+
+    // local symbols of other documents cannot be referenced
+    let b = &index.documents[1];
+    assert_eq!("b.py", b.relative_path);
+    assert_eq!(
+        vec![(vec![0, 0, 3], "stack-graphs . . . a/foo().", 0)],
+        occurrences(b)
+    );
+}
+
+#[test]
+fn documents_outside_project_root_are_skipped() {
+    let mut exporter = ScipExporter::new(Path::new("/project"));
+    let local = span(0, 0, 3);
+    exporter.add_reference(
+        Path::new("/project/a.py"),
+        &span(1, 0, 3),
+        [definition("/other/b.py", &local, &[], None)],
+    );
+    let index = exporter.index();
+    assert_eq!(1, index.documents.len());
+    assert_eq!("a.py", index.documents[0].relative_path);
+    assert!(index.documents[0].occurrences.is_empty());
+}
@@ -11,11 +11,13 @@ use stack_graphs::arena::Handle;
 use stack_graphs::graph::File;
 use stack_graphs::graph::StackGraph;
 use stack_graphs::partial::PartialPaths;
+use stack_graphs::serde::NoFilter;
 use stack_graphs::stitching::Database;
 use stack_graphs::stitching::ForwardPartialPathStitcher;
 use std::path::Path;
 use std::path::PathBuf;
 use tree_sitter_graph::Variables;
+use tree_sitter_stack_graphs::test::CommentSyntax;
 use tree_sitter_stack_graphs::test::Test;
 use tree_sitter_stack_graphs::BuildError;
 use tree_sitter_stack_graphs::NoCancellation;
@@ -76,6 +78,22 @@ fn build_stack_graph_into(
     Ok(())
 }
 
+fn build_test_graph(test: &mut Test, tsg_source: &str) {
+    let mut globals = Variables::new();
+    for fragments in test.fragments.iter().chain(&test.includes) {
+        globals.clear();
+        fragments.add_globals_to(&mut globals);
+        build_stack_graph_into(
+            &mut test.graph,
+            fragments.file,
+            &fragments.source,
+            tsg_source,
+            &globals,
+        )
+        .expect("Could not load stack graph");
+    }
+}
+
 fn check_test(
     python_path: &Path,
     python_source: &str,
@@ -83,8 +101,17 @@ fn check_test(
     expected_successes: usize,
     expected_failures: usize,
 ) {
-    let mut test =
+    let test =
         Test::from_source(python_path, python_source, python_path).expect("Could not parse test");
+    check_parsed_test(test, tsg_source, expected_successes, expected_failures);
+}
+
+fn check_parsed_test(
+    mut test: Test,
+    tsg_source: &str,
+    expected_successes: usize,
+    expected_failures: usize,
+) {
     let assertion_count: usize = test.fragments.iter().map(|f| f.assertions.len()).sum();
     assert_eq!(
         expected_successes + expected_failures,
@@ -93,22 +120,10 @@ fn check_test(
         expected_successes + expected_failures,
         assertion_count,
     );
-    let mut globals = Variables::new();
-    for fragments in &test.fragments {
-        globals.clear();
-        fragments.add_globals_to(&mut globals);
-        build_stack_graph_into(
-            &mut test.graph,
-            fragments.file,
-            &fragments.source,
-            tsg_source,
-            &globals,
-        )
-        .expect("Could not load stack graph");
-    }
+    build_test_graph(&mut test, tsg_source);
     let mut partials = PartialPaths::new();
     let mut db = Database::new();
-    for fragment in &test.fragments {
+    for fragment in test.fragments.iter().chain(&test.includes) {
         ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
             &test.graph,
             &mut partials,
@@ -327,3 +342,222 @@ fn test_cannot_set_global_before_first_fragment() {
         panic!("Parsing test unexpectedly succeeded.");
     }
 }
+
+#[test]
+fn test_can_select_builtins_layers() {
+    let python = r#"
+      # --- builtins: django, pytest ---
+      # --- path: a.py ---
+      pass
+      # --- builtins: pytest,numpy ---
+    "#;
+    let test = Test::from_source(&PATH, python, &PATH).expect("Could not parse test");
+    assert_eq!(vec!["django", "pytest", "numpy"], test.builtins_layers);
+}
+
+#[test]
+fn test_can_opt_out_of_builtins() {
+    let python = r#"
+      # --- builtins: none ---
+      # --- path: a.py ---
+      pass
+    "#;
+    let test = Test::from_source(&PATH, python, &PATH).expect("Could not parse test");
+    assert!(test.no_builtins);
+    assert!(test.builtins_layers.is_empty());
+
+    let test = Test::from_source(&PATH, "pass", &PATH).expect("Could not parse test");
+    assert!(!test.no_builtins);
+}
+
+#[test]
+fn test_cannot_combine_no_builtins_with_layers() {
+    let python = r#"
+      # --- builtins: django ---
+      # --- path: a.py ---
+      pass
+      # --- builtins: none ---
+    "#;
+    if Test::from_source(&PATH, python, &PATH).is_ok() {
+        panic!("Parsing test unexpectedly succeeded.");
+    }
+}
+
+#[test]
+fn test_can_include_files() {
+    let python = r#"
+      # --- include: fixtures/stdlib.py ---
+      # --- path: a.py ---
+      x = 1;
+      # --- include: fixtures/stdlib.py ---
+        x;
+      # ^ defined: 4
+    "#;
+    let mut read_paths = Vec::new();
+    let test = Test::from_source_with_includes(Path::new("tests/test.py"), python, &PATH, |path| {
+        read_paths.push(path.to_path_buf());
+        Ok("x = 2\n".to_string())
+    })
+    .expect("Could not parse test");
+    assert_eq!(vec![PathBuf::from("tests/fixtures/stdlib.py")], read_paths);
+    assert_eq!(1, test.includes.len());
+    assert_eq!(PathBuf::from("fixtures/stdlib.py"), test.includes[0].path);
+    assert!(test.includes[0].assertions.is_empty());
+    // the definition in the include file is found, but not reported as unexpected
+    check_parsed_test(test, &TSG, 1, 0);
+}
+
+#[test]
+fn test_cannot_include_missing_file() {
+    let python = r#"
+      # --- include: missing.py ---
+      pass
+    "#;
+    if Test::from_source_with_includes(&PATH, python, &PATH, |_| {
+        Err(std::io::Error::from(std::io::ErrorKind::NotFound))
+    })
+    .is_ok()
+    {
+        panic!("Parsing test unexpectedly succeeded.");
+    }
+}
+
+#[test]
+fn test_can_check_graph() {
+    let python = r#"
+      x = 1
+      y = x
+    "#;
+    let mut test = Test::from_source(&PATH, python, &PATH).expect("Could not parse test");
+    build_test_graph(&mut test, &TSG);
+    let golden = test.graph.to_serializable();
+    let golden_path = Path::new("test.graph.json");
+
+    let result = test.check_graph(&NoFilter, golden_path, Some(golden.clone()));
+    assert_eq!((1, 0), (result.success_count(), result.failure_count()));
+
+    let result = test.check_graph(&NoFilter, golden_path, None);
+    assert_eq!((0, 1), (result.success_count(), result.failure_count()));
+
+    let python = r#"
+      x = 1
+      y = x
+      z = y
+    "#;
+    let mut test = Test::from_source(&PATH, python, &PATH).expect("Could not parse test");
+    build_test_graph(&mut test, &TSG);
+    let result = test.check_graph(&NoFilter, golden_path, Some(golden));
+    assert_eq!((0, 1), (result.success_count(), result.failure_count()));
+}
+
+#[test]
+fn test_can_assert_defined_via_file() {
+    let python = r#"
+      # --- path: a.py ---
+      x = 1
+      # --- path: b.py ---
+      pass
+      # --- path: c.py ---
+        x
+      # ^ defined: 3 via a.py
+      # ^ defined: 3 via b.py
+    "#;
+    check_test(&PATH, python, &TSG, 1, 1);
+}
+
+#[test]
+fn test_cannot_assert_via_unknown_file() {
+    let python = r#"
+      # --- path: a.py ---
+      x = 1
+        x
+      # ^ defined: 3 via b.py
+    "#;
+    if Test::from_source(&PATH, python, &PATH).is_ok() {
+        panic!("Parsing test unexpectedly succeeded.");
+    }
+}
+
+#[test]
+fn test_cannot_use_via_with_other_assertions() {
+    let python = r#"
+      # --- path: a.py ---
+        x
+      # ^ refers: x via a.py
+    "#;
+    if Test::from_source(&PATH, python, &PATH).is_ok() {
+        panic!("Parsing test unexpectedly succeeded.");
+    }
+}
+
+#[test]
+fn can_assert_several_columns_on_one_line() {
+    let python = r#"
+      x = 1
+      y = 2
+        x;                  y
+      # ^ defined: 2        ^ defined: 3
+      # ^ refers: x         ^ defined: 2
+    "#;
+    check_test(&PATH, python, &TSG, 3, 1);
+}
+
+#[test]
+fn can_assert_explicit_columns() {
+    let python = r#"
+      x = 1
+      y = 2
+        x;                  y
+      # @9 defined: 2  @29 defined: 3
+      # @9 refers: x
+    "#;
+    check_test(&PATH, python, &TSG, 3, 0);
+}
+
+#[test]
+fn test_cannot_assert_explicit_column_zero() {
+    let python = r#"
+      x = 1
+      # @0 defines: x
+    "#;
+    if Test::from_source(&PATH, python, &PATH).is_ok() {
+        panic!("Parsing test unexpectedly succeeded.");
+    }
+}
+
+#[test]
+fn test_can_assert_in_configured_line_comments() {
+    let python = r#"
+      # --- path: a.py ---
+      x = 1
+        x
+      # ^ defined: 3
+        "^ defined: 3"
+    "#;
+    let test =
+        Test::from_source_with_comments(&PATH, python, &PATH, &[CommentSyntax::Line("#".into())])
+            .expect("Could not parse test");
+    assert_eq!(PathBuf::from("a.py"), test.fragments[0].path);
+    // the assertion in the string is not in a comment, and therefore ignored
+    check_parsed_test(test, &TSG, 1, 0);
+}
+
+#[test]
+fn test_can_assert_in_configured_block_comments() {
+    let python = r#"
+      """ --- path: a.py --- """
+      x = 1
+          x
+      """ ^ defined: 3 """
+      """ @11 refers: x """
+    "#;
+    let test = Test::from_source_with_comments(
+        &PATH,
+        python,
+        &PATH,
+        &[CommentSyntax::Block("\"\"\"".into(), "\"\"\"".into())],
+    )
+    .expect("Could not parse test");
+    assert_eq!(PathBuf::from("a.py"), test.fragments[0].path);
+    check_parsed_test(test, &TSG, 2, 0);
+}